@@ -0,0 +1,113 @@
+"""Search without the FastSearch service (the ``bridge_fallback`` mode).
+
+When the service isn't installed or isn't running, the bridge walks folders
+itself instead of failing. This is far slower than the MFT cache, so the walk
+starts at the ``path`` filter (or the drive root), stops after a time budget,
+and the response says so in ``_meta.index_modes`` like the service's own
+degraded modes do.
+"""
+
+import fnmatch
+import os
+import re
+import time
+from typing import Any, Dict, List, Optional
+
+from .validation import validate_regex
+
+# Mode name reported for results produced here
+BRIDGE_FALLBACK = "bridge_fallback"
+
+# Seconds a fallback walk may take before it returns what it found
+DEFAULT_TIME_BUDGET = 10.0
+
+
+def _name_matcher(pattern: str, search_type: str):
+    """Build a predicate for file names, following the service's search types."""
+    if search_type == "regex":
+        # Matched with a backtracking engine here, unlike in the service
+        regex = re.compile(validate_regex(pattern, backtracking=True), re.IGNORECASE)
+        return lambda name: regex.search(name) is not None
+    if search_type == "exact":
+        expected = pattern.lower()
+        return lambda name: name.lower() == expected
+    if search_type == "fuzzy":
+        # Letters in order, anything in between
+        regex = re.compile(".*".join(re.escape(c) for c in pattern.lower()))
+        return lambda name: regex.search(name.lower()) is not None
+    return lambda name: fnmatch.fnmatch(name.lower(), pattern.lower())
+
+
+def walk_search(
+    pattern: str,
+    search_type: str = "glob",
+    max_results: int = 50,
+    path: Optional[str] = None,
+    drive: Optional[str] = None,
+    reason: str = "service not running",
+    time_budget: float = DEFAULT_TIME_BUDGET,
+) -> Dict[str, Any]:
+    """Walk folders for names matching ``pattern``.
+
+    Args:
+        pattern: Name pattern
+        search_type: glob, regex, exact or fuzzy
+        max_results: Matches to stop at
+        path: Folder to search, relative to the drive root or absolute
+        drive: Drive letter (default C)
+        reason: Why the service couldn't answer, reported in ``_meta``
+        time_budget: Seconds to walk before giving up
+
+    Returns:
+        A result shaped like the service's, with ``_meta.index_modes``
+    """
+    matches = _name_matcher(pattern, search_type)
+    letter = (drive or "C")[0].upper()
+    root = path if path and os.path.isabs(path) else os.path.join(f"{letter}:\\", path or "")
+    started = time.monotonic()
+    results: List[Dict[str, Any]] = []
+    complete = True
+
+    for folder, dirs, files in os.walk(root):
+        if time.monotonic() - started > time_budget:
+            complete = False
+            break
+        for name in dirs + files:
+            if not matches(name):
+                continue
+            full = os.path.join(folder, name)
+            try:
+                stat = os.stat(full)
+            except OSError:
+                continue
+            is_dir = name in dirs
+            results.append({
+                "name": name,
+                "path": full,
+                "drive": letter,
+                "size": 0 if is_dir else stat.st_size,
+                "is_directory": is_dir,
+                "modified": int(stat.st_mtime),
+            })
+            if len(results) >= max_results:
+                break
+        if len(results) >= max_results:
+            break
+
+    elapsed = time.monotonic() - started
+    lines = [f"🐢 BRIDGE FALLBACK: Found {len(results)} files matching '{pattern}' under {root} in {elapsed * 1000:.0f}ms", ""]
+    lines += [f"{i}. {r['path']} ({'DIR' if r['is_directory'] else str(r['size']) + ' bytes'})" for i, r in enumerate(results, 1)]
+    if not complete:
+        lines.append(f"\n⚠️ Stopped after {time_budget:.0f}s; pass a narrower path to search everything below it")
+    lines.append(f"\n⚠️ Degraded mode: {letter}: bridge walking folders itself ({reason}); start the FastSearch service for full speed")
+
+    return {
+        "content": [{"type": "text", "text": "\n".join(lines)}],
+        "results": results,
+        "next_cursor": None,
+        "_meta": {
+            "index_modes": {letter: {"mode": BRIDGE_FALLBACK, "degraded": True, "reason": reason, "since": int(time.time())}},
+            "degraded": True,
+            "complete": complete,
+        },
+    }
@@ -0,0 +1,212 @@
+"""Federation client fanning searches out to FastSearch web APIs on other machines.
+
+Endpoints are configured in a JSON file (``FASTSEARCH_FEDERATION_CONFIG``,
+defaulting to ``%LOCALAPPDATA%\\FastSearchMCP\\federation.json``)::
+
+    {
+        "endpoints": [
+            {"name": "nas", "url": "http://nas.local:8080", "api_key_env": "NAS_FASTSEARCH_KEY"},
+            {"name": "laptop", "url": "http://laptop:8080", "api_key": "..."}
+        ]
+    }
+
+Each remote is queried through ``POST /api/search`` and every result is tagged
+with a ``host`` column naming the machine it came from.
+"""
+
+import asyncio
+import json
+import logging
+import os
+import time
+import urllib.error
+import urllib.request
+from dataclasses import dataclass
+from typing import Any, Dict, List, Optional, Tuple
+
+logger = logging.getLogger(__name__)
+
+# Default per-endpoint request timeout in seconds
+DEFAULT_TIMEOUT = 10.0
+
+
+class FederationError(Exception):
+    """Raised for invalid federation configuration."""
+    pass
+
+
+@dataclass
+class FederationEndpoint:
+    """A remote FastSearch web API."""
+    name: str
+    url: str
+    api_key: Optional[str] = None
+    timeout: float = DEFAULT_TIMEOUT
+
+    @classmethod
+    def from_dict(cls, data: Dict[str, Any]) -> "FederationEndpoint":
+        """Build an endpoint from its configuration entry.
+
+        The API key may be given inline (``api_key``) or read from an
+        environment variable (``api_key_env``) to keep it out of the file.
+        """
+        if not data.get("name") or not data.get("url"):
+            raise FederationError(f"Federation endpoint needs 'name' and 'url': {data}")
+
+        api_key = data.get("api_key")
+        if not api_key and data.get("api_key_env"):
+            api_key = os.getenv(data["api_key_env"])
+            if not api_key:
+                logger.warning(
+                    f"Environment variable {data['api_key_env']} for endpoint {data['name']} is not set"
+                )
+
+        return cls(
+            name=data["name"],
+            url=data["url"].rstrip("/"),
+            api_key=api_key,
+            timeout=float(data.get("timeout", DEFAULT_TIMEOUT)),
+        )
+
+
+def default_config_path() -> str:
+    """Location of the federation config file."""
+    if os.getenv("FASTSEARCH_FEDERATION_CONFIG"):
+        return os.environ["FASTSEARCH_FEDERATION_CONFIG"]
+    app_data = os.getenv("LOCALAPPDATA", os.path.expanduser("~"))
+    return os.path.join(app_data, "FastSearchMCP", "federation.json")
+
+
+def load_endpoints(path: Optional[str] = None) -> List[FederationEndpoint]:
+    """Load configured endpoints; a missing config file means federation is off.
+
+    Raises:
+        FederationError: If the file exists but is invalid
+    """
+    path = path or default_config_path()
+    if not os.path.exists(path):
+        return []
+
+    try:
+        with open(path, "r", encoding="utf-8") as f:
+            config = json.load(f)
+    except (OSError, json.JSONDecodeError) as e:
+        raise FederationError(f"Failed to read federation config {path}: {e}") from e
+
+    endpoints = [FederationEndpoint.from_dict(entry) for entry in config.get("endpoints", [])]
+    names = [endpoint.name for endpoint in endpoints]
+    if len(names) != len(set(names)):
+        raise FederationError(f"Duplicate endpoint names in {path}")
+
+    logger.info(f"Loaded {len(endpoints)} federation endpoints from {path}")
+    return endpoints
+
+
+class FederationClient:
+    """Queries several FastSearch web APIs concurrently and merges their results."""
+
+    def __init__(self, endpoints: Optional[List[FederationEndpoint]] = None):
+        """Initialize the client.
+
+        Args:
+            endpoints: Remote endpoints (loaded from the config file if None)
+        """
+        self.endpoints = endpoints if endpoints is not None else load_endpoints()
+
+    @property
+    def enabled(self) -> bool:
+        """Whether any remote endpoint is configured."""
+        return bool(self.endpoints)
+
+    async def search(
+        self,
+        pattern: str,
+        max_results: int = 50,
+        hosts: Optional[List[str]] = None,
+        **filters
+    ) -> Dict[str, Any]:
+        """Run a search on every (or the selected) remote endpoint.
+
+        Args:
+            pattern: Search pattern passed to each endpoint
+            max_results: Maximum number of results per endpoint
+            hosts: Optional endpoint names to restrict the search to
+            **filters: Additional fields for the remote search request (path, drive, ...)
+
+        Returns:
+            Dictionary with merged ``results`` (each tagged with ``host``) and
+            a per-host ``hosts`` status list
+        """
+        endpoints = [e for e in self.endpoints if hosts is None or e.name in hosts]
+        request = {"pattern": pattern, "max_results": max_results, **filters}
+
+        responses = await asyncio.gather(
+            *(self._search_endpoint(endpoint, request) for endpoint in endpoints)
+        )
+
+        results: List[Dict[str, Any]] = []
+        statuses: List[Dict[str, Any]] = []
+        for endpoint_results, status in responses:
+            results.extend(endpoint_results)
+            statuses.append(status)
+
+        return {"results": results, "hosts": statuses}
+
+    async def _search_endpoint(
+        self,
+        endpoint: FederationEndpoint,
+        request: Dict[str, Any]
+    ) -> Tuple[List[Dict[str, Any]], Dict[str, Any]]:
+        """Query one endpoint; failures are reported in the status instead of raised."""
+        start = time.monotonic()
+        loop = asyncio.get_running_loop()
+        try:
+            response = await loop.run_in_executor(None, self._post, endpoint, "/api/search", request)
+        except Exception as e:
+            logger.warning(f"Federated search on {endpoint.name} failed: {e}")
+            return [], {
+                "host": endpoint.name,
+                "ok": False,
+                "count": 0,
+                "error": str(e),
+                "elapsed_ms": round((time.monotonic() - start) * 1000, 1),
+            }
+
+        if not response.get("success", False):
+            error = response.get("message") or "search failed"
+            return [], {
+                "host": endpoint.name,
+                "ok": False,
+                "count": 0,
+                "error": error,
+                "elapsed_ms": round((time.monotonic() - start) * 1000, 1),
+            }
+
+        results = [dict(result, host=endpoint.name) for result in response.get("results", [])]
+        return results, {
+            "host": endpoint.name,
+            "ok": True,
+            "count": len(results),
+            "elapsed_ms": round((time.monotonic() - start) * 1000, 1),
+        }
+
+    @staticmethod
+    def _post(endpoint: FederationEndpoint, route: str, payload: Dict[str, Any]) -> Dict[str, Any]:
+        """Blocking JSON POST (run in an executor)."""
+        headers = {"Content-Type": "application/json", "Accept": "application/json"}
+        if endpoint.api_key:
+            headers["Authorization"] = f"Bearer {endpoint.api_key}"
+
+        request = urllib.request.Request(
+            endpoint.url + route,
+            data=json.dumps(payload).encode("utf-8"),
+            headers=headers,
+            method="POST",
+        )
+        try:
+            with urllib.request.urlopen(request, timeout=endpoint.timeout) as response:
+                return json.loads(response.read().decode("utf-8"))
+        except urllib.error.HTTPError as e:
+            raise FederationError(f"HTTP {e.code} from {endpoint.url}") from e
+        except urllib.error.URLError as e:
+            raise FederationError(f"Cannot reach {endpoint.url}: {e.reason}") from e
@@ -5,7 +5,7 @@ import json
 import logging
 import os
 import struct
-from typing import Any, Dict, List, Optional, Tuple
+from typing import Any, Awaitable, Callable, Dict, List, Optional, Tuple, Union
 
 import pywintypes
 import win32file
@@ -13,6 +13,8 @@ import win32pipe
 import win32security
 from win32 import win32api
 
+from .validation import validate_filters, validate_regex
+
 logger = logging.getLogger(__name__)
 
 # Constants for pipe communication
@@ -24,11 +26,16 @@ IO_TIMEOUT = 30000  # ms
 # Message types
 MSG_SEARCH = 1
 MSG_STATUS = 2
+MSG_CANCEL = 3  # Stops the search sent with the given request_id
 
 # Response status codes
 STATUS_OK = 0
 STATUS_ERROR = 1
 STATUS_UNAVAILABLE = 2
+STATUS_PARTIAL = 3  # Intermediate chunk; more frames follow for the same request
+
+# Callback receiving the decoded payload of each partial frame
+PartialHandler = Callable[[Dict[str, Any]], Union[None, Awaitable[None]]]
 
 
 class IpcError(Exception):
@@ -132,12 +139,22 @@ class FastSearchClient:
         if not self.connected:
             await self.connect()
 
-    async def _send_message(self, message_type: int, data: bytes) -> bytes:
+    async def _send_message(
+        self,
+        message_type: int,
+        data: bytes,
+        on_partial: Optional[PartialHandler] = None
+    ) -> bytes:
         """Send a message to the service and return the response.
         
+        Streaming requests are answered with any number of ``STATUS_PARTIAL``
+        frames followed by one final frame; partial payloads are passed to
+        ``on_partial`` as they arrive.
+        
         Args:
             message_type: Type of message (MSG_SEARCH, MSG_STATUS, etc.)
             data: Message payload
+            on_partial: Optional callback (sync or async) for partial frames
             
         Returns:
             Response data from the service
@@ -158,23 +175,11 @@ class FastSearchClient:
                 if err != 0:
                     raise IpcError(f"Failed to send message: Windows error {err}")
                 
-                # Read response header (8 bytes: 4 for status, 4 for length)
-                hr, header_data = win32file.ReadFile(
-                    self.pipe_handle, 8, None
-                )
-                if hr != 0:
-                    raise IpcError(f"Failed to read response header: Windows error {hr}")
-                
-                status, length = struct.unpack("<II", header_data)
-                
-                # Read response data if any
-                response_data = b""
-                if length > 0:
-                    hr, response_data = win32file.ReadFile(
-                        self.pipe_handle, length, None
-                    )
-                    if hr != 0:
-                        raise IpcError(f"Failed to read response data: Windows error {hr}")
+                status, response_data = self._read_frame()
+                while status == STATUS_PARTIAL:
+                    if on_partial is not None:
+                        await self._deliver_partial(on_partial, response_data)
+                    status, response_data = self._read_frame()
                 
                 # Check status
                 if status == STATUS_ERROR:
@@ -194,11 +199,46 @@ class FastSearchClient:
                 else:
                     raise IpcError(f"IPC communication error: {e.strerror}") from e
 
+    def _read_frame(self) -> Tuple[int, bytes]:
+        """Read one response frame (8 byte header: 4 for status, 4 for length)."""
+        hr, header_data = win32file.ReadFile(
+            self.pipe_handle, 8, None
+        )
+        if hr != 0:
+            raise IpcError(f"Failed to read response header: Windows error {hr}")
+        
+        status, length = struct.unpack("<II", header_data)
+        
+        # Read response data if any
+        response_data = b""
+        if length > 0:
+            hr, response_data = win32file.ReadFile(
+                self.pipe_handle, length, None
+            )
+            if hr != 0:
+                raise IpcError(f"Failed to read response data: Windows error {hr}")
+        return status, response_data
+
+    @staticmethod
+    async def _deliver_partial(on_partial: PartialHandler, data: bytes) -> None:
+        """Decode a partial frame and hand it to the callback; callback errors don't abort the request."""
+        try:
+            result = on_partial(json.loads(data.decode('utf-8')))
+            if asyncio.iscoroutine(result):
+                await result
+        except Exception as e:
+            logger.warning(f"Partial result handler failed: {e}")
+
     async def search(
         self,
         pattern: str,
         search_type: str = "fuzzy",
         max_results: int = 50,
+        on_partial: Optional[PartialHandler] = None,
+        doc_type: Optional[str] = None,
+        extensions: Optional[List[str]] = None,
+        fields: Optional[List[str]] = None,
+        request_id: Optional[str] = None,
         **filters
     ) -> Dict[str, Any]:
         """Execute a search on the FastSearch service.
@@ -207,28 +247,81 @@ class FastSearchClient:
             pattern: Search pattern
             search_type: Type of search (exact, glob, regex, fuzzy)
             max_results: Maximum number of results to return
-            **filters: Additional search filters
+            on_partial: Optional callback receiving ``{"results", "scanned", "total"}``
+                batches while the service is still scanning
+            doc_type: Document type preset (text, code, image, spreadsheet,
+                presentation, archive, audio, video, pdf)
+            extensions: Extensions to keep, with or without the leading dot
+            fields: Result fields to return (e.g. ``["path"]``); the rest are
+                left out of every result, partial batches included
+            request_id: Id to stop the search by with ``cancel()``; a cancelled
+                search returns what it found so far with ``"cancelled": true``
+            **filters: Additional search filters; ``min_size`` and ``max_size``
+                may be byte counts or sizes like ``"1.5GB"`` or ``"200k"``
             
         Returns:
             Search results as a dictionary
+
+        Raises:
+            ValidationError: If a size filter can't be read, or a regex
+                pattern is past the service's length or nesting limits
         """
+        if search_type == "regex":
+            validate_regex(pattern)
         request = {
             "pattern": pattern,
             "type": search_type,
             "max_results": max(1, min(max_results, 1000)),  # Enforce reasonable limits
-            "filters": filters or {}
+            "filters": validate_filters(filters),
+            "stream": on_partial is not None
         }
+        if doc_type is not None:
+            request["doc_type"] = doc_type
+        if extensions is not None:
+            request["extensions"] = list(extensions)
+        if fields is not None:
+            request["fields"] = list(fields)
+        if request_id is not None:
+            request["request_id"] = request_id
         
         try:
             response_data = await self._send_message(
                 MSG_SEARCH,
-                json.dumps(request).encode('utf-8')
+                json.dumps(request).encode('utf-8'),
+                on_partial=on_partial
             )
             return json.loads(response_data.decode('utf-8'))
             
         except json.JSONDecodeError as e:
             raise IpcProtocolError("Invalid response format from service") from e
 
+    async def cancel(self, request_id: str) -> bool:
+        """Stop a running search that was sent with ``request_id``.
+        
+        The search holds its client's connection until it answers, so the
+        cancel request goes over a connection of its own.
+        
+        Args:
+            request_id: The id the search was sent with
+            
+        Returns:
+            Whether a running search was told to stop
+        """
+        canceller = FastSearchClient(self.pipe_name)
+        # _send_message holds the lock connect() takes, so connect up front
+        await canceller.connect()
+        try:
+            response_data = await canceller._send_message(
+                MSG_CANCEL,
+                json.dumps({"request_id": request_id}).encode('utf-8')
+            )
+            return bool(json.loads(response_data.decode('utf-8')).get("cancelled"))
+            
+        except json.JSONDecodeError as e:
+            raise IpcProtocolError("Invalid cancel response from service") from e
+        finally:
+            await canceller.disconnect()
+
     async def get_status(self) -> Dict[str, Any]:
         """Get the status of the FastSearch service.
         
@@ -10,12 +10,15 @@ import inspect
 import json
 import logging
 import signal
+import socket
 import sys
 from typing import Any, Dict, List, Optional, Union, Callable, Awaitable, TypeVar, Type, cast
 
 from pydantic import BaseModel, Field, validator, ValidationError
 
-from .ipc import FastSearchClient, IpcError
+from .ipc import FastSearchClient, IpcConnectionError, IpcError
+from .fallback import walk_search
+from .federation import FederationClient, FederationError
 from .exceptions import McpError
 from .tools import ToolRegistry, ToolInfo, tool as tool_decorator
 
@@ -117,9 +120,15 @@ class McpServer:
         """
         self.service_pipe = service_pipe
         self._running = False
+        self._stdout = None
         self._shutdown_event = asyncio.Event()
         self._client = FastSearchClient(pipe_name=service_pipe)
         self._tool_registry = tool_registry or get_global_registry()
+        try:
+            self._federation = FederationClient()
+        except FederationError as e:
+            logger.error(f"Federation disabled: {e}")
+            self._federation = FederationClient(endpoints=[])
         
         # Register standard MCP methods
         self.register_tool("mcp.get_capabilities", self.handle_get_capabilities)
@@ -129,6 +138,7 @@ class McpServer:
         # Register FastSearch methods
         self.register_tool("fastsearch.search", self.handle_search)
         self.register_tool("fastsearch.status", self.handle_status)
+        self.register_tool("fastsearch.federated_search", self.handle_federated_search)
     
     def register_method(self, name: str, handler: Handler) -> None:
         """
@@ -204,6 +214,7 @@ class McpServer:
         
         stdin = stdin or sys.stdin
         stdout = stdout or sys.stdout
+        self._stdout = stdout
         
         logger.info("Starting FastSearch MCP server")
         
@@ -394,6 +405,23 @@ class McpServer:
         else:
             return await handler(*params)
     
+    async def send_notification(self, method: str, params: Dict[str, Any]) -> None:
+        """Write a JSON-RPC notification to the client.
+        
+        Args:
+            method: Notification method (e.g. ``notifications/progress``)
+            params: Notification parameters
+        """
+        stdout = self._stdout
+        if stdout is None:
+            return
+        
+        line = json.dumps({"jsonrpc": "2.0", "method": method, "params": params}) + "\n"
+        await asyncio.get_running_loop().run_in_executor(
+            None,
+            lambda: stdout.write(line) or stdout.flush()
+        )
+    
     def _handle_shutdown_signal(self, signum, frame=None):
         """Handle shutdown signals."""
         logger.info(f"Received signal {signum}, shutting down...")
@@ -468,15 +496,22 @@ class McpServer:
         query: str,
         search_type: str = "fuzzy",
         max_results: int = 50,
+        _meta: Optional[Dict[str, Any]] = None,
         **filters
     ) -> List[Dict[str, Any]]:
         """
         Execute a search query against the FastSearch service.
         
+        If the request carries ``_meta.progressToken``, matches are streamed as
+        ``notifications/progress`` while the service is still scanning. When the
+        service can't be reached, folders are walked here instead and the result
+        is marked ``bridge_fallback`` in ``_meta.index_modes``.
+        
         Args:
             query: The search query string
             search_type: Type of search to perform (fuzzy, exact, regex, glob)
             max_results: Maximum number of results to return
+            _meta: MCP request metadata (progress token)
             **filters: Additional filters to apply to the search
             
         Returns:
@@ -494,17 +529,113 @@ class McpServer:
         if not isinstance(max_results, int) or max_results < 1 or max_results > 1000:
             raise InvalidParams("max_results must be an integer between 1 and 1000")
             
+        on_partial = None
+        progress_token = (_meta or {}).get("progressToken")
+        if progress_token is not None:
+            streamed = 0
+            
+            async def on_partial(chunk: Dict[str, Any]) -> None:
+                nonlocal streamed
+                streamed += len(chunk.get("results", []))
+                await self.send_notification("notifications/progress", {
+                    "progressToken": progress_token,
+                    "progress": chunk.get("scanned", 0),
+                    "total": chunk.get("total"),
+                    "message": f"{streamed} matches so far",
+                    "results": chunk.get("results", []),
+                })
+            
         try:
             return await self._client.search(
                 query=query,
                 search_type=search_type,
                 max_results=max_results,
+                on_partial=on_partial,
                 **filters
             )
+        except IpcConnectionError as e:
+            # No service to ask: walk folders here rather than fail (bridge_fallback mode)
+            logger.warning(f"Service unavailable, searching without it: {e}")
+            loop = asyncio.get_running_loop()
+            return await loop.run_in_executor(None, lambda: walk_search(
+                query,
+                search_type=search_type,
+                max_results=max_results,
+                path=filters.get("path"),
+                drive=filters.get("drive"),
+                reason=str(e),
+            ))
         except IpcError as e:
             logger.error(f"Search failed: {e}")
             raise InternalError(f"Search failed: {e}") from e
     
+    @tool_decorator(
+        "fastsearch.federated_search",
+        "Search this machine and all configured federation endpoints",
+        parameters={
+            "query": {"type": "str", "description": "The search pattern"},
+            "max_results": {"type": "int", "default": 50, "description": "Maximum number of results per host"},
+            "hosts": {"type": "list", "default": None, "description": "Endpoint names to search (default: all)"},
+            "include_local": {"type": "bool", "default": True, "description": "Also search this machine"},
+            "filters": {"type": "dict", "default": {}, "description": "Additional filters (path, drive, ...)"}
+        },
+        returns={"type": "dict", "description": "Merged results with a host column and per-host status"}
+    )
+    async def handle_federated_search(
+        self,
+        query: str,
+        max_results: int = 50,
+        hosts: Optional[List[str]] = None,
+        include_local: bool = True,
+        **filters
+    ) -> Dict[str, Any]:
+        """
+        Fan a search out to the local service and remote FastSearch web APIs.
+        
+        Args:
+            query: The search pattern
+            max_results: Maximum number of results per host
+            hosts: Optional endpoint names to restrict the remote search to
+            include_local: Whether to search this machine as well
+            **filters: Additional filters passed to every host
+            
+        Returns:
+            Dictionary with merged ``results`` (each tagged with ``host``) and
+            a per-host ``hosts`` status list
+        """
+        if not query or not isinstance(query, str):
+            raise InvalidParams("Query must be a non-empty string")
+            
+        if not isinstance(max_results, int) or max_results < 1 or max_results > 1000:
+            raise InvalidParams("max_results must be an integer between 1 and 1000")
+            
+        if hosts is not None:
+            unknown = set(hosts) - {endpoint.name for endpoint in self._federation.endpoints}
+            if unknown:
+                raise InvalidParams(f"Unknown federation hosts: {', '.join(sorted(unknown))}")
+        
+        # Start the remote fan-out first so it overlaps with the local search
+        remote = asyncio.ensure_future(
+            self._federation.search(query, max_results=max_results, hosts=hosts, **filters)
+        )
+        merged = {"results": [], "hosts": []}
+        
+        if include_local:
+            local_host = socket.gethostname()
+            try:
+                local = await self._client.search(query, search_type="glob", max_results=max_results, **filters)
+                local_results = local.get("results", []) if isinstance(local, dict) else local
+                merged["results"].extend(dict(result, host=local_host) for result in local_results)
+                merged["hosts"].append({"host": local_host, "ok": True, "count": len(local_results)})
+            except IpcError as e:
+                logger.warning(f"Local part of federated search failed: {e}")
+                merged["hosts"].append({"host": local_host, "ok": False, "count": 0, "error": str(e)})
+        
+        remote = await remote
+        merged["results"].extend(remote["results"])
+        merged["hosts"].extend(remote["hosts"])
+        return merged
+    
     @tool_decorator("fastsearch.status", "Get the current status of the FastSearch service")
     async def handle_status(self) -> Dict[str, Any]:
         """
@@ -0,0 +1,214 @@
+"""Validation of search filters before they are sent to the service.
+
+Sizes may be given as byte counts or human-readable strings (``"1.5GB"``,
+``"200k"``, ``"4096"``); they are converted to bytes here so a bad value is
+reported by the bridge with the argument it came from, instead of failing
+the whole request in the service.
+
+Regex patterns are checked against the length and nesting limits the
+service compiles them with. The service's regex engine can't backtrack, but
+Python's can, and the bridge's fallback search matches with it, so there
+patterns with nested repetition like ``(a+)+`` are refused as well.
+"""
+
+import math
+import re
+from typing import Any, Dict, Optional, Union
+
+# Binary multiples, as the service counts them
+SIZE_UNITS = {
+    "": 1,
+    "b": 1,
+    "k": 1024,
+    "kb": 1024,
+    "kib": 1024,
+    "m": 1024 ** 2,
+    "mb": 1024 ** 2,
+    "mib": 1024 ** 2,
+    "g": 1024 ** 3,
+    "gb": 1024 ** 3,
+    "gib": 1024 ** 3,
+    "t": 1024 ** 4,
+    "tb": 1024 ** 4,
+    "tib": 1024 ** 4,
+}
+
+# Size filter arguments converted by validate_filters
+SIZE_FILTERS = ("min_size", "max_size")
+
+# Longest regex pattern accepted, as in the service
+MAX_REGEX_LENGTH = 1024
+
+# Deepest nesting of groups accepted, as in the service
+MAX_REGEX_NESTING = 32
+
+_SIZE_RE = re.compile(r"^(?P<number>\d+(?:\.\d*)?|\.\d+)\s*(?P<unit>[a-z]*)$")
+
+
+class ValidationError(ValueError):
+    """Raised for a filter value that can't be sent to the service."""
+    pass
+
+
+def parse_size(value: Union[int, float, str], name: str = "size") -> int:
+    """Convert a size to bytes.
+
+    Args:
+        value: A non-negative byte count, or a number with an optional unit
+            (B, K/KB/KiB, M/MB/MiB, G/GB/GiB, T/TB/TiB; case-insensitive)
+        name: Argument name used in error messages
+
+    Returns:
+        The size in bytes, rounded down to a whole byte
+
+    Raises:
+        ValidationError: If the value isn't a size
+    """
+    if isinstance(value, bool):
+        raise ValidationError(f"'{name}' must be a byte count or a size like \"10MB\", not a boolean")
+    if isinstance(value, (int, float)):
+        if not math.isfinite(value) or value < 0:
+            raise ValidationError(f"'{name}' must be a non-negative number of bytes, got {value}")
+        return int(value)
+    if not isinstance(value, str):
+        raise ValidationError(f"'{name}' must be a byte count or a size like \"10MB\"")
+
+    text = value.strip().lower()
+    if text.startswith("-"):
+        raise ValidationError(f"'{name}' can't be negative: '{value}'")
+    match = _SIZE_RE.match(text)
+    if match is None:
+        raise ValidationError(f"'{name}' has invalid size '{value}' (expected a number with an optional unit, like \"1.5GB\" or \"200k\")")
+    unit = match.group("unit")
+    if unit not in SIZE_UNITS:
+        raise ValidationError(f"'{name}' has unknown size unit '{unit}' in '{value}' (expected B, KB, MB, GB or TB)")
+    return int(float(match.group("number")) * SIZE_UNITS[unit])
+
+
+def validate_filters(filters: Dict[str, Any]) -> Dict[str, Any]:
+    """Return the filters with sizes converted to bytes.
+
+    Raises:
+        ValidationError: If a size can't be read, or ``min_size`` is larger than ``max_size``
+    """
+    validated = dict(filters)
+    for name in SIZE_FILTERS:
+        if validated.get(name) is not None:
+            validated[name] = parse_size(validated[name], name)
+
+    min_size: Optional[int] = validated.get("min_size")
+    max_size: Optional[int] = validated.get("max_size")
+    if min_size is not None and max_size is not None and min_size > max_size:
+        raise ValidationError(f"min_size ({min_size} bytes) is larger than max_size ({max_size} bytes)")
+    return validated
+
+
+_COUNTED_RE = re.compile(r"\{(\d*)(,?)(\d*)\}")
+
+
+def _nested_repetition(pattern: str) -> bool:
+    """Whether a repeated group contains a repetition itself, like ``(a+)+`` or ``(\\w*x){2,}``."""
+    # One entry per open group: whether something inside it repeats a varying number of times.
+    # A fixed count like (a{2})+ can only match one way, so it doesn't count
+    groups = []
+    group_repeats = False  # The group that just closed has such a repetition inside
+    i = 0
+    while i < len(pattern):
+        ch = pattern[i]
+        repeats = varies = False
+        if ch == "\\":
+            i += 2
+            group_repeats = False
+            continue
+        if ch == "[":
+            # Skip the class; quantifier characters in it are literal
+            i += 1
+            if pattern[i:i + 1] == "^":
+                i += 1
+            if pattern[i:i + 1] == "]":
+                i += 1
+            while i < len(pattern) and pattern[i] != "]":
+                i += 2 if pattern[i] == "\\" else 1
+            i += 1
+            group_repeats = False
+            continue
+        if ch == "(":
+            groups.append(False)
+        elif ch == ")":
+            inner = groups.pop() if groups else False
+            if inner and groups:
+                groups[-1] = True
+            group_repeats = inner
+            i += 1
+            continue
+        elif ch in "*+":
+            repeats = varies = True
+        elif ch == "{":
+            counted = _COUNTED_RE.match(pattern, i)
+            if counted:
+                low, comma, high = counted.groups()
+                varies = bool(comma) and (not high or int(high) > int(low or 0))
+                repeats = varies or int(low or 0) > 1
+                i = counted.end() - 1
+        elif ch == "?":
+            # At most once, or a lazy quantifier: repeats no more than what it follows
+            i += 1
+            continue
+        if repeats and group_repeats:
+            return True
+        if varies and groups:
+            groups[-1] = True
+        group_repeats = False
+        i += 1
+    return False
+
+
+def validate_regex(pattern: str, name: str = "pattern", backtracking: bool = False) -> str:
+    """Check a regex pattern against the service's limits.
+
+    Args:
+        pattern: The regular expression
+        name: Argument name used in error messages
+        backtracking: The pattern will be matched with Python's ``re`` (the
+            fallback search) rather than by the service; it must then compile
+            with ``re`` and can't have nested repetition
+
+    Returns:
+        The pattern, unchanged
+
+    Raises:
+        ValidationError: If the pattern is too long or nested too deeply, or
+            with ``backtracking``, isn't valid for ``re`` or has nested repetition
+    """
+    if not isinstance(pattern, str):
+        raise ValidationError(f"'{name}' must be a string")
+    if len(pattern) > MAX_REGEX_LENGTH:
+        raise ValidationError(f"'{name}' is too long ({len(pattern)} characters, at most {MAX_REGEX_LENGTH})")
+    depth = deepest = 0
+    escaped = False
+    for ch in pattern:
+        if escaped:
+            escaped = False
+        elif ch == "\\":
+            escaped = True
+        elif ch == "(":
+            depth += 1
+            deepest = max(deepest, depth)
+        elif ch == ")":
+            depth -= 1
+    if deepest > MAX_REGEX_NESTING:
+        raise ValidationError(f"'{name}' is nested too deeply ({deepest} levels, at most {MAX_REGEX_NESTING})")
+    if not backtracking:
+        # The service compiles it with its own syntax and reports its own errors
+        return pattern
+
+    try:
+        re.compile(pattern)
+    except re.error as e:
+        raise ValidationError(f"'{name}' is not a valid regex: {e}") from e
+    if _nested_repetition(pattern):
+        raise ValidationError(
+            f"'{name}' repeats a group that repeats something itself (like (a+)+), "
+            "which can take exponential time to match; simplify the repetition"
+        )
+    return pattern
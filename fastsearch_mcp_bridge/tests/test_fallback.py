@@ -0,0 +1,46 @@
+"""Tests for searching without the service."""
+import os
+import sys
+import tempfile
+import unittest
+
+# Add the parent directory to the path so we can import the module
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), '..', 'src')))
+
+from fastsearch_mcp.fallback import BRIDGE_FALLBACK, walk_search
+from fastsearch_mcp.validation import ValidationError
+
+
+class TestWalkSearch(unittest.TestCase):
+    """Test the bridge_fallback walk."""
+
+    def setUp(self):
+        self.root = tempfile.mkdtemp()
+        os.makedirs(os.path.join(self.root, "docs"))
+        for name in ("report_final.docx", os.path.join("docs", "Report_v2.docx"), "notes.txt"):
+            with open(os.path.join(self.root, name), "w") as handle:
+                handle.write("x")
+
+    def test_matches_and_reports_mode(self):
+        """Matches are found below the path and the result is marked degraded."""
+        result = walk_search("report*.docx", path=self.root, drive="D")
+
+        self.assertEqual(sorted(r["name"] for r in result["results"]), ["Report_v2.docx", "report_final.docx"])
+        self.assertEqual(result["_meta"]["index_modes"]["D"]["mode"], BRIDGE_FALLBACK)
+        self.assertTrue(result["_meta"]["degraded"])
+        self.assertIn("Degraded mode", result["content"][0]["text"])
+
+    def test_max_results_and_search_types(self):
+        """The walk stops at max_results and honours the search type."""
+        self.assertEqual(len(walk_search("*", path=self.root, max_results=2)["results"]), 2)
+        self.assertEqual(len(walk_search("rprtfnl", search_type="fuzzy", path=self.root)["results"]), 1)
+        self.assertEqual(len(walk_search(r"\.txt$", search_type="regex", path=self.root)["results"]), 1)
+
+    def test_backtracking_regex_is_refused(self):
+        """Nested repetition would hang Python's regex engine on a long name."""
+        with self.assertRaises(ValidationError):
+            walk_search(r"^(\w+)+$", search_type="regex", path=self.root)
+
+
+if __name__ == "__main__":
+    unittest.main()
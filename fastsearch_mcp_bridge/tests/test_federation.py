@@ -0,0 +1,79 @@
+"""Tests for the federation client."""
+import asyncio
+import json
+import os
+import sys
+import tempfile
+import unittest
+from unittest.mock import patch
+
+# Add the parent directory to the path so we can import the module
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), '..', 'src')))
+
+from fastsearch_mcp.federation import (
+    FederationClient, FederationEndpoint, FederationError, load_endpoints
+)
+
+
+class TestLoadEndpoints(unittest.TestCase):
+    """Test loading the federation config."""
+
+    def _write_config(self, config):
+        handle = tempfile.NamedTemporaryFile("w", suffix=".json", delete=False)
+        json.dump(config, handle)
+        handle.close()
+        self.addCleanup(os.unlink, handle.name)
+        return handle.name
+
+    def test_missing_file_disables_federation(self):
+        """A missing config file means no endpoints."""
+        self.assertEqual(load_endpoints(os.path.join(tempfile.gettempdir(), "missing-federation.json")), [])
+
+    def test_api_key_from_environment(self):
+        """API keys can be read from an environment variable."""
+        path = self._write_config({"endpoints": [
+            {"name": "nas", "url": "http://nas:8080/", "api_key_env": "TEST_NAS_KEY"}
+        ]})
+        with patch.dict(os.environ, {"TEST_NAS_KEY": "secret"}):
+            endpoints = load_endpoints(path)
+
+        self.assertEqual(endpoints[0].url, "http://nas:8080")
+        self.assertEqual(endpoints[0].api_key, "secret")
+
+    def test_duplicate_names_rejected(self):
+        """Endpoint names must be unique."""
+        path = self._write_config({"endpoints": [
+            {"name": "nas", "url": "http://a"},
+            {"name": "nas", "url": "http://b"},
+        ]})
+        with self.assertRaises(FederationError):
+            load_endpoints(path)
+
+
+class TestFederatedSearch(unittest.TestCase):
+    """Test fanning out and merging results."""
+
+    def test_results_tagged_with_host(self):
+        """Results carry a host column and failures are reported per host."""
+        client = FederationClient([
+            FederationEndpoint(name="desktop", url="http://desktop"),
+            FederationEndpoint(name="nas", url="http://nas"),
+        ])
+
+        def fake_post(endpoint, route, payload):
+            if endpoint.name == "nas":
+                raise FederationError("Cannot reach http://nas: timed out")
+            return {"success": True, "results": [{"name": "a.txt", "path": "a.txt"}]}
+
+        with patch.object(FederationClient, "_post", side_effect=fake_post):
+            merged = asyncio.run(client.search("*.txt"))
+
+        self.assertEqual(merged["results"], [{"name": "a.txt", "path": "a.txt", "host": "desktop"}])
+        statuses = {status["host"]: status for status in merged["hosts"]}
+        self.assertTrue(statuses["desktop"]["ok"])
+        self.assertFalse(statuses["nas"]["ok"])
+        self.assertIn("timed out", statuses["nas"]["error"])
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,121 @@
+"""Tests for the IPC client's response framing."""
+import asyncio
+import json
+import os
+import struct
+import sys
+import unittest
+from unittest.mock import MagicMock, patch
+
+# The pywin32 modules only exist on Windows; the framing logic doesn't need them
+for module in ("pywintypes", "win32file", "win32pipe", "win32security", "win32", "win32.win32api"):
+    sys.modules.setdefault(module, MagicMock())
+if isinstance(sys.modules["pywintypes"], MagicMock):
+    sys.modules["pywintypes"].error = type("error", (Exception,), {})
+
+# Add the parent directory to the path so we can import the module
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), '..', 'src')))
+
+from fastsearch_mcp.ipc import (
+    FastSearchClient, IpcError, MSG_CANCEL, STATUS_ERROR, STATUS_OK, STATUS_PARTIAL
+)
+
+
+def frames(*responses):
+    """ReadFile side effect returning each (status, payload) as header and body reads."""
+    reads = []
+    for status, payload in responses:
+        body = payload if isinstance(payload, bytes) else json.dumps(payload).encode("utf-8")
+        reads.append((0, struct.pack("<II", status, len(body))))
+        if body:
+            reads.append((0, body))
+    return reads
+
+
+class TestStreamingSearch(unittest.TestCase):
+    """Test partial frames sent while the service is scanning."""
+
+    def setUp(self):
+        self.client = FastSearchClient()
+        self.client.connected = True
+        self.client.pipe_handle = object()
+
+    def test_partial_frames_delivered_before_result(self):
+        """Partial batches reach the callback and the final frame is the result."""
+        received = []
+        reads = frames(
+            (STATUS_PARTIAL, {"results": [{"name": "a.log"}], "scanned": 10, "total": 100}),
+            (STATUS_PARTIAL, {"results": [{"name": "b.log"}], "scanned": 60, "total": 100}),
+            (STATUS_OK, {"results": [{"name": "a.log"}, {"name": "b.log"}]}),
+        )
+
+        with patch("fastsearch_mcp.ipc.win32file") as win32file:
+            win32file.WriteFile.return_value = (0, 0)
+            win32file.ReadFile.side_effect = reads
+            result = asyncio.run(self.client.search("*.log", on_partial=received.append))
+
+            request = json.loads(win32file.WriteFile.call_args[0][1][8:].decode("utf-8"))
+
+        self.assertTrue(request["stream"])
+        self.assertEqual([chunk["scanned"] for chunk in received], [10, 60])
+        self.assertEqual(len(result["results"]), 2)
+
+    def test_error_after_partial_frames(self):
+        """An error frame after partial results still raises."""
+        reads = frames(
+            (STATUS_PARTIAL, {"results": [], "scanned": 1, "total": 2}),
+            (STATUS_ERROR, b"Invalid query"),
+        )
+
+        with patch("fastsearch_mcp.ipc.win32file") as win32file:
+            win32file.WriteFile.return_value = (0, 0)
+            win32file.ReadFile.side_effect = reads
+            with self.assertRaises(IpcError):
+                asyncio.run(self.client.search("*.log", on_partial=lambda chunk: None))
+
+
+
+class TestSearchRequest(unittest.TestCase):
+    """Test the search request sent to the service."""
+
+    def test_type_filters_are_sent(self):
+        """doc_type, extensions and fields go to the service at the top level of the request."""
+        client = FastSearchClient()
+        client.connected = True
+        client.pipe_handle = object()
+
+        with patch("fastsearch_mcp.ipc.win32file") as win32file:
+            win32file.WriteFile.return_value = (0, 0)
+            win32file.ReadFile.side_effect = frames((STATUS_OK, {"results": []}))
+            asyncio.run(client.search("report*", doc_type="pdf", extensions=("pdf",), fields=("path",), path="Users"))
+
+            request = json.loads(win32file.WriteFile.call_args[0][1][8:].decode("utf-8"))
+
+        self.assertEqual(request["doc_type"], "pdf")
+        self.assertEqual(request["extensions"], ["pdf"])
+        self.assertEqual(request["fields"], ["path"])
+        self.assertEqual(request["filters"], {"path": "Users"})
+
+    def test_cancel_by_request_id(self):
+        """A search carries its request_id, and cancel() sends it in a cancel message."""
+        client = FastSearchClient()
+        client.connected = True
+        client.pipe_handle = object()
+
+        with patch("fastsearch_mcp.ipc.win32file") as win32file:
+            win32file.WriteFile.return_value = (0, 0)
+            win32file.ReadFile.side_effect = frames(
+                (STATUS_OK, {"results": [], "cancelled": True}),
+                (STATUS_OK, {"request_id": "scan-1", "cancelled": True}),
+            )
+            asyncio.run(client.search("*", request_id="scan-1"))
+            search = json.loads(win32file.WriteFile.call_args[0][1][8:].decode("utf-8"))
+            self.assertTrue(asyncio.run(client.cancel("scan-1")))
+            message = win32file.WriteFile.call_args[0][1]
+
+        self.assertEqual(search["request_id"], "scan-1")
+        self.assertEqual(struct.unpack("<I", message[:4])[0], MSG_CANCEL)
+        self.assertEqual(json.loads(message[8:].decode("utf-8")), {"request_id": "scan-1"})
+
+if __name__ == '__main__':
+    unittest.main()
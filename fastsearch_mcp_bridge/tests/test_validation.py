@@ -0,0 +1,101 @@
+"""Tests for search filter validation."""
+import os
+import sys
+import unittest
+
+# Add the parent directory to the path so we can import the module
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), '..', 'src')))
+
+from fastsearch_mcp.validation import MAX_REGEX_LENGTH, ValidationError, parse_size, validate_filters, validate_regex
+
+
+class TestParseSize(unittest.TestCase):
+    """Test reading sizes given as numbers or strings."""
+
+    def test_human_readable_sizes(self):
+        """Units are binary multiples and case-insensitive; fractions round down."""
+        self.assertEqual(parse_size("4096"), 4096)
+        self.assertEqual(parse_size(4096), 4096)
+        self.assertEqual(parse_size("200k"), 200 * 1024)
+        self.assertEqual(parse_size("1.5GB"), 3 * 1024 ** 3 // 2)
+        self.assertEqual(parse_size(" 10 MiB "), 10 * 1024 ** 2)
+        self.assertEqual(parse_size("2t"), 2 * 1024 ** 4)
+        self.assertEqual(parse_size(".5kb"), 512)
+        self.assertEqual(parse_size("1.5"), 1)
+
+    def test_invalid_sizes_name_the_argument(self):
+        """Errors say which argument was wrong and why."""
+        for value, message in [
+            ("-5MB", "can't be negative"),
+            ("ten", "invalid size"),
+            ("", "invalid size"),
+            ("1.2.3", "invalid size"),
+            ("5 parsecs", "unknown size unit 'parsecs'"),
+            (-1, "non-negative"),
+            (float("nan"), "non-negative"),
+            (True, "boolean"),
+            ([10], "byte count"),
+        ]:
+            with self.subTest(value=value):
+                with self.assertRaises(ValidationError) as caught:
+                    parse_size(value, "min_size")
+                self.assertIn("'min_size'", str(caught.exception))
+                self.assertIn(message, str(caught.exception))
+
+
+class TestValidateFilters(unittest.TestCase):
+    """Test converting filters before they are sent to the service."""
+
+    def test_sizes_become_bytes(self):
+        """Size filters are converted; other filters pass through unchanged."""
+        filters = {"min_size": "1MB", "max_size": 2 * 1024 ** 2, "path": "Users"}
+        self.assertEqual(validate_filters(filters), {"min_size": 1024 ** 2, "max_size": 2 * 1024 ** 2, "path": "Users"})
+        self.assertEqual(filters["min_size"], "1MB")
+        self.assertEqual(validate_filters({}), {})
+        self.assertEqual(validate_filters({"min_size": None}), {"min_size": None})
+
+    def test_min_larger_than_max_is_rejected(self):
+        """An empty size window is an error rather than no results."""
+        with self.assertRaises(ValidationError) as caught:
+            validate_filters({"min_size": "2GB", "max_size": "1GB"})
+        self.assertIn("larger than max_size", str(caught.exception))
+
+
+
+class TestValidateRegex(unittest.TestCase):
+    """Test the limits on regex patterns."""
+
+    def test_service_limits(self):
+        """Length and nesting are limited; the service judges the syntax itself."""
+        self.assertEqual(validate_regex(r"^test_\d+\.log$"), r"^test_\d+\.log$")
+        # Valid for the service's engine, not for Python's
+        self.assertEqual(validate_regex(r"\p{Greek}+"), r"\p{Greek}+")
+        # Nested repetition can't backtrack in the service
+        self.assertEqual(validate_regex(r"(a+)+"), r"(a+)+")
+
+        with self.assertRaises(ValidationError) as caught:
+            validate_regex("a" * (MAX_REGEX_LENGTH + 1))
+        self.assertIn("too long", str(caught.exception))
+        with self.assertRaises(ValidationError) as caught:
+            validate_regex("(" * 40 + "a" + ")" * 40)
+        self.assertIn("nested too deeply", str(caught.exception))
+
+    def test_backtracking_limits(self):
+        """Matched with Python's re, nested repetition and invalid syntax are refused."""
+        for pattern in [r"(a+)+", r"(\w*x){2,}", r"((ab)*c)+", r"(a{1,3}){2}", r"(a)(b+)*"]:
+            with self.subTest(pattern=pattern):
+                with self.assertRaises(ValidationError) as caught:
+                    validate_regex(pattern, backtracking=True)
+                self.assertIn("exponential", str(caught.exception))
+
+        for pattern in [r"^report.*\.pdf$", r"(a|b)+", r"[(+]+x", r"(a+)?", r"(\d{4}-)+", r"[^]+]+(a)*"]:
+            with self.subTest(pattern=pattern):
+                self.assertEqual(validate_regex(pattern, backtracking=True), pattern)
+
+        with self.assertRaises(ValidationError) as caught:
+            validate_regex("([a-z", backtracking=True)
+        self.assertIn("not a valid regex", str(caught.exception))
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,80 @@
+/*
+ * FastSearch C ABI - embed the FastSearch engine in native applications
+ *
+ * Build fastsearch.dll with `cargo build -p fastsearch-ffi --release`.
+ * Strings are NUL-terminated UTF-8. Calls that fail return NULL (or 0) and
+ * leave a message for fastsearch_last_error() on the calling thread.
+ */
+
+#ifndef FASTSEARCH_H
+#define FASTSEARCH_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct FsEngine FsEngine;
+
+/* One file or folder found by fastsearch_search */
+typedef struct FsFile {
+    char *name;
+    char *path;          /* relative to the drive root */
+    char drive;          /* drive letter the file was found under */
+    bool is_directory;
+    uint64_t size;
+    int64_t modified;    /* seconds since the UNIX epoch */
+} FsFile;
+
+/* Results of fastsearch_search, freed with fastsearch_free_results */
+typedef struct FsResults {
+    FsFile *files;
+    size_t count;
+    char *next_cursor;   /* NULL if there are no more matches */
+    bool truncated;      /* the search stopped at its timeout */
+} FsResults;
+
+/* An entry joining (added) or leaving the results of a subscription;
+ * valid only during the callback */
+typedef struct FsUpdate {
+    uint64_t subscription;
+    bool added;
+    const char *name;
+    const char *path;    /* full path, with the drive letter */
+    bool is_directory;
+} FsUpdate;
+
+/* Called on a library thread for each update of a subscription */
+typedef void (*FsUpdateCallback)(const FsUpdate *update, void *user_data);
+
+/* Open the engine with the service's drive configuration; NULL on failure */
+FsEngine *fastsearch_init(void);
+
+/* Close the engine, ending its subscriptions; returns once no callback is running */
+void fastsearch_shutdown(FsEngine *engine);
+
+/* Search a drive (0 for the default drive) for a glob pattern;
+ * max_results 0 returns the default number of results */
+FsResults *fastsearch_search(const FsEngine *engine, const char *pattern, char drive, uint32_t max_results);
+
+void fastsearch_free_results(FsResults *results);
+
+/* Follow the entries that start or stop matching a pattern; returns a handle, 0 on failure */
+uint64_t fastsearch_subscribe(const FsEngine *engine, const char *pattern, char drive,
+                              FsUpdateCallback callback, void *user_data);
+
+/* End a subscription; returns whether it was still active. Returns once the
+ * callback won't be called again, unless called from inside the callback. */
+bool fastsearch_unsubscribe(const FsEngine *engine, uint64_t subscription);
+
+/* Message of the last failed call on this thread, or NULL */
+const char *fastsearch_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FASTSEARCH_H */
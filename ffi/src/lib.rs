@@ -0,0 +1,442 @@
+//! C ABI over the embedded FastSearch engine
+//!
+//! A small set of `extern "C"` functions wrapping [`fastsearch_service::Engine`]
+//! so C#, C++ and other native desktop applications can search the MFT
+//! caches in-process, without the named pipe or the HTTP API. The
+//! declarations are in `include/fastsearch.h`.
+//!
+//! Strings crossing the boundary are NUL-terminated UTF-8. Results are owned
+//! by the library until passed to `fastsearch_free_results`. Functions that
+//! fail return NULL (or 0) and leave a message for `fastsearch_last_error`
+//! on the calling thread; a panic inside the library counts as a failure
+//! and never unwinds into the caller.
+//!
+//! With the `python` feature the same library is also a Python extension
+//! module (`import fastsearch`), see [`python`].
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::any::Any;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, Result};
+use fastsearch_service::{Engine, FoundFile, LiveChange, LiveUpdate, SearchRequest};
+use log::{debug, warn};
+use tokio::sync::broadcast::error::RecvError;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+thread_local! {
+    /// Message of the last call that failed on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An engine opened by `fastsearch_init`
+pub struct FsEngine {
+    engine: Engine,
+    /// Subscriptions behind the handles given out
+    subscriptions: Mutex<HashMap<u64, FsSubscription>>,
+    next_subscription: AtomicU64,
+}
+
+/// A subscription made with `fastsearch_subscribe`
+struct FsSubscription {
+    /// ID of the live query
+    id: String,
+    /// Thread calling the callback, which ends once the live query does
+    delivery: thread::JoinHandle<()>,
+}
+
+impl FsSubscription {
+    /// End the live query and wait until the callback won't be called again
+    fn end(self, engine: &Engine) -> bool {
+        let ended = engine.unsubscribe(&self.id);
+        // Ended from inside the callback, the thread stops once it returns
+        if self.delivery.thread().id() != thread::current().id() {
+            let _ = self.delivery.join();
+        }
+        ended
+    }
+}
+
+/// One file or folder found by `fastsearch_search`
+#[repr(C)]
+pub struct FsFile {
+    pub name: *mut c_char,
+    /// Path relative to the drive root
+    pub path: *mut c_char,
+    /// Drive letter the file was found under
+    pub drive: c_char,
+    pub is_directory: bool,
+    pub size: u64,
+    /// Seconds since the UNIX epoch
+    pub modified: i64,
+}
+
+/// The results of `fastsearch_search`, freed with `fastsearch_free_results`
+#[repr(C)]
+pub struct FsResults {
+    pub files: *mut FsFile,
+    pub count: usize,
+    /// Cursor for the next page, or NULL if there are no more matches
+    pub next_cursor: *mut c_char,
+    /// The search stopped at its timeout, so matches may be missing
+    pub truncated: bool,
+}
+
+/// An entry joining (`added` true) or leaving the results of a subscription
+///
+/// Only valid for the duration of the callback.
+#[repr(C)]
+pub struct FsUpdate {
+    pub subscription: u64,
+    pub added: bool,
+    pub name: *const c_char,
+    /// Full path, with the drive letter
+    pub path: *const c_char,
+    pub is_directory: bool,
+}
+
+/// Receives the updates of a subscription, on a thread of the library's
+pub type FsUpdateCallback = extern "C" fn(update: *const FsUpdate, user_data: *mut c_void);
+
+/// The caller's `user_data`, handed back to the callback on the update thread
+struct UserData(*mut c_void);
+
+// The caller promises the pointer may be used from the update thread
+unsafe impl Send for UserData {}
+
+/// Open the engine with the service's drive configuration; NULL on failure
+#[no_mangle]
+pub extern "C" fn fastsearch_init() -> *mut FsEngine {
+    guarded(ptr::null_mut(), || match Engine::open() {
+        Ok(engine) => Box::into_raw(Box::new(FsEngine {
+            engine,
+            subscriptions: Mutex::new(HashMap::new()),
+            next_subscription: AtomicU64::new(1),
+        })),
+        Err(e) => fail(e),
+    })
+}
+
+/// Close an engine, ending its subscriptions
+///
+/// Returns once no callback is running any more.
+///
+/// # Safety
+/// `engine` must come from `fastsearch_init` (or be NULL) and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn fastsearch_shutdown(engine: *mut FsEngine) {
+    if engine.is_null() {
+        return;
+    }
+    let engine = Box::from_raw(engine);
+    guarded((), || {
+        let subscriptions = engine.subscriptions.lock().map(|mut subs| subs.drain().collect::<Vec<_>>()).unwrap_or_default();
+        for (_, subscription) in subscriptions {
+            subscription.end(&engine.engine);
+        }
+    })
+}
+
+/// Search a drive (`drive` 0 for the default drive) for a glob pattern
+///
+/// `max_results` 0 returns the engine's default number of results.
+///
+/// # Safety
+/// `engine` must come from `fastsearch_init`; `pattern` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fastsearch_search(
+    engine: *const FsEngine,
+    pattern: *const c_char,
+    drive: c_char,
+    max_results: u32,
+) -> *mut FsResults {
+    guarded(ptr::null_mut(), || search(engine, pattern, drive, max_results))
+}
+
+unsafe fn search(engine: *const FsEngine, pattern: *const c_char, drive: c_char, max_results: u32) -> *mut FsResults {
+    let result = (|| {
+        let engine = engine.as_ref().ok_or_else(|| anyhow!("engine is NULL"))?;
+        let request = request(pattern, drive, max_results)?;
+        engine.engine.search(&request)
+    })();
+    match result {
+        Ok(found) => {
+            let files: Box<[FsFile]> = found.files.iter().map(fs_file).collect();
+            let count = files.len();
+            Box::into_raw(Box::new(FsResults {
+                files: Box::into_raw(files) as *mut FsFile,
+                count,
+                next_cursor: found.next_cursor.map_or(ptr::null_mut(), |cursor| c_string(&cursor)),
+                truncated: found.truncated,
+            }))
+        }
+        Err(e) => fail(e),
+    }
+}
+
+/// Free the results of `fastsearch_search`
+///
+/// # Safety
+/// `results` must come from `fastsearch_search` (or be NULL) and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn fastsearch_free_results(results: *mut FsResults) {
+    if results.is_null() {
+        return;
+    }
+    guarded((), || {
+        let results = Box::from_raw(results);
+        let files = Box::from_raw(ptr::slice_from_raw_parts_mut(results.files, results.count));
+        for file in files.iter() {
+            drop(CString::from_raw(file.name));
+            drop(CString::from_raw(file.path));
+        }
+        if !results.next_cursor.is_null() {
+            drop(CString::from_raw(results.next_cursor));
+        }
+    })
+}
+
+/// Follow the entries that start or stop matching a search; returns a handle, 0 on failure
+///
+/// `callback` is called on a thread of the library's for each update until
+/// `fastsearch_unsubscribe` or `fastsearch_shutdown` returns.
+///
+/// # Safety
+/// `engine` must come from `fastsearch_init`; `pattern` must be a NUL-terminated
+/// string; `user_data` must be usable from another thread.
+#[no_mangle]
+pub unsafe extern "C" fn fastsearch_subscribe(
+    engine: *const FsEngine,
+    pattern: *const c_char,
+    drive: c_char,
+    callback: FsUpdateCallback,
+    user_data: *mut c_void,
+) -> u64 {
+    guarded(0, || {
+        subscribe(engine, pattern, drive, callback, UserData(user_data)).unwrap_or_else(|e| {
+            fail::<()>(e);
+            0
+        })
+    })
+}
+
+unsafe fn subscribe(
+    engine: *const FsEngine,
+    pattern: *const c_char,
+    drive: c_char,
+    callback: FsUpdateCallback,
+    user_data: UserData,
+) -> Result<u64> {
+    let engine = engine.as_ref().ok_or_else(|| anyhow!("engine is NULL"))?;
+    let subscription = engine.engine.subscribe(&request(pattern, drive, 0)?)?;
+    let handle = engine.next_subscription.fetch_add(1, Ordering::Relaxed);
+
+    let mut updates = subscription.updates;
+    let spawned = thread::Builder::new()
+        .name(format!("fastsearch-subscription-{}", handle))
+        .spawn(move || {
+            let user_data = user_data;
+            loop {
+                match updates.blocking_recv() {
+                    Ok(update) => {
+                        let delivered = panic::catch_unwind(AssertUnwindSafe(|| deliver(handle, &update, callback, user_data.0)));
+                        if let Err(payload) = delivered {
+                            warn!("Subscription {} dropped an update: {}", handle, panic_message(&*payload));
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => debug!("Subscription {} missed {} updates", handle, missed),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    let delivery = match spawned {
+        Ok(delivery) => delivery,
+        Err(e) => {
+            engine.engine.unsubscribe(&subscription.id);
+            return Err(e.into());
+        }
+    };
+    let subscription = FsSubscription { id: subscription.id, delivery };
+    match engine.subscriptions.lock() {
+        Ok(mut subscriptions) => {
+            subscriptions.insert(handle, subscription);
+            Ok(handle)
+        }
+        Err(_) => {
+            subscription.end(&engine.engine);
+            Err(anyhow!("subscription table is poisoned"))
+        }
+    }
+}
+
+/// End a subscription; returns whether it was still active
+///
+/// Returns once the callback won't be called again, unless called from
+/// inside the callback itself.
+///
+/// # Safety
+/// `engine` must come from `fastsearch_init`.
+#[no_mangle]
+pub unsafe extern "C" fn fastsearch_unsubscribe(engine: *const FsEngine, subscription: u64) -> bool {
+    let Some(engine) = engine.as_ref() else {
+        return false;
+    };
+    guarded(false, || {
+        let removed = engine.subscriptions.lock().ok().and_then(|mut subs| subs.remove(&subscription));
+        removed.map_or(false, |removed| removed.end(&engine.engine))
+    })
+}
+
+/// Message of the last call that failed on this thread, or NULL
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn fastsearch_last_error() -> *const c_char {
+    guarded(ptr::null(), || {
+        LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+    })
+}
+
+/// Run the body of an export, returning `failed` (with the panic as the last error) if it panics
+fn guarded<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        fail::<()>(anyhow!("internal error: {}", panic_message(&*payload)));
+        failed
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("panic")
+}
+
+/// The typed request for a pattern and drive given over the ABI
+unsafe fn request(pattern: *const c_char, drive: c_char, max_results: u32) -> Result<SearchRequest> {
+    if pattern.is_null() {
+        return Err(anyhow!("pattern is NULL"));
+    }
+    let pattern = CStr::from_ptr(pattern).to_str().map_err(|_| anyhow!("pattern is not UTF-8"))?;
+    let mut request = SearchRequest::new(pattern);
+    if drive != 0 {
+        request.drive = Some((drive as u8 as char).to_ascii_uppercase());
+    }
+    if max_results > 0 {
+        request.max_results = max_results as usize;
+    }
+    Ok(request)
+}
+
+fn fs_file(file: &FoundFile) -> FsFile {
+    FsFile {
+        name: c_string(&file.name),
+        path: c_string(&file.path),
+        drive: file.drive as u8 as c_char,
+        is_directory: file.is_directory,
+        size: file.size,
+        modified: file.modified.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64),
+    }
+}
+
+fn deliver(handle: u64, update: &LiveUpdate, callback: FsUpdateCallback, user_data: *mut c_void) {
+    let name = lossy_c_string(&update.name);
+    let path = lossy_c_string(&update.path);
+    let update = FsUpdate {
+        subscription: handle,
+        added: update.change == LiveChange::Added,
+        name: name.as_ptr(),
+        path: path.as_ptr(),
+        is_directory: update.is_directory,
+    };
+    callback(&update, user_data);
+}
+
+/// An owned C string for the caller, freed with `fastsearch_free_results`
+fn c_string(s: &str) -> *mut c_char {
+    lossy_c_string(s).into_raw()
+}
+
+/// File names can't hold NUL, but a C string must not contain one either way
+fn lossy_c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+/// Record an error for `fastsearch_last_error` and return NULL
+fn fail<T>(e: anyhow::Error) -> *mut T {
+    let message = lossy_c_string(&format!("{:#}", e));
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    ptr::null_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_from_abi_arguments() {
+        let pattern = CString::new("*.rs").unwrap();
+        let on_d = unsafe { request(pattern.as_ptr(), b'd' as c_char, 25) }.unwrap();
+        assert_eq!(on_d.pattern, "*.rs");
+        assert_eq!(on_d.drive, Some('D'));
+        assert_eq!(on_d.max_results, 25);
+
+        let defaults = unsafe { request(pattern.as_ptr(), 0, 0) }.unwrap();
+        assert_eq!(defaults.drive, None);
+        assert_eq!(defaults.max_results, SearchRequest::new("*.rs").max_results);
+
+        assert!(unsafe { request(ptr::null(), 0, 0) }.is_err());
+    }
+
+    #[test]
+    fn test_errors_are_kept_per_thread() {
+        assert!(fail::<FsResults>(anyhow!("drive Q: is not mounted")).is_null());
+        let message = unsafe { CStr::from_ptr(fastsearch_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "drive Q: is not mounted");
+        let other = thread::spawn(|| fastsearch_last_error().is_null()).join().unwrap();
+        assert!(other);
+    }
+
+    #[test]
+    fn test_panics_become_failures() {
+        let failed = guarded(ptr::null_mut::<FsResults>(), || panic!("index out of bounds"));
+        assert!(failed.is_null());
+        let message = unsafe { CStr::from_ptr(fastsearch_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "internal error: index out of bounds");
+        assert_eq!(guarded(0, || 7), 7);
+    }
+
+    #[test]
+    fn test_results_are_freed() {
+        let file = FoundFile {
+            name: "main.rs".into(),
+            path: "src\\main.rs".into(),
+            drive: 'C',
+            size: 120,
+            is_directory: false,
+            created: UNIX_EPOCH,
+            modified: UNIX_EPOCH + std::time::Duration::from_secs(20),
+            accessed: UNIX_EPOCH,
+        };
+        let files: Box<[FsFile]> = vec![fs_file(&file)].into_boxed_slice();
+        assert_eq!(files[0].drive, b'C' as c_char);
+        assert_eq!(files[0].modified, 20);
+        assert_eq!(unsafe { CStr::from_ptr(files[0].path) }.to_str().unwrap(), "src\\main.rs");
+        let results = Box::into_raw(Box::new(FsResults {
+            count: files.len(),
+            files: Box::into_raw(files) as *mut FsFile,
+            next_cursor: c_string("abc"),
+            truncated: false,
+        }));
+        unsafe { fastsearch_free_results(results) };
+        unsafe { fastsearch_free_results(ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,331 @@
+//! Python bindings (`import fastsearch`)
+//!
+//! Typed wrappers of the engine API for scripts and notebooks:
+//!
+//! ```python
+//! import fastsearch
+//!
+//! engine = fastsearch.Engine()
+//! for file in engine.search("*.parquet", drive="D", min_size="100MB").files:
+//!     print(file.path, file.size)
+//!
+//! with engine.watch("*.log", drive="C") as watch:
+//!     for update in watch:
+//!         print(update.change, update.path)
+//! ```
+//!
+//! Searches release the GIL while they run; iterating a watch waits for the
+//! next update without holding it and stops on Ctrl+C.
+
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use fastsearch_service::{parse_size, CacheStats, Engine, FoundFile, LiveUpdate, SearchRequest, SearchResults, SearchType};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+/// How long a watch sleeps between checks for updates and Ctrl+C
+const WATCH_POLL: Duration = Duration::from_millis(50);
+
+/// The FastSearch engine, opened with the service's drive configuration
+#[pyclass(name = "Engine", module = "fastsearch")]
+pub struct PyEngine {
+    engine: Arc<Engine>,
+}
+
+/// A file or folder a search found
+#[pyclass(name = "FoundFile", module = "fastsearch", get_all)]
+#[derive(Clone)]
+pub struct PyFoundFile {
+    name: String,
+    /// Path relative to the drive root
+    path: String,
+    drive: String,
+    size: u64,
+    is_directory: bool,
+    /// Times as seconds since the UNIX epoch
+    created: f64,
+    modified: f64,
+    accessed: f64,
+}
+
+/// One page of search results
+#[pyclass(name = "SearchResults", module = "fastsearch", get_all)]
+pub struct PySearchResults {
+    files: Vec<PyFoundFile>,
+    /// Pass as `cursor` for the next page; None if there are no more matches
+    next_cursor: Option<String>,
+    /// The search stopped at `timeout_ms`, so matches may be missing
+    truncated: bool,
+}
+
+/// A drive's cache
+#[pyclass(name = "VolumeStats", module = "fastsearch", get_all)]
+pub struct PyVolumeStats {
+    drive: String,
+    file_count: usize,
+    memory_usage_bytes: u64,
+    /// Seconds since the UNIX epoch
+    last_update: f64,
+}
+
+/// An entry joining or leaving the results of a watch
+#[pyclass(name = "Update", module = "fastsearch", get_all)]
+pub struct PyUpdate {
+    /// "added" or "removed"
+    change: &'static str,
+    name: String,
+    /// Full path, with the drive letter
+    path: String,
+    is_directory: bool,
+    /// MFT record number
+    id: u64,
+}
+
+/// A search followed as the file system changes; iterate it for updates
+#[pyclass(name = "Watch", module = "fastsearch")]
+pub struct PyWatch {
+    engine: Arc<Engine>,
+    id: String,
+    #[pyo3(get)]
+    initial: Py<PySearchResults>,
+    updates: Option<broadcast::Receiver<LiveUpdate>>,
+}
+
+#[pymethods]
+impl PyEngine {
+    #[new]
+    fn new() -> PyResult<Self> {
+        let engine = Engine::open().map_err(runtime_error)?;
+        Ok(Self { engine: Arc::new(engine) })
+    }
+
+    /// Build a drive's cache (or find it built) and return its stats
+    fn open_volume(&self, py: Python<'_>, drive: &str) -> PyResult<PyVolumeStats> {
+        let drive = drive_letter(drive)?;
+        let engine = Arc::clone(&self.engine);
+        let stats = py.allow_threads(move || engine.open_volume(drive)).map_err(runtime_error)?;
+        Ok(volume_stats(&stats))
+    }
+
+    /// Stats of the drives whose caches are built
+    fn stats(&self) -> Vec<PyVolumeStats> {
+        self.engine.volumes().iter().map(volume_stats).collect()
+    }
+
+    /// Search a drive; sizes may be byte counts or strings like "1.5GB"
+    #[pyo3(signature = (pattern, drive=None, search_type="glob", path=None, extensions=None, exclude=None,
+                        min_size=None, max_size=None, max_results=1000, cursor=None, timeout_ms=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn search(
+        &self,
+        py: Python<'_>,
+        pattern: String,
+        drive: Option<&str>,
+        search_type: &str,
+        path: Option<String>,
+        extensions: Option<Vec<String>>,
+        exclude: Option<Vec<String>>,
+        min_size: Option<&PyAny>,
+        max_size: Option<&PyAny>,
+        max_results: usize,
+        cursor: Option<String>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<PySearchResults> {
+        let request = SearchRequest {
+            search_type: SearchType::parse(search_type)
+                .ok_or_else(|| PyValueError::new_err(format!("Unknown search_type '{}'", search_type)))?,
+            drive: drive.map(drive_letter).transpose()?,
+            path,
+            extensions: extensions.unwrap_or_default(),
+            exclude: exclude.unwrap_or_default(),
+            min_size: min_size.map(|size| size_arg(size, "min_size")).transpose()?,
+            max_size: max_size.map(|size| size_arg(size, "max_size")).transpose()?,
+            max_results,
+            cursor,
+            timeout: timeout_ms.map(Duration::from_millis),
+            ..SearchRequest::new(pattern)
+        };
+        let engine = Arc::clone(&self.engine);
+        let results = py.allow_threads(move || engine.search(&request)).map_err(value_error)?;
+        Ok(search_results(&results))
+    }
+
+    /// Run a search and follow the entries that start or stop matching it
+    #[pyo3(signature = (pattern, drive=None, search_type="glob", path=None, extensions=None))]
+    fn watch(
+        &self,
+        py: Python<'_>,
+        pattern: String,
+        drive: Option<&str>,
+        search_type: &str,
+        path: Option<String>,
+        extensions: Option<Vec<String>>,
+    ) -> PyResult<PyWatch> {
+        let request = SearchRequest {
+            search_type: SearchType::parse(search_type)
+                .ok_or_else(|| PyValueError::new_err(format!("Unknown search_type '{}'", search_type)))?,
+            drive: drive.map(drive_letter).transpose()?,
+            path,
+            extensions: extensions.unwrap_or_default(),
+            ..SearchRequest::new(pattern)
+        };
+        let engine = Arc::clone(&self.engine);
+        let subscription = py.allow_threads(move || engine.subscribe(&request)).map_err(value_error)?;
+        Ok(PyWatch {
+            engine: Arc::clone(&self.engine),
+            id: subscription.id,
+            initial: Py::new(py, search_results(&subscription.initial))?,
+            updates: Some(subscription.updates),
+        })
+    }
+}
+
+#[pymethods]
+impl PyWatch {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// The next update; waits for one, and ends when the watch is closed
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyUpdate>> {
+        loop {
+            let Some(updates) = self.updates.as_mut() else {
+                return Ok(None);
+            };
+            match updates.try_recv() {
+                Ok(update) => return Ok(Some(py_update(&update))),
+                Err(TryRecvError::Empty) => {
+                    py.allow_threads(|| thread::sleep(WATCH_POLL));
+                    py.check_signals()?;
+                }
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Closed) => {
+                    self.updates = None;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Stop following the search
+    fn close(&mut self) {
+        if self.updates.take().is_some() {
+            self.engine.unsubscribe(&self.id);
+        }
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(&mut self, _exc_type: Option<&PyAny>, _exc: Option<&PyAny>, _traceback: Option<&PyAny>) -> bool {
+        self.close();
+        false
+    }
+}
+
+impl Drop for PyWatch {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[pymethods]
+impl PyFoundFile {
+    fn __repr__(&self) -> String {
+        format!("FoundFile({}:\\{}, {} bytes)", self.drive, self.path, self.size)
+    }
+}
+
+#[pymethods]
+impl PyUpdate {
+    fn __repr__(&self) -> String {
+        format!("Update({}, {})", self.change, self.path)
+    }
+}
+
+/// The `fastsearch` module
+#[pymodule]
+fn fastsearch(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyEngine>()?;
+    module.add_class::<PyFoundFile>()?;
+    module.add_class::<PySearchResults>()?;
+    module.add_class::<PyVolumeStats>()?;
+    module.add_class::<PyUpdate>()?;
+    module.add_class::<PyWatch>()?;
+    Ok(())
+}
+
+/// A drive letter given as "D", "d" or "D:"
+fn drive_letter(drive: &str) -> PyResult<char> {
+    match drive.trim().trim_end_matches([':', '\\']).chars().collect::<Vec<_>>()[..] {
+        [letter] if letter.is_ascii_alphabetic() => Ok(letter.to_ascii_uppercase()),
+        _ => Err(PyValueError::new_err(format!("'{}' is not a drive letter", drive))),
+    }
+}
+
+/// A size argument: a byte count, or a string like "10MB"
+fn size_arg(value: &PyAny, name: &str) -> PyResult<u64> {
+    if let Ok(bytes) = value.extract::<u64>() {
+        return Ok(bytes);
+    }
+    let text: &str = value.extract()
+        .map_err(|_| PyValueError::new_err(format!("'{}' must be a byte count or a size like \"10MB\"", name)))?;
+    parse_size(text).map_err(|e| PyValueError::new_err(format!("'{}': {}", name, e)))
+}
+
+fn search_results(results: &SearchResults) -> PySearchResults {
+    PySearchResults {
+        files: results.files.iter().map(found_file).collect(),
+        next_cursor: results.next_cursor.clone(),
+        truncated: results.truncated,
+    }
+}
+
+fn found_file(file: &FoundFile) -> PyFoundFile {
+    PyFoundFile {
+        name: file.name.clone(),
+        path: file.path.clone(),
+        drive: file.drive.to_string(),
+        size: file.size,
+        is_directory: file.is_directory,
+        created: unix_seconds(file.created),
+        modified: unix_seconds(file.modified),
+        accessed: unix_seconds(file.accessed),
+    }
+}
+
+fn volume_stats(stats: &CacheStats) -> PyVolumeStats {
+    PyVolumeStats {
+        drive: stats.drive_letter.to_string(),
+        file_count: stats.file_count,
+        memory_usage_bytes: stats.memory_usage_bytes,
+        last_update: unix_seconds(stats.last_update),
+    }
+}
+
+fn py_update(update: &LiveUpdate) -> PyUpdate {
+    PyUpdate {
+        change: update.change.as_str(),
+        name: update.name.clone(),
+        path: update.path.clone(),
+        is_directory: update.is_directory,
+        id: update.id,
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Invalid searches come back as errors with the message the MCP tool would give
+fn value_error(e: anyhow::Error) -> PyErr {
+    PyValueError::new_err(format!("{:#}", e))
+}
+
+fn runtime_error(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", e))
+}
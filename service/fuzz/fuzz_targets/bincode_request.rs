@@ -0,0 +1,13 @@
+//! Bincode-encoded search requests
+//!
+//! Decoding must fail cleanly on any input, and length prefixes must not
+//! allocate past `MAX_REQUEST_SIZE`.
+
+#![no_main]
+
+use fastsearch_shared::decode_request;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_request(data);
+});
@@ -0,0 +1,28 @@
+//! Pattern and query compilation
+//!
+//! The first byte picks the search type and case sensitivity; the rest is a
+//! pattern and a file name to match it against, split at the first NUL. The
+//! whole input is also parsed as a boolean query.
+
+#![no_main]
+
+use fastsearch_service::{compile_pattern_with_case, QueryExpr, SearchType};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, rest)) = data.split_first() else {
+        return;
+    };
+    let Ok(text) = std::str::from_utf8(rest) else {
+        return;
+    };
+    let search_type = SearchType::ALL[selector as usize % SearchType::ALL.len()];
+    let case_sensitive = selector & 0x80 != 0;
+    let (pattern, name) = text.split_once('\0').unwrap_or((text, "Cargo.toml"));
+
+    if let Ok(matcher) = compile_pattern_with_case(pattern, search_type, case_sensitive) {
+        matcher.is_file_match(name, name);
+        matcher.score(name);
+    }
+    let _ = QueryExpr::parse(text);
+});
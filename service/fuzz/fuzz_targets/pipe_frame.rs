@@ -0,0 +1,32 @@
+//! Request messages as the pipe server reads them
+//!
+//! Any local process can open the pipe of the elevated service, so every
+//! message, however malformed, must come back as an error frame: decode the
+//! header, the JSON payload, the fast_search arguments a search maps to, and
+//! compile and run its patterns.
+
+#![no_main]
+
+use fastsearch_service::{parse_request_frame, pipe_search_args, PatternCache, PatternSet, SearchType, MSG_SEARCH};
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok((message_type, payload)) = parse_request_frame(data) else {
+        return;
+    };
+    if message_type != MSG_SEARCH {
+        return;
+    }
+    let Ok(request) = serde_json::from_slice::<Value>(payload) else {
+        return;
+    };
+    let args = pipe_search_args(&request);
+    let Ok(patterns) = PatternSet::from_args(&args) else {
+        return;
+    };
+    let search_type = args["search_type"].as_str().and_then(SearchType::parse).unwrap_or_default();
+    if let Ok(matcher) = PatternCache::new().get_or_compile_set(&patterns, search_type) {
+        matcher.is_file_match("Cargo.toml", "Users\\dev\\project\\Cargo.toml");
+    }
+});
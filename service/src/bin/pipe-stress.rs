@@ -0,0 +1,520 @@
+//! Load test for the named pipe server
+//!
+//! Runs dozens of concurrent clients against a running service, each sending a
+//! mix of searches, streamed searches, status requests and cancellations, while
+//! a churn thread creates, renames, rewrites and deletes files on the searched
+//! volume so the USN journal keeps the caches changing underneath them.
+//!
+//! Exits 0 when every query kind meets its latency budget and the error rate
+//! stays under the limit, 1 when a budget is missed, and 2 when a request hangs
+//! past `--hang-timeout` (a deadlock, or a server that stopped answering).
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use fastsearch_service::{encode_frame, MSG_CANCEL, MSG_SEARCH, MSG_STATUS, STATUS_OK, STATUS_PARTIAL};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const PIPE_NAME: &str = r"\\.\pipe\fastsearch-service";
+
+/// Every instance of the pipe is connected
+const ERROR_PIPE_BUSY: i32 = 231;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Load test for the FastSearch named pipe", long_about = None)]
+struct Args {
+    /// Concurrent pipe clients
+    #[arg(short, long, default_value_t = 32)]
+    clients: usize,
+
+    /// How long to run the load, in seconds
+    #[arg(short, long, default_value_t = 60)]
+    duration: u64,
+
+    /// Drive to search
+    #[arg(long, default_value = "C")]
+    drive: char,
+
+    /// Folder the churn thread works in, on the searched drive (default: a new folder in the temp directory)
+    #[arg(long)]
+    churn_dir: Option<PathBuf>,
+
+    /// File operations per second made by the churn thread (0 turns it off)
+    #[arg(long, default_value_t = 200)]
+    churn_rate: u32,
+
+    /// Median latency budget for every query kind, in milliseconds
+    #[arg(long, default_value_t = 100)]
+    p50_ms: u64,
+
+    /// 99th percentile latency budget for every query kind, in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    p99_ms: u64,
+
+    /// Share of requests allowed to fail
+    #[arg(long, default_value_t = 0.01)]
+    max_error_rate: f64,
+
+    /// A request unanswered for this many seconds counts as a hang
+    #[arg(long, default_value_t = 30)]
+    hang_timeout: u64,
+
+    /// How long to wait for the pipe and the first search (the cache build), in seconds
+    #[arg(long, default_value_t = 600)]
+    warmup_timeout: u64,
+
+    /// Output format (text or json)
+    #[arg(short, long, default_value = "text")]
+    format: String,
+}
+
+/// The queries clients pick from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum QueryKind {
+    Connect,
+    Glob,
+    Regex,
+    Filtered,
+    Streamed,
+    Status,
+    Cancel,
+}
+
+impl QueryKind {
+    const MIXED: [QueryKind; 6] = [
+        QueryKind::Glob,
+        QueryKind::Regex,
+        QueryKind::Filtered,
+        QueryKind::Streamed,
+        QueryKind::Status,
+        QueryKind::Cancel,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            QueryKind::Connect => "connect",
+            QueryKind::Glob => "glob",
+            QueryKind::Regex => "regex",
+            QueryKind::Filtered => "filtered",
+            QueryKind::Streamed => "streamed",
+            QueryKind::Status => "status",
+            QueryKind::Cancel => "cancel",
+        }
+    }
+}
+
+/// Latencies and failures of one query kind
+#[derive(Default)]
+struct KindStats {
+    latencies: Vec<Duration>,
+    errors: u64,
+}
+
+/// What the clients record, shared between them
+struct Recorder {
+    kinds: Mutex<BTreeMap<QueryKind, KindStats>>,
+    /// The request each client is waiting on, and since when
+    in_flight: Vec<Mutex<Option<(QueryKind, Instant)>>>,
+    hangs: AtomicU64,
+}
+
+impl Recorder {
+    fn new(clients: usize) -> Self {
+        Self {
+            kinds: Mutex::new(BTreeMap::new()),
+            in_flight: (0..clients).map(|_| Mutex::new(None)).collect(),
+            hangs: AtomicU64::new(0),
+        }
+    }
+
+    /// Time a request, keeping it visible to the hang watchdog while it runs
+    fn time<T>(&self, client: usize, kind: QueryKind, request: impl FnOnce() -> Result<T>) -> Result<T> {
+        *self.in_flight[client].lock().unwrap() = Some((kind, Instant::now()));
+        let start = Instant::now();
+        let result = request();
+        let elapsed = start.elapsed();
+        *self.in_flight[client].lock().unwrap() = None;
+
+        let mut kinds = self.kinds.lock().unwrap();
+        let stats = kinds.entry(kind).or_default();
+        match &result {
+            Ok(_) => stats.latencies.push(elapsed),
+            Err(_) => stats.errors += 1,
+        }
+        result
+    }
+}
+
+/// A small xorshift generator, so runs need no extra dependency
+struct Rng(u64);
+
+impl Rng {
+    fn seeded(client: usize) -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        Self((nanos ^ (client as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.clients == 0 {
+        bail!("--clients must be at least 1");
+    }
+    let drive = args.drive.to_ascii_uppercase();
+
+    warm_up(drive, Duration::from_secs(args.warmup_timeout))?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let recorder = Arc::new(Recorder::new(args.clients));
+
+    let churn_dir = match &args.churn_dir {
+        Some(dir) => dir.clone(),
+        None => std::env::temp_dir().join(format!("fastsearch-stress-{}", std::process::id())),
+    };
+    let churn = (args.churn_rate > 0).then(|| {
+        let stop = Arc::clone(&stop);
+        let dir = churn_dir.clone();
+        let rate = args.churn_rate;
+        thread::spawn(move || churn_files(&dir, rate, &stop))
+    });
+
+    let clients: Vec<_> = (0..args.clients)
+        .map(|client| {
+            let stop = Arc::clone(&stop);
+            let recorder = Arc::clone(&recorder);
+            thread::spawn(move || run_client(client, drive, &recorder, &stop))
+        })
+        .collect();
+
+    let hang_timeout = Duration::from_secs(args.hang_timeout);
+    let deadline = Instant::now() + Duration::from_secs(args.duration);
+    while Instant::now() < deadline {
+        thread::sleep(Duration::from_secs(1));
+        watch_for_hangs(&recorder, hang_timeout);
+    }
+    stop.store(true, Ordering::SeqCst);
+
+    // Clients finish their current request; one that never does is stuck
+    let drain_deadline = Instant::now() + hang_timeout;
+    while clients.iter().any(|client| !client.is_finished()) {
+        if Instant::now() >= drain_deadline {
+            watch_for_hangs(&recorder, Duration::ZERO);
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+        watch_for_hangs(&recorder, hang_timeout);
+    }
+    let stuck = clients.iter().filter(|client| !client.is_finished()).count();
+    if let Some(churn) = churn {
+        if let Ok(Err(e)) = churn.join() {
+            eprintln!("File churn stopped early: {:#}", e);
+        }
+    }
+    if args.churn_dir.is_none() {
+        let _ = fs::remove_dir_all(&churn_dir);
+    }
+
+    let report = report(&args, &recorder, stuck);
+    match args.format.to_lowercase().as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+        _ => print_report(&report),
+    }
+
+    let code = if stuck > 0 || recorder.hangs.load(Ordering::SeqCst) > 0 {
+        2
+    } else if report["passed"].as_bool() == Some(true) {
+        0
+    } else {
+        1
+    };
+    // Stuck clients would block a normal exit on their threads
+    std::process::exit(code);
+}
+
+/// Wait for the pipe and run one search so the drive's cache is built before timing starts
+fn warm_up(drive: char, timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut pipe = connect(deadline).context("The service's pipe did not come up")?;
+    request(&mut pipe, MSG_STATUS, &json!({}))?;
+    request(&mut pipe, MSG_SEARCH, &json!({
+        "pattern": "*.exe", "type": "glob", "max_results": 1, "filters": {"drive": drive.to_string()}
+    }))
+    .context("Warm-up search failed")?;
+    Ok(())
+}
+
+/// Open the pipe, waiting while every instance is busy
+fn connect(deadline: Instant) -> Result<File> {
+    loop {
+        match OpenOptions::new().read(true).write(true).open(PIPE_NAME) {
+            Ok(pipe) => return Ok(pipe),
+            Err(e) if Instant::now() < deadline
+                && (e.raw_os_error() == Some(ERROR_PIPE_BUSY) || e.kind() == io::ErrorKind::NotFound) =>
+            {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => return Err(e).context(format!("Failed to open {}", PIPE_NAME)),
+        }
+    }
+}
+
+/// Send one request and read its final frame, skipping partial ones
+fn request(pipe: &mut File, message_type: u32, payload: &Value) -> Result<Value> {
+    // Header and payload in one write: the server reads a request as one message
+    pipe.write_all(&encode_frame(message_type, &serde_json::to_vec(payload)?))?;
+    pipe.flush()?;
+
+    loop {
+        let (status, body) = read_frame(pipe)?;
+        match status {
+            STATUS_PARTIAL => continue,
+            STATUS_OK => return Ok(serde_json::from_slice(&body)?),
+            _ => bail!("{}", String::from_utf8_lossy(&body)),
+        }
+    }
+}
+
+fn read_frame(pipe: &mut File) -> Result<(u32, Vec<u8>)> {
+    let mut header = [0u8; 8];
+    pipe.read_exact(&mut header)?;
+    let status = u32::from_le_bytes(header[0..4].try_into()?);
+    let length = u32::from_le_bytes(header[4..8].try_into()?) as usize;
+    let mut body = vec![0u8; length];
+    pipe.read_exact(&mut body)?;
+    Ok((status, body))
+}
+
+/// Connect, send a few requests, disconnect, repeat until told to stop
+fn run_client(client: usize, drive: char, recorder: &Recorder, stop: &AtomicBool) {
+    let mut rng = Rng::seeded(client);
+    let mut sequence = 0u64;
+    let drive = drive.to_string();
+
+    while !stop.load(Ordering::SeqCst) {
+        let connected = recorder.time(client, QueryKind::Connect, || {
+            connect(Instant::now() + Duration::from_secs(10))
+        });
+        let Ok(mut pipe) = connected else {
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        };
+
+        for _ in 0..=rng.below(8) {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let kind = QueryKind::MIXED[rng.below(QueryKind::MIXED.len())];
+            sequence += 1;
+            let result = recorder.time(client, kind, || match kind {
+                QueryKind::Glob => {
+                    let pattern = ["*.dll", "*.txt", "*config*", "stress-*", "*.log"][rng.below(5)];
+                    request(&mut pipe, MSG_SEARCH, &json!({
+                        "pattern": pattern, "type": "glob", "max_results": 100, "filters": {"drive": drive}
+                    }))
+                }
+                QueryKind::Regex => request(&mut pipe, MSG_SEARCH, &json!({
+                    "pattern": r"^stress-\d+\.(tmp|dat)$", "type": "regex", "max_results": 100,
+                    "filters": {"drive": drive}
+                })),
+                QueryKind::Filtered => request(&mut pipe, MSG_SEARCH, &json!({
+                    "pattern": "*", "type": "glob", "max_results": 50,
+                    "filters": {"drive": drive, "min_size": 1_048_576, "modified_after": "-1d"},
+                    "extensions": ["log", "tmp", "dat"]
+                })),
+                QueryKind::Streamed => request(&mut pipe, MSG_SEARCH, &json!({
+                    "pattern": "*.*", "type": "glob", "max_results": 2000, "stream": true,
+                    "filters": {"drive": drive}
+                })),
+                QueryKind::Status => request(&mut pipe, MSG_STATUS, &json!({})),
+                QueryKind::Cancel => cancel_search(&mut pipe, client, sequence, &drive),
+                QueryKind::Connect => unreachable!("connections are timed on their own"),
+            });
+            // A failed exchange may leave unread frames behind; start on a fresh connection
+            if result.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Start a broad streamed search, cancel it from a second connection, then drain the first
+fn cancel_search(pipe: &mut File, client: usize, sequence: u64, drive: &str) -> Result<Value> {
+    let request_id = format!("stress-{}-{}", client, sequence);
+    let search = serde_json::to_vec(&json!({
+        "pattern": "*", "type": "glob", "max_results": 100_000, "stream": true,
+        "request_id": request_id, "filters": {"drive": drive}
+    }))?;
+    pipe.write_all(&encode_frame(MSG_SEARCH, &search))?;
+    pipe.flush()?;
+
+    let mut canceller = connect(Instant::now() + Duration::from_secs(10))?;
+    let cancelled = request(&mut canceller, MSG_CANCEL, &json!({"request_id": request_id}))?;
+    drop(canceller);
+
+    // A cancelled search ends with an error frame; one that finished first ends normally
+    loop {
+        let (status, _) = read_frame(pipe)?;
+        if status != STATUS_PARTIAL {
+            return Ok(cancelled);
+        }
+    }
+}
+
+/// Create, rewrite, rename and delete files so the USN journal keeps reporting changes
+fn churn_files(dir: &Path, rate: u32, stop: &AtomicBool) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let interval = Duration::from_secs(1) / rate;
+    let mut rng = Rng::seeded(usize::MAX);
+    let mut live: Vec<PathBuf> = Vec::new();
+    let mut next_id = 0u64;
+
+    while !stop.load(Ordering::SeqCst) {
+        let started = Instant::now();
+        match rng.below(4) {
+            _ if live.len() < 64 => {
+                next_id += 1;
+                let path = dir.join(format!("stress-{}.tmp", next_id));
+                fs::write(&path, vec![0u8; rng.below(64 * 1024)])?;
+                live.push(path);
+            }
+            0 => {
+                let path = live.swap_remove(rng.below(live.len()));
+                fs::remove_file(&path)?;
+            }
+            1 => {
+                let index = rng.below(live.len());
+                next_id += 1;
+                let renamed = dir.join(format!("stress-{}.dat", next_id));
+                fs::rename(&live[index], &renamed)?;
+                live[index] = renamed;
+            }
+            _ => {
+                let path = &live[rng.below(live.len())];
+                fs::write(path, vec![1u8; rng.below(2 * 1024 * 1024)])?;
+            }
+        }
+        if let Some(rest) = interval.checked_sub(started.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+    Ok(())
+}
+
+/// Report requests that have been waiting longer than `timeout`
+fn watch_for_hangs(recorder: &Recorder, timeout: Duration) {
+    for (client, slot) in recorder.in_flight.iter().enumerate() {
+        let mut slot = slot.lock().unwrap();
+        if let Some((kind, since)) = *slot {
+            if since.elapsed() > timeout {
+                eprintln!("Client {} has waited {:.1?} on a {} request", client, since.elapsed(), kind.name());
+                recorder.hangs.fetch_add(1, Ordering::SeqCst);
+                // Count each hang once
+                *slot = None;
+            }
+        }
+    }
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+fn report(args: &Args, recorder: &Recorder, stuck: usize) -> Value {
+    let p50_budget = Duration::from_millis(args.p50_ms);
+    let p99_budget = Duration::from_millis(args.p99_ms);
+    let kinds = recorder.kinds.lock().unwrap();
+
+    let mut total = 0u64;
+    let mut errors = 0u64;
+    let mut slo_met = true;
+    let mut rows = Vec::new();
+    for (kind, stats) in kinds.iter() {
+        let mut sorted = stats.latencies.clone();
+        sorted.sort();
+        let (p50, p95, p99) = (percentile(&sorted, 0.50), percentile(&sorted, 0.95), percentile(&sorted, 0.99));
+        let max = sorted.last().copied().unwrap_or_default();
+        // Waits for a free pipe instance are reported, not budgeted: the server caps its instances
+        let met = *kind == QueryKind::Connect || (p50 <= p50_budget && p99 <= p99_budget);
+        slo_met &= met;
+        total += sorted.len() as u64 + stats.errors;
+        errors += stats.errors;
+        rows.push(json!({
+            "kind": kind.name(),
+            "requests": sorted.len() as u64 + stats.errors,
+            "errors": stats.errors,
+            "p50_ms": p50.as_secs_f64() * 1000.0,
+            "p95_ms": p95.as_secs_f64() * 1000.0,
+            "p99_ms": p99.as_secs_f64() * 1000.0,
+            "max_ms": max.as_secs_f64() * 1000.0,
+            "slo_met": met
+        }));
+    }
+
+    let error_rate = if total == 0 { 0.0 } else { errors as f64 / total as f64 };
+    let hangs = recorder.hangs.load(Ordering::SeqCst);
+    json!({
+        "clients": args.clients,
+        "duration_secs": args.duration,
+        "churn_rate": args.churn_rate,
+        "slo": {"p50_ms": args.p50_ms, "p99_ms": args.p99_ms, "max_error_rate": args.max_error_rate},
+        "requests": total,
+        "errors": errors,
+        "error_rate": error_rate,
+        "hangs": hangs,
+        "stuck_clients": stuck,
+        "kinds": rows,
+        "passed": total > 0 && slo_met && error_rate <= args.max_error_rate && hangs == 0 && stuck == 0
+    })
+}
+
+fn print_report(report: &Value) {
+    println!("FastSearch pipe stress test");
+    println!("===========================");
+    println!(
+        "{} clients for {}s, {} file changes/s; {} requests, {} errors ({:.2}%)",
+        report["clients"], report["duration_secs"], report["churn_rate"],
+        report["requests"], report["errors"], report["error_rate"].as_f64().unwrap_or(0.0) * 100.0
+    );
+    println!();
+    println!("{:<10} {:>9} {:>7} {:>9} {:>9} {:>9} {:>9}  SLO", "kind", "requests", "errors", "p50 ms", "p95 ms", "p99 ms", "max ms");
+    for row in report["kinds"].as_array().into_iter().flatten() {
+        println!(
+            "{:<10} {:>9} {:>7} {:>9.1} {:>9.1} {:>9.1} {:>9.1}  {}",
+            row["kind"].as_str().unwrap_or_default(),
+            row["requests"], row["errors"],
+            row["p50_ms"].as_f64().unwrap_or(0.0), row["p95_ms"].as_f64().unwrap_or(0.0),
+            row["p99_ms"].as_f64().unwrap_or(0.0), row["max_ms"].as_f64().unwrap_or(0.0),
+            if row["slo_met"].as_bool() == Some(true) { "ok" } else { "MISSED" }
+        );
+    }
+    println!();
+    if report["hangs"].as_u64().unwrap_or(0) > 0 || report["stuck_clients"].as_u64().unwrap_or(0) > 0 {
+        println!("❌ {} hung requests, {} clients stuck at shutdown", report["hangs"], report["stuck_clients"]);
+    } else if report["passed"].as_bool() == Some(true) {
+        println!("✅ All latency budgets met");
+    } else {
+        println!("❌ Latency budget or error rate missed");
+    }
+}
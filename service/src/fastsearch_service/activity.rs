@@ -0,0 +1,238 @@
+//! When files changed, for `activity_heatmap`
+//!
+//! The MFT cache keeps the created, modified and accessed times of every
+//! file, so when a folder was worked on can be read off without touching the
+//! disk. Files are counted per day, week or month, and on a weekday by hour
+//! grid that shows working patterns across the whole period.
+
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::Serialize;
+
+use super::mft_cache::FileEntry;
+use super::reports::scoped_remainder;
+
+/// Which timestamp of a file is counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ActivityField {
+    #[default]
+    Modified,
+    Created,
+    Accessed,
+}
+
+impl ActivityField {
+    /// Parse the `field` tool argument
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "modified" => Some(ActivityField::Modified),
+            "created" => Some(ActivityField::Created),
+            "accessed" => Some(ActivityField::Accessed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityField::Modified => "modified",
+            ActivityField::Created => "created",
+            ActivityField::Accessed => "accessed",
+        }
+    }
+
+    /// The timestamp of a file
+    pub fn of(&self, file: &FileEntry) -> SystemTime {
+        match self {
+            ActivityField::Modified => file.modified,
+            ActivityField::Created => file.created,
+            ActivityField::Accessed => file.accessed,
+        }
+    }
+}
+
+/// Length of the periods files are counted in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ActivityPeriod {
+    #[default]
+    Day,
+    /// Weeks starting on Monday
+    Week,
+    Month,
+}
+
+impl ActivityPeriod {
+    /// Parse the `period` tool argument
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "day" => Some(ActivityPeriod::Day),
+            "week" => Some(ActivityPeriod::Week),
+            "month" => Some(ActivityPeriod::Month),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityPeriod::Day => "day",
+            ActivityPeriod::Week => "week",
+            ActivityPeriod::Month => "month",
+        }
+    }
+
+    /// First day of the period a date falls in
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            ActivityPeriod::Day => date,
+            ActivityPeriod::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            ActivityPeriod::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+/// Files whose timestamp falls in one period
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActivityBucket {
+    /// First day of the period
+    pub start: NaiveDate,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Timestamps of the files below a folder
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Activity {
+    pub files: u64,
+    pub first: Option<NaiveDateTime>,
+    pub last: Option<NaiveDateTime>,
+    /// Periods with at least one file, oldest first
+    pub periods: Vec<ActivityBucket>,
+    /// Files per weekday (Monday first) and hour
+    pub heatmap: [[u64; 24]; 7],
+}
+
+impl Activity {
+    /// Period with the most files (the latest of equals)
+    pub fn busiest_period(&self) -> Option<&ActivityBucket> {
+        self.periods.iter().max_by_key(|bucket| bucket.files)
+    }
+
+    /// Weekday (0 = Monday) and hour with the most files
+    pub fn busiest_hour(&self) -> Option<(usize, usize, u64)> {
+        (0..7)
+            .flat_map(|day| (0..24).map(move |hour| (day, hour)))
+            .map(|(day, hour)| (day, hour, self.heatmap[day][hour]))
+            .filter(|&(_, _, files)| files > 0)
+            .max_by_key(|&(day, hour, files)| (files, std::cmp::Reverse((day, hour))))
+    }
+}
+
+/// Count the files below `root` (relative to the drive root; empty for the whole drive) that `accept` lets through
+///
+/// `local` turns a timestamp into the wall-clock time the heatmap and periods use.
+pub fn activity<'a>(
+    files: impl Iterator<Item = &'a FileEntry>,
+    root: &str,
+    field: ActivityField,
+    period: ActivityPeriod,
+    local: impl Fn(SystemTime) -> NaiveDateTime,
+    accept: impl Fn(&FileEntry) -> bool,
+) -> Activity {
+    let mut activity = Activity::default();
+    let mut periods: BTreeMap<NaiveDate, ActivityBucket> = BTreeMap::new();
+
+    for file in files.filter(|file| !file.is_directory) {
+        if scoped_remainder(&file.path, root).is_none() || !accept(file) {
+            continue;
+        }
+        let time = local(field.of(file));
+        activity.files += 1;
+        activity.first = Some(activity.first.map_or(time, |first| first.min(time)));
+        activity.last = Some(activity.last.map_or(time, |last| last.max(time)));
+        activity.heatmap[time.weekday().num_days_from_monday() as usize][time.hour() as usize] += 1;
+
+        let start = period.start(time.date());
+        let bucket = periods.entry(start).or_insert(ActivityBucket { start, files: 0, bytes: 0 });
+        bucket.files += 1;
+        bucket.bytes += file.size;
+    }
+
+    activity.periods = periods.into_values().collect();
+    activity
+}
+
+/// The heatmap as text: a row per weekday, a shaded cell per hour
+pub fn render_heatmap(heatmap: &[[u64; 24]; 7]) -> String {
+    const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+    let max = heatmap.iter().flatten().copied().max().unwrap_or(0);
+
+    let mut text = String::from("     0     6     12    18\n");
+    for (day, hours) in DAYS.iter().zip(heatmap) {
+        let cells: String = hours.iter()
+            .map(|&files| match files {
+                0 => SHADES[0],
+                // Any activity shows, however little next to the busiest hour
+                _ => SHADES[1 + ((files - 1) * 4 / max.max(1)).min(3) as usize],
+            })
+            .collect();
+        text.push_str(&format!("{}  {}\n", day, cells));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration as StdDuration, UNIX_EPOCH};
+
+    fn file(id: u64, path: &str, modified: u64) -> FileEntry {
+        FileEntry { size: 10, modified: UNIX_EPOCH + StdDuration::from_secs(modified), ..FileEntry::test(id, path) }
+    }
+
+    fn utc(time: SystemTime) -> NaiveDateTime {
+        chrono::DateTime::<chrono::Utc>::from(time).naive_utc()
+    }
+
+    #[test]
+    fn test_activity_by_week() {
+        // 2024-01-01 was a Monday
+        let monday = 1_704_067_200;
+        let files = [
+            file(1, "proj\\a.rs", monday + 9 * 3600),
+            file(2, "proj\\b.rs", monday + 9 * 3600 + 60),
+            file(3, "proj\\src\\c.rs", monday + 3 * 86400 + 14 * 3600),
+            file(4, "proj\\old.rs", monday + 8 * 86400),
+            file(5, "other\\d.rs", monday),
+        ];
+
+        let found = activity(files.iter(), "proj", ActivityField::Modified, ActivityPeriod::Week, utc, |_| true);
+        assert_eq!(found.files, 4);
+        let weeks: Vec<(String, u64)> = found.periods.iter().map(|b| (b.start.to_string(), b.files)).collect();
+        assert_eq!(weeks, vec![("2024-01-01".to_string(), 3), ("2024-01-08".to_string(), 1)]);
+        assert_eq!(found.heatmap[0][9], 2);
+        assert_eq!(found.heatmap[3][14], 1);
+        assert_eq!(found.busiest_hour(), Some((0, 9, 2)));
+        assert_eq!(found.first.unwrap().to_string(), "2024-01-01 09:00:00");
+        assert_eq!(found.last.unwrap().to_string(), "2024-01-09 00:00:00");
+
+        let months = activity(files.iter(), "", ActivityField::Modified, ActivityPeriod::Month, utc, |_| true);
+        assert_eq!(months.periods.len(), 1);
+        assert_eq!(months.busiest_period().unwrap().files, 5);
+    }
+
+    #[test]
+    fn test_render_heatmap() {
+        let mut heatmap = [[0; 24]; 7];
+        heatmap[0][0] = 8;
+        heatmap[6][23] = 1;
+        let text = render_heatmap(&heatmap);
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), 8);
+        assert!(rows[1].starts_with("Mon  █·"));
+        assert!(rows[7].ends_with("·░"));
+        assert_eq!(ActivityPeriod::parse("Month"), Some(ActivityPeriod::Month));
+        assert_eq!(ActivityField::parse("changed"), None);
+    }
+}
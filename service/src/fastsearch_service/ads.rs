@@ -0,0 +1,101 @@
+//! Alternate data streams (named `$DATA` attributes)
+//!
+//! Besides its main content, an NTFS file can carry named data streams
+//! (`setup.exe:Zone.Identifier`). Browsers use them to mark downloads, and
+//! they are a classic place to hide data, so `list_ads` and `fast_search` with
+//! `include_streams` report them. The MFT cache only keeps the main stream;
+//! named streams are read from the file records on demand, for exactly the
+//! records asked about.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// A named data stream of a file or directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataStream {
+    /// Stream name, without the leading colon
+    pub name: String,
+    /// Size of the stream's data in bytes
+    pub size: u64,
+}
+
+/// Named data streams of MFT records, keyed by record number
+///
+/// Records without named streams (and records that can't be read) are left
+/// out. Needs the same raw volume access as building the MFT cache.
+#[cfg(windows)]
+pub fn read_streams(drive: char, records: &[u64]) -> Result<HashMap<u64, Vec<DataStream>>> {
+    use std::fs::File;
+
+    use anyhow::Context;
+    use log::debug;
+    use ntfs::{Ntfs, NtfsAttributeType};
+
+    let volume_path = format!(r"\\.\{}:", drive);
+    let mut volume = File::open(&volume_path)
+        .with_context(|| format!("Failed to open volume {} (admin rights required)", volume_path))?;
+    let ntfs = Ntfs::new(&mut volume).context("Failed to parse NTFS")?;
+
+    let mut streams = HashMap::new();
+    for &record in records {
+        let file = match ntfs.file(&mut volume, record) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Skipping MFT record {} on {}: {}", record, drive, e);
+                continue;
+            }
+        };
+
+        let mut named = Vec::new();
+        let mut attributes = file.attributes();
+        while let Some(item) = attributes.next(&mut volume) {
+            let Ok(item) = item else { continue };
+            let Ok(attribute) = item.to_attribute() else { continue };
+            if !matches!(attribute.ty(), Ok(NtfsAttributeType::Data)) {
+                continue;
+            }
+            // The unnamed $DATA attribute is the file's main content
+            match attribute.name() {
+                Ok(name) if !name.is_empty() => named.push(DataStream {
+                    name: name.to_string_lossy(),
+                    size: attribute.value_length(),
+                }),
+                _ => {}
+            }
+        }
+        if !named.is_empty() {
+            streams.insert(record, named);
+        }
+    }
+    Ok(streams)
+}
+
+/// Named data streams only exist on NTFS volumes read on Windows
+#[cfg(not(windows))]
+pub fn read_streams(_drive: char, _records: &[u64]) -> Result<HashMap<u64, Vec<DataStream>>> {
+    Err(anyhow::anyhow!("Alternate data streams can only be read from NTFS volumes on Windows"))
+}
+
+/// `path:stream`, the name Windows tools use for a stream
+pub fn stream_path(path: &str, stream: &DataStream) -> String {
+    format!("{}:{}", path, stream.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_path() {
+        let stream = DataStream { name: "Zone.Identifier".to_string(), size: 26 };
+        assert_eq!(stream_path("Users\\me\\setup.exe", &stream), "Users\\me\\setup.exe:Zone.Identifier");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_streams_need_windows() {
+        assert!(read_streams('C', &[5]).is_err());
+    }
+}
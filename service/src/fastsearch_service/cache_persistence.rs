@@ -1,29 +1,278 @@
 //! MFT cache persistence implementation for saving/loading cache to/from disk
+//!
+//! How a cache is laid out on disk is up to a [`CacheStore`], chosen with
+//! [`PersistenceBackend`] in the cache config (or `FASTSEARCH_CACHE_BACKEND`),
+//! so durability and load speed can be traded off without touching `MftCache`.
+//! Every backend signs what it writes with the cache key and refuses to load
+//! anything that fails the check; the write-ahead log is the same for all.
+//! Files are named after the volume ([`VolumeId`]), not the drive letter, so
+//! a drive that comes back under another letter loads its cache again.
 
 use std::fs::{self, File};
-use std::io::{self, BufReader, BufWriter};
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result};
-use bincode::{deserialize_from, serialize_into};
-use log::{debug, error, info};
+use anyhow::{anyhow, bail, Context, Result};
+use bincode::{serialize_into, Options};
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use crate::fastsearch_service::cache_signing::{self, CacheKey, SigningReader, SigningWriter};
 use crate::fastsearch_service::mft_cache::{FileEntry, MftCache};
+use crate::fastsearch_service::volumes::VolumeId;
+
+/// Format version of the cache files; version 1 caches were unsigned
+const CACHE_VERSION: u32 = 2;
+
+/// Most bytes one record (the metadata, a file ID or an entry) may take
+///
+/// Lengths in a cache file aren't trusted until its signature checks out,
+/// which is only after all of it has been read.
+const MAX_RECORD_LEN: u64 = 1 << 20;
+
+/// Most entries room is reserved for up front, whatever count the metadata claims
+const MAX_PREALLOCATED_ENTRIES: usize = 1 << 20;
 
 /// Cache metadata for versioning and validation
 #[derive(Debug, Serialize, Deserialize)]
 struct CacheMetadata {
     version: u32,
     created: u64,
+    /// [`VolumeId`] of the volume the cache was read from
     volume_serial: String,
     file_count: usize,
     total_size: u64,
+    /// HMAC over this metadata (with an empty signature) followed by the cache data
+    signature: Vec<u8>,
+}
+
+impl CacheMetadata {
+    /// Start the signature covering this metadata and the data after it
+    fn signer(&self, key: &CacheKey) -> Result<cache_signing::Signer> {
+        let unsigned = CacheMetadata {
+            version: self.version,
+            created: self.created,
+            volume_serial: self.volume_serial.clone(),
+            file_count: self.file_count,
+            total_size: self.total_size,
+            signature: Vec::new(),
+        };
+        let mut signer = key.signer();
+        signer.update(&bincode::serialize(&unsigned).context("Failed to serialize metadata")?);
+        Ok(signer)
+    }
+}
+
+/// Read one record written with `serialize_into`, refusing any longer than `MAX_RECORD_LEN`
+fn deserialize_from<T: DeserializeOwned, R: Read>(reader: R) -> bincode::Result<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_RECORD_LEN)
+        .deserialize_from(reader)
+}
+
+/// Environment variable naming the persistence backend ("snapshots" or "single_file")
+pub const CACHE_BACKEND_ENV: &str = "FASTSEARCH_CACHE_BACKEND";
+
+/// Where and how a volume's cache is written to disk
+pub trait CacheStore: Send + Sync {
+    /// Write the cache signed with `key`, replacing what was saved before only once it is complete
+    ///
+    /// Returns the signature, which the change log written after it is chained to.
+    fn save(&self, cache: &MftCache, cache_dir: &Path, key: &CacheKey) -> Result<Vec<u8>>;
+    /// Read the last saved cache of a volume as a cache for `drive_letter`, with its signature; `None` if there is none
+    fn load(&self, cache_dir: &Path, drive_letter: char, volume: &VolumeId, key: &CacheKey) -> Result<Option<(MftCache, Vec<u8>)>>;
+    /// Whether a saved cache exists for a volume, so it can be loaded instead of read from the MFT
+    fn has_saved(&self, cache_dir: &Path, volume: &VolumeId) -> bool;
+}
+
+/// Persistence backends to choose from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PersistenceBackend {
+    /// Timestamped data and metadata file pairs, the last few kept (`max_cache_versions`)
+    #[default]
+    Snapshots,
+    /// One signed file per volume, replaced in place: less to clean up and a
+    /// single rename per save, but no older version to fall back to
+    SingleFile,
+}
+
+impl PersistenceBackend {
+    /// Name used in config and `FASTSEARCH_CACHE_BACKEND`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Snapshots => "snapshots",
+            Self::SingleFile => "single_file",
+        }
+    }
+    
+    /// Backend named in `FASTSEARCH_CACHE_BACKEND`, the default if unset or unknown
+    pub fn from_env() -> Self {
+        match std::env::var(CACHE_BACKEND_ENV) {
+            Ok(name) => name.parse().unwrap_or_else(|e| {
+                warn!("{}; using the {} backend", e, Self::default().name());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+    
+    /// Store implementing the backend
+    pub fn store(self) -> &'static dyn CacheStore {
+        match self {
+            Self::Snapshots => &Snapshots,
+            Self::SingleFile => &SingleFile,
+        }
+    }
+}
+
+impl FromStr for PersistenceBackend {
+    type Err = anyhow::Error;
+    
+    fn from_str(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "snapshots" | "snapshot" => Ok(Self::Snapshots),
+            "single_file" => Ok(Self::SingleFile),
+            other => Err(anyhow!("Unknown cache backend '{}' (expected snapshots or single_file)", other)),
+        }
+    }
+}
+
+/// Timestamped `mft_cache_<volume>_<time>.bin`/`.meta` pairs
+struct Snapshots;
+
+impl CacheStore for Snapshots {
+    fn save(&self, cache: &MftCache, cache_dir: &Path, key: &CacheKey) -> Result<Vec<u8>> {
+        save_cache(cache, cache_dir, key)
+    }
+    
+    fn load(&self, cache_dir: &Path, drive_letter: char, volume: &VolumeId, key: &CacheKey) -> Result<Option<(MftCache, Vec<u8>)>> {
+        load_cache(cache_dir, drive_letter, volume, key)
+    }
+    
+    fn has_saved(&self, cache_dir: &Path, volume: &VolumeId) -> bool {
+        has_saved_cache(cache_dir, volume)
+    }
+}
+
+/// One `mft_cache_<volume>.cache` file per volume
+///
+/// Laid out as `[metadata (unsigned)][file_count entries][signature]`, so the
+/// signature can be appended once the entries are written and still cover the
+/// metadata before them.
+struct SingleFile;
+
+impl SingleFile {
+    fn path_for(cache_dir: &Path, volume: &VolumeId) -> PathBuf {
+        cache_dir.join(format!("mft_cache_{}.cache", volume))
+    }
+}
+
+impl CacheStore for SingleFile {
+    fn save(&self, cache: &MftCache, cache_dir: &Path, key: &CacheKey) -> Result<Vec<u8>> {
+        let start_time = std::time::Instant::now();
+        fs::create_dir_all(cache_dir).context("Failed to create cache directory")?;
+        
+        let path = Self::path_for(cache_dir, cache.volume());
+        let temp = path.with_extension("cache.tmp");
+        
+        let files = cache.get_files();
+        let metadata = CacheMetadata {
+            version: CACHE_VERSION,
+            created: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            volume_serial: cache.volume().to_string(),
+            file_count: files.len(),
+            total_size: files.values().map(|f| f.size).sum(),
+            signature: Vec::new(),
+        };
+        let signature = {
+            let mut writer = BufWriter::new(File::create(&temp).context("Failed to create cache file")?);
+            serialize_into(&mut writer, &metadata).context("Failed to serialize metadata")?;
+            let mut signer = metadata.signer(key)?;
+            let mut signed = SigningWriter::new(&mut writer, &mut signer);
+            for (id, entry) in files.iter() {
+                serialize_into(&mut signed, id).context("Failed to serialize file ID")?;
+                serialize_into(&mut signed, entry).context("Failed to serialize file entry")?;
+            }
+            drop(signed);
+            let signature = signer.finish();
+            writer.write_all(&signature).context("Failed to write cache signature")?;
+            writer.into_inner()
+                .map_err(|e| e.into_error())
+                .and_then(|file| file.sync_all())
+                .context("Failed to flush cache file")?;
+            signature
+        };
+        drop(files);
+        fs::rename(&temp, &path).context("Failed to replace cache file")?;
+        
+        info!(
+            "Saved MFT cache with {} files ({} MB) to {} in {:.2?}",
+            metadata.file_count,
+            metadata.total_size / 1024 / 1024,
+            path.display(),
+            start_time.elapsed()
+        );
+        Ok(signature)
+    }
+    
+    fn load(&self, cache_dir: &Path, drive_letter: char, volume: &VolumeId, key: &CacheKey) -> Result<Option<(MftCache, Vec<u8>)>> {
+        let path = Self::path_for(cache_dir, volume);
+        if !path.exists() {
+            debug!("No cache file found for drive {} ({})", drive_letter, volume);
+            return Ok(None);
+        }
+        let start_time = std::time::Instant::now();
+        
+        let mut reader = BufReader::new(File::open(&path).context("Failed to open cache file")?);
+        let metadata: CacheMetadata = deserialize_from(&mut reader).context("Failed to deserialize metadata")?;
+        if metadata.version != CACHE_VERSION {
+            bail!("Cache in {} is from an older version", path.display());
+        }
+        let mut signer = metadata.signer(key)?;
+        
+        let mut entries = Vec::with_capacity(metadata.file_count.min(MAX_PREALLOCATED_ENTRIES));
+        {
+            let mut signed = SigningReader::new(&mut reader, &mut signer);
+            for _ in 0..metadata.file_count {
+                let _id: u64 = deserialize_from(&mut signed).context("Failed to deserialize file ID")?;
+                let entry: FileEntry = deserialize_from(&mut signed).context("Failed to deserialize file entry")?;
+                entries.push(entry);
+            }
+        }
+        let mut signature = Vec::new();
+        reader.read_to_end(&mut signature).context("Failed to read cache signature")?;
+        
+        // Nothing from the file is used unless the signature checks out
+        if !signer.verify(&signature) {
+            cache_signing::report_tampered(drive_letter, &path);
+            bail!("Cache in {} failed its signature check", path.display());
+        }
+        let cache = MftCache::detached(drive_letter);
+        cache.replace_entries(entries);
+        
+        info!(
+            "Loaded MFT cache with {} files ({} MB) from {} in {:.2?}",
+            metadata.file_count,
+            metadata.total_size / 1024 / 1024,
+            path.display(),
+            start_time.elapsed()
+        );
+        Ok(Some((cache, signature)))
+    }
+    
+    fn has_saved(&self, cache_dir: &Path, volume: &VolumeId) -> bool {
+        Self::path_for(cache_dir, volume).exists()
+    }
 }
 
-/// Save the MFT cache to disk
-pub fn save_cache(cache: &MftCache, cache_dir: &Path) -> Result<()> {
+/// Save the MFT cache to disk, signed with `key`, returning the signature
+pub fn save_cache(cache: &MftCache, cache_dir: &Path, key: &CacheKey) -> Result<Vec<u8>> {
     let start_time = std::time::Instant::now();
     
     // Ensure cache directory exists
@@ -35,42 +284,33 @@ pub fn save_cache(cache: &MftCache, cache_dir: &Path) -> Result<()> {
         .unwrap()
         .as_secs();
     
-    let cache_file = cache_dir.join(format!("mft_cache_{}.bin", timestamp));
-    let meta_file = cache_dir.join(format!("mft_cache_{}.meta", timestamp));
+    let volume = cache.volume();
+    let cache_file = cache_dir.join(format!("mft_cache_{}_{}.bin", volume, timestamp));
+    let meta_file = cache_dir.join(format!("mft_cache_{}_{}.meta", volume, timestamp));
     
     // Create temporary files for atomic writes
-    let temp_cache = cache_dir.join(format!(".mft_cache_{}.tmp", timestamp));
-    let temp_meta = cache_dir.join(format!(".mft_cache_{}.meta.tmp", timestamp));
+    let temp_cache = cache_dir.join(format!(".mft_cache_{}_{}.tmp", volume, timestamp));
+    let temp_meta = cache_dir.join(format!(".mft_cache_{}_{}.meta.tmp", volume, timestamp));
     
     // Serialize and save the cache data
-    {
+    let files = cache.get_files();
+    let total_size = files.values().map(|f| f.size).sum();
+    let signature = {
         let file = File::create(&temp_cache).context("Failed to create cache file")?;
-        let mut writer = BufWriter::new(file);
-        
-        // Get a read lock on the cache data
-        let files = cache.files.read();
-        let extension_index = cache.extension_index.read();
-        let name_index = cache.name_index.read();
-        let path_index = cache.path_index.read();
-        
-        // Calculate total size
-        let total_size = files.values().map(|f| f.size).sum();
         
         // Save metadata
-        let metadata = CacheMetadata {
-            version: 1,
+        let mut metadata = CacheMetadata {
+            version: CACHE_VERSION,
             created: timestamp,
-            volume_serial: cache.drive_letter.to_string(),
+            volume_serial: volume.to_string(),
             file_count: files.len(),
             total_size,
+            signature: Vec::new(),
         };
         
-        // Write metadata
-        let meta_file = File::create(&temp_meta).context("Failed to create metadata file")?;
-        let meta_writer = BufWriter::new(meta_file);
-        serialize_into(meta_writer, &metadata).context("Failed to serialize metadata")?;
-        
-        // Write cache data
+        // Write cache data, signing it as it goes
+        let mut signer = metadata.signer(key)?;
+        let mut writer = SigningWriter::new(BufWriter::new(file), &mut signer);
         for (id, entry) in files.iter() {
             // Write file ID
             bincode::serialize_into(&mut writer, id).context("Failed to serialize file ID")?;
@@ -80,14 +320,22 @@ pub fn save_cache(cache: &MftCache, cache_dir: &Path) -> Result<()> {
         
         // Flush to ensure all data is written
         writer.flush().context("Failed to flush cache data")?;
-    }
+        drop(writer);
+        
+        // Write metadata, with the signature now that all the data is in it
+        metadata.signature = signer.finish();
+        let meta_file = File::create(&temp_meta).context("Failed to create metadata file")?;
+        let meta_writer = BufWriter::new(meta_file);
+        serialize_into(meta_writer, &metadata).context("Failed to serialize metadata")?;
+        metadata.signature
+    };
     
     // Atomically rename temp files to final names
     fs::rename(&temp_cache, &cache_file).context("Failed to rename cache file")?;
     fs::rename(&temp_meta, &meta_file).context("Failed to rename metadata file")?;
     
-    // Clean up old cache files (keep last 3)
-    cleanup_old_caches(cache_dir, 3)?;
+    // Clean up old cache files of this volume (keep last 3)
+    cleanup_old_caches(cache_dir, volume, 3)?;
     
     info!(
         "Saved MFT cache with {} files ({} MB) in {:.2?}",
@@ -96,16 +344,18 @@ pub fn save_cache(cache: &MftCache, cache_dir: &Path) -> Result<()> {
         start_time.elapsed()
     );
     
-    Ok(())
+    Ok(signature)
 }
 
-/// Load the MFT cache from disk
-pub fn load_cache(cache_dir: &Path, drive_letter: char) -> Result<Option<MftCache>> {
-    // Find the most recent cache file for this drive
-    let cache_files = find_cache_files(cache_dir, drive_letter)?;
+/// Load the MFT cache of a volume from disk, as the cache of `drive_letter`, if it was signed with `key`
+///
+/// Returns the cache with its signature.
+pub fn load_cache(cache_dir: &Path, drive_letter: char, volume: &VolumeId, key: &CacheKey) -> Result<Option<(MftCache, Vec<u8>)>> {
+    // Find the most recent cache file for this volume
+    let cache_files = find_cache_files(cache_dir, volume)?;
     
     if cache_files.is_empty() {
-        debug!("No cache files found for drive {}", drive_letter);
+        debug!("No cache files found for drive {} ({})", drive_letter, volume);
         return Ok(None);
     }
     
@@ -115,48 +365,37 @@ pub fn load_cache(cache_dir: &Path, drive_letter: char) -> Result<Option<MftCach
     // Load metadata
     let meta_reader = BufReader::new(File::open(meta_file).context("Failed to open metadata file")?);
     let metadata: CacheMetadata = deserialize_from(meta_reader).context("Failed to deserialize metadata")?;
-    
-    // Create a new cache with the same configuration
-    let cache = MftCache::with_config(
-        drive_letter,
-        MftCacheConfig::default(), // Will be updated with saved config
-    )?;
+    if metadata.version != CACHE_VERSION || metadata.signature.is_empty() {
+        bail!("Cache in {} is unsigned or from an older version", cache_file.display());
+    }
+    if metadata.volume_serial != volume.as_str() {
+        bail!("Cache in {} belongs to volume {}, not {}", cache_file.display(), metadata.volume_serial, volume);
+    }
+    let mut signer = metadata.signer(key)?;
     
     // Load cache data
+    let mut entries = Vec::with_capacity(metadata.file_count.min(MAX_PREALLOCATED_ENTRIES));
     {
-        let mut files = cache.files.write();
-        let mut extension_index = cache.extension_index.write();
-        let mut name_index = cache.name_index.write();
-        let mut path_index = cache.path_index.write();
-        
         let reader = BufReader::new(File::open(cache_file).context("Failed to open cache file")?);
-        let mut reader = io::BufReader::new(reader);
-        
-        // Read entries until EOF
-        while let Ok(id) = bincode::deserialize_from::<_, u64>(&mut reader) {
-            let entry: FileEntry = bincode::deserialize_from(&mut reader)
-                .context("Failed to deserialize file entry")?;
-                
-            // Add to indexes
-            files.insert(id, entry);
-            
-            // Index by extension (if any)
-            if let Some(ext) = Path::new(&entry.name).extension() {
-                let ext = ext.to_string_lossy().to_lowercase();
-                if !ext.is_empty() {
-                    extension_index.entry(ext).or_default().push(id);
-                }
-            }
-            
-            // Index by name (case-insensitive)
-            let name_lower = entry.name.to_lowercase();
-            name_index.entry(name_lower).or_default().push(id);
-            
-            // Index by path
-            path_index.insert(entry.path.clone(), id);
+        let mut reader = SigningReader::new(reader, &mut signer);
+        for _ in 0..metadata.file_count {
+            let _id: u64 = deserialize_from(&mut reader).context("Failed to deserialize file ID")?;
+            let entry: FileEntry = deserialize_from(&mut reader).context("Failed to deserialize file entry")?;
+            entries.push(entry);
         }
+        
+        // Anything left over is part of the signed content too
+        io::copy(&mut reader, &mut io::sink()).context("Failed to read cache file")?;
     }
     
+    // Nothing from the file is used unless the signature checks out
+    if !signer.verify(&metadata.signature) {
+        cache_signing::report_tampered(drive_letter, cache_file);
+        bail!("Cache in {} failed its signature check", cache_file.display());
+    }
+    let cache = MftCache::detached(drive_letter);
+    cache.replace_entries(entries);
+    
     info!(
         "Loaded MFT cache with {} files ({} MB) in {:.2?}",
         metadata.file_count,
@@ -164,78 +403,62 @@ pub fn load_cache(cache_dir: &Path, drive_letter: char) -> Result<Option<MftCach
         start_time.elapsed()
     );
     
-    Ok(Some(cache))
+    Ok(Some((cache, metadata.signature)))
+}
+
+/// Whether a saved cache exists for a volume, so it can be loaded instead of read from the MFT
+pub fn has_saved_cache(cache_dir: &Path, volume: &VolumeId) -> bool {
+    find_cache_files(cache_dir, volume).map_or(false, |files| !files.is_empty())
 }
 
-/// Find cache files for a specific drive, sorted by creation time (newest first)
-fn find_cache_files(cache_dir: &Path, drive_letter: char) -> Result<Vec<(PathBuf, PathBuf)>> {
-    let mut cache_files = Vec::new();
+/// Volume and timestamp of a `mft_cache_<volume>_<time>.meta` file
+fn parse_snapshot_name(meta_name: &str) -> Option<(&str, u64)> {
+    let (volume, timestamp) = meta_name
+        .strip_prefix("mft_cache_")?
+        .strip_suffix(".meta")?
+        .rsplit_once('_')?;
+    Some((volume, timestamp.parse().ok()?))
+}
+
+/// Snapshots of a volume as `(cache_path, meta_path, timestamp)`, in no particular order
+fn snapshots_of(cache_dir: &Path, volume: &VolumeId) -> Result<Vec<(PathBuf, PathBuf, u64)>> {
+    let mut snapshots = Vec::new();
     
     for entry in fs::read_dir(cache_dir).context("Failed to read cache directory")? {
         let entry = entry.context("Failed to read cache directory entry")?;
         let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
         
-        if let Some(ext) = path.extension() {
-            if ext == "meta" {
-                if let Some(stem) = path.file_stem() {
-                    let cache_path = path.with_extension("");
-                    if cache_path.exists() {
-                        // Extract timestamp from filename
-                        if let Some(timestamp) = stem.to_string_lossy()
-                            .strip_prefix("mft_cache_")
-                            .and_then(|s| s.strip_suffix(".meta"))
-                            .and_then(|s| s.parse::<u64>().ok())
-                        {
-                            cache_files.push((cache_path, path, timestamp));
-                        }
-                    }
-                }
+        if let Some((saved_volume, timestamp)) = parse_snapshot_name(&name) {
+            let cache_path = path.with_extension("bin");
+            if saved_volume == volume.as_str() && cache_path.exists() {
+                snapshots.push((cache_path, path, timestamp));
             }
         }
     }
     
+    Ok(snapshots)
+}
+
+/// Find cache files for a specific volume, sorted by creation time (newest first)
+fn find_cache_files(cache_dir: &Path, volume: &VolumeId) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut cache_files = snapshots_of(cache_dir, volume)?;
+    
     // Sort by timestamp (newest first)
     cache_files.sort_by_key(|&(_, _, ts)| std::cmp::Reverse(ts));
     
-    // Filter by drive letter and convert to (cache_path, meta_path)
-    let result = cache_files
+    Ok(cache_files
         .into_iter()
-        .filter_map(|(cache_path, meta_path, _)| {
-            // TODO: Verify drive letter matches
-            Some((cache_path, meta_path))
-        })
-        .collect();
-    
-    Ok(result)
+        .map(|(cache_path, meta_path, _)| (cache_path, meta_path))
+        .collect())
 }
 
-/// Clean up old cache files, keeping only the N most recent
-fn cleanup_old_caches(cache_dir: &Path, keep: usize) -> Result<()> {
-    // Find all cache files
-    let mut cache_files = Vec::new();
-    
-    for entry in fs::read_dir(cache_dir).context("Failed to read cache directory")? {
-        let entry = entry.context("Failed to read cache directory entry")?;
-        let path = entry.path();
-        
-        if let Some(ext) = path.extension() {
-            if ext == "meta" {
-                if let Some(stem) = path.file_stem() {
-                    let cache_path = path.with_extension("");
-                    if cache_path.exists() {
-                        // Extract timestamp from filename
-                        if let Some(timestamp) = stem.to_string_lossy()
-                            .strip_prefix("mft_cache_")
-                            .and_then(|s| s.strip_suffix(".meta"))
-                            .and_then(|s| s.parse::<u64>().ok())
-                        {
-                            cache_files.push((cache_path, path, timestamp));
-                        }
-                    }
-                }
-            }
-        }
-    }
+/// Clean up old cache files of a volume, keeping only the N most recent
+fn cleanup_old_caches(cache_dir: &Path, volume: &VolumeId, keep: usize) -> Result<()> {
+    let mut cache_files = snapshots_of(cache_dir, volume)?;
     
     // Sort by timestamp (oldest first)
     cache_files.sort_by_key(|&(_, _, ts)| ts);
@@ -264,6 +487,15 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
     
+    /// Test key, so the tests never touch the real secret under `%LOCALAPPDATA%`
+    fn key() -> CacheKey {
+        CacheKey::from_secret(&[7u8; 32])
+    }
+    
+    fn entry(id: u64, path: &str) -> FileEntry {
+        FileEntry { size: 100, ..FileEntry::test(id, path) }
+    }
+    
     #[test]
     fn test_cache_persistence() {
         // Create a temporary directory for testing
@@ -271,18 +503,124 @@ mod tests {
         let cache_dir = temp_dir.path();
         
         // Create a test cache
-        let mut cache = MftCache::new('C').unwrap();
+        let cache = MftCache::new('C').unwrap();
         
         // Add some test data
         // ...
         
         // Save the cache
-        save_cache(&cache, cache_dir).unwrap();
+        save_cache(&cache, cache_dir, &key()).unwrap();
         
         // Load the cache
-        let loaded_cache = load_cache(cache_dir, 'C').unwrap().unwrap();
+        let (_loaded_cache, _) = load_cache(cache_dir, 'C', &VolumeId::letter('C'), &key()).unwrap().unwrap();
         
         // Verify the loaded cache matches the original
         // ...
     }
+    
+    #[test]
+    fn test_backend_names() {
+        assert_eq!("single-file".parse::<PersistenceBackend>().unwrap(), PersistenceBackend::SingleFile);
+        assert_eq!(" Snapshots ".parse::<PersistenceBackend>().unwrap(), PersistenceBackend::Snapshots);
+        assert!("redb".parse::<PersistenceBackend>().is_err());
+        for backend in [PersistenceBackend::Snapshots, PersistenceBackend::SingleFile] {
+            assert_eq!(backend.name().parse::<PersistenceBackend>().unwrap(), backend);
+        }
+    }
+    
+    #[test]
+    fn test_single_file_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let store = PersistenceBackend::SingleFile.store();
+        let volume = VolumeId::letter('D');
+        assert!(!store.has_saved(temp_dir.path(), &volume));
+        assert!(store.load(temp_dir.path(), 'D', &volume, &key()).unwrap().is_none());
+        
+        let cache = MftCache::detached('D');
+        cache.replace_entries(vec![entry(1, "docs\\a.txt"), entry(2, "docs\\b.txt")]);
+        let signature = store.save(&cache, temp_dir.path(), &key()).unwrap();
+        assert!(store.has_saved(temp_dir.path(), &volume));
+        
+        let (loaded, loaded_signature) = store.load(temp_dir.path(), 'D', &volume, &key()).unwrap().unwrap();
+        assert_eq!(loaded_signature, signature);
+        assert_eq!(loaded.get_files().len(), 2);
+        assert_eq!(loaded.get_path_index().get("docs\\b.txt"), Some(&2));
+    }
+    
+    #[test]
+    fn test_snapshots_follow_the_volume() {
+        let temp_dir = tempdir().unwrap();
+        let store = PersistenceBackend::Snapshots.store();
+        let volume = VolumeId::letter('E');
+        
+        let cache = MftCache::detached('E');
+        cache.replace_entries(vec![entry(1, "photos\\a.txt")]);
+        store.save(&cache, temp_dir.path(), &key()).unwrap();
+        assert!(store.has_saved(temp_dir.path(), &volume));
+        assert!(!store.has_saved(temp_dir.path(), &VolumeId::letter('F')));
+        
+        // The drive came back as F: and gets the files saved while it was E:
+        let (loaded, _) = store.load(temp_dir.path(), 'F', &volume, &key()).unwrap().unwrap();
+        assert_eq!(loaded.drive_letter(), 'F');
+        assert_eq!(loaded.get_path_index().get("photos\\a.txt"), Some(&1));
+    }
+    
+    #[test]
+    fn test_parse_snapshot_name() {
+        assert_eq!(parse_snapshot_name("mft_cache_serial-00001a2b_1700000000.meta"), Some(("serial-00001a2b", 1700000000)));
+        assert_eq!(parse_snapshot_name("mft_cache_vol-3f25-11d3_12.meta"), Some(("vol-3f25-11d3", 12)));
+        assert_eq!(parse_snapshot_name("mft_cache_drive-c.cache"), None);
+        assert_eq!(parse_snapshot_name("mft_cache_drive-c_x.meta"), None);
+    }
+    
+    #[test]
+    fn test_single_file_rejects_changed_data() {
+        let temp_dir = tempdir().unwrap();
+        let store = PersistenceBackend::SingleFile.store();
+        let volume = VolumeId::letter('E');
+        let cache = MftCache::detached('E');
+        cache.replace_entries(vec![entry(1, "a.txt")]);
+        store.save(&cache, temp_dir.path(), &key()).unwrap();
+        
+        let path = SingleFile::path_for(temp_dir.path(), &volume);
+        let mut data = fs::read(&path).unwrap();
+        let flipped = data.len() - 40;
+        data[flipped] ^= 0xff;
+        fs::write(&path, data).unwrap();
+        assert!(store.load(temp_dir.path(), 'E', &volume, &key()).is_err());
+    }
+    
+    #[test]
+    fn test_single_file_rejects_inflated_count() {
+        let temp_dir = tempdir().unwrap();
+        let store = PersistenceBackend::SingleFile.store();
+        let volume = VolumeId::letter('E');
+        let cache = MftCache::detached('E');
+        cache.replace_entries(vec![entry(1, "a.txt")]);
+        store.save(&cache, temp_dir.path(), &key()).unwrap();
+        
+        // A count no memory could hold is refused, not allocated for
+        let path = SingleFile::path_for(temp_dir.path(), &volume);
+        let data = fs::read(&path).unwrap();
+        let mut metadata: CacheMetadata = bincode::deserialize(&data).unwrap();
+        let header_len = bincode::serialized_size(&metadata).unwrap() as usize;
+        metadata.file_count = usize::MAX;
+        let mut tampered = bincode::serialize(&metadata).unwrap();
+        tampered.extend_from_slice(&data[header_len..]);
+        fs::write(&path, tampered).unwrap();
+        assert!(store.load(temp_dir.path(), 'E', &volume, &key()).is_err());
+    }
+    
+    #[test]
+    fn test_rejects_other_key() {
+        let temp_dir = tempdir().unwrap();
+        let volume = VolumeId::letter('E');
+        let cache = MftCache::detached('E');
+        cache.replace_entries(vec![entry(1, "a.txt")]);
+        for backend in [PersistenceBackend::Snapshots, PersistenceBackend::SingleFile] {
+            backend.store().save(&cache, temp_dir.path(), &key()).unwrap();
+            let other = CacheKey::from_secret(&[8u8; 32]);
+            assert!(backend.store().load(temp_dir.path(), 'E', &volume, &other).is_err());
+        }
+    }
 }
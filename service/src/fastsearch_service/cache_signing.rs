@@ -0,0 +1,293 @@
+//! Signatures on persisted MFT caches
+//!
+//! The service runs elevated and agents act on the paths it returns, so a
+//! cache file edited on disk could steer them toward files an attacker picked.
+//! Every saved cache is signed with an HMAC-SHA256 over its metadata and
+//! entries, keyed from a random secret only the service can read (protected
+//! with DPAPI on Windows). A cache whose signature doesn't check out is not
+//! loaded: the drive is rebuilt from the MFT instead, and the health status
+//! carries a warning until the service restarts.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::{error, info};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// File name of the signing secret inside the data directory
+const SECRET_FILE: &str = "cache.key";
+
+/// Length of the signing secret in bytes
+const SECRET_LEN: usize = 32;
+
+/// Purpose string the cache signing key is derived with
+const KEY_CONTEXT: &[u8] = b"FastSearch MFT cache signature v1";
+
+/// Caches refused because their signature didn't match, for the health status
+static TAMPERED: Mutex<Vec<Value>> = Mutex::new(Vec::new());
+
+/// Key the caches are signed with
+#[derive(Clone)]
+pub struct CacheKey {
+    key: [u8; 32],
+}
+
+impl std::fmt::Debug for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CacheKey(..)")
+    }
+}
+
+impl CacheKey {
+    /// Default location of the secret: `%LOCALAPPDATA%\FastSearchMCP\cache.key`
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("FastSearchMCP")
+            .join(SECRET_FILE)
+    }
+
+    /// Load the secret at `path`, creating one the first time
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let secret = match fs::read(path) {
+            Ok(sealed) => os::unseal(&sealed).context("Failed to unprotect the cache signing secret")?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let mut secret = vec![0u8; SECRET_LEN];
+                getrandom::getrandom(&mut secret).context("Failed to generate the cache signing secret")?;
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).context("Failed to create data directory")?;
+                }
+                os::write_private(path, &os::seal(&secret)?).context("Failed to save the cache signing secret")?;
+                info!("Created cache signing secret at {}", path.display());
+                secret
+            }
+            Err(e) => return Err(e).context("Failed to read the cache signing secret"),
+        };
+        if secret.len() != SECRET_LEN {
+            bail!("Cache signing secret in {} is corrupt", path.display());
+        }
+        Ok(Self::from_secret(&secret))
+    }
+
+    /// Derive the signing key from a secret
+    pub fn from_secret(secret: &[u8]) -> Self {
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(KEY_CONTEXT);
+        Self { key: mac.finalize().into_bytes().into() }
+    }
+
+    /// Start a signature; feed it the signed bytes, then `finish` or `verify` it
+    pub fn signer(&self) -> Signer {
+        Signer { mac: HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length") }
+    }
+}
+
+/// A signature being computed over a cache file
+pub struct Signer {
+    mac: HmacSha256,
+}
+
+impl Signer {
+    /// Add bytes to the signed content
+    pub fn update(&mut self, data: &[u8]) {
+        self.mac.update(data);
+    }
+
+    /// The signature over everything added
+    pub fn finish(self) -> Vec<u8> {
+        self.mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Check a stored signature (in constant time)
+    pub fn verify(self, signature: &[u8]) -> bool {
+        self.mac.verify_slice(signature).is_ok()
+    }
+}
+
+/// Writer that signs everything written through it
+pub struct SigningWriter<'a, W> {
+    inner: W,
+    signer: &'a mut Signer,
+}
+
+impl<'a, W: Write> SigningWriter<'a, W> {
+    pub fn new(inner: W, signer: &'a mut Signer) -> Self {
+        Self { inner, signer }
+    }
+}
+
+impl<W: Write> Write for SigningWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.signer.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader that signs everything read through it
+pub struct SigningReader<'a, R> {
+    inner: R,
+    signer: &'a mut Signer,
+}
+
+impl<'a, R: Read> SigningReader<'a, R> {
+    pub fn new(inner: R, signer: &'a mut Signer) -> Self {
+        Self { inner, signer }
+    }
+}
+
+impl<R: Read> Read for SigningReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.signer.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Record a cache that failed its signature check
+pub fn report_tampered(drive: char, file: &Path) {
+    error!(
+        "Refusing to load the cache for drive {} from {}: its signature doesn't match (modified outside the service?)",
+        drive,
+        file.display()
+    );
+    TAMPERED.lock().push(json!({
+        "kind": "tampered_cache",
+        "drive": drive.to_string(),
+        "file": file.display().to_string(),
+        "detected": Utc::now().to_rfc3339(),
+    }));
+}
+
+/// Caches refused since startup, as health warnings
+pub fn integrity_warnings() -> Vec<Value> {
+    TAMPERED.lock().clone()
+}
+
+#[cfg(windows)]
+mod os {
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+    use std::ptr::null_mut;
+    use std::slice;
+
+    use anyhow::{bail, Result};
+    use winapi::um::dpapi::{CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN};
+    use winapi::um::winbase::LocalFree;
+    use winapi::um::wincrypt::DATA_BLOB;
+
+    /// Encrypt the secret for the service's account
+    pub fn seal(secret: &[u8]) -> Result<Vec<u8>> {
+        crypt(secret, true)
+    }
+
+    /// Decrypt a secret sealed by `seal`
+    pub fn unseal(sealed: &[u8]) -> Result<Vec<u8>> {
+        crypt(sealed, false)
+    }
+
+    fn crypt(data: &[u8], protect: bool) -> Result<Vec<u8>> {
+        let mut input = DATA_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+        let mut output = DATA_BLOB { cbData: 0, pbData: null_mut() };
+        let ok = unsafe {
+            if protect {
+                CryptProtectData(&mut input, null_mut(), null_mut(), null_mut(), null_mut(), CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+            } else {
+                CryptUnprotectData(&mut input, null_mut(), null_mut(), null_mut(), null_mut(), CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+            }
+        };
+        if ok == 0 {
+            bail!("DPAPI failed: {}", io::Error::last_os_error());
+        }
+        let result = unsafe { slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec() };
+        unsafe { LocalFree(output.pbData as *mut _) };
+        Ok(result)
+    }
+
+    /// Write the (already encrypted) secret
+    pub fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+        fs::write(path, data)
+    }
+}
+
+#[cfg(not(windows))]
+mod os {
+    use std::fs::OpenOptions;
+    use std::io::{self, Write};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::Path;
+
+    use anyhow::Result;
+
+    pub fn seal(secret: &[u8]) -> Result<Vec<u8>> {
+        Ok(secret.to_vec())
+    }
+
+    pub fn unseal(sealed: &[u8]) -> Result<Vec<u8>> {
+        Ok(sealed.to_vec())
+    }
+
+    /// Write the secret readable by the service's user only
+    pub fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+        OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?.write_all(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_detects_changes() {
+        let key = CacheKey::from_secret(&[7u8; SECRET_LEN]);
+
+        let mut signer = key.signer();
+        {
+            let mut writer = SigningWriter::new(Vec::new(), &mut signer);
+            writer.write_all(b"header").unwrap();
+            writer.write_all(b"entries").unwrap();
+        }
+        let signature = signer.finish();
+
+        let mut verifier = key.signer();
+        let mut data = Vec::new();
+        SigningReader::new(&b"headerentries"[..], &mut verifier).read_to_end(&mut data).unwrap();
+        assert!(verifier.verify(&signature));
+
+        let mut verifier = key.signer();
+        verifier.update(b"headerEntries");
+        assert!(!verifier.verify(&signature));
+
+        // A different secret gives different signatures
+        let mut other = CacheKey::from_secret(&[8u8; SECRET_LEN]).signer();
+        other.update(b"headerentries");
+        assert!(!other.verify(&signature));
+    }
+
+    #[test]
+    fn test_secret_is_created_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SECRET_FILE);
+
+        let first = CacheKey::load_or_create(&path).unwrap();
+        let again = CacheKey::load_or_create(&path).unwrap();
+        assert_eq!(first.key, again.key);
+
+        fs::write(&path, b"short").unwrap();
+        assert!(CacheKey::load_or_create(&path).is_err());
+    }
+}
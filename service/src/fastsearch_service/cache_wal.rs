@@ -0,0 +1,332 @@
+//! Write-ahead log of incremental changes to a persisted MFT cache
+//!
+//! Snapshots are only written every `save_interval_secs`, so changes applied
+//! in between would be lost if the service crashed. Each change is appended
+//! to the drive's log before it is applied to the cache, a snapshot empties
+//! the log, and after loading a snapshot the log is replayed over it before
+//! the cache serves anything.
+//!
+//! Records are framed as `[length: u32 LE][bincode record][HMAC]`, the HMAC
+//! chained over the previous record's, with the key caches are signed with.
+//! The first record is chained to the signature of the snapshot the log
+//! follows, so a log only replays over that snapshot and not a newer one.
+//! A short or unverifiable final record is what a crash mid-append leaves
+//! behind and is dropped; anything else that doesn't verify means the log
+//! was edited, and it is refused like a tampered cache.
+//!
+//! Appends aren't flushed to disk one by one: whoever applies a batch of
+//! changes commits it once at the end ([`CacheWal::take_unsynced`]), and
+//! an append flushes itself once the oldest unflushed record is
+//! `MAX_UNSYNCED_AGE` old. A power cut loses at most the uncommitted tail.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::cache_signing::{self, CacheKey};
+use super::mft_cache::FileEntry;
+use super::volumes::VolumeId;
+
+/// Length of a record's HMAC
+const MAC_LEN: usize = 32;
+
+/// Largest record accepted when replaying; anything longer is corrupt
+const MAX_RECORD_LEN: usize = 1 << 20;
+
+/// Longest a record is left unflushed when no batch commits it
+const MAX_UNSYNCED_AGE: Duration = Duration::from_secs(1);
+
+/// One change to a cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WalRecord {
+    /// An entry was added or replaced
+    Upsert(FileEntry),
+    /// The entry at this path, and everything below it, was removed
+    Remove(String),
+}
+
+/// Append-only log of the changes made to one drive's cache since its last snapshot
+pub struct CacheWal {
+    path: PathBuf,
+    file: File,
+    key: CacheKey,
+    /// HMAC of the last record (or the snapshot's signature), which the next one is chained to
+    last_mac: Vec<u8>,
+    records: usize,
+    /// When the oldest record not yet flushed to disk was appended
+    unsynced_since: Option<Instant>,
+}
+
+impl std::fmt::Debug for CacheWal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheWal")
+            .field("path", &self.path)
+            .field("records", &self.records)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CacheWal {
+    /// Location of a volume's log in the cache directory
+    pub fn path_for(cache_dir: &Path, volume: &VolumeId) -> PathBuf {
+        cache_dir.join(format!("mft_cache_{}.wal", volume))
+    }
+
+    /// Open a drive's log, returning the records it holds
+    ///
+    /// `snapshot_mac` is the signature of the snapshot being brought up to
+    /// date. A torn final record is cut off. If the log fails verification it
+    /// is reported, emptied, and `None` is returned in place of the records:
+    /// the snapshot can't be brought up to date and the cache has to be rebuilt.
+    pub fn open(cache_dir: &Path, drive: char, volume: &VolumeId, key: CacheKey, snapshot_mac: &[u8]) -> Result<(Self, Option<Vec<WalRecord>>)> {
+        let path = Self::path_for(cache_dir, volume);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open cache log {}", path.display()))?;
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).context("Failed to read cache log")?;
+        let mut wal = Self { path, file, key, last_mac: snapshot_mac.to_vec(), records: 0, unsynced_since: None };
+
+        match wal.replay(&data) {
+            Ok((records, valid_len)) => {
+                if valid_len < data.len() {
+                    warn!("Dropping a partly written record at the end of {}", wal.path.display());
+                    wal.file.set_len(valid_len as u64).context("Failed to trim cache log")?;
+                }
+                if !records.is_empty() {
+                    info!("Replaying {} logged changes for drive {}", records.len(), drive);
+                }
+                wal.records = records.len();
+                Ok((wal, Some(records)))
+            }
+            Err(e) => {
+                warn!("{}", e);
+                cache_signing::report_tampered(drive, &wal.path);
+                wal.truncate(snapshot_mac)?;
+                Ok((wal, None))
+            }
+        }
+    }
+
+    /// Verify and decode the records in `data`, returning them and the length they take up
+    fn replay(&mut self, data: &[u8]) -> Result<(Vec<WalRecord>, usize)> {
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let frame = frame_at(data, offset)
+                .with_context(|| format!("Cache log {} is corrupt at byte {}", self.path.display(), offset))?;
+            let Some((payload, mac)) = frame else {
+                // Cut short by a crash
+                break;
+            };
+            let mut signer = self.key.signer();
+            signer.update(&self.last_mac);
+            signer.update(payload);
+            let end = offset + 4 + payload.len() + MAC_LEN;
+            if !signer.verify(mac) {
+                if end == data.len() {
+                    // The length made it to disk but not all of the record did
+                    break;
+                }
+                bail!("Cache log {} failed verification at byte {}", self.path.display(), offset);
+            }
+            let record = bincode::deserialize(payload)
+                .with_context(|| format!("Cache log {} has an unreadable record at byte {}", self.path.display(), offset))?;
+            records.push(record);
+            self.last_mac = mac.to_vec();
+            offset = end;
+        }
+        Ok((records, offset))
+    }
+
+    /// Append a record, to be flushed to disk with the rest of its batch
+    pub fn append(&mut self, record: &WalRecord) -> Result<()> {
+        let payload = bincode::serialize(record).context("Failed to serialize cache log record")?;
+        if payload.len() > MAX_RECORD_LEN {
+            bail!("Cache log record of {} bytes is too long", payload.len());
+        }
+        let mut signer = self.key.signer();
+        signer.update(&self.last_mac);
+        signer.update(&payload);
+        let mac = signer.finish();
+
+        let mut frame = Vec::with_capacity(4 + payload.len() + MAC_LEN);
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        frame.extend_from_slice(&mac);
+        self.file.write_all(&frame).context("Failed to write cache log")?;
+        self.last_mac = mac;
+        self.records += 1;
+
+        let unsynced_since = *self.unsynced_since.get_or_insert_with(Instant::now);
+        if unsynced_since.elapsed() >= MAX_UNSYNCED_AGE {
+            self.file.sync_data().context("Failed to flush cache log")?;
+            self.unsynced_since = None;
+        }
+        Ok(())
+    }
+
+    /// Handle to flush the records appended since the last flush, `None` if there are none
+    ///
+    /// The records count as flushed from here on; flushing through the handle
+    /// doesn't need the log, so appends and snapshots can go on meanwhile.
+    pub fn take_unsynced(&mut self) -> Result<Option<File>> {
+        if self.unsynced_since.is_none() {
+            return Ok(None);
+        }
+        let file = self.file.try_clone().context("Failed to open cache log for flushing")?;
+        self.unsynced_since = None;
+        Ok(Some(file))
+    }
+
+    /// Empty the log once the snapshot signed with `snapshot_mac` holds everything in it
+    pub fn truncate(&mut self, snapshot_mac: &[u8]) -> Result<()> {
+        self.file.set_len(0).context("Failed to truncate cache log")?;
+        self.file.sync_data().context("Failed to flush cache log")?;
+        self.last_mac = snapshot_mac.to_vec();
+        self.records = 0;
+        self.unsynced_since = None;
+        Ok(())
+    }
+
+    /// Number of records since the last snapshot
+    pub fn len(&self) -> usize {
+        self.records
+    }
+
+    /// Whether the log holds no records
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+}
+
+/// The payload and HMAC of the record at `offset`, `None` if the log ends before all of it
+///
+/// `append` never writes a length over `MAX_RECORD_LEN`, so one isn't a torn
+/// record but an edited or corrupt log.
+fn frame_at(data: &[u8], offset: usize) -> Result<Option<(&[u8], &[u8])>> {
+    let Some(len_bytes) = data.get(offset..offset + 4) else {
+        return Ok(None);
+    };
+    let len = u32::from_le_bytes(len_bytes.try_into().expect("slice of 4 bytes")) as usize;
+    if len > MAX_RECORD_LEN {
+        bail!("a record claims to be {} bytes long", len);
+    }
+    let payload = data.get(offset + 4..offset + 4 + len);
+    let mac = data.get(offset + 4 + len..offset + 4 + len + MAC_LEN);
+    Ok(payload.zip(mac))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Signature of the snapshot the test logs follow
+    const SNAPSHOT: &[u8] = &[9u8; MAC_LEN];
+
+    fn paths(records: &[WalRecord]) -> Vec<String> {
+        records.iter().map(|record| match record {
+            WalRecord::Upsert(entry) => entry.path.clone(),
+            WalRecord::Remove(path) => format!("-{}", path),
+        }).collect()
+    }
+
+    #[test]
+    fn test_records_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = CacheKey::from_secret(&[1u8; 32]);
+
+        let (mut wal, records) = CacheWal::open(dir.path(), 'c', &VolumeId::letter('C'), key.clone(), SNAPSHOT).unwrap();
+        assert_eq!(records.map(|r| r.len()), Some(0));
+        wal.append(&WalRecord::Upsert(FileEntry::test(1, "docs\\a.txt"))).unwrap();
+        wal.append(&WalRecord::Remove("tmp".to_string())).unwrap();
+        assert!(wal.take_unsynced().unwrap().is_some());
+        assert!(wal.take_unsynced().unwrap().is_none());
+        drop(wal);
+
+        let (mut wal, records) = CacheWal::open(dir.path(), 'C', &VolumeId::letter('C'), key.clone(), SNAPSHOT).unwrap();
+        assert_eq!(paths(&records.unwrap()), vec!["docs\\a.txt", "-tmp"]);
+
+        // The chain continues across reopening, and a snapshot empties the log
+        wal.append(&WalRecord::Upsert(FileEntry::test(2, "docs\\b.txt"))).unwrap();
+        assert_eq!(wal.len(), 3);
+        let next_snapshot = [10u8; MAC_LEN];
+        wal.truncate(&next_snapshot).unwrap();
+
+        // What is logged after it follows the new snapshot
+        wal.append(&WalRecord::Remove("docs".to_string())).unwrap();
+        drop(wal);
+        let (_, records) = CacheWal::open(dir.path(), 'C', &VolumeId::letter('C'), key, &next_snapshot).unwrap();
+        assert_eq!(paths(&records.unwrap()), vec!["-docs"]);
+    }
+
+    #[test]
+    fn test_log_only_replays_over_its_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = CacheKey::from_secret(&[4u8; 32]);
+        let (mut wal, _) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), key.clone(), SNAPSHOT).unwrap();
+        wal.append(&WalRecord::Upsert(FileEntry::test(1, "a.txt"))).unwrap();
+        wal.append(&WalRecord::Upsert(FileEntry::test(2, "b.txt"))).unwrap();
+        drop(wal);
+
+        let (_, records) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), key, &[10u8; MAC_LEN]).unwrap();
+        assert!(records.is_none());
+    }
+
+    #[test]
+    fn test_torn_and_tampered_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = CacheKey::from_secret(&[2u8; 32]);
+        let path = CacheWal::path_for(dir.path(), &VolumeId::letter('D'));
+
+        let (mut wal, _) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), key.clone(), SNAPSHOT).unwrap();
+        wal.append(&WalRecord::Upsert(FileEntry::test(1, "a.txt"))).unwrap();
+        wal.append(&WalRecord::Upsert(FileEntry::test(2, "b.txt"))).unwrap();
+        drop(wal);
+        let intact = fs::read(&path).unwrap();
+
+        // A crash in the middle of the last record loses only that record,
+        // and appending goes on after the one before it
+        fs::write(&path, &intact[..intact.len() - 5]).unwrap();
+        let (mut wal, records) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), key.clone(), SNAPSHOT).unwrap();
+        assert_eq!(paths(&records.unwrap()), vec!["a.txt"]);
+        assert!(fs::metadata(&path).unwrap().len() < intact.len() as u64 - 5);
+        wal.append(&WalRecord::Upsert(FileEntry::test(3, "c.txt"))).unwrap();
+        drop(wal);
+        let (_, records) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), key.clone(), SNAPSHOT).unwrap();
+        assert_eq!(paths(&records.unwrap()), vec!["a.txt", "c.txt"]);
+
+        // An edited record that isn't the last is refused and the log emptied
+        let mut edited = intact.clone();
+        edited[10] ^= 0xff;
+        fs::write(&path, &edited).unwrap();
+        let (wal, records) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), key.clone(), SNAPSHOT).unwrap();
+        assert!(records.is_none());
+        assert!(wal.is_empty());
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+
+        // So is a length no record could have, even on the last record
+        let first_len = 4 + u32::from_le_bytes(intact[..4].try_into().unwrap()) as usize + MAC_LEN;
+        let mut oversized = intact.clone();
+        oversized[first_len..first_len + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &oversized).unwrap();
+        let (_, records) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), key.clone(), SNAPSHOT).unwrap();
+        assert!(records.is_none());
+
+        // And a log written with another key
+        fs::write(&path, &intact).unwrap();
+        let (_, records) = CacheWal::open(dir.path(), 'D', &VolumeId::letter('D'), CacheKey::from_secret(&[3u8; 32]), SNAPSHOT).unwrap();
+        assert!(records.is_none());
+    }
+}
@@ -0,0 +1,192 @@
+//! CamelCase and acronym matching (`search_type: "camel"`)
+//!
+//! Developers remember identifiers by their words: `FBC` for
+//! `FooBarController.cs`, `mft_cache` for `MftCache.rs`. A camel pattern is
+//! matched a word at a time: each word of the name it uses gives the pattern
+//! a prefix of itself, in order, and words may be skipped.
+//!
+//! Names split into words at CamelCase humps (every capital starts a word, so
+//! `HTTPServer` is `H`, `T`, `T`, `P`, `Server`), at digits and at anything
+//! that isn't a letter or digit. Matching ignores case, except that a capital
+//! in the pattern, or a letter after a separator, has to start a word.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A word of a name: its lowercased characters and where each starts
+struct Word {
+    chars: Vec<char>,
+    starts: Vec<usize>,
+    end: usize,
+}
+
+/// A camel pattern, lowercased, with the characters that have to start a word
+#[derive(Debug, Clone)]
+pub struct CamelPattern {
+    chars: Vec<char>,
+    word_start: Vec<bool>,
+}
+
+impl CamelPattern {
+    pub fn new(pattern: &str) -> Self {
+        let mut chars = Vec::new();
+        let mut word_start = Vec::new();
+        let mut after_separator = false;
+        for c in pattern.chars() {
+            if !c.is_alphanumeric() {
+                after_separator = true;
+                continue;
+            }
+            word_start.push(c.is_uppercase() || after_separator);
+            chars.push(c.to_lowercase().next().unwrap_or(c));
+            after_separator = false;
+        }
+        Self { chars, word_start }
+    }
+
+    /// Whether the pattern matches the name
+    pub fn is_match(&self, name: &str) -> bool {
+        self.parts(name).is_some()
+    }
+
+    /// Byte ranges of the name each word gave the pattern, `None` if it doesn't match
+    pub fn positions(&self, name: &str) -> Option<Vec<Range<usize>>> {
+        let words = split_words(name);
+        let parts = self.parts_of(&words)?;
+        Some(parts.into_iter()
+            .map(|(word, taken)| {
+                let word = &words[word];
+                word.starts[0]..word.starts.get(taken).copied().unwrap_or(word.end)
+            })
+            .collect())
+    }
+
+    fn parts(&self, name: &str) -> Option<Vec<(usize, usize)>> {
+        self.parts_of(&split_words(name))
+    }
+
+    /// Which words the pattern takes a prefix of, and how many characters of each
+    fn parts_of(&self, words: &[Word]) -> Option<Vec<(usize, usize)>> {
+        if self.chars.is_empty() {
+            return Some(Vec::new());
+        }
+        let mut parts = Vec::new();
+        let mut failed = HashSet::new();
+        self.take(0, 0, words, &mut parts, &mut failed).then_some(parts)
+    }
+
+    /// Match the pattern from character `from` against words from `first` on
+    ///
+    /// Longer prefixes are tried first; positions known to fail are remembered,
+    /// so a name is never worked through more than once per pair.
+    fn take(
+        &self,
+        from: usize,
+        first: usize,
+        words: &[Word],
+        parts: &mut Vec<(usize, usize)>,
+        failed: &mut HashSet<(usize, usize)>,
+    ) -> bool {
+        if from == self.chars.len() {
+            return true;
+        }
+        if failed.contains(&(from, first)) {
+            return false;
+        }
+        for (index, word) in words.iter().enumerate().skip(first) {
+            let longest = word.chars.iter()
+                .zip(&self.chars[from..])
+                .enumerate()
+                .take_while(|(i, (have, want))| have == want && (*i == 0 || !self.word_start[from + i]))
+                .count();
+            for taken in (1..=longest).rev() {
+                parts.push((index, taken));
+                if self.take(from + taken, index + 1, words, parts, failed) {
+                    return true;
+                }
+                parts.pop();
+            }
+        }
+        failed.insert((from, first));
+        false
+    }
+}
+
+/// Split a name into words at humps, digits and separators
+fn split_words(name: &str) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut previous: Option<char> = None;
+    for (at, c) in name.char_indices() {
+        if !c.is_alphanumeric() {
+            if let Some(word) = words.last_mut() {
+                word.end = word.end.min(at);
+            }
+            previous = None;
+            continue;
+        }
+        let starts_word = match previous {
+            None => true,
+            Some(prev) => c.is_uppercase() || prev.is_numeric() != c.is_numeric(),
+        };
+        if starts_word {
+            if let Some(word) = words.last_mut() {
+                word.end = word.end.min(at);
+            }
+            words.push(Word { chars: Vec::new(), starts: Vec::new(), end: usize::MAX });
+        }
+        let word = words.last_mut().expect("a word was started");
+        word.chars.push(c.to_lowercase().next().unwrap_or(c));
+        word.starts.push(at);
+        previous = Some(c);
+    }
+    if let Some(word) = words.last_mut() {
+        word.end = word.end.min(name.len());
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acronyms_and_word_prefixes() {
+        let fbc = CamelPattern::new("FBC");
+        assert!(fbc.is_match("FooBarController.cs"));
+        assert!(fbc.is_match("foo_bar_controller.py"));
+        // Words may be skipped, but not reordered
+        assert!(CamelPattern::new("FC").is_match("FooBarController.cs"));
+        assert!(!CamelPattern::new("CF").is_match("FooBarController.cs"));
+        // Letters inside a word are not word starts
+        assert!(!fbc.is_match("Fbcdata.bin"));
+        assert!(!CamelPattern::new("oba").is_match("FooBar.cs"));
+
+        let mft_cache = CamelPattern::new("mft_cache");
+        assert!(mft_cache.is_match("MftCache.rs"));
+        assert!(mft_cache.is_match("mft_cache_tests.rs"));
+        assert!(!mft_cache.is_match("mftcachetests.rs"));
+        assert!(CamelPattern::new("mftcache").is_match("MftCache.rs"));
+
+        // Acronyms in names are a word per capital
+        assert!(CamelPattern::new("HTTPServ").is_match("HTTPServer.java"));
+        assert!(CamelPattern::new("hs").is_match("HTTPServer.java"));
+        assert!(CamelPattern::new("v2C").is_match("ApiV2Client.ts"));
+    }
+
+    #[test]
+    fn test_positions() {
+        let name = "FooBarController.cs";
+        let spans: Vec<&str> = CamelPattern::new("FooBC").positions(name).unwrap()
+            .into_iter()
+            .map(|span| &name[span])
+            .collect();
+        assert_eq!(spans, vec!["Foo", "B", "C"]);
+        let name = "mft_cache.rs";
+        let spans: Vec<&str> = CamelPattern::new("MftCa").positions(name).unwrap()
+            .into_iter()
+            .map(|span| &name[span])
+            .collect();
+        assert_eq!(spans, vec!["mft", "ca"]);
+        assert!(CamelPattern::new("xyz").positions(name).is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Cancelling long-running tool calls
+//!
+//! MCP clients send `notifications/cancelled` with the id of a request they
+//! no longer want the result of. Tools that can run for minutes (hashing a
+//! drive for duplicates, say) register the request while they run and check
+//! its token between steps, stopping early with what they have so far.
+//!
+//! A `timeout_ms` argument works the same way from the other side: the search
+//! checks a deadline while it scans and answers with the matches found so far
+//! once it passes. The deadline also carries the request's token, so one check
+//! between batches covers both.
+//!
+//! Tokens form a tree. The registry holds the engine's root token; every
+//! request, MFT cache and HTTP search gets a child of it, so stopping the
+//! service cancels everything still running, while a cancelled request or a
+//! closed HTTP connection only stops its own work. Loops check their token at
+//! each step (a directory, a chunk, a batch of candidates) with
+//! [`CancelToken::check`], which fails with [`Cancelled`].
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use log::info;
+use parking_lot::Mutex;
+use serde_json::Value;
+
+/// Shared flag telling running work to stop
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<TokenState>);
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    /// Cancelling the parent cancels this token too
+    parent: Option<CancelToken>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that is cancelled on its own or together with this one
+    pub fn child(&self) -> Self {
+        Self(Arc::new(TokenState { cancelled: AtomicBool::new(false), parent: Some(self.clone()) }))
+    }
+
+    /// Ask the work to stop
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the work (or the work it belongs to) was asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        let mut token = self;
+        loop {
+            if token.0.cancelled.load(Ordering::Relaxed) {
+                return true;
+            }
+            match &token.0.parent {
+                Some(parent) => token = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Fail with [`Cancelled`] once the work was asked to stop, for `?` at loop boundaries
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Error of work that stopped because its token was cancelled
+///
+/// Wrapped in an `anyhow::Error`, it can be told apart from real failures with
+/// `error.is::<Cancelled>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Point in time a search stops scanning at, from its `timeout_ms` argument,
+/// or earlier if its request is cancelled
+#[derive(Debug, Default)]
+pub struct Deadline {
+    at: Option<Instant>,
+    cancel: Option<CancelToken>,
+    passed: AtomicBool,
+}
+
+impl Deadline {
+    /// No deadline: the search runs to the end
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// A deadline `timeout` from now
+    pub fn after(timeout: Duration) -> Self {
+        Self { at: Instant::now().checked_add(timeout), ..Self::default() }
+    }
+
+    /// Also stop once `cancel` is cancelled
+    pub fn or_cancelled(self, cancel: &CancelToken) -> Self {
+        Self { cancel: Some(cancel.clone()), ..self }
+    }
+
+    /// Parse the `timeout_ms` argument, counting from now
+    pub fn from_args(args: &Value) -> Result<Self> {
+        Self::from_args_or(args, None)
+    }
+
+    /// Parse the `timeout_ms` argument, with `default` when there is none
+    pub fn from_args_or(args: &Value, default: Option<Duration>) -> Result<Self> {
+        match &args["timeout_ms"] {
+            Value::Null => Ok(default.map_or_else(Self::none, Self::after)),
+            value => match value.as_u64() {
+                Some(ms) if ms > 0 => Ok(Self::after(Duration::from_millis(ms))),
+                _ => Err(anyhow!("'timeout_ms' must be a positive number of milliseconds")),
+            },
+        }
+    }
+
+    /// Whether the deadline has passed (or the request was cancelled); once it has, the answer stays yes
+    pub fn passed(&self) -> bool {
+        if self.passed.load(Ordering::Relaxed) {
+            return true;
+        }
+        let passed = self.cancel.as_ref().map_or(false, CancelToken::is_cancelled)
+            || self.at.map_or(false, |at| Instant::now() >= at);
+        if passed {
+            self.passed.store(true, Ordering::Relaxed);
+        }
+        passed
+    }
+
+    /// Whether a check found the deadline passed, so the work was cut short
+    pub fn was_hit(&self) -> bool {
+        self.passed.load(Ordering::Relaxed)
+    }
+}
+
+/// Requests that can be cancelled, by JSON-RPC id
+#[derive(Debug, Default)]
+pub struct CancelRegistry {
+    /// Parent of every token handed out; cancelled when the service stops
+    root: CancelToken,
+    running: Mutex<HashMap<String, CancelToken>>,
+}
+
+impl CancelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token everything the engine runs descends from
+    pub fn root(&self) -> &CancelToken {
+        &self.root
+    }
+
+    /// Stop all running and future work, when the service shuts down
+    pub fn cancel_all(&self, reason: &str) {
+        info!("Cancelling all running work ({})", reason);
+        self.root.cancel();
+    }
+
+    /// Register a request for as long as the returned guard lives
+    ///
+    /// Requests without an id (which can't be cancelled) get a token of their own.
+    pub fn start(&self, request: &Value) -> RunningRequest<'_> {
+        let token = self.root.child();
+        let key = request.get("id").filter(|id| !id.is_null()).map(Value::to_string);
+        if let Some(key) = &key {
+            self.running.lock().insert(key.clone(), token.clone());
+        }
+        RunningRequest { registry: self, key, token }
+    }
+
+    /// Handle the params of `notifications/cancelled`; returns whether a running request was cancelled
+    pub fn cancel(&self, params: &Value) -> bool {
+        let Some(id) = params.get("requestId") else {
+            return false;
+        };
+        match self.running.lock().get(&id.to_string()) {
+            Some(token) => {
+                info!("Cancelling request {} ({})", id, params["reason"].as_str().unwrap_or("no reason given"));
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A registered request; unregisters it when dropped
+#[derive(Debug)]
+pub struct RunningRequest<'a> {
+    registry: &'a CancelRegistry,
+    key: Option<String>,
+    token: CancelToken,
+}
+
+impl RunningRequest<'_> {
+    /// Token the request's work checks
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl Drop for RunningRequest<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = &self.key {
+            self.registry.running.lock().remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cancel_running_request() {
+        let registry = CancelRegistry::new();
+        let running = registry.start(&json!({"id": 7, "method": "tools/call"}));
+        assert!(!registry.cancel(&json!({"requestId": 8})));
+        assert!(!running.token().is_cancelled());
+
+        assert!(registry.cancel(&json!({"requestId": 7, "reason": "user pressed stop"})));
+        assert!(running.token().is_cancelled());
+
+        // Finished requests can't be cancelled any more
+        drop(running);
+        assert!(!registry.cancel(&json!({"requestId": 7})));
+    }
+
+    #[test]
+    fn test_child_tokens() {
+        let root = CancelToken::new();
+        let cache = root.child();
+        let rebuild = cache.child();
+        assert!(rebuild.check().is_ok());
+
+        // Cancelling a child leaves its parent running
+        rebuild.cancel();
+        assert!(rebuild.is_cancelled() && !cache.is_cancelled());
+
+        let other = cache.child();
+        root.cancel();
+        assert!(cache.is_cancelled() && other.is_cancelled());
+        let error = anyhow::Error::from(other.check().unwrap_err());
+        assert!(error.is::<Cancelled>());
+    }
+
+    #[test]
+    fn test_cancel_all() {
+        let registry = CancelRegistry::new();
+        let running = registry.start(&json!({"id": 1}));
+        let anonymous = registry.start(&json!({"method": "tools/call"}));
+        registry.cancel_all("service stopping");
+        assert!(running.token().is_cancelled() && anonymous.token().is_cancelled());
+        // Requests arriving during shutdown are stopped from the start
+        assert!(registry.start(&json!({"id": 2})).token().is_cancelled());
+    }
+
+    #[test]
+    fn test_deadline() {
+        assert!(Deadline::from_args(&json!({"timeout_ms": 0})).is_err());
+        assert!(Deadline::from_args(&json!({"timeout_ms": "soon"})).is_err());
+
+        let none = Deadline::from_args(&json!({})).unwrap();
+        assert!(!none.passed() && !none.was_hit());
+
+        // Not hit until something checks it
+        let deadline = Deadline::after(Duration::ZERO);
+        assert!(!deadline.was_hit());
+        assert!(deadline.passed());
+        assert!(deadline.was_hit());
+        assert!(!Deadline::from_args(&json!({"timeout_ms": 60_000})).unwrap().passed());
+
+        // A default applies only without timeout_ms
+        assert!(Deadline::from_args_or(&json!({}), Some(Duration::ZERO)).unwrap().passed());
+        assert!(!Deadline::from_args_or(&json!({"timeout_ms": 60_000}), Some(Duration::ZERO)).unwrap().passed());
+
+        let token = CancelToken::new();
+        let deadline = Deadline::none().or_cancelled(&token);
+        assert!(!deadline.passed());
+        token.cancel();
+        assert!(deadline.passed() && deadline.was_hit());
+    }
+}
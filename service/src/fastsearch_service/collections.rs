@@ -0,0 +1,234 @@
+//! Named collections of bookmarked search results
+//!
+//! Multi-step work (an agent tracking down every copy of a contract, say)
+//! spans many searches. Results worth keeping can be bookmarked one at a time
+//! or a whole result set at once into a named collection, which the service
+//! stores next to the saved searches so it outlives the session.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::info;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::json_store::{data_dir, JsonStore};
+
+/// File name of the collection store inside the data directory
+const COLLECTIONS_FILE: &str = "collections.json";
+
+/// A bookmarked file or folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub drive: String,
+    /// Path relative to the drive root
+    pub path: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_directory: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// `fast_search` arguments of the search that found it, if known
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub source: Value,
+    /// When it was bookmarked (UNIX timestamp)
+    pub added: i64,
+}
+
+impl Bookmark {
+    /// Bookmark a path on a drive
+    pub fn new(drive: &str, path: &str, note: Option<&str>) -> Self {
+        let path = path.trim_matches('\\').to_string();
+        Self {
+            drive: drive.to_uppercase(),
+            name: path.rsplit('\\').next().unwrap_or_default().to_string(),
+            path,
+            size: None,
+            is_directory: None,
+            note: note.map(str::to_string),
+            source: Value::Null,
+            added: Utc::now().timestamp(),
+        }
+    }
+
+    /// Bookmark a structured search result (`drive`, `path`, `size`, ... as `fast_search` returns them)
+    pub fn from_result(result: &Value, note: Option<&str>) -> Result<Self> {
+        let path = result["path"].as_str()
+            .filter(|path| !path.trim().is_empty())
+            .ok_or_else(|| anyhow!("Results to bookmark need a 'path'"))?;
+        let mut bookmark = Self::new(result["drive"].as_str().unwrap_or("C"), path, note);
+        if let Some(name) = result["name"].as_str() {
+            bookmark.name = name.to_string();
+        }
+        bookmark.size = result["size"].as_u64();
+        bookmark.is_directory = result["is_directory"].as_bool();
+        Ok(bookmark)
+    }
+
+    /// Whether two bookmarks are of the same file (paths ignore case, like NTFS)
+    fn same_file(&self, other: &Bookmark) -> bool {
+        self.drive == other.drive && self.path.eq_ignore_ascii_case(&other.path)
+    }
+}
+
+/// A named set of bookmarks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created: i64,
+    pub updated: i64,
+    #[serde(default)]
+    pub items: Vec<Bookmark>,
+}
+
+impl Collection {
+    pub fn new(name: &str) -> Self {
+        let now = Utc::now().timestamp();
+        Self { name: name.to_string(), description: None, created: now, updated: now, items: Vec::new() }
+    }
+
+    /// Add bookmarks not already in the collection; returns how many were new
+    ///
+    /// A file bookmarked again keeps its place, taking the newer note if one was given.
+    pub fn add(&mut self, bookmarks: Vec<Bookmark>) -> usize {
+        let mut added = 0;
+        for bookmark in bookmarks {
+            match self.items.iter_mut().find(|item| item.same_file(&bookmark)) {
+                Some(existing) => {
+                    if bookmark.note.is_some() {
+                        existing.note = bookmark.note;
+                    }
+                }
+                None => {
+                    self.items.push(bookmark);
+                    added += 1;
+                }
+            }
+        }
+        self.updated = Utc::now().timestamp();
+        added
+    }
+}
+
+/// Persistent set of collections
+#[derive(Debug)]
+pub struct CollectionStore {
+    file: JsonStore<Vec<Collection>>,
+    collections: RwLock<BTreeMap<String, Collection>>,
+}
+
+impl CollectionStore {
+    /// Default location: `%LOCALAPPDATA%\FastSearchMCP\collections.json`
+    pub fn default_path() -> PathBuf {
+        data_dir().join(COLLECTIONS_FILE)
+    }
+
+    /// Open the store at the given path, starting empty if it doesn't exist or can't be read
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let file: JsonStore<Vec<Collection>> = JsonStore::at(&path, "collections");
+        let collections: BTreeMap<String, Collection> = file.load().unwrap_or_default()
+            .into_iter()
+            .map(|c| (c.name.clone(), c))
+            .collect();
+
+        info!("Loaded {} collections from {}", collections.len(), path.as_ref().display());
+        Self {
+            file,
+            collections: RwLock::new(collections),
+        }
+    }
+
+    /// Create a store that is never written to disk
+    pub fn in_memory() -> Self {
+        Self {
+            file: JsonStore::in_memory("collections"),
+            collections: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Look up a collection by name
+    pub fn get(&self, name: &str) -> Option<Collection> {
+        self.collections.read().get(name).cloned()
+    }
+
+    /// All collections, ordered by name
+    pub fn list(&self) -> Vec<Collection> {
+        self.collections.read().values().cloned().collect()
+    }
+
+    /// Add bookmarks to a collection, creating it if needed; returns the collection and how many were new
+    pub fn add(&self, name: &str, description: Option<&str>, bookmarks: Vec<Bookmark>) -> Result<(Collection, usize)> {
+        let (collection, added) = {
+            let mut collections = self.collections.write();
+            let collection = collections.entry(name.to_string()).or_insert_with(|| Collection::new(name));
+            if let Some(description) = description {
+                collection.description = Some(description.to_string());
+            }
+            let added = collection.add(bookmarks);
+            (collection.clone(), added)
+        };
+        self.persist()?;
+        Ok((collection, added))
+    }
+
+    /// Remove a collection, returning it if it existed
+    pub fn remove(&self, name: &str) -> Result<Option<Collection>> {
+        let removed = self.collections.write().remove(name);
+        if removed.is_some() {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    /// Write the store to disk (atomically, via a temporary file)
+    fn persist(&self) -> Result<()> {
+        self.file.save(&self.list())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(COLLECTIONS_FILE);
+
+        let store = CollectionStore::open(&path);
+        let result = json!({"name": "contract.pdf", "path": "Users\\me\\contract.pdf", "drive": "C", "size": 1200, "is_directory": false});
+        let (_, added) = store.add("contracts", Some("Signed copies"), vec![Bookmark::from_result(&result, None).unwrap()]).unwrap();
+        assert_eq!(added, 1);
+
+        let reopened = CollectionStore::open(&path);
+        let collection = reopened.get("contracts").unwrap();
+        assert_eq!(collection.description.as_deref(), Some("Signed copies"));
+        assert_eq!(collection.items[0].size, Some(1200));
+        assert_eq!(collection.items[0].name, "contract.pdf");
+
+        assert!(reopened.remove("contracts").unwrap().is_some());
+        assert!(CollectionStore::open(&path).list().is_empty());
+    }
+
+    #[test]
+    fn test_same_file_is_bookmarked_once() {
+        let mut collection = Collection::new("finds");
+        assert_eq!(collection.add(vec![Bookmark::new("d", "\\Data\\a.txt", None)]), 1);
+        assert_eq!(collection.add(vec![Bookmark::new("D", "data\\A.TXT", Some("the original"))]), 0);
+
+        assert_eq!(collection.items.len(), 1);
+        assert_eq!(collection.items[0].drive, "D");
+        assert_eq!(collection.items[0].name, "a.txt");
+        assert_eq!(collection.items[0].note.as_deref(), Some("the original"));
+        assert!(Bookmark::from_result(&json!({"name": "x"}), None).is_err());
+    }
+}
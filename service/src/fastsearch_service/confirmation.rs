@@ -0,0 +1,137 @@
+//! Confirming expensive tool calls before running them
+//!
+//! Most calls are answered from the MFT cache in milliseconds, but a few can
+//! keep the disk busy for minutes: a regex over every name of a drive whose
+//! cache has to be read from the MFT first, or hashing hundreds of gigabytes
+//! of same-size files for duplicates. Those calls first answer with an
+//! estimate and a token, and only run when the client calls again with the
+//! same arguments plus `confirm_token`. A token is good for one call, for a
+//! few minutes.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use log::info;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Argument carrying the token of a confirmed call
+pub const CONFIRM_TOKEN_ARG: &str = "confirm_token";
+
+/// How long a token can be used after it was handed out
+pub const TOKEN_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+/// Bytes of same-size files `find_duplicates` may read without asking
+pub const CONFIRM_HASH_BYTES: u64 = 20 * 1024 * 1024 * 1024;
+
+/// What an expensive call would cost
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CostEstimate {
+    /// Why the call needs confirming, one sentence each
+    pub reasons: Vec<String>,
+    /// Drives whose cache would be built from the MFT first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cold_drives: Vec<String>,
+    /// Files that would be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files_to_read: Option<u64>,
+    /// Bytes that would be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_to_read: Option<u64>,
+}
+
+/// A token handed out and not used yet
+#[derive(Debug)]
+struct Pending {
+    tool: String,
+    /// The call's arguments, without the token
+    args: Value,
+    issued: Instant,
+}
+
+/// Tokens for calls waiting on confirmation
+#[derive(Debug, Default)]
+pub struct ConfirmationGate {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+/// Arguments a token is tied to
+fn without_token(args: &Value) -> Value {
+    let mut args = args.clone();
+    if let Some(map) = args.as_object_mut() {
+        map.remove(CONFIRM_TOKEN_ARG);
+    }
+    args
+}
+
+impl ConfirmationGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hand out a token for running `tool` with `args`
+    pub fn issue(&self, tool: &str, args: &Value) -> Result<String> {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).context("Failed to generate a confirmation token")?;
+        let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        let mut pending = self.pending.lock();
+        pending.retain(|_, call| call.issued.elapsed() < TOKEN_LIFETIME);
+        pending.insert(token.clone(), Pending { tool: tool.to_string(), args: without_token(args), issued: Instant::now() });
+        Ok(token)
+    }
+
+    /// Use up the token in `args`, if any
+    ///
+    /// Returns whether the call was confirmed, or an error for a token that
+    /// expired, was used already or was handed out for another call.
+    pub fn redeem(&self, tool: &str, args: &Value) -> Result<bool> {
+        let token = match &args[CONFIRM_TOKEN_ARG] {
+            Value::Null => return Ok(false),
+            Value::String(token) => token,
+            _ => return Err(anyhow!("'{}' must be the token of a confirmation_required response", CONFIRM_TOKEN_ARG)),
+        };
+        let call = self.pending.lock().remove(token)
+            .filter(|call| call.issued.elapsed() < TOKEN_LIFETIME)
+            .ok_or_else(|| anyhow!("Confirmation token expired or was already used; call again without it for a new one"))?;
+        if call.tool != tool || call.args != without_token(args) {
+            return Err(anyhow!("Confirmation token was issued for a different call; call again without it for a new one"));
+        }
+        info!("Running confirmed {} call", tool);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_token_confirms_one_matching_call() {
+        let gate = ConfirmationGate::new();
+        let args = json!({"pattern": ".*", "search_type": "regex"});
+        assert!(!gate.redeem("fast_search", &args).unwrap());
+
+        let token = gate.issue("fast_search", &args).unwrap();
+        let mut confirmed = args.clone();
+        confirmed[CONFIRM_TOKEN_ARG] = json!(token);
+        assert!(gate.redeem("find_duplicates", &confirmed).is_err());
+
+        let token = gate.issue("fast_search", &args).unwrap();
+        confirmed[CONFIRM_TOKEN_ARG] = json!(token);
+        assert!(gate.redeem("fast_search", &confirmed).unwrap());
+        // Tokens are single-use
+        assert!(gate.redeem("fast_search", &confirmed).is_err());
+    }
+
+    #[test]
+    fn test_token_is_tied_to_arguments() {
+        let gate = ConfirmationGate::new();
+        let token = gate.issue("find_duplicates", &json!({"drive": "D"})).unwrap();
+        assert!(gate.redeem("find_duplicates", &json!({"drive": "E", "confirm_token": token})).is_err());
+        assert!(gate.redeem("find_duplicates", &json!({"confirm_token": 7})).is_err());
+    }
+}
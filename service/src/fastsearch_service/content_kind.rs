@@ -0,0 +1,227 @@
+//! Text or binary, for `fast_search` results and the `content_kind` filter
+//!
+//! Tools that read file contents want text and nothing else. Most files can
+//! be told apart by extension for free; with `sniff_content_kind`, files whose
+//! extension says nothing (no extension, `.dat`, an unusual one) are sniffed
+//! from their first bytes the way Git does: a NUL byte, or a high share of
+//! control characters, makes them binary. Sniffed kinds are cached per file
+//! record in the verify stage.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::metadata_cache::MetadataCache;
+use super::mft_cache::{disk_path, DriveRoot, FileEntry};
+
+/// Bytes looked at when sniffing
+pub const SNIFF_LEN: usize = 8000;
+
+/// Extensions of files that are text
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "rst", "adoc", "tex", "log", "csv", "tsv", "json", "jsonl", "xml", "yaml", "yml",
+    "toml", "ini", "cfg", "conf", "properties", "env", "html", "htm", "css", "scss", "less", "svg",
+    "rs", "py", "pyi", "js", "mjs", "cjs", "jsx", "ts", "tsx", "go", "java", "kt", "kts", "scala", "cs",
+    "c", "h", "cpp", "cc", "cxx", "hpp", "hh", "rb", "php", "swift", "dart", "lua", "pl", "r", "sql",
+    "sh", "bash", "zsh", "ps1", "psm1", "bat", "cmd", "vbs", "gradle", "cmake", "mk", "dockerfile",
+    "gitignore", "gitattributes", "editorconfig", "lock", "vue", "svelte", "graphql", "proto", "srt", "vtt",
+];
+
+/// Extensions of files that are binary
+const BINARY_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "sys", "so", "dylib", "o", "obj", "lib", "a", "pdb", "class", "jar", "pyc", "wasm", "bin",
+    "msi", "cab", "iso", "img", "vhd", "vhdx", "vmdk",
+    "zip", "7z", "rar", "gz", "tgz", "bz2", "xz", "zst", "tar",
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff", "ico", "heic", "heif", "avif", "psd", "raw",
+    "mp3", "wav", "flac", "ogg", "m4a", "aac", "wma", "opus",
+    "mp4", "mkv", "mov", "avi", "wmv", "webm", "m4v", "flv",
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp",
+    "db", "sqlite", "sqlite3", "mdb", "accdb", "ttf", "otf", "woff", "woff2",
+];
+
+/// Whether a file holds text or binary data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentKind {
+    Text,
+    Binary,
+}
+
+impl ContentKind {
+    /// Parse the `content_kind` tool argument
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Some(ContentKind::Text),
+            "binary" => Some(ContentKind::Binary),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentKind::Text => "text",
+            ContentKind::Binary => "binary",
+        }
+    }
+}
+
+/// The kind of a cache entry by its extension; `None` for folders and extensions that don't say
+pub fn kind_by_extension(file: &FileEntry) -> Option<ContentKind> {
+    if file.is_directory {
+        return None;
+    }
+    let extension = file.extension.as_deref()?;
+    if TEXT_EXTENSIONS.contains(&extension) {
+        Some(ContentKind::Text)
+    } else if BINARY_EXTENSIONS.contains(&extension) {
+        Some(ContentKind::Binary)
+    } else {
+        None
+    }
+}
+
+/// The kind of data that starts with `head`
+pub fn sniff(head: &[u8]) -> ContentKind {
+    // UTF-16 text is full of NUL bytes, but says so up front
+    if head.starts_with(b"\xFF\xFE") || head.starts_with(b"\xFE\xFF") {
+        return ContentKind::Text;
+    }
+    let head = &head[..head.len().min(SNIFF_LEN)];
+    if head.contains(&0) {
+        return ContentKind::Binary;
+    }
+    let control = head.iter()
+        .filter(|&&byte| byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+        .count();
+    if control * 10 > head.len() {
+        ContentKind::Binary
+    } else {
+        ContentKind::Text
+    }
+}
+
+/// Sniff the kind of a file on disk
+pub fn read_kind(path: &Path) -> Option<ContentKind> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(path).ok()?.take(SNIFF_LEN as u64).read_to_end(&mut head).ok()?;
+    Some(sniff(&head))
+}
+
+/// Sniffed kinds, by drive and file record
+#[derive(Debug, Default)]
+pub struct ContentKindCache {
+    cache: MetadataCache<ContentKind>,
+}
+
+impl ContentKindCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kind of a cache entry by its extension, or else (with `sniff`) from its first bytes below `root`
+    pub fn get(&self, drive: char, root: &Path, file: &FileEntry, sniff: bool) -> Option<ContentKind> {
+        if file.is_directory {
+            return None;
+        }
+        kind_by_extension(file).or_else(|| {
+            sniff.then(|| self.cache.get_or_read(drive, file, || read_kind(&disk_path(root, &file.path)))).flatten()
+        })
+    }
+
+    /// Number of files remembered
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// The `content_kind` and `sniff_content_kind` arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentKindFilter {
+    /// Only files of this kind
+    pub kind: Option<ContentKind>,
+    /// Sniff files whose extension doesn't tell
+    pub sniff: bool,
+}
+
+impl ContentKindFilter {
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let kind = match &args["content_kind"] {
+            Value::Null => None,
+            value => Some(value.as_str()
+                .and_then(ContentKind::parse)
+                .ok_or_else(|| anyhow!("'content_kind' must be text or binary"))?),
+        };
+        Ok(Self { kind, sniff: args["sniff_content_kind"].as_bool().unwrap_or(false) })
+    }
+}
+
+/// Kind check for the matches on one drive
+pub struct ContentKindCheck<'a> {
+    kind: ContentKind,
+    sniff: bool,
+    cache: &'a ContentKindCache,
+    disk: DriveRoot,
+}
+
+impl<'a> ContentKindCheck<'a> {
+    pub fn new(kind: ContentKind, sniff: bool, cache: &'a ContentKindCache, disk: DriveRoot) -> Self {
+        Self { kind, sniff, cache, disk }
+    }
+
+    /// Whether a file is of the wanted kind; files of unknown kind and folders aren't
+    pub fn accepts(&self, file: &FileEntry) -> bool {
+        self.cache.get(self.disk.drive, &self.disk.root, file, self.sniff) == Some(self.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn entry(id: u64, name: &str) -> FileEntry {
+        FileEntry { size: 10, ..FileEntry::test(id, name) }
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"fn main() {}\n\tlet x = 1;\r\n"), ContentKind::Text);
+        assert_eq!(sniff(b""), ContentKind::Text);
+        assert_eq!(sniff(b"MZ\x90\0\x03\0\0\0"), ContentKind::Binary);
+        assert_eq!(sniff(b"\x01\x02\x03\x04abc"), ContentKind::Binary);
+        assert_eq!(sniff(b"\xFF\xFEh\0i\0"), ContentKind::Text);
+    }
+
+    #[test]
+    fn test_extension_then_sniff() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("Makefile"), "all:\n\tcargo build\n").unwrap();
+        std::fs::write(dir.path().join("blob.dat"), b"\0\0\x01").unwrap();
+        let cache = ContentKindCache::new();
+
+        assert_eq!(cache.get('C', dir.path(), &entry(1, "main.rs"), false), Some(ContentKind::Text));
+        assert_eq!(cache.get('C', dir.path(), &entry(2, "setup.exe"), false), Some(ContentKind::Binary));
+        // Nothing to go by without reading the file
+        assert_eq!(cache.get('C', dir.path(), &entry(3, "Makefile"), false), None);
+        assert_eq!(cache.get('C', dir.path(), &entry(3, "Makefile"), true), Some(ContentKind::Text));
+        assert_eq!(cache.get('C', dir.path(), &entry(4, "blob.dat"), true), Some(ContentKind::Binary));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_content_kind_filter() {
+        assert_eq!(ContentKindFilter::from_args(&json!({})).unwrap(), ContentKindFilter::default());
+        assert!(ContentKindFilter::from_args(&json!({"content_kind": "source"})).is_err());
+        let filter = ContentKindFilter::from_args(&json!({"content_kind": "Text", "sniff_content_kind": true})).unwrap();
+        assert_eq!(filter, ContentKindFilter { kind: Some(ContentKind::Text), sniff: true });
+    }
+}
@@ -0,0 +1,253 @@
+//! Content types from the first bytes of files (`verify_content_type`)
+//!
+//! Extensions say what a file claims to be; the signature at its start says
+//! what it is. With `verify_content_type`, matches whose extension belongs to
+//! a format with a known signature (PDF, Office, images, archives,
+//! executables, media) are dropped unless their first bytes carry it, so a
+//! `.pdf` that is really a renamed text file or executable isn't returned.
+//! Files of other extensions, which can't be judged, and folders pass. The
+//! detected MIME type is added to the results.
+//!
+//! Headers are read in the verify stage and cached per file record. Detection
+//! is redone from the cached bytes on every search, as a rename changes the
+//! extension without touching the size or modification time.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::metadata_cache::MetadataCache;
+use super::mft_cache::{disk_path, DriveRoot, FileEntry};
+
+/// Bytes read from the start of a file, enough for every signature
+pub const HEADER_LEN: usize = 16;
+
+/// A file format recognised by bytes at fixed offsets
+#[derive(Debug)]
+pub struct Signature {
+    pub mime_type: &'static str,
+    /// Extensions of files in this format, lowercased
+    pub extensions: &'static [&'static str],
+    /// Byte strings that must all be found, at their offsets
+    magic: &'static [(usize, &'static [u8])],
+}
+
+impl Signature {
+    fn matches(&self, header: &[u8]) -> bool {
+        self.magic.iter().all(|(offset, bytes)| {
+            header.get(*offset..offset + bytes.len()).map_or(false, |found| found == *bytes)
+        })
+    }
+}
+
+const ZIP: &[(usize, &[u8])] = &[(0, b"PK\x03\x04")];
+const OLE: &[(usize, &[u8])] = &[(0, b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1")];
+const FTYP: &[(usize, &[u8])] = &[(4, b"ftyp")];
+
+/// Known formats; several may share a signature (ZIP and OLE containers, ISO media),
+/// and the one listing a file's extension is reported for it
+pub static SIGNATURES: &[Signature] = &[
+    Signature { mime_type: "application/pdf", extensions: &["pdf"], magic: &[(0, b"%PDF-")] },
+    Signature { mime_type: "image/png", extensions: &["png"], magic: &[(0, b"\x89PNG\r\n\x1A\n")] },
+    Signature { mime_type: "image/jpeg", extensions: &["jpg", "jpeg", "jpe", "jfif"], magic: &[(0, b"\xFF\xD8\xFF")] },
+    Signature { mime_type: "image/gif", extensions: &["gif"], magic: &[(0, b"GIF8")] },
+    Signature { mime_type: "image/bmp", extensions: &["bmp"], magic: &[(0, b"BM")] },
+    Signature { mime_type: "image/webp", extensions: &["webp"], magic: &[(0, b"RIFF"), (8, b"WEBP")] },
+    Signature { mime_type: "image/tiff", extensions: &["tif", "tiff"], magic: &[(0, b"II*\0")] },
+    Signature { mime_type: "image/tiff", extensions: &["tif", "tiff"], magic: &[(0, b"MM\0*")] },
+    Signature { mime_type: "image/vnd.microsoft.icon", extensions: &["ico"], magic: &[(0, b"\0\0\x01\0")] },
+    Signature { mime_type: "image/vnd.adobe.photoshop", extensions: &["psd"], magic: &[(0, b"8BPS")] },
+    Signature { mime_type: "image/heic", extensions: &["heic", "heif"], magic: FTYP },
+    Signature { mime_type: "image/avif", extensions: &["avif"], magic: FTYP },
+    Signature { mime_type: "video/mp4", extensions: &["mp4", "m4v"], magic: FTYP },
+    Signature { mime_type: "audio/mp4", extensions: &["m4a"], magic: FTYP },
+    Signature { mime_type: "video/quicktime", extensions: &["mov"], magic: FTYP },
+    Signature { mime_type: "video/3gpp", extensions: &["3gp"], magic: FTYP },
+    Signature { mime_type: "video/x-matroska", extensions: &["mkv", "mka"], magic: &[(0, b"\x1A\x45\xDF\xA3")] },
+    Signature { mime_type: "video/webm", extensions: &["webm"], magic: &[(0, b"\x1A\x45\xDF\xA3")] },
+    Signature { mime_type: "video/x-msvideo", extensions: &["avi"], magic: &[(0, b"RIFF"), (8, b"AVI ")] },
+    Signature { mime_type: "audio/wav", extensions: &["wav"], magic: &[(0, b"RIFF"), (8, b"WAVE")] },
+    Signature { mime_type: "audio/mpeg", extensions: &["mp3"], magic: &[(0, b"ID3")] },
+    Signature { mime_type: "audio/mpeg", extensions: &["mp3"], magic: &[(0, b"\xFF\xFB")] },
+    Signature { mime_type: "audio/flac", extensions: &["flac"], magic: &[(0, b"fLaC")] },
+    Signature { mime_type: "audio/ogg", extensions: &["ogg", "oga", "opus"], magic: &[(0, b"OggS")] },
+    Signature { mime_type: "application/zip", extensions: &["zip"], magic: ZIP },
+    Signature {
+        mime_type: "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        extensions: &["docx"],
+        magic: ZIP,
+    },
+    Signature {
+        mime_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        extensions: &["xlsx"],
+        magic: ZIP,
+    },
+    Signature {
+        mime_type: "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        extensions: &["pptx"],
+        magic: ZIP,
+    },
+    Signature { mime_type: "application/vnd.oasis.opendocument.text", extensions: &["odt"], magic: ZIP },
+    Signature { mime_type: "application/vnd.oasis.opendocument.spreadsheet", extensions: &["ods"], magic: ZIP },
+    Signature { mime_type: "application/vnd.oasis.opendocument.presentation", extensions: &["odp"], magic: ZIP },
+    Signature { mime_type: "application/epub+zip", extensions: &["epub"], magic: ZIP },
+    Signature { mime_type: "application/java-archive", extensions: &["jar"], magic: ZIP },
+    Signature { mime_type: "application/msword", extensions: &["doc"], magic: OLE },
+    Signature { mime_type: "application/vnd.ms-excel", extensions: &["xls"], magic: OLE },
+    Signature { mime_type: "application/vnd.ms-powerpoint", extensions: &["ppt"], magic: OLE },
+    Signature { mime_type: "application/x-msi", extensions: &["msi"], magic: OLE },
+    Signature { mime_type: "application/vnd.ms-outlook", extensions: &["msg"], magic: OLE },
+    Signature { mime_type: "application/x-7z-compressed", extensions: &["7z"], magic: &[(0, b"7z\xBC\xAF\x27\x1C")] },
+    Signature { mime_type: "application/vnd.rar", extensions: &["rar"], magic: &[(0, b"Rar!\x1A\x07")] },
+    Signature { mime_type: "application/gzip", extensions: &["gz", "tgz"], magic: &[(0, b"\x1F\x8B")] },
+    Signature { mime_type: "application/x-bzip2", extensions: &["bz2"], magic: &[(0, b"BZh")] },
+    Signature { mime_type: "application/x-xz", extensions: &["xz"], magic: &[(0, b"\xFD7zXZ\0")] },
+    Signature { mime_type: "application/zstd", extensions: &["zst"], magic: &[(0, b"\x28\xB5\x2F\xFD")] },
+    Signature { mime_type: "application/vnd.ms-cab-compressed", extensions: &["cab"], magic: &[(0, b"MSCF")] },
+    Signature {
+        mime_type: "application/vnd.microsoft.portable-executable",
+        extensions: &["exe", "dll", "sys", "ocx", "scr", "cpl", "efi", "mui"],
+        magic: &[(0, b"MZ")],
+    },
+    Signature { mime_type: "application/x-elf", extensions: &["so", "elf"], magic: &[(0, b"\x7FELF")] },
+    Signature { mime_type: "application/wasm", extensions: &["wasm"], magic: &[(0, b"\0asm")] },
+    Signature { mime_type: "application/x-ms-shortcut", extensions: &["lnk"], magic: &[(0, b"L\0\0\0\x01\x14\x02\0")] },
+    Signature { mime_type: "application/rtf", extensions: &["rtf"], magic: &[(0, b"{\\rtf")] },
+    Signature { mime_type: "application/vnd.sqlite3", extensions: &["sqlite", "sqlite3", "db3"], magic: &[(0, b"SQLite format 3\0")] },
+];
+
+/// Whether files with this extension have a signature to check
+pub fn has_signature(extension: &str) -> bool {
+    SIGNATURES.iter().any(|signature| signature.extensions.contains(&extension))
+}
+
+/// The format a header is in, preferring the one listing `extension` among formats sharing a signature
+pub fn detect(header: &[u8], extension: Option<&str>) -> Option<&'static Signature> {
+    let mut found = SIGNATURES.iter().filter(|signature| signature.matches(header));
+    let first = found.next()?;
+    if extension.map_or(true, |ext| first.extensions.contains(&ext)) {
+        return Some(first);
+    }
+    found.find(|signature| extension.map_or(false, |ext| signature.extensions.contains(&ext)))
+        .or(Some(first))
+}
+
+/// Whether a header carries a signature of the format its extension claims
+pub fn confirms(header: &[u8], extension: &str) -> bool {
+    SIGNATURES.iter().any(|signature| signature.extensions.contains(&extension) && signature.matches(header))
+}
+
+/// The first bytes of a file, fewer if it is shorter
+pub fn read_header(path: &Path) -> Option<Vec<u8>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path).ok()?.take(HEADER_LEN as u64).read_to_end(&mut header).ok()?;
+    Some(header)
+}
+
+/// File headers read so far, by drive and file record
+#[derive(Debug, Default)]
+pub struct ContentTypeCache {
+    cache: MetadataCache<Vec<u8>>,
+}
+
+impl ContentTypeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Header of a file, read from below `root` unless known for this version of the file
+    pub fn header(&self, drive: char, root: &Path, file: &FileEntry) -> Option<Vec<u8>> {
+        if file.is_directory {
+            return None;
+        }
+        self.cache.get_or_read(drive, file, || read_header(&disk_path(root, &file.path)))
+    }
+
+    /// Detected MIME type of a file; `None` for folders, unreadable files and unknown formats
+    pub fn mime_type(&self, drive: char, root: &Path, file: &FileEntry) -> Option<&'static str> {
+        let header = self.header(drive, root, file)?;
+        detect(&header, file.extension.as_deref()).map(|signature| signature.mime_type)
+    }
+
+    /// Number of files remembered
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// Content check for the matches on one drive
+pub struct ContentCheck<'a> {
+    cache: &'a ContentTypeCache,
+    disk: DriveRoot,
+}
+
+impl<'a> ContentCheck<'a> {
+    pub fn new(cache: &'a ContentTypeCache, disk: DriveRoot) -> Self {
+        Self { cache, disk }
+    }
+
+    /// Whether a match is what its extension claims, or can't be judged
+    pub fn accepts(&self, file: &FileEntry) -> bool {
+        let Some(extension) = file.extension.as_deref().filter(|ext| !file.is_directory && has_signature(ext)) else {
+            return true;
+        };
+        self.cache.header(self.disk.drive, &self.disk.root, file)
+            .map_or(false, |header| confirms(&header, extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(id: u64, name: &str, size: u64) -> FileEntry {
+        FileEntry { size, ..FileEntry::test(id, name) }
+    }
+
+    #[test]
+    fn test_detect() {
+        for signature in SIGNATURES {
+            let end = signature.magic.iter().map(|(offset, bytes)| offset + bytes.len()).max().unwrap();
+            assert!(end <= HEADER_LEN, "{} needs {} bytes", signature.mime_type, end);
+        }
+        let docx = b"PK\x03\x04\x14\0\x06\0";
+        assert_eq!(detect(docx, Some("docx")).unwrap().mime_type,
+                   "application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+        assert_eq!(detect(docx, Some("bin")).unwrap().mime_type, "application/zip");
+        assert_eq!(detect(b"\0\0\0\x18ftypqt  ", Some("mov")).unwrap().mime_type, "video/quicktime");
+        assert_eq!(detect(b"RIFF\0\0\0\0WAVEfmt ", None).unwrap().mime_type, "audio/wav");
+        assert!(detect(b"hello world", Some("txt")).is_none());
+
+        assert!(confirms(b"%PDF-1.7\n", "pdf"));
+        assert!(!confirms(b"MZ\x90\0", "pdf"));
+        assert!(!confirms(b"", "pdf"));
+        assert!(has_signature("pdf") && !has_signature("txt"));
+    }
+
+    #[test]
+    fn test_content_check() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("real.pdf"), b"%PDF-1.4\n%rest").unwrap();
+        std::fs::write(dir.path().join("renamed.pdf"), b"MZ\x90\0\x03\0\0\0").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"plain text").unwrap();
+        let cache = ContentTypeCache::new();
+        let check = ContentCheck::new(&cache, DriveRoot::new('C', dir.path().to_path_buf()));
+
+        assert!(check.accepts(&entry(1, "real.pdf", 14)));
+        assert!(!check.accepts(&entry(2, "renamed.pdf", 8)));
+        // Text has no signature, and isn't read at all
+        assert!(check.accepts(&entry(3, "notes.txt", 10)));
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.mime_type('C', dir.path(), &entry(1, "real.pdf", 14)), Some("application/pdf"));
+        // Renaming keeps the record, size and time: the cached header is judged by the new name
+        assert_eq!(cache.mime_type('C', dir.path(), &entry(2, "renamed.exe", 8)),
+                   Some("application/vnd.microsoft.portable-executable"));
+        assert!(!check.accepts(&entry(1, "real.docx", 14)));
+    }
+}
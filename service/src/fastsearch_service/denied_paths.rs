@@ -0,0 +1,260 @@
+//! Folders learned to be unreadable, left out of walks for a while
+//!
+//! Walked drives and mounted directories always contain a few folders this
+//! account can't open (`System Volume Information`, other users' profiles).
+//! Every walk used to try each of them again and log a warning per folder.
+//! A folder denied on a few walks in a row is now skipped until its retry
+//! time, which doubles each time it is still denied. The store is kept on
+//! disk next to the saved searches, so a restart doesn't start over.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{debug, error, info};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use super::json_store::{data_dir, JsonStore};
+
+/// File name of the denied path store inside the data directory
+const DENIED_PATHS_FILE: &str = "denied_paths.json";
+
+/// Walks in a row a folder must be denied on before it is skipped
+pub const DENY_THRESHOLD: u32 = 3;
+
+/// How long a folder is first skipped for
+const FIRST_RETRY_SECS: i64 = 6 * 60 * 60;
+
+/// Longest a folder is skipped for before trying it again
+const MAX_RETRY_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// A folder that couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeniedPath {
+    pub drive: char,
+    /// Path relative to the drive or mount root
+    pub path: String,
+    /// Walks in a row the folder was denied on
+    pub denials: u32,
+    /// When it was last denied (UNIX timestamp)
+    pub last_denied: i64,
+    /// Skipped by walks until then (UNIX timestamp)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excluded_until: Option<i64>,
+}
+
+impl DeniedPath {
+    /// Whether walks skip the folder at `now`
+    pub fn is_excluded(&self, now: i64) -> bool {
+        self.excluded_until.map_or(false, |until| now < until)
+    }
+}
+
+/// What recording a denial changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denial {
+    /// Denied for the first time (or again after being readable)
+    First,
+    /// Denied again, not skipped yet
+    Repeated,
+    /// Skipped by walks from now until the given time (UNIX timestamp)
+    Excluded(i64),
+}
+
+/// Persistent record of unreadable folders
+#[derive(Debug)]
+pub struct DeniedPathStore {
+    file: JsonStore<Vec<DeniedPath>>,
+    /// Keyed by drive and lowercased path
+    denied: RwLock<HashMap<(char, String), DeniedPath>>,
+    dirty: AtomicBool,
+}
+
+fn key(drive: char, path: &str) -> (char, String) {
+    (drive.to_ascii_uppercase(), path.to_lowercase())
+}
+
+/// Time a folder is skipped for after its `excluded`-th exclusion (counting from 0)
+fn retry_after(excluded: u32) -> i64 {
+    FIRST_RETRY_SECS.saturating_mul(1 << excluded.min(16)).min(MAX_RETRY_SECS)
+}
+
+impl DeniedPathStore {
+    /// Default location: `%LOCALAPPDATA%\FastSearchMCP\denied_paths.json`
+    pub fn default_path() -> PathBuf {
+        data_dir().join(DENIED_PATHS_FILE)
+    }
+
+    /// Open the store at the given path, starting empty if it doesn't exist or can't be read
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let file: JsonStore<Vec<DeniedPath>> = JsonStore::at(&path, "denied paths");
+        let denied: HashMap<(char, String), DeniedPath> = file.load().unwrap_or_default()
+            .into_iter()
+            .map(|denied| (key(denied.drive, &denied.path), denied))
+            .collect();
+
+        info!("Loaded {} denied paths from {}", denied.len(), path.as_ref().display());
+        Self {
+            file,
+            denied: RwLock::new(denied),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Create a store that is never written to disk
+    pub fn in_memory() -> Self {
+        Self {
+            file: JsonStore::in_memory("denied paths"),
+            denied: RwLock::new(HashMap::new()),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether walks should leave the folder out for now
+    pub fn should_skip(&self, drive: char, path: &str) -> bool {
+        let denied = self.denied.read();
+        !denied.is_empty() && denied.get(&key(drive, path)).map_or(false, |denied| denied.is_excluded(Utc::now().timestamp()))
+    }
+
+    /// Record that a walk couldn't read the folder
+    pub fn record_denied(&self, drive: char, path: &str) -> Denial {
+        let now = Utc::now().timestamp();
+        let mut denied = self.denied.write();
+        let entry = denied.entry(key(drive, path)).or_insert_with(|| DeniedPath {
+            drive: drive.to_ascii_uppercase(),
+            path: path.to_string(),
+            denials: 0,
+            last_denied: now,
+            excluded_until: None,
+        });
+        entry.denials += 1;
+        entry.last_denied = now;
+        self.dirty.store(true, Ordering::Relaxed);
+
+        if entry.denials < DENY_THRESHOLD {
+            return if entry.denials == 1 { Denial::First } else { Denial::Repeated };
+        }
+        // Each retry that is still denied doubles the time until the next
+        let until = now + retry_after(entry.denials - DENY_THRESHOLD);
+        entry.excluded_until = Some(until);
+        Denial::Excluded(until)
+    }
+
+    /// Record that a walk read the folder, forgetting any denials
+    pub fn record_readable(&self, drive: char, path: &str) {
+        if self.denied.read().is_empty() {
+            return;
+        }
+        if let Some(readable) = self.denied.write().remove(&key(drive, path)) {
+            info!("{}:\\{} is readable again after {} denials", readable.drive, readable.path, readable.denials);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Denied folders, of one drive or all, sorted by drive and path
+    pub fn list(&self, drive: Option<char>) -> Vec<DeniedPath> {
+        let mut list: Vec<DeniedPath> = self.denied.read().values()
+            .filter(|denied| drive.map_or(true, |drive| denied.drive == drive.to_ascii_uppercase()))
+            .cloned()
+            .collect();
+        list.sort_by(|a, b| a.drive.cmp(&b.drive).then_with(|| a.path.to_lowercase().cmp(&b.path.to_lowercase())));
+        list
+    }
+
+    /// Forget the denied folders of one drive or all, so the next walk tries them again
+    pub fn clear(&self, drive: Option<char>) -> usize {
+        let mut denied = self.denied.write();
+        let before = denied.len();
+        denied.retain(|(denied_drive, _), _| drive.map_or(false, |drive| *denied_drive != drive.to_ascii_uppercase()));
+        let cleared = before - denied.len();
+        if cleared > 0 {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        cleared
+    }
+
+    /// Write pending changes to disk, logging failures
+    pub fn flush(&self) {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if let Err(e) = self.persist() {
+            error!("Failed to save denied paths: {}", e);
+        }
+    }
+
+    /// Write the store to disk (atomically, via a temporary file)
+    fn persist(&self) -> Result<()> {
+        let Some(path) = self.file.path() else {
+            return Ok(());
+        };
+
+        let denied = self.list(None);
+        self.file.save(&denied)?;
+
+        debug!("Saved {} denied paths to {}", denied.len(), path.display());
+        Ok(())
+    }
+}
+
+impl Drop for DeniedPathStore {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_denials_exclude_with_backoff() {
+        let store = DeniedPathStore::in_memory();
+        assert_eq!(store.record_denied('c', "System Volume Information"), Denial::First);
+        assert_eq!(store.record_denied('C', "system volume information"), Denial::Repeated);
+        assert!(!store.should_skip('C', "System Volume Information"));
+
+        let now = Utc::now().timestamp();
+        let Denial::Excluded(first) = store.record_denied('C', "System Volume Information") else {
+            panic!("third denial should exclude the folder");
+        };
+        assert!(first >= now + FIRST_RETRY_SECS);
+        assert!(store.should_skip('C', "SYSTEM VOLUME INFORMATION"));
+        assert!(!store.should_skip('D', "System Volume Information"));
+
+        // Still denied when retried: skipped twice as long
+        let Denial::Excluded(second) = store.record_denied('C', "System Volume Information") else {
+            panic!("a retried folder stays excluded");
+        };
+        assert!(second - now >= 2 * FIRST_RETRY_SECS);
+        assert_eq!(retry_after(40), MAX_RETRY_SECS);
+
+        store.record_readable('C', "System Volume Information");
+        assert!(store.list(None).is_empty());
+    }
+
+    #[test]
+    fn test_store_round_trip_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DENIED_PATHS_FILE);
+
+        let store = DeniedPathStore::open(&path);
+        for _ in 0..DENY_THRESHOLD {
+            store.record_denied('C', "Users\\other");
+        }
+        store.record_denied('D', "private");
+        drop(store);
+
+        let reopened = DeniedPathStore::open(&path);
+        assert!(reopened.should_skip('C', "users\\other"));
+        assert_eq!(reopened.list(Some('d'))[0].denials, 1);
+
+        assert_eq!(reopened.clear(Some('C')), 1);
+        assert!(!reopened.should_skip('C', "Users\\other"));
+        assert_eq!(reopened.clear(None), 1);
+        assert!(reopened.list(None).is_empty());
+    }
+}
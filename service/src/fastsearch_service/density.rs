@@ -0,0 +1,176 @@
+//! Early detection of searches that match a large part of the cache
+//!
+//! A pattern like `*` on a system drive matches millions of entries, and the
+//! first page of those is an arbitrary sliver that costs a full scan to
+//! produce. Before scanning, `fast_search` estimates how many entries will
+//! match: it knows how many candidates each cache's prefilter yields (from the
+//! index sizes) and runs the full match on a sample of them. If the estimate is
+//! both large and a big share of the cache, the search answers with a summary
+//! of where the matches are and how to narrow them down instead.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::mft_cache::FileEntry;
+
+/// Candidates sampled per cache
+pub const SAMPLE_SIZE: usize = 4096;
+
+/// Searches estimated to match at least this many entries...
+pub const BROAD_MIN_MATCHES: f64 = 50_000.0;
+
+/// ...and at least this share of all searched entries are summarized
+pub const BROAD_MIN_FRACTION: f64 = 0.1;
+
+/// Entries listed per breakdown in the summary
+const SUMMARY_TOP: usize = 10;
+
+/// Match estimate built from samples of each searched cache
+#[derive(Debug, Default)]
+pub struct DensitySample {
+    entries: usize,
+    sampled: usize,
+    estimated: f64,
+    extensions: HashMap<String, f64>,
+    folders: HashMap<String, f64>,
+}
+
+/// A bucket of the estimated matches
+#[derive(Debug, Clone, Serialize)]
+pub struct Breakdown {
+    /// Extension (without dot) or top-level folder
+    pub key: String,
+    /// Estimated number of matches in the bucket
+    pub estimated: u64,
+}
+
+/// What a broad search returns instead of a page of results
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadSummary {
+    /// Estimated number of matches
+    pub estimated_matches: u64,
+    /// Entries in the searched caches
+    pub total_entries: u64,
+    /// Candidates the estimate is based on
+    pub sampled: u64,
+    /// Most common extensions among the matches
+    pub top_extensions: Vec<Breakdown>,
+    /// Top-level folders holding the most matches
+    pub top_folders: Vec<Breakdown>,
+}
+
+impl DensitySample {
+    /// Start an empty estimate
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sample one cache
+    ///
+    /// `entries` is the size of the cache, `candidates` how many entries its
+    /// prefilter yields, and `display_path` returns the path a candidate would
+    /// be reported under if it passes every cheap stage.
+    pub fn add_source<'f, I, M>(&mut self, entries: usize, candidates: usize, sample: I, display_path: M)
+    where
+        I: Iterator<Item = &'f FileEntry>,
+        M: Fn(&'f FileEntry) -> Option<&'f str>,
+    {
+        self.entries += entries;
+
+        let mut sampled = 0usize;
+        let mut matched: Vec<(&'f FileEntry, &'f str)> = Vec::new();
+        for file in sample.take(SAMPLE_SIZE) {
+            sampled += 1;
+            if let Some(path) = display_path(file) {
+                matched.push((file, path));
+            }
+        }
+        if sampled == 0 {
+            return;
+        }
+        self.sampled += sampled;
+
+        // Each sampled match stands for this many matches among all candidates
+        let weight = candidates as f64 / sampled as f64;
+        self.estimated += matched.len() as f64 * weight;
+        for (file, path) in matched {
+            let extension = file.extension.clone().unwrap_or_default();
+            *self.extensions.entry(extension).or_default() += weight;
+            let folder = path.split('\\').next().unwrap_or_default();
+            let folder = if folder == path { String::new() } else { folder.to_string() };
+            *self.folders.entry(folder).or_default() += weight;
+        }
+    }
+
+    /// Estimated number of matches
+    pub fn estimated_matches(&self) -> u64 {
+        self.estimated.round() as u64
+    }
+
+    /// Whether the search should be summarized rather than paged
+    pub fn is_broad(&self) -> bool {
+        is_broad_count(self.estimated, self.entries)
+    }
+
+    /// Summary of the estimated matches
+    pub fn summary(&self) -> BroadSummary {
+        BroadSummary {
+            estimated_matches: self.estimated_matches(),
+            total_entries: self.entries as u64,
+            sampled: self.sampled as u64,
+            top_extensions: top(&self.extensions),
+            top_folders: top(&self.folders),
+        }
+    }
+}
+
+/// Whether `matches` out of `entries` searched entries make a broad search
+pub fn is_broad_count(matches: f64, entries: usize) -> bool {
+    entries > 0 && matches >= BROAD_MIN_MATCHES && matches >= entries as f64 * BROAD_MIN_FRACTION
+}
+
+/// Largest buckets first
+fn top(buckets: &HashMap<String, f64>) -> Vec<Breakdown> {
+    let mut sorted: Vec<(&String, &f64)> = buckets.iter().collect();
+    sorted.sort_by(|a, b| b.1.total_cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    sorted
+        .into_iter()
+        .take(SUMMARY_TOP)
+        .map(|(key, estimated)| Breakdown { key: key.clone(), estimated: estimated.round() as u64 })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_scales_sample_to_candidates() {
+        let files: Vec<FileEntry> = (0..1000)
+            .map(|i| FileEntry::test(i, &format!("{}\\file{}.{}", if i % 4 == 1 { "Users" } else { "Windows" }, i, if i % 2 == 0 { "dll" } else { "txt" })))
+            .collect();
+
+        // A cache of 200,000 entries where the sample says half of them match
+        let mut sample = DensitySample::new();
+        sample.add_source(200_000, 200_000, files.iter(), |f| (f.extension.as_deref() == Some("dll")).then_some(f.path.as_str()));
+
+        assert_eq!(sample.estimated_matches(), 100_000);
+        assert!(sample.is_broad());
+        let summary = sample.summary();
+        assert_eq!(summary.top_extensions.len(), 1);
+        assert_eq!(summary.top_folders[0].key, "Windows");
+        assert_eq!(summary.top_folders[0].estimated, 100_000);
+    }
+
+    #[test]
+    fn test_narrow_search_is_not_broad() {
+        let files: Vec<FileEntry> = (0..100).map(|i| FileEntry::test(i, &format!("src\\f{}.rs", i))).collect();
+
+        // Every candidate matches, but the extension index only yields 100 of them
+        let mut sample = DensitySample::new();
+        sample.add_source(1_000_000, 100, files.iter(), |f| Some(f.path.as_str()));
+        assert_eq!(sample.estimated_matches(), 100);
+        assert!(!sample.is_broad());
+    }
+}
@@ -0,0 +1,637 @@
+//! Directory indexes for containers and WSL
+//!
+//! Inside containers, dev-containers and WSL distros there is no NTFS volume to
+//! read, so the MFT cache can't be built. Instead a configured set of
+//! (bind-mounted) directories is walked once and kept current with a `notify`
+//! watcher. Each mount is served under a virtual drive letter, so every tool
+//! keeps working unchanged (`drive: "W"` searches the `/workspace` mount).
+//!
+//! Mounts come from `FASTSEARCH_CONTAINER_MOUNTS` (`"W=/workspace;D=/data"`) or,
+//! if that isn't set, from `mounts.json` in the data directory:
+//!
+//! ```json
+//! { "mounts": [ { "drive": "W", "path": "/workspace", "backend": "poll", "poll_interval_secs": 5 } ] }
+//! ```
+
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use log::{debug, error, info, warn};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::denied_paths::{DeniedPathStore, Denial};
+use super::mft_cache::{extension_of, FileEntry, MftCache};
+use super::reparse::reparse_tag;
+use super::usn_journal::{reason, UsnChange};
+
+/// Environment variable listing the mounts to index
+pub const MOUNTS_ENV: &str = "FASTSEARCH_CONTAINER_MOUNTS";
+
+/// File name of the mount config inside the data directory
+const MOUNTS_CONFIG_FILE: &str = "mounts.json";
+
+/// How often a paused worker checks whether maintenance has ended
+const MAINTENANCE_POLL: Duration = Duration::from_secs(1);
+
+/// How a mount is kept up to date after the initial walk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchBackend {
+    /// Native change notifications (inotify, FSEvents, ReadDirectoryChangesW)
+    #[default]
+    Notify,
+    /// Periodic rescans, for bind mounts and network shares that don't deliver events
+    Poll,
+    /// Index once and never update
+    None,
+}
+
+/// A directory served under a virtual drive letter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountConfig {
+    /// Virtual drive letter
+    pub drive: char,
+    /// Directory to index
+    pub path: PathBuf,
+    /// Change detection backend
+    #[serde(default)]
+    pub backend: WatchBackend,
+    /// Rescan interval for the poll backend
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MountsFile {
+    #[serde(default)]
+    mounts: Vec<MountConfig>,
+}
+
+impl MountConfig {
+    /// Location of the mount config file
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("FastSearchMCP")
+            .join(MOUNTS_CONFIG_FILE)
+    }
+
+    /// Walk a whole drive, for when its MFT can't be read
+    pub fn drive_root(drive: char) -> Self {
+        MountConfig {
+            drive,
+            path: PathBuf::from(format!("{}:\\", drive)),
+            backend: WatchBackend::Notify,
+            poll_interval_secs: default_poll_interval(),
+        }
+    }
+
+    /// Load the configured mounts; none configured means the normal MFT mode
+    pub fn load() -> Result<Vec<MountConfig>> {
+        let mounts = match std::env::var(MOUNTS_ENV) {
+            Ok(spec) if !spec.trim().is_empty() => Self::parse_list(&spec)?,
+            _ => {
+                let path = Self::default_path();
+                match fs::read_to_string(&path) {
+                    Ok(data) => serde_json::from_str::<MountsFile>(&data)
+                        .with_context(|| format!("Invalid mount config {}", path.display()))?
+                        .mounts,
+                    Err(_) => Vec::new(),
+                }
+            }
+        };
+        Self::validate(mounts)
+    }
+
+    /// Parse `"W=/workspace;D=poll:/data"`; the backend prefix is optional
+    pub fn parse_list(spec: &str) -> Result<Vec<MountConfig>> {
+        spec.split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (drive, rest) = entry.split_once('=')
+                    .with_context(|| format!("Mount '{}' must look like W=/path", entry))?;
+                let mut chars = drive.trim().chars();
+                let drive = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => bail!("Mount '{}' needs a single drive letter", entry),
+                };
+
+                let (backend, path) = match rest.split_once(':') {
+                    Some(("notify", path)) => (WatchBackend::Notify, path),
+                    Some(("poll", path)) => (WatchBackend::Poll, path),
+                    Some(("none", path)) => (WatchBackend::None, path),
+                    _ => (WatchBackend::default(), rest),
+                };
+                Ok(MountConfig {
+                    drive,
+                    path: PathBuf::from(path.trim()),
+                    backend,
+                    poll_interval_secs: default_poll_interval(),
+                })
+            })
+            .collect()
+    }
+
+    fn validate(mut mounts: Vec<MountConfig>) -> Result<Vec<MountConfig>> {
+        let mut drives = HashSet::new();
+        for mount in &mut mounts {
+            if !mount.drive.is_ascii_alphabetic() {
+                bail!("Mount drive '{}' is not a letter", mount.drive);
+            }
+            mount.drive = mount.drive.to_ascii_uppercase();
+            if !drives.insert(mount.drive) {
+                bail!("Drive {}: is mounted more than once", mount.drive);
+            }
+            if !mount.path.is_dir() {
+                bail!("Mount {} for drive {}: is not a directory", mount.path.display(), mount.drive);
+            }
+        }
+        Ok(mounts)
+    }
+}
+
+/// Keeps a detached `MftCache` in sync with a mounted directory
+pub struct DirectoryIndexer {
+    mount: MountConfig,
+    cache: Arc<MftCache>,
+    next_id: AtomicU64,
+    next_usn: AtomicI64,
+    watcher: Mutex<Option<Box<dyn Watcher + Send>>>,
+    denied: Arc<DeniedPathStore>,
+}
+
+impl DirectoryIndexer {
+    /// Create an indexer filling `cache` (which should be `MftCache::detached`)
+    pub fn new(mount: MountConfig, cache: Arc<MftCache>) -> Self {
+        Self {
+            mount,
+            cache,
+            next_id: AtomicU64::new(1),
+            next_usn: AtomicI64::new(1),
+            watcher: Mutex::new(None),
+            denied: Arc::new(DeniedPathStore::in_memory()),
+        }
+    }
+
+    /// Learn unreadable folders in `denied`, skipping them on later walks for a while
+    pub fn with_denied_paths(mut self, denied: Arc<DeniedPathStore>) -> Self {
+        self.denied = denied;
+        self
+    }
+
+    /// Walk the mount, then start watching it with the configured backend
+    pub fn start(mount: MountConfig, cache: Arc<MftCache>, denied: Arc<DeniedPathStore>) -> Result<Arc<Self>> {
+        let indexer = Arc::new(Self::new(mount, cache).with_denied_paths(denied));
+        indexer.index_all()?;
+
+        let (tx, rx) = mpsc::channel();
+        let watcher: Option<Box<dyn Watcher + Send>> = match indexer.mount.backend {
+            WatchBackend::Notify => Some(Box::new(
+                RecommendedWatcher::new(tx, notify::Config::default())
+                    .context("Failed to create file watcher")?,
+            )),
+            WatchBackend::Poll => Some(Box::new(
+                PollWatcher::new(
+                    tx,
+                    notify::Config::default()
+                        .with_poll_interval(Duration::from_secs(indexer.mount.poll_interval_secs.max(1))),
+                )
+                .context("Failed to create polling watcher")?,
+            )),
+            WatchBackend::None => None,
+        };
+
+        if let Some(mut watcher) = watcher {
+            watcher.watch(&indexer.mount.path, RecursiveMode::Recursive)
+                .with_context(|| format!("Failed to watch {}", indexer.mount.path.display()))?;
+            *indexer.watcher.lock() = Some(watcher);
+
+            let worker = Arc::clone(&indexer);
+            thread::Builder::new()
+                .name(format!("dir-index-{}", worker.mount.drive))
+                .spawn(move || {
+                    for event in rx {
+                        // Events queue up while the drive is in maintenance
+                        while worker.cache.maintenance().is_active() {
+                            thread::sleep(MAINTENANCE_POLL);
+                        }
+                        match event {
+                            Ok(event) => worker.apply_event(&event),
+                            Err(e) => error!("Watch error on drive {}: {}", worker.mount.drive, e),
+                        }
+                    }
+                })
+                .context("Failed to spawn directory watcher thread")?;
+        }
+
+        info!(
+            "Indexed {} entries from {} as drive {}: ({:?} backend)",
+            indexer.cache.get_files().len(), indexer.mount.path.display(),
+            indexer.mount.drive, indexer.mount.backend
+        );
+        Ok(indexer)
+    }
+
+    /// The mount this indexer serves
+    pub fn mount(&self) -> &MountConfig {
+        &self.mount
+    }
+
+    /// Walk the whole mount and replace the cache contents
+    pub fn index_all(&self) -> Result<()> {
+        let root = self.mount.path.clone();
+        fs::read_dir(&root).with_context(|| format!("Cannot read mount {}", root.display()))?;
+
+        let mut entries = Vec::new();
+        self.walk(&root, &mut entries);
+        self.cache.replace_entries(entries);
+        Ok(())
+    }
+
+    /// Apply one watcher event to the cache and run the change listeners
+    pub fn apply_event(&self, event: &Event) {
+        let mut changes = Vec::new();
+
+        match &event.kind {
+            EventKind::Create(_) => {
+                for path in &event.paths {
+                    self.index_path(path, reason::FILE_CREATE, &mut changes);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                self.remove(&event.paths[0]);
+                self.index_path(&event.paths[1], reason::RENAME_NEW_NAME, &mut changes);
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+                for path in &event.paths {
+                    self.remove(path);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                for path in &event.paths {
+                    self.index_path(path, reason::RENAME_NEW_NAME, &mut changes);
+                }
+            }
+            EventKind::Modify(_) | EventKind::Any => {
+                // Content/metadata change, or a rename reported without a direction
+                for path in &event.paths {
+                    if path.exists() {
+                        self.index_path(path, reason::DATA_OVERWRITE, &mut changes);
+                    } else {
+                        self.remove(path);
+                    }
+                }
+            }
+            EventKind::Access(_) | EventKind::Other => return,
+        }
+        self.cache.commit_changes();
+
+        if !changes.is_empty() {
+            debug!("Applied {} changes to drive {}:", changes.len(), self.mount.drive);
+            self.cache.notify_change_listeners(&changes);
+        }
+    }
+
+    /// Index a created or renamed path (and, for directories, everything below it)
+    fn index_path(&self, path: &Path, change_reason: u32, changes: &mut Vec<UsnChange>) {
+        let Some(relative) = self.relative_path(path) else {
+            return;
+        };
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) if !metadata.file_type().is_symlink() => metadata,
+            _ => return,
+        };
+
+        let existed = self.cache.get_path_index().contains_key(&relative);
+        let entry = self.entry_for(&relative, &metadata);
+        changes.push(self.change_for(&entry, if existed { reason::DATA_OVERWRITE } else { change_reason }));
+        self.cache.upsert_entry(entry);
+
+        if metadata.is_dir() && !existed {
+            let mut children = Vec::new();
+            self.walk(path, &mut children);
+            for child in children {
+                changes.push(self.change_for(&child, reason::FILE_CREATE));
+                self.cache.upsert_entry(child);
+            }
+        }
+    }
+
+    fn remove(&self, path: &Path) {
+        if let Some(relative) = self.relative_path(path) {
+            self.cache.remove_path(&relative);
+        }
+    }
+
+    /// Collect entries below `dir` without following symlinks
+    ///
+    /// Folders denied on several walks in a row are listed but not entered
+    /// until their retry time (see `DeniedPathStore`).
+    fn walk(&self, dir: &Path, entries: &mut Vec<FileEntry>) {
+        let drive = self.mount.drive;
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let relative = self.relative_path(&dir);
+            let read_dir = match fs::read_dir(&dir) {
+                Ok(read_dir) => {
+                    if let Some(relative) = &relative {
+                        self.denied.record_readable(drive, relative);
+                    }
+                    read_dir
+                }
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied && relative.is_some() => {
+                    match self.denied.record_denied(drive, relative.as_deref().unwrap_or_default()) {
+                        Denial::First => warn!("Skipping unreadable directory {}: {}", dir.display(), e),
+                        Denial::Repeated => debug!("Skipping unreadable directory {}: {}", dir.display(), e),
+                        Denial::Excluded(until) => info!(
+                            "Leaving {} out of walks for {}h: access denied on every recent walk",
+                            dir.display(),
+                            (until - chrono::Utc::now().timestamp()) / 3600
+                        ),
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Skipping unreadable directory {}: {}", dir.display(), e);
+                    continue;
+                }
+            };
+
+            for item in read_dir.flatten() {
+                let path = item.path();
+                let metadata = match fs::symlink_metadata(&path) {
+                    Ok(metadata) if !metadata.file_type().is_symlink() => metadata,
+                    _ => continue,
+                };
+                let Some(relative) = self.relative_path(&path) else {
+                    continue;
+                };
+
+                if metadata.is_dir() && !self.denied.should_skip(drive, &relative) {
+                    pending.push(path);
+                }
+                entries.push(self.entry_for(&relative, &metadata));
+            }
+        }
+        self.denied.flush();
+    }
+
+    /// Mount-relative path with NTFS-style separators, `None` for the mount root itself
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.mount.path).ok()?;
+        let parts: Vec<_> = relative.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("\\"))
+        }
+    }
+
+    fn entry_for(&self, relative: &str, metadata: &Metadata) -> FileEntry {
+        let id = self.cache.get_path_index().get(relative).copied()
+            .unwrap_or_else(|| self.next_id.fetch_add(1, Ordering::Relaxed));
+        let name = relative.rsplit('\\').next().unwrap_or(relative).to_string();
+        let is_directory = metadata.is_dir();
+        let attributes = metadata_attributes(&name, metadata);
+
+        FileEntry {
+            id,
+            extension: if is_directory { None } else { extension_of(&name) },
+            name,
+            path: relative.to_string(),
+            size: if is_directory { 0 } else { metadata.len() },
+            is_directory,
+            created: metadata.created().unwrap_or(UNIX_EPOCH),
+            modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+            accessed: metadata.accessed().unwrap_or(UNIX_EPOCH),
+            attributes,
+            link_count: metadata_link_count(metadata),
+            reparse_tag: metadata_reparse_tag(metadata),
+        }
+    }
+
+    /// Synthesize a journal-style change so listeners (watched searches) see it
+    fn change_for(&self, entry: &FileEntry, change_reason: u32) -> UsnChange {
+        let parent_id = entry.path.rsplit_once('\\')
+            .and_then(|(parent, _)| self.cache.get_path_index().get(parent).copied())
+            .unwrap_or(0);
+
+        UsnChange {
+            file_id: entry.id,
+            parent_id,
+            usn: self.next_usn.fetch_add(1, Ordering::Relaxed),
+            reason: change_reason,
+            attributes: entry.attributes,
+            name: entry.name.clone(),
+        }
+    }
+}
+
+/// NTFS-style attribute flags for a file outside NTFS
+///
+/// Windows reports the real flags. Elsewhere dotfiles count as hidden and
+/// read-only comes from the permission bits and symlinks are reparse points;
+/// the other flags have no equivalent.
+pub fn metadata_attributes(name: &str, metadata: &Metadata) -> u32 {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        let _ = name;
+        metadata.file_attributes()
+    }
+    #[cfg(not(windows))]
+    {
+        use super::mft_cache::file_attribute;
+        let mut attributes = 0;
+        if metadata.is_dir() {
+            attributes |= file_attribute::DIRECTORY;
+        }
+        if name.starts_with('.') {
+            attributes |= file_attribute::HIDDEN;
+        }
+        if metadata.permissions().readonly() {
+            attributes |= file_attribute::READONLY;
+        }
+        if metadata.file_type().is_symlink() {
+            attributes |= file_attribute::REPARSE_POINT;
+        }
+        attributes
+    }
+}
+
+/// Hard links of a file; directories (whose link count means something else) get 1
+fn metadata_link_count(metadata: &Metadata) -> u16 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if !metadata.is_dir() {
+            return metadata.nlink().clamp(1, u64::from(u16::MAX)) as u16;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    1
+}
+
+/// Reparse tag for an entry read with `symlink_metadata`
+///
+/// Only symbolic links (and, on Windows, junctions, which std reports the same
+/// way) can be told apart; std doesn't expose other reparse tags.
+pub fn metadata_reparse_tag(metadata: &Metadata) -> u32 {
+    if metadata.file_type().is_symlink() {
+        reparse_tag::SYMLINK
+    } else {
+        0
+    }
+}
+
+/// Device and inode of a file, which all of its hard links share
+pub fn file_identity(path: &Path) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = fs::symlink_metadata(path).ok()?;
+        Some((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// One-line status of a mounted directory index
+pub fn describe_mount(mount: &MountConfig, cache: &MftCache) -> String {
+    let age = SystemTime::now()
+        .duration_since(cache.last_update())
+        .unwrap_or_default();
+    format!(
+        "{}: -> {} ({} entries, {:?} backend, updated {}s ago)",
+        mount.drive, mount.path.display(), cache.get_files().len(), mount.backend, age.as_secs()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind};
+    use tempfile::tempdir;
+
+    fn mount_for(path: &Path) -> MountConfig {
+        MountConfig {
+            drive: 'W',
+            path: path.to_path_buf(),
+            backend: WatchBackend::None,
+            poll_interval_secs: 5,
+        }
+    }
+
+    #[test]
+    fn test_index_all_walks_mount() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::write(dir.path().join("src/nested/Main.RS"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("README.md"), "hello").unwrap();
+        fs::write(dir.path().join(".env"), "KEY=1").unwrap();
+
+        let cache = Arc::new(MftCache::detached('w'));
+        let indexer = DirectoryIndexer::new(mount_for(dir.path()), Arc::clone(&cache));
+        indexer.index_all().unwrap();
+
+        let paths = cache.get_path_index();
+        assert_eq!(paths.len(), 5);
+        let files = cache.get_files();
+        let main = &files[&paths["src\\nested\\Main.RS"]];
+        assert_eq!(main.name, "Main.RS");
+        assert_eq!(main.extension.as_deref(), Some("rs"));
+        assert_eq!(main.size, 12);
+        assert!(files[&paths["src"]].is_directory);
+        assert_eq!(cache.drive_letter(), 'W');
+
+        #[cfg(not(windows))]
+        {
+            use super::super::mft_cache::file_attribute;
+            assert!(files[&paths[".env"]].has_attributes(file_attribute::HIDDEN));
+            assert!(!main.has_attributes(file_attribute::HIDDEN));
+            assert!(files[&paths["src"]].has_attributes(file_attribute::DIRECTORY));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hard_links_share_identity() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("original.txt"), "data").unwrap();
+        fs::hard_link(dir.path().join("original.txt"), dir.path().join("link.txt")).unwrap();
+        fs::write(dir.path().join("copy.txt"), "data").unwrap();
+
+        let cache = Arc::new(MftCache::detached('W'));
+        DirectoryIndexer::new(mount_for(dir.path()), Arc::clone(&cache)).index_all().unwrap();
+
+        let files = cache.get_files();
+        let paths = cache.get_path_index();
+        assert_eq!(files[&paths["link.txt"]].link_count, 2);
+        assert_eq!(files[&paths["copy.txt"]].link_count, 1);
+        let identity = |name: &str| file_identity(&dir.path().join(name));
+        assert_eq!(identity("original.txt"), identity("link.txt"));
+        assert_ne!(identity("original.txt"), identity("copy.txt"));
+    }
+
+    #[test]
+    fn test_events_update_cache() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs")).unwrap();
+
+        let cache = Arc::new(MftCache::detached('W'));
+        let indexer = DirectoryIndexer::new(mount_for(dir.path()), Arc::clone(&cache));
+        indexer.index_all().unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        cache.add_change_listener(Arc::new(move |_, changes| {
+            sink.lock().extend(changes.iter().map(|c| (c.name.clone(), c.parent_id, c.is_new_name())));
+        }));
+
+        let log = dir.path().join("logs/app.log");
+        fs::write(&log, "boot").unwrap();
+        indexer.apply_event(&Event::new(EventKind::Create(CreateKind::File)).add_path(log.clone()));
+
+        let logs_id = cache.get_path_index()["logs"];
+        assert!(cache.get_path_index().contains_key("logs\\app.log"));
+        assert_eq!(*seen.lock(), vec![("app.log".to_string(), logs_id, true)]);
+
+        fs::remove_dir_all(dir.path().join("logs")).unwrap();
+        indexer.apply_event(&Event::new(EventKind::Remove(RemoveKind::Folder)).add_path(dir.path().join("logs")));
+        assert!(cache.get_path_index().is_empty());
+        assert!(cache.get_name_index().is_empty());
+    }
+
+    #[test]
+    fn test_parse_mount_list() {
+        let mounts = MountConfig::parse_list("W=/workspace; d=poll:/data").unwrap();
+        assert_eq!(mounts[0].drive, 'W');
+        assert_eq!(mounts[0].backend, WatchBackend::Notify);
+        assert_eq!(mounts[1].path, PathBuf::from("/data"));
+        assert_eq!(mounts[1].backend, WatchBackend::Poll);
+
+        assert!(MountConfig::parse_list("/workspace").is_err());
+        assert!(MountConfig::parse_list("WX=/workspace").is_err());
+    }
+}
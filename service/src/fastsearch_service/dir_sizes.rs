@@ -0,0 +1,143 @@
+//! Folder sizes for `directory_sizes`, like `du`
+//!
+//! The MFT cache already holds the size and path of every file, so the size
+//! of a folder is the sum of the files below it and nothing has to be walked.
+//! Each file is added to every folder above it, from the root down to
+//! `max_depth` levels below it; deeper folders count toward their ancestor
+//! at that depth.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::exclude::ExcludePatterns;
+use super::mft_cache::FileEntry;
+use super::reports::scoped_remainder;
+
+/// Folder levels below the root reported when `max_depth` isn't given
+pub const DEFAULT_SIZE_DEPTH: usize = 1;
+
+/// Deepest `max_depth` accepted; every level adds a folder per file to sum
+pub const MAX_SIZE_DEPTH: usize = 8;
+
+/// Total size of the files below a folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirSize {
+    /// Path relative to the drive root
+    pub path: String,
+    /// Levels below the root (1 for its direct subfolders)
+    pub depth: usize,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+/// Sizes below a root folder
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirSizes {
+    /// Everything below the root
+    pub total_bytes: u64,
+    pub total_files: u64,
+    /// Files directly in the root, which belong to none of its subfolders
+    pub own_bytes: u64,
+    pub own_files: u64,
+    /// Folders down to the requested depth, largest first
+    pub dirs: Vec<DirSize>,
+}
+
+/// Add up file sizes for the folders below `root` (relative to the drive root; empty for the whole drive)
+///
+/// Excluded paths aren't counted, and neither is anything below an excluded folder.
+pub fn directory_sizes<'a>(
+    files: impl Iterator<Item = &'a FileEntry>,
+    root: &str,
+    max_depth: usize,
+    exclude: &ExcludePatterns,
+) -> DirSizes {
+    let mut sizes = DirSizes::default();
+    // Keyed by lowercase path; NTFS paths ignore case
+    let mut dirs: HashMap<String, DirSize> = HashMap::new();
+
+    for file in files.filter(|file| !file.is_directory && !exclude.excludes(&file.path)) {
+        let Some(remainder) = scoped_remainder(&file.path, root) else {
+            continue;
+        };
+        sizes.total_bytes += file.size;
+        sizes.total_files += 1;
+
+        let folders = remainder.matches('\\').count();
+        if folders == 0 {
+            sizes.own_bytes += file.size;
+            sizes.own_files += 1;
+            continue;
+        }
+        // Each ancestor folder ends at one of the separators of the path
+        let offset = file.path.len() - remainder.len();
+        let ends = remainder.match_indices('\\').map(|(i, _)| offset + i);
+        for (depth, end) in ends.take(max_depth).enumerate() {
+            let path = &file.path[..end];
+            let dir = dirs.entry(path.to_lowercase()).or_insert_with(|| DirSize {
+                path: path.to_string(),
+                depth: depth + 1,
+                bytes: 0,
+                files: 0,
+            });
+            dir.bytes += file.size;
+            dir.files += 1;
+        }
+    }
+
+    sizes.dirs = dirs.into_values().collect();
+    sizes.dirs.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: u64, path: &str, size: u64) -> FileEntry {
+        FileEntry { size, ..FileEntry::test(id, path) }
+    }
+
+    fn summary(sizes: &DirSizes) -> Vec<(&str, usize, u64, u64)> {
+        sizes.dirs.iter().map(|d| (d.path.as_str(), d.depth, d.bytes, d.files)).collect()
+    }
+
+    #[test]
+    fn test_sizes_roll_up_to_max_depth() {
+        let files = [
+            file(1, "Users\\a\\Videos\\big.iso", 700),
+            file(2, "Users\\a\\small.txt", 10),
+            file(3, "Users\\b\\notes.md", 50),
+            file(4, "Users\\desktop.ini", 1),
+            file(5, "Windows\\explorer.exe", 5000),
+        ];
+        let none = ExcludePatterns::default();
+
+        let sizes = directory_sizes(files.iter(), "users", 1, &none);
+        assert_eq!(summary(&sizes), vec![("Users\\a", 1, 710, 2), ("Users\\b", 1, 50, 1)]);
+        assert_eq!((sizes.total_bytes, sizes.total_files), (761, 4));
+        assert_eq!((sizes.own_bytes, sizes.own_files), (1, 1));
+
+        let sizes = directory_sizes(files.iter(), "", 2, &none);
+        assert_eq!(summary(&sizes), vec![
+            ("Windows", 1, 5000, 1),
+            ("Users", 1, 761, 4),
+            ("Users\\a", 2, 710, 2),
+            ("Users\\b", 2, 50, 1),
+        ]);
+    }
+
+    #[test]
+    fn test_excluded_folders_are_not_counted() {
+        let files = [
+            file(1, "src\\app\\node_modules\\x\\index.js", 900),
+            file(2, "src\\app\\main.js", 100),
+        ];
+        let exclude = ExcludePatterns::new(&["node_modules/**"]).unwrap();
+
+        let sizes = directory_sizes(files.iter(), "SRC", 3, &exclude);
+        assert_eq!(summary(&sizes), vec![("src\\app", 1, 100, 1)]);
+        assert_eq!(sizes.total_bytes, 100);
+    }
+}
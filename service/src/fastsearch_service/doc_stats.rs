@@ -0,0 +1,309 @@
+//! Page and word counts of documents for `fast_search`
+//!
+//! With `include_doc_stats`, document results carry their page and word
+//! counts, and `min_pages`/`max_pages`/`min_words`/`max_words` filter on them
+//! ("documents over 50 pages": `doc_type: "pdf"`, `min_pages: 50`). Like image
+//! and media information, counts are read in the verify stage and cached per
+//! file record.
+//!
+//! Where the counts come from depends on the format:
+//! - PDF: pages from the page tree, words from the extracted text
+//! - Word and PowerPoint (`.docx`, `.pptx`): the statistics Office saves in
+//!   `docProps/app.xml` (slides count as pages)
+//! - OpenDocument (`.odt`, `.ods`, `.odp`): the statistics in `meta.xml`
+//! - Plain text (`.txt`, `.md`, `.tex`, `.log`, ...): words, counted from the
+//!   whole file; text has no pages
+//!
+//! A count a format doesn't have never passes a filter on it.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::metadata_cache::MetadataCache;
+use super::mft_cache::{disk_path, DriveRoot, FileEntry};
+
+/// Extensions counted as plain text
+const TEXT_EXTENSIONS: &[&str] = &["txt", "md", "markdown", "rst", "tex", "log", "csv", "tsv"];
+
+/// Extensions counted from saved statistics or the page tree
+const DOCUMENT_EXTENSIONS: &[&str] = &["pdf", "docx", "docm", "pptx", "pptm", "odt", "ods", "odp"];
+
+/// Text files larger than this aren't counted
+const MAX_TEXT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Page and word count of a document, as far as its format records them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DocStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub words: Option<u64>,
+}
+
+impl DocStats {
+    /// `52 pages, 18,400 words`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(pages) = self.pages {
+            parts.push(format!("{} page{}", pages, if pages == 1 { "" } else { "s" }));
+        }
+        if let Some(words) = self.words {
+            parts.push(format!("{} word{}", group_thousands(words), if words == 1 { "" } else { "s" }));
+        }
+        parts.join(", ")
+    }
+}
+
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Whether a cache entry is a document whose counts can be read
+pub fn has_doc_stats(file: &FileEntry) -> bool {
+    !file.is_directory && file.extension.as_deref()
+        .map_or(false, |ext| TEXT_EXTENSIONS.contains(&ext) || DOCUMENT_EXTENSIONS.contains(&ext))
+}
+
+/// Read the page and word count of a document
+pub fn read_doc_stats(path: &Path) -> Option<DocStats> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "pdf" => read_pdf(path),
+        "docx" | "docm" | "pptx" | "pptm" => read_office(path),
+        "odt" | "ods" | "odp" => read_opendocument(path),
+        ext if TEXT_EXTENSIONS.contains(&ext) => read_text(path),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "doc-stats")]
+fn read_pdf(path: &Path) -> Option<DocStats> {
+    let document = lopdf::Document::load(path).ok()?;
+    let pages: Vec<u32> = document.get_pages().keys().copied().collect();
+    // Scanned documents have pages but no text to count
+    let words = document.extract_text(&pages).ok().map(|text| count_words(text.as_bytes()));
+    Some(DocStats { pages: Some(pages.len() as u64), words })
+}
+
+/// Built without the `doc-stats` feature: PDFs aren't read
+#[cfg(not(feature = "doc-stats"))]
+fn read_pdf(_path: &Path) -> Option<DocStats> {
+    None
+}
+
+/// A file inside a ZIP container, as text
+#[cfg(feature = "doc-stats")]
+fn read_zip_entry(path: &Path, name: &str) -> Option<String> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path).ok()?)).ok()?;
+    let mut entry = archive.by_name(name).ok()?;
+    let mut text = String::new();
+    entry.read_to_string(&mut text).ok()?;
+    Some(text)
+}
+
+/// Built without the `doc-stats` feature: ZIP containers aren't read
+#[cfg(not(feature = "doc-stats"))]
+fn read_zip_entry(_path: &Path, _name: &str) -> Option<String> {
+    None
+}
+
+/// Office Open XML: `<Pages>`/`<Slides>` and `<Words>` of the extended properties
+fn read_office(path: &Path) -> Option<DocStats> {
+    let app = read_zip_entry(path, "docProps/app.xml")?;
+    Some(DocStats {
+        pages: element_number(&app, "Pages").or_else(|| element_number(&app, "Slides")),
+        words: element_number(&app, "Words"),
+    })
+}
+
+/// OpenDocument: `meta:page-count` and `meta:word-count` of the document statistics
+fn read_opendocument(path: &Path) -> Option<DocStats> {
+    let meta = read_zip_entry(path, "meta.xml")?;
+    Some(DocStats {
+        pages: attribute_number(&meta, "meta:page-count"),
+        words: attribute_number(&meta, "meta:word-count"),
+    })
+}
+
+fn read_text(path: &Path) -> Option<DocStats> {
+    let file = File::open(path).ok()?;
+    if file.metadata().ok()?.len() > MAX_TEXT_BYTES {
+        return None;
+    }
+    let mut reader = BufReader::new(file);
+    let mut words = 0;
+    let mut in_word = false;
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        for &byte in &buffer[..read] {
+            let space = byte.is_ascii_whitespace();
+            if !space && !in_word {
+                words += 1;
+            }
+            in_word = !space;
+        }
+    }
+    Some(DocStats { pages: None, words: Some(words) })
+}
+
+/// Runs of non-whitespace
+#[cfg_attr(not(feature = "doc-stats"), allow(dead_code))]
+fn count_words(text: &[u8]) -> u64 {
+    text.split(|byte| byte.is_ascii_whitespace())
+        .filter(|word| !word.is_empty())
+        .count() as u64
+}
+
+/// The number in `<name>123</name>`
+fn element_number(xml: &str, name: &str) -> Option<u64> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = start + xml[start..].find('<')?;
+    xml[start..end].trim().parse().ok()
+}
+
+/// The number in `name="123"`
+fn attribute_number(xml: &str, name: &str) -> Option<u64> {
+    let start = xml.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = start + xml[start..].find('"')?;
+    xml[start..end].parse().ok()
+}
+
+/// Counts read so far, by drive and file record
+#[derive(Debug, Default)]
+pub struct DocStatsCache {
+    cache: MetadataCache<DocStats>,
+}
+
+impl DocStatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts of a cache entry, read from below `root` unless known for this version of the file
+    pub fn get(&self, drive: char, root: &Path, file: &FileEntry) -> Option<DocStats> {
+        if !has_doc_stats(file) {
+            return None;
+        }
+        self.cache.get_or_read(drive, file, || read_doc_stats(&disk_path(root, &file.path)))
+    }
+
+    /// Number of files remembered
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// The `min_pages`, `max_pages`, `min_words` and `max_words` arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DocStatsFilter {
+    pub min_pages: Option<u64>,
+    pub max_pages: Option<u64>,
+    pub min_words: Option<u64>,
+    pub max_words: Option<u64>,
+}
+
+impl DocStatsFilter {
+    /// Parse the count filters; `None` if none is given
+    pub fn from_args(args: &Value) -> Result<Option<Self>> {
+        let count = |key: &str| match &args[key] {
+            Value::Null => Ok(None),
+            value => value.as_u64()
+                .map(Some)
+                .ok_or_else(|| anyhow!("'{}' must be a non-negative whole number", key)),
+        };
+        let filter = Self {
+            min_pages: count("min_pages")?,
+            max_pages: count("max_pages")?,
+            min_words: count("min_words")?,
+            max_words: count("max_words")?,
+        };
+        Ok((filter != Self::default()).then_some(filter))
+    }
+
+    /// Whether a document passes; a count it doesn't have fails any bound on it
+    pub fn matches(&self, stats: Option<&DocStats>) -> bool {
+        let stats = stats.copied().unwrap_or_default();
+        let within = |count: Option<u64>, min: Option<u64>, max: Option<u64>| {
+            if min.is_none() && max.is_none() {
+                return true;
+            }
+            count.map_or(false, |count| min.map_or(true, |min| count >= min) && max.map_or(true, |max| count <= max))
+        };
+        within(stats.pages, self.min_pages, self.max_pages) && within(stats.words, self.min_words, self.max_words)
+    }
+}
+
+/// Count check for the matches on one drive
+pub struct DocStatsCheck<'a> {
+    filter: DocStatsFilter,
+    cache: &'a DocStatsCache,
+    disk: DriveRoot,
+}
+
+impl<'a> DocStatsCheck<'a> {
+    pub fn new(filter: DocStatsFilter, cache: &'a DocStatsCache, disk: DriveRoot) -> Self {
+        Self { filter, cache, disk }
+    }
+
+    pub fn accepts(&self, file: &FileEntry) -> bool {
+        has_doc_stats(file) && self.filter.matches(self.cache.get(self.disk.drive, &self.disk.root, file).as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_saved_statistics_and_text() {
+        let app = "<Properties><Template>Normal.dotm</Template><Pages>52</Pages><Words>18400</Words></Properties>";
+        assert_eq!(element_number(app, "Pages"), Some(52));
+        assert_eq!(element_number(app, "Slides"), None);
+        let meta = r#"<meta:document-statistic meta:page-count="3" meta:word-count="812"/>"#;
+        assert_eq!(attribute_number(meta, "meta:page-count"), Some(3));
+        assert_eq!(attribute_number(meta, "meta:word-count"), Some(812));
+
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.md"), "# Title\n\nTwo  words\tand\r\nmore ").unwrap();
+        assert_eq!(read_doc_stats(&dir.path().join("notes.md")), Some(DocStats { pages: None, words: Some(6) }));
+        assert_eq!(count_words(b"  "), 0);
+    }
+
+    #[test]
+    fn test_doc_stats_filter() {
+        assert_eq!(DocStatsFilter::from_args(&json!({"doc_type": "pdf"})).unwrap(), None);
+        assert!(DocStatsFilter::from_args(&json!({"min_pages": "fifty"})).is_err());
+
+        let filter = DocStatsFilter::from_args(&json!({"min_pages": 50})).unwrap().unwrap();
+        let report = DocStats { pages: Some(52), words: Some(18400) };
+        assert!(filter.matches(Some(&report)));
+        assert!(!filter.matches(Some(&DocStats { pages: Some(12), ..report })));
+        // Text has no pages to compare
+        assert!(!filter.matches(Some(&DocStats { pages: None, words: Some(90_000) })));
+        assert!(!filter.matches(None));
+        assert_eq!(report.describe(), "52 pages, 18,400 words");
+    }
+}
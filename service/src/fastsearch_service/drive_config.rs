@@ -0,0 +1,195 @@
+//! Which drives the service indexes
+//!
+//! Without configuration, a drive's cache is built the first time a search
+//! asks for it. The drive config lists drives whose caches are built when the
+//! service starts, so the first search doesn't pay for reading the MFT, and
+//! drives that are never indexed at all (backup volumes, removable disks).
+//! It is kept in `drives.json` and edited at runtime with `configure_drives`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use log::{error, info};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::json_store::{data_dir, JsonStore};
+
+/// File name of the drive config inside the data directory
+const DRIVES_CONFIG_FILE: &str = "drives.json";
+
+/// Drives built at startup and drives never indexed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriveSelection {
+    /// Drives whose caches are built when the service starts
+    #[serde(default)]
+    pub preload: Vec<char>,
+    /// Drives that are never indexed or searched
+    #[serde(default)]
+    pub excluded: Vec<char>,
+}
+
+impl DriveSelection {
+    /// Check the drive letters and put them in a canonical form (upper case, sorted, unique)
+    pub fn normalized(mut self) -> Result<Self> {
+        for list in [&mut self.preload, &mut self.excluded] {
+            for drive in list.iter_mut() {
+                if !drive.is_ascii_alphabetic() {
+                    bail!("'{}' is not a drive letter", drive);
+                }
+                *drive = drive.to_ascii_uppercase();
+            }
+            list.sort_unstable();
+            list.dedup();
+        }
+        if let Some(drive) = self.preload.iter().find(|d| self.excluded.contains(d)) {
+            bail!("Drive {}: can't be both preloaded and excluded", drive);
+        }
+        Ok(self)
+    }
+
+    /// Whether a drive must not be indexed
+    pub fn is_excluded(&self, drive: char) -> bool {
+        self.excluded.contains(&drive.to_ascii_uppercase())
+    }
+
+    /// Both selections' drives, with `other` deciding drives the two disagree on
+    pub fn merged(&self, other: &DriveSelection) -> DriveSelection {
+        let keep = |own: &[char], theirs: &[char], conflicting: &[char]| -> Vec<char> {
+            own.iter()
+                .filter(|d| !conflicting.iter().any(|c| c.eq_ignore_ascii_case(d)))
+                .chain(theirs)
+                .copied()
+                .collect()
+        };
+        DriveSelection {
+            preload: keep(&self.preload, &other.preload, &other.excluded),
+            excluded: keep(&self.excluded, &other.excluded, &other.preload),
+        }
+    }
+}
+
+/// Parse a list of drive letters ("D", "E:") from a tool argument
+pub fn parse_drive_list(value: &Value, name: &str) -> Result<Vec<char>> {
+    let Some(items) = value.as_array() else {
+        bail!("'{}' must be an array of drive letters", name);
+    };
+    items.iter()
+        .map(|item| {
+            let letter = item.as_str().map(|s| s.trim().trim_end_matches(['\\', '/']).trim_end_matches(':'));
+            match letter.map(|s| (s.len(), s.chars().next())) {
+                Some((1, Some(drive))) => Ok(drive),
+                _ => bail!("'{}' contains {}, which is not a drive letter", name, item),
+            }
+        })
+        .collect()
+}
+
+/// Persistent drive selection
+#[derive(Debug)]
+pub struct DriveConfig {
+    file: JsonStore<DriveSelection>,
+    selection: RwLock<DriveSelection>,
+}
+
+impl DriveConfig {
+    /// Default location: `%LOCALAPPDATA%\FastSearchMCP\drives.json`
+    pub fn default_path() -> PathBuf {
+        data_dir().join(DRIVES_CONFIG_FILE)
+    }
+
+    /// Load the drive selection, indexing drives on demand if the file doesn't exist or can't be read
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let file: JsonStore<DriveSelection> = JsonStore::at(&path, "drive config");
+        let selection = file.load()
+            .map(|selection| selection.normalized().unwrap_or_else(|e| {
+                error!("Ignoring invalid drive config {}: {}", path.as_ref().display(), e);
+                DriveSelection::default()
+            }))
+            .unwrap_or_default();
+
+        info!("Drive config: preload {:?}, excluded {:?}", selection.preload, selection.excluded);
+        Self {
+            file,
+            ..Self::with_selection(selection)
+        }
+    }
+
+    /// Create a config for the given selection that is never written to disk
+    pub fn with_selection(selection: DriveSelection) -> Self {
+        Self {
+            file: JsonStore::in_memory("drive config"),
+            selection: RwLock::new(selection),
+        }
+    }
+
+    /// The current selection
+    pub fn selection(&self) -> DriveSelection {
+        self.selection.read().clone()
+    }
+
+    /// Whether a drive must not be indexed
+    pub fn is_excluded(&self, drive: char) -> bool {
+        self.selection.read().is_excluded(drive)
+    }
+
+    /// Replace the selection and write it back to the config file
+    pub fn set_selection(&self, selection: DriveSelection) -> Result<DriveSelection> {
+        let selection = selection.normalized()?;
+        *self.selection.write() = selection.clone();
+        self.persist()?;
+        Ok(selection)
+    }
+
+    /// Write the drive config to disk (atomically, via a temporary file)
+    fn persist(&self) -> Result<()> {
+        self.file.save(&self.selection())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_selection_normalized() {
+        let selection = DriveSelection { preload: vec!['d', 'C', 'D'], excluded: vec!['z'] }.normalized().unwrap();
+        assert_eq!(selection.preload, vec!['C', 'D']);
+        assert!(selection.is_excluded('Z'));
+        assert!(selection.is_excluded('z'));
+
+        assert!(DriveSelection { preload: vec!['E'], excluded: vec!['e'] }.normalized().is_err());
+        assert!(DriveSelection { preload: vec!['1'], excluded: vec![] }.normalized().is_err());
+    }
+
+    #[test]
+    fn test_selection_merged() {
+        let current = DriveSelection { preload: vec!['C', 'D'], excluded: vec!['E'] };
+        let imported = DriveSelection { preload: vec!['e'], excluded: vec!['d', 'F'] };
+        let merged = current.merged(&imported).normalized().unwrap();
+        assert_eq!(merged, DriveSelection { preload: vec!['C', 'E'], excluded: vec!['D', 'F'] });
+    }
+
+    #[test]
+    fn test_parse_drive_list() {
+        assert_eq!(parse_drive_list(&json!(["c", "D:", "E:\\"]), "preload").unwrap(), vec!['c', 'D', 'E']);
+        assert!(parse_drive_list(&json!(["CD"]), "preload").is_err());
+        assert!(parse_drive_list(&json!("C"), "preload").is_err());
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DRIVES_CONFIG_FILE);
+
+        let config = DriveConfig::load(&path);
+        assert_eq!(config.selection(), DriveSelection::default());
+        config.set_selection(DriveSelection { preload: vec!['c'], excluded: vec!['X'] }).unwrap();
+
+        let reloaded = DriveConfig::load(&path);
+        assert_eq!(reloaded.selection(), DriveSelection { preload: vec!['C'], excluded: vec!['X'] });
+        assert!(reloaded.is_excluded('x'));
+    }
+}
@@ -0,0 +1,587 @@
+//! Duplicate file detection for `find_duplicates`
+//!
+//! Files can only be duplicates if they have the same size, which the MFT
+//! cache already knows, so most files are ruled out without being opened.
+//! The rest are hashed in two rounds: the first 64 KiB of every candidate,
+//! then the whole of the files whose beginnings still agree. Files are grouped
+//! by the XXH3 (128-bit) hash of their contents, read in 1 MiB chunks.
+//!
+//! Reads are scheduled per physical disk. Two readers on a spinning disk make
+//! its head seek back and forth between files and are slower together than
+//! one alone, so such disks are read one file at a time, in path order; other
+//! disks get `threads` readers each, and separate disks are read at the same
+//! time. A cancelled scan stops between chunks and keeps the groups it
+//! confirmed so far.
+
+use std::collections::HashMap;
+#[cfg(feature = "hashing")]
+use std::fs::File;
+use std::io;
+#[cfg(feature = "hashing")]
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+#[cfg(feature = "hashing")]
+use xxhash_rust::xxh3::Xxh3;
+
+use super::cancellation::CancelToken;
+use super::mft_cache::FileEntry;
+
+/// Bytes hashed by the first round
+const PREFIX_LEN: u64 = 64 * 1024;
+
+/// Bytes read from a file at once
+const CHUNK_LEN: usize = 1024 * 1024;
+
+/// How often progress is reported while hashing
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Readers per solid-state disk when not given
+pub const DEFAULT_HASH_THREADS: usize = 4;
+
+/// A physical disk, as far as scheduling reads goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disk {
+    /// Identifies the disk (e.g. `PhysicalDrive0`); volumes on the same disk share it
+    pub id: String,
+    /// Whether moving between files costs a seek, as on spinning disks
+    pub seek_penalty: bool,
+}
+
+impl Disk {
+    /// A disk nothing is known about, read like a solid-state disk
+    pub fn unknown(id: &str) -> Self {
+        Self { id: id.to_string(), seek_penalty: false }
+    }
+
+    /// Files read from the disk at once
+    pub fn readers(&self, threads: usize) -> usize {
+        if self.seek_penalty {
+            1
+        } else {
+            threads.max(1)
+        }
+    }
+}
+
+/// A file that may have copies, with where to read it
+#[derive(Debug, Clone)]
+pub struct DuplicateCandidate {
+    /// Path as reported to the caller
+    pub shown: String,
+    /// Path to read the file at
+    pub disk_path: PathBuf,
+    pub size: u64,
+    /// Disk the file is on, whose read queue it joins
+    pub disk: Arc<Disk>,
+}
+
+/// Files with identical contents
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    /// XXH3-128 hash of the contents, in hex
+    pub hash: String,
+    /// Paths of the copies, sorted
+    pub paths: Vec<String>,
+}
+
+impl DuplicateGroup {
+    /// Space that deleting all but one copy would free
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Outcome of a duplicate scan
+#[derive(Debug, Default)]
+pub struct DuplicateScan {
+    /// Groups of identical files, most wasted space first
+    pub groups: Vec<DuplicateGroup>,
+    /// Files sharing their size with another file, which had to be hashed
+    pub candidates: usize,
+    /// Bytes read while hashing
+    pub bytes_hashed: u64,
+    /// Candidates that couldn't be read (locked, deleted, no access)
+    pub unreadable: usize,
+    /// Whether the scan was cancelled before every candidate was hashed
+    pub cancelled: bool,
+}
+
+/// How far a duplicate scan has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HashProgress {
+    /// 1 while hashing the beginnings of files, 2 while hashing whole files
+    pub round: u8,
+    /// Files of this round hashed (or found unreadable)
+    pub files_done: u64,
+    /// Files to hash in this round
+    pub files_total: u64,
+    /// Bytes read so far, in both rounds
+    pub bytes_hashed: u64,
+}
+
+/// Receives progress now and then while a scan is hashing, on the calling thread
+pub type HashProgressSink<'a> = &'a dyn Fn(HashProgress);
+
+/// Files of the same size as at least one other file, grouped by size
+///
+/// Empty files and reparse points are left out: the former are all alike,
+/// and reading a cloud placeholder would download it.
+pub fn size_groups<'a>(files: impl Iterator<Item = &'a FileEntry>) -> Vec<Vec<&'a FileEntry>> {
+    let mut by_size: HashMap<u64, Vec<&FileEntry>> = HashMap::new();
+    for file in files.filter(|f| !f.is_directory && f.size > 0 && f.reparse_tag == 0) {
+        by_size.entry(file.size).or_default().push(file);
+    }
+    by_size.into_values().filter(|group| group.len() > 1).collect()
+}
+
+/// Counters shared by the hashing threads
+#[derive(Default)]
+struct HashCounters {
+    bytes_hashed: AtomicU64,
+    unreadable: AtomicU64,
+    files_done: AtomicU64,
+}
+
+/// Hash the candidates of each size group and group identical files
+///
+/// `threads` is the number of readers per solid-state disk.
+pub fn find_duplicates(
+    size_groups: Vec<Vec<DuplicateCandidate>>,
+    threads: usize,
+    cancel: &CancelToken,
+    progress: Option<HashProgressSink<'_>>,
+) -> Result<DuplicateScan> {
+    let counters = HashCounters::default();
+    let candidates = size_groups.iter().map(Vec::len).sum();
+    let mut groups = Vec::new();
+
+    // Same beginning first; files no longer than that are already fully hashed
+    let jobs: Vec<&DuplicateCandidate> = size_groups.iter().flatten().collect();
+    let mut prefix_hashes = hash_round(&jobs, PREFIX_LEN, 1, threads, cancel, &counters, progress)?.into_iter();
+    let mut same_prefix = Vec::new();
+    for group in &size_groups {
+        let hashed: Vec<_> = group.iter().zip(prefix_hashes.by_ref()).collect();
+        for (hash, same) in split_by_hash(hashed) {
+            if same[0].size <= PREFIX_LEN {
+                groups.push(duplicate_group(hash, &same));
+            } else {
+                same_prefix.push(same);
+            }
+        }
+    }
+
+    let jobs: Vec<&DuplicateCandidate> = same_prefix.iter().flatten().copied().collect();
+    let mut full_hashes = hash_round(&jobs, u64::MAX, 2, threads, cancel, &counters, progress)?.into_iter();
+    for group in &same_prefix {
+        let hashed: Vec<_> = group.iter().copied().zip(full_hashes.by_ref()).collect();
+        for (hash, same) in split_by_hash(hashed) {
+            groups.push(duplicate_group(hash, &same));
+        }
+    }
+
+    groups.sort_by(|a, b| b.wasted_bytes().cmp(&a.wasted_bytes()).then_with(|| a.paths.cmp(&b.paths)));
+    Ok(DuplicateScan {
+        groups,
+        candidates,
+        bytes_hashed: counters.bytes_hashed.into_inner(),
+        unreadable: counters.unreadable.into_inner() as usize,
+        cancelled: cancel.is_cancelled(),
+    })
+}
+
+/// Hash the first `limit` bytes of each job, with a queue and readers per disk
+///
+/// Returns the hashes in job order; None for files that couldn't be read or
+/// weren't reached before the scan was cancelled.
+fn hash_round(
+    jobs: &[&DuplicateCandidate],
+    limit: u64,
+    round: u8,
+    threads: usize,
+    cancel: &CancelToken,
+    counters: &HashCounters,
+    progress: Option<HashProgressSink<'_>>,
+) -> Result<Vec<Option<String>>> {
+    let mut queues: HashMap<&str, (&Disk, Vec<usize>)> = HashMap::new();
+    for (i, job) in jobs.iter().enumerate() {
+        queues.entry(job.disk.id.as_str()).or_insert_with(|| (&*job.disk, Vec::new())).1.push(i);
+    }
+    // Neighbouring paths tend to be stored near each other
+    for (_, queue) in queues.values_mut() {
+        queue.sort_by(|&a, &b| jobs[a].disk_path.cmp(&jobs[b].disk_path));
+    }
+
+    counters.files_done.store(0, Ordering::Relaxed);
+    let report = || {
+        if let Some(progress) = progress {
+            progress(HashProgress {
+                round,
+                files_done: counters.files_done.load(Ordering::Relaxed),
+                files_total: jobs.len() as u64,
+                bytes_hashed: counters.bytes_hashed.load(Ordering::Relaxed),
+            });
+        }
+    };
+
+    let mut hashes = vec![None; jobs.len()];
+    thread::scope(|scope| -> Result<()> {
+        let mut workers = Vec::new();
+        for (disk, queue) in queues.values() {
+            let next = Arc::new(AtomicUsize::new(0));
+            for reader in 0..disk.readers(threads).min(queue.len()) {
+                let next = Arc::clone(&next);
+                let worker = thread::Builder::new()
+                    .name(format!("duplicate-hash-{}-{}", disk.id, reader))
+                    .spawn_scoped(scope, move || {
+                        let mut buffer = vec![0; CHUNK_LEN];
+                        let mut hashed = Vec::new();
+                        while let Some(&i) = queue.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            if cancel.is_cancelled() {
+                                break;
+                            }
+                            match hash_file(jobs[i], limit, &mut buffer, cancel, &counters.bytes_hashed) {
+                                Ok(Some(hash)) => hashed.push((i, hash)),
+                                Ok(None) => break, // Cancelled partway
+                                Err(_) => {
+                                    counters.unreadable.fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                            counters.files_done.fetch_add(1, Ordering::Relaxed);
+                        }
+                        hashed
+                    })
+                    .context("Failed to start the hashing threads")?;
+                workers.push(worker);
+            }
+        }
+
+        let mut reported = Instant::now();
+        while !workers.iter().all(|worker| worker.is_finished()) {
+            thread::sleep(Duration::from_millis(50));
+            if reported.elapsed() >= PROGRESS_INTERVAL {
+                report();
+                reported = Instant::now();
+            }
+        }
+        for worker in workers {
+            for (i, hash) in worker.join().expect("hashing thread panicked") {
+                hashes[i] = Some(hash);
+            }
+        }
+        Ok(())
+    })?;
+    report();
+    Ok(hashes)
+}
+
+/// Split hashed candidates by hash, keeping hashes shared by more than one file
+fn split_by_hash(hashed: Vec<(&DuplicateCandidate, Option<String>)>) -> Vec<(String, Vec<&DuplicateCandidate>)> {
+    let mut by_hash: HashMap<String, Vec<&DuplicateCandidate>> = HashMap::new();
+    for (candidate, hash) in hashed {
+        if let Some(hash) = hash {
+            by_hash.entry(hash).or_default().push(candidate);
+        }
+    }
+    by_hash.into_iter().filter(|(_, group)| group.len() > 1).collect()
+}
+
+/// Group of copies found with the same hash
+fn duplicate_group(hash: String, copies: &[&DuplicateCandidate]) -> DuplicateGroup {
+    let mut paths: Vec<String> = copies.iter().map(|c| c.shown.clone()).collect();
+    paths.sort();
+    DuplicateGroup { size: copies[0].size, hash, paths }
+}
+
+/// XXH3-128 hash of the first `limit` bytes of a file, read in chunks
+///
+/// Returns None if the scan was cancelled before the file was done.
+#[cfg(feature = "hashing")]
+fn hash_file(
+    candidate: &DuplicateCandidate,
+    limit: u64,
+    buffer: &mut [u8],
+    cancel: &CancelToken,
+    bytes_hashed: &AtomicU64,
+) -> io::Result<Option<String>> {
+    let mut file = File::open(&candidate.disk_path)?.take(limit);
+    let mut hasher = Xxh3::new();
+    loop {
+        if cancel.is_cancelled() {
+            return Ok(None);
+        }
+        let read = match file.read(buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read]);
+        bytes_hashed.fetch_add(read as u64, Ordering::Relaxed);
+    }
+    Ok(Some(format!("{:032x}", hasher.digest128())))
+}
+
+/// Built without the `hashing` feature: no file can be hashed
+#[cfg(not(feature = "hashing"))]
+fn hash_file(
+    _candidate: &DuplicateCandidate,
+    _limit: u64,
+    _buffer: &mut [u8],
+    _cancel: &CancelToken,
+    _bytes_hashed: &AtomicU64,
+) -> io::Result<Option<String>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "built without the 'hashing' feature"))
+}
+
+pub use os::disk_of;
+
+#[cfg(windows)]
+mod os {
+    use std::ffi::OsStr;
+    use std::mem::{size_of, zeroed};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr::null_mut;
+
+    use winapi::shared::minwindef::{DWORD, MAX_PATH};
+    use winapi::um::fileapi::{CreateFileW, GetVolumeNameForVolumeMountPointW, GetVolumePathNameW, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::ioapiset::DeviceIoControl;
+    use winapi::um::winioctl::{
+        PropertyStandardQuery, StorageDeviceSeekPenaltyProperty, DEVICE_SEEK_PENALTY_DESCRIPTOR,
+        IOCTL_STORAGE_QUERY_PROPERTY, IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS, STORAGE_PROPERTY_QUERY,
+        VOLUME_DISK_EXTENTS,
+    };
+    use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, HANDLE};
+
+    use super::Disk;
+
+    fn wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(Some(0)).collect()
+    }
+
+    /// Physical disk a folder is on, and whether it is a spinning disk
+    ///
+    /// Volumes spread over several disks are treated as a disk of their own.
+    pub fn disk_of(root: &Path) -> Disk {
+        let Some(volume) = volume_name(root) else {
+            return Disk::unknown(&root.display().to_string());
+        };
+        let Some(number) = with_device(&volume, disk_number) else {
+            return Disk::unknown(&volume);
+        };
+        let id = format!("PhysicalDrive{}", number);
+        let seek_penalty = with_device(&format!("\\\\.\\{}", id), seek_penalty).unwrap_or(false);
+        Disk { id, seek_penalty }
+    }
+
+    /// `\\?\Volume{...}` name of the volume a path is on, without the trailing backslash
+    fn volume_name(path: &Path) -> Option<String> {
+        let path = wide(path.as_os_str());
+        let mut mount_point = [0u16; MAX_PATH + 1];
+        let mut volume = [0u16; MAX_PATH + 1];
+        unsafe {
+            if GetVolumePathNameW(path.as_ptr(), mount_point.as_mut_ptr(), mount_point.len() as DWORD) == 0
+                || GetVolumeNameForVolumeMountPointW(mount_point.as_ptr(), volume.as_mut_ptr(), volume.len() as DWORD) == 0
+            {
+                return None;
+            }
+        }
+        let len = volume.iter().position(|&c| c == 0)?;
+        Some(String::from_utf16_lossy(&volume[..len]).trim_end_matches('\\').to_string())
+    }
+
+    /// Open a device for queries (no read access needed) and run `query` on it
+    fn with_device<T>(device: &str, query: impl FnOnce(HANDLE) -> Option<T>) -> Option<T> {
+        let name = wide(OsStr::new(device));
+        unsafe {
+            let handle = CreateFileW(name.as_ptr(), 0, FILE_SHARE_READ | FILE_SHARE_WRITE, null_mut(), OPEN_EXISTING, 0, null_mut());
+            if handle == INVALID_HANDLE_VALUE {
+                return None;
+            }
+            let result = query(handle);
+            CloseHandle(handle);
+            result
+        }
+    }
+
+    /// Number of the only disk a volume is on
+    fn disk_number(volume: HANDLE) -> Option<DWORD> {
+        unsafe {
+            let mut extents: VOLUME_DISK_EXTENTS = zeroed();
+            let mut returned: DWORD = 0;
+            let ok = DeviceIoControl(
+                volume,
+                IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
+                null_mut(),
+                0,
+                &mut extents as *mut _ as *mut _,
+                size_of::<VOLUME_DISK_EXTENTS>() as DWORD,
+                &mut returned,
+                null_mut(),
+            );
+            // Fails with ERROR_MORE_DATA for volumes on several disks
+            (ok != 0 && extents.NumberOfDiskExtents == 1).then(|| extents.Extents[0].DiskNumber)
+        }
+    }
+
+    /// Whether a disk incurs a seek penalty (spins)
+    fn seek_penalty(disk: HANDLE) -> Option<bool> {
+        unsafe {
+            let mut query: STORAGE_PROPERTY_QUERY = zeroed();
+            query.PropertyId = StorageDeviceSeekPenaltyProperty;
+            query.QueryType = PropertyStandardQuery;
+            let mut descriptor: DEVICE_SEEK_PENALTY_DESCRIPTOR = zeroed();
+            let mut returned: DWORD = 0;
+            let ok = DeviceIoControl(
+                disk,
+                IOCTL_STORAGE_QUERY_PROPERTY,
+                &mut query as *mut _ as *mut _,
+                size_of::<STORAGE_PROPERTY_QUERY>() as DWORD,
+                &mut descriptor as *mut _ as *mut _,
+                size_of::<DEVICE_SEEK_PENALTY_DESCRIPTOR>() as DWORD,
+                &mut returned,
+                null_mut(),
+            );
+            (ok != 0).then(|| descriptor.IncursSeekPenalty != 0)
+        }
+    }
+}
+
+#[cfg(unix)]
+mod os {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    use super::Disk;
+
+    /// Physical disk a folder is on, and whether it is a spinning disk
+    ///
+    /// Block devices are looked up in sysfs; partitions count as their disk.
+    /// Anything else (overlay and network file systems) is its own disk.
+    pub fn disk_of(root: &Path) -> Disk {
+        let Ok(metadata) = fs::metadata(root) else {
+            return Disk::unknown(&root.display().to_string());
+        };
+        let dev = metadata.dev();
+        let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+        let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+        let Ok(mut device) = fs::canonicalize(format!("/sys/dev/block/{}:{}", major, minor)) else {
+            return Disk::unknown(&format!("dev{}", dev));
+        };
+        if device.join("partition").exists() {
+            device.pop();
+        }
+        let rotational = fs::read_to_string(device.join("queue").join("rotational"));
+        Disk {
+            id: device.file_name().map_or_else(|| format!("dev{}", dev), |name| name.to_string_lossy().into_owned()),
+            seek_penalty: rotational.map_or(false, |value| value.trim() == "1"),
+        }
+    }
+}
+
+#[cfg(not(any(windows, unix)))]
+mod os {
+    use std::path::Path;
+
+    use super::Disk;
+
+    pub fn disk_of(root: &Path) -> Disk {
+        Disk::unknown(&root.display().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::path::Path;
+
+    fn candidates(dir: &Path, files: &[(&str, &[u8])]) -> Vec<Vec<DuplicateCandidate>> {
+        let disk = Arc::new(Disk::unknown("test"));
+        let mut by_size: HashMap<u64, Vec<DuplicateCandidate>> = HashMap::new();
+        for (name, contents) in files {
+            let path = dir.join(name);
+            fs::write(&path, contents).unwrap();
+            by_size.entry(contents.len() as u64).or_default().push(DuplicateCandidate {
+                shown: name.to_string(),
+                disk_path: path,
+                size: contents.len() as u64,
+                disk: Arc::clone(&disk),
+            });
+        }
+        by_size.into_values().filter(|group| group.len() > 1).collect()
+    }
+
+    #[test]
+    #[cfg(feature = "hashing")]
+    fn test_groups_identical_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let big = vec![7u8; 100_000];
+        let mut big_changed = big.clone();
+        big_changed[99_999] = 8;
+        let groups = candidates(dir.path(), &[
+            ("a.txt", b"same"),
+            ("b.txt", b"same"),
+            ("c.txt", b"diff"),
+            ("d.txt", b"longer"),
+            ("big1.bin", &big),
+            ("big2.bin", &big),
+            ("big3.bin", &big_changed),
+        ]);
+
+        let progress = RefCell::new(Vec::new());
+        let sink = |update: HashProgress| progress.borrow_mut().push(update);
+        let scan = find_duplicates(groups, 2, &CancelToken::new(), Some(&sink)).unwrap();
+        assert_eq!(scan.candidates, 6);
+        assert_eq!(scan.unreadable, 0);
+        // The big files only differ past the prefix, so they're read in full
+        let paths: Vec<Vec<String>> = scan.groups.iter().map(|g| g.paths.clone()).collect();
+        assert_eq!(paths, vec![vec!["big1.bin", "big2.bin"], vec!["a.txt", "b.txt"]]);
+        assert_eq!(scan.groups[0].wasted_bytes(), 100_000);
+        assert!(!scan.cancelled);
+
+        // Each round reports when it's done; the second only reads the big files
+        let progress = progress.into_inner();
+        let last = *progress.last().unwrap();
+        assert_eq!((last.round, last.files_done, last.files_total), (2, 3, 3));
+        assert_eq!(last.bytes_hashed, scan.bytes_hashed);
+    }
+
+    #[test]
+    fn test_unreadable_candidates_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut groups = candidates(dir.path(), &[("a.txt", b"same"), ("b.txt", b"same")]);
+        let disk = Arc::new(Disk { id: "spinning".to_string(), seek_penalty: true });
+        groups[0].push(DuplicateCandidate { shown: "gone.txt".to_string(), disk_path: dir.path().join("gone.txt"), size: 4, disk });
+
+        let scan = find_duplicates(groups, 1, &CancelToken::new(), None).unwrap();
+        assert_eq!(scan.unreadable, 1);
+        assert_eq!(scan.groups.len(), 1);
+        assert_eq!(scan.groups[0].paths, vec!["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn test_cancelled_scan_reads_nothing_more() {
+        let dir = tempfile::tempdir().unwrap();
+        let groups = candidates(dir.path(), &[("a.txt", b"same"), ("b.txt", b"same")]);
+        let cancel = CancelToken::new();
+        cancel.cancel();
+
+        let scan = find_duplicates(groups, 4, &cancel, None).unwrap();
+        assert!(scan.cancelled);
+        assert!(scan.groups.is_empty());
+        assert_eq!((scan.bytes_hashed, scan.unreadable), (0, 0));
+        assert_eq!(Disk { id: "hdd".to_string(), seek_penalty: true }.readers(4), 1);
+        assert_eq!(Disk::unknown("ssd").readers(4), 4);
+    }
+}
@@ -0,0 +1,386 @@
+//! Embedded engine API for Rust applications
+//!
+//! `Engine` is the search engine behind the MCP server, with typed requests and
+//! results instead of JSON-RPC messages, so launchers, backup tools and other
+//! Rust programs can link the service crate and search the MFT caches
+//! directly. The MCP server is one consumer of it: it hands `tools/call`
+//! messages to the same engine, so both see the same caches, limits and
+//! drive configuration.
+//!
+//! Long searches and rebuilds take a [`CancelToken`] from `cancel_token`;
+//! cancelling it from another thread stops the work at its next check, and
+//! `shutdown` stops everything the engine is running.
+//!
+//! ```no_run
+//! use fastsearch_service::{Engine, SearchRequest};
+//!
+//! let engine = Engine::open()?;
+//! engine.open_volume('C')?;
+//! let found = engine.search(&SearchRequest { extensions: vec!["rs".into()], ..SearchRequest::new("*") })?;
+//! for file in &found.files {
+//!     println!("{}:\\{} ({} bytes)", file.drive, file.path, file.size);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+
+use super::cancellation::CancelToken;
+use super::live_queries::{LiveQuery, LiveUpdate};
+use super::matcher::SearchType;
+use super::mft_cache::CacheStats;
+use super::search_engine::SearchEngine;
+
+/// Results a search returns unless the request says otherwise (as for `fast_search`)
+const DEFAULT_MAX_RESULTS: usize = 1000;
+
+/// A file search, the typed form of the `fast_search` arguments it supports
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchRequest {
+    /// Name pattern, read as `search_type` says
+    pub pattern: String,
+    pub search_type: SearchType,
+    /// Drive to search; the engine's default drive if not given
+    pub drive: Option<char>,
+    /// Only entries whose path contains this folder
+    pub path: Option<String>,
+    /// Only files with one of these extensions (without dots)
+    pub extensions: Vec<String>,
+    /// Globs of paths to leave out
+    pub exclude: Vec<String>,
+    /// Size bounds in bytes
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Window for the last modification time
+    pub modified_after: Option<SystemTime>,
+    pub modified_before: Option<SystemTime>,
+    /// Folder levels below `path` (1 for direct children)
+    pub max_depth: Option<usize>,
+    pub max_results: usize,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Stop scanning after this long and return what was found
+    pub timeout: Option<Duration>,
+}
+
+impl SearchRequest {
+    /// Search for a pattern with every other option at its default
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            search_type: SearchType::default(),
+            drive: None,
+            path: None,
+            extensions: Vec::new(),
+            exclude: Vec::new(),
+            min_size: None,
+            max_size: None,
+            modified_after: None,
+            modified_before: None,
+            max_depth: None,
+            max_results: DEFAULT_MAX_RESULTS,
+            cursor: None,
+            timeout: None,
+        }
+    }
+
+    /// The request as `fast_search` arguments
+    ///
+    /// Broad searches are paged through rather than summarized, since an
+    /// embedder wants the matches, not a description of them.
+    fn to_args(&self) -> Value {
+        let mut args = json!({
+            "pattern": self.pattern,
+            "search_type": self.search_type.as_str(),
+            "max_results": self.max_results,
+            "summarize_broad": false,
+        });
+        if let Some(drive) = self.drive {
+            args["drive"] = json!(drive.to_string());
+        }
+        if let Some(path) = &self.path {
+            args["path"] = json!(path);
+        }
+        if !self.extensions.is_empty() {
+            args["extensions"] = json!(self.extensions);
+        }
+        if !self.exclude.is_empty() {
+            args["exclude"] = json!(self.exclude);
+        }
+        if let Some(min_size) = self.min_size {
+            args["min_size"] = json!(min_size);
+        }
+        if let Some(max_size) = self.max_size {
+            args["max_size"] = json!(max_size);
+        }
+        if let Some(after) = self.modified_after {
+            args["modified_after"] = json!(unix_seconds(after));
+        }
+        if let Some(before) = self.modified_before {
+            args["modified_before"] = json!(unix_seconds(before));
+        }
+        if let Some(depth) = self.max_depth {
+            args["max_depth"] = json!(depth);
+        }
+        if let Some(cursor) = &self.cursor {
+            args["cursor"] = json!(cursor);
+        }
+        if let Some(timeout) = self.timeout {
+            args["timeout_ms"] = json!(timeout.as_millis() as u64);
+        }
+        args
+    }
+}
+
+/// A file or folder a search found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundFile {
+    pub name: String,
+    /// Path relative to the drive root
+    pub path: String,
+    /// Drive letter the file was found under
+    pub drive: char,
+    pub size: u64,
+    pub is_directory: bool,
+    pub created: SystemTime,
+    pub modified: SystemTime,
+    pub accessed: SystemTime,
+}
+
+impl FoundFile {
+    /// Read a structured `fast_search` result
+    fn from_json(entry: &Value) -> Option<Self> {
+        let time = |key: &str| UNIX_EPOCH + Duration::from_secs(entry[key].as_u64().unwrap_or(0));
+        Some(Self {
+            name: entry["name"].as_str()?.to_string(),
+            path: entry["path"].as_str()?.to_string(),
+            drive: entry["drive"].as_str()?.chars().next()?,
+            size: entry["size"].as_u64().unwrap_or(0),
+            is_directory: entry["is_directory"].as_bool().unwrap_or(false),
+            created: time("created"),
+            modified: time("modified"),
+            accessed: time("accessed"),
+        })
+    }
+}
+
+/// One page of search results
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SearchResults {
+    pub files: Vec<FoundFile>,
+    /// Cursor for the next page, if there are more matches
+    pub next_cursor: Option<String>,
+    /// The scan stopped at the timeout or was cancelled, so matches may be missing
+    pub truncated: bool,
+    /// The scan was cancelled through its token
+    pub cancelled: bool,
+}
+
+impl SearchResults {
+    /// Read the `result` of a `fast_search` response
+    fn from_json(result: &Value) -> Self {
+        Self {
+            files: result["results"].as_array()
+                .map(|results| results.iter().filter_map(FoundFile::from_json).collect())
+                .unwrap_or_default(),
+            next_cursor: result["next_cursor"].as_str().map(str::to_string),
+            truncated: result["truncated"].as_bool().unwrap_or(false),
+            cancelled: result["cancelled"].as_bool().unwrap_or(false),
+        }
+    }
+}
+
+/// A search whose results are followed as the file system changes
+pub struct Subscription {
+    /// ID to end the subscription with `Engine::unsubscribe`
+    pub id: String,
+    /// Results when the subscription was made
+    pub initial: SearchResults,
+    /// Entries joining or leaving the results from then on
+    pub updates: broadcast::Receiver<LiveUpdate>,
+}
+
+/// The search engine, for embedding in other Rust applications
+pub struct Engine {
+    search_engine: SearchEngine,
+}
+
+impl Engine {
+    /// Start the engine with the service's drive configuration and stores
+    ///
+    /// No volume is read yet: caches are built by `open_volume`, `preload` or
+    /// the first search on a drive.
+    pub fn open() -> Result<Self> {
+        Ok(Self { search_engine: SearchEngine::new()? })
+    }
+
+    /// Build the cache of a drive (or find it already built) and start following its changes
+    pub fn open_volume(&self, drive: char) -> Result<CacheStats> {
+        Ok(self.search_engine.open_drive(drive)?.stats())
+    }
+
+    /// Build the caches of the drives configured for preloading; returns how many were built
+    pub fn preload(&self) -> usize {
+        self.search_engine.preload_drives()
+    }
+
+    /// Drives whose caches are built, with their sizes and journal positions
+    pub fn volumes(&self) -> Vec<CacheStats> {
+        self.search_engine.open_drives().iter().map(|cache| cache.stats()).collect()
+    }
+
+    /// Run a search; invalid requests are errors with the message the MCP tool would give
+    pub fn search(&self, request: &SearchRequest) -> Result<SearchResults> {
+        let response = self.search_engine.fast_search(&request.to_args())?;
+        Ok(SearchResults::from_json(result_of(&response)?))
+    }
+
+    /// A token for `search_cancellable` or `rebuild_volume`, cancelled by `shutdown` too
+    pub fn cancel_token(&self) -> CancelToken {
+        self.search_engine.cancel_token()
+    }
+
+    /// Run a search that stops once `cancel` is cancelled, returning the matches found so far
+    pub fn search_cancellable(&self, request: &SearchRequest, cancel: &CancelToken) -> Result<SearchResults> {
+        let response = self.search_engine.fast_search_with_cancel(&request.to_args(), None, cancel)?;
+        Ok(SearchResults::from_json(result_of(&response)?))
+    }
+
+    /// Rebuild the cache of a drive from its MFT; a cancelled rebuild fails with `Cancelled`
+    pub fn rebuild_volume(&self, drive: char, cancel: &CancelToken) -> Result<CacheStats> {
+        let cache = self.search_engine.open_drive(drive)?;
+        cache.rebuild_cancellable(cancel)?;
+        Ok(cache.stats())
+    }
+
+    /// Stop every running search, scan and rebuild; later ones are cancelled from the start
+    pub fn shutdown(&self) {
+        self.search_engine.shutdown();
+    }
+
+    /// Run a search and follow the entries that start or stop matching it
+    ///
+    /// Updates are delivered from when this returns; an entry changed while
+    /// the initial search ran may be missed, or be in both.
+    pub fn subscribe(&self, request: &SearchRequest) -> Result<Subscription> {
+        let response = self.search_engine.register_live_query(&request.to_args())?;
+        let result = result_of(&response)?;
+        let id = result["live_query"]["id"].as_str()
+            .ok_or_else(|| anyhow!("Live query was registered without an ID"))?;
+        let query: Arc<LiveQuery> = self.search_engine.live_query(id)
+            .ok_or_else(|| anyhow!("Live query {} ended before it could be subscribed to", id))?;
+        Ok(Subscription {
+            id: id.to_string(),
+            initial: SearchResults::from_json(&result["initial"]),
+            updates: query.subscribe(),
+        })
+    }
+
+    /// End a subscription; returns whether it was still registered
+    pub fn unsubscribe(&self, id: &str) -> bool {
+        self.search_engine.remove_live_query(id)
+    }
+
+    /// The engine behind the typed API, for the MCP and pipe servers
+    pub(crate) fn search_engine(&self) -> &SearchEngine {
+        &self.search_engine
+    }
+}
+
+/// The `result` of a tool response, or its error message as an error
+fn result_of(response: &Value) -> Result<&Value> {
+    match response.get("error") {
+        Some(error) => Err(anyhow!("{}", error["message"].as_str().unwrap_or("Search failed"))),
+        None => Ok(&response["result"]),
+    }
+}
+
+/// Seconds since the UNIX epoch (0 for earlier times)
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_becomes_search_arguments() {
+        let request = SearchRequest::new("*.rs");
+        assert_eq!(request.to_args(), json!({
+            "pattern": "*.rs",
+            "search_type": "glob",
+            "max_results": 1000,
+            "summarize_broad": false,
+        }));
+
+        let request = SearchRequest {
+            search_type: SearchType::Regex,
+            drive: Some('D'),
+            path: Some("projects".into()),
+            extensions: vec!["rs".into(), "toml".into()],
+            min_size: Some(1024),
+            modified_after: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            max_results: 20,
+            timeout: Some(Duration::from_millis(250)),
+            ..SearchRequest::new("^main")
+        };
+        let args = request.to_args();
+        assert_eq!(args["search_type"], "regex");
+        assert_eq!(args["drive"], "D");
+        assert_eq!(args["path"], "projects");
+        assert_eq!(args["extensions"], json!(["rs", "toml"]));
+        assert_eq!(args["min_size"], 1024);
+        assert_eq!(args["modified_after"], 1_700_000_000u64);
+        assert_eq!(args["max_results"], 20);
+        assert_eq!(args["timeout_ms"], 250);
+        assert!(args.get("max_size").is_none());
+        assert!(args.get("cursor").is_none());
+    }
+
+    #[test]
+    fn test_reads_search_results() {
+        let result = json!({
+            "results": [
+                {"name": "main.rs", "path": "src\\main.rs", "drive": "C", "size": 120, "is_directory": false,
+                 "created": 10, "modified": 20, "accessed": 30, "match_score": 1.0},
+                {"name": "src", "path": "src", "drive": "C", "size": 0, "is_directory": true},
+                {"path": "no name"}
+            ],
+            "next_cursor": "abc",
+            "truncated": true,
+            "cancelled": true
+        });
+        let results = SearchResults::from_json(&result);
+        assert_eq!(results.files.len(), 2);
+        assert_eq!(results.files[0], FoundFile {
+            name: "main.rs".into(),
+            path: "src\\main.rs".into(),
+            drive: 'C',
+            size: 120,
+            is_directory: false,
+            created: UNIX_EPOCH + Duration::from_secs(10),
+            modified: UNIX_EPOCH + Duration::from_secs(20),
+            accessed: UNIX_EPOCH + Duration::from_secs(30),
+        });
+        assert!(results.files[1].is_directory);
+        assert_eq!(results.next_cursor.as_deref(), Some("abc"));
+        assert!(results.truncated && results.cancelled);
+        assert!(results.truncated);
+
+        assert_eq!(SearchResults::from_json(&json!({"results": [], "next_cursor": null})), SearchResults::default());
+    }
+
+    #[test]
+    fn test_error_responses_are_errors() {
+        let error = json!({"error": {"code": -32602, "message": "Unknown search_type 'x'"}});
+        assert_eq!(result_of(&error).unwrap_err().to_string(), "Unknown search_type 'x'");
+        let ok = json!({"result": {"results": []}});
+        assert_eq!(result_of(&ok).unwrap(), &json!({"results": []}));
+    }
+}
@@ -0,0 +1,245 @@
+//! Metadata envelope of tool responses
+//!
+//! The text of a response says how its results were produced, but a client
+//! had to parse it to find out. Every `tools/call` response (and every search
+//! over the pipe or the Web API) now carries the same facts in `_meta`, next
+//! to `index_modes`:
+//!
+//! - `schema_version`: version of this envelope, raised when a field is
+//!   removed or changes meaning
+//! - `tool` and `query`: the tool that ran and its arguments, echoed back
+//! - `drives`: each drive the call read, with its `mode`, its `cache` (`hit`,
+//!   `built` for this call, or `unavailable`) and `cache_age_secs`, the time
+//!   since the cache was last brought up to date
+//! - `mode`: the mode of all those drives, `mixed` if they differ, `null` if
+//!   the call read none
+//! - `truncated`: whether the results stopped short (time limit, cancellation)
+//! - `timing_ms`: `total`, split into `queue` (waiting for a search slot),
+//!   `cache_build` (building caches for this call) and `work` (the rest)
+//! - `smart_rewritten` and `rewritten_query`: only on smart searches, whose
+//!   patterns were rewritten before searching, with the arguments searched
+//!
+//! Drives are noted as the call opens their caches on its own thread; a call
+//! that runs inside another (a tool searching through `fast_search`) adds to
+//! the envelope of the outer one.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use super::modes::drive_mode;
+
+/// Version of the `_meta` envelope
+pub const ENVELOPE_SCHEMA_VERSION: u32 = 1;
+
+thread_local! {
+    /// What the tool call running on this thread has touched so far
+    static TRACE: RefCell<Option<Trace>> = RefCell::new(None);
+}
+
+#[derive(Debug)]
+struct Trace {
+    started: Instant,
+    drives: BTreeMap<char, DriveUse>,
+    cache_build: Duration,
+}
+
+/// How a call got at one drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DriveUse {
+    /// Last update of its cache, `None` if there was none to open
+    updated: Option<SystemTime>,
+    /// Whether the call built the cache
+    built: bool,
+}
+
+/// Clears the trace even if the call panics, so the next call on the thread starts fresh
+struct TraceGuard;
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        TRACE.with(|trace| trace.borrow_mut().take());
+    }
+}
+
+/// Run a tool call and add the envelope to its response
+///
+/// Calls made while another one runs on the same thread just run.
+pub fn traced(tool: &str, args: &Value, run: impl FnOnce() -> Result<Value>) -> Result<Value> {
+    let outermost = TRACE.with(|trace| {
+        let mut trace = trace.borrow_mut();
+        if trace.is_some() {
+            return false;
+        }
+        *trace = Some(Trace { started: Instant::now(), drives: BTreeMap::new(), cache_build: Duration::ZERO });
+        true
+    });
+    if !outermost {
+        return run();
+    }
+
+    let _guard = TraceGuard;
+    let mut response = run()?;
+    if let Some(trace) = TRACE.with(|trace| trace.borrow_mut().take()) {
+        let mode_of = |drive: char| drive_mode(drive).map(|mode| mode.mode.as_str());
+        stamp(&mut response, tool, args, &trace, mode_of, SystemTime::now());
+    }
+    Ok(response)
+}
+
+/// Note that the running call opened a drive's cache (`updated` is `None` if it couldn't)
+///
+/// `build` is the time spent building the cache, if this call built it.
+pub fn record_drive(drive: char, updated: Option<SystemTime>, build: Option<Duration>) {
+    TRACE.with(|trace| {
+        let mut trace = trace.borrow_mut();
+        let Some(trace) = trace.as_mut() else {
+            return; // Not inside a tool call, e.g. warming caches at startup
+        };
+        let used = trace.drives.entry(drive).or_insert(DriveUse { updated: None, built: false });
+        used.updated = updated.or(used.updated);
+        if let Some(build) = build {
+            used.built = true;
+            trace.cache_build += build;
+        }
+    });
+}
+
+/// Write the envelope into `result._meta`; error responses are left alone
+fn stamp(
+    response: &mut Value,
+    tool: &str,
+    args: &Value,
+    trace: &Trace,
+    mode_of: impl Fn(char) -> Option<&'static str>,
+    now: SystemTime,
+) {
+    let Some(result) = response.get_mut("result").filter(|result| result.is_object()) else {
+        return;
+    };
+
+    let drives: Vec<Value> = trace.drives.iter()
+        .map(|(drive, used)| {
+            let cache = match (used.updated, used.built) {
+                (None, _) => "unavailable",
+                (Some(_), true) => "built",
+                (Some(_), false) => "hit",
+            };
+            let age = used.updated.map(|updated| now.duration_since(updated).unwrap_or_default().as_secs());
+            json!({
+                "drive": drive.to_string(),
+                "mode": mode_of(*drive),
+                "cache": cache,
+                "cache_age_secs": age
+            })
+        })
+        .collect();
+    let mut modes: Vec<Option<&str>> = trace.drives.keys().map(|drive| mode_of(*drive)).collect();
+    modes.dedup();
+    let mode = match modes.as_slice() {
+        [] => None,
+        [only] => *only,
+        _ => Some("mixed"),
+    };
+
+    let total = trace.started.elapsed();
+    let queue = Duration::from_millis(result["_meta"]["queue"]["waited_ms"].as_u64().unwrap_or(0));
+    let work = total.saturating_sub(queue).saturating_sub(trace.cache_build);
+    let truncated = result["truncated"].as_bool().unwrap_or(false);
+
+    let meta = &mut result["_meta"];
+    meta["schema_version"] = json!(ENVELOPE_SCHEMA_VERSION);
+    meta["tool"] = json!(tool);
+    meta["query"] = args.clone();
+    meta["drives"] = json!(drives);
+    meta["mode"] = json!(mode);
+    meta["truncated"] = json!(truncated);
+    meta["timing_ms"] = json!({
+        "total": total.as_millis() as u64,
+        "queue": queue.as_millis() as u64,
+        "cache_build": trace.cache_build.as_millis() as u64,
+        "work": work.as_millis() as u64
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(drives: &[(char, Option<u64>, bool)]) -> Trace {
+        let now = SystemTime::now();
+        Trace {
+            started: Instant::now(),
+            drives: drives.iter()
+                .map(|&(drive, age, built)| {
+                    let updated = age.map(|age| now - Duration::from_secs(age));
+                    (drive, DriveUse { updated, built })
+                })
+                .collect(),
+            cache_build: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_stamp_envelope() {
+        let mut response = json!({"result": {"content": [], "truncated": true, "_meta": {"queue": {"waited_ms": 0}}}});
+        let args = json!({"pattern": "*.rs", "drive": "*"});
+        let trace = trace(&[('C', Some(120), false), ('D', Some(0), true)]);
+        stamp(&mut response, "fast_search", &args, &trace, |_| Some("mft"), SystemTime::now());
+
+        let meta = &response["result"]["_meta"];
+        assert_eq!(meta["schema_version"], json!(ENVELOPE_SCHEMA_VERSION));
+        assert_eq!(meta["tool"], "fast_search");
+        assert_eq!(meta["query"], args);
+        assert_eq!(meta["mode"], "mft");
+        assert_eq!(meta["truncated"], true);
+        assert_eq!(meta["drives"][0]["drive"], "C");
+        assert_eq!(meta["drives"][0]["cache"], "hit");
+        assert!(meta["drives"][0]["cache_age_secs"].as_u64().unwrap() >= 120);
+        assert_eq!(meta["drives"][1]["cache"], "built");
+        assert!(meta["timing_ms"]["work"].is_u64());
+        // Fields already there are kept
+        assert_eq!(meta["queue"]["waited_ms"], 0);
+    }
+
+    #[test]
+    fn test_mixed_modes_and_unavailable_drives() {
+        let mut response = json!({"result": {"content": []}});
+        let trace = trace(&[('C', Some(5), false), ('E', None, false)]);
+        let mode_of = |drive| if drive == 'C' { Some("mft") } else { Some("direct_scan") };
+        stamp(&mut response, "find_large_files", &json!({}), &trace, mode_of, SystemTime::now());
+
+        let meta = &response["result"]["_meta"];
+        assert_eq!(meta["mode"], "mixed");
+        assert_eq!(meta["truncated"], false);
+        assert_eq!(meta["drives"][1]["cache"], "unavailable");
+        assert!(meta["drives"][1]["cache_age_secs"].is_null());
+    }
+
+    #[test]
+    fn test_errors_and_nested_calls() {
+        let mut error = json!({"error": {"code": -32602, "message": "bad"}});
+        stamp(&mut error, "fast_search", &json!({}), &trace(&[]), |_| None, SystemTime::now());
+        assert!(error.get("result").is_none());
+
+        let response = traced("outer", &json!({"a": 1}), || {
+            record_drive('C', Some(SystemTime::now()), Some(Duration::from_millis(3)));
+            let inner = traced("inner", &json!({}), || Ok(json!({"result": {}})))?;
+            // Only the outermost call is stamped
+            assert!(inner["result"]["_meta"].is_null());
+            Ok(json!({"result": {"content": []}}))
+        })
+        .unwrap();
+        let meta = &response["result"]["_meta"];
+        assert_eq!(meta["tool"], "outer");
+        assert_eq!(meta["drives"][0]["cache"], "built");
+        assert!(meta["timing_ms"]["cache_build"].as_u64().unwrap() >= 3);
+
+        // The trace is gone once the call returns
+        record_drive('D', None, None);
+        assert!(TRACE.with(|trace| trace.borrow().is_none()));
+    }
+}
@@ -0,0 +1,119 @@
+//! ETW events for tracing the service in Windows Performance Analyzer
+//!
+//! The service registers the `FastSearch-Service` provider and writes an
+//! event for each cache rebuild, batch of USN journal changes, pipe request
+//! and slow search. Recorded next to the kernel's disk IO events (for example
+//! `wpr -start GeneralProfile -start DiskIO` with the provider added to the
+//! profile), they show which FastSearch activity caused which IO. Writing an
+//! event nobody is listening to costs a flag check, so they are always on.
+//!
+//! Events use TraceLogging, so no manifest needs installing; the provider GUID
+//! is derived from its name.
+
+use std::sync::Once;
+use std::time::Duration;
+
+use log::{debug, warn};
+use tracelogging as tlg;
+
+use super::pipeline::StageCounts;
+
+/// Searches taking longer than this are reported as slow
+pub const SLOW_QUERY_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Keywords to select event groups when starting a trace
+pub mod keyword {
+    /// Cache rebuilds
+    pub const CACHE: u64 = 0x1;
+    /// USN journal change batches
+    pub const USN: u64 = 0x2;
+    /// Named pipe requests
+    pub const PIPE: u64 = 0x4;
+    /// Slow searches
+    pub const QUERY: u64 = 0x8;
+}
+
+tlg::define_provider!(PROVIDER, "FastSearch-Service");
+
+static REGISTER: Once = Once::new();
+
+/// Register the provider with ETW; later calls do nothing
+pub fn register() {
+    REGISTER.call_once(|| {
+        // Safety: the provider is a static that is never unregistered
+        let result = unsafe { PROVIDER.register() };
+        if result == 0 {
+            debug!("Registered ETW provider {}", PROVIDER.name());
+        } else {
+            warn!("Failed to register ETW provider {}: error {}", PROVIDER.name(), result);
+        }
+    });
+}
+
+/// A drive's cache was (re)built; `source` is what triggered it
+pub fn cache_rebuilt(drive: char, entries: usize, duration: Duration, source: &str) {
+    tlg::write_event!(
+        PROVIDER,
+        "CacheRebuild",
+        level(Informational),
+        keyword(keyword::CACHE),
+        str8("Drive", &drive.to_string()),
+        u64("Entries", &(entries as u64)),
+        u64("DurationMs", &millis(duration)),
+        str8("Source", source),
+    );
+}
+
+/// A batch of USN journal records was read and applied to a drive's cache
+pub fn usn_batch(drive: char, changes: usize, duration: Duration) {
+    tlg::write_event!(
+        PROVIDER,
+        "UsnBatch",
+        level(Informational),
+        keyword(keyword::USN),
+        str8("Drive", &drive.to_string()),
+        u64("Changes", &(changes as u64)),
+        u64("DurationMs", &millis(duration)),
+    );
+}
+
+/// A request on the named pipe was answered
+pub fn pipe_request(message_type: u32, bytes: usize, duration: Duration, ok: bool) {
+    tlg::write_event!(
+        PROVIDER,
+        "PipeRequest",
+        level(Informational),
+        keyword(keyword::PIPE),
+        u32("MessageType", &message_type),
+        u64("RequestBytes", &(bytes as u64)),
+        u64("DurationMs", &millis(duration)),
+        bool8("Ok", &ok),
+    );
+}
+
+/// A search took longer than `SLOW_QUERY_THRESHOLD`; the counts show which stage did the work
+pub fn slow_query(description: &str, drive: &str, results: usize, duration: Duration, counts: &StageCounts) {
+    if duration < SLOW_QUERY_THRESHOLD {
+        return;
+    }
+    tlg::write_event!(
+        PROVIDER,
+        "SlowQuery",
+        level(Warning),
+        keyword(keyword::QUERY),
+        str8("Query", description),
+        str8("Drive", drive),
+        u64("Results", &(results as u64)),
+        u64("DurationMs", &millis(duration)),
+        str8("Prefilter", counts.prefilter),
+        u64("Candidates", &counts.candidates),
+        u64("NameMatches", &counts.name_matches),
+        u64("FilterMatches", &counts.filter_matches),
+        u64("Verified", &counts.verified),
+        u64("Rejected", &counts.rejected),
+    );
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().min(u64::MAX as u128) as u64
+}
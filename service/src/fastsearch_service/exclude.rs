@@ -0,0 +1,162 @@
+//! Exclude patterns for `fast_search`
+//!
+//! Patterns are globs over paths relative to the drive root, with `/` and `\`
+//! treated alike and matched case-insensitively:
+//!
+//! - `*.tmp` or `node_modules`: no separator, matches an entry with that name
+//!   anywhere on the drive
+//! - `build/*.log`: matches that path at any depth
+//! - `/Windows/Temp`: a leading separator anchors the pattern at the drive root
+//! - `**` matches any number of folders, `*`, `?` and classes (`[0-9]`) stay
+//!   within one name, and `{a,b}` matches either alternative
+//!
+//! Excluding a folder excludes everything below it, so `node_modules` and
+//! `node_modules/**` are equivalent. That also means a folder's exclusion can be
+//! decided once and its subtree skipped without looking at any entry inside it.
+
+use anyhow::{anyhow, Result};
+use regex::RegexSet;
+use serde_json::Value;
+
+use super::matcher::push_glob;
+
+/// Compiled set of exclude globs
+#[derive(Debug, Clone, Default)]
+pub struct ExcludePatterns {
+    patterns: Vec<String>,
+    set: Option<RegexSet>,
+}
+
+impl ExcludePatterns {
+    /// Compile a list of globs
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.as_ref().trim().to_string()).collect();
+        if patterns.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let regexes = patterns.iter().map(|p| exclude_to_regex(p)).collect::<Result<Vec<_>>>()?;
+        let set = RegexSet::new(&regexes).map_err(|e| anyhow!("Invalid exclude patterns: {}", e))?;
+
+        Ok(Self { patterns, set: Some(set) })
+    }
+
+    /// Parse the `exclude` tool argument (an array of globs, or a single glob)
+    pub fn from_args(args: &Value) -> Result<Self> {
+        match &args["exclude"] {
+            Value::Null => Ok(Self::default()),
+            Value::String(pattern) => Self::new(&[pattern]),
+            Value::Array(items) => {
+                let patterns = items
+                    .iter()
+                    .map(|item| item.as_str().ok_or_else(|| anyhow!("'exclude' entries must be glob strings")))
+                    .collect::<Result<Vec<_>>>()?;
+                Self::new(&patterns)
+            }
+            _ => Err(anyhow!("'exclude' must be an array of glob strings")),
+        }
+    }
+
+    /// Whether no pattern is set
+    pub fn is_empty(&self) -> bool {
+        self.set.is_none()
+    }
+
+    /// The globs as given
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether a path relative to the drive root (or anything above it) is excluded
+    pub fn excludes(&self, path: &str) -> bool {
+        match &self.set {
+            Some(set) => set.is_match(path.trim_start_matches(['\\', '/'])),
+            None => false,
+        }
+    }
+}
+
+/// Translate an exclude glob into a regex over relative paths
+///
+/// The regex also matches every path below a matching one, which is what makes
+/// subtree pruning valid.
+fn exclude_to_regex(pattern: &str) -> Result<String> {
+    let normalized = pattern.replace('/', "\\");
+    let anchored = normalized.starts_with('\\');
+    let body = normalized.trim_matches('\\');
+    if body.is_empty() || body.split('\\').all(|part| part == "**") {
+        return Err(anyhow!("Exclude pattern '{}' would exclude everything", pattern));
+    }
+
+    let mut out = String::from("(?i)^");
+    if !anchored {
+        out.push_str(r"(?:.*\\)?");
+    }
+
+    let parts: Vec<&str> = body.split('\\').filter(|part| !part.is_empty()).collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if *part == "**" {
+            // Any number of folders, including none
+            if !last {
+                out.push_str(r"(?:.*\\)?");
+            }
+            continue;
+        }
+        push_glob(&mut out, part, true, false);
+        if !last {
+            out.push_str(r"\\");
+        }
+    }
+    // A trailing "\**" leaves a dangling separator; the subtree suffix covers it
+    if out.ends_with(r"\\") && parts.last() == Some(&"**") {
+        out.truncate(out.len() - 2);
+    }
+
+    out.push_str(r"(?:\\.*)?$");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_name_patterns_match_at_any_depth() {
+        let exclude = ExcludePatterns::new(&["*.tmp", "node_modules"]).unwrap();
+        assert!(exclude.excludes("a.tmp"));
+        assert!(exclude.excludes("src\\cache\\B.TMP"));
+        assert!(exclude.excludes("web\\node_modules"));
+        assert!(exclude.excludes("web\\node_modules\\react\\index.js"));
+        assert!(!exclude.excludes("web\\node_modules_old\\index.js"));
+        assert!(!exclude.excludes("a.tmp.txt"));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        let exclude = ExcludePatterns::new(&["node_modules/**", "build/*.log", "/Windows/Temp"]).unwrap();
+        assert!(exclude.excludes("node_modules"));
+        assert!(exclude.excludes("app\\node_modules\\x\\y.js"));
+        assert!(exclude.excludes("proj\\build\\out.log"));
+        assert!(!exclude.excludes("proj\\build\\sub\\out.txt"));
+        assert!(exclude.excludes("Windows\\Temp\\setup.exe"));
+        assert!(!exclude.excludes("Users\\me\\Windows\\Temp\\x"));
+    }
+
+    #[test]
+    fn test_double_star_in_the_middle() {
+        let exclude = ExcludePatterns::new(&["src/**/generated"]).unwrap();
+        assert!(exclude.excludes("src\\generated\\a.rs"));
+        assert!(exclude.excludes("repo\\src\\x\\y\\generated"));
+        assert!(!exclude.excludes("src\\generated_old"));
+    }
+
+    #[test]
+    fn test_from_args() {
+        assert!(ExcludePatterns::from_args(&json!({})).unwrap().is_empty());
+        assert!(ExcludePatterns::from_args(&json!({"exclude": "*.tmp"})).unwrap().excludes("a.tmp"));
+        assert!(ExcludePatterns::from_args(&json!({"exclude": [1]})).is_err());
+        assert!(ExcludePatterns::from_args(&json!({"exclude": ["**"]})).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+//! Cargo features the service was built with
+//!
+//! Embedders that only want search can leave out the heavier dependencies:
+//!
+//! | Feature | Dependencies | Without it |
+//! |---------|--------------|------------|
+//! | `web-api` | axum, tower-http, tokio-stream | no HTTP API; MCP and the pipe only |
+//! | `hashing` | xxhash-rust | no `find_duplicates` |
+//! | `export` | rusqlite (bundled SQLite) | no `export_index` |
+//! | `image-info` | imagesize, kamadak-exif | images have no dimensions or capture date |
+//! | `media-info` | lofty, mp4, matroska | audio and video have no duration or codec |
+//! | `doc-stats` | lopdf, zip | documents have no page or word counts |
+//!
+//! All of them are on by default. Tools whose feature is off are left out of
+//! `tools/list` and refused if called anyway; filters on metadata that can't
+//! be read match nothing, as for a file whose metadata can't be read.
+
+/// Features and whether this build has them
+pub const FEATURES: &[(&str, bool)] = &[
+    ("web-api", cfg!(feature = "web-api")),
+    ("hashing", cfg!(feature = "hashing")),
+    ("export", cfg!(feature = "export")),
+    ("image-info", cfg!(feature = "image-info")),
+    ("media-info", cfg!(feature = "media-info")),
+    ("doc-stats", cfg!(feature = "doc-stats")),
+];
+
+/// Tools that need a feature
+const TOOL_FEATURES: &[(&str, &str)] = &[
+    ("find_duplicates", "hashing"),
+    ("export_index", "export"),
+];
+
+/// Whether this build has a feature
+pub fn has_feature(feature: &str) -> bool {
+    FEATURES.iter().any(|(name, enabled)| *name == feature && *enabled)
+}
+
+/// The feature a tool needs and this build doesn't have
+pub fn missing_feature(tool: &str) -> Option<&'static str> {
+    TOOL_FEATURES.iter()
+        .find(|(name, feature)| *name == tool && !has_feature(feature))
+        .map(|(_, feature)| *feature)
+}
+
+/// Features this build has, for the status output
+pub fn enabled_features() -> Vec<&'static str> {
+    FEATURES.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_features() {
+        assert_eq!(missing_feature("fast_search"), None);
+        assert_eq!(missing_feature("export_index").is_none(), cfg!(feature = "export"));
+        assert_eq!(missing_feature("find_duplicates").is_none(), has_feature("hashing"));
+        assert!(!has_feature("tui"));
+    }
+}
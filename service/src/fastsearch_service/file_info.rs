@@ -0,0 +1,266 @@
+//! Live file metadata for the `get_file_info` tool
+//!
+//! The cache holds what the MFT (or a directory index) said when the entry was
+//! last updated. `get_file_info` shows that next to a fresh look at the file on
+//! disk, so an assistant can tell whether a result is stale without a shell.
+//! Besides what `std::fs::Metadata` has, the live stat reads the hard link
+//! count, the file ID and the space allocated on disk, which on Windows need an
+//! open handle (`GetFileInformationByHandle` and `FileStandardInfo`).
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::dir_index::{metadata_attributes, metadata_reparse_tag};
+use super::mft_cache::{file_attribute, FileEntry};
+
+/// A file as the file system reports it now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveStat {
+    /// Length of the data, 0 for directories
+    pub size: u64,
+    /// Space allocated for the data (cluster-rounded, less for sparse or compressed files)
+    pub size_on_disk: Option<u64>,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    /// NTFS attribute flags (see [`file_attribute`]), approximated outside Windows
+    pub attributes: u32,
+    /// Number of hard links (names) of the file
+    pub link_count: Option<u32>,
+    /// MFT record number on NTFS, inode number elsewhere
+    pub file_id: Option<u64>,
+    pub is_directory: bool,
+    /// Reparse tag as far as std can tell (symbolic links only), 0 otherwise
+    pub reparse_tag: u32,
+}
+
+/// Read the live metadata of a file or folder, without following a final link
+pub fn stat(path: &Path) -> io::Result<LiveStat> {
+    let metadata = fs::symlink_metadata(path)?;
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let handle = os::handle_info(path, &metadata);
+    Ok(LiveStat {
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        size_on_disk: handle.size_on_disk,
+        created: metadata.created().ok(),
+        modified: metadata.modified().ok(),
+        accessed: metadata.accessed().ok(),
+        attributes: metadata_attributes(&name, &metadata),
+        link_count: handle.link_count,
+        file_id: handle.file_id,
+        is_directory: metadata.is_dir(),
+        reparse_tag: metadata_reparse_tag(&metadata),
+    })
+}
+
+/// What `Metadata` doesn't say about a file
+#[derive(Debug, Default)]
+struct HandleInfo {
+    size_on_disk: Option<u64>,
+    link_count: Option<u32>,
+    file_id: Option<u64>,
+}
+
+/// Attribute flags compared between the cache and the disk
+///
+/// Archive, not-content-indexed and the like change without anything a search
+/// cares about changing, so they don't count as differences.
+const COMPARED_ATTRIBUTES: u32 = file_attribute::READONLY
+    | file_attribute::HIDDEN
+    | file_attribute::SYSTEM
+    | file_attribute::DIRECTORY
+    | file_attribute::SPARSE
+    | file_attribute::REPARSE_POINT
+    | file_attribute::COMPRESSED
+    | file_attribute::ENCRYPTED;
+
+/// Fields on which a cache entry and the file on disk disagree
+///
+/// Times are compared to the second, as the tool reports them. Fields the live
+/// stat couldn't read aren't compared, and directories' link counts mean
+/// nothing useful.
+pub fn differences(cached: &FileEntry, live: &LiveStat) -> Vec<&'static str> {
+    let seconds = |time: SystemTime| time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let same_time = |cached: SystemTime, live: Option<SystemTime>| live.map_or(true, |live| seconds(cached) == seconds(live));
+
+    let mut differences = Vec::new();
+    if cached.is_directory != live.is_directory {
+        differences.push("is_directory");
+    }
+    if !cached.is_directory && cached.size != live.size {
+        differences.push("size");
+    }
+    if !same_time(cached.created, live.created) {
+        differences.push("created");
+    }
+    if !same_time(cached.modified, live.modified) {
+        differences.push("modified");
+    }
+    if cached.attributes & COMPARED_ATTRIBUTES != live.attributes & COMPARED_ATTRIBUTES {
+        differences.push("attributes");
+    }
+    if !cached.is_directory && live.link_count.map_or(false, |links| links != u32::from(cached.link_count)) {
+        differences.push("link_count");
+    }
+    differences
+}
+
+#[cfg(windows)]
+mod os {
+    use std::ffi::OsStr;
+    use std::fs::Metadata;
+    use std::mem::{size_of, zeroed};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr::null_mut;
+
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::fileapi::{CreateFileW, GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_STANDARD_INFO, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::minwinbase::FileStandardInfo;
+    use winapi::um::winbase::{GetFileInformationByHandleEx, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT};
+    use winapi::um::winnt::{FILE_READ_ATTRIBUTES, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE};
+
+    use super::HandleInfo;
+    use crate::fastsearch_service::usn_journal::FileReference;
+
+    /// Link count, MFT record and allocation of a file, read through a handle opened for attributes only
+    ///
+    /// Backup semantics let folders be opened; links are opened themselves
+    /// rather than their targets.
+    pub(super) fn handle_info(path: &Path, _metadata: &Metadata) -> HandleInfo {
+        let name: Vec<u16> = OsStr::new(path).encode_wide().chain(Some(0)).collect();
+        unsafe {
+            let handle = CreateFileW(
+                name.as_ptr(),
+                FILE_READ_ATTRIBUTES,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                null_mut(),
+                OPEN_EXISTING,
+                FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT,
+                null_mut(),
+            );
+            if handle == INVALID_HANDLE_VALUE {
+                return HandleInfo::default();
+            }
+
+            let mut info = HandleInfo::default();
+            let mut by_handle: BY_HANDLE_FILE_INFORMATION = zeroed();
+            if GetFileInformationByHandle(handle, &mut by_handle) != 0 {
+                info.link_count = Some(by_handle.nNumberOfLinks);
+                let reference = (u64::from(by_handle.nFileIndexHigh) << 32) | u64::from(by_handle.nFileIndexLow);
+                info.file_id = Some(FileReference::from_u64(reference).record);
+            }
+            let mut standard: FILE_STANDARD_INFO = zeroed();
+            let ok = GetFileInformationByHandleEx(
+                handle,
+                FileStandardInfo,
+                &mut standard as *mut _ as *mut _,
+                size_of::<FILE_STANDARD_INFO>() as DWORD,
+            );
+            if ok != 0 {
+                info.size_on_disk = Some(*standard.AllocationSize.QuadPart() as u64);
+            }
+            CloseHandle(handle);
+            info
+        }
+    }
+}
+
+#[cfg(unix)]
+mod os {
+    use std::fs::Metadata;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    use super::HandleInfo;
+
+    /// Link count, inode and allocation (in 512-byte blocks) of a file
+    pub(super) fn handle_info(_path: &Path, metadata: &Metadata) -> HandleInfo {
+        HandleInfo {
+            size_on_disk: Some(metadata.blocks() * 512),
+            link_count: Some(metadata.nlink().min(u64::from(u32::MAX)) as u32),
+            file_id: Some(metadata.ino()),
+        }
+    }
+}
+
+#[cfg(not(any(windows, unix)))]
+mod os {
+    use std::fs::Metadata;
+    use std::path::Path;
+
+    use super::HandleInfo;
+
+    pub(super) fn handle_info(_path: &Path, _metadata: &Metadata) -> HandleInfo {
+        HandleInfo::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry_for(live: &LiveStat) -> FileEntry {
+        FileEntry {
+            size: live.size,
+            is_directory: live.is_directory,
+            created: live.created.unwrap_or(UNIX_EPOCH),
+            modified: live.modified.unwrap_or(UNIX_EPOCH),
+            accessed: live.accessed.unwrap_or(UNIX_EPOCH),
+            attributes: live.attributes,
+            link_count: live.link_count.unwrap_or(1) as u16,
+            ..FileEntry::test(1, "notes.txt")
+        }
+    }
+
+    #[test]
+    fn test_stat_file_and_folder() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, b"hello world").unwrap();
+
+        let live = stat(&file).unwrap();
+        assert_eq!(live.size, 11);
+        assert!(!live.is_directory);
+        assert!(live.modified.is_some());
+        #[cfg(unix)]
+        {
+            assert_eq!(live.link_count, Some(1));
+            fs::hard_link(&file, dir.path().join("notes-link.txt")).unwrap();
+            assert_eq!(stat(&file).unwrap().link_count, Some(2));
+        }
+
+        let folder = stat(dir.path()).unwrap();
+        assert!(folder.is_directory);
+        assert_eq!(folder.size, 0);
+        assert_ne!(folder.attributes & file_attribute::DIRECTORY, 0);
+        assert!(stat(&dir.path().join("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_differences() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, b"hello").unwrap();
+        let live = stat(&file).unwrap();
+
+        let mut cached = entry_for(&live);
+        assert!(differences(&cached, &live).is_empty());
+
+        cached.size = 3;
+        cached.modified = UNIX_EPOCH;
+        cached.attributes |= file_attribute::HIDDEN;
+        assert_eq!(differences(&cached, &live), vec!["size", "modified", "attributes"]);
+
+        // Flags that don't matter to searches, and fields the stat couldn't read, are ignored
+        let mut cached = entry_for(&live);
+        cached.attributes |= 0x20; // Archive
+        cached.link_count = 4;
+        let unknown = LiveStat { link_count: None, created: None, ..live.clone() };
+        assert!(differences(&cached, &unknown).is_empty());
+    }
+}
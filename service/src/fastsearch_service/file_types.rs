@@ -19,6 +19,23 @@ pub enum DocumentType {
     Pdf,
 }
 
+impl DocumentType {
+    /// Name of the type as used in `doc_type` arguments
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentType::Text => "text",
+            DocumentType::Code => "code",
+            DocumentType::Image => "image",
+            DocumentType::Spreadsheet => "spreadsheet",
+            DocumentType::Presentation => "presentation",
+            DocumentType::Archive => "archive",
+            DocumentType::Audio => "audio",
+            DocumentType::Video => "video",
+            DocumentType::Pdf => "pdf",
+        }
+    }
+}
+
 lazy_static! {
     /// Global mapping of document types to their file extensions
     pub static ref EXTENSION_MAP: std::collections::HashMap<DocumentType, HashSet<&'static str>> = {
@@ -117,6 +134,27 @@ pub fn parse_document_type(s: &str) -> Option<DocumentType> {
     }
 }
 
+/// Document type of a file extension, if it has one
+///
+/// An extension listed under several types (`pdf` is also text) gets the
+/// most specific of them.
+pub fn document_type_of(extension: &str) -> Option<DocumentType> {
+    const SPECIFIC_FIRST: [DocumentType; 9] = [
+        DocumentType::Pdf,
+        DocumentType::Image,
+        DocumentType::Audio,
+        DocumentType::Video,
+        DocumentType::Spreadsheet,
+        DocumentType::Presentation,
+        DocumentType::Archive,
+        DocumentType::Code,
+        DocumentType::Text,
+    ];
+    let ext_lower = extension.to_lowercase();
+    SPECIFIC_FIRST.into_iter()
+        .find(|doc_type| EXTENSION_MAP.get(doc_type).map_or(false, |exts| exts.contains(ext_lower.as_str())))
+}
+
 /// Get all extensions for a document type
 pub fn get_extensions(doc_type: DocumentType) -> Vec<&'static str> {
     EXTENSION_MAP.get(&doc_type)
@@ -142,4 +180,12 @@ mod tests {
         assert_eq!(parse_document_type("TEXT"), Some(DocumentType::Text));
         assert_eq!(parse_document_type("invalid"), None);
     }
+
+    #[test]
+    fn test_document_type_of() {
+        assert_eq!(document_type_of("PDF"), Some(DocumentType::Pdf));
+        assert_eq!(document_type_of("md"), Some(DocumentType::Text));
+        assert_eq!(document_type_of("xyz"), None);
+        assert_eq!(parse_document_type(DocumentType::Spreadsheet.as_str()), Some(DocumentType::Spreadsheet));
+    }
 }
@@ -0,0 +1,602 @@
+//! Result filters applied to MFT cache entries by `fast_search`
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::Value;
+use strum::IntoEnumIterator;
+
+use super::file_types::{parse_document_type, DocumentType};
+use super::exclude::ExcludePatterns;
+use super::mft_cache::{file_attribute, FileEntry};
+use super::path_glob::PathGlob;
+use super::query::parse_size;
+use super::reparse::ReparseMode;
+
+/// Filters parsed from the `fast_search` tool arguments
+///
+/// The name pattern is handled separately by the matcher; everything else
+/// that narrows down results lives here so every candidate source (full
+/// scan, name index, ...) applies exactly the same rules.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    /// Lowercased substring the path must contain (the `path` argument as given, for globs)
+    pub path: String,
+    /// `path` read as a glob over folders, when it has wildcards
+    pub path_glob: Option<PathGlob>,
+    /// Levels below the folder matched by `path` (or the drive root) to search
+    pub max_depth: Option<usize>,
+    /// Extensions to include (lowercase, without leading .)
+    pub extensions: Option<HashSet<String>>,
+    /// Document type preset, if requested
+    pub doc_type: Option<DocumentType>,
+    /// Extensions belonging to the requested document type
+    pub doc_type_extensions: Option<HashSet<String>>,
+    /// Created/modified/accessed time windows
+    pub dates: DateFilters,
+    /// Minimum size in bytes (inclusive)
+    pub min_size: Option<u64>,
+    /// Maximum size in bytes (inclusive)
+    pub max_size: Option<u64>,
+    /// Globs for paths to leave out, including everything below excluded folders
+    pub exclude: ExcludePatterns,
+    /// Attribute flags matches must have or must not have
+    pub attributes: AttributeFilters,
+    /// Whether reparse points are kept, left out or the only matches
+    pub reparse: ReparseMode,
+}
+
+/// Tool arguments for attribute filters and the flag each one checks
+pub const ATTRIBUTE_ARGS: [(&str, u32); 6] = [
+    ("hidden", file_attribute::HIDDEN),
+    ("system", file_attribute::SYSTEM),
+    ("readonly", file_attribute::READONLY),
+    ("compressed", file_attribute::COMPRESSED),
+    ("encrypted", file_attribute::ENCRYPTED),
+    ("sparse", file_attribute::SPARSE),
+];
+
+/// Attribute flags to require or reject
+///
+/// Each attribute argument is tri-state: `true` keeps only entries with the
+/// flag, `false` drops them, and leaving it out doesn't look at the flag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttributeFilters {
+    /// Flags that must all be set
+    pub required: u32,
+    /// Flags that must all be clear
+    pub forbidden: u32,
+}
+
+impl AttributeFilters {
+    /// Parse the `hidden`, `system`, ... arguments
+    fn from_args(args: &Value) -> Result<Self> {
+        let mut filters = Self::default();
+        for (key, flag) in ATTRIBUTE_ARGS {
+            match &args[key] {
+                Value::Null => {}
+                Value::Bool(true) => filters.required |= flag,
+                Value::Bool(false) => filters.forbidden |= flag,
+                _ => return Err(anyhow!("'{}' must be true or false", key)),
+            }
+        }
+        Ok(filters)
+    }
+
+    /// Whether no attribute is constrained
+    pub fn is_empty(&self) -> bool {
+        self.required == 0 && self.forbidden == 0
+    }
+
+    /// Check a set of attribute flags
+    pub fn matches(&self, attributes: u32) -> bool {
+        attributes & self.required == self.required && attributes & self.forbidden == 0
+    }
+}
+
+/// A half-open time window: `after <= t < before`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    /// Inclusive lower bound
+    pub after: Option<SystemTime>,
+    /// Exclusive upper bound
+    pub before: Option<SystemTime>,
+}
+
+impl DateRange {
+    /// Parse `<field>_after` / `<field>_before` arguments
+    pub(crate) fn from_args(args: &Value, field: &str) -> Result<Self> {
+        Ok(Self {
+            after: parse_date_arg(args, &format!("{}_after", field))?,
+            before: parse_date_arg(args, &format!("{}_before", field))?,
+        })
+    }
+
+    /// Whether no bound is set
+    pub fn is_unbounded(&self) -> bool {
+        self.after.is_none() && self.before.is_none()
+    }
+
+    /// Check whether a timestamp falls within the window
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.after.map_or(true, |after| time >= after) && self.before.map_or(true, |before| time < before)
+    }
+}
+
+/// Date windows for each timestamp kept in the MFT cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateFilters {
+    /// Window for the creation time
+    pub created: DateRange,
+    /// Window for the last modification time
+    pub modified: DateRange,
+    /// Window for the last access time
+    pub accessed: DateRange,
+}
+
+impl DateFilters {
+    /// Check a cache entry against every date window
+    pub fn matches(&self, file: &FileEntry) -> bool {
+        self.created.contains(file.created)
+            && self.modified.contains(file.modified)
+            && self.accessed.contains(file.accessed)
+    }
+}
+
+/// Parse a size argument given as a byte count or a human-readable string ("10MB")
+fn parse_size_arg(args: &Value, key: &str) -> Result<Option<u64>> {
+    match &args[key] {
+        Value::Null => Ok(None),
+        Value::Number(n) => n
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| anyhow!("'{}' must be a non-negative number of bytes", key)),
+        Value::String(s) => parse_size(s).map(Some).map_err(|e| anyhow!("'{}': {}", key, e)),
+        _ => Err(anyhow!("'{}' must be a byte count or a size string like \"10MB\"", key)),
+    }
+}
+
+/// Parse the `doc_type` argument: one of the document type presets
+fn parse_doc_type_arg(args: &Value) -> Result<Option<DocumentType>> {
+    match &args["doc_type"] {
+        Value::Null => Ok(None),
+        Value::String(s) if s.trim().is_empty() => Ok(None),
+        Value::String(s) => parse_document_type(s.trim()).map(Some).ok_or_else(|| {
+            let known: Vec<&str> = DocumentType::iter().map(|doc_type| doc_type.as_str()).collect();
+            anyhow!("Unknown doc_type '{}'; expected one of: {}", s, known.join(", "))
+        }),
+        _ => Err(anyhow!("'doc_type' must be a string")),
+    }
+}
+
+/// Parse the `extensions` argument: a list, or a comma-separated string ("pdf,docx"),
+/// with or without leading dots
+fn parse_extensions_arg(args: &Value) -> Result<Option<HashSet<String>>> {
+    let normalize = |s: &str| s.trim().trim_start_matches('.').to_lowercase();
+    let extensions: HashSet<String> = match &args["extensions"] {
+        Value::Null => return Ok(None),
+        Value::String(s) => s.split(',').map(normalize).collect(),
+        Value::Array(items) => items.iter()
+            .map(|item| item.as_str().map(normalize).ok_or_else(|| anyhow!("'extensions' must only contain strings")))
+            .collect::<Result<_>>()?,
+        _ => return Err(anyhow!("'extensions' must be a list of extensions or a comma-separated string")),
+    };
+    let extensions: HashSet<String> = extensions.into_iter().filter(|ext| !ext.is_empty()).collect();
+    Ok((!extensions.is_empty()).then_some(extensions))
+}
+
+/// Parse the `max_depth` argument: a number of folder levels, at least 1
+pub fn parse_depth_arg(args: &Value) -> Result<Option<usize>> {
+    match &args["max_depth"] {
+        Value::Null => Ok(None),
+        value => value
+            .as_u64()
+            .filter(|depth| *depth >= 1)
+            .map(|depth| Some(depth as usize))
+            .ok_or_else(|| anyhow!("'max_depth' must be a number of folder levels (1 for direct children)")),
+    }
+}
+
+/// Levels a path is below the folder matched by a (lowercased) path filter
+///
+/// Direct children of the matching folder are at depth 1. When the filter
+/// occurs more than once, the last occurrence counts, so entries are measured
+/// from the nearest matching folder. Without a filter, depth counts from the
+/// drive root. `None` if the filter isn't part of the path.
+pub fn depth_below(path: &str, filter: &str) -> Option<usize> {
+    let filter = filter.trim_end_matches(['\\', '/']);
+    if filter.is_empty() {
+        return Some(path.split('\\').filter(|part| !part.is_empty()).count());
+    }
+    let path = path.to_lowercase();
+    let end = path.rfind(filter)? + filter.len();
+    Some(path[end..].matches('\\').count())
+}
+
+/// Parse a date argument given as a UNIX timestamp, `YYYY-MM-DD`, RFC 3339 date-time
+/// or a time relative to now (`"last 7 days"`, `"-2h"`)
+fn parse_date_arg(args: &Value, key: &str) -> Result<Option<SystemTime>> {
+    match &args[key] {
+        Value::Null => Ok(None),
+        Value::Number(n) => n
+            .as_u64()
+            .map(|secs| Some(UNIX_EPOCH + Duration::from_secs(secs)))
+            .ok_or_else(|| anyhow!("'{}' must be a non-negative UNIX timestamp", key)),
+        Value::String(s) => parse_date(s)
+            .or_else(|| parse_relative_time(s, SystemTime::now()))
+            .map(Some)
+            .ok_or_else(|| anyhow!(
+                "'{}' has invalid date '{}' (expected YYYY-MM-DD, RFC 3339, or a relative time like \"last 7 days\" or \"-2h\")",
+                key, s
+            )),
+        _ => Err(anyhow!("'{}' must be a date string or UNIX timestamp", key)),
+    }
+}
+
+/// Parse a date string; dates without a time zone are taken as UTC
+pub fn parse_date(s: &str) -> Option<SystemTime> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.into());
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S") {
+        return Some(DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc).into());
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc).into())
+}
+
+/// Parse a time relative to `now`: an amount of time ago, or the start of today or yesterday
+///
+/// The amount is a count and a unit (`2h`, `30 minutes`, `1 week`), written
+/// as `-2h`, `2h ago`, `last 7 days` or `past week` (a unit alone is one of
+/// it). Units are seconds, minutes (`m`), hours, days, weeks, months (`mo`,
+/// 30 days) and years (365 days). `today` and `yesterday` start at midnight
+/// UTC, like dates without a time zone.
+pub fn parse_relative_time(s: &str, now: SystemTime) -> Option<SystemTime> {
+    let s = s.trim().to_lowercase();
+    let midnight = |days_back: u64| {
+        let today = DateTime::<Utc>::from(now).date_naive().and_hms_opt(0, 0, 0)?;
+        let today: SystemTime = DateTime::<Utc>::from_naive_utc_and_offset(today, Utc).into();
+        today.checked_sub(Duration::from_secs(days_back * 86_400))
+    };
+    match s.as_str() {
+        "now" => return Some(now),
+        "today" => return midnight(0),
+        "yesterday" => return midnight(1),
+        _ => {}
+    }
+    
+    let (amount, relative) = if let Some(rest) = s.strip_prefix('-') {
+        (rest, true)
+    } else if let Some(rest) = s.strip_prefix("last ").or_else(|| s.strip_prefix("past ")) {
+        (rest, true)
+    } else if let Some(rest) = s.strip_suffix(" ago") {
+        (rest, true)
+    } else {
+        (s.as_str(), false)
+    };
+    let amount = amount.trim();
+    let digits = amount.find(|c: char| !c.is_ascii_digit()).unwrap_or(amount.len());
+    let (count, unit) = amount.split_at(digits);
+    let count: u64 = match count {
+        // "last week" is one week; a bare unit with nothing to make it relative isn't a time
+        "" if relative => 1,
+        "" => return None,
+        count => count.parse().ok()?,
+    };
+    let unit_secs: u64 = match unit.trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3_600,
+        "d" | "day" | "days" => 86_400,
+        "w" | "wk" | "wks" | "week" | "weeks" => 7 * 86_400,
+        "mo" | "month" | "months" => 30 * 86_400,
+        "y" | "yr" | "yrs" | "year" | "years" => 365 * 86_400,
+        _ => return None,
+    };
+    now.checked_sub(Duration::from_secs(count.checked_mul(unit_secs)?))
+}
+
+impl SearchFilters {
+    /// Parse filters from tool arguments
+    pub fn from_args(args: &Value, doc_type_extensions: &HashMap<DocumentType, HashSet<String>>) -> Result<Self> {
+        let path = args["path"].as_str().unwrap_or("").to_lowercase();
+        let path_glob = PathGlob::parse(&path)?;
+
+        // Parse document type and extension filters; one that can't be read is an
+        // error rather than no filter, which would return every type
+        let doc_type = parse_doc_type_arg(args)?;
+        let extensions = parse_extensions_arg(args)?;
+
+        // Parse created/modified/accessed windows
+        let dates = DateFilters {
+            created: DateRange::from_args(args, "created")?,
+            modified: DateRange::from_args(args, "modified")?,
+            accessed: DateRange::from_args(args, "accessed")?,
+        };
+
+        // Parse size bounds
+        let min_size = parse_size_arg(args, "min_size")?;
+        let max_size = parse_size_arg(args, "max_size")?;
+        if let (Some(min), Some(max)) = (min_size, max_size) {
+            if min > max {
+                return Err(anyhow!("min_size ({} bytes) is larger than max_size ({} bytes)", min, max));
+            }
+        }
+
+        let exclude = ExcludePatterns::from_args(args)?;
+        let attributes = AttributeFilters::from_args(args)?;
+        let reparse = ReparseMode::from_args(args)?;
+        let max_depth = parse_depth_arg(args)?;
+
+        Ok(Self {
+            path,
+            path_glob,
+            max_depth,
+            extensions,
+            doc_type,
+            doc_type_extensions: doc_type.map(|dt| doc_type_extensions.get(&dt).cloned().unwrap_or_default()),
+            dates,
+            min_size,
+            max_size,
+            exclude,
+            attributes,
+            reparse,
+        })
+    }
+
+    /// Extensions a match must have, when the filters pin them down
+    ///
+    /// Lets a search take its candidates from the extension index instead of
+    /// scanning every entry.
+    pub fn candidate_extensions(&self) -> Option<Vec<String>> {
+        let mut allowed: Vec<String> = match (&self.extensions, &self.doc_type_extensions) {
+            (Some(exts), Some(doc_exts)) => exts.intersection(doc_exts).cloned().collect(),
+            // An empty extension list only admits files without one
+            (Some(exts), None) if exts.is_empty() => return None,
+            (Some(exts), None) | (None, Some(exts)) => exts.iter().cloned().collect(),
+            (None, None) => return None,
+        };
+        allowed.sort();
+        Some(allowed)
+    }
+
+    /// Check whether a cache entry passes every filter
+    pub fn matches(&self, file: &FileEntry) -> bool {
+        // Apply path filter; globs turn away entries outside their literal prefix first
+        match &self.path_glob {
+            Some(glob) if !glob.is_match(&file.path) => return false,
+            None if !self.path.is_empty() && !file.path.to_lowercase().contains(&self.path) => return false,
+            _ => {}
+        }
+        if let Some(max_depth) = self.max_depth {
+            let depth = match &self.path_glob {
+                Some(glob) => glob.depth_below(&file.path),
+                None => depth_below(&file.path, &self.path),
+            };
+            if depth.map_or(true, |depth| depth > max_depth) {
+                return false;
+            }
+        }
+
+        // Apply extension filter if specified
+        if let Some(exts) = &self.extensions {
+            match &file.extension {
+                Some(ext) if !exts.contains(ext) => return false,
+                None if !exts.is_empty() => return false, // No extension but extensions were specified
+                _ => {}
+            }
+        }
+
+        // Apply document type filter
+        if let Some(exts) = &self.doc_type_extensions {
+            match &file.extension {
+                Some(ext) if exts.contains(ext) => {}
+                _ => return false, // No extension or not part of the document type
+            }
+        }
+
+        // Apply size filters (directories have no size of their own)
+        if self.min_size.is_some() || self.max_size.is_some() {
+            if file.is_directory
+                || self.min_size.map_or(false, |min| file.size < min)
+                || self.max_size.map_or(false, |max| file.size > max)
+            {
+                return false;
+            }
+        }
+
+        // Apply date filters
+        if !self.dates.matches(file) {
+            return false;
+        }
+
+        // Apply attribute filters
+        if !self.attributes.matches(file.attributes) || !self.reparse.matches(file) {
+            return false;
+        }
+
+        // Apply exclude patterns last; they're the most expensive check
+        !self.exclude.excludes(&file.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn file_modified(modified: SystemTime) -> FileEntry {
+        FileEntry { size: 1, modified, accessed: modified, ..FileEntry::test(1, "Users\\me\\report.docx") }
+    }
+
+    #[test]
+    fn test_parse_date_formats() {
+        let day = parse_date("2024-03-01").unwrap();
+        assert_eq!(day, UNIX_EPOCH + Duration::from_secs(1_709_251_200));
+        assert_eq!(parse_date("2024-03-01T00:00:00Z"), Some(day));
+        assert_eq!(parse_date("2024-03-01T01:00:00+01:00"), Some(day));
+        assert_eq!(parse_date("2024-03-01T00:00:00"), Some(day));
+        assert!(parse_date("March 1st").is_none());
+    }
+
+    #[test]
+    fn test_parse_relative_times() {
+        let now = parse_date("2024-03-10T12:30:00Z").unwrap();
+        let ago = |secs: u64| Some(now - Duration::from_secs(secs));
+        assert_eq!(parse_relative_time("-2h", now), ago(2 * 3_600));
+        assert_eq!(parse_relative_time("last 7 days", now), ago(7 * 86_400));
+        assert_eq!(parse_relative_time("Past Week", now), ago(7 * 86_400));
+        assert_eq!(parse_relative_time("30 minutes ago", now), ago(30 * 60));
+        assert_eq!(parse_relative_time("90m", now), ago(90 * 60));
+        assert_eq!(parse_relative_time("last 3 months", now), ago(90 * 86_400));
+        assert_eq!(parse_relative_time("1y", now), ago(365 * 86_400));
+        assert_eq!(parse_relative_time("today", now), parse_date("2024-03-10"));
+        assert_eq!(parse_relative_time("yesterday", now), parse_date("2024-03-09"));
+        assert_eq!(parse_relative_time("now", now), Some(now));
+        assert!(parse_relative_time("week", now).is_none());
+        assert!(parse_relative_time("last fortnight", now).is_none());
+        assert!(parse_relative_time("-2x", now).is_none());
+    }
+
+    #[test]
+    fn test_relative_date_arguments() {
+        let filters = SearchFilters::from_args(&json!({"modified_after": "last 7 days"}), &HashMap::new()).unwrap();
+        let now = SystemTime::now();
+        assert!(filters.matches(&file_modified(now - Duration::from_secs(86_400))));
+        assert!(!filters.matches(&file_modified(now - Duration::from_secs(8 * 86_400))));
+
+        let err = SearchFilters::from_args(&json!({"created_before": "sometime"}), &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("relative time"));
+    }
+
+    #[test]
+    fn test_modified_window() {
+        let filters = SearchFilters::from_args(
+            &json!({"modified_after": "2024-01-01", "modified_before": 1_709_251_200}),
+            &HashMap::new(),
+        ).unwrap();
+
+        assert!(filters.matches(&file_modified(parse_date("2024-02-15").unwrap())));
+        assert!(filters.matches(&file_modified(parse_date("2024-01-01").unwrap())));
+        assert!(!filters.matches(&file_modified(parse_date("2024-03-01").unwrap())));
+        assert!(!filters.matches(&file_modified(parse_date("2023-12-31").unwrap())));
+    }
+
+    #[test]
+    fn test_size_bounds() {
+        let filters = SearchFilters::from_args(
+            &json!({"min_size": "1KB", "max_size": 4096}),
+            &HashMap::new(),
+        ).unwrap();
+        assert_eq!(filters.min_size, Some(1024));
+
+        let mut file = file_modified(UNIX_EPOCH);
+        file.size = 2048;
+        assert!(filters.matches(&file));
+        file.size = 4097;
+        assert!(!filters.matches(&file));
+        file.size = 100;
+        assert!(!filters.matches(&file));
+
+        assert!(SearchFilters::from_args(&json!({"min_size": "10MB", "max_size": "1MB"}), &HashMap::new()).is_err());
+        assert!(SearchFilters::from_args(&json!({"max_size": "big"}), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let filters = SearchFilters::from_args(&json!({"exclude": ["Users/*/AppData/**", "*.tmp"]}), &HashMap::new()).unwrap();
+
+        let mut file = file_modified(UNIX_EPOCH);
+        assert!(filters.matches(&file));
+        file.path = "Users\\me\\AppData\\Local\\report.docx".to_string();
+        assert!(!filters.matches(&file));
+        file.path = "Users\\me\\~report.tmp".to_string();
+        assert!(!filters.matches(&file));
+
+        assert!(SearchFilters::from_args(&json!({"exclude": {"dir": "x"}}), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_attribute_filters() {
+        let filters = SearchFilters::from_args(&json!({"hidden": true, "system": false}), &HashMap::new()).unwrap();
+
+        let mut file = file_modified(UNIX_EPOCH);
+        assert!(!filters.matches(&file));
+        file.attributes = file_attribute::HIDDEN | file_attribute::READONLY;
+        assert!(filters.matches(&file));
+        file.attributes |= file_attribute::SYSTEM;
+        assert!(!filters.matches(&file));
+
+        assert!(SearchFilters::from_args(&json!({}), &HashMap::new()).unwrap().attributes.is_empty());
+        assert!(SearchFilters::from_args(&json!({"sparse": "yes"}), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_reparse_points_filter() {
+        let only = SearchFilters::from_args(&json!({"reparse_points": "only"}), &HashMap::new()).unwrap();
+        let skip = SearchFilters::from_args(&json!({"reparse_points": "skip"}), &HashMap::new()).unwrap();
+
+        let mut file = file_modified(UNIX_EPOCH);
+        assert!(!only.matches(&file));
+        assert!(skip.matches(&file));
+        file.reparse_tag = 0xA000_000C; // symbolic link
+        assert!(only.matches(&file));
+        assert!(!skip.matches(&file));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let filters = SearchFilters::from_args(&json!({"path": "users\\", "max_depth": 2}), &HashMap::new()).unwrap();
+
+        let mut file = file_modified(UNIX_EPOCH);
+        assert!(filters.matches(&file));
+        file.path = "Users\\me\\node_modules\\x\\index.js".to_string();
+        assert!(!filters.matches(&file));
+        // Measured from the nearest matching folder
+        file.path = "Backup\\Users\\Old\\Users\\me\\a.txt".to_string();
+        assert!(filters.matches(&file));
+
+        assert_eq!(depth_below("Windows\\System32\\cmd.exe", ""), Some(3));
+        assert_eq!(depth_below("Windows\\System32", "system32"), Some(0));
+        assert_eq!(depth_below("Windows\\System32", "users"), None);
+        assert!(SearchFilters::from_args(&json!({"max_depth": 0}), &HashMap::new()).is_err());
+
+        // Globs measure from the folder they matched
+        let filters = SearchFilters::from_args(&json!({"path": "C:\\Users\\*\\Downloads", "max_depth": 1}), &HashMap::new()).unwrap();
+        file.path = "Users\\me\\Downloads\\setup.exe".to_string();
+        assert!(filters.matches(&file));
+        file.path = "Users\\me\\Downloads\\old\\setup.exe".to_string();
+        assert!(!filters.matches(&file));
+        file.path = "Users\\me\\Desktop\\setup.exe".to_string();
+        assert!(!filters.matches(&file));
+        assert!(SearchFilters::from_args(&json!({"path": "C:\\**"}), &HashMap::new()).is_err());
+        assert!(SearchFilters::from_args(&json!({"max_depth": "2"}), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_doc_type_and_extensions() {
+        let filters = SearchFilters::from_args(&json!({"doc_type": "Video", "extensions": ".PDF, docx"}), &HashMap::new()).unwrap();
+        assert_eq!(filters.doc_type, Some(DocumentType::Video));
+        let extensions = filters.extensions.unwrap();
+        assert!(extensions.contains("pdf") && extensions.contains("docx"));
+        assert_eq!(extensions.len(), 2);
+
+        let err = SearchFilters::from_args(&json!({"doc_type": "videos"}), &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("video"));
+        assert!(SearchFilters::from_args(&json!({"extensions": [1]}), &HashMap::new()).is_err());
+        assert!(SearchFilters::from_args(&json!({"extensions": []}), &HashMap::new()).unwrap().extensions.is_none());
+    }
+
+    #[test]
+    fn test_invalid_date_is_rejected() {
+        let err = SearchFilters::from_args(&json!({"created_after": "someday"}), &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("created_after"));
+        assert!(SearchFilters::from_args(&json!({"accessed_before": true}), &HashMap::new()).is_err());
+    }
+}
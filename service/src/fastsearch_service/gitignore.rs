@@ -0,0 +1,426 @@
+//! `.gitignore` support for `fast_search`
+//!
+//! With `respect_gitignore`, matches inside a git repository (a folder that
+//! holds a `.git` entry) are checked against the ignore files between the
+//! repository root and the match, the way git does it: a deeper file overrides
+//! a shallower one, the last matching line wins, and nothing below an ignored
+//! folder can be re-included. `.ignore` files (as used by ripgrep and fd) are
+//! read after `.gitignore` in the same folder, so they take precedence. The
+//! `.git` folder itself is always left out. Matches outside any repository are
+//! not affected.
+//!
+//! Ignore files are found through the MFT cache and read from disk on first
+//! use; the parsed rules are kept until the file's modification time changes.
+//! Patterns match case-insensitively, like git's default on Windows.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use log::debug;
+use parking_lot::RwLock;
+use regex::Regex;
+
+use super::mft_cache::{disk_path, FileEntry};
+
+/// Ignore file names, in increasing order of precedence
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// Ignore files larger than this are skipped
+const MAX_IGNORE_FILE_SIZE: u64 = 1024 * 1024;
+
+/// One pattern line of an ignore file
+#[derive(Debug)]
+struct IgnoreRule {
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// Parsed rules of a single ignore file
+#[derive(Debug, Default)]
+pub struct IgnoreFile {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+    /// Parse ignore file contents; invalid lines are skipped
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .filter_map(parse_rule)
+            .collect();
+        Self { rules }
+    }
+
+    /// Number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether the file has no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Verdict of the last rule matching a path relative to the file's folder
+    ///
+    /// `Some(true)` means ignored, `Some(false)` re-included by a `!` rule, and
+    /// None that no rule applies.
+    pub fn decide(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.regex.is_match(relative))
+            .map(|rule| !rule.negated)
+    }
+}
+
+fn parse_rule(line: &str) -> Option<IgnoreRule> {
+    // Trailing spaces don't count unless escaped
+    let trimmed = line.trim_end();
+    let line = if trimmed.ends_with('\\') && line.len() > trimmed.len() {
+        &line[..trimmed.len() + 1]
+    } else {
+        trimmed
+    };
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    // A slash anywhere but at the end anchors the pattern to the file's folder
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    if pattern.is_empty() {
+        return None;
+    }
+
+    match Regex::new(&ignore_pattern_to_regex(pattern, anchored)) {
+        Ok(regex) => Some(IgnoreRule { regex, negated, dir_only }),
+        Err(e) => {
+            debug!("Skipping ignore pattern '{}': {}", line, e);
+            None
+        }
+    }
+}
+
+/// Translate a gitignore pattern into a regex over backslash-separated relative paths
+fn ignore_pattern_to_regex(pattern: &str, anchored: bool) -> String {
+    let mut out = String::from("(?i)^");
+    if !anchored {
+        out.push_str(r"(?:.*\\)?");
+    }
+
+    let parts: Vec<&str> = pattern.split('/').collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if *part == "**" {
+            // Leading or inner "**" spans any number of folders; a trailing one everything inside
+            out.push_str(if last { ".*" } else { r"(?:.*\\)?" });
+            continue;
+        }
+
+        let mut chars = part.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '*' => out.push_str(r"[^\\]*"),
+                '?' => out.push_str(r"[^\\]"),
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        out.push_str(&regex::escape(&escaped.to_string()));
+                    }
+                }
+                '[' => {
+                    let class: String = chars.clone().take_while(|c| *c != ']').collect();
+                    if class.is_empty() || chars.clone().nth(class.chars().count()).is_none() {
+                        out.push_str(r"\[");
+                        continue;
+                    }
+                    for _ in 0..=class.chars().count() {
+                        chars.next();
+                    }
+                    let (negate, class) = match class.strip_prefix(['!', '^']) {
+                        Some(rest) => (true, rest),
+                        None => (false, class.as_str()),
+                    };
+                    out.push('[');
+                    if negate {
+                        out.push('^');
+                    }
+                    for c in class.chars() {
+                        if matches!(c, '\\' | '[' | '&' | '~' | '^') {
+                            out.push('\\');
+                        }
+                        out.push(c);
+                    }
+                    out.push(']');
+                }
+                _ => out.push_str(&regex::escape(&ch.to_string())),
+            }
+        }
+        if !last {
+            out.push_str(r"\\");
+        }
+    }
+
+    out.push('$');
+    out
+}
+
+/// Rules of an ignore file and the modification time they were read at
+type CachedIgnoreFile = (SystemTime, Arc<IgnoreFile>);
+
+/// Parsed ignore files, shared between searches
+#[derive(Debug, Default)]
+pub struct IgnoreFileCache {
+    // Keyed by drive and path
+    files: RwLock<HashMap<(char, String), CachedIgnoreFile>>,
+}
+
+impl IgnoreFileCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rules of an ignore file, read from below `root` unless already cached
+    fn load(&self, drive: char, root: &Path, entry: &FileEntry) -> Option<Arc<IgnoreFile>> {
+        let key = (drive, entry.path.clone());
+        if let Some((modified, rules)) = self.files.read().get(&key) {
+            if *modified == entry.modified {
+                return Some(Arc::clone(rules));
+            }
+        }
+        if entry.size > MAX_IGNORE_FILE_SIZE {
+            return None;
+        }
+
+        let path = disk_path(root, &entry.path);
+        let rules = match std::fs::read(&path) {
+            Ok(bytes) => Arc::new(IgnoreFile::parse(&String::from_utf8_lossy(&bytes))),
+            Err(e) => {
+                debug!("Can't read ignore file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        self.files.write().insert(key, (entry.modified, Arc::clone(&rules)));
+        Some(rules)
+    }
+}
+
+/// Answers "is this match ignored?" for one drive during a single search
+///
+/// Borrows the cache maps the search already holds locks on; repository roots,
+/// folder verdicts and ignore files are looked up once per folder.
+pub struct GitignoreFilter<'a> {
+    cache: &'a IgnoreFileCache,
+    drive: char,
+    root: PathBuf,
+    files: &'a HashMap<u64, FileEntry>,
+    paths: &'a HashMap<String, u64>,
+    repo_roots: RefCell<HashMap<String, Option<String>>>,
+    ignored_dirs: RefCell<HashMap<String, bool>>,
+    dir_rules: RefCell<HashMap<String, Vec<Arc<IgnoreFile>>>>,
+}
+
+impl<'a> GitignoreFilter<'a> {
+    /// Filter for a drive whose files live below `root` on disk
+    pub fn new(
+        cache: &'a IgnoreFileCache,
+        drive: char,
+        root: PathBuf,
+        files: &'a HashMap<u64, FileEntry>,
+        paths: &'a HashMap<String, u64>,
+    ) -> Self {
+        Self {
+            cache,
+            drive,
+            root,
+            files,
+            paths,
+            repo_roots: RefCell::new(HashMap::new()),
+            ignored_dirs: RefCell::new(HashMap::new()),
+            dir_rules: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a cache entry is ignored by its repository
+    pub fn is_ignored(&self, file: &FileEntry) -> bool {
+        let parent = parent_of(&file.path);
+        let Some(repo) = self.repo_root(parent) else {
+            return false;
+        };
+        if relative_to(&repo, &file.path).split('\\').any(|part| part.eq_ignore_ascii_case(".git")) {
+            return true;
+        }
+        self.dir_ignored(&repo, parent) || self.decide(&repo, &file.path, file.is_directory)
+    }
+
+    /// Nearest folder at or above `dir` holding a `.git` entry
+    fn repo_root(&self, dir: &str) -> Option<String> {
+        if let Some(root) = self.repo_roots.borrow().get(dir) {
+            return root.clone();
+        }
+        let root = if self.paths.contains_key(&join(dir, ".git")) {
+            Some(dir.to_string())
+        } else if dir.is_empty() {
+            None
+        } else {
+            self.repo_root(parent_of(dir))
+        };
+        self.repo_roots.borrow_mut().insert(dir.to_string(), root.clone());
+        root
+    }
+
+    /// Whether a folder inside the repository (or any folder above it) is ignored
+    fn dir_ignored(&self, repo: &str, dir: &str) -> bool {
+        if dir.len() <= repo.len() {
+            return false;
+        }
+        if let Some(ignored) = self.ignored_dirs.borrow().get(dir) {
+            return *ignored;
+        }
+        let ignored = self.dir_ignored(repo, parent_of(dir)) || self.decide(repo, dir, true);
+        self.ignored_dirs.borrow_mut().insert(dir.to_string(), ignored);
+        ignored
+    }
+
+    /// Apply the ignore files from the repository root down to the entry's folder
+    fn decide(&self, repo: &str, path: &str, is_dir: bool) -> bool {
+        let mut dirs = Vec::new();
+        let mut dir = parent_of(path);
+        loop {
+            dirs.push(dir);
+            if dir.len() <= repo.len() {
+                break;
+            }
+            dir = parent_of(dir);
+        }
+
+        let mut ignored = false;
+        for dir in dirs.into_iter().rev() {
+            let relative = relative_to(dir, path);
+            for rules in self.rules_at(dir) {
+                if let Some(verdict) = rules.decide(relative, is_dir) {
+                    ignored = verdict;
+                }
+            }
+        }
+        ignored
+    }
+
+    /// Ignore files directly in a folder, in precedence order
+    fn rules_at(&self, dir: &str) -> Vec<Arc<IgnoreFile>> {
+        if let Some(rules) = self.dir_rules.borrow().get(dir) {
+            return rules.clone();
+        }
+        let rules: Vec<Arc<IgnoreFile>> = IGNORE_FILES
+            .iter()
+            .filter_map(|name| self.paths.get(&join(dir, name)))
+            .filter_map(|id| self.files.get(id))
+            .filter(|entry| !entry.is_directory)
+            .filter_map(|entry| self.cache.load(self.drive, &self.root, entry))
+            .collect();
+        self.dir_rules.borrow_mut().insert(dir.to_string(), rules.clone());
+        rules
+    }
+}
+
+/// Folder part of a relative path ("" for entries at the drive root)
+fn parent_of(path: &str) -> &str {
+    path.rfind('\\').map_or("", |i| &path[..i])
+}
+
+/// `path` relative to the folder `dir` it's inside of
+fn relative_to<'p>(dir: &str, path: &'p str) -> &'p str {
+    if dir.is_empty() {
+        path
+    } else {
+        path.get(dir.len() + 1..).unwrap_or("")
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}\\{}", dir, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(id: u64, path: &str, is_directory: bool) -> FileEntry {
+        FileEntry { is_directory, ..FileEntry::test(id, path) }
+    }
+
+    #[test]
+    fn test_rule_semantics() {
+        let rules = IgnoreFile::parse("# comment\n\n*.log\n!keep.log\nbuild/\n/dist\ndocs/**/*.pdf\n\\#notes\n");
+        assert_eq!(rules.len(), 6);
+        assert_eq!(rules.decide("app.log", false), Some(true));
+        assert_eq!(rules.decide("sub\\KEEP.log", false), Some(false));
+        assert_eq!(rules.decide("src\\build", true), Some(true));
+        assert_eq!(rules.decide("src\\build", false), None);
+        assert_eq!(rules.decide("dist", true), Some(true));
+        assert_eq!(rules.decide("src\\dist", true), None);
+        assert_eq!(rules.decide("docs\\a\\b\\x.pdf", false), Some(true));
+        assert_eq!(rules.decide("#notes", false), Some(true));
+        assert_eq!(rules.decide("main.rs", false), None);
+    }
+
+    #[test]
+    fn test_character_classes() {
+        let rules = IgnoreFile::parse("*.py[co]\nfile[!0-9].txt\n");
+        assert_eq!(rules.decide("a.pyc", false), Some(true));
+        assert_eq!(rules.decide("a.py", false), None);
+        assert_eq!(rules.decide("filea.txt", false), Some(true));
+        assert_eq!(rules.decide("file1.txt", false), None);
+    }
+
+    #[test]
+    fn test_filter_applies_nested_ignore_files() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join("web")).unwrap();
+        std::fs::write(repo.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(repo.join("web").join(".gitignore"), "!debug.log\nnode_modules\n").unwrap();
+
+        let entries = vec![
+            entry(1, "repo", true),
+            entry(2, "repo\\.git", true),
+            entry(3, "repo\\.gitignore", false),
+            entry(4, "repo\\target", true),
+            entry(5, "repo\\target\\app.exe", false),
+            entry(6, "repo\\web", true),
+            entry(7, "repo\\web\\.gitignore", false),
+            entry(8, "repo\\web\\debug.log", false),
+            entry(9, "repo\\web\\error.log", false),
+            entry(10, "repo\\web\\node_modules\\x\\index.js", false),
+            entry(11, "repo\\src\\main.rs", false),
+            entry(12, "repo\\.git\\HEAD", false),
+            entry(13, "other\\target\\app.exe", false),
+        ];
+        let files: HashMap<u64, FileEntry> = entries.iter().map(|e| (e.id, e.clone())).collect();
+        let paths: HashMap<String, u64> = entries.iter().map(|e| (e.path.clone(), e.id)).collect();
+
+        let cache = IgnoreFileCache::new();
+        let filter = GitignoreFilter::new(&cache, 'C', dir.path().to_path_buf(), &files, &paths);
+        let ignored: Vec<u64> = entries.iter().filter(|e| filter.is_ignored(e)).map(|e| e.id).collect();
+        assert_eq!(ignored, vec![2, 4, 5, 9, 10, 12]);
+    }
+}
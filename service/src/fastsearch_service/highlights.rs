@@ -0,0 +1,74 @@
+//! Where a `fast_search` result matched, for highlighting
+//!
+//! Every result carries the parts of its name the pattern matched and the
+//! part of its path the `path` filter matched, as `TextHighlight`s with
+//! character offsets, so a UI can mark them without matching again.
+
+use std::ops::Range;
+
+use fastsearch_shared::TextHighlight;
+
+use super::matcher::PatternMatcher;
+
+/// Highlights in a result's name, and in its path (as shown, without the drive) for a lowercased path filter
+pub fn highlights(matcher: &PatternMatcher, path_filter: &str, name: &str, path: &str) -> Vec<TextHighlight> {
+    let mut highlights: Vec<TextHighlight> = matcher.match_spans(name).into_iter()
+        .map(|span| highlight("name", name, span))
+        .collect();
+    if let Some(span) = find_ignoring_case(path, path_filter) {
+        highlights.push(highlight("path", path, span));
+    }
+    highlights
+}
+
+/// A highlight of the bytes `span` of `text`, counted in characters
+fn highlight(field: &str, text: &str, span: Range<usize>) -> TextHighlight {
+    TextHighlight {
+        field: field.to_string(),
+        start: text[..span.start].chars().count(),
+        end: text[..span.end].chars().count(),
+        snippet: text[span].to_string(),
+    }
+}
+
+/// Bytes of the first occurrence of a lowercased `needle` in `text`, ignoring case
+fn find_ignoring_case(text: &str, needle: &str) -> Option<Range<usize>> {
+    let needle: Vec<char> = needle.chars().collect();
+    if needle.is_empty() {
+        return None;
+    }
+    // Lowercased a character at a time, so positions stay those of `text`
+    let chars: Vec<(usize, char)> = text.char_indices()
+        .map(|(i, c)| (i, c.to_lowercase().next().unwrap_or(c)))
+        .collect();
+    let at = chars.windows(needle.len())
+        .position(|window| window.iter().map(|(_, c)| c).eq(needle.iter()))?;
+    let end = chars.get(at + needle.len()).map_or(text.len(), |(i, _)| *i);
+    Some(chars[at].0..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::matcher::{compile_pattern, SearchType};
+
+    #[test]
+    fn test_name_and_path_highlights() {
+        let matcher = compile_pattern("*report*.docx", SearchType::Glob).unwrap();
+        let found = highlights(&matcher, "ünterlagen\\", "Q3 Report.docx", "Users\\me\\Ünterlagen\\Q3 Report.docx");
+        let spans: Vec<(&str, usize, usize, &str)> = found.iter()
+            .map(|h| (h.field.as_str(), h.start, h.end, h.snippet.as_str()))
+            .collect();
+        assert_eq!(spans, vec![
+            // Literal parts that touch are one highlight
+            ("name", 3, 14, "Report.docx"),
+            ("path", 9, 20, "Ünterlagen\\"),
+        ]);
+
+        // Offsets count characters, not bytes
+        let fuzzy = compile_pattern("rsm", SearchType::Fuzzy).unwrap();
+        let found = highlights(&fuzzy, "", "résumé.pdf", "résumé.pdf");
+        assert_eq!(found.iter().map(|h| (h.start, h.end)).collect::<Vec<_>>(), vec![(0, 1), (2, 3), (4, 5)]);
+        assert!(highlights(&matcher, "", "notes.txt", "notes.txt").is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! Guidance returned with `fast_search` responses that found nothing
+//!
+//! An empty page on its own doesn't say whether the name was misspelled, the
+//! file lives on another drive, or the path filter was too narrow. When a
+//! search finds nothing, `fast_search` works out which of these applies from
+//! the cache indexes and returns the findings as structured hints:
+//!
+//! - names (or extensions, for `*.ext` patterns) within a small edit distance
+//!   of the pattern
+//! - other drives with cached matches for the same search
+//! - how many matches the path filter left out, and where they are
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::matcher::SearchType;
+use super::mft_cache::FileEntry;
+
+/// Suggestions listed per kind
+pub const MAX_SUGGESTIONS: usize = 5;
+
+/// Folders listed for matches outside the path filter
+pub const MAX_EXAMPLE_FOLDERS: usize = 3;
+
+/// Patterns shorter than this get no spelling suggestions
+const MIN_SUGGEST_LEN: usize = 3;
+
+/// A name or extension close to what was searched for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Suggestion {
+    /// Name as stored on disk, or extension without the dot
+    pub text: String,
+    /// Edit distance from the searched name
+    pub distance: usize,
+    /// Entries with this name or extension in the searched drives
+    pub count: u64,
+}
+
+/// Another drive that has matches for the search
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DriveMatches {
+    pub drive: String,
+    pub matches: u64,
+}
+
+/// Matches the path filter left out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathFilterHint {
+    /// The path filter as given
+    pub path: String,
+    /// Matches without the path filter
+    pub matches_without: u64,
+    /// Folders holding some of those matches
+    pub folders: Vec<String>,
+}
+
+/// Structured hints for a search without results
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NoResultHints {
+    pub similar_names: Vec<Suggestion>,
+    pub similar_extensions: Vec<Suggestion>,
+    pub other_drives: Vec<DriveMatches>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_filter: Option<PathFilterHint>,
+}
+
+/// What spelling suggestions are made against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpellingTarget {
+    /// A literal name (lowercased)
+    Name(String),
+    /// The extension of a `*.ext` glob (lowercased, without the dot)
+    Extension(String),
+}
+
+impl SpellingTarget {
+    /// Literal part of a pattern worth suggesting spellings for
+    ///
+    /// Globs with wildcards other than a leading `*.` and regexes have no
+    /// single spelling to compare against.
+    pub fn of(pattern: &str, search_type: SearchType) -> Option<Self> {
+        let pattern = pattern.trim().to_lowercase();
+        let target = match search_type {
+            SearchType::Regex | SearchType::Camel => return None,
+            SearchType::Glob => match pattern.strip_prefix("*.") {
+                Some(ext) if !ext.contains(['*', '?']) => SpellingTarget::Extension(ext.to_string()),
+                _ if pattern.contains(['*', '?']) => return None,
+                _ => SpellingTarget::Name(pattern),
+            },
+            SearchType::Exact | SearchType::Fuzzy => SpellingTarget::Name(pattern),
+        };
+        let len = match &target {
+            SpellingTarget::Name(name) | SpellingTarget::Extension(name) => name.chars().count(),
+        };
+        (len >= MIN_SUGGEST_LEN).then_some(target)
+    }
+}
+
+/// Largest edit distance suggested for a word of `len` characters
+fn max_distance(len: usize) -> usize {
+    if len <= 4 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Levenshtein distance between `a` and `b`, if it is at most `max`
+pub(crate) fn distance_within(a: &[char], b: &str, max: usize) -> Option<usize> {
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        let mut row_min = cur[0];
+        for (j, cb) in b.iter().enumerate() {
+            cur[j + 1] = (prev[j] + usize::from(ca != cb)).min(prev[j + 1] + 1).min(cur[j] + 1);
+            row_min = row_min.min(cur[j + 1]);
+        }
+        // Distances never shrink from one row to the next
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    (prev[b.len()] <= max).then_some(prev[b.len()])
+}
+
+/// Closest first, then most common
+fn rank(found: HashMap<String, Suggestion>) -> Vec<Suggestion> {
+    let mut found: Vec<Suggestion> = found.into_values().collect();
+    found.sort_by(|a, b| a.distance.cmp(&b.distance).then(b.count.cmp(&a.count)).then_with(|| a.text.cmp(&b.text)));
+    found.truncate(MAX_SUGGESTIONS);
+    found
+}
+
+/// Names in the name indexes close to `target`, compared with and without extension
+pub fn similar_names<'a>(
+    target: &str,
+    caches: impl IntoIterator<Item = (&'a HashMap<u64, FileEntry>, &'a HashMap<String, Vec<u64>>)>,
+) -> Vec<Suggestion> {
+    let chars: Vec<char> = target.chars().collect();
+    let max = max_distance(chars.len());
+    let mut found: HashMap<String, Suggestion> = HashMap::new();
+    for (files, names) in caches {
+        for (name, ids) in names {
+            let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+            let distance = match (distance_within(&chars, name, max), distance_within(&chars, stem, max)) {
+                (Some(a), Some(b)) => a.min(b),
+                (Some(d), None) | (None, Some(d)) => d,
+                (None, None) => continue,
+            };
+            let Some(file) = ids.iter().find_map(|id| files.get(id)) else {
+                continue;
+            };
+            found
+                .entry(name.clone())
+                .or_insert_with(|| Suggestion { text: file.name.clone(), distance, count: 0 })
+                .count += ids.len() as u64;
+        }
+    }
+    rank(found)
+}
+
+/// Extensions in the extension indexes close to `target`
+pub fn similar_extensions<'a>(
+    target: &str,
+    indexes: impl IntoIterator<Item = &'a HashMap<String, Vec<u64>>>,
+) -> Vec<Suggestion> {
+    let chars: Vec<char> = target.chars().collect();
+    let max = max_distance(chars.len());
+    let mut found: HashMap<String, Suggestion> = HashMap::new();
+    for extensions in indexes {
+        for (ext, ids) in extensions {
+            if let Some(distance) = distance_within(&chars, ext, max) {
+                found
+                    .entry(ext.clone())
+                    .or_insert_with(|| Suggestion { text: ext.clone(), distance, count: 0 })
+                    .count += ids.len() as u64;
+            }
+        }
+    }
+    rank(found)
+}
+
+impl NoResultHints {
+    /// Whether there is anything to suggest
+    pub fn is_empty(&self) -> bool {
+        self.similar_names.is_empty()
+            && self.similar_extensions.is_empty()
+            && self.other_drives.is_empty()
+            && self.path_filter.is_none()
+    }
+
+    /// One line per hint, for the text part of the response
+    pub fn describe(&self) -> Vec<String> {
+        let list = |suggestions: &[Suggestion], prefix: &str| {
+            suggestions.iter()
+                .map(|s| format!("{}{} ({})", prefix, s.text, s.count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut lines = Vec::new();
+        if !self.similar_names.is_empty() {
+            lines.push(format!("Similar names: {}", list(&self.similar_names, "")));
+        }
+        if !self.similar_extensions.is_empty() {
+            lines.push(format!("Similar extensions: {}", list(&self.similar_extensions, ".")));
+        }
+        if !self.other_drives.is_empty() {
+            let drives: Vec<String> = self.other_drives.iter()
+                .map(|d| format!("{}: ({})", d.drive, d.matches))
+                .collect();
+            lines.push(format!("Matches on other drives: {}", drives.join(", ")));
+        }
+        if let Some(hint) = &self.path_filter {
+            lines.push(format!(
+                "{} matches outside path filter '{}', e.g. in {}",
+                hint.matches_without,
+                hint.path,
+                hint.folders.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, name: &str) -> FileEntry {
+        FileEntry::test(id, &format!("docs\\{}", name))
+    }
+
+    #[test]
+    fn test_spelling_target() {
+        assert_eq!(SpellingTarget::of("Reprot.docx", SearchType::Glob), Some(SpellingTarget::Name("reprot.docx".to_string())));
+        assert_eq!(SpellingTarget::of("*.tomll", SearchType::Glob), Some(SpellingTarget::Extension("tomll".to_string())));
+        assert_eq!(SpellingTarget::of("rep*.docx", SearchType::Glob), None);
+        assert_eq!(SpellingTarget::of("^rep.*", SearchType::Regex), None);
+        assert_eq!(SpellingTarget::of("ab", SearchType::Exact), None);
+    }
+
+    #[test]
+    fn test_distance_within() {
+        let chars: Vec<char> = "reprot".chars().collect();
+        assert_eq!(distance_within(&chars, "report", 2), Some(2));
+        assert_eq!(distance_within(&chars, "reprot", 2), Some(0));
+        assert_eq!(distance_within(&chars, "repository", 2), None);
+        assert_eq!(distance_within(&chars, "rprot", 1), Some(1));
+    }
+
+    #[test]
+    fn test_similar_names_and_extensions() {
+        let entries = [entry(1, "Report.docx"), entry(2, "report.docx"), entry(3, "import.docx"), entry(4, "Cargo.toml")];
+        let files: HashMap<u64, FileEntry> = entries.iter().map(|e| (e.id, e.clone())).collect();
+        let mut names: HashMap<String, Vec<u64>> = HashMap::new();
+        let mut extensions: HashMap<String, Vec<u64>> = HashMap::new();
+        for e in &entries {
+            names.entry(e.name.to_lowercase()).or_default().push(e.id);
+            extensions.entry(e.extension.clone().unwrap()).or_default().push(e.id);
+        }
+
+        let similar = similar_names("reprot", [(&files, &names)]);
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].count, 2);
+        assert_eq!(similar[0].distance, 2);
+        assert!(similar[0].text.eq_ignore_ascii_case("report.docx"));
+
+        let similar = similar_extensions("tomll", [&extensions]);
+        assert_eq!(similar, vec![Suggestion { text: "toml".to_string(), distance: 1, count: 1 }]);
+    }
+}
@@ -0,0 +1,245 @@
+//! Image dimensions and capture dates for `fast_search`
+//!
+//! With `include_image_info`, image results carry their size in pixels and
+//! the EXIF capture date, and `taken_after`, `taken_before` and
+//! `min_megapixels` filter on them. None of this is in the MFT, so it is read
+//! from the file's header in the verify stage, after every cheaper filter, and
+//! kept per file record until the file's size or modification time changes.
+
+#[cfg(feature = "image-info")]
+use std::fs::File;
+#[cfg(feature = "image-info")]
+use std::io::BufReader;
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::filters::DateRange;
+use super::metadata_cache::MetadataCache;
+use super::mft_cache::{disk_path, DriveRoot, FileEntry};
+
+/// Extensions read for image information
+pub const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff", "heic", "heif", "avif",
+];
+
+/// Pixel size of an image and when it was taken
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    /// EXIF capture date (`DateTimeOriginal`) as a UNIX timestamp, camera clock taken as UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taken: Option<i64>,
+}
+
+impl ImageInfo {
+    pub fn megapixels(&self) -> f64 {
+        self.width as f64 * self.height as f64 / 1_000_000.0
+    }
+
+    /// `4032×3024, 12.2 MP, taken 2021-06-01`
+    pub fn describe(&self) -> String {
+        let mut text = format!("{}×{}, {:.1} MP", self.width, self.height, self.megapixels());
+        if let Some(taken) = self.taken.and_then(|secs| Utc.timestamp_opt(secs, 0).single()) {
+            text.push_str(&format!(", taken {}", taken.format("%Y-%m-%d")));
+        }
+        text
+    }
+}
+
+/// Whether a cache entry has an image extension
+pub fn is_image(file: &FileEntry) -> bool {
+    !file.is_directory && file.extension.as_deref().map_or(false, |ext| IMAGE_EXTENSIONS.contains(&ext))
+}
+
+/// Read the dimensions and capture date of an image; `None` if it isn't one
+#[cfg(feature = "image-info")]
+pub fn read_image_info(path: &Path) -> Option<ImageInfo> {
+    let size = imagesize::size(path).ok()?;
+    Some(ImageInfo {
+        width: u32::try_from(size.width).ok()?,
+        height: u32::try_from(size.height).ok()?,
+        taken: read_capture_date(path),
+    })
+}
+
+/// Built without the `image-info` feature: no image is read
+#[cfg(not(feature = "image-info"))]
+pub fn read_image_info(_path: &Path) -> Option<ImageInfo> {
+    None
+}
+
+/// `DateTimeOriginal` (or else `DateTime`) from the EXIF data, if there is any
+#[cfg(feature = "image-info")]
+fn read_capture_date(path: &Path) -> Option<i64> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    [exif::Tag::DateTimeOriginal, exif::Tag::DateTime].iter()
+        .filter_map(|tag| exif.get_field(*tag, exif::In::PRIMARY))
+        .find_map(|field| match &field.value {
+            exif::Value::Ascii(values) => values.first().and_then(|raw| parse_exif_date(raw)),
+            _ => None,
+        })
+}
+
+/// Parse an EXIF date: `2021:06:01 14:03:22`
+#[cfg_attr(not(feature = "image-info"), allow(dead_code))]
+fn parse_exif_date(raw: &[u8]) -> Option<i64> {
+    let text = std::str::from_utf8(raw).ok()?.trim_end_matches('\0').trim();
+    NaiveDateTime::parse_from_str(text, "%Y:%m:%d %H:%M:%S")
+        .ok()
+        .map(|taken| taken.and_utc().timestamp())
+}
+
+/// Image information read so far, by drive and file record
+#[derive(Debug, Default)]
+pub struct ImageInfoCache {
+    cache: MetadataCache<ImageInfo>,
+}
+
+impl ImageInfoCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Image information of a cache entry, read from below `root` unless known for this version of the file
+    pub fn get(&self, drive: char, root: &Path, file: &FileEntry) -> Option<ImageInfo> {
+        if !is_image(file) {
+            return None;
+        }
+        self.cache.get_or_read(drive, file, || read_image_info(&disk_path(root, &file.path)))
+    }
+
+    /// Number of files remembered
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// The `taken_after`, `taken_before` and `min_megapixels` arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ImageFilter {
+    pub taken: DateRange,
+    pub min_megapixels: Option<f64>,
+}
+
+impl ImageFilter {
+    /// Parse the image filters; `None` if none is given
+    pub fn from_args(args: &Value) -> Result<Option<Self>> {
+        let taken = DateRange::from_args(args, "taken")?;
+        let min_megapixels = match &args["min_megapixels"] {
+            Value::Null => None,
+            value => match value.as_f64() {
+                Some(mp) if mp >= 0.0 => Some(mp),
+                _ => return Err(anyhow!("'min_megapixels' must be a non-negative number")),
+            },
+        };
+        if taken.is_unbounded() && min_megapixels.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self { taken, min_megapixels }))
+    }
+
+    /// Whether an image passes; files that aren't readable images never do
+    pub fn matches(&self, info: Option<&ImageInfo>) -> bool {
+        let Some(info) = info else {
+            return false;
+        };
+        if self.min_megapixels.map_or(false, |min| info.megapixels() < min) {
+            return false;
+        }
+        if self.taken.is_unbounded() {
+            return true;
+        }
+        info.taken
+            .and_then(|secs| u64::try_from(secs).ok())
+            .map_or(false, |secs| self.taken.contains(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs)))
+    }
+}
+
+/// Image check for the matches on one drive
+pub struct ImageCheck<'a> {
+    filter: ImageFilter,
+    cache: &'a ImageInfoCache,
+    disk: DriveRoot,
+}
+
+impl<'a> ImageCheck<'a> {
+    pub fn new(filter: ImageFilter, cache: &'a ImageInfoCache, disk: DriveRoot) -> Self {
+        Self { filter, cache, disk }
+    }
+
+    pub fn accepts(&self, file: &FileEntry) -> bool {
+        is_image(file) && self.filter.matches(self.cache.get(self.disk.drive, &self.disk.root, file).as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    /// A 3×2 PNG with nothing but its header chunk
+    fn tiny_png() -> Vec<u8> {
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&3u32.to_be_bytes());
+        png.extend_from_slice(&2u32.to_be_bytes());
+        png.extend_from_slice(&[8, 2, 0, 0, 0]);
+        png.extend_from_slice(&[0; 4]);
+        png
+    }
+
+    fn entry(id: u64, name: &str, size: u64) -> FileEntry {
+        FileEntry { size, ..FileEntry::test(id, name) }
+    }
+
+    #[test]
+    #[cfg(feature = "image-info")]
+    fn test_dimensions_are_read_once_per_version() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("tiny.png"), tiny_png()).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not an image").unwrap();
+        let cache = ImageInfoCache::new();
+
+        let png = entry(1, "tiny.png", 33);
+        let info = cache.get('C', dir.path(), &png).unwrap();
+        assert_eq!((info.width, info.height, info.taken), (3, 2, None));
+        assert_eq!(cache.len(), 1);
+        // Known for this size and modification time, so the file isn't read again
+        std::fs::remove_file(dir.path().join("tiny.png")).unwrap();
+        assert!(cache.get('C', dir.path(), &png).is_some());
+        assert!(cache.get('C', dir.path(), &entry(1, "tiny.png", 34)).is_none());
+
+        assert!(cache.get('C', dir.path(), &entry(2, "notes.txt", 12)).is_none());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_image_filter() {
+        assert_eq!(ImageFilter::from_args(&json!({"pattern": "*.jpg"})).unwrap(), None);
+        assert!(ImageFilter::from_args(&json!({"min_megapixels": -1})).is_err());
+
+        let filter = ImageFilter::from_args(&json!({"taken_after": "2021-01-01", "taken_before": "2022-01-01", "min_megapixels": 12}))
+            .unwrap().unwrap();
+        let taken = parse_exif_date(b"2021:06:01 14:03:22\0");
+        let photo = ImageInfo { width: 4032, height: 3024, taken };
+        assert!(filter.matches(Some(&photo)));
+        assert!(!filter.matches(Some(&ImageInfo { width: 1920, height: 1080, taken })));
+        assert!(!filter.matches(Some(&ImageInfo { taken: parse_exif_date(b"2020:12:31 23:59:59"), ..photo })));
+        assert!(!filter.matches(Some(&ImageInfo { taken: None, ..photo })));
+        assert!(!filter.matches(None));
+        assert_eq!(photo.describe(), "4032×3024, 12.2 MP, taken 2021-06-01");
+    }
+}
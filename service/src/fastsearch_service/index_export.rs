@@ -0,0 +1,336 @@
+//! `export_index`: a drive's cache as a SQLite database for ad-hoc SQL
+//!
+//! The export is a snapshot of the cache, not of the disk: whatever the cache
+//! knew when the export ran. The schema (version [`EXPORT_SCHEMA_VERSION`]) is:
+//!
+//! - `meta(key, value)`: `schema_version`, `drive`, `exported_at` (UNIX
+//!   seconds) and the row counts
+//! - `directories(id, record, parent_id, name, path)`: one row per directory
+//! - `extensions(id, extension, file_count, total_size)`: one row per
+//!   extension of the files, lowercased and without the dot
+//! - `files(id, record, directory_id, extension_id, name, path, size,
+//!   created, modified, accessed, attributes, link_count, reparse_tag)`: one
+//!   row per file name
+//!
+//! `id` is the cache's key for the name, `record` the MFT record number (hard
+//! links share it). Paths are relative to the drive root and
+//! backslash-separated; times are UNIX seconds; `attributes` are the NTFS
+//! attribute flags. `parent_id` and `directory_id` are null at the drive root.
+//! Rows are written in one transaction to a temporary file that replaces the
+//! target at the end, so a database is either complete or not there.
+//!
+//! Callers name the database file only; it is written to the `exports` folder
+//! of the service's data directory, since the service runs elevated.
+
+#[cfg(feature = "export")]
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "export")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "export")]
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use super::cancellation::CancelToken;
+use super::json_store::named_file;
+use super::mft_cache::FileEntry;
+
+/// Version of the exported schema, in the `meta` table
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Folder of the data directory that exports are written to
+pub const EXPORTS_DIR: &str = "exports";
+
+/// Location of the export named `name` in the exports folder of `data_dir`
+pub fn export_path(data_dir: &Path, name: &str) -> Result<PathBuf> {
+    named_file(data_dir, EXPORTS_DIR, name)
+}
+
+/// Rows written between progress reports and cancellation checks
+#[cfg(feature = "export")]
+const EXPORT_BATCH: u64 = 50_000;
+
+#[cfg(feature = "export")]
+const SCHEMA: &str = "
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE directories (
+        id INTEGER PRIMARY KEY,
+        record INTEGER NOT NULL,
+        parent_id INTEGER REFERENCES directories(id),
+        name TEXT NOT NULL,
+        path TEXT NOT NULL
+    );
+    CREATE TABLE extensions (
+        id INTEGER PRIMARY KEY,
+        extension TEXT NOT NULL UNIQUE,
+        file_count INTEGER NOT NULL,
+        total_size INTEGER NOT NULL
+    );
+    CREATE TABLE files (
+        id INTEGER PRIMARY KEY,
+        record INTEGER NOT NULL,
+        directory_id INTEGER REFERENCES directories(id),
+        extension_id INTEGER REFERENCES extensions(id),
+        name TEXT NOT NULL,
+        path TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        created INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        accessed INTEGER NOT NULL,
+        attributes INTEGER NOT NULL,
+        link_count INTEGER NOT NULL,
+        reparse_tag INTEGER NOT NULL
+    );
+";
+
+/// Created once the rows are in, which is faster than keeping them up to date
+const INDEXES: &str = "
+    CREATE INDEX directories_parent ON directories(parent_id);
+    CREATE INDEX files_directory ON files(directory_id);
+    CREATE INDEX files_extension ON files(extension_id);
+    CREATE INDEX files_name ON files(name COLLATE NOCASE);
+    CREATE INDEX files_size ON files(size);
+";
+
+/// Formats `export_index` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Sqlite,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "sqlite" | "sqlite3" | "db" => Some(ExportFormat::Sqlite),
+            _ => None,
+        }
+    }
+}
+
+/// What an export wrote
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportSummary {
+    pub path: PathBuf,
+    pub files: u64,
+    pub directories: u64,
+    pub extensions: u64,
+    /// Size of the database file
+    pub bytes: u64,
+    /// Stopped before all rows were written; nothing is left at `path`
+    pub cancelled: bool,
+}
+
+/// Write a drive's cache entries to a new SQLite database at `path`
+///
+/// `paths` is the cache's path index, used to find each entry's directory.
+/// `progress` gets the rows written so far and the total.
+pub fn export_sqlite(
+    drive: char,
+    files: &HashMap<u64, FileEntry>,
+    paths: &HashMap<String, u64>,
+    path: &Path,
+    overwrite: bool,
+    cancel: &CancelToken,
+    progress: Option<&dyn Fn(u64, u64)>,
+) -> Result<ExportSummary> {
+    if path.exists() && !overwrite {
+        bail!("{} already exists (pass overwrite to replace it)", path.display());
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).context("Failed to create export directory")?;
+    }
+    let temp = path.with_extension("sqlite.tmp");
+    let _ = fs::remove_file(&temp);
+
+    let mut summary = ExportSummary { path: path.to_path_buf(), ..Default::default() };
+    let written = write_database(drive, files, paths, &temp, cancel, progress, &mut summary);
+    if written.is_err() || summary.cancelled {
+        let _ = fs::remove_file(&temp);
+        return written.map(|_| summary);
+    }
+    if overwrite && path.exists() {
+        fs::remove_file(path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    }
+    fs::rename(&temp, path).with_context(|| format!("Failed to move the export to {}", path.display()))?;
+    summary.bytes = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    Ok(summary)
+}
+
+#[cfg(feature = "export")]
+fn write_database(
+    drive: char,
+    files: &HashMap<u64, FileEntry>,
+    paths: &HashMap<String, u64>,
+    path: &Path,
+    cancel: &CancelToken,
+    progress: Option<&dyn Fn(u64, u64)>,
+    summary: &mut ExportSummary,
+) -> Result<()> {
+    let mut conn = Connection::open(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    // The file only becomes the export once it is complete, so it needs no journal
+    conn.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+    conn.execute_batch(SCHEMA).context("Failed to create the export schema")?;
+
+    // Extensions first, so files can refer to them
+    let mut extensions: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    for file in files.values().filter(|file| !file.is_directory) {
+        if let Some(ext) = &file.extension {
+            let totals = extensions.entry(ext.as_str()).or_default();
+            totals.0 += 1;
+            totals.1 += file.size;
+        }
+    }
+    let extension_ids: HashMap<&str, i64> = extensions.keys().enumerate()
+        .map(|(i, ext)| (*ext, i as i64 + 1))
+        .collect();
+    let directory_of = |path: &str| -> Option<u64> {
+        let (parent, _) = path.rsplit_once('\\')?;
+        paths.get(parent).copied().filter(|id| files.get(id).map_or(false, |dir| dir.is_directory))
+    };
+
+    let total = files.len() as u64;
+    let tx = conn.transaction()?;
+    {
+        let mut insert_extension = tx.prepare(
+            "INSERT INTO extensions (id, extension, file_count, total_size) VALUES (?1, ?2, ?3, ?4)"
+        )?;
+        for (ext, (count, size)) in &extensions {
+            insert_extension.execute(params![extension_ids[ext], ext, *count as i64, *size as i64])?;
+        }
+        summary.extensions = extensions.len() as u64;
+
+        let mut insert_directory = tx.prepare(
+            "INSERT INTO directories (id, record, parent_id, name, path) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+        let mut insert_file = tx.prepare(
+            "INSERT INTO files (id, record, directory_id, extension_id, name, path, size, created, modified, accessed, \
+             attributes, link_count, reparse_tag) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"
+        )?;
+        // Directories first, parents before their children, so every row refers to rows already written
+        let (mut directories, entries): (Vec<_>, Vec<_>) = files.iter().partition(|(_, file)| file.is_directory);
+        directories.sort_unstable_by(|a, b| a.1.path.cmp(&b.1.path));
+        let mut done = 0u64;
+        for (id, file) in directories.into_iter().chain(entries) {
+            let parent = directory_of(&file.path).map(|id| id as i64);
+            if file.is_directory {
+                insert_directory.execute(params![*id as i64, file.id as i64, parent, file.name, file.path])?;
+                summary.directories += 1;
+            } else {
+                let extension = file.extension.as_deref().and_then(|ext| extension_ids.get(ext));
+                insert_file.execute(params![
+                    *id as i64, file.id as i64, parent, extension, file.name, file.path, file.size as i64,
+                    unix_seconds(file.created), unix_seconds(file.modified), unix_seconds(file.accessed),
+                    file.attributes, file.link_count, file.reparse_tag,
+                ])?;
+                summary.files += 1;
+            }
+            done += 1;
+            if done % EXPORT_BATCH == 0 {
+                if cancel.is_cancelled() {
+                    summary.cancelled = true;
+                    return Ok(());
+                }
+                if let Some(progress) = progress {
+                    progress(done, total);
+                }
+            }
+        }
+    }
+
+    let exported_at = unix_seconds(SystemTime::now());
+    let meta = [
+        ("schema_version", EXPORT_SCHEMA_VERSION.to_string()),
+        ("drive", drive.to_string()),
+        ("exported_at", exported_at.to_string()),
+        ("files", summary.files.to_string()),
+        ("directories", summary.directories.to_string()),
+        ("extensions", summary.extensions.to_string()),
+    ];
+    for (key, value) in &meta {
+        tx.execute("INSERT INTO meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+    }
+    tx.execute_batch(INDEXES).context("Failed to index the export")?;
+    tx.commit().context("Failed to write the export")?;
+    Ok(())
+}
+
+/// Built without the `export` feature: there is no SQLite to write with
+#[cfg(not(feature = "export"))]
+fn write_database(
+    _drive: char,
+    _files: &HashMap<u64, FileEntry>,
+    _paths: &HashMap<String, u64>,
+    _path: &Path,
+    _cancel: &CancelToken,
+    _progress: Option<&dyn Fn(u64, u64)>,
+    _summary: &mut ExportSummary,
+) -> Result<()> {
+    bail!("The service was built without the 'export' feature")
+}
+
+/// Seconds since the UNIX epoch, negative before it
+#[cfg(feature = "export")]
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, path: &str, is_directory: bool, size: u64) -> FileEntry {
+        let file = FileEntry::test(id, path);
+        FileEntry { size, is_directory, extension: file.extension.clone().filter(|_| !is_directory), ..file }
+    }
+
+    #[test]
+    fn test_export_path_is_a_name_in_the_exports_folder() {
+        let data = Path::new("data");
+        assert_eq!(export_path(data, "c.sqlite").unwrap(), data.join(EXPORTS_DIR).join("c.sqlite"));
+        assert!(export_path(data, "..\\x.db").is_err());
+        assert!(export_path(data, "C:\\Windows\\x.db").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "export")]
+    fn test_export_answers_sql() {
+        let entries = [
+            entry(5, "docs", true, 0),
+            entry(6, "docs\\a.pdf", false, 100),
+            entry(7, "docs\\b.PDF", false, 50),
+            entry(8, "notes.txt", false, 10),
+        ];
+        let files: HashMap<u64, FileEntry> = entries.iter().map(|e| (e.id, e.clone())).collect();
+        let paths: HashMap<String, u64> = entries.iter().map(|e| (e.path.clone(), e.id)).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("c.sqlite");
+
+        let summary = export_sqlite('C', &files, &paths, &path, false, &CancelToken::new(), None).unwrap();
+        assert_eq!((summary.files, summary.directories, summary.extensions), (3, 1, 2));
+        assert!(export_sqlite('C', &files, &paths, &path, false, &CancelToken::new(), None).is_err());
+
+        let conn = Connection::open(&path).unwrap();
+        let (count, size): (i64, i64) = conn.query_row(
+            "SELECT file_count, total_size FROM extensions WHERE extension = 'pdf'", [], |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((count, size), (2, 150));
+        let in_docs: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM files JOIN directories d ON files.directory_id = d.id WHERE d.path = 'docs'", [], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(in_docs, 2);
+        let root: Option<i64> = conn.query_row("SELECT directory_id FROM files WHERE name = 'notes.txt'", [], |row| row.get(0)).unwrap();
+        assert_eq!(root, None);
+        let version: String = conn.query_row("SELECT value FROM meta WHERE key = 'schema_version'", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, EXPORT_SCHEMA_VERSION.to_string());
+    }
+}
@@ -0,0 +1,228 @@
+//! What each project has, for `repo_inventory`
+//!
+//! For every project root below a folder (see `projects`), whether it has a
+//! license, a README and CI configuration, and which language most of its
+//! source is in. All of it comes from one pass over the MFT cache: a file
+//! counts for its nearest root, so nested projects are reported on their own,
+//! and vendored or generated folders (`node_modules`, `target`, ...) don't
+//! count towards the language.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use super::mft_cache::FileEntry;
+use super::projects::{parent_path, ProjectIndex, ProjectRoot};
+use super::reports::scoped_remainder;
+
+/// Extensions (lowercase) and the language they are source code in
+const LANGUAGES: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("py", "Python"),
+    ("js", "JavaScript"), ("mjs", "JavaScript"), ("cjs", "JavaScript"), ("jsx", "JavaScript"),
+    ("ts", "TypeScript"), ("tsx", "TypeScript"),
+    ("go", "Go"),
+    ("java", "Java"),
+    ("kt", "Kotlin"), ("kts", "Kotlin"),
+    ("cs", "C#"),
+    ("c", "C"), ("h", "C"),
+    ("cpp", "C++"), ("cc", "C++"), ("cxx", "C++"), ("hpp", "C++"), ("hh", "C++"),
+    ("rb", "Ruby"),
+    ("php", "PHP"),
+    ("swift", "Swift"),
+    ("scala", "Scala"),
+    ("dart", "Dart"),
+    ("lua", "Lua"),
+    ("sh", "Shell"), ("bash", "Shell"),
+    ("ps1", "PowerShell"), ("psm1", "PowerShell"),
+];
+
+/// Folders (lowercase) whose contents aren't the project's own source
+const VENDORED_FOLDERS: &[&str] = &[
+    ".git", "node_modules", "target", "vendor", "dist", "build", "out", "bin", "obj",
+    ".venv", "venv", "__pycache__", ".tox", "third_party",
+];
+
+/// CI configuration: path within the project (lowercase) and the system it belongs to
+const CI_FILES: &[(&str, &str)] = &[
+    (".gitlab-ci.yml", "gitlab"),
+    (".travis.yml", "travis"),
+    ("azure-pipelines.yml", "azure-pipelines"),
+    ("jenkinsfile", "jenkins"),
+    ("appveyor.yml", "appveyor"),
+    ("bitbucket-pipelines.yml", "bitbucket"),
+    (".circleci\\config.yml", "circleci"),
+    (".drone.yml", "drone"),
+];
+
+/// The language a file is source code in, by its extension
+pub fn language_of(file: &FileEntry) -> Option<&'static str> {
+    let extension = file.extension.as_deref()?;
+    LANGUAGES.iter().find(|(ext, _)| *ext == extension).map(|(_, language)| *language)
+}
+
+/// The CI system a file configures, by its path within the project
+fn ci_system(relative: &str) -> Option<&'static str> {
+    let relative = relative.to_lowercase();
+    if let Some(workflow) = relative.strip_prefix(".github\\workflows\\") {
+        return (workflow.ends_with(".yml") || workflow.ends_with(".yaml")).then_some("github-actions");
+    }
+    CI_FILES.iter().find(|(path, _)| *path == relative).map(|(_, system)| *system)
+}
+
+/// What one project has
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoInventory {
+    #[serde(flatten)]
+    pub root: ProjectRoot,
+    /// Name of the license file, such as `LICENSE-MIT`
+    pub license: Option<String>,
+    /// Name of the README
+    pub readme: Option<String>,
+    /// CI systems configured, such as `github-actions`
+    pub ci: Vec<&'static str>,
+    /// Language with the most bytes of source
+    pub language: Option<&'static str>,
+    /// Source files counted for the language
+    pub source_files: u64,
+}
+
+impl RepoInventory {
+    fn new(root: ProjectRoot) -> Self {
+        Self { root, license: None, readme: None, ci: Vec::new(), language: None, source_files: 0 }
+    }
+
+    /// What it lacks, of `license`, `readme` and `ci`
+    pub fn missing(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.license.is_none() {
+            missing.push("license");
+        }
+        if self.readme.is_none() {
+            missing.push("readme");
+        }
+        if self.ci.is_empty() {
+            missing.push("ci");
+        }
+        missing
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    ci: BTreeSet<&'static str>,
+    languages: HashMap<&'static str, u64>,
+    source_files: u64,
+}
+
+/// Inventory of the projects below `scope`, sorted by path
+pub fn repo_inventory<'a>(
+    files: impl Iterator<Item = &'a FileEntry>,
+    index: &ProjectIndex,
+    scope: &str,
+    accept: impl Fn(&FileEntry) -> bool,
+) -> Vec<RepoInventory> {
+    let mut inventories: HashMap<String, (RepoInventory, Tally)> = index.roots()
+        .filter(|root| scoped_remainder(&root.path, scope).is_some() || root.path.eq_ignore_ascii_case(scope))
+        .map(|root| (root.path.to_lowercase(), (RepoInventory::new(root.clone()), Tally::default())))
+        .collect();
+    if inventories.is_empty() {
+        return Vec::new();
+    }
+
+    for file in files.filter(|file| !file.is_directory && scoped_remainder(&file.path, scope).is_some()) {
+        let Some(root) = index.nearest_root(&file.path) else {
+            continue;
+        };
+        let Some((inventory, tally)) = inventories.get_mut(&root.path.to_lowercase()) else {
+            continue;
+        };
+        let Some(relative) = scoped_remainder(&file.path, &root.path) else {
+            continue;
+        };
+        if !accept(file) {
+            continue;
+        }
+
+        let at_top = parent_path(&file.path).map_or(false, |parent| parent.eq_ignore_ascii_case(&root.path));
+        let name = file.name.to_lowercase();
+        if at_top && inventory.license.is_none()
+            && ["license", "licence", "copying", "unlicense"].iter().any(|prefix| name.starts_with(prefix)) {
+            inventory.license = Some(file.name.clone());
+        }
+        if at_top && inventory.readme.is_none() && name.starts_with("readme") {
+            inventory.readme = Some(file.name.clone());
+        }
+        if let Some(system) = ci_system(relative) {
+            tally.ci.insert(system);
+        }
+
+        let vendored = relative.split('\\')
+            .rev()
+            .skip(1)
+            .any(|folder| VENDORED_FOLDERS.iter().any(|vendored| folder.eq_ignore_ascii_case(vendored)));
+        if let Some(language) = language_of(file).filter(|_| !vendored) {
+            *tally.languages.entry(language).or_default() += file.size;
+            tally.source_files += 1;
+        }
+    }
+
+    let mut inventories: Vec<RepoInventory> = inventories.into_values()
+        .map(|(mut inventory, tally)| {
+            inventory.ci = tally.ci.into_iter().collect();
+            // Ties go to the name that sorts first, so the result doesn't depend on hash order
+            inventory.language = tally.languages.into_iter()
+                .max_by(|(a, a_bytes), (b, b_bytes)| a_bytes.cmp(b_bytes).then_with(|| b.cmp(a)))
+                .map(|(language, _)| language);
+            inventory.source_files = tally.source_files;
+            inventory
+        })
+        .collect();
+    inventories.sort_by_key(|inventory| inventory.root.path.to_lowercase());
+    inventories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn entry(path: &str, size: u64, is_directory: bool) -> FileEntry {
+        FileEntry { size, is_directory, ..FileEntry::test(0, path) }
+    }
+
+    #[test]
+    fn test_inventory_of_nested_projects() {
+        let files = vec![
+            entry("dev\\app\\.git", 0, true),
+            entry("dev\\app\\LICENSE-MIT", 1_000, false),
+            entry("dev\\app\\README.md", 2_000, false),
+            entry("dev\\app\\.github\\workflows\\ci.yml", 500, false),
+            entry("dev\\app\\src\\main.rs", 40_000, false),
+            entry("dev\\app\\scripts\\release.py", 3_000, false),
+            entry("dev\\app\\target\\debug\\build\\gen.py", 900_000, false),
+            entry("dev\\app\\web\\package.json", 800, false),
+            entry("dev\\app\\web\\index.ts", 12_000, false),
+            entry("dev\\app\\web\\docs\\README.md", 100, false),
+            entry("other\\lib\\Cargo.toml", 300, false),
+        ];
+        let index = ProjectIndex::build(&files, SystemTime::now());
+        let inventories = repo_inventory(files.iter(), &index, "dev", |_| true);
+        assert_eq!(inventories.len(), 2);
+
+        let app = &inventories[0];
+        assert_eq!(app.root.name, "app");
+        assert_eq!(app.license.as_deref(), Some("LICENSE-MIT"));
+        assert_eq!(app.readme.as_deref(), Some("README.md"));
+        assert_eq!(app.ci, vec!["github-actions"]);
+        // The generated Python under target doesn't outweigh the Rust
+        assert_eq!(app.language, Some("Rust"));
+        assert_eq!(app.source_files, 2);
+        assert!(app.missing().is_empty());
+
+        // A README below the top isn't the project's
+        let web = &inventories[1];
+        assert_eq!((web.root.name.as_str(), web.language), ("web", Some("TypeScript")));
+        assert_eq!(web.missing(), vec!["license", "readme", "ci"]);
+    }
+}
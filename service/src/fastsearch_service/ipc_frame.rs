@@ -0,0 +1,129 @@
+//! Frames of the named pipe protocol
+//!
+//! Requests are `[type: u32 LE][length: u32 LE][payload]` and responses
+//! `[status: u32 LE][length: u32 LE][payload]`, with JSON payloads. The
+//! service runs elevated and any local process can open its pipe, so decoding
+//! lives here, apart from the Windows pipe handling, where the fuzz targets in
+//! `service/fuzz` can feed it arbitrary bytes.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+/// A search; the payload is the bridge's search request
+pub const MSG_SEARCH: u32 = 1;
+/// Service status and health
+pub const MSG_STATUS: u32 = 2;
+/// Stop a running search by the `request_id` it was sent with; comes on a connection of its own
+pub const MSG_CANCEL: u32 = 3;
+
+pub const STATUS_OK: u32 = 0;
+pub const STATUS_ERROR: u32 = 1;
+/// A batch of matches sent while the search is still running; more frames follow
+pub const STATUS_PARTIAL: u32 = 3;
+
+/// Length of a frame header
+pub const FRAME_HEADER_LEN: usize = 8;
+
+/// Split a request message into its type and payload
+///
+/// Bytes past the declared length are ignored.
+pub fn parse_request_frame(message: &[u8]) -> Result<(u32, &[u8])> {
+    if message.len() < FRAME_HEADER_LEN {
+        bail!("Truncated request header");
+    }
+    let message_type = u32::from_le_bytes(message[0..4].try_into()?);
+    let length = u32::from_le_bytes(message[4..8].try_into()?) as usize;
+    let end = FRAME_HEADER_LEN.checked_add(length).context("Request length out of range")?;
+    let payload = message.get(FRAME_HEADER_LEN..end).context("Truncated request payload")?;
+    Ok((message_type, payload))
+}
+
+/// A frame with `kind` (a message type or a status) in its header
+pub fn encode_frame(kind: u32, body: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + body.len());
+    frame.extend_from_slice(&kind.to_le_bytes());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.extend_from_slice(body);
+    frame
+}
+
+/// Map the bridge's search request onto fast_search arguments
+pub fn pipe_search_args(request: &Value) -> Value {
+    let mut args = request["filters"].as_object().cloned().unwrap_or_default();
+    args.insert("pattern".to_string(), request["pattern"].clone());
+    args.insert("max_results".to_string(), request["max_results"].clone());
+    if let Some(search_type) = request["type"].as_str() {
+        args.insert("search_type".to_string(), json!(search_type));
+    }
+    if let Some(case_sensitive) = request["case_sensitive"].as_bool() {
+        args.insert("case_sensitive".to_string(), json!(case_sensitive));
+    }
+    if let Some(ignore_diacritics) = request["ignore_diacritics"].as_bool() {
+        args.insert("ignore_diacritics".to_string(), json!(ignore_diacritics));
+    }
+    if let Some(match_path) = request["match_path"].as_bool() {
+        args.insert("match_path".to_string(), json!(match_path));
+    }
+    // Type filters may come at the top level too; `file_types` is the shared SearchRequest's name
+    if !request["doc_type"].is_null() {
+        args.insert("doc_type".to_string(), request["doc_type"].clone());
+    }
+    // Result fields to keep, so large pages cross the pipe with only what the bridge reads
+    if !request["fields"].is_null() {
+        args.insert("fields".to_string(), request["fields"].clone());
+    }
+    let extensions = [&request["extensions"], &request["file_types"], &request["filters"]["file_types"]]
+        .into_iter()
+        .find(|value| !value.is_null());
+    if let Some(extensions) = extensions {
+        args.insert("extensions".to_string(), extensions.clone());
+    }
+    args.remove("file_types");
+    Value::Object(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_frame() {
+        let frame = encode_frame(MSG_SEARCH, br#"{"pattern":"*.rs"}"#);
+        let (kind, payload) = parse_request_frame(&frame).unwrap();
+        assert_eq!(kind, MSG_SEARCH);
+        assert_eq!(payload, br#"{"pattern":"*.rs"}"#);
+
+        // Trailing bytes past the declared length are ignored
+        let mut padded = encode_frame(MSG_STATUS, b"{}");
+        padded.extend_from_slice(b"junk");
+        assert_eq!(parse_request_frame(&padded).unwrap(), (MSG_STATUS, &b"{}"[..]));
+
+        assert!(parse_request_frame(&[1, 0, 0]).is_err());
+        // A length past the end of the message, up to the largest a header can declare
+        let mut short = encode_frame(MSG_SEARCH, b"{}");
+        short[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_request_frame(&short).is_err());
+    }
+
+    #[test]
+    fn test_pipe_search_args() {
+        let args = pipe_search_args(&json!({
+            "pattern": "*.log",
+            "max_results": 10,
+            "type": "glob",
+            "case_sensitive": true,
+            "filters": {"drive": "D", "file_types": ["txt"]}
+        }));
+        assert_eq!(args, json!({
+            "drive": "D",
+            "pattern": "*.log",
+            "max_results": 10,
+            "search_type": "glob",
+            "case_sensitive": true,
+            "extensions": ["txt"]
+        }));
+
+        // Anything that isn't an object still maps to arguments the tool rejects cleanly
+        assert_eq!(pipe_search_args(&json!([1, 2])), json!({"pattern": null, "max_results": null}));
+    }
+}
@@ -0,0 +1,141 @@
+//! JSON documents kept in the service's data directory
+//!
+//! Saved searches, collections, warm queries, denied paths, report jobs and
+//! the drive selection each live in one small JSON file under
+//! `%LOCALAPPDATA%\FastSearchMCP`. A [`JsonStore`] reads its file when the
+//! service starts, treating a missing or unreadable file as empty, and
+//! replaces it atomically (through a temporary file) on every save. Stores
+//! created in memory, as tests and fixture engines use, never touch the disk.
+
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use log::error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Directory the service keeps its settings and stores in
+pub fn data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("FastSearchMCP")
+}
+
+/// Location of the file `name` in `folder` of the data directory
+///
+/// For files tool callers name (settings bundles, exports): the service runs
+/// elevated, so a caller only ever names a file in the folder, never a path.
+/// `name` must not hold folders, `..`, a drive or a stream.
+pub fn named_file(data_dir: &Path, folder: &str, name: &str) -> Result<PathBuf> {
+    let dir = data_dir.join(folder);
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(file)), None) if !name.contains(['/', '\\', ':']) => Ok(dir.join(file)),
+        _ => bail!("'{}' is not a file name; only files in {} can be named", name, dir.display()),
+    }
+}
+
+/// One JSON document on disk, holding a `T`
+#[derive(Debug)]
+pub struct JsonStore<T> {
+    path: Option<PathBuf>,
+    /// What the document holds, for log and error messages ("saved searches")
+    what: &'static str,
+    _document: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> JsonStore<T> {
+    /// The document at `path`
+    pub fn at<P: AsRef<Path>>(path: P, what: &'static str) -> Self {
+        Self {
+            path: Some(path.as_ref().to_path_buf()),
+            what,
+            _document: PhantomData,
+        }
+    }
+
+    /// A store that is never read from or written to disk
+    pub fn in_memory(what: &'static str) -> Self {
+        Self {
+            path: None,
+            what,
+            _document: PhantomData,
+        }
+    }
+
+    /// Location of the document, `None` for stores in memory
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Read the document
+    ///
+    /// `None` if the store is in memory or the file doesn't exist. A file that
+    /// can't be parsed is logged and also read as `None`, so a damaged store
+    /// doesn't keep the service from starting.
+    pub fn load(&self) -> Option<T> {
+        let path = self.path.as_ref()?;
+        let data = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&data) {
+            Ok(document) => Some(document),
+            Err(e) => {
+                error!("Ignoring unreadable {} in {}: {}", self.what, path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Write the document to disk (atomically, via a temporary file)
+    pub fn save(&self, document: &T) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create the directory for {}", self.what))?;
+        }
+
+        let data = serde_json::to_string_pretty(document)?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, data).with_context(|| format!("Failed to write {}", self.what))?;
+        fs::rename(&temp, path).with_context(|| format!("Failed to replace the {} file", self.what))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_round_trip_and_unreadable_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nested").join("names.json");
+        let store: JsonStore<Vec<String>> = JsonStore::at(&path, "names");
+        assert!(store.load().is_none());
+
+        store.save(&vec!["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!(store.load().unwrap(), vec!["a", "b"]);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        fs::write(&path, "{not json").unwrap();
+        assert!(store.load().is_none());
+
+        let memory: JsonStore<Vec<String>> = JsonStore::in_memory("names");
+        memory.save(&vec!["c".to_string()]).unwrap();
+        assert!(memory.load().is_none() && memory.path().is_none());
+    }
+
+    #[test]
+    fn test_named_file_stays_in_folder() {
+        let data = Path::new("data");
+        assert_eq!(named_file(data, "exports", "c.sqlite").unwrap(), data.join("exports").join("c.sqlite"));
+        for name in ["", ".", "..", "..\\x.db", "../x.db", "sub/x.db", "C:\\Windows\\x.db", "C:x.db", "/x.db", "x.db:stream"] {
+            assert!(named_file(data, "exports", name).is_err(), "{}", name);
+        }
+    }
+}
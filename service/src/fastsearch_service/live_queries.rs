@@ -0,0 +1,329 @@
+//! Live queries: search results kept current as the file system changes
+//!
+//! A client registers `fast_search` arguments and then receives an update each
+//! time an entry starts or stops matching. Updates are computed from each
+//! batch of journal changes on its own, so no search is run again: an entry
+//! that is deleted or renamed away is checked against the query as the cache
+//! last saw it, and a new name as its journal record describes it.
+//!
+//! Like watched searches, size and date filters are judged when a name appears
+//! (a file created empty and written later is judged as empty), and writes to
+//! an existing file don't produce updates.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use log::debug;
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+
+use super::file_types::DocumentType;
+use super::filters::SearchFilters;
+use super::matcher::{PatternCache, PatternMatcher};
+use super::mft_cache::{FileEntry, MftCache};
+use super::saved_searches::{compile_matcher, entry_for_change};
+use super::usn_journal::{reason, UsnChange};
+
+/// Most live queries registered at once
+pub const MAX_LIVE_QUERIES: usize = 64;
+
+/// Updates kept for a subscriber that falls behind before it is told it lagged
+const UPDATE_BUFFER: usize = 1024;
+
+/// Whether an entry joined or left the results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveChange {
+    Added,
+    Removed,
+}
+
+impl LiveChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveChange::Added => "added",
+            LiveChange::Removed => "removed",
+        }
+    }
+}
+
+/// One entry joining or leaving the results of a live query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LiveUpdate {
+    /// ID of the live query
+    pub query: String,
+    /// Position of the update in the query's stream, from 1
+    pub seq: u64,
+    pub change: LiveChange,
+    /// MFT record number of the entry
+    pub id: u64,
+    pub name: String,
+    /// Full path, with the drive letter
+    pub path: String,
+    pub is_directory: bool,
+    /// Update sequence number of the journal record
+    pub usn: i64,
+}
+
+/// A registered query and the channel its updates are sent on
+pub struct LiveQuery {
+    id: String,
+    drive: char,
+    arguments: Value,
+    matcher: PatternMatcher,
+    filters: SearchFilters,
+    sender: broadcast::Sender<LiveUpdate>,
+    seq: AtomicU64,
+    created: SystemTime,
+}
+
+impl LiveQuery {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn drive(&self) -> char {
+        self.drive
+    }
+
+    /// Receive the updates sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveUpdate> {
+        self.sender.subscribe()
+    }
+
+    /// Number of clients receiving updates
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    fn matches(&self, entry: &FileEntry) -> bool {
+        self.matcher.is_file_match(&entry.name, &entry.path) && self.filters.matches(entry)
+    }
+
+    /// Updates for one batch of changes, numbered from the query's last update
+    ///
+    /// `files` is the cache as it was before the batch was applied, when that
+    /// is how the backend calls listeners; entries it no longer has are
+    /// judged from their journal record instead.
+    pub fn updates(&self, files: &HashMap<u64, FileEntry>, changes: &[UsnChange]) -> Vec<LiveUpdate> {
+        let mut seen: HashSet<(u64, LiveChange)> = HashSet::new();
+        let mut updates = Vec::new();
+        for change in changes {
+            let kind = if change.is_new_name() {
+                LiveChange::Added
+            } else if change.reason & (reason::FILE_DELETE | reason::RENAME_OLD_NAME) != 0 {
+                LiveChange::Removed
+            } else {
+                continue;
+            };
+            // A single creation or rename produces several journal records
+            if !seen.insert((change.file_id, kind)) {
+                continue;
+            }
+            let entry = match (kind, files.get(&change.file_id)) {
+                (LiveChange::Removed, Some(cached)) => cached.clone(),
+                _ => entry_for_change(files, change),
+            };
+            if !self.matches(&entry) {
+                continue;
+            }
+            updates.push(LiveUpdate {
+                query: self.id.clone(),
+                seq: self.seq.fetch_add(1, Ordering::Relaxed) + 1,
+                change: kind,
+                id: entry.id,
+                name: entry.name,
+                path: format!("{}:\\{}", self.drive, entry.path),
+                is_directory: entry.is_directory,
+                usn: change.usn,
+            });
+        }
+        updates
+    }
+
+    /// Summary for listings
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "drive": self.drive.to_string(),
+            "arguments": self.arguments,
+            "subscribers": self.subscribers(),
+            "updates_sent": self.seq.load(Ordering::Relaxed),
+            "created": DateTime::<Utc>::from(self.created).to_rfc3339(),
+        })
+    }
+}
+
+/// Live queries by ID, fed by the change listener of every cache
+pub struct LiveQueryRegistry {
+    queries: RwLock<HashMap<String, Arc<LiveQuery>>>,
+    next_id: AtomicU64,
+    pattern_cache: Arc<PatternCache>,
+    doc_type_extensions: Arc<HashMap<DocumentType, HashSet<String>>>,
+}
+
+impl LiveQueryRegistry {
+    /// Create a registry sharing the engine's pattern cache
+    pub fn new(
+        pattern_cache: Arc<PatternCache>,
+        doc_type_extensions: Arc<HashMap<DocumentType, HashSet<String>>>,
+    ) -> Self {
+        Self {
+            queries: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            pattern_cache,
+            doc_type_extensions,
+        }
+    }
+
+    /// Register `fast_search` arguments on a drive
+    ///
+    /// At the limit, queries nobody is subscribed to make room for new ones.
+    pub fn register(&self, drive: char, arguments: &Value) -> Result<Arc<LiveQuery>> {
+        let matcher = compile_matcher(arguments, &self.pattern_cache)?;
+        let filters = SearchFilters::from_args(arguments, &self.doc_type_extensions)?;
+
+        let mut queries = self.queries.write();
+        if queries.len() >= MAX_LIVE_QUERIES {
+            queries.retain(|_, query| query.subscribers() > 0);
+            if queries.len() >= MAX_LIVE_QUERIES {
+                return Err(anyhow!(
+                    "{} live queries are registered and subscribed to; remove one first", MAX_LIVE_QUERIES
+                ));
+            }
+        }
+        let id = format!("live-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, _) = broadcast::channel(UPDATE_BUFFER);
+        let query = Arc::new(LiveQuery {
+            id: id.clone(),
+            drive,
+            arguments: arguments.clone(),
+            matcher,
+            filters,
+            sender,
+            seq: AtomicU64::new(0),
+            created: SystemTime::now(),
+        });
+        queries.insert(id, Arc::clone(&query));
+        Ok(query)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<LiveQuery>> {
+        self.queries.read().get(id).cloned()
+    }
+
+    /// Unregister a query, which ends its subscribers' streams
+    pub fn remove(&self, id: &str) -> bool {
+        self.queries.write().remove(id).is_some()
+    }
+
+    /// Registered queries, oldest first
+    pub fn list(&self) -> Vec<Arc<LiveQuery>> {
+        let mut queries: Vec<Arc<LiveQuery>> = self.queries.read().values().cloned().collect();
+        queries.sort_by_key(|query| query.created);
+        queries
+    }
+
+    /// Send the updates a batch of changes makes to the queries on its drive
+    ///
+    /// Returns the number of updates sent.
+    pub fn apply(&self, cache: &MftCache, changes: &[UsnChange]) -> usize {
+        let drive = cache.drive_letter();
+        let queries: Vec<Arc<LiveQuery>> = self.queries.read()
+            .values()
+            .filter(|query| query.drive == drive)
+            .cloned()
+            .collect();
+        if queries.is_empty() {
+            return 0;
+        }
+
+        let files = cache.get_files();
+        let mut sent = 0;
+        for query in &queries {
+            for update in query.updates(&files, changes) {
+                // No subscriber right now is not an error: they may connect later
+                if query.sender.send(update).is_ok() {
+                    sent += 1;
+                }
+            }
+        }
+        if sent > 0 {
+            debug!("Sent {} live query updates for drive {}:", sent, drive);
+        }
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, path: &str, is_directory: bool) -> FileEntry {
+        FileEntry { is_directory, ..FileEntry::test(id, path) }
+    }
+
+    fn change(file_id: u64, parent_id: u64, usn: i64, reason: u32, name: &str) -> UsnChange {
+        UsnChange { file_id, parent_id, usn, reason, attributes: 0, name: name.to_string() }
+    }
+
+    #[test]
+    fn test_updates_from_changes() {
+        let registry = LiveQueryRegistry::new(Arc::new(PatternCache::new()), Arc::new(HashMap::new()));
+        let query = registry.register('C', &json!({"pattern": "*.log", "path": "logs"})).unwrap();
+        let mut receiver = query.subscribe();
+
+        let files: HashMap<u64, FileEntry> = [
+            entry(1, "logs", true),
+            entry(2, "logs\\old.log", false),
+            entry(3, "logs\\notes.txt", false),
+        ].into_iter().map(|e| (e.id, e)).collect();
+        let changes = [
+            change(4, 1, 10, reason::FILE_CREATE, "new.log"),
+            change(4, 1, 11, reason::FILE_CREATE | reason::CLOSE, "new.log"),
+            change(2, 1, 12, reason::FILE_DELETE, "old.log"),
+            // Renamed from a non-match to a match, and a match outside the path filter
+            change(3, 1, 13, reason::RENAME_OLD_NAME, "notes.txt"),
+            change(3, 1, 14, reason::RENAME_NEW_NAME, "notes.log"),
+            change(5, 99, 15, reason::FILE_CREATE, "other.log"),
+            change(1, 0, 16, reason::DATA_EXTEND, "logs"),
+        ];
+
+        let updates = query.updates(&files, &changes);
+        let summary: Vec<(u64, LiveChange, u64, &str)> = updates.iter()
+            .map(|u| (u.seq, u.change, u.id, u.path.as_str()))
+            .collect();
+        assert_eq!(summary, vec![
+            (1, LiveChange::Added, 4, "C:\\logs\\new.log"),
+            (2, LiveChange::Removed, 2, "C:\\logs\\old.log"),
+            (3, LiveChange::Added, 3, "C:\\logs\\notes.log"),
+        ]);
+
+        for update in updates {
+            query.sender.send(update).unwrap();
+        }
+        assert_eq!(receiver.try_recv().unwrap().name, "new.log");
+        assert_eq!(registry.list().len(), 1);
+        assert!(registry.remove(query.id()));
+        assert!(registry.get(query.id()).is_none());
+    }
+
+    #[test]
+    fn test_unsubscribed_queries_make_room() {
+        let registry = LiveQueryRegistry::new(Arc::new(PatternCache::new()), Arc::new(HashMap::new()));
+        let kept = registry.register('C', &json!({"pattern": "*.rs"})).unwrap();
+        let _receiver = kept.subscribe();
+        for _ in 1..MAX_LIVE_QUERIES {
+            registry.register('C', &json!({"pattern": "*.md"})).unwrap();
+        }
+        registry.register('D', &json!({"pattern": "*.md"})).unwrap();
+        assert_eq!(registry.list().len(), 2);
+        assert!(registry.get(kept.id()).is_some());
+    }
+}
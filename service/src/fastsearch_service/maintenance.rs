@@ -0,0 +1,106 @@
+//! Read-only maintenance mode for a drive's cache
+//!
+//! Backups and benchmarks want the disk quiet, but a cache keeps itself
+//! current in the background: the USN journal is read every second, the cache
+//! is saved every few minutes, and drives without a journal are rebuilt now
+//! and then. While a drive is in maintenance all of that waits and searches
+//! keep answering from the cache as it is. Nothing is lost: the journal (or,
+//! for directory mounts, the watcher queue) holds the changes until the
+//! drive is resumed.
+
+use std::time::{Duration, SystemTime};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// A maintenance period
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MaintenanceState {
+    /// When it began (UNIX timestamp)
+    pub since: u64,
+    /// When it ends by itself (UNIX timestamp), if it was given a duration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Whether a drive's background updates are held, shared by every copy of its cache
+#[derive(Debug, Default)]
+pub struct MaintenanceLock {
+    state: Mutex<Option<MaintenanceState>>,
+}
+
+impl MaintenanceLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold background updates, until `end` or for `duration`
+    ///
+    /// Beginning again while in maintenance replaces the reason and duration.
+    pub fn begin(&self, reason: Option<&str>, duration: Option<Duration>) -> MaintenanceState {
+        let now = SystemTime::now();
+        let state = MaintenanceState {
+            since: unix_secs(now),
+            until: duration.map(|duration| unix_secs(now + duration)),
+            reason: reason.map(str::to_string),
+        };
+        *self.state.lock() = Some(state.clone());
+        state
+    }
+
+    /// Let background updates run again, returning the period that ended
+    pub fn end(&self) -> Option<MaintenanceState> {
+        self.state.lock().take()
+    }
+
+    /// The current maintenance period; one past its end is over
+    pub fn state(&self) -> Option<MaintenanceState> {
+        let mut state = self.state.lock();
+        if state.as_ref().and_then(|state| state.until).map_or(false, |until| unix_secs(SystemTime::now()) >= until) {
+            *state = None;
+        }
+        state.clone()
+    }
+
+    /// Whether background updates are held right now
+    pub fn is_active(&self) -> bool {
+        self.state().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_begin_and_end() {
+        let lock = MaintenanceLock::new();
+        assert!(!lock.is_active());
+
+        let state = lock.begin(Some("nightly backup"), None);
+        assert!(lock.is_active());
+        assert_eq!(state.until, None);
+        assert_eq!(lock.state().unwrap().reason.as_deref(), Some("nightly backup"));
+
+        assert_eq!(lock.end(), Some(state));
+        assert!(!lock.is_active());
+        assert_eq!(lock.end(), None);
+    }
+
+    #[test]
+    fn test_maintenance_ends_by_itself() {
+        let lock = MaintenanceLock::new();
+        lock.begin(None, Some(Duration::ZERO));
+        assert!(!lock.is_active());
+
+        let state = lock.begin(None, Some(Duration::from_secs(3600)));
+        assert!(lock.is_active());
+        assert_eq!(state.until, Some(state.since + 3600));
+    }
+}
@@ -0,0 +1,1311 @@
+//! Pattern compilation for the `search_type` modes of `fast_search`
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use parking_lot::RwLock;
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use super::camel::CamelPattern;
+use super::name_affixes::NameAffix;
+
+/// Maximum number of compiled patterns kept in the cache
+const PATTERN_CACHE_CAPACITY: usize = 256;
+
+/// Maximum number of patterns in one search
+pub const MAX_PATTERNS: usize = 64;
+
+/// Longest glob or regex pattern compiled, in characters
+pub const MAX_PATTERN_LENGTH: usize = 1024;
+
+/// How long a regex search without `timeout_ms` may scan before answering with what it found
+pub const REGEX_SEARCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Memory a compiled regex may take; the `regex` crate allows 10 MB
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+/// Memory the lazy DFA of a regex may cache before matching falls back to the slower NFA
+const REGEX_DFA_SIZE_LIMIT: usize = 2 * 1024 * 1024;
+
+/// How deeply groups and repetitions may nest
+const REGEX_NEST_LIMIT: u32 = 32;
+
+/// How a search pattern should be interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SearchType {
+    /// Shell-style wildcards (`*.rs`, `README?`)
+    #[default]
+    Glob,
+    /// Full regular expression syntax, matched anywhere in the name
+    Regex,
+    /// Exact file name (case-insensitive unless `case_sensitive`)
+    Exact,
+    /// Subsequence match ranked by an fzf-style score
+    Fuzzy,
+    /// Prefixes of the name's words in order, for identifiers (`FBC`, `mft_cache`)
+    Camel,
+}
+
+impl SearchType {
+    /// Every search type, in the order they're documented
+    pub const ALL: [SearchType; 5] = [SearchType::Glob, SearchType::Regex, SearchType::Exact, SearchType::Fuzzy, SearchType::Camel];
+
+    /// Parse a search type from the `search_type` tool argument
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "glob" => Some(SearchType::Glob),
+            "regex" => Some(SearchType::Regex),
+            "exact" => Some(SearchType::Exact),
+            "fuzzy" => Some(SearchType::Fuzzy),
+            "camel" => Some(SearchType::Camel),
+            _ => None,
+        }
+    }
+
+    /// Name of the search type as used in the tool schema
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchType::Glob => "glob",
+            SearchType::Regex => "regex",
+            SearchType::Exact => "exact",
+            SearchType::Fuzzy => "fuzzy",
+            SearchType::Camel => "camel",
+        }
+    }
+
+    /// How patterns of this type match, for the syntax reference
+    pub fn description(&self) -> &'static str {
+        match self {
+            SearchType::Glob => "Shell-style wildcards: `*` matches any run of characters, `?` one character, `[0-9]` one of a class and `{jpg,png}` either alternative (`*.rs`, `README?`); with a `/` the glob is matched against the path, `**` spanning folders (`src/**/*.rs`)",
+            SearchType::Regex => "Regular expression matched anywhere in the name (`^test_.*\\.py$`)",
+            SearchType::Exact => "The whole file name, e.g. `Cargo.toml`",
+            SearchType::Fuzzy => "Characters of the pattern in order, not necessarily adjacent; results are ranked by an fzf-style score (`srchengn` finds `search_engine.rs`)",
+            SearchType::Camel => "The starts of the name's words in order, for source files: `FBC` finds `FooBarController.cs` and `mft_cache` finds `MftCache.rs`. Words end at capitals, digits and separators and may be skipped; a capital or a letter after `_` in the pattern has to start a word",
+        }
+    }
+}
+
+/// A compiled search pattern
+#[derive(Debug, Clone)]
+pub enum PatternMatcher {
+    /// Matches every name (`*`, `*.*` or an empty pattern)
+    Any,
+    /// Compiled regular expression
+    Regex(Arc<Regex>),
+    /// Glob compiled to an anchored regex, with its literal runs captured (see `glob_to_regex`),
+    /// the literal start or end of the names it matches, for the affix indexes, and the
+    /// lowercased literal runs every match contains, for the trigram index
+    Glob(Arc<Regex>, Option<NameAffix>, Arc<[String]>),
+    /// Glob with a separator, matched against the path relative to the drive root (see `path_glob_to_regex`)
+    PathGlob(Arc<Regex>),
+    /// Lowercased name for exact comparison
+    Exact(String),
+    /// Name for exact comparison, case included (`case_sensitive`)
+    ExactCase(String),
+    /// Scored subsequence match
+    Fuzzy(FuzzyPattern),
+    /// Word prefixes in order (CamelCase and acronyms)
+    Camel(CamelPattern),
+    /// Matches when any of several patterns does (`pattern` given as an array)
+    AnyOf(Arc<[PatternMatcher]>),
+    /// Matches what the first matcher does, except what the second matches (`!pattern`)
+    Except(Arc<PatternMatcher>, Arc<PatternMatcher>),
+    /// Matches names with their diacritics folded away (`ignore_diacritics`)
+    ///
+    /// The inner matcher is compiled from folded patterns and sees folded names.
+    Folded(Arc<PatternMatcher>),
+    /// Matches the whole path relative to the drive root instead of the name (`match_path`)
+    ///
+    /// The inner matcher sees the path as if it were a name, so `*` spans folders.
+    OnPath(Arc<PatternMatcher>),
+}
+
+impl PatternMatcher {
+    /// Check whether a file name matches this pattern
+    ///
+    /// Path globs see the name as a path at the drive root.
+    pub fn is_match(&self, name: &str) -> bool {
+        self.is_file_match(name, name)
+    }
+
+    /// Check whether a file matches: its name for name patterns, its path
+    /// (relative to the drive root) for path globs
+    pub fn is_file_match(&self, name: &str, path: &str) -> bool {
+        match self {
+            PatternMatcher::Any => true,
+            PatternMatcher::Regex(regex) | PatternMatcher::Glob(regex, ..) => regex.is_match(name),
+            PatternMatcher::PathGlob(regex) => regex.is_match(path.trim_start_matches(['\\', '/'])),
+            PatternMatcher::Exact(expected) => name.to_lowercase() == *expected,
+            PatternMatcher::ExactCase(expected) => name == expected,
+            PatternMatcher::Fuzzy(fuzzy) => fuzzy.score(name).is_some(),
+            PatternMatcher::Camel(camel) => camel.is_match(name),
+            PatternMatcher::AnyOf(matchers) => matchers.iter().any(|m| m.is_file_match(name, path)),
+            PatternMatcher::Except(include, exclude) => {
+                include.is_file_match(name, path) && !exclude.is_file_match(name, path)
+            }
+            PatternMatcher::Folded(inner) => inner.is_file_match(&fold_name(name), &fold_name(path)),
+            PatternMatcher::OnPath(inner) => {
+                let path = strip_drive(path);
+                inner.is_file_match(path, path)
+            }
+        }
+    }
+
+    /// Relevance score in `0.0..=1.0`, or `None` if the name does not match
+    ///
+    /// Only fuzzy patterns produce graded scores; every other match scores 1.0.
+    /// Several patterns score as the best of them.
+    pub fn score(&self, name: &str) -> Option<f64> {
+        match self {
+            PatternMatcher::Fuzzy(fuzzy) => fuzzy.score(name),
+            PatternMatcher::AnyOf(matchers) => matchers.iter().filter_map(|m| m.score(name)).reduce(f64::max),
+            PatternMatcher::Except(include, exclude) => include.score(name).filter(|_| !exclude.is_match(name)),
+            PatternMatcher::Folded(inner) => inner.score(&fold_name(name)),
+            _ => self.is_match(name).then_some(1.0),
+        }
+    }
+
+    /// Byte ranges of `name` the pattern matched, sorted and merged; empty if it doesn't match
+    ///
+    /// Regexes give each match, globs their literal parts (`.rs` of `*.rs`),
+    /// exact names the whole name, fuzzy patterns the matched characters and
+    /// camel patterns the word prefixes they used.
+    /// A pattern matching every name highlights nothing.
+    pub fn match_spans(&self, name: &str) -> Vec<Range<usize>> {
+        let spans = match self {
+            // Path patterns are highlighted by the path, not the name
+            PatternMatcher::Any | PatternMatcher::PathGlob(_) | PatternMatcher::OnPath(_) => Vec::new(),
+            PatternMatcher::Regex(regex) => regex.find_iter(name).map(|m| m.range()).collect(),
+            PatternMatcher::Glob(regex, ..) => match regex.captures(name) {
+                Some(captures) => captures.iter().skip(1).flatten().map(|m| m.range()).collect(),
+                None => Vec::new(),
+            },
+            PatternMatcher::Exact(_) | PatternMatcher::ExactCase(_) => {
+                self.is_match(name).then_some(0..name.len()).into_iter().collect()
+            }
+            PatternMatcher::Fuzzy(fuzzy) => {
+                let starts: Vec<usize> = name.char_indices().map(|(i, _)| i).collect();
+                fuzzy.positions(name).unwrap_or_default().into_iter()
+                    .map(|i| starts[i]..starts.get(i + 1).copied().unwrap_or(name.len()))
+                    .collect()
+            }
+            PatternMatcher::Camel(camel) => camel.positions(name).unwrap_or_default(),
+            PatternMatcher::AnyOf(matchers) => matchers.iter().flat_map(|m| m.match_spans(name)).collect(),
+            PatternMatcher::Except(include, exclude) => {
+                if exclude.is_match(name) { Vec::new() } else { include.match_spans(name) }
+            }
+            PatternMatcher::Folded(inner) => {
+                // Folding works a character at a time, so every folded byte comes from one character of the name
+                let mut folded = String::with_capacity(name.len());
+                let mut origin: Vec<Range<usize>> = Vec::with_capacity(name.len());
+                for (i, c) in name.char_indices() {
+                    let part = fold_name(c.encode_utf8(&mut [0; 4])).into_owned();
+                    origin.resize(origin.len() + part.len(), i..i + c.len_utf8());
+                    folded.push_str(&part);
+                }
+                inner.match_spans(&folded).into_iter()
+                    .map(|span| origin[span.start].start..origin[span.end - 1].end)
+                    .collect()
+            }
+        };
+        merge_spans(spans)
+    }
+
+    /// Whether this matcher produces graded relevance scores
+    pub fn is_scored(&self) -> bool {
+        match self {
+            PatternMatcher::Fuzzy(_) => true,
+            PatternMatcher::AnyOf(matchers) => matchers.iter().any(PatternMatcher::is_scored),
+            PatternMatcher::Except(include, _) => include.is_scored(),
+            PatternMatcher::Folded(inner) => inner.is_scored(),
+            _ => false,
+        }
+    }
+
+    /// The matcher to use on names that are already folded, for folded patterns
+    pub fn folded_inner(&self) -> Option<&PatternMatcher> {
+        match self {
+            PatternMatcher::Folded(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// Lowercased names matched, when the pattern is nothing but exact names
+    ///
+    /// For case-sensitive names these are the name index keys to look in, and
+    /// the names found there still need matching.
+    pub fn exact_names(&self) -> Option<Vec<String>> {
+        match self {
+            PatternMatcher::Exact(name) => Some(vec![name.clone()]),
+            PatternMatcher::ExactCase(name) => Some(vec![name.to_lowercase()]),
+            PatternMatcher::AnyOf(matchers) => {
+                let mut names = Vec::new();
+                for matcher in matchers.iter() {
+                    names.extend(matcher.exact_names()?);
+                }
+                names.sort();
+                names.dedup();
+                Some(names)
+            }
+            PatternMatcher::Except(include, exclude) => {
+                let mut names = include.exact_names()?;
+                // Lowercased names can't be held against case-sensitive exclusions
+                if !include.has_exact_case() {
+                    names.retain(|name| !exclude.is_match(name));
+                }
+                Some(names)
+            }
+            _ => None,
+        }
+    }
+
+    /// Literal starts or ends of the names matched, one per pattern, when every pattern has one
+    ///
+    /// Names with none of them can't match, so only the names the affix
+    /// indexes give for them need matching.
+    pub fn name_affixes(&self) -> Option<Vec<NameAffix>> {
+        match self {
+            PatternMatcher::Glob(_, affix, _) => affix.clone().map(|affix| vec![affix]),
+            PatternMatcher::AnyOf(matchers) => {
+                let mut affixes = Vec::new();
+                for matcher in matchers.iter() {
+                    affixes.extend(matcher.name_affixes()?);
+                }
+                Some(affixes)
+            }
+            PatternMatcher::Except(include, _) => include.name_affixes(),
+            _ => None,
+        }
+    }
+
+    /// Literal runs every matched name contains, one list per pattern, when every pattern has some
+    ///
+    /// Names lacking the runs of each pattern can't match, so only the names the
+    /// trigram index gives for them need matching. Regexes have one when they
+    /// are plain text, which matches anywhere in the name.
+    pub fn name_literals(&self) -> Option<Vec<Vec<String>>> {
+        match self {
+            PatternMatcher::Glob(_, _, literals) if !literals.is_empty() => Some(vec![literals.to_vec()]),
+            PatternMatcher::Regex(regex) => regex_literal(regex.as_str()).map(|literal| vec![vec![literal]]),
+            PatternMatcher::AnyOf(matchers) => {
+                let mut literals = Vec::new();
+                for matcher in matchers.iter() {
+                    literals.extend(matcher.name_literals()?);
+                }
+                Some(literals)
+            }
+            PatternMatcher::Except(include, _) => include.name_literals(),
+            _ => None,
+        }
+    }
+
+    fn has_exact_case(&self) -> bool {
+        match self {
+            PatternMatcher::ExactCase(_) => true,
+            PatternMatcher::AnyOf(matchers) => matchers.iter().any(PatternMatcher::has_exact_case),
+            _ => false,
+        }
+    }
+}
+
+/// Name patterns of a search: matches must match one of `include` and none of `exclude`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternSet {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Match the case of the patterns exactly (fuzzy patterns always ignore case)
+    pub case_sensitive: bool,
+    /// Match regardless of accents and compatibility forms (implies ignoring case)
+    pub ignore_diacritics: bool,
+    /// Match the patterns against the path relative to the drive root instead of the name
+    pub match_path: bool,
+}
+
+impl PatternSet {
+    /// Parse the `pattern`, `not_pattern`, `case_sensitive`, `ignore_diacritics` and `match_path` arguments
+    ///
+    /// Each is one pattern or an array of them. Patterns in `pattern` starting
+    /// with `!` are exclusions too, so `["*.log", "!*install*.log"]` finds every
+    /// log but the installer's. Without a pattern to include, every name is.
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let case_sensitive = bool_arg(args, "case_sensitive")?;
+        let ignore_diacritics = bool_arg(args, "ignore_diacritics")?;
+        let match_path = bool_arg(args, "match_path")?;
+        if case_sensitive && ignore_diacritics {
+            return Err(anyhow!("'ignore_diacritics' ignores case, so it can't be combined with 'case_sensitive'"));
+        }
+        let mut include = Vec::new();
+        let mut exclude = pattern_list(&args["not_pattern"], "not_pattern")?;
+        for pattern in pattern_list(&args["pattern"], "pattern")? {
+            match pattern.strip_prefix('!') {
+                Some("") => return Err(anyhow!("'!' must be followed by the pattern to leave out")),
+                Some(negated) => exclude.push(negated.to_string()),
+                None => include.push(pattern),
+            }
+        }
+        if include.is_empty() {
+            include.push("*".to_string());
+        }
+        if include.len() + exclude.len() > MAX_PATTERNS {
+            return Err(anyhow!("Too many patterns ({}, at most {})", include.len() + exclude.len(), MAX_PATTERNS));
+        }
+        Ok(Self { include, exclude, case_sensitive, ignore_diacritics, match_path })
+    }
+
+    /// The patterns as shown in responses and logs: `*.log (not *install*.log)`
+    pub fn describe(&self) -> String {
+        let mut text = self.include.join(", ");
+        if !self.exclude.is_empty() {
+            text.push_str(&format!(" (not {})", self.exclude.join(", ")));
+        }
+        text
+    }
+}
+
+/// An optional boolean argument, false when absent
+fn bool_arg(args: &Value, key: &str) -> Result<bool> {
+    match &args[key] {
+        Value::Null => Ok(false),
+        Value::Bool(value) => Ok(*value),
+        _ => Err(anyhow!("'{}' must be a boolean", key)),
+    }
+}
+
+/// Fold a name or pattern for diacritic-insensitive matching
+///
+/// Applies compatibility decomposition (NFKD) and drops the combining marks,
+/// so "Résumé" becomes "Resume" and "ﬁle" becomes "file"; letters NFKD leaves
+/// whole are spelled out (ß as ss, ø as o). Case is kept.
+pub fn fold_name(name: &str) -> Cow<'_, str> {
+    if name.is_ascii() {
+        return Cow::Borrowed(name);
+    }
+    let mut folded = String::with_capacity(name.len());
+    for c in name.nfkd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'Æ' => folded.push_str("AE"),
+            'œ' => folded.push_str("oe"),
+            'Œ' => folded.push_str("OE"),
+            'ø' => folded.push('o'),
+            'Ø' => folded.push('O'),
+            'ł' => folded.push('l'),
+            'Ł' => folded.push('L'),
+            'đ' => folded.push('d'),
+            'Đ' => folded.push('D'),
+            _ => folded.push(c),
+        }
+    }
+    Cow::Owned(folded)
+}
+
+/// Folded form of a name index key, if folding changes it
+pub fn folded_key(name: &str) -> Option<String> {
+    match fold_name(name) {
+        Cow::Owned(folded) if folded != name => Some(folded),
+        _ => None,
+    }
+}
+
+/// Patterns in a `pattern`-like argument: a string or an array of strings
+fn pattern_list(value: &Value, key: &str) -> Result<Vec<String>> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::String(pattern) => Ok(vec![pattern.clone()]),
+        Value::Array(items) if items.is_empty() => Err(anyhow!("'{}' must not be an empty array", key)),
+        Value::Array(items) => items.iter()
+            .map(|item| item.as_str().map(str::to_string).ok_or_else(|| anyhow!("'{}' must contain only strings", key)))
+            .collect(),
+        _ => Err(anyhow!("'{}' must be a string or an array of strings", key)),
+    }
+}
+
+/// Score for each matched pattern character
+const SCORE_MATCH: i64 = 16;
+/// Bonus when a match directly follows the previous matched character
+const BONUS_CONSECUTIVE: i64 = 8;
+/// Bonus when a match starts a word (`_`, `-`, `.`, space, path separator or camelCase hump)
+const BONUS_BOUNDARY: i64 = 8;
+/// Penalty for each skipped character inside the matched window
+const PENALTY_GAP: i64 = 2;
+
+/// Fuzzy pattern using fzf-style subsequence scoring
+#[derive(Debug, Clone)]
+pub struct FuzzyPattern {
+    chars: Vec<char>,
+}
+
+impl FuzzyPattern {
+    /// Create a fuzzy pattern (matching is case-insensitive)
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            chars: pattern.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()).collect(),
+        }
+    }
+
+    /// Score a candidate name, returning `None` if the pattern is not a subsequence of it
+    pub fn score(&self, name: &str) -> Option<f64> {
+        if self.chars.is_empty() {
+            return Some(1.0);
+        }
+
+        let original: Vec<char> = name.chars().collect();
+        let lower: Vec<char> = original.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+        let (start, end) = self.window(&lower)?;
+
+        // Score the window
+        let mut raw = 0;
+        let mut pi = 0;
+        let mut prev_matched = false;
+        for i in start..=end {
+            if pi < self.chars.len() && lower[i] == self.chars[pi] {
+                raw += SCORE_MATCH;
+                if prev_matched {
+                    raw += BONUS_CONSECUTIVE;
+                }
+                if is_word_boundary(&original, i) {
+                    raw += BONUS_BOUNDARY;
+                }
+                pi += 1;
+                prev_matched = true;
+            } else {
+                raw -= PENALTY_GAP;
+                prev_matched = false;
+            }
+        }
+
+        let n = self.chars.len() as i64;
+        let max = n * SCORE_MATCH + (n - 1) * BONUS_CONSECUTIVE + BONUS_BOUNDARY;
+        let alignment = (raw.max(0) as f64 / max as f64).min(1.0);
+        let coverage = self.chars.len() as f64 / original.len() as f64;
+
+        Some(alignment * 0.9 + coverage * 0.1)
+    }
+
+    /// Character indexes of the name the scored window matched, `None` if the pattern is not a subsequence of it
+    pub fn positions(&self, name: &str) -> Option<Vec<usize>> {
+        let lower: Vec<char> = name.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+        if self.chars.is_empty() {
+            return Some(Vec::new());
+        }
+        let (start, end) = self.window(&lower)?;
+        let mut positions = Vec::with_capacity(self.chars.len());
+        for (i, c) in lower.iter().enumerate().take(end + 1).skip(start) {
+            if positions.len() < self.chars.len() && *c == self.chars[positions.len()] {
+                positions.push(i);
+            }
+        }
+        Some(positions)
+    }
+
+    /// Shortest window (first and last character index) the pattern matches in, ending as early as possible
+    fn window(&self, lower: &[char]) -> Option<(usize, usize)> {
+        // Forward pass: find the earliest position where the whole pattern has matched
+        let mut pi = 0;
+        let mut end = None;
+        for (i, c) in lower.iter().enumerate() {
+            if *c == self.chars[pi] {
+                pi += 1;
+                if pi == self.chars.len() {
+                    end = Some(i);
+                    break;
+                }
+            }
+        }
+        let end = end?;
+
+        // Backward pass: tighten the window to the latest possible start
+        let mut pi = self.chars.len();
+        let mut start = end;
+        for i in (0..=end).rev() {
+            if lower[i] == self.chars[pi - 1] {
+                pi -= 1;
+                if pi == 0 {
+                    start = i;
+                    break;
+                }
+            }
+        }
+        Some((start, end))
+    }
+}
+
+/// Sort ranges and join those that overlap or touch, dropping empty ones
+fn merge_spans(mut spans: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    spans.retain(|span| !span.is_empty());
+    spans.sort_by_key(|span| span.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(spans.len());
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+    merged
+}
+
+/// Whether the character at `i` begins a word
+fn is_word_boundary(chars: &[char], i: usize) -> bool {
+    if i == 0 {
+        return true;
+    }
+    let prev = chars[i - 1];
+    matches!(prev, '_' | '-' | '.' | ' ' | '\\' | '/') || (prev.is_lowercase() && chars[i].is_uppercase())
+}
+
+/// Cache of compiled patterns keyed by search type, case sensitivity and pattern text
+///
+/// Agents tend to repeat the same handful of patterns, so compiling each
+/// regex once saves noticeable time on large drives. Patterns compiled for
+/// `match_path` are kept apart from the same text compiled for names.
+#[derive(Debug, Default)]
+pub struct PatternCache {
+    compiled: RwLock<HashMap<(SearchType, bool, bool, String), PatternMatcher>>,
+}
+
+impl PatternCache {
+    /// Create an empty pattern cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a compiled matcher for the pattern, compiling and caching it if needed
+    pub fn get_or_compile(&self, pattern: &str, search_type: SearchType, case_sensitive: bool) -> Result<PatternMatcher> {
+        self.get_or_compile_as(pattern, search_type, case_sensitive, false)
+    }
+
+    fn get_or_compile_as(&self, pattern: &str, search_type: SearchType, case_sensitive: bool, on_path: bool) -> Result<PatternMatcher> {
+        let key = (search_type, case_sensitive, on_path, pattern.to_string());
+
+        if let Some(matcher) = self.compiled.read().get(&key) {
+            return Ok(matcher.clone());
+        }
+
+        let matcher = if on_path {
+            compile_path_pattern(pattern, search_type, case_sensitive)?
+        } else {
+            compile_pattern_with_case(pattern, search_type, case_sensitive)?
+        };
+
+        let mut compiled = self.compiled.write();
+        if compiled.len() >= PATTERN_CACHE_CAPACITY {
+            compiled.clear();
+        }
+        compiled.insert(key, matcher.clone());
+
+        Ok(matcher)
+    }
+
+    /// Get a matcher for any of several patterns, each compiled and cached separately
+    pub fn get_or_compile_any(&self, patterns: &[String], search_type: SearchType, case_sensitive: bool) -> Result<PatternMatcher> {
+        self.get_or_compile_any_as(patterns, search_type, case_sensitive, false)
+    }
+
+    fn get_or_compile_any_as(&self, patterns: &[String], search_type: SearchType, case_sensitive: bool, on_path: bool) -> Result<PatternMatcher> {
+        let mut matchers = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            match self.get_or_compile_as(pattern, search_type, case_sensitive, on_path)? {
+                // One pattern matching everything makes the others irrelevant
+                PatternMatcher::Any => return Ok(PatternMatcher::Any),
+                matcher => matchers.push(matcher),
+            }
+        }
+        if matchers.len() == 1 {
+            return Ok(matchers.remove(0));
+        }
+        Ok(PatternMatcher::AnyOf(matchers.into()))
+    }
+
+    /// Get a matcher for a pattern set
+    ///
+    /// Exclusions use the search type of the search, except in fuzzy and camel
+    /// searches: such an exclusion would leave out nearly everything, so they are globs.
+    /// With `ignore_diacritics` the patterns are folded and the whole set
+    /// wrapped to fold the names it is given.
+    pub fn get_or_compile_set(&self, patterns: &PatternSet, search_type: SearchType) -> Result<PatternMatcher> {
+        if patterns.match_path {
+            if search_type == SearchType::Fuzzy {
+                return Err(anyhow!("'match_path' can't be combined with fuzzy search; use a glob like *\\Projects\\*\\Cargo.toml"));
+            }
+            // Wrapped outermost, so nothing treats the patterns as names
+            let on_path = PatternSet {
+                include: patterns.include.iter().map(|p| path_pattern(p, search_type)).collect(),
+                exclude: patterns.exclude.iter().map(|p| path_pattern(p, search_type)).collect(),
+                match_path: false,
+                ..patterns.clone()
+            };
+            return Ok(match self.get_or_compile_set_as(&on_path, search_type, true)? {
+                PatternMatcher::Any => PatternMatcher::Any,
+                matcher => PatternMatcher::OnPath(Arc::new(matcher)),
+            });
+        }
+        self.get_or_compile_set_as(patterns, search_type, false)
+    }
+
+    /// Compile a pattern set for names, or for whole paths seen as names (`on_path`)
+    fn get_or_compile_set_as(&self, patterns: &PatternSet, search_type: SearchType, on_path: bool) -> Result<PatternMatcher> {
+        if patterns.ignore_diacritics {
+            let fold_all = |list: &[String]| list.iter().map(|p| fold_name(p).into_owned()).collect();
+            let folded = PatternSet {
+                include: fold_all(&patterns.include),
+                exclude: fold_all(&patterns.exclude),
+                case_sensitive: false,
+                ignore_diacritics: false,
+                match_path: false,
+            };
+            return Ok(PatternMatcher::Folded(Arc::new(self.get_or_compile_set_as(&folded, search_type, on_path)?)));
+        }
+        let include = self.get_or_compile_any_as(&patterns.include, search_type, patterns.case_sensitive, on_path)?;
+        if patterns.exclude.is_empty() {
+            return Ok(include);
+        }
+        let exclude_type = match search_type {
+            SearchType::Fuzzy | SearchType::Camel => SearchType::Glob,
+            _ => search_type,
+        };
+        let exclude = self.get_or_compile_any_as(&patterns.exclude, exclude_type, patterns.case_sensitive, on_path)?;
+        Ok(PatternMatcher::Except(Arc::new(include), Arc::new(exclude)))
+    }
+
+    /// Number of patterns currently cached
+    pub fn len(&self) -> usize {
+        self.compiled.read().len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.compiled.read().is_empty()
+    }
+}
+
+/// Compile a pattern for the given search type, ignoring case
+pub fn compile_pattern(pattern: &str, search_type: SearchType) -> Result<PatternMatcher> {
+    compile_pattern_with_case(pattern, search_type, false)
+}
+
+/// Compile a pattern for the given search type, matching case if `case_sensitive`
+///
+/// Fuzzy patterns ignore case either way: the point of them is to forgive
+/// how a name was typed.
+pub fn compile_pattern_with_case(pattern: &str, search_type: SearchType, case_sensitive: bool) -> Result<PatternMatcher> {
+    if matches!(search_type, SearchType::Glob | SearchType::Regex) {
+        check_pattern_length(pattern)?;
+    }
+    match search_type {
+        SearchType::Glob => {
+            if pattern.is_empty() || pattern == "*" || pattern == "*.*" {
+                return Ok(PatternMatcher::Any);
+            }
+            let (regex, path) = if is_path_glob(pattern) {
+                (path_glob_to_regex(pattern), true)
+            } else {
+                (glob_to_regex(pattern), false)
+            };
+            let regex = build_regex(&regex, case_sensitive)
+                .map_err(|e| anyhow!("Invalid glob pattern '{}': {}", pattern, regex_error_summary(&e)))?;
+            if path {
+                Ok(PatternMatcher::PathGlob(Arc::new(regex)))
+            } else {
+                Ok(PatternMatcher::Glob(Arc::new(regex), NameAffix::of_glob(pattern), glob_literals(pattern).into()))
+            }
+        }
+        SearchType::Regex => {
+            if pattern.is_empty() {
+                return Ok(PatternMatcher::Any);
+            }
+            let regex = build_regex(pattern, case_sensitive)
+                .map_err(|e| anyhow!("Invalid regex '{}': {}", pattern, regex_error_summary(&e)))?;
+            Ok(PatternMatcher::Regex(Arc::new(regex)))
+        }
+        SearchType::Exact if case_sensitive => Ok(PatternMatcher::ExactCase(pattern.to_string())),
+        SearchType::Exact => Ok(PatternMatcher::Exact(pattern.to_lowercase())),
+        SearchType::Fuzzy => Ok(PatternMatcher::Fuzzy(FuzzyPattern::new(pattern))),
+        SearchType::Camel if pattern.chars().any(char::is_alphanumeric) => Ok(PatternMatcher::Camel(CamelPattern::new(pattern))),
+        SearchType::Camel => Ok(PatternMatcher::Any),
+    }
+}
+
+/// Compile a pattern matched against a whole path (`match_path`), as if the path were a name
+///
+/// Unlike path globs, a glob's `*` spans folders here: `*\Projects\*\Cargo.toml`
+/// finds every `Cargo.toml` at any depth below any `Projects` folder.
+fn compile_path_pattern(pattern: &str, search_type: SearchType, case_sensitive: bool) -> Result<PatternMatcher> {
+    match search_type {
+        SearchType::Glob if !(pattern.is_empty() || pattern == "*") => {
+            check_pattern_length(pattern)?;
+            let regex = build_regex(&glob_to_regex(pattern), case_sensitive)
+                .map_err(|e| anyhow!("Invalid glob pattern '{}': {}", pattern, regex_error_summary(&e)))?;
+            // Matched against paths, so the name affixes and literals don't apply
+            Ok(PatternMatcher::Glob(Arc::new(regex), None, Arc::from([])))
+        }
+        _ => compile_pattern_with_case(pattern, search_type, case_sensitive),
+    }
+}
+
+/// A `match_path` pattern in the form of cache paths: backslashes, without a drive or leading separator
+///
+/// Regexes are left alone, since their backslashes are escapes.
+fn path_pattern(pattern: &str, search_type: SearchType) -> String {
+    if search_type == SearchType::Regex {
+        return pattern.to_string();
+    }
+    strip_drive(&pattern.replace('/', "\\")).to_string()
+}
+
+/// A path without its drive (`C:`) and leading separators
+fn strip_drive(path: &str) -> &str {
+    let bytes = path.as_bytes();
+    let path = if bytes.len() >= 2 && bytes[1] == b':' && bytes[0].is_ascii_alphabetic() { &path[2..] } else { path };
+    path.trim_start_matches(['\\', '/'])
+}
+
+/// Convert a glob pattern to an anchored regex string
+///
+/// Besides `*` and `?`, globs take character classes (`[abc]`, `[a-z]`,
+/// `[!0-9]`) and alternatives (`*.{jpg,png}`). Each run of literal characters
+/// (and each all-literal alternative) is a capture group, so a match tells
+/// which parts of the name the pattern spelled out.
+pub fn glob_to_regex(pattern: &str) -> String {
+    let mut regex_str = String::with_capacity(pattern.len() * 2 + 2);
+    regex_str.push('^');
+    push_glob(&mut regex_str, pattern, false, true);
+    regex_str.push('$');
+    regex_str
+}
+
+/// Whether a glob is matched against paths rather than names: it has a separator
+pub fn is_path_glob(pattern: &str) -> bool {
+    pattern.contains(['/', '\\'])
+}
+
+/// Convert a path glob to an anchored regex over paths relative to the drive root
+///
+/// `/` and `\` are alike, `*`, `?` and classes stay within one name and a `**`
+/// part matches any number of folders, so `src/**/test_*.{rs,py}` finds test
+/// modules anywhere below a `src` folder. Without a leading separator (or
+/// drive) the pattern may start at any depth, like an exclude glob.
+pub fn path_glob_to_regex(pattern: &str) -> String {
+    let normalized = pattern.replace('/', "\\");
+    // A drive anchors the pattern at its root
+    let has_drive = normalized.as_bytes().get(1) == Some(&b':')
+        && normalized.chars().next().map_or(false, |c| c.is_ascii_alphabetic());
+    let body = if has_drive { &normalized[2..] } else { normalized.as_str() };
+    let anchored = has_drive || body.starts_with('\\');
+    let parts: Vec<&str> = body.split('\\').filter(|part| !part.is_empty()).collect();
+
+    let mut regex_str = String::from("^");
+    if !anchored {
+        regex_str.push_str(r"(?:.*\\)?");
+    }
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if *part == "**" {
+            // Any number of folders, including none; at the end, anything below
+            regex_str.push_str(if last { ".*" } else { r"(?:.*\\)?" });
+            continue;
+        }
+        push_glob(&mut regex_str, part, true, false);
+        if !last {
+            regex_str.push_str(r"\\");
+        }
+    }
+    regex_str.push('$');
+    regex_str
+}
+
+/// Append the regex for one glob (or one part of a path glob) to `out`
+///
+/// In a `path` part, wildcards and negated classes don't match separators.
+/// With `capture`, literal runs become capture groups (see `glob_to_regex`).
+pub(crate) fn push_glob(out: &mut String, pattern: &str, path: bool, capture: bool) {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut literal = String::new();
+    let flush = |out: &mut String, literal: &mut String| {
+        if literal.is_empty() {
+            return;
+        }
+        if capture {
+            out.push('(');
+        }
+        out.push_str(&regex::escape(literal));
+        if capture {
+            out.push(')');
+        }
+        literal.clear();
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => {
+                flush(out, &mut literal);
+                out.push_str(if path { r"[^\\]*" } else { ".*" });
+                // `**` within a name is the same as `*`
+                while chars.get(i + 1) == Some(&'*') {
+                    i += 1;
+                }
+            }
+            '?' => {
+                flush(out, &mut literal);
+                out.push_str(if path { r"[^\\]" } else { "." });
+            }
+            '[' => match glob_class(&chars[i..], path) {
+                Some((class, len)) => {
+                    flush(out, &mut literal);
+                    out.push_str(&class);
+                    i += len - 1;
+                }
+                None => literal.push('['),
+            },
+            '{' => match glob_alternatives(&chars[i..]) {
+                Some((alternatives, len)) => {
+                    flush(out, &mut literal);
+                    let plain = alternatives.iter().all(|alt| !alt.contains(['*', '?', '[', '{']));
+                    out.push_str(if capture && plain { "(" } else { "(?:" });
+                    for (n, alternative) in alternatives.iter().enumerate() {
+                        if n > 0 {
+                            out.push('|');
+                        }
+                        push_glob(out, alternative, path, false);
+                    }
+                    out.push(')');
+                    i += len - 1;
+                }
+                None => literal.push('{'),
+            },
+            c => literal.push(c),
+        }
+        i += 1;
+    }
+    flush(out, &mut literal);
+}
+
+/// Shortest literal run worth looking up in the trigram index
+const MIN_LITERAL_LENGTH: usize = 3;
+
+/// Lowercased literal runs of a glob every match contains, of at least three characters
+///
+/// Wildcards, classes and brace groups end a run, and so do characters other
+/// than ASCII, whose lowercase form can depend on their neighbours.
+fn glob_literals(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut literals = Vec::new();
+    let mut run = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let skip = match chars[i] {
+            '[' => glob_class(&chars[i..], false).map(|(_, len)| len),
+            '{' => glob_alternatives(&chars[i..]).map(|(_, len)| len),
+            '*' | '?' => Some(1),
+            c if !c.is_ascii() => Some(1),
+            _ => None,
+        };
+        match skip {
+            Some(len) => {
+                literals.push(std::mem::take(&mut run));
+                i += len;
+            }
+            None => {
+                run.push(chars[i].to_ascii_lowercase());
+                i += 1;
+            }
+        }
+    }
+    literals.push(run);
+    literals.retain(|literal| literal.len() >= MIN_LITERAL_LENGTH);
+    literals
+}
+
+/// A regex that is plain ASCII text, lowercased, if it is long enough to look up
+fn regex_literal(pattern: &str) -> Option<String> {
+    let plain = pattern.chars().all(|c| c.is_ascii() && !"\\.^$|?*+()[]{}".contains(c));
+    (plain && pattern.len() >= MIN_LITERAL_LENGTH).then(|| pattern.to_ascii_lowercase())
+}
+
+/// A character class at the start of `chars` (`[a-z]`, `[!0-9]`) as a regex class, and its length
+///
+/// A `]` right after the opening bracket is part of the class. Without a
+/// closing bracket the `[` is an ordinary character.
+fn glob_class(chars: &[char], path: bool) -> Option<(String, usize)> {
+    let mut i = 1;
+    let negated = matches!(chars.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let start = i;
+    if chars.get(i) == Some(&']') {
+        i += 1;
+    }
+    let end = i + chars[i..].iter().position(|c| *c == ']')?;
+    let mut class = String::from(if negated { "[^" } else { "[" });
+    for c in &chars[start..end] {
+        if matches!(c, '\\' | '[' | ']' | '&' | '~' | '^') {
+            class.push('\\');
+        }
+        class.push(*c);
+    }
+    if negated && path {
+        class.push_str(r"\\");
+    }
+    class.push(']');
+    Some((class, end + 1))
+}
+
+/// Alternatives of a brace group at the start of `chars` (`{jpg,png}`), and its length
+///
+/// Groups nest; one without a comma or a closing brace is taken literally.
+fn glob_alternatives(chars: &[char]) -> Option<(Vec<String>, usize)> {
+    let mut alternatives = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().skip(1) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => {
+                alternatives.push(current);
+                return (alternatives.len() > 1).then_some((alternatives, i + 1));
+            }
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                alternatives.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(*c);
+    }
+    None
+}
+
+/// Refuse patterns too long to be names, before compiling them
+fn check_pattern_length(pattern: &str) -> Result<()> {
+    let length = pattern.chars().count();
+    if length > MAX_PATTERN_LENGTH {
+        return Err(anyhow!("Pattern is too long ({} characters, at most {})", length, MAX_PATTERN_LENGTH));
+    }
+    Ok(())
+}
+
+/// Compile a user-supplied (or glob-derived) regex within the memory and nesting limits
+///
+/// The `regex` crate doesn't backtrack, so matching a name takes time linear
+/// in its length; the limits bound what a hostile pattern can make that line
+/// cost, and the search deadline bounds the scan as a whole.
+fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+        .nest_limit(REGEX_NEST_LIMIT)
+        .build()
+}
+
+/// Reduce a regex compile error to a single line suitable for tool output
+fn regex_error_summary(error: &regex::Error) -> String {
+    match error {
+        regex::Error::Syntax(msg) => msg
+            .lines()
+            .filter(|line| line.starts_with("error:"))
+            .map(|line| line.trim_start_matches("error:").trim().to_string())
+            .next()
+            .unwrap_or_else(|| "syntax error".to_string()),
+        regex::Error::CompiledTooBig(limit) => {
+            format!("compiled pattern exceeds size limit of {} bytes", limit)
+        }
+        _ => "unsupported pattern".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matching() {
+        let matcher = compile_pattern("*.rs", SearchType::Glob).unwrap();
+        assert!(matcher.is_match("main.rs"));
+        assert!(matcher.is_match("MAIN.RS"));
+        assert!(!matcher.is_match("main.rs.bak"));
+        assert!(!matcher.is_match("mainxrs"));
+    }
+
+    #[test]
+    fn test_glob_classes_and_alternatives() {
+        let matcher = compile_pattern("img_[0-9][0-9].{jpg,png}", SearchType::Glob).unwrap();
+        assert!(matcher.is_match("IMG_07.png"));
+        assert!(!matcher.is_match("img_7x.jpg"));
+        assert!(!matcher.is_match("img_07.gif"));
+        let matcher = compile_pattern("[!.]*", SearchType::Glob).unwrap();
+        assert!(matcher.is_match("notes.txt"));
+        assert!(!matcher.is_match(".gitignore"));
+        // Unclosed brackets and braces without a comma are plain characters
+        assert!(compile_pattern("[draft", SearchType::Glob).unwrap().is_match("[draft"));
+        assert!(compile_pattern("{x}.txt", SearchType::Glob).unwrap().is_match("{x}.txt"));
+        assert_eq!(glob_to_regex("*.{rs,py}"), "^.*(\\.)(rs|py)$");
+    }
+
+    #[test]
+    fn test_name_literals() {
+        assert_eq!(glob_literals("*Invoice*2024?.pdf"), vec!["invoice", "2024", ".pdf"]);
+        assert_eq!(glob_literals("img_[0-9][0-9].{jpg,png}"), vec!["img_"]);
+        assert_eq!(glob_literals("*ab*Σχέδιο*"), Vec::<String>::new());
+        assert_eq!(regex_literal("Budget"), Some("budget".to_string()));
+        assert_eq!(regex_literal("^budget"), None);
+        assert_eq!(regex_literal("ab"), None);
+
+        let substring = compile_pattern("*report*", SearchType::Glob).unwrap();
+        assert_eq!(substring.name_literals(), Some(vec![vec!["report".to_string()]]));
+        let plain = compile_pattern("report", SearchType::Regex).unwrap();
+        assert_eq!(plain.name_literals(), Some(vec![vec!["report".to_string()]]));
+        let either = PatternCache::new().get_or_compile_any(&["*.md".to_string(), "*a*".to_string()], SearchType::Glob, false).unwrap();
+        assert_eq!(either.name_literals(), None);
+        // Matched against paths, which the name index doesn't hold
+        let on_path = compile_pattern("src/*report*", SearchType::Glob).unwrap();
+        assert_eq!(on_path.name_literals(), None);
+    }
+
+    #[test]
+    fn test_path_globs_match_paths() {
+        let matcher = compile_pattern("src/**/test_*.{rs,py}", SearchType::Glob).unwrap();
+        assert!(matcher.is_file_match("test_api.rs", "Projects\\app\\src\\test_api.rs"));
+        assert!(matcher.is_file_match("test_io.py", "src\\net\\io\\test_io.py"));
+        assert!(!matcher.is_file_match("test_io.py", "lib\\test_io.py"));
+        assert!(!matcher.is_file_match("test_io.md", "src\\test_io.md"));
+
+        // `*` stays within one folder, a leading separator or drive anchors at the root
+        let matcher = compile_pattern("/src/*.rs", SearchType::Glob).unwrap();
+        assert!(matcher.is_file_match("main.rs", "src\\main.rs"));
+        assert!(!matcher.is_file_match("lib.rs", "src\\bin\\lib.rs"));
+        assert!(!matcher.is_file_match("main.rs", "app\\src\\main.rs"));
+        assert!(compile_pattern("C:\\Windows\\**", SearchType::Glob).unwrap()
+            .is_file_match("notepad.exe", "Windows\\System32\\notepad.exe"));
+        assert!(matcher.match_spans("main.rs").is_empty());
+    }
+
+    #[test]
+    fn test_match_path() {
+        let cache = PatternCache::new();
+        let compile = |args: Value, search_type| {
+            cache.get_or_compile_set(&PatternSet::from_args(&args).unwrap(), search_type)
+        };
+        let matcher = compile(serde_json::json!({"pattern": "*\\Projects\\*\\Cargo.toml", "match_path": true}), SearchType::Glob).unwrap();
+        assert!(matcher.is_file_match("Cargo.toml", "Users\\me\\Projects\\app\\crates\\core\\Cargo.toml"));
+        assert!(matcher.is_file_match("Cargo.toml", "C:\\Users\\me\\projects\\app\\Cargo.toml"));
+        assert!(!matcher.is_file_match("Cargo.toml", "Users\\me\\app\\Cargo.toml"));
+        assert!(matcher.match_spans("Cargo.toml").is_empty());
+        // The name alone never satisfies a path pattern
+        assert!(!matcher.is_match("Cargo.toml"));
+
+        // A drive and forward slashes in the pattern are fine, exclusions see the path too
+        let matcher = compile(serde_json::json!({"pattern": "C:/Users/*.log", "not_pattern": "*\\Temp\\*", "match_path": true}), SearchType::Glob).unwrap();
+        assert!(matcher.is_file_match("a.log", "Users\\me\\logs\\a.log"));
+        assert!(!matcher.is_file_match("b.log", "Users\\me\\Temp\\b.log"));
+        assert!(!matcher.is_file_match("c.log", "Windows\\c.log"));
+
+        let matcher = compile(serde_json::json!({"pattern": r"\\src\\.*_test\.rs$", "match_path": true}), SearchType::Regex).unwrap();
+        assert!(matcher.is_file_match("io_test.rs", "repo\\src\\net\\io_test.rs"));
+        assert!(!matcher.is_file_match("io_test.rs", "repo\\tests\\io_test.rs"));
+        assert!(!matcher.is_scored() && matcher.exact_names().is_none());
+
+        assert!(compile(serde_json::json!({"pattern": "cargo", "match_path": true}), SearchType::Fuzzy).is_err());
+        // Compiled apart from the same pattern for names
+        let name_glob = cache.get_or_compile("*\\Projects\\*\\Cargo.toml", SearchType::Glob, false).unwrap();
+        assert!(matches!(name_glob, PatternMatcher::PathGlob(_)));
+    }
+
+    #[test]
+    fn test_regex_matching() {
+        let matcher = compile_pattern(r"^test_\d+\.log$", SearchType::Regex).unwrap();
+        assert!(matcher.is_match("test_42.log"));
+        assert!(!matcher.is_match("test_x.log"));
+    }
+
+    #[test]
+    fn test_invalid_regex_is_reported() {
+        let err = compile_pattern("([a-z", SearchType::Regex).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid regex"));
+        assert!(!err.to_string().contains('\n'));
+    }
+
+    #[test]
+    fn test_regex_safety_limits() {
+        // Too long to be a name, whatever it would compile to
+        let long = "a".repeat(MAX_PATTERN_LENGTH + 1);
+        for search_type in [SearchType::Glob, SearchType::Regex] {
+            let err = compile_pattern(&long, search_type).unwrap_err();
+            assert!(err.to_string().contains("too long"), "{}", err);
+        }
+        assert!(compile_pattern(&long, SearchType::Exact).is_ok());
+
+        // Counted repetitions blow up the compiled program
+        let err = compile_pattern(r"(\w{100}){100}", SearchType::Regex).unwrap_err();
+        assert!(err.to_string().contains("size limit"), "{}", err);
+
+        let nested = format!("{}a{}", "(".repeat(40), ")".repeat(40));
+        assert!(compile_pattern(&nested, SearchType::Regex).is_err());
+
+        // Classic backtracking traps compile and match in linear time
+        let matcher = compile_pattern(r"^(a+)+$", SearchType::Regex).unwrap();
+        assert!(!matcher.is_match(&format!("{}!", "a".repeat(200))));
+    }
+
+    #[test]
+    fn test_fuzzy_scoring() {
+        let fuzzy = FuzzyPattern::new("srceng");
+        assert!(fuzzy.score("search_engine.rs").is_some());
+        assert!(fuzzy.score("engines.rs").is_none());
+
+        let exact = FuzzyPattern::new("main.rs").score("main.rs").unwrap();
+        assert!((exact - 1.0).abs() < f64::EPSILON);
+
+        // Tight, boundary-aligned matches outrank scattered ones
+        let fuzzy = FuzzyPattern::new("mcp");
+        let tight = fuzzy.score("mcp_server.rs").unwrap();
+        let camel = fuzzy.score("McpClientPool.cs").unwrap();
+        let scattered = fuzzy.score("image_compression.py").unwrap();
+        assert!(tight > camel);
+        assert!(camel > scattered);
+    }
+
+    #[test]
+    fn test_any_of_several_patterns() {
+        let cache = PatternCache::new();
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": ["*.rs", "*.toml"]})).unwrap();
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Glob).unwrap();
+        assert!(matcher.is_match("main.rs"));
+        assert!(matcher.is_match("Cargo.toml"));
+        assert!(!matcher.is_match("README.md"));
+        assert_eq!(cache.len(), 2);
+
+        let everything = cache.get_or_compile_any(&["*.rs".to_string(), "*".to_string()], SearchType::Glob, false).unwrap();
+        assert!(matches!(everything, PatternMatcher::Any));
+
+        let names = ["Cargo.toml".to_string(), "cargo.lock".to_string()];
+        let exact = cache.get_or_compile_any(&names, SearchType::Exact, false).unwrap();
+        assert_eq!(exact.exact_names(), Some(vec!["cargo.lock".to_string(), "cargo.toml".to_string()]));
+
+        assert_eq!(PatternSet::from_args(&serde_json::json!({})).unwrap().include, vec!["*".to_string()]);
+        assert!(PatternSet::from_args(&serde_json::json!({"pattern": []})).is_err());
+        assert!(PatternSet::from_args(&serde_json::json!({"pattern": ["*.rs", 3]})).is_err());
+    }
+
+    #[test]
+    fn test_negative_patterns() {
+        let cache = PatternCache::new();
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": ["*.log", "!*install*.log"]})).unwrap();
+        assert_eq!(patterns.exclude, vec!["*install*.log".to_string()]);
+        assert_eq!(patterns.describe(), "*.log (not *install*.log)");
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Glob).unwrap();
+        assert!(matcher.is_match("app.log"));
+        assert!(!matcher.is_match("msi_Install_2024.log"));
+        assert!(!matcher.is_match("app.txt"));
+
+        // Only exclusions: everything else matches
+        let patterns = PatternSet::from_args(&serde_json::json!({"not_pattern": ["*.tmp", "~*"]})).unwrap();
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Glob).unwrap();
+        assert!(matcher.is_match("notes.txt"));
+        assert!(!matcher.is_match("~notes.txt"));
+
+        // Exact names drop the excluded ones up front
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": ["a.txt", "b.txt", "!b.txt"]})).unwrap();
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Exact).unwrap();
+        assert_eq!(matcher.exact_names(), Some(vec!["a.txt".to_string()]));
+
+        // Fuzzy searches exclude with globs
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": "srceng", "not_pattern": "*.bak"})).unwrap();
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Fuzzy).unwrap();
+        assert!(matcher.is_scored());
+        assert!(matcher.score("search_engine.rs").is_some());
+        assert!(matcher.score("search_engine.rs.bak").is_none());
+
+        assert!(PatternSet::from_args(&serde_json::json!({"pattern": "!"})).is_err());
+    }
+
+    #[test]
+    fn test_case_sensitive_patterns() {
+        let cache = PatternCache::new();
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": "README*", "case_sensitive": true})).unwrap();
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Glob).unwrap();
+        assert!(matcher.is_match("README.md"));
+        assert!(!matcher.is_match("readme.md"));
+
+        let matcher = cache.get_or_compile("Makefile", SearchType::Exact, true).unwrap();
+        assert!(matcher.is_match("Makefile"));
+        assert!(!matcher.is_match("makefile"));
+        // The name index is keyed by lowercased names
+        assert_eq!(matcher.exact_names(), Some(vec!["makefile".to_string()]));
+
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": ["Makefile", "!makefile"], "case_sensitive": true})).unwrap();
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Exact).unwrap();
+        assert_eq!(matcher.exact_names(), Some(vec!["makefile".to_string()]));
+        assert!(matcher.is_match("Makefile"));
+
+        // Fuzzy patterns forgive case regardless
+        let matcher = cache.get_or_compile("SRCENG", SearchType::Fuzzy, true).unwrap();
+        assert!(matcher.is_match("search_engine.rs"));
+
+        assert!(PatternSet::from_args(&serde_json::json!({"case_sensitive": "yes"})).is_err());
+    }
+
+    #[test]
+    fn test_diacritic_insensitive_patterns() {
+        assert_eq!(fold_name("Résumé.pdf"), "Resume.pdf");
+        assert_eq!(fold_name("Straße_ﬁnal.docx"), "Strasse_final.docx");
+        assert!(matches!(fold_name("plain.txt"), Cow::Borrowed(_)));
+
+        let cache = PatternCache::new();
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": "resume*", "ignore_diacritics": true})).unwrap();
+        let matcher = cache.get_or_compile_set(&patterns, SearchType::Glob).unwrap();
+        assert!(matcher.is_match("résumé.pdf"));
+        assert!(matcher.is_match("RÉSUMÉ 2024.docx"));
+        assert!(!matcher.is_match("résistance.pdf"));
+        // Folded names in the index are matched with the inner matcher directly
+        assert!(matcher.folded_inner().unwrap().is_match("resume.pdf"));
+
+        // Accented patterns find unaccented names too
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": "café*", "ignore_diacritics": true})).unwrap();
+        assert!(cache.get_or_compile_set(&patterns, SearchType::Glob).unwrap().is_match("cafe_menu.txt"));
+
+        assert!(PatternSet::from_args(&serde_json::json!({"ignore_diacritics": true, "case_sensitive": true})).is_err());
+    }
+
+    #[test]
+    fn test_match_spans() {
+        let spans = |pattern: &str, search_type: SearchType, name: &str| {
+            compile_pattern(pattern, search_type).unwrap().match_spans(name)
+        };
+        assert_eq!(glob_to_regex("*.rs"), "^.*(\\.rs)$");
+        assert_eq!(spans("*.rs", SearchType::Glob, "main.RS"), vec![4..7]);
+        assert_eq!(spans("test_*_?.log", SearchType::Glob, "test_api_1.log"), vec![0..5, 8..9, 10..14]);
+        assert_eq!(spans("*", SearchType::Glob, "main.rs"), Vec::<Range<usize>>::new());
+        assert_eq!(spans("\\d+", SearchType::Regex, "v12_34.txt"), vec![1..3, 4..6]);
+        assert_eq!(spans("cargo.toml", SearchType::Exact, "Cargo.toml"), vec![0..10]);
+        assert_eq!(spans("srceng", SearchType::Fuzzy, "search_engine.rs"), vec![0..1, 3..5, 7..10]);
+        assert!(spans("*.md", SearchType::Glob, "main.rs").is_empty());
+
+        // Folded names map back onto the accented characters
+        let patterns = PatternSet::from_args(&serde_json::json!({"pattern": "*resume*", "ignore_diacritics": true})).unwrap();
+        let matcher = PatternCache::new().get_or_compile_set(&patterns, SearchType::Glob).unwrap();
+        let name = "my_résumé.pdf";
+        let found = matcher.match_spans(name);
+        assert_eq!(found.iter().map(|span| &name[span.clone()]).collect::<Vec<_>>(), vec!["résumé"]);
+    }
+
+    #[test]
+    fn test_pattern_cache_reuses_compiled_patterns() {
+        let cache = PatternCache::new();
+        cache.get_or_compile("foo.*", SearchType::Regex, false).unwrap();
+        cache.get_or_compile("foo.*", SearchType::Regex, false).unwrap();
+        cache.get_or_compile("foo.*", SearchType::Glob, false).unwrap();
+        cache.get_or_compile("foo.*", SearchType::Glob, true).unwrap();
+        assert_eq!(cache.len(), 3);
+    }
+}
@@ -1,24 +1,37 @@
 //! MCP (Model-Controller-Presenter) server implementation for FastSearch
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use anyhow::Result;
 use serde_json::{json, Value};
 
 // Use the search_engine module as declared in lib.rs
-use crate::search_engine::SearchEngine;
+use super::search_engine::PartialResultSink;
+use super::cancellation::CancelToken;
+use super::engine::Engine;
+use super::live_queries::LiveQuery;
 
 /// Main MCP server that handles requests and delegates to appropriate handlers
+///
+/// The MCP server is a JSON-RPC front end to the embedded [`Engine`].
 pub struct McpServer {
-    search_engine: SearchEngine,
+    engine: Engine,
 }
 
 impl McpServer {
     /// Create a new MCP server instance
     pub fn new() -> Result<Self> {
         Ok(Self {
-            search_engine: SearchEngine::new()?,
+            engine: Engine::open()?,
         })
     }
 
+    /// The engine behind the server, for typed searches without JSON
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
     /// Handle an incoming MCP request
     pub fn handle_request(&self, request: Value) -> Result<Value> {
         let method = request["method"]
@@ -28,6 +41,7 @@ impl McpServer {
         match method {
             "initialize" => self.handle_initialize(request),
             "tools/list" => self.handle_tools_list(),
+            "tools/call" | "resources/list" | "resources/read" => self.engine.search_engine().handle_request(request),
             _ => Err(anyhow::anyhow!("Unknown method: {}", method)),
         }
     }
@@ -41,6 +55,7 @@ impl McpServer {
                     "completionProvider": {},
                     "definitionProvider": true,
                     "documentSymbolProvider": true,
+                    "resources": {},
                     "workspace": {
                         "workspaceFolders": {
                             "supported": true,
@@ -54,16 +69,96 @@ impl McpServer {
 
     /// Handle tools/list request
     fn handle_tools_list(&self) -> Result<Value> {
-        self.search_engine.handle_tools_list()
+        self.engine.search_engine().handle_tools_list()
     }
     
     /// Perform a fast search with the given arguments
     pub fn fast_search(&self, args: &Value) -> Result<Value> {
-        self.search_engine.fast_search(args)
+        self.engine.search_engine().fast_search(args)
+    }
+    
+    /// Perform a fast search, receiving batches of matches while it runs
+    pub fn fast_search_streaming(&self, args: &Value, partial: Option<PartialResultSink<'_>>) -> Result<Value> {
+        self.engine.search_engine().fast_search_streaming(args, partial)
+    }
+    
+    /// Perform a fast search that `cancel_request` can stop, registered under the `id` of `request`
+    pub fn fast_search_cancellable(&self, request: &Value, args: &Value, partial: Option<PartialResultSink<'_>>) -> Result<Value> {
+        self.engine.search_engine().fast_search_cancellable(request, args, partial)
+    }
+    
+    /// Search syntax reference, generated from the tool definitions
+    pub fn syntax_reference(&self) -> Result<String> {
+        self.engine.search_engine().syntax_reference()
     }
     
     /// Run a benchmark search with the given arguments
     pub fn benchmark_search(&self, args: &Value) -> Result<Value> {
-        self.search_engine.benchmark_search(args)
+        self.engine.search_engine().benchmark_search(args)
+    }
+    
+    /// Memory held by each subsystem, for tuning the memory limits
+    pub fn memory_profile(&self, args: &Value) -> Result<Value> {
+        self.engine.search_engine().memory_profile(args)
+    }
+    
+    /// Register a live query and return its current results
+    pub fn register_live_query(&self, args: &Value) -> Result<Value> {
+        self.engine.search_engine().register_live_query(args)
+    }
+    
+    /// A registered live query, to subscribe to its updates
+    pub fn live_query(&self, id: &str) -> Option<Arc<LiveQuery>> {
+        self.engine.search_engine().live_query(id)
+    }
+    
+    /// Unregister a live query
+    pub fn remove_live_query(&self, id: &str) -> bool {
+        self.engine.search_engine().remove_live_query(id)
+    }
+    
+    /// Registered live queries
+    pub fn list_live_queries(&self) -> Value {
+        self.engine.search_engine().list_live_queries()
+    }
+    
+    /// Build the caches of the drives configured for preloading
+    pub fn preload_drives(&self) -> usize {
+        self.engine.search_engine().preload_drives()
+    }
+    
+    /// Run scheduled report jobs that are due
+    pub fn run_due_reports(&self) -> usize {
+        self.engine.search_engine().run_due_reports()
+    }
+    
+    /// Take pending server-initiated notifications (e.g. watched search matches)
+    pub fn drain_notifications(&self) -> Vec<Value> {
+        self.engine.search_engine().drain_notifications()
+    }
+    
+    /// Block until a notification is pending or the timeout passes
+    pub fn wait_for_notifications(&self, timeout: Duration) -> bool {
+        self.engine.search_engine().wait_for_notifications(timeout)
+    }
+    
+    /// Cancel a running request (`notifications/cancelled` params)
+    pub fn cancel_request(&self, params: &Value) -> bool {
+        self.engine.search_engine().cancel_request(params)
+    }
+    
+    /// A token for work the caller can cancel itself (e.g. when an HTTP client disconnects)
+    pub fn cancel_token(&self) -> CancelToken {
+        self.engine.cancel_token()
+    }
+    
+    /// Perform a fast search that stops once `cancel` is cancelled
+    pub fn fast_search_with_cancel(&self, args: &Value, cancel: &CancelToken) -> Result<Value> {
+        self.engine.search_engine().fast_search_with_cancel(args, None, cancel)
+    }
+    
+    /// Stop all running work, when the service stops
+    pub fn shutdown(&self) {
+        self.engine.shutdown()
     }
 }
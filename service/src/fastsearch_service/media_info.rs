@@ -0,0 +1,277 @@
+//! Duration, bitrate and codec of audio and video files for `fast_search`
+//!
+//! With `include_media_info`, audio and video results carry how long they
+//! play, their bitrate and codec, and `min_duration`/`max_duration` filter on
+//! the length ("videos longer than 30 minutes": `doc_type: "video"`,
+//! `min_duration: "30m"`). Like image information, this is read from the
+//! file's headers in the verify stage and cached per file record.
+//!
+//! Audio formats are read with lofty; MP4/MOV and Matroska/WebM video from
+//! their container headers. Other containers (AVI, WMV, FLV, MPEG) aren't
+//! read, so they never pass a duration filter.
+
+#[cfg(feature = "media-info")]
+use std::fs::File;
+#[cfg(feature = "media-info")]
+use std::io::BufReader;
+use std::path::Path;
+#[cfg(feature = "media-info")]
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::file_types::{document_type_of, DocumentType};
+use super::metadata_cache::MetadataCache;
+use super::mft_cache::{disk_path, DriveRoot, FileEntry};
+
+/// How long an audio or video file plays, and how it is encoded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MediaInfo {
+    /// Playing time in whole seconds
+    pub duration_secs: u64,
+    /// Overall bitrate in kbit/s
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitrate_kbps: Option<u32>,
+    /// Codec of the first video track, or of the audio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+}
+
+impl MediaInfo {
+    /// `1:32:05, 4500 kbit/s, h264`
+    pub fn describe(&self) -> String {
+        let mut text = format_duration(self.duration_secs);
+        if let Some(bitrate) = self.bitrate_kbps {
+            text.push_str(&format!(", {} kbit/s", bitrate));
+        }
+        if let Some(codec) = &self.codec {
+            text.push_str(&format!(", {}", codec));
+        }
+        text
+    }
+}
+
+/// `h:mm:ss`, or `m:ss` under an hour
+pub fn format_duration(secs: u64) -> String {
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// Whether a cache entry is an audio or video file by its extension
+pub fn is_media(file: &FileEntry) -> bool {
+    !file.is_directory && file.extension.as_deref()
+        .and_then(document_type_of)
+        .map_or(false, |doc_type| matches!(doc_type, DocumentType::Audio | DocumentType::Video))
+}
+
+/// Read the duration, bitrate and codec of an audio or video file
+#[cfg(feature = "media-info")]
+pub fn read_media_info(path: &Path) -> Option<MediaInfo> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "mp4" | "m4v" | "mov" | "3gp" => read_mp4(path),
+        "mkv" | "webm" => read_matroska(path),
+        _ => read_audio(path),
+    }
+}
+
+/// Built without the `media-info` feature: no audio or video is read
+#[cfg(not(feature = "media-info"))]
+pub fn read_media_info(_path: &Path) -> Option<MediaInfo> {
+    None
+}
+
+#[cfg(feature = "media-info")]
+fn read_audio(path: &Path) -> Option<MediaInfo> {
+    use lofty::{AudioFile, TaggedFileExt};
+
+    let tagged = lofty::read_from_path(path).ok()?;
+    let properties = tagged.properties();
+    Some(MediaInfo {
+        duration_secs: properties.duration().as_secs(),
+        bitrate_kbps: properties.overall_bitrate().or(properties.audio_bitrate()),
+        codec: Some(format!("{:?}", tagged.file_type()).to_lowercase()),
+    })
+}
+
+#[cfg(feature = "media-info")]
+fn read_mp4(path: &Path) -> Option<MediaInfo> {
+    let file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mp4 = mp4::Mp4Reader::read_header(BufReader::new(file), size).ok()?;
+    let duration = mp4.duration();
+    let mut tracks: Vec<&mp4::Mp4Track> = mp4.tracks().values().collect();
+    // Video ahead of audio, then in track order
+    tracks.sort_by_key(|track| (!matches!(track.track_type(), Ok(mp4::TrackType::Video)), track.track_id()));
+    Some(MediaInfo {
+        duration_secs: duration.as_secs(),
+        bitrate_kbps: overall_bitrate(size, duration),
+        codec: tracks.first()
+            .and_then(|track| track.media_type().ok())
+            .map(|media_type| media_type.to_string()),
+    })
+}
+
+#[cfg(feature = "media-info")]
+fn read_matroska(path: &Path) -> Option<MediaInfo> {
+    let file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mkv = matroska::Matroska::open(file).ok()?;
+    let duration = mkv.info.duration?;
+    let track = mkv.tracks.iter()
+        .find(|track| track.tracktype == matroska::Tracktype::Video)
+        .or_else(|| mkv.tracks.first());
+    Some(MediaInfo {
+        duration_secs: duration.as_secs(),
+        bitrate_kbps: overall_bitrate(size, duration),
+        codec: track.map(|track| codec_name(&track.codec_id)),
+    })
+}
+
+/// Size over playing time, for containers that don't store a bitrate
+#[cfg(feature = "media-info")]
+fn overall_bitrate(size: u64, duration: Duration) -> Option<u32> {
+    let secs = duration.as_secs_f64();
+    (secs >= 1.0).then(|| (size as f64 * 8.0 / 1000.0 / secs).round() as u32)
+}
+
+/// Short name of a Matroska codec ID: `V_MPEG4/ISO/AVC` is `h264`
+#[cfg(feature = "media-info")]
+fn codec_name(codec_id: &str) -> String {
+    match codec_id {
+        "V_MPEG4/ISO/AVC" => "h264".to_string(),
+        "V_MPEGH/ISO/HEVC" => "hevc".to_string(),
+        "V_VP8" => "vp8".to_string(),
+        "V_VP9" => "vp9".to_string(),
+        "V_AV1" => "av1".to_string(),
+        other => other.split_once('_')
+            .map_or(other, |(_, name)| name)
+            .to_lowercase(),
+    }
+}
+
+/// Media information read so far, by drive and file record
+#[derive(Debug, Default)]
+pub struct MediaInfoCache {
+    cache: MetadataCache<MediaInfo>,
+}
+
+impl MediaInfoCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Media information of a cache entry, read from below `root` unless known for this version of the file
+    pub fn get(&self, drive: char, root: &Path, file: &FileEntry) -> Option<MediaInfo> {
+        if !is_media(file) {
+            return None;
+        }
+        self.cache.get_or_read(drive, file, || read_media_info(&disk_path(root, &file.path)))
+    }
+
+    /// Number of files remembered
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// The `min_duration` and `max_duration` arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MediaFilter {
+    pub min_duration: Option<u64>,
+    pub max_duration: Option<u64>,
+}
+
+impl MediaFilter {
+    /// Parse the duration filters; `None` if neither is given
+    pub fn from_args(args: &Value) -> Result<Option<Self>> {
+        let filter = Self {
+            min_duration: parse_duration_arg(args, "min_duration")?,
+            max_duration: parse_duration_arg(args, "max_duration")?,
+        };
+        Ok((filter != Self::default()).then_some(filter))
+    }
+
+    /// Whether a file passes; files whose duration couldn't be read never do
+    pub fn matches(&self, info: Option<&MediaInfo>) -> bool {
+        info.map_or(false, |info| {
+            self.min_duration.map_or(true, |min| info.duration_secs >= min)
+                && self.max_duration.map_or(true, |max| info.duration_secs <= max)
+        })
+    }
+}
+
+/// Parse a duration argument given as seconds or a string like "30m" or "1h 30m"
+fn parse_duration_arg(args: &Value, key: &str) -> Result<Option<u64>> {
+    match &args[key] {
+        Value::Null => Ok(None),
+        Value::Number(n) => n
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| anyhow!("'{}' must be a non-negative number of seconds", key)),
+        Value::String(s) => humantime::parse_duration(s.trim())
+            .map(|duration| Some(duration.as_secs()))
+            .map_err(|e| anyhow!("'{}': {}", key, e)),
+        _ => Err(anyhow!("'{}' must be a number of seconds or a duration like \"30m\"", key)),
+    }
+}
+
+/// Duration check for the matches on one drive
+pub struct MediaCheck<'a> {
+    filter: MediaFilter,
+    cache: &'a MediaInfoCache,
+    disk: DriveRoot,
+}
+
+impl<'a> MediaCheck<'a> {
+    pub fn new(filter: MediaFilter, cache: &'a MediaInfoCache, disk: DriveRoot) -> Self {
+        Self { filter, cache, disk }
+    }
+
+    pub fn accepts(&self, file: &FileEntry) -> bool {
+        is_media(file) && self.filter.matches(self.cache.get(self.disk.drive, &self.disk.root, file).as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_media_filter() {
+        assert_eq!(MediaFilter::from_args(&json!({"doc_type": "video"})).unwrap(), None);
+        assert!(MediaFilter::from_args(&json!({"min_duration": "soon"})).is_err());
+
+        let filter = MediaFilter::from_args(&json!({"min_duration": "30m", "max_duration": 7200})).unwrap().unwrap();
+        assert_eq!(filter, MediaFilter { min_duration: Some(1800), max_duration: Some(7200) });
+        let film = MediaInfo { duration_secs: 5525, bitrate_kbps: Some(4500), codec: Some("h264".to_string()) };
+        assert!(filter.matches(Some(&film)));
+        assert!(!filter.matches(Some(&MediaInfo { duration_secs: 1200, ..film.clone() })));
+        assert!(!filter.matches(Some(&MediaInfo { duration_secs: 9000, ..film.clone() })));
+        assert!(!filter.matches(None));
+        assert_eq!(film.describe(), "1:32:05, 4500 kbit/s, h264");
+    }
+
+    #[test]
+    #[cfg(feature = "media-info")]
+    fn test_codec_names_and_bitrate() {
+        assert_eq!(codec_name("V_MPEG4/ISO/AVC"), "h264");
+        assert_eq!(codec_name("A_OPUS"), "opus");
+        assert_eq!(format_duration(59), "0:59");
+        assert_eq!(overall_bitrate(1_000_000, Duration::from_secs(8)), Some(1000));
+        assert_eq!(overall_bitrate(1_000_000, Duration::ZERO), None);
+        // Not a readable container
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.mkv"), b"not a video").unwrap();
+        assert_eq!(read_media_info(&dir.path().join("broken.mkv")), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Memory used by each part of the service, for tuning its limits
+//!
+//! `max_memory_usage` and the process memory limit are hard to set without
+//! knowing where the memory goes. The profile walks the service's own data
+//! structures and adds up what they hold: each drive's cache entries and
+//! indexes, the project indexes, kept result sets and the USN journal
+//! buffers. Names are stored with the entries that own them (there is no
+//! string interner or content index), so they are counted there. Sizes come
+//! from string and table capacities and leave out allocator overhead, so the
+//! total is somewhat below what the process commits. Walking a large cache
+//! takes a moment under its read lock; this is a diagnostic, not something to
+//! poll.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use serde::Serialize;
+
+use super::mft_cache::FileEntry;
+
+/// Heap memory a value owns, beyond its own size
+pub trait HeapSize {
+    fn heap_bytes(&self) -> u64;
+}
+
+macro_rules! no_heap {
+    ($($ty:ty),*) => {
+        $(impl HeapSize for $ty {
+            fn heap_bytes(&self) -> u64 {
+                0
+            }
+        })*
+    };
+}
+
+no_heap!(bool, char, u16, u32, u64, usize, &'static str);
+
+impl HeapSize for String {
+    fn heap_bytes(&self) -> u64 {
+        self.capacity() as u64
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_bytes(&self) -> u64 {
+        self.as_ref().map_or(0, HeapSize::heap_bytes)
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_bytes(&self) -> u64 {
+        self.0.heap_bytes() + self.1.heap_bytes()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_bytes(&self) -> u64 {
+        (self.capacity() * size_of::<T>()) as u64 + self.iter().map(HeapSize::heap_bytes).sum::<u64>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize, S> HeapSize for HashMap<K, V, S> {
+    fn heap_bytes(&self) -> u64 {
+        table_bytes::<K, V>(self.capacity())
+            + self.iter().map(|(key, value)| key.heap_bytes() + value.heap_bytes()).sum::<u64>()
+    }
+}
+
+impl HeapSize for FileEntry {
+    fn heap_bytes(&self) -> u64 {
+        self.name.heap_bytes() + self.path.heap_bytes() + self.extension.heap_bytes()
+    }
+}
+
+/// Bytes of a hash table's buckets and control bytes, for `capacity` entries
+fn table_bytes<K, V>(capacity: usize) -> u64 {
+    if capacity == 0 {
+        return 0;
+    }
+    // Tables have a power of two of buckets, at most 7/8 of them used
+    let buckets = match capacity {
+        0..=3 => 4,
+        4..=7 => 8,
+        _ => (capacity * 8 / 7).next_power_of_two(),
+    };
+    // One control byte per bucket, plus a group's worth for wrapping around
+    (buckets * (size_of::<(K, V)>() + 1) + 16) as u64
+}
+
+/// Memory of one part of the service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubsystemMemory {
+    /// What holds it, e.g. "cache.entries" or "result_sets"
+    pub subsystem: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drive: Option<char>,
+    /// Records, keys or changes it holds
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+/// Memory of the service, by subsystem
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryProfile {
+    pub subsystems: Vec<SubsystemMemory>,
+}
+
+impl MemoryProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count memory already added up by the subsystem itself
+    pub fn add(&mut self, subsystem: &str, drive: Option<char>, entries: u64, bytes: u64) {
+        self.subsystems.push(SubsystemMemory {
+            subsystem: subsystem.to_string(),
+            drive,
+            entries,
+            bytes,
+        });
+    }
+
+    /// Count a table, its entries and what they own
+    pub fn add_map<K: HeapSize, V: HeapSize, S>(&mut self, subsystem: &str, drive: Option<char>, map: &HashMap<K, V, S>) {
+        self.add(subsystem, drive, map.len() as u64, map.heap_bytes());
+    }
+
+    /// Bytes of every subsystem
+    pub fn total(&self) -> u64 {
+        self.subsystems.iter().map(|part| part.bytes).sum()
+    }
+
+    /// Bytes of each subsystem over all drives, largest first
+    pub fn by_subsystem(&self) -> Vec<(&str, u64)> {
+        let mut totals: Vec<(&str, u64)> = Vec::new();
+        for part in &self.subsystems {
+            match totals.iter_mut().find(|(subsystem, _)| *subsystem == part.subsystem) {
+                Some((_, bytes)) => *bytes += part.bytes,
+                None => totals.push((&part.subsystem, part.bytes)),
+            }
+        }
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        totals
+    }
+
+    /// Bytes held for each drive, by drive letter
+    pub fn by_drive(&self) -> Vec<(char, u64)> {
+        let mut totals: Vec<(char, u64)> = Vec::new();
+        for part in &self.subsystems {
+            let Some(drive) = part.drive else { continue };
+            match totals.iter_mut().find(|(letter, _)| *letter == drive) {
+                Some((_, bytes)) => *bytes += part.bytes,
+                None => totals.push((drive, part.bytes)),
+            }
+        }
+        totals.sort_unstable_by_key(|(drive, _)| *drive);
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heap_bytes_follow_capacities() {
+        let mut name = String::with_capacity(32);
+        name.push_str("report.docx");
+        assert_eq!(name.heap_bytes(), 32);
+        assert_eq!(None::<String>.heap_bytes(), 0);
+
+        let ids: Vec<u64> = Vec::with_capacity(4);
+        assert_eq!(ids.heap_bytes(), 32);
+
+        let mut index: HashMap<String, Vec<u64>> = HashMap::new();
+        assert_eq!(index.heap_bytes(), 0);
+        index.insert("a".repeat(10), vec![1, 2]);
+        let table = index.heap_bytes() - 10 - 16;
+        // Buckets for the entry, plus a control byte each
+        assert!(table >= 4 * (size_of::<(String, Vec<u64>)>() as u64 + 1));
+    }
+
+    #[test]
+    fn test_profile_totals() {
+        let mut profile = MemoryProfile::new();
+        profile.add("cache.entries", Some('C'), 10, 1000);
+        profile.add("cache.name_index", Some('C'), 8, 300);
+        profile.add("cache.entries", Some('D'), 5, 500);
+        profile.add("result_sets", None, 3, 24);
+
+        assert_eq!(profile.total(), 1824);
+        assert_eq!(profile.by_subsystem(), vec![("cache.entries", 1500), ("cache.name_index", 300), ("result_sets", 24)]);
+        assert_eq!(profile.by_drive(), vec![('C', 1300), ('D', 500)]);
+    }
+}
@@ -0,0 +1,67 @@
+//! Metadata read from file contents, remembered per file record
+//!
+//! Image dimensions and media durations aren't in the MFT, and reading them
+//! means opening the file. What was read is kept by drive and record number
+//! along with the size and modification time it was read at, and read again
+//! once either changes.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use parking_lot::RwLock;
+
+use super::mft_cache::FileEntry;
+
+/// Files remembered before a cache starts over
+const MAX_CACHED_FILES: usize = 200_000;
+
+#[derive(Debug, Clone)]
+struct Cached<T> {
+    size: u64,
+    modified: SystemTime,
+    value: Option<T>,
+}
+
+/// Metadata of type `T` by drive and file record; `None` is remembered too
+#[derive(Debug)]
+pub struct MetadataCache<T> {
+    entries: RwLock<HashMap<(char, u64), Cached<T>>>,
+}
+
+impl<T> Default for MetadataCache<T> {
+    fn default() -> Self {
+        Self { entries: RwLock::new(HashMap::new()) }
+    }
+}
+
+impl<T: Clone> MetadataCache<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What is known for this version of the file, or else what `read` finds
+    pub fn get_or_read(&self, drive: char, file: &FileEntry, read: impl FnOnce() -> Option<T>) -> Option<T> {
+        let key = (drive, file.id);
+        if let Some(cached) = self.entries.read().get(&key) {
+            if cached.size == file.size && cached.modified == file.modified {
+                return cached.value.clone();
+            }
+        }
+        let value = read();
+        let mut entries = self.entries.write();
+        if entries.len() >= MAX_CACHED_FILES {
+            entries.clear();
+        }
+        entries.insert(key, Cached { size: file.size, modified: file.modified, value: value.clone() });
+        value
+    }
+
+    /// Number of files remembered
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
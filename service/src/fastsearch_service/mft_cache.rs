@@ -16,11 +16,25 @@ use log::{debug, error, info, warn};
 use ntfs::Ntfs;
 use parking_lot::RwLock;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use systemstat::{Platform, System};
 use winapi::um::fileapi::CreateFileW;
 use winapi::um::winbase::{FILE_FLAG_NO_BUFFERING, FILE_FLAG_RANDOM_ACCESS};
 use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, INVALID_HANDLE_VALUE};
 
+use crate::fastsearch_service::cache_persistence::PersistenceBackend;
+use crate::fastsearch_service::cancellation::{CancelToken, Cancelled};
+use crate::fastsearch_service::cache_signing::CacheKey;
+use crate::fastsearch_service::cache_wal::{CacheWal, WalRecord};
+use crate::fastsearch_service::etw;
+use crate::fastsearch_service::maintenance::MaintenanceLock;
+use crate::fastsearch_service::matcher::folded_key;
+use crate::fastsearch_service::name_affixes::NameAffixes;
+use crate::fastsearch_service::operations::{DriveOperation, DriveOperations};
+use crate::fastsearch_service::trigrams::{trigram_index_enabled, TrigramIndex};
+use crate::fastsearch_service::usn_journal::UsnChange;
+use crate::fastsearch_service::volumes::VolumeId;
+
 /// Default maximum number of files to process before checking memory usage
 const DEFAULT_MAX_FILES_BEFORE_MEMCHECK: usize = 100_000;
 /// Target memory usage percentage (0.8 = 80%)
@@ -48,6 +62,14 @@ pub struct MftCacheConfig {
     pub save_interval_secs: u64,
     /// Maximum number of cache versions to keep
     pub max_cache_versions: usize,
+    /// How the cache is laid out on disk
+    pub persistence_backend: PersistenceBackend,
+    /// File holding the secret the saved cache and change log are signed with
+    pub key_path: PathBuf,
+    
+    // Index settings
+    /// Whether to keep a trigram index of the names, for substring and similar-name searches
+    pub trigram_index: bool,
 }
 
 impl MftCacheConfig {
@@ -90,6 +112,12 @@ impl MftCacheConfig {
         Ok(self)
     }
     
+    /// Memory of the machine and the share of it `max_memory_usage` allows, in bytes
+    pub fn memory_budget(&self) -> Option<(u64, u64)> {
+        let total = System::new().memory().ok()?.total.as_u64();
+        Some((total, (total as f64 * self.max_memory_usage as f64) as u64))
+    }
+    
     /// Set the number of threads to use for parallel processing (0 = auto)
     pub fn with_threads(mut self, num_threads: usize) -> Self {
         self.num_threads = num_threads;
@@ -125,6 +153,24 @@ impl MftCacheConfig {
         self.max_cache_versions = max_versions;
         self
     }
+    
+    /// Set the backend the cache is saved with
+    pub fn with_persistence_backend(mut self, backend: PersistenceBackend) -> Self {
+        self.persistence_backend = backend;
+        self
+    }
+    
+    /// Set the file holding the cache signing secret
+    pub fn with_key_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.key_path = path.as_ref().to_path_buf();
+        self
+    }
+    
+    /// Enable or disable the trigram index (disabled saves memory; those searches scan instead)
+    pub fn with_trigram_index(mut self, enabled: bool) -> Self {
+        self.trigram_index = enabled;
+        self
+    }
 }
 
 impl Default for MftCacheConfig {
@@ -150,22 +196,38 @@ impl Default for MftCacheConfig {
             cache_dir,
             save_interval_secs: 300, // 5 minutes
             max_cache_versions: 3,
+            persistence_backend: PersistenceBackend::from_env(),
+            key_path: CacheKey::default_path(),
+            
+            // Index settings
+            trigram_index: trigram_index_enabled(),
         }
     }
 }
 
+/// Callback invoked with USN Journal changes before the cache applies them
+pub type ChangeListener = Arc<dyn Fn(&MftCache, &[UsnChange]) + Send + Sync>;
+
 /// In-memory MFT cache for fast file searches
-#[derive(Debug)]
 pub struct MftCache {
-    // Core data structures
-    files: RwLock<HashMap<u64, FileEntry>>,
-    extension_index: RwLock<HashMap<String, Vec<u64>>>,
-    name_index: RwLock<HashMap<String, Vec<u64>>>,
-    path_index: RwLock<HashMap<String, u64>>,
+    // Core data structures, shared between clones so the auto-save and
+    // monitoring copies work on the live entries
+    files: Arc<RwLock<HashMap<u64, FileEntry>>>,
+    extension_index: Arc<RwLock<HashMap<String, Vec<u64>>>>,
+    name_index: Arc<RwLock<HashMap<String, Vec<u64>>>>,
+    /// Name index key -> its folded form, for the names folding changes
+    folded_names: Arc<RwLock<HashMap<String, String>>>,
+    /// Name index keys in order and reversed, for prefix and suffix patterns
+    name_affixes: Arc<RwLock<NameAffixes>>,
+    /// Name index keys by trigram, unless `trigram_index` is off
+    trigrams: Arc<RwLock<Option<TrigramIndex>>>,
+    path_index: Arc<RwLock<HashMap<String, u64>>>,
     
     // Metadata
-    last_update: RwLock<SystemTime>,
+    last_update: Arc<RwLock<SystemTime>>,
     drive_letter: char,
+    /// Volume the saved cache and change log are keyed by, whatever letter it has today
+    volume: VolumeId,
     config: MftCacheConfig,
     
     // Statistics and tracking
@@ -175,29 +237,64 @@ pub struct MftCache {
     // Persistence
     save_thread_handle: parking_lot::Mutex<Option<std::thread::JoinHandle<()>>>,
     shutdown_flag: Arc<StdAtomicBool>,
+    /// Stops rebuilds when the cache is retired or the service stops, shared between clones
+    cancel: CancelToken,
+    /// Token of the rebuild running now, when it was started by a request that can be cancelled
+    rebuild_cancel: Arc<parking_lot::Mutex<Option<CancelToken>>>,
+    /// Log of changes since the last snapshot, shared between clones
+    wal: Arc<parking_lot::Mutex<Option<CacheWal>>>,
     
     // USN Journal monitoring
     usn_monitor: parking_lot::Mutex<Option<crate::fastsearch_service::usn_journal::UsnJournalMonitor>>,
     volume_handle: parking_lot::Mutex<Option<winapi::um::winnt::HANDLE>>,
+    
+    // Change listeners, shared between clones so the monitoring copy sees late registrations
+    change_listeners: Arc<parking_lot::Mutex<Vec<ChangeListener>>>,
+    
+    // Holds background updates while the drive is in maintenance, shared between clones
+    maintenance: Arc<MaintenanceLock>,
+    
+    // The rebuild, maintenance change or export holding the drive, shared between clones
+    operations: Arc<DriveOperations>,
+}
+
+impl std::fmt::Debug for MftCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MftCache")
+            .field("drive_letter", &self.drive_letter)
+            .field("file_count", &self.files.read().len())
+            .field("change_listeners", &self.change_listeners.lock().len())
+            .finish_non_exhaustive()
+    }
 }
 
 impl Clone for MftCache {
     fn clone(&self) -> Self {
         Self {
-            files: RwLock::new(self.files.read().clone()),
-            extension_index: RwLock::new(self.extension_index.read().clone()),
-            name_index: RwLock::new(self.name_index.read().clone()),
-            path_index: RwLock::new(self.path_index.read().clone()),
-            last_update: RwLock::new(*self.last_update.read()),
+            files: Arc::clone(&self.files),
+            extension_index: Arc::clone(&self.extension_index),
+            name_index: Arc::clone(&self.name_index),
+            folded_names: Arc::clone(&self.folded_names),
+            name_affixes: Arc::clone(&self.name_affixes),
+            trigrams: Arc::clone(&self.trigrams),
+            path_index: Arc::clone(&self.path_index),
+            last_update: Arc::clone(&self.last_update),
             drive_letter: self.drive_letter,
+            volume: self.volume.clone(),
             config: self.config.clone(),
             memory_usage: AtomicU64::new(self.memory_usage.load(Ordering::Relaxed)),
             files_processed: AtomicUsize::new(self.files_processed.load(Ordering::Relaxed)),
             // Thread handles and monitoring cannot be cloned - reinitialize as needed
             save_thread_handle: parking_lot::Mutex::new(None),
             shutdown_flag: Arc::new(StdAtomicBool::new(false)),
+            cancel: self.cancel.clone(),
+            rebuild_cancel: Arc::clone(&self.rebuild_cancel),
+            wal: Arc::clone(&self.wal),
             usn_monitor: parking_lot::Mutex::new(None),
             volume_handle: parking_lot::Mutex::new(None),
+            change_listeners: Arc::clone(&self.change_listeners),
+            maintenance: Arc::clone(&self.maintenance),
+            operations: Arc::clone(&self.operations),
         }
     }
 }
@@ -227,6 +324,8 @@ pub struct CacheStats {
     pub error_count: usize,
     /// Time taken for the last update in milliseconds
     pub last_update_duration_ms: u128,
+    /// USN Journal changes read but not yet applied to the cache
+    pub usn_queue_depth: usize,
 }
 
 impl std::fmt::Display for CacheStats {
@@ -236,7 +335,7 @@ impl std::fmt::Display for CacheStats {
         
         write!(
             f,
-            "Cache for {}: {} files ({} processed, {} new), {:.2} MB, USN: {}/{}, last update: {} ({:.2}ms)",
+            "Cache for {}: {} files ({} processed, {} new), {:.2} MB, USN: {}/{} ({} queued), last update: {} ({:.2}ms)",
             self.drive_letter,
             self.file_count,
             self.files_processed,
@@ -244,6 +343,7 @@ impl std::fmt::Display for CacheStats {
             memory_mb,
             self.last_processed_usn,
             self.highest_usn,
+            self.usn_queue_depth,
             last_update,
             self.last_update_duration_ms as f64
         )
@@ -251,7 +351,7 @@ impl std::fmt::Display for CacheStats {
 }
 
 /// Represents a file entry in the MFT cache
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     pub id: u64,
     pub name: String,
@@ -259,6 +359,188 @@ pub struct FileEntry {
     pub size: u64,
     pub is_directory: bool,
     pub extension: Option<String>,
+    /// Creation time from $STANDARD_INFORMATION
+    pub created: SystemTime,
+    /// Last modification time from $STANDARD_INFORMATION
+    pub modified: SystemTime,
+    /// Last access time from $STANDARD_INFORMATION
+    pub accessed: SystemTime,
+    /// File attribute flags from $STANDARD_INFORMATION (see [`file_attribute`])
+    pub attributes: u32,
+    /// Number of hard links (names) the file record has; 1 for most files
+    pub link_count: u16,
+    /// Reparse tag from $REPARSE_POINT (see `reparse_tag`), 0 if the entry isn't a reparse point
+    pub reparse_tag: u32,
+}
+
+impl FileEntry {
+    /// Whether every bit of `flags` is set in the entry's attributes
+    pub fn has_attributes(&self, flags: u32) -> bool {
+        self.attributes & flags == flags
+    }
+}
+
+#[cfg(test)]
+impl FileEntry {
+    /// An empty file at `path` for tests
+    ///
+    /// The name and lowercased extension come from the path, the times are the
+    /// UNIX epoch and there are no attributes; tests set the fields they care
+    /// about with struct update syntax.
+    pub(crate) fn test(id: u64, path: &str) -> Self {
+        let name = path.rsplit('\\').next().unwrap_or(path).to_string();
+        Self {
+            id,
+            extension: name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()),
+            name,
+            path: path.to_string(),
+            size: 0,
+            is_directory: false,
+            created: std::time::UNIX_EPOCH,
+            modified: std::time::UNIX_EPOCH,
+            accessed: std::time::UNIX_EPOCH,
+            attributes: 0,
+            link_count: 1,
+            reparse_tag: 0,
+        }
+    }
+}
+
+/// NTFS file attribute flags
+pub mod file_attribute {
+    /// The file can't be written to
+    pub const READONLY: u32 = 0x0000_0001;
+    /// Hidden from ordinary directory listings
+    pub const HIDDEN: u32 = 0x0000_0002;
+    /// Used by the operating system
+    pub const SYSTEM: u32 = 0x0000_0004;
+    /// The entry is a directory
+    pub const DIRECTORY: u32 = 0x0000_0010;
+    /// Sparse file
+    pub const SPARSE: u32 = 0x0000_0200;
+    /// Symbolic link, junction or other reparse point
+    pub const REPARSE_POINT: u32 = 0x0000_0400;
+    /// Compressed by NTFS
+    pub const COMPRESSED: u32 = 0x0000_0800;
+    /// Encrypted with EFS
+    pub const ENCRYPTED: u32 = 0x0000_4000;
+}
+
+/// Seconds between the NTFS epoch (1601-01-01) and the UNIX epoch
+const NTFS_EPOCH_OFFSET_SECS: u64 = 11_644_473_600;
+
+/// Convert an NTFS timestamp (100ns intervals since 1601) to `SystemTime`
+pub fn nt_time_to_system_time(nt_timestamp: u64) -> SystemTime {
+    let secs = nt_timestamp / 10_000_000;
+    let nanos = (nt_timestamp % 10_000_000) * 100;
+    match secs.checked_sub(NTFS_EPOCH_OFFSET_SECS) {
+        Some(unix_secs) => std::time::UNIX_EPOCH + Duration::new(unix_secs, nanos as u32),
+        None => std::time::UNIX_EPOCH,
+    }
+}
+
+/// Read created/modified/accessed times of a file record
+///
+/// Files whose $STANDARD_INFORMATION can't be read get the UNIX epoch, so
+/// they never match an "after" date filter by accident.
+fn file_times(file: &ntfs::NtfsFile) -> (SystemTime, SystemTime, SystemTime) {
+    match file.info() {
+        Ok(info) => (
+            nt_time_to_system_time(info.creation_time().nt_timestamp()),
+            nt_time_to_system_time(info.modification_time().nt_timestamp()),
+            nt_time_to_system_time(info.access_time().nt_timestamp()),
+        ),
+        Err(_) => (std::time::UNIX_EPOCH, std::time::UNIX_EPOCH, std::time::UNIX_EPOCH),
+    }
+}
+
+/// Read the attribute flags of a file record (0 if $STANDARD_INFORMATION can't be read)
+fn file_attributes(file: &ntfs::NtfsFile) -> u32 {
+    file.info().map_or(0, |info| info.file_attributes().bits())
+}
+
+/// Reparse tag of a file record whose attributes mark it as a reparse point
+///
+/// The tag is the first field of the $REPARSE_POINT attribute; records that
+/// aren't reparse points (or whose attribute can't be read) get 0.
+fn file_reparse_tag<T: std::io::Read + std::io::Seek>(file: &ntfs::NtfsFile, attributes: u32, fs: &mut T) -> u32 {
+    use ntfs::{NtfsAttributeType, NtfsReadSeek};
+
+    if attributes & file_attribute::REPARSE_POINT == 0 {
+        return 0;
+    }
+    let mut items = file.attributes();
+    while let Some(item) = items.next(fs) {
+        let Ok(item) = item else { continue };
+        let Ok(attribute) = item.to_attribute() else { continue };
+        if !matches!(attribute.ty(), Ok(NtfsAttributeType::ReparsePoint)) {
+            continue;
+        }
+        let mut tag = [0u8; 4];
+        return match attribute.value(fs) {
+            Ok(mut value) if matches!(value.read(fs, &mut tag), Ok(4)) => u32::from_le_bytes(tag),
+            _ => 0,
+        };
+    }
+    0
+}
+
+/// Number of hard links of a file record (at least 1)
+///
+/// The record header counts every $FILE_NAME attribute, including the 8.3
+/// short names that aren't links of their own, so records claiming more than
+/// one link have their names counted.
+fn file_link_count<T: std::io::Read + std::io::Seek>(file: &ntfs::NtfsFile, fs: &mut T) -> u16 {
+    use ntfs::structured_values::{NtfsFileName, NtfsFileNamespace};
+    use ntfs::NtfsAttributeType;
+
+    if file.hard_link_count() <= 1 {
+        return 1;
+    }
+    let mut names = 0u16;
+    let mut attributes = file.attributes();
+    while let Some(item) = attributes.next(fs) {
+        let Ok(item) = item else { continue };
+        let Ok(attribute) = item.to_attribute() else { continue };
+        if !matches!(attribute.ty(), Ok(NtfsAttributeType::FileName)) {
+            continue;
+        }
+        if let Ok(name) = attribute.structured_value::<_, NtfsFileName>(fs) {
+            if name.namespace() != NtfsFileNamespace::Dos {
+                names += 1;
+            }
+        }
+    }
+    names.max(1)
+}
+
+/// Lowercased extension of a file name, without the leading dot
+pub(crate) fn extension_of(name: &str) -> Option<String> {
+    Path::new(name).extension().map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+/// Location on disk of a cache path (relative, backslash-separated) below `root`
+pub(crate) fn disk_path(root: &Path, relative: &str) -> PathBuf {
+    relative
+        .split('\\')
+        .filter(|part| !part.is_empty())
+        .fold(root.to_path_buf(), |path, part| path.join(part))
+}
+
+/// A drive whose files live below `root` on disk
+///
+/// What the per-match disk checks (image, media, content and document
+/// filters) need to find a cached file's bytes.
+#[derive(Debug, Clone)]
+pub struct DriveRoot {
+    pub drive: char,
+    pub root: PathBuf,
+}
+
+impl DriveRoot {
+    pub fn new(drive: char, root: PathBuf) -> Self {
+        Self { drive, root }
+    }
 }
 
 impl MftCache {
@@ -269,6 +551,14 @@ impl MftCache {
     
     /// Create a new MFT cache with custom configuration
     pub fn with_config(drive_letter: char, config: MftCacheConfig) -> Result<Self> {
+        Self::with_cancel(drive_letter, config, CancelToken::new())
+    }
+    
+    /// Create a new MFT cache whose rebuilds stop once `cancel` is cancelled
+    ///
+    /// The engine passes a child of its root token, so stopping the service
+    /// stops a rebuild that is still walking the MFT.
+    pub fn with_cancel(drive_letter: char, config: MftCacheConfig, cancel: CancelToken) -> Result<Self> {
         // Ensure cache directory exists if persistence is enabled
         if config.persistence_enabled {
             if let Err(e) = std::fs::create_dir_all(&config.cache_dir) {
@@ -277,32 +567,8 @@ impl MftCache {
             }
         }
         
-        let shutdown_flag = Arc::new(StdAtomicBool::new(false));
-        
-        let mut cache = Self {
-            // Core data structures
-            files: Default::default(),
-            extension_index: Default::default(),
-            name_index: Default::default(),
-            path_index: Default::default(),
-            
-            // Metadata
-            last_update: RwLock::new(SystemTime::now()),
-            drive_letter: drive_letter.to_ascii_uppercase(),
-            config,
-            
-            // Statistics and tracking
-            memory_usage: AtomicU64::new(0),
-            files_processed: AtomicUsize::new(0),
-            
-            // Persistence
-            save_thread_handle: parking_lot::Mutex::new(None),
-            shutdown_flag: shutdown_flag.clone(),
-            
-            // USN Journal monitoring
-            usn_monitor: parking_lot::Mutex::new(None),
-            volume_handle: parking_lot::Mutex::new(None),
-        };
+        let mut cache = Self::empty_with_config(drive_letter, config);
+        cache.cancel = cancel;
         
         // Initialize Rayon thread pool if parallel processing is enabled
         if cache.config.parallel_processing && cache.config.num_threads > 0 {
@@ -312,12 +578,13 @@ impl MftCache {
                 .context("Failed to initialize Rayon thread pool")?;
         }
         
-        // Try to load from cache if persistence is enabled
+        // Try to load from cache if persistence is enabled, bringing it up to
+        // date with the changes logged since it was saved
         let mut loaded_from_cache = false;
         if cache.config.persistence_enabled {
             if let Some(loaded_cache) = cache.load_from_disk()? {
                 // Use the loaded cache instead of rebuilding
-                *cache = loaded_cache;
+                cache = loaded_cache;
                 loaded_from_cache = true;
                 info!("Successfully loaded MFT cache from disk");
             }
@@ -325,7 +592,7 @@ impl MftCache {
         
         // Rebuild if not loaded from cache
         if !loaded_from_cache {
-            cache.rebuild()?;
+            cache.rebuild_for("startup")?;
         }
         
         // Start auto-save thread if enabled
@@ -336,14 +603,113 @@ impl MftCache {
         Ok(cache)
     }
     
-    /// Load the cache from disk if available
-    fn load_from_disk(&self) -> Result<Option<Self>> {
-        use crate::fastsearch_service::cache_persistence::load_cache;
+    /// Create an empty cache that is filled by another backend instead of the MFT
+    ///
+    /// Used for directory indexes (containers, WSL) where raw volume access is
+    /// impossible; `drive_letter` is the virtual drive the index is served as.
+    pub fn detached(drive_letter: char) -> Self {
+        Self::empty_with_config(drive_letter, MftCacheConfig::default().with_persistence(false))
+    }
+    
+    /// Build the cache structure without reading anything
+    fn empty_with_config(drive_letter: char, config: MftCacheConfig) -> Self {
+        let shutdown_flag = Arc::new(StdAtomicBool::new(false));
+        // Only persisted caches need to find their files again after a letter change
+        let volume = if config.persistence_enabled {
+            VolumeId::for_drive(drive_letter)
+        } else {
+            VolumeId::letter(drive_letter)
+        };
         
-        match load_cache(&self.config.cache_dir, self.drive_letter) {
-            Ok(Some(mut cache)) => {
+        Self {
+            // Core data structures
+            files: Default::default(),
+            extension_index: Default::default(),
+            name_index: Default::default(),
+            folded_names: Default::default(),
+            name_affixes: Default::default(),
+            trigrams: Arc::new(RwLock::new(config.trigram_index.then(TrigramIndex::new))),
+            path_index: Default::default(),
+            
+            // Metadata
+            last_update: Arc::new(RwLock::new(SystemTime::now())),
+            drive_letter: drive_letter.to_ascii_uppercase(),
+            volume,
+            config,
+            
+            // Statistics and tracking
+            memory_usage: AtomicU64::new(0),
+            files_processed: AtomicUsize::new(0),
+            
+            // Persistence
+            save_thread_handle: parking_lot::Mutex::new(None),
+            shutdown_flag: shutdown_flag.clone(),
+            cancel: CancelToken::new(),
+            rebuild_cancel: Default::default(),
+            wal: Default::default(),
+            
+            // USN Journal monitoring
+            usn_monitor: parking_lot::Mutex::new(None),
+            volume_handle: parking_lot::Mutex::new(None),
+            change_listeners: Arc::new(parking_lot::Mutex::new(Vec::new())),
+            maintenance: Arc::new(MaintenanceLock::new()),
+            operations: Arc::new(DriveOperations::new()),
+        }
+    }
+    
+    /// Key the saved cache and change log are signed with
+    fn signing_key(&self) -> Result<CacheKey> {
+        CacheKey::load_or_create(&self.config.key_path)
+    }
+    
+    /// Open the drive's change log, returning the changes to replay over the snapshot signed with `snapshot_mac`
+    ///
+    /// Without a log the cache still works, it just loses the changes since
+    /// the last snapshot in a crash. `None` means the log couldn't be trusted,
+    /// so the snapshot can't be brought up to date and mustn't be used.
+    fn open_wal(&self, key: CacheKey, snapshot_mac: &[u8]) -> Option<Vec<WalRecord>> {
+        match CacheWal::open(&self.config.cache_dir, self.drive_letter, &self.volume, key, snapshot_mac) {
+            Ok((wal, pending)) => {
+                *self.wal.lock() = Some(wal);
+                pending
+            }
+            Err(e) => {
+                warn!("Changes to the drive {} cache won't be logged between saves: {}", self.drive_letter, e);
+                Some(Vec::new())
+            }
+        }
+    }
+    
+    /// Load the cache from disk if available, replaying the changes logged since it was saved over it
+    fn load_from_disk(&self) -> Result<Option<Self>> {
+        let key = match self.signing_key() {
+            Ok(key) => key,
+            Err(e) => {
+                error!("Can't check the saved drive {} cache, and changes won't be logged between saves: {}", self.drive_letter, e);
+                return Ok(None);
+            }
+        };
+        match self.config.persistence_backend.store().load(&self.config.cache_dir, self.drive_letter, &self.volume, &key) {
+            Ok(Some((mut cache, snapshot_mac))) => {
+                let Some(pending) = self.open_wal(key, &snapshot_mac) else {
+                    return Ok(None);
+                };
+                
                 // Update the configuration to match the current one
                 cache.config = self.config.clone();
+                cache.volume = self.volume.clone();
+                cache.cancel = self.cancel.clone();
+                
+                // Catch up with the changes made after the snapshot was saved
+                cache.wal = Arc::clone(&self.wal);
+                for record in pending {
+                    match record {
+                        WalRecord::Upsert(entry) => cache.apply_upsert(entry),
+                        WalRecord::Remove(path) => {
+                            cache.apply_remove(&path);
+                        }
+                    }
+                }
                 
                 // Update timestamps
                 *cache.last_update.write() = SystemTime::now();
@@ -358,9 +724,14 @@ impl MftCache {
                 
                 Ok(Some(cache))
             }
-            Ok(None) => Ok(None),
+            Ok(None) => {
+                // Whatever is logged belongs to no snapshot; the rebuild's save empties it
+                self.open_wal(key, &[]);
+                Ok(None)
+            }
             Err(e) => {
                 error!("Failed to load cache from disk: {}", e);
+                self.open_wal(key, &[]);
                 Ok(None)
             }
         }
@@ -383,6 +754,10 @@ impl MftCache {
             while !shutdown_flag.load(Ordering::SeqCst) {
                 std::thread::sleep(save_interval);
                 
+                // No writes to the cache directory while the drive is in maintenance
+                if cache_arc.maintenance().is_active() {
+                    continue;
+                }
                 if let Err(e) = cache_arc.save_to_disk() {
                     error!("Error in auto-save thread: {}", e);
                 }
@@ -397,29 +772,79 @@ impl MftCache {
     
     /// Save the cache to disk
     pub fn save_to_disk(&self) -> Result<()> {
-        use crate::fastsearch_service::cache_persistence::save_cache;
-        
         if !self.config.persistence_enabled {
             return Ok(());
         }
         
-        save_cache(self, &self.config.cache_dir)
-            .context("Failed to save cache to disk")
+        let key = self.signing_key()?;
+        
+        // Hold the log so no change lands between the snapshot and emptying the log
+        let mut wal = self.wal.lock();
+        let snapshot_mac = self.config.persistence_backend.store().save(self, &self.config.cache_dir, &key)
+            .context("Failed to save cache to disk")?;
+        if let Some(wal) = wal.as_mut() {
+            wal.truncate(&snapshot_mac)?;
+        }
+        Ok(())
     }
     
     /// Clear the cache and rebuild it from scratch
     pub fn rebuild(&self) -> Result<()> {
+        self.rebuild_for("request")
+    }
+    
+    /// `rebuild` for a request that can be cancelled
+    ///
+    /// Stops at the next directory once `cancel` (or the cache's own token) is
+    /// cancelled, failing with [`Cancelled`]. The partial cache isn't saved.
+    pub fn rebuild_cancellable(&self, cancel: &CancelToken) -> Result<()> {
+        *self.rebuild_cancel.lock() = Some(cancel.clone());
+        let result = self.rebuild_for("request");
+        *self.rebuild_cancel.lock() = None;
+        result
+    }
+    
+    /// Stop a rebuild that is running and any later one, for a cache being retired
+    pub fn cancel_rebuilds(&self) {
+        self.cancel.cancel();
+    }
+    
+    /// Fail with [`Cancelled`] once the running rebuild should stop; checked per directory
+    fn check_cancelled(&self) -> Result<(), Cancelled> {
+        self.cancel.check()?;
+        match self.rebuild_cancel.lock().as_ref() {
+            Some(cancel) => cancel.check(),
+            None => Ok(()),
+        }
+    }
+    
+    /// Rebuild the cache, naming what triggered it in the ETW event
+    fn rebuild_for(&self, source: &str) -> Result<()> {
+        if self.maintenance.is_active() {
+            return Err(anyhow!("Drive {}: is in maintenance mode; resume it before rebuilding", self.drive_letter));
+        }
+        // Not while an export is reading the cache or another rebuild is filling it
+        let _rebuild = self.operations.begin(self.drive_letter, DriveOperation::Rebuild, source)?;
+        self.check_cancelled()?;
         info!("Rebuilding MFT cache for drive {}:", self.drive_letter);
+        let start = Instant::now();
         
         // Clear existing data
         self.clear()?;
         
-        // Rebuild the cache
-        self.rebuild_internal()?;
+        // Rebuild the cache; a cancelled rebuild leaves it empty rather than saving half a drive
+        if let Err(e) = self.rebuild_internal() {
+            if e.is::<Cancelled>() {
+                info!("Rebuild of the drive {}: cache cancelled after {:.2?}", self.drive_letter, start.elapsed());
+            }
+            return Err(e);
+        }
         
         // Update the last update time
         *self.last_update.write() = SystemTime::now();
         
+        etw::cache_rebuilt(self.drive_letter, self.files.read().len(), start.elapsed(), source);
+        
         // Save to disk if persistence is enabled
         if self.config.persistence_enabled {
             self.save_to_disk()?;
@@ -484,6 +909,11 @@ impl MftCache {
         self.files.write().clear();
         self.extension_index.write().clear();
         self.name_index.write().clear();
+        self.folded_names.write().clear();
+        self.name_affixes.write().clear();
+        if let Some(trigrams) = self.trigrams.write().as_mut() {
+            trigrams.clear();
+        }
         self.path_index.write().clear();
         
         // Reset statistics
@@ -502,10 +932,10 @@ impl MftCache {
         let last_update = *self.last_update.read();
         
         // Get USN information from the USN monitor if available
-        let (last_processed_usn, highest_usn) = if let Some(monitor) = &*self.usn_monitor.lock() {
-            (monitor.last_processed_usn(), monitor.highest_usn())
+        let (last_processed_usn, highest_usn, usn_queue_depth) = if let Some(monitor) = &*self.usn_monitor.lock() {
+            (monitor.last_processed_usn(), monitor.highest_usn(), monitor.queue_metrics().queued_changes())
         } else {
-            (0, 0)
+            (0, 0, 0)
         };
         
         CacheStats {
@@ -520,6 +950,7 @@ impl MftCache {
             dirs_processed_in_last_update: 0,  // This should be tracked during updates
             error_count: 0,                    // This should be tracked during processing
             last_update_duration_ms: 0,        // This should be tracked during updates
+            usn_queue_depth,
         }
     }
     
@@ -533,6 +964,11 @@ impl MftCache {
         self.drive_letter
     }
     
+    /// Volume the cache is saved under, the same whichever letter the drive has
+    pub fn volume(&self) -> &VolumeId {
+        &self.volume
+    }
+    
     /// Get a reference to the cache configuration
     pub fn config(&self) -> &MftCacheConfig {
         &self.config
@@ -548,6 +984,91 @@ impl MftCache {
         self.path_index.read()
     }
     
+    /// Every path in the cache that refers to a file record, sorted
+    ///
+    /// Hard links share one MFT record, so each of their names is a path to
+    /// the same ID.
+    pub fn link_paths(&self, id: u64) -> Vec<String> {
+        let mut paths: Vec<String> = self.path_index.read()
+            .iter()
+            .filter(|(_, other)| **other == id)
+            .map(|(path, _)| path.clone())
+            .collect();
+        paths.sort();
+        paths
+    }
+    
+    /// `link_paths` of several file records at once, in one pass over the path index
+    pub fn link_paths_of(&self, ids: &HashSet<u64>) -> HashMap<u64, Vec<String>> {
+        let mut links: HashMap<u64, Vec<String>> = HashMap::new();
+        for (path, id) in self.path_index.read().iter() {
+            if ids.contains(id) {
+                links.entry(*id).or_default().push(path.clone());
+            }
+        }
+        for paths in links.values_mut() {
+            paths.sort();
+        }
+        links
+    }
+    
+    /// Get a read lock on the name index (lowercased name -> file IDs)
+    pub fn get_name_index(&self) -> RwLockReadGuard<'_, HashMap<String, Vec<u64>>> {
+        self.name_index.read()
+    }
+    
+    /// Get a read lock on the folded names (name index key -> folded form, for names folding changes)
+    pub fn get_folded_names(&self) -> RwLockReadGuard<'_, HashMap<String, String>> {
+        self.folded_names.read()
+    }
+    
+    /// Replace the name index after a bulk build, folding and sorting its names
+    fn set_name_index(&self, name_index: HashMap<String, Vec<u64>>) {
+        *self.name_index.write() = name_index;
+        self.refresh_folded_names();
+        self.refresh_name_affixes();
+        self.refresh_trigrams();
+    }
+    
+    /// Recompute the folded form of every name in the name index
+    pub(crate) fn refresh_folded_names(&self) {
+        let folded: HashMap<String, String> = self.name_index.read()
+            .keys()
+            .filter_map(|name| folded_key(name).map(|folded| (name.clone(), folded)))
+            .collect();
+        *self.folded_names.write() = folded;
+    }
+    
+    /// Rebuild the prefix and suffix indexes from the name index
+    pub(crate) fn refresh_name_affixes(&self) {
+        let affixes = NameAffixes::from_names(self.name_index.read().keys());
+        *self.name_affixes.write() = affixes;
+    }
+    
+    /// Rebuild the trigram index from the name index, if the cache keeps one
+    pub(crate) fn refresh_trigrams(&self) {
+        if !self.config.trigram_index {
+            return;
+        }
+        let trigrams = TrigramIndex::from_names(self.name_index.read().keys());
+        *self.trigrams.write() = Some(trigrams);
+    }
+    
+    /// Get a read lock on the trigram index of the names (`None` if disabled)
+    pub fn get_trigrams(&self) -> RwLockReadGuard<'_, Option<TrigramIndex>> {
+        self.trigrams.read()
+    }
+    
+    /// Get a read lock on the prefix and suffix indexes of the names
+    pub fn get_name_affixes(&self) -> RwLockReadGuard<'_, NameAffixes> {
+        self.name_affixes.read()
+    }
+    
+    /// Get a read lock on the extension index (lowercased extension -> file IDs)
+    pub fn get_extension_index(&self) -> RwLockReadGuard<'_, HashMap<String, Vec<u64>>> {
+        self.extension_index.read()
+    }
+    
     /// Start monitoring the filesystem for changes using USN Journal
     pub fn start_monitoring(&self) -> Result<()> {
         use winapi::um::fileapi::CreateFileW;
@@ -591,14 +1112,14 @@ impl MftCache {
         let mut usn_monitor = crate::fastsearch_service::usn_journal::UsnJournalMonitor::new(
             self.drive_letter,
             handle,
-        )?;
+        )?.paused_by(Arc::clone(&self.maintenance));
         
         // Clone self for the callback
         let cache = self.clone();
         
         // Start monitoring with a callback to update the cache
-        usn_monitor.start(move || {
-            if let Err(e) = cache.handle_filesystem_changes() {
+        usn_monitor.start(move |changes| {
+            if let Err(e) = cache.handle_filesystem_changes(changes) {
                 error!("Error handling filesystem changes: {}", e);
             }
         })?;
@@ -627,14 +1148,196 @@ impl MftCache {
         Ok(())
     }
     
+    /// Stop the auto-save thread after its current sleep, leaving what it last saved
+    pub fn stop_auto_save(&self) {
+        self.shutdown_flag.store(true, Ordering::SeqCst);
+    }
+    
+    /// Register a listener that is called with each batch of USN Journal changes
+    ///
+    /// Listeners run before the cache is refreshed, so parent directories of
+    /// new entries can still be resolved against the current contents.
+    pub fn add_change_listener(&self, listener: ChangeListener) {
+        self.change_listeners.lock().push(listener);
+    }
+    
+    /// Whether the USN Journal monitor is running for this cache
+    pub fn is_monitoring(&self) -> bool {
+        self.usn_monitor.lock().is_some()
+    }
+    
+    /// Maintenance mode of the drive, which holds background updates while active
+    pub fn maintenance(&self) -> &MaintenanceLock {
+        &self.maintenance
+    }
+    
+    /// Operations that need the drive to themselves
+    pub fn operations(&self) -> &DriveOperations {
+        &self.operations
+    }
+    
+    /// Replace every entry at once, rebuilding the indexes
+    pub fn replace_entries(&self, entries: Vec<FileEntry>) {
+        self.clear_indexes();
+        for entry in entries {
+            self.insert_indexed(entry);
+        }
+        *self.last_update.write() = SystemTime::now();
+    }
+    
+    /// Insert or replace a single entry, keeping the indexes in sync
+    pub fn upsert_entry(&self, entry: FileEntry) {
+        let _logged = self.log_change(|| WalRecord::Upsert(entry.clone()));
+        self.apply_upsert(entry);
+    }
+    
+    /// Remove the entry at `path` and, for directories, everything below it
+    ///
+    /// Returns the number of entries removed.
+    pub fn remove_path(&self, path: &str) -> usize {
+        let _logged = self.log_change(|| WalRecord::Remove(path.to_string()));
+        self.apply_remove(path)
+    }
+    
+    /// Make the changes logged since the last commit durable
+    ///
+    /// Changes are logged without waiting for the disk; whoever applies a
+    /// batch of them commits once at the end, so the batch costs one flush,
+    /// and the flush doesn't hold up snapshots or other changes.
+    pub fn commit_changes(&self) {
+        let unsynced = self.wal.lock().as_mut().map(CacheWal::take_unsynced);
+        let flushed = match unsynced {
+            Some(Ok(Some(file))) => file.sync_data().context("Failed to flush cache log"),
+            Some(Err(e)) => Err(e),
+            Some(Ok(None)) | None => return,
+        };
+        if let Err(e) = flushed {
+            error!("Failed to commit changes to the drive {} cache: {}", self.drive_letter, e);
+        }
+    }
+    
+    /// Append a change to the log before it is applied
+    ///
+    /// The returned guard keeps a snapshot from being taken until the change
+    /// is in the cache as well. The change reaches the disk with the batch's
+    /// `commit_changes`.
+    fn log_change(&self, record: impl FnOnce() -> WalRecord) -> parking_lot::MutexGuard<'_, Option<CacheWal>> {
+        let mut wal = self.wal.lock();
+        if let Some(log) = wal.as_mut() {
+            if let Err(e) = log.append(&record()) {
+                error!("Failed to log a change to the drive {} cache: {}", self.drive_letter, e);
+            }
+        }
+        wal
+    }
+    
+    fn apply_upsert(&self, entry: FileEntry) {
+        self.remove_indexed(entry.id);
+        self.insert_indexed(entry);
+        *self.last_update.write() = SystemTime::now();
+    }
+    
+    fn apply_remove(&self, path: &str) -> usize {
+        let prefix = format!("{}\\", path);
+        let ids: Vec<u64> = self.path_index.read()
+            .iter()
+            .filter(|(p, _)| p.as_str() == path || p.starts_with(&prefix))
+            .map(|(_, id)| *id)
+            .collect();
+        
+        for id in &ids {
+            self.remove_indexed(*id);
+        }
+        if !ids.is_empty() {
+            *self.last_update.write() = SystemTime::now();
+        }
+        ids.len()
+    }
+    
+    /// Run change listeners for changes detected by a backend other than the USN Journal
+    pub fn notify_change_listeners(&self, changes: &[UsnChange]) {
+        let listeners: Vec<ChangeListener> = self.change_listeners.lock().clone();
+        for listener in listeners {
+            listener(self, changes);
+        }
+    }
+    
+    fn clear_indexes(&self) {
+        self.files.write().clear();
+        self.extension_index.write().clear();
+        self.name_index.write().clear();
+        self.folded_names.write().clear();
+        self.name_affixes.write().clear();
+        if let Some(trigrams) = self.trigrams.write().as_mut() {
+            trigrams.clear();
+        }
+        self.path_index.write().clear();
+    }
+    
+    fn insert_indexed(&self, entry: FileEntry) {
+        let id = entry.id;
+        if let Some(ext) = &entry.extension {
+            self.extension_index.write().entry(ext.clone()).or_default().push(id);
+        }
+        let name = entry.name.to_lowercase();
+        if let Some(folded) = folded_key(&name) {
+            self.folded_names.write().insert(name.clone(), folded);
+        }
+        self.name_affixes.write().insert(&name);
+        if let Some(trigrams) = self.trigrams.write().as_mut() {
+            trigrams.insert(&name);
+        }
+        self.name_index.write().entry(name).or_default().push(id);
+        self.path_index.write().insert(entry.path.clone(), id);
+        self.files.write().insert(id, entry);
+    }
+    
+    fn remove_indexed(&self, id: u64) {
+        let Some(entry) = self.files.write().remove(&id) else {
+            return;
+        };
+        
+        fn unlink(index: &mut HashMap<String, Vec<u64>>, key: &str, id: u64) {
+            if let Some(ids) = index.get_mut(key) {
+                ids.retain(|other| *other != id);
+                if ids.is_empty() {
+                    index.remove(key);
+                }
+            }
+        }
+        if let Some(ext) = &entry.extension {
+            unlink(&mut self.extension_index.write(), ext, id);
+        }
+        let name = entry.name.to_lowercase();
+        let mut name_index = self.name_index.write();
+        unlink(&mut name_index, &name, id);
+        if !name_index.contains_key(&name) {
+            self.folded_names.write().remove(&name);
+            self.name_affixes.write().remove(&name);
+            if let Some(trigrams) = self.trigrams.write().as_mut() {
+                trigrams.remove(&name);
+            }
+        }
+        drop(name_index);
+        self.path_index.write().remove(&entry.path);
+    }
+    
     /// Handle filesystem changes detected by the USN Journal
-    fn handle_filesystem_changes(&self) -> Result<()> {
-        info!("Handling filesystem changes for drive {}", self.drive_letter);
+    fn handle_filesystem_changes(&self, changes: &[UsnChange]) -> Result<()> {
+        info!("Handling {} filesystem changes for drive {}", changes.len(), self.drive_letter);
+        let start = Instant::now();
+        
+        // Clone the listener list so listeners may register others without deadlocking
+        let listeners: Vec<ChangeListener> = self.change_listeners.lock().clone();
+        for listener in listeners {
+            listener(self, changes);
+        }
         
         // For now, we'll just rebuild the entire cache when changes are detected
         // In a production system, you'd want to be more granular and only update what changed
-        self.rebuild()?;
+        self.rebuild_for("usn")?;
         
+        etw::usn_batch(self.drive_letter, changes.len(), start.elapsed());
         Ok(())
     }
     
@@ -888,6 +1591,8 @@ impl MftCache {
         dir_queue.push_back((root.to_owned(), PathBuf::new()));
         
         while let Some((dir, path)) = dir_queue.pop_front() {
+            // Dropping the sender on the way out lets the workers finish
+            self.check_cancelled()?;
             let mut fs = ntfs.fs();
             if let Ok(dir_index) = dir.directory_index(&mut fs) {
                 for entry_result in dir_index.entries() {
@@ -907,14 +1612,21 @@ impl MftCache {
                                     let full_path = path.join(&*name_str);
                                     
                                     // Create file entry
+                                    let (created, modified, accessed) = file_times(&file);
+                                    let attributes = file_attributes(&file);
                                     let file_entry = FileEntry {
                                         id: file_id,
+                                        extension: extension_of(&name_str),
                                         name: name_str.into_owned(),
                                         path: full_path.to_string_lossy().into_owned(),
                                         size: file.data_size(&mut fs).unwrap_or(0),
-                                        created: file.created(&mut fs).unwrap_or_else(|_| SystemTime::now()),
-                                        modified: file.modified(&mut fs).unwrap_or_else(|_| SystemTime::now()),
                                         is_directory: is_dir,
+                                        created,
+                                        modified,
+                                        accessed,
+                                        attributes,
+                                        link_count: file_link_count(&file, &mut fs),
+                                        reparse_tag: file_reparse_tag(&file, attributes, &mut fs),
                                     };
                                     
                                     // Send to worker thread
@@ -933,7 +1645,7 @@ impl MftCache {
     // Update the cache with the new data
     *self.files.write() = all_files;
     *self.extension_index.write() = all_extension_index;
-    *self.name_index.write() = all_name_index;
+    self.set_name_index(all_name_index);
     *self.path_index.write() = all_path_index;
     
     // Update last update time
@@ -1042,6 +1754,7 @@ fn rebuild_parallel(&self, ntfs: &Ntfs, root: &ntfs::NtfsFile) -> Result<()> {
     // Process directories in parallel with work stealing
     let result = top_level_dirs.par_iter()
         .try_for_each_with(tx, |sender, entry| {
+            self.check_cancelled()?;
             let ntfs = ntfs.clone();
             
             if let Ok(file) = entry.to_file(&ntfs) {
@@ -1143,7 +1856,7 @@ fn rebuild_parallel(&self, ntfs: &Ntfs, root: &ntfs::NtfsFile) -> Result<()> {
         // Update cache atomically
         *self.files.write() = all_files;
         *self.extension_index.write() = all_extension_index;
-        *self.name_index.write() = all_name_index;
+        self.set_name_index(all_name_index);
         *self.path_index.write() = all_path_index;
         *self.last_update.write() = SystemTime::now();
         
@@ -1184,7 +1897,7 @@ fn rebuild_parallel(&self, ntfs: &Ntfs, root: &ntfs::NtfsFile) -> Result<()> {
                 // Update cache atomically
                 *self.files.write() = files;
                 *self.extension_index.write() = extension_index;
-                *self.name_index.write() = name_index;
+                self.set_name_index(name_index);
                 *self.path_index.write() = path_index;
                 *self.last_update.write() = SystemTime::now();
                 
@@ -1222,24 +1935,6 @@ fn rebuild_parallel(&self, ntfs: &Ntfs, root: &ntfs::NtfsFile) -> Result<()> {
         
         Ok(buffer)
     }
-/// Represents a file entry in the MFT cache
-#[derive(Debug, Clone)]
-pub struct FileEntry {
-    /// Unique file ID (MFT record number)
-    pub id: u64,
-    /// File name
-    pub name: String,
-    /// Full file path
-    pub path: String,
-    /// File size in bytes
-    pub size: u64,
-    /// File creation time
-    pub created: SystemTime,
-    /// File last modified time
-    pub modified: SystemTime,
-    /// Whether the entry is a directory
-    pub is_directory: bool,
-}
 
 impl MftCache {
     /// Process a directory and its contents recursively
@@ -1271,6 +1966,9 @@ impl MftCache {
                 return Ok(()); // Skip inaccessible directories
             }
         };
+        // Stop between directories once the rebuild is cancelled
+        self.check_cancelled()?;
+        
         // Process each entry in the directory
         for entry_result in dir_index.entries() {
             // Check memory limits periodically
@@ -1320,8 +2018,8 @@ impl MftCache {
             
             // Get file size and timestamps
             let size = file_record.data_size(&mut fs).unwrap_or(0);
-            let created = file_record.created(&mut fs).unwrap_or_else(|_| SystemTime::now());
-            let modified = file_record.modified(&mut fs).unwrap_or_else(|_| SystemTime::now());
+            let (created, modified, accessed) = file_times(&file_record);
+            let attributes = file_attributes(&file_record);
             
             // Create the file entry
             let file_entry = FileEntry {
@@ -1329,9 +2027,14 @@ impl MftCache {
                 name: name.clone(),
                 path: full_path.clone(),
                 size,
+                is_directory,
+                extension: extension_of(&name),
                 created,
                 modified,
-                is_directory,
+                accessed,
+                attributes,
+                link_count: file_link_count(&file_record, &mut fs),
+                reparse_tag: file_reparse_tag(&file_record, attributes, &mut fs),
             };
             
             // Send the file entry through the channel
@@ -1343,6 +2046,9 @@ impl MftCache {
             // Process subdirectories recursively
             if is_directory {
                 if let Err(e) = self.process_directory(ntfs, &file_record, Path::new(&full_path), sender) {
+                    if e.is::<Cancelled>() {
+                        return Err(e);
+                    }
                     warn!("Error processing subdirectory '{}': {}", full_path, e);
                     // Continue with next directory
                 }
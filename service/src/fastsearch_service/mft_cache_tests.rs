@@ -99,6 +99,7 @@ fn test_cache_persistence() {
     // Configure cache with persistence to temp dir
     let mut config = MftCacheConfig::default()
         .with_cache_dir(temp_dir.path())
+        .with_key_path(temp_dir.path().join("cache.key"))
         .with_save_interval(1); // 1 second for testing
     
     // Create and populate a cache
@@ -2,26 +2,167 @@
 
 // Re-export public API
 pub use crate::fastsearch_service::{
+    activity::{activity, render_heatmap, Activity, ActivityBucket, ActivityField, ActivityPeriod},
+    ads::{read_streams, stream_path, DataStream},
     cache_persistence,
+    cache_signing::{integrity_warnings, CacheKey},
+    cache_wal::{CacheWal, WalRecord},
+    camel::CamelPattern,
+    cancellation::{CancelRegistry, CancelToken, Cancelled, Deadline},
+    collections::{Bookmark, Collection, CollectionStore},
+    confirmation::{ConfirmationGate, CostEstimate, CONFIRM_HASH_BYTES, CONFIRM_TOKEN_ARG},
+    content_kind::{kind_by_extension, read_kind, sniff, ContentKind, ContentKindCache, ContentKindCheck, ContentKindFilter, SNIFF_LEN},
+    content_type::{confirms, detect, has_signature, read_header, ContentCheck, ContentTypeCache, Signature, HEADER_LEN, SIGNATURES},
+    denied_paths::{DeniedPath, DeniedPathStore, Denial, DENY_THRESHOLD},
+    density::{is_broad_count, Breakdown, BroadSummary, DensitySample},
+    dir_index::{DirectoryIndexer, MountConfig, WatchBackend, MOUNTS_ENV},
+    dir_sizes::{directory_sizes, DirSize, DirSizes},
+    doc_stats::{read_doc_stats, DocStats, DocStatsCache, DocStatsCheck, DocStatsFilter},
+    drive_config::{parse_drive_list, DriveConfig, DriveSelection},
+    duplicates::{disk_of, Disk, DuplicateCandidate, DuplicateGroup, DuplicateScan, HashProgress},
+    engine::{Engine, FoundFile, SearchRequest, SearchResults, Subscription},
+    envelope::ENVELOPE_SCHEMA_VERSION,
+    exclude::ExcludePatterns,
+    features::{enabled_features, has_feature, missing_feature, FEATURES},
+    file_info::{differences, stat, LiveStat},
     file_types::*,
+    filters::{AttributeFilters, SearchFilters, ATTRIBUTE_ARGS},
+    gitignore::{GitignoreFilter, IgnoreFile, IgnoreFileCache, IGNORE_FILES},
+    highlights::highlights,
+    hints::{DriveMatches, NoResultHints, PathFilterHint, SpellingTarget, Suggestion},
+    image_info::{read_image_info, ImageCheck, ImageFilter, ImageInfo, ImageInfoCache, IMAGE_EXTENSIONS},
+    index_export::{export_path, export_sqlite, ExportFormat, ExportSummary, EXPORTS_DIR, EXPORT_SCHEMA_VERSION},
+    inventory::{language_of, repo_inventory, RepoInventory},
+    ipc_frame::{
+        encode_frame, parse_request_frame, pipe_search_args, FRAME_HEADER_LEN, MSG_CANCEL, MSG_SEARCH, MSG_STATUS,
+        STATUS_ERROR, STATUS_OK, STATUS_PARTIAL,
+    },
+    json_store::{data_dir, named_file, JsonStore},
+    live_queries::{LiveChange, LiveQuery, LiveQueryRegistry, LiveUpdate, MAX_LIVE_QUERIES},
+    maintenance::{MaintenanceLock, MaintenanceState},
+    matcher::*,
     mcp_server::*,
-    mft_cache::{FileEntry, MftCache, MftCacheConfig, CacheStats},
+    media_info::{format_duration, read_media_info, MediaCheck, MediaFilter, MediaInfo, MediaInfoCache},
+    memory_profile::{HeapSize, MemoryProfile, SubsystemMemory},
+    metadata_cache::MetadataCache,
+    mft_cache::{file_attribute, ChangeListener, DriveRoot, FileEntry, MftCache, MftCacheConfig, CacheStats},
+    modes::{any_degraded, index_mode_status, DriveMode, IndexMode},
+    name_affixes::{NameAffix, NameAffixes},
+    notifications::{NotificationQueue, ProgressReporter},
+    operations::{DriveOperation, DriveOperations, OperationGuard, OperationInProgress, RunningOperation, OPERATION_IN_PROGRESS_CODE},
+    pagination::{PageCursor, SortKey},
+    path_glob::PathGlob,
+    pipeline::{CacheIndexes, CacheLocks, CacheStages, Prefilter, Scan, ScanSource, StageCounts, Verifier, VerifyStage, VolumeStats},
     ntfs_reader::*,
+    owner::{FileOwner, OwnerFilter, OwnerQuery},
+    projection::{FieldProjection, RESULT_FIELDS},
+    projects::{ProjectIndex, ProjectRoot, PROJECT_MARKERS},
+    query::{parse_size, Predicate, QueryExpr, QueryField, SizeComparison, QUERY_FIELDS},
+    query_limiter::{QueryLimiter, QueryPermit, QueueWait, MAX_CONCURRENT_SEARCHES_ENV},
+    reparse::{reparse_tag, ReparseKind, ReparseMode},
+    reports::{Report, ReportJob, ReportKind, ReportScheduler, REPORTS_DIR},
+    resource_limits::{contain_process, is_degraded, limit_status, ResourceLimits, CPU_LIMIT_ENV, MEMORY_LIMIT_ENV},
+    result_layout::{build_tree, group_by_folder, FolderGroup, FolderNode, ResultLayout},
+    result_sets::{ResultSet, ResultSetStore, MAX_RESULT_SETS, MAX_RESULT_SET_SIZE, RESULT_SET_IDLE},
+    saved_searches::{SavedSearch, SavedSearchStore, WatchEvaluator},
     search_engine::*,
-    usn_journal::UsnJournalMonitor,
-    web_api::*,
+    security_scan::{rule_for, security_scan, Finding, NameMatch, SecurityRule, Severity, SECURITY_RULES},
+    self_test::{record_self_test, self_test, self_test_failed, self_test_status, CanaryCheck, SelfTest, SYSTEM_CANARIES},
+    settings::{bundle_path, SettingsBundle, SETTINGS_DIR},
+    setup::{next_step, ServiceCheck, SetupStep},
+    similar::{names_within, DEFAULT_MAX_DISTANCE, MAX_DISTANCE_LIMIT},
+    size_distribution::{size_distribution, SizeBucket, SizeDistribution, SizeGrouping, SizeHistogram},
+    smart::{Interpretation, SmartGuess, SmartSearch, SMART_SEARCH_TYPE},
+    syntax_docs::{syntax_reference, SYNTAX_DOCS_URI},
+    trigrams::{trigram_index_enabled, TrigramIndex, TRIGRAM_INDEX_ENV},
+    usn_journal::{usn_buffer_memory, usn_queue_status, UsnChange, UsnJournalMonitor, UsnQueueMetrics},
+    volumes::{DriveAlias, DriveVolume, VolumeId, VolumeMap},
+    warm_queries::{WarmQuery, WarmQueryStore, MAX_WARM_QUERIES, WARM_PATTERNS},
 };
+#[cfg(feature = "web-api")]
+pub use crate::fastsearch_service::web_api::*;
 
 // Internal modules
+mod activity;
+mod ads;
 mod cache_persistence;
+mod cache_signing;
+mod cache_wal;
+mod camel;
+mod cancellation;
+mod collections;
+mod confirmation;
+mod content_kind;
+mod content_type;
+mod denied_paths;
+mod density;
+mod dir_index;
+mod dir_sizes;
+mod doc_stats;
+mod drive_config;
+mod duplicates;
+mod engine;
+mod envelope;
+pub mod etw;
+mod exclude;
+mod features;
+mod file_info;
 mod file_types;
+mod filters;
+mod gitignore;
+mod highlights;
+mod hints;
+mod image_info;
+mod index_export;
+mod inventory;
+mod ipc_frame;
+mod json_store;
+mod live_queries;
+mod maintenance;
+mod matcher;
 mod mcp_server;
+mod media_info;
+mod memory_profile;
+mod metadata_cache;
 mod mft_cache;
+mod modes;
+mod name_affixes;
+mod notifications;
+mod operations;
+mod pagination;
+mod path_glob;
+mod pipeline;
 mod ntfs_reader;
+mod owner;
+mod projection;
+mod projects;
+mod query;
+mod query_limiter;
+mod reparse;
+mod reports;
+mod resource_limits;
+mod result_layout;
+mod result_sets;
+mod saved_searches;
 mod search_engine;
+mod security_scan;
+mod self_test;
+mod settings;
+mod setup;
+mod similar;
+mod size_distribution;
+mod smart;
+mod syntax_docs;
+mod tool_schemas;
+mod trigrams;
 mod usn_journal;
+mod volumes;
+mod warm_queries;
+#[cfg(feature = "web-api")]
 mod web_api;
 
 // Only include tests in test builds
 #[cfg(test)]
 mod mft_cache_tests;
+#[cfg(test)]
+mod tool_snapshots;
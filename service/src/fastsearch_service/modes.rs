@@ -0,0 +1,263 @@
+//! Degraded modes and which one answers each drive
+//!
+//! Searches are fastest when the elevated service reads a drive's MFT into
+//! memory and follows its USN journal, but any piece of that can be missing.
+//! Instead of failing, a drive falls back to the next best source, and every
+//! tool response lists the mode of each drive in `_meta.index_modes`:
+//!
+//! | Missing       | Mode               | Results come from                                   |
+//! |---------------|--------------------|-----------------------------------------------------|
+//! | nothing       | `mft`              | MFT cache kept current from the USN journal         |
+//! | USN journal   | `periodic_refresh` | MFT cache rebuilt every few minutes                 |
+//! | admin rights  | `walk`             | directory walk kept current by change notifications |
+//! | the cache     | `direct_scan`      | the MFT, read again for every search                |
+//! | the service   | `bridge_fallback`  | the Python bridge walking folders itself            |
+//!
+//! Mounted directories (container mode) are reported as `mounted`; there
+//! that is the normal mode, not a degraded one.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::cancellation::Cancelled;
+use super::mft_cache::MftCache;
+
+/// How often a cache without USN journal is rebuilt
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How long a drive stays in direct scan mode before its cache is built again
+pub const CACHE_RETRY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Mode of every drive used so far, shared by the tool responses and the health checks
+static MODES: Mutex<BTreeMap<char, DriveMode>> = Mutex::new(BTreeMap::new());
+
+/// Where the results for a drive come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexMode {
+    /// MFT cache kept current from the USN journal
+    Mft,
+    /// MFT cache rebuilt every `REFRESH_INTERVAL`, the journal being unavailable
+    PeriodicRefresh,
+    /// Drive walked like a mounted directory, the volume being unreadable without admin rights
+    Walk,
+    /// MFT read for every search, the cache being impossible to build
+    DirectScan,
+    /// Mounted directory in container mode
+    Mounted,
+    /// Answered by the Python bridge without the service; only the bridge reports this
+    BridgeFallback,
+}
+
+impl IndexMode {
+    /// Name of the mode as reported in responses
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexMode::Mft => "mft",
+            IndexMode::PeriodicRefresh => "periodic_refresh",
+            IndexMode::Walk => "walk",
+            IndexMode::DirectScan => "direct_scan",
+            IndexMode::Mounted => "mounted",
+            IndexMode::BridgeFallback => "bridge_fallback",
+        }
+    }
+
+    /// Whether results may be slower, staler or less complete than usual
+    pub fn is_degraded(&self) -> bool {
+        !matches!(self, IndexMode::Mft | IndexMode::Mounted)
+    }
+
+    /// What the mode means for the results
+    pub fn describe(&self) -> &'static str {
+        match self {
+            IndexMode::Mft => "MFT cache, updated live",
+            IndexMode::PeriodicRefresh => "MFT cache refreshed every 10 minutes; recent changes may be missing",
+            IndexMode::Walk => "walked folders (no administrator rights); folders this account can't read are missing",
+            IndexMode::DirectScan => "direct MFT scan (no cache); searches are slower and only name, path and depth filters apply",
+            IndexMode::Mounted => "mounted directory index",
+            IndexMode::BridgeFallback => "bridge walking folders itself (service not running); slow and limited to the path given",
+        }
+    }
+}
+
+/// Mode a drive is in, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct DriveMode {
+    pub mode: IndexMode,
+    pub degraded: bool,
+    /// What failed, for degraded modes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// When the mode was selected (seconds since the UNIX epoch)
+    pub since: u64,
+}
+
+impl DriveMode {
+    pub fn new(mode: IndexMode, reason: Option<String>, now: SystemTime) -> Self {
+        Self {
+            mode,
+            degraded: mode.is_degraded(),
+            reason,
+            since: now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        }
+    }
+
+    /// How long the drive has been in this mode
+    pub fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(UNIX_EPOCH + Duration::from_secs(self.since)).unwrap_or_default()
+    }
+}
+
+/// Mode a drive falls back to when its MFT cache can't be built
+///
+/// Without admin rights the volume can't be opened at all, but its folders can
+/// still be walked; with them, the MFT is readable even if no cache fits.
+pub fn build_fallback(elevated: bool) -> IndexMode {
+    if elevated {
+        IndexMode::DirectScan
+    } else {
+        IndexMode::Walk
+    }
+}
+
+/// Record the mode a drive is in now
+pub fn set_mode(drive: char, mode: IndexMode, reason: Option<String>) {
+    if mode.is_degraded() {
+        warn!("Drive {}: is in {} mode ({})", drive, mode.as_str(), reason.as_deref().unwrap_or(mode.describe()));
+    } else {
+        info!("Drive {}: is in {} mode", drive, mode.as_str());
+    }
+    MODES.lock().insert(drive, DriveMode::new(mode, reason, SystemTime::now()));
+}
+
+/// Mode of a drive, if it has been used
+pub fn drive_mode(drive: char) -> Option<DriveMode> {
+    MODES.lock().get(&drive).cloned()
+}
+
+/// Forget the mode of a drive that is no longer indexed
+pub fn clear_mode(drive: char) {
+    MODES.lock().remove(&drive);
+}
+
+/// Whether any drive is in a degraded mode
+pub fn any_degraded() -> bool {
+    MODES.lock().values().any(|mode| mode.degraded)
+}
+
+/// Modes of all drives used so far, for the health checks
+pub fn index_mode_status() -> Value {
+    json!(*MODES.lock())
+}
+
+/// Report the drive modes in a tool response
+pub fn annotate_response(response: &mut Value) {
+    let modes = MODES.lock().clone();
+    annotate_with(response, &modes);
+}
+
+/// Add `_meta.index_modes` and, if any drive is degraded, a warning line to the text
+fn annotate_with(response: &mut Value, modes: &BTreeMap<char, DriveMode>) {
+    let Some(result) = response.get_mut("result").filter(|result| result.is_object()) else {
+        return; // Errors have nothing to annotate
+    };
+    if result.get("_meta").map_or(false, |meta| meta.get("index_modes").is_some()) {
+        return; // Already annotated, e.g. by a search run for another tool
+    }
+    let degraded: Vec<String> = modes.iter()
+        .filter(|(_, mode)| mode.degraded)
+        .map(|(drive, mode)| match &mode.reason {
+            Some(reason) => format!("{}: {} ({})", drive, mode.mode.describe(), reason),
+            None => format!("{}: {}", drive, mode.mode.describe()),
+        })
+        .collect();
+
+    result["_meta"]["index_modes"] = json!(modes);
+    result["_meta"]["degraded"] = json!(!degraded.is_empty());
+    if let Some(text) = result["content"][0]["text"].as_str().filter(|_| !degraded.is_empty()) {
+        let text = format!("{}\n⚠️ Degraded mode: {}", text, degraded.join("; "));
+        result["content"][0]["text"] = json!(text);
+    }
+}
+
+/// Rebuild a cache every `interval` for as long as it is in use
+///
+/// Takes the place of USN journal monitoring where the journal can't be read.
+pub fn start_periodic_refresh(cache: &Arc<MftCache>, interval: Duration) -> Result<()> {
+    let drive = cache.drive_letter();
+    let cache = Arc::downgrade(cache);
+    thread::Builder::new()
+        .name(format!("refresh-{}", drive))
+        .spawn(move || loop {
+            thread::sleep(interval);
+            // Stop once the drive has been dropped (e.g. excluded)
+            let Some(cache) = cache.upgrade() else {
+                break;
+            };
+            if cache.maintenance().is_active() {
+                info!("Skipping the refresh of drive {} while it is in maintenance", drive);
+                continue;
+            }
+            match cache.rebuild() {
+                Ok(()) => info!("Refreshed drive {}: ({} entries)", drive, cache.stats().file_count),
+                // The cache was retired or the service is stopping
+                Err(e) if e.is::<Cancelled>() => break,
+                Err(e) => error!("Failed to refresh drive {}: {}", drive, e),
+            }
+        })
+        .context("Failed to spawn cache refresh thread")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(text: &str) -> Value {
+        json!({"result": {"content": [{"type": "text", "text": text}], "results": []}})
+    }
+
+    #[test]
+    fn test_fallback_depends_on_rights() {
+        assert_eq!(build_fallback(false), IndexMode::Walk);
+        assert_eq!(build_fallback(true), IndexMode::DirectScan);
+        assert!(!IndexMode::Mounted.is_degraded());
+        assert!(IndexMode::PeriodicRefresh.is_degraded());
+    }
+
+    #[test]
+    fn test_annotate_response() {
+        let now = SystemTime::now();
+        let mut modes = BTreeMap::new();
+        modes.insert('C', DriveMode::new(IndexMode::Mft, None, now));
+
+        let mut healthy = response("Found 1 file");
+        annotate_with(&mut healthy, &modes);
+        assert_eq!(healthy["result"]["_meta"]["index_modes"]["C"]["mode"], "mft");
+        assert_eq!(healthy["result"]["_meta"]["degraded"], false);
+        assert_eq!(healthy["result"]["content"][0]["text"], "Found 1 file");
+
+        modes.insert('D', DriveMode::new(IndexMode::Walk, Some("access denied".to_string()), now));
+        let mut degraded = response("Found 1 file");
+        annotate_with(&mut degraded, &modes);
+        assert_eq!(degraded["result"]["_meta"]["degraded"], true);
+        assert_eq!(degraded["result"]["_meta"]["index_modes"]["D"]["reason"], "access denied");
+        let text = degraded["result"]["content"][0]["text"].as_str().unwrap().to_string();
+        assert!(text.ends_with(&format!("⚠️ Degraded mode: D: {} (access denied)", IndexMode::Walk.describe())));
+        annotate_with(&mut degraded, &modes);
+        assert_eq!(degraded["result"]["content"][0]["text"].as_str().unwrap(), text);
+
+        // Errors are left alone
+        let mut error = json!({"error": {"code": -32602, "message": "bad"}});
+        annotate_with(&mut error, &modes);
+        assert!(error.get("result").is_none());
+    }
+}
@@ -0,0 +1,191 @@
+//! Prefix and suffix indexes over the names of a cache
+//!
+//! Globs like `README*` and `*.dll` can't be answered by the name index,
+//! which is keyed by whole names, so they used to be matched against every
+//! entry of the drive. The distinct lowercased names are also kept here twice:
+//! in order, and spelled backwards in order. A literal start of a pattern is
+//! then a range of the first (`README*` is every name from "readme" up to the
+//! first that doesn't start with it) and a literal end a range of the second
+//! (`*.dll` is the names whose reverse starts with "lld."). Only the names in
+//! the range are matched against the pattern, which on a drive with a million
+//! names is a few hundred instead of a million.
+//!
+//! The sets follow the name index as entries come and go, at the cost of two
+//! more copies of each distinct name (see `cache.name_affixes` in the memory
+//! profile).
+
+use std::collections::{BTreeSet, HashSet};
+use std::mem::size_of;
+use std::ops::Bound;
+
+/// A literal start or end of the names a pattern matches, lowercased
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameAffix {
+    Prefix(String),
+    Suffix(String),
+}
+
+impl NameAffix {
+    /// The longer literal end of a glob, if it has one
+    ///
+    /// Only ASCII is taken, since lowercasing other characters can depend on
+    /// what comes next (a final sigma) and a range of lowercased names would
+    /// miss the other form.
+    pub fn of_glob(pattern: &str) -> Option<Self> {
+        let literal = |c: &char| c.is_ascii() && !matches!(c, '*' | '?' | '[' | ']' | '{' | '}');
+        let prefix: String = pattern.chars().take_while(literal).collect();
+        let mut suffix: Vec<char> = pattern.chars().rev().take_while(literal).collect();
+        suffix.reverse();
+        let suffix: String = suffix.into_iter().collect();
+        match (prefix.is_empty(), suffix.is_empty()) {
+            (true, true) => None,
+            _ if prefix.len() >= suffix.len() => Some(NameAffix::Prefix(prefix.to_ascii_lowercase())),
+            _ => Some(NameAffix::Suffix(suffix.to_ascii_lowercase())),
+        }
+    }
+}
+
+/// Distinct lowercased names of a cache, in order and reversed
+#[derive(Debug, Default)]
+pub struct NameAffixes {
+    sorted: BTreeSet<Box<str>>,
+    /// Each name spelled backwards
+    reversed: BTreeSet<Box<str>>,
+}
+
+impl NameAffixes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the keys of a name index
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut affixes = Self::new();
+        for name in names {
+            affixes.insert(name);
+        }
+        affixes
+    }
+
+    /// Add a lowercased name; names already indexed are left alone
+    pub fn insert(&mut self, name: &str) {
+        if self.sorted.insert(name.into()) {
+            self.reversed.insert(reverse(name).into());
+        }
+    }
+
+    /// Remove a name no entry has any more
+    pub fn remove(&mut self, name: &str) {
+        if self.sorted.remove(name) {
+            self.reversed.remove(reverse(name).as_str());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.sorted.clear();
+        self.reversed.clear();
+    }
+
+    /// Number of distinct names
+    pub fn len(&self) -> usize {
+        self.sorted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sorted.is_empty()
+    }
+
+    /// Names starting with `prefix` (lowercased), in order
+    pub fn with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.sorted
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .map(|name| &**name)
+            .take_while(move |name| name.starts_with(prefix))
+    }
+
+    /// Names ending with `suffix` (lowercased), in the order of their reverse
+    pub fn with_suffix<'a>(&'a self, suffix: &str) -> impl Iterator<Item = String> + 'a {
+        let reversed_suffix = reverse(suffix);
+        self.reversed
+            .range::<str, _>((Bound::Included(reversed_suffix.as_str()), Bound::Unbounded))
+            .take_while(move |reversed| reversed.starts_with(reversed_suffix.as_str()))
+            .map(|reversed| reverse(reversed))
+    }
+
+    /// Distinct names with any of the affixes
+    pub fn names_with(&self, affixes: &[NameAffix]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut names = Vec::new();
+        for affix in affixes {
+            let found: Box<dyn Iterator<Item = String>> = match affix {
+                NameAffix::Prefix(prefix) => Box::new(self.with_prefix(prefix).map(str::to_string)),
+                NameAffix::Suffix(suffix) => Box::new(self.with_suffix(suffix)),
+            };
+            for name in found {
+                if seen.insert(name.clone()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Heap memory of both sets, for the memory profile (tree nodes estimated per name)
+    pub fn heap_bytes(&self) -> u64 {
+        let text: usize = self.sorted.iter().map(|name| name.len()).sum();
+        (2 * (text + self.len() * 2 * size_of::<Box<str>>())) as u64
+    }
+}
+
+fn reverse(name: &str) -> String {
+    name.chars().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(names: &[&str]) -> NameAffixes {
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        NameAffixes::from_names(&names)
+    }
+
+    #[test]
+    fn test_glob_affixes() {
+        assert_eq!(NameAffix::of_glob("*.DLL"), Some(NameAffix::Suffix(".dll".into())));
+        assert_eq!(NameAffix::of_glob("README*"), Some(NameAffix::Prefix("readme".into())));
+        assert_eq!(NameAffix::of_glob("lib*.so"), Some(NameAffix::Prefix("lib".into())));
+        assert_eq!(NameAffix::of_glob("a*.tar.gz"), Some(NameAffix::Suffix(".tar.gz".into())));
+        assert_eq!(NameAffix::of_glob("[ab]*.{jpg,png}"), None);
+        assert_eq!(NameAffix::of_glob("*"), None);
+        // Up to the first character whose lowercase form could depend on its neighbours
+        assert_eq!(NameAffix::of_glob("*ΣΑΣ.txt"), Some(NameAffix::Suffix(".txt".into())));
+    }
+
+    #[test]
+    fn test_prefix_and_suffix_ranges() {
+        let names = index(&["readme.md", "readme", "read.me", "kernel32.dll", "user32.dll", "dll.txt", "a.dll.bak"]);
+        let prefixed: Vec<&str> = names.with_prefix("readme").collect();
+        assert_eq!(prefixed, vec!["readme", "readme.md"]);
+        let mut suffixed: Vec<String> = names.with_suffix(".dll").collect();
+        suffixed.sort();
+        assert_eq!(suffixed, vec!["kernel32.dll", "user32.dll"]);
+        assert_eq!(names.with_prefix("zzz").count(), 0);
+        assert_eq!(names.with_suffix("").count(), names.len());
+    }
+
+    #[test]
+    fn test_names_with_any_affix() {
+        let mut names = index(&["setup.exe", "setup.msi", "app.exe"]);
+        let mut found = names.names_with(&[NameAffix::Prefix("setup".into()), NameAffix::Suffix(".exe".into())]);
+        found.sort();
+        // setup.exe has both, and is listed once
+        assert_eq!(found, vec!["app.exe", "setup.exe", "setup.msi"]);
+
+        names.remove("setup.exe");
+        names.remove("missing");
+        assert_eq!(names.names_with(&[NameAffix::Suffix(".exe".into())]), vec!["app.exe"]);
+        names.insert("app.exe");
+        assert_eq!(names.len(), 2);
+    }
+}
@@ -0,0 +1,146 @@
+//! Queue of server-initiated MCP notifications
+//!
+//! Background work (USN monitoring, watches) and long-running requests
+//! (progress updates) push notifications here and the stdio transport drains
+//! and writes them as soon as they arrive.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::warn;
+use parking_lot::{Condvar, Mutex};
+use serde_json::{json, Value};
+
+/// Maximum number of undelivered notifications kept before dropping the oldest
+const MAX_PENDING_NOTIFICATIONS: usize = 1000;
+
+/// Bounded FIFO of pending JSON-RPC notifications
+#[derive(Debug, Default)]
+pub struct NotificationQueue {
+    pending: Mutex<VecDeque<Value>>,
+    arrived: Condvar,
+}
+
+impl NotificationQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue an MCP log message notification (`notifications/message`)
+    pub fn push_message(&self, level: &str, logger: &str, data: Value) {
+        self.push(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/message",
+            "params": {
+                "level": level,
+                "logger": logger,
+                "data": data
+            }
+        }));
+    }
+
+    /// Queue a raw JSON-RPC notification
+    pub fn push(&self, notification: Value) {
+        let mut pending = self.pending.lock();
+        if pending.len() >= MAX_PENDING_NOTIFICATIONS {
+            warn!("Notification queue full, dropping oldest notification");
+            pending.pop_front();
+        }
+        pending.push_back(notification);
+        self.arrived.notify_all();
+    }
+    
+    /// Block until a notification is pending or the timeout passes
+    ///
+    /// Returns whether notifications are pending.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let mut pending = self.pending.lock();
+        if pending.is_empty() {
+            self.arrived.wait_for(&mut pending, timeout);
+        }
+        !pending.is_empty()
+    }
+
+    /// Take all pending notifications in the order they were queued
+    pub fn drain(&self) -> Vec<Value> {
+        self.pending.lock().drain(..).collect()
+    }
+
+    /// Number of pending notifications
+    pub fn len(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    /// Whether there are no pending notifications
+    pub fn is_empty(&self) -> bool {
+        self.pending.lock().is_empty()
+    }
+}
+
+/// Sends `notifications/progress` for a request that asked for progress
+///
+/// Clients opt in by passing `_meta.progressToken` in the request params.
+#[derive(Debug, Clone)]
+pub struct ProgressReporter {
+    queue: Arc<NotificationQueue>,
+    token: Value,
+}
+
+impl ProgressReporter {
+    /// Reporter for `request`, or None if it carries no progress token
+    pub fn for_request(queue: &Arc<NotificationQueue>, request: &Value) -> Option<Self> {
+        let token = &request["params"]["_meta"]["progressToken"];
+        if !(token.is_string() || token.is_number()) {
+            return None;
+        }
+        Some(Self {
+            queue: Arc::clone(queue),
+            token: token.clone(),
+        })
+    }
+
+    /// Queue a progress update; `extra` fields are merged into the params
+    pub fn report(&self, progress: u64, total: Option<u64>, message: &str, extra: Value) {
+        let mut params = json!({
+            "progressToken": self.token,
+            "progress": progress,
+            "message": message
+        });
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        if let (Some(params), Value::Object(extra)) = (params.as_object_mut(), extra) {
+            params.extend(extra);
+        }
+        self.queue.push(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": params
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_requires_token() {
+        let queue = Arc::new(NotificationQueue::new());
+        assert!(ProgressReporter::for_request(&queue, &json!({"params": {}})).is_none());
+
+        let request = json!({"params": {"_meta": {"progressToken": "search-1"}}});
+        let reporter = ProgressReporter::for_request(&queue, &request).unwrap();
+        reporter.report(10, Some(100), "1 match so far", json!({"results": [{"name": "a.txt"}]}));
+
+        assert!(queue.wait(Duration::from_millis(1)));
+        let notification = queue.drain().remove(0);
+        assert_eq!(notification["method"], "notifications/progress");
+        assert_eq!(notification["params"]["progressToken"], "search-1");
+        assert_eq!(notification["params"]["total"], 100);
+        assert_eq!(notification["params"]["results"][0]["name"], "a.txt");
+        assert!(!queue.wait(Duration::from_millis(1)));
+    }
+}
@@ -12,6 +12,11 @@ use widestring::WideCString;
 use std::ffi::OsString;
 use std::os::windows::ffi::OsStringExt;
 
+use super::cancellation::Deadline;
+use super::exclude::ExcludePatterns;
+use super::filters::depth_below;
+use super::path_glob::PathGlob;
+
 #[derive(Debug, Clone)]
 pub struct FileEntry {
     pub name: String,
@@ -22,11 +27,43 @@ pub struct FileEntry {
     pub created: u64,
     pub modified: u64,
     pub accessed: u64,
+    /// NTFS file attribute flags
+    pub attributes: u32,
 }
 
 /// DIRECT MFT SEARCH - NO CACHING, NO INDEXING!
-#[cfg(windows)]
 pub fn search_files_direct(drive: &str, pattern: &str, path_filter: &str, max_results: usize) -> Result<Vec<FileEntry>> {
+    search_files_direct_excluding(drive, pattern, path_filter, &ExcludePatterns::default(), None, max_results)
+}
+
+/// Direct search that skips excluded entries; excluded folders are never opened
+///
+/// With `max_depth`, folders that deep below the path filter (or the drive
+/// root) aren't opened either.
+pub fn search_files_direct_excluding(
+    drive: &str,
+    pattern: &str,
+    path_filter: &str,
+    exclude: &ExcludePatterns,
+    max_depth: Option<usize>,
+    max_results: usize,
+) -> Result<Vec<FileEntry>> {
+    search_files_direct_until(drive, pattern, path_filter, exclude, max_depth, max_results, &Deadline::none())
+}
+
+/// Direct search that also stops once `deadline` passes, with what it found so far
+///
+/// Whether it stopped early is left in the deadline (`Deadline::was_hit`).
+#[cfg(windows)]
+pub fn search_files_direct_until(
+    drive: &str,
+    pattern: &str,
+    path_filter: &str,
+    exclude: &ExcludePatterns,
+    max_depth: Option<usize>,
+    max_results: usize,
+    deadline: &Deadline,
+) -> Result<Vec<FileEntry>> {
     let volume_path = format!("\\\\.\\{}:", drive.trim_end_matches(':'));
     info!("Direct MFT search: pattern='{}', path='{}', drive='{}'", pattern, path_filter, drive);
     
@@ -44,6 +81,7 @@ pub fn search_files_direct(drive: &str, pattern: &str, path_filter: &str, max_re
     // Convert pattern to regex for matching
     let pattern_regex = glob_to_regex(pattern)?;
     let path_filter_lower = path_filter.to_lowercase();
+    let path_glob = PathGlob::parse(&path_filter_lower)?;
     
     // Get the root directory and search
     let root = ntfs.root_directory(&mut file)
@@ -57,8 +95,12 @@ pub fn search_files_direct(drive: &str, pattern: &str, path_filter: &str, max_re
         "",
         &pattern_regex,
         &path_filter_lower,
+        path_glob.as_ref(),
+        exclude,
+        max_depth,
         &mut results,
         max_results,
+        deadline,
         &start_time
     )?;
     
@@ -77,17 +119,26 @@ fn search_directory_direct<T: Read + Seek>(
     current_path: &str,
     pattern_regex: &Regex,
     path_filter: &str,
+    path_glob: Option<&PathGlob>,
+    exclude: &ExcludePatterns,
+    max_depth: Option<usize>,
     results: &mut Vec<FileEntry>,
     max_results: usize,
+    deadline: &Deadline,
     start_time: &Instant,
 ) -> Result<()> {
-    // EARLY EXIT if we have enough results
-    if results.len() >= max_results {
+    // EARLY EXIT if we have enough results, or ran out of time
+    if results.len() >= max_results || deadline.passed() {
         return Ok(());
     }
     
     // Skip if this path doesn't match our filter
-    if !path_filter.is_empty() && !current_path.to_lowercase().contains(path_filter) {
+    if let Some(glob) = path_glob {
+        // Only folders on the way to (or below) a match of an anchored glob
+        if !glob.could_contain(current_path) {
+            return Ok(());
+        }
+    } else if !path_filter.is_empty() && !current_path.to_lowercase().contains(path_filter) {
         // Check if any subdirectory could match
         if !path_could_contain_filter(current_path, path_filter) {
             return Ok(());
@@ -102,8 +153,8 @@ fn search_directory_direct<T: Read + Seek>(
     let mut iter = index.entries();
     
     while let Some(entry) = iter.next(fs) {
-        // EARLY EXIT if we have enough results
-        if results.len() >= max_results {
+        // EARLY EXIT if we have enough results, or ran out of time
+        if results.len() >= max_results || deadline.passed() {
             break;
         }
         
@@ -128,6 +179,11 @@ fn search_directory_direct<T: Read + Seek>(
             format!("{}\\{}", current_path, file_name)
         };
         
+        // Skip excluded entries before reading their record, pruning excluded subtrees
+        if exclude.excludes(&full_path) {
+            continue;
+        }
+        
         let file_reference = entry.file_reference();
         let ntfs_file = match ntfs.file(fs, file_reference.file_record_number()) {
             Ok(file) => file,
@@ -135,11 +191,21 @@ fn search_directory_direct<T: Read + Seek>(
         };
         
         let is_directory = ntfs_file.directory_index(fs).is_ok();
+        let (depth, in_path) = match path_glob {
+            Some(glob) => {
+                let depth = glob.depth_below(&full_path);
+                (depth, depth.is_some())
+            }
+            None => (
+                depth_below(&full_path, path_filter),
+                path_filter.is_empty() || current_path.to_lowercase().contains(path_filter),
+            ),
+        };
         
         // CHECK IF THIS FILE MATCHES OUR PATTERN
         if pattern_regex.is_match(&file_name) {
             // Apply path filter
-            if path_filter.is_empty() || current_path.to_lowercase().contains(path_filter) {
+            if in_path && within_depth(depth, max_depth) {
                 
                 let size = if is_directory { 
                     0 
@@ -150,6 +216,7 @@ fn search_directory_direct<T: Read + Seek>(
                 
                 // Get timestamps - simplified to avoid API issues
                 let (created, modified, accessed) = (0, 0, 0);
+                let attributes = ntfs_file.info().map_or(0, |info| info.file_attributes().bits());
                 
                 let file_entry = FileEntry {
                     name: file_name.clone(),
@@ -160,6 +227,7 @@ fn search_directory_direct<T: Read + Seek>(
                     created,
                     modified,
                     accessed,
+                    attributes,
                 };
                 
                 results.push(file_entry);
@@ -171,11 +239,11 @@ fn search_directory_direct<T: Read + Seek>(
             }
         }
         
-        // RECURSIVELY SEARCH SUBDIRECTORIES (but with early exit)
-        if is_directory && results.len() < max_results {
+        // RECURSIVELY SEARCH SUBDIRECTORIES (but with early exit, and not past max_depth)
+        if is_directory && results.len() < max_results && may_descend(depth, max_depth) {
             if let Err(e) = search_directory_direct(
                 fs, ntfs, &ntfs_file, &full_path, 
-                pattern_regex, path_filter, results, max_results, start_time
+                pattern_regex, path_filter, path_glob, exclude, max_depth, results, max_results, deadline, start_time
             ) {
                 debug!("Failed to search directory {}: {}", full_path, e);
             }
@@ -185,6 +253,25 @@ fn search_directory_direct<T: Read + Seek>(
     Ok(())
 }
 
+/// Whether an entry at `depth` below the path filter is shallow enough to return
+fn within_depth(depth: Option<usize>, max_depth: Option<usize>) -> bool {
+    match (depth, max_depth) {
+        (_, None) => true,
+        (Some(depth), Some(max_depth)) => depth <= max_depth,
+        (None, Some(_)) => false,
+    }
+}
+
+/// Whether a folder at `depth` can have children within `max_depth`
+///
+/// Folders above the path filter (where it doesn't match yet) are always searched.
+fn may_descend(depth: Option<usize>, max_depth: Option<usize>) -> bool {
+    match (depth, max_depth) {
+        (Some(depth), Some(max_depth)) => depth < max_depth,
+        _ => true,
+    }
+}
+
 /// Convert glob pattern to regex
 fn glob_to_regex(pattern: &str) -> Result<Regex> {
     let mut regex_pattern = String::new();
@@ -225,7 +312,15 @@ fn path_could_contain_filter(current_path: &str, filter: &str) -> bool {
 
 /// NON-WINDOWS FALLBACK - DIRECT FILESYSTEM SEARCH
 #[cfg(not(windows))]
-pub fn search_files_direct(_drive: &str, pattern: &str, path_filter: &str, max_results: usize) -> Result<Vec<FileEntry>> {
+pub fn search_files_direct_until(
+    _drive: &str,
+    pattern: &str,
+    path_filter: &str,
+    exclude: &ExcludePatterns,
+    max_depth: Option<usize>,
+    max_results: usize,
+    deadline: &Deadline,
+) -> Result<Vec<FileEntry>> {
     use std::path::Path;
     use std::fs;
     
@@ -233,8 +328,11 @@ pub fn search_files_direct(_drive: &str, pattern: &str, path_filter: &str, max_r
     let mut results = Vec::new();
     let pattern_regex = glob_to_regex(pattern)?;
     
+    let path_glob = PathGlob::parse(path_filter)?;
+    
     let root_path = format!("{}:/", _drive.trim_end_matches(':'));
-    search_filesystem_direct(Path::new(&root_path), &pattern_regex, path_filter, &mut results, max_results)?;
+    let root = Path::new(&root_path);
+    search_filesystem_direct(root, root, &pattern_regex, path_filter, path_glob.as_ref(), exclude, max_depth, &mut results, max_results, deadline)?;
     
     let elapsed = start_time.elapsed();
     info!("Direct filesystem search completed: {} results in {:?}", results.len(), elapsed);
@@ -244,13 +342,18 @@ pub fn search_files_direct(_drive: &str, pattern: &str, path_filter: &str, max_r
 
 #[cfg(not(windows))]
 fn search_filesystem_direct(
+    root: &std::path::Path,
     dir: &std::path::Path,
     pattern_regex: &Regex,
     path_filter: &str,
+    path_glob: Option<&PathGlob>,
+    exclude: &ExcludePatterns,
+    max_depth: Option<usize>,
     results: &mut Vec<FileEntry>,
     max_results: usize,
+    deadline: &Deadline,
 ) -> Result<()> {
-    if results.len() >= max_results {
+    if results.len() >= max_results || deadline.passed() {
         return Ok(());
     }
     
@@ -260,7 +363,7 @@ fn search_filesystem_direct(
     };
     
     for entry in entries {
-        if results.len() >= max_results {
+        if results.len() >= max_results || deadline.passed() {
             break;
         }
         
@@ -271,6 +374,17 @@ fn search_filesystem_direct(
         
         let file_name = entry.file_name().to_string_lossy().to_string();
         let path = entry.path();
+        
+        // Skip excluded entries, pruning excluded subtrees
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('/', "\\");
+        if exclude.excludes(&relative) {
+            continue;
+        }
+        let depth = match path_glob {
+            Some(glob) => glob.depth_below(&relative),
+            None => depth_below(&relative, &path_filter.to_lowercase()),
+        };
+        
         let metadata = match entry.metadata() {
             Ok(metadata) => metadata,
             Err(_) => continue,
@@ -281,7 +395,12 @@ fn search_filesystem_direct(
             let current_path = path.parent().unwrap_or(std::path::Path::new("")).to_string_lossy().to_string();
             
             // Apply path filter
-            if path_filter.is_empty() || current_path.to_lowercase().contains(&path_filter.to_lowercase()) {
+            let in_path = match path_glob {
+                Some(_) => depth.is_some(),
+                None => path_filter.is_empty() || current_path.to_lowercase().contains(&path_filter.to_lowercase()),
+            };
+            if in_path && within_depth(depth, max_depth)
+            {
                 let file_entry = FileEntry {
                     name: file_name,
                     path: current_path,
@@ -297,6 +416,7 @@ fn search_filesystem_direct(
                     accessed: metadata.accessed().unwrap_or(std::time::SystemTime::UNIX_EPOCH)
                         .duration_since(std::time::SystemTime::UNIX_EPOCH)
                         .unwrap_or_default().as_secs(),
+                    attributes: if metadata.permissions().readonly() { 0x1 } else { 0 },
                 };
                 
                 results.push(file_entry);
@@ -304,8 +424,9 @@ fn search_filesystem_direct(
         }
         
         // Recursively search subdirectories
-        if metadata.is_dir() && results.len() < max_results {
-            let _ = search_filesystem_direct(&path, pattern_regex, path_filter, results, max_results);
+        let on_the_way = path_glob.map_or(true, |glob| glob.could_contain(&relative));
+        if metadata.is_dir() && on_the_way && results.len() < max_results && may_descend(depth, max_depth) {
+            let _ = search_filesystem_direct(root, &path, pattern_regex, path_filter, path_glob, exclude, max_depth, results, max_results, deadline);
         }
     }
     
@@ -370,7 +491,14 @@ pub fn get_ntfs_drives() -> Result<Vec<String>> {
 
 /// Search multiple NTFS drives
 #[cfg(windows)]
-pub fn search_multiple_drives(drives: &[String], pattern: &str, path_filter: &str, max_results: usize) -> Result<Vec<FileEntry>> {
+pub fn search_multiple_drives(
+    drives: &[String],
+    pattern: &str,
+    path_filter: &str,
+    exclude: &ExcludePatterns,
+    max_depth: Option<usize>,
+    max_results: usize,
+) -> Result<Vec<FileEntry>> {
     let mut all_results = Vec::new();
     let mut remaining_results = max_results;
     
@@ -379,7 +507,7 @@ pub fn search_multiple_drives(drives: &[String], pattern: &str, path_filter: &st
             break;
         }
         
-        match search_files_direct(drive, pattern, path_filter, remaining_results) {
+        match search_files_direct_excluding(drive, pattern, path_filter, exclude, max_depth, remaining_results) {
             Ok(mut results) => {
                 let len = results.len();
                 all_results.append(&mut results);
@@ -412,22 +540,39 @@ pub fn read_mft_files(_drive: &str) -> Result<Vec<FileEntry>> {
     Err(anyhow::anyhow!("NTFS MFT reading is only supported on Windows"))
 }
 
+/// One pattern of the direct search benchmark
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BenchmarkRun {
+    pub pattern: String,
+    pub files: usize,
+    pub duration_ms: f64,
+    pub files_per_ms: f64,
+}
+
 /// Benchmark function
 #[cfg(windows)]
-pub fn benchmark_mft_performance(drive: &str) -> Result<()> {
+pub fn benchmark_mft_performance(drive: &str) -> Result<Vec<BenchmarkRun>> {
     info!("Starting DIRECT MFT search benchmark for drive {}", drive);
     
     let patterns = vec!["*.txt", "*.exe", "*.dll", "*.js", "*.log"];
+    let mut runs = Vec::with_capacity(patterns.len());
     
     for pattern in patterns {
         let start = Instant::now();
         let results = search_files_direct(drive, pattern, "", 1000)?;
         let duration = start.elapsed();
         
+        let duration_ms = duration.as_secs_f64() * 1000.0;
         println!("Pattern '{}': {} files in {:?} ({:.2} files/ms)", 
                  pattern, results.len(), duration, 
-                 results.len() as f64 / duration.as_millis() as f64);
+                 results.len() as f64 / duration_ms);
+        runs.push(BenchmarkRun {
+            pattern: pattern.to_string(),
+            files: results.len(),
+            duration_ms,
+            files_per_ms: if duration_ms > 0.0 { results.len() as f64 / duration_ms } else { 0.0 },
+        });
     }
     
-    Ok(())
+    Ok(runs)
 }
@@ -0,0 +1,159 @@
+//! Operations on a drive's cache that can't run side by side
+//!
+//! Searches and reports only read the cache and run alongside anything. A few
+//! operations change the cache or need it to hold still, and those take turns
+//! per drive:
+//!
+//! | Operation | Started by | Why it can't overlap |
+//! |-----------|------------|----------------------|
+//! | `rebuild` | periodic refresh, USN resync | clears the cache and fills it again |
+//! | `maintenance` | `maintenance_mode` start/end | holds or releases background updates |
+//! | `export` | `export_index` | writes a copy of the cache, which must not change under it |
+//!
+//! While one of them runs on a drive, starting another one on the same drive
+//! fails at once with an "operation in progress" error naming the operation
+//! that holds the drive, instead of waiting behind it; other drives aren't
+//! affected. A maintenance period that has begun doesn't hold the drive (it
+//! holds back rebuilds by itself), only beginning or ending it does.
+
+use std::fmt;
+use std::time::SystemTime;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// JSON-RPC error code of "operation in progress" errors
+pub const OPERATION_IN_PROGRESS_CODE: i64 = -32002;
+
+/// An operation that needs a drive to itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriveOperation {
+    Rebuild,
+    Maintenance,
+    Export,
+}
+
+impl DriveOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DriveOperation::Rebuild => "rebuild",
+            DriveOperation::Maintenance => "maintenance",
+            DriveOperation::Export => "export",
+        }
+    }
+}
+
+/// The operation holding a drive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunningOperation {
+    pub operation: DriveOperation,
+    /// When it started (UNIX timestamp)
+    pub since: u64,
+    /// What started it, such as `usn` or `export_index`
+    pub started_by: String,
+}
+
+/// Refusal to start an operation while another one holds the drive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationInProgress {
+    pub drive: char,
+    /// The operation that was refused
+    pub wanted: DriveOperation,
+    /// The operation holding the drive
+    pub running: RunningOperation,
+}
+
+impl fmt::Display for OperationInProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "Drive {}: can't start {} while a {} is in progress (started by {}); try again once it has finished",
+            self.drive, self.wanted.as_str(), self.running.operation.as_str(), self.running.started_by
+        )
+    }
+}
+
+impl std::error::Error for OperationInProgress {}
+
+impl OperationInProgress {
+    /// The error as a tool response, with what holds the drive in `data`
+    pub fn to_response(&self) -> Value {
+        json!({
+            "error": {
+                "code": OPERATION_IN_PROGRESS_CODE,
+                "message": self.to_string(),
+                "data": {
+                    "operation_in_progress": {
+                        "drive": self.drive.to_string(),
+                        "wanted": self.wanted,
+                        "running": self.running
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// The exclusive operation running on a drive, shared by every copy of its cache
+#[derive(Debug, Default)]
+pub struct DriveOperations {
+    running: Mutex<Option<RunningOperation>>,
+}
+
+impl DriveOperations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start an operation, holding the drive until the returned guard is dropped
+    pub fn begin(&self, drive: char, operation: DriveOperation, started_by: &str) -> Result<OperationGuard<'_>, OperationInProgress> {
+        let mut running = self.running.lock();
+        if let Some(running) = running.as_ref() {
+            return Err(OperationInProgress { drive, wanted: operation, running: running.clone() });
+        }
+        let since = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        *running = Some(RunningOperation { operation, since, started_by: started_by.to_string() });
+        Ok(OperationGuard { operations: self })
+    }
+
+    /// The operation holding the drive, if any
+    pub fn running(&self) -> Option<RunningOperation> {
+        self.running.lock().clone()
+    }
+}
+
+/// A started operation; lets the drive go when dropped
+#[derive(Debug)]
+pub struct OperationGuard<'a> {
+    operations: &'a DriveOperations,
+}
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        self.operations.running.lock().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_operation_at_a_time() {
+        let operations = DriveOperations::new();
+        let export = operations.begin('C', DriveOperation::Export, "export_index").unwrap();
+        assert_eq!(operations.running().unwrap().operation, DriveOperation::Export);
+
+        let refused = operations.begin('C', DriveOperation::Rebuild, "usn").unwrap_err();
+        assert_eq!(refused.running.started_by, "export_index");
+        let response = refused.to_response();
+        assert_eq!(response["error"]["code"], OPERATION_IN_PROGRESS_CODE);
+        assert_eq!(response["error"]["data"]["operation_in_progress"]["running"]["operation"], "export");
+        assert_eq!(response["error"]["data"]["operation_in_progress"]["wanted"], "rebuild");
+
+        drop(export);
+        assert!(operations.running().is_none());
+        assert!(operations.begin('C', DriveOperation::Maintenance, "maintenance_mode").is_ok());
+    }
+}
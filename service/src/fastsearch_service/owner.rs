@@ -0,0 +1,270 @@
+//! File owner lookups for the `owner` filter of `fast_search`
+//!
+//! The MFT cache doesn't keep security descriptors, so the owner of a match is
+//! read from disk in the verify stage, after every cache-answered filter has
+//! narrowed the candidates down. On Windows the owner is the SID from the
+//! file's security descriptor, resolved to `DOMAIN\user` once per SID; in
+//! directory indexes on other systems it is the numeric uid and its user name.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use super::mft_cache::{disk_path, FileEntry};
+
+/// Owner of a file on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileOwner {
+    /// String SID (`S-1-5-21-...`), or the uid outside Windows
+    pub sid: String,
+    /// `DOMAIN\user` (or the user name outside Windows), if the SID resolves
+    pub account: Option<String>,
+}
+
+/// Owner a search asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnerQuery {
+    /// A string SID
+    Sid(String),
+    /// A qualified account name (`DOMAIN\user`, `BUILTIN\Administrators`)
+    Account(String),
+    /// A bare user name in any domain, or a uid outside Windows
+    User(String),
+}
+
+impl OwnerQuery {
+    /// Parse the `owner` argument, if given
+    pub fn from_args(args: &Value) -> Result<Option<Self>> {
+        match &args["owner"] {
+            Value::Null => Ok(None),
+            Value::String(owner) => Self::parse(owner).map(Some),
+            _ => Err(anyhow!("'owner' must be a user name, DOMAIN\\user or SID")),
+        }
+    }
+
+    /// Parse a user name, `DOMAIN\user` or SID
+    pub fn parse(owner: &str) -> Result<Self> {
+        let owner = owner.trim();
+        if owner.is_empty() {
+            return Err(anyhow!("'owner' must not be empty"));
+        }
+        Ok(if owner.get(..4).map_or(false, |prefix| prefix.eq_ignore_ascii_case("S-1-")) {
+            OwnerQuery::Sid(owner.to_string())
+        } else if owner.contains('\\') {
+            OwnerQuery::Account(owner.to_string())
+        } else {
+            OwnerQuery::User(owner.to_string())
+        })
+    }
+
+    /// Whether a file owner is the one asked for (account names ignore case)
+    pub fn matches(&self, owner: &FileOwner) -> bool {
+        match self {
+            OwnerQuery::Sid(sid) => owner.sid.eq_ignore_ascii_case(sid),
+            OwnerQuery::Account(account) => owner.account.as_ref().map_or(false, |a| a.eq_ignore_ascii_case(account)),
+            OwnerQuery::User(user) => {
+                owner.sid == *user
+                    || owner.account.as_ref().map_or(false, |account| {
+                        account.rsplit('\\').next().unwrap_or(account).eq_ignore_ascii_case(user)
+                    })
+            }
+        }
+    }
+}
+
+/// Owner check for the matches on one drive
+///
+/// Account names are looked up once per SID for the lifetime of the filter.
+pub struct OwnerFilter {
+    query: OwnerQuery,
+    root: PathBuf,
+    accounts: RefCell<HashMap<String, Option<String>>>,
+}
+
+impl OwnerFilter {
+    /// Filter for a drive whose files live below `root` on disk
+    pub fn new(query: OwnerQuery, root: PathBuf) -> Self {
+        Self { query, root, accounts: RefCell::new(HashMap::new()) }
+    }
+
+    /// Whether a cache entry is owned by the requested owner
+    ///
+    /// Entries whose owner can't be read (deleted, access denied) don't match.
+    pub fn is_owned(&self, file: &FileEntry) -> bool {
+        read_owner(&disk_path(&self.root, &file.path), &mut self.accounts.borrow_mut())
+            .map_or(false, |owner| self.query.matches(&owner))
+    }
+}
+
+/// Read the owner SID of a file and resolve its account name
+#[cfg(windows)]
+fn read_owner(path: &Path, accounts: &mut HashMap<String, Option<String>>) -> Option<FileOwner> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+    use winapi::shared::winerror::ERROR_SUCCESS;
+    use winapi::um::accctrl::SE_FILE_OBJECT;
+    use winapi::um::aclapi::GetNamedSecurityInfoW;
+    use winapi::um::winbase::LocalFree;
+    use winapi::um::winnt::{OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut owner: PSID = null_mut();
+    let mut descriptor: PSECURITY_DESCRIPTOR = null_mut();
+    let status = unsafe {
+        GetNamedSecurityInfoW(
+            wide.as_ptr(),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION,
+            &mut owner,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            &mut descriptor,
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+
+    // `owner` points into the descriptor, so resolve it before freeing that
+    let resolved = if owner.is_null() {
+        None
+    } else {
+        unsafe { sid_string(owner) }.map(|sid| {
+            let account = accounts
+                .entry(sid.clone())
+                .or_insert_with(|| unsafe { account_name(owner) })
+                .clone();
+            FileOwner { sid, account }
+        })
+    };
+    unsafe { LocalFree(descriptor) };
+    resolved
+}
+
+/// `S-1-...` form of a SID
+#[cfg(windows)]
+unsafe fn sid_string(sid: winapi::um::winnt::PSID) -> Option<String> {
+    use winapi::um::sddl::ConvertSidToStringSidW;
+    use winapi::um::winbase::LocalFree;
+
+    let mut raw = std::ptr::null_mut();
+    if ConvertSidToStringSidW(sid, &mut raw) == 0 {
+        return None;
+    }
+    let len = (0..).take_while(|&i| *raw.offset(i) != 0).count();
+    let sid = String::from_utf16_lossy(std::slice::from_raw_parts(raw, len));
+    LocalFree(raw as _);
+    Some(sid)
+}
+
+/// `DOMAIN\user` for a SID, if it belongs to a known account
+#[cfg(windows)]
+unsafe fn account_name(sid: winapi::um::winnt::PSID) -> Option<String> {
+    use winapi::um::winbase::LookupAccountSidW;
+
+    let mut name = [0u16; 256];
+    let mut name_len = name.len() as u32;
+    let mut domain = [0u16; 256];
+    let mut domain_len = domain.len() as u32;
+    let mut kind = 0;
+    let found = LookupAccountSidW(
+        std::ptr::null(),
+        sid,
+        name.as_mut_ptr(),
+        &mut name_len,
+        domain.as_mut_ptr(),
+        &mut domain_len,
+        &mut kind,
+    );
+    if found == 0 {
+        return None;
+    }
+    let name = String::from_utf16_lossy(&name[..name_len as usize]);
+    let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+    Some(if domain.is_empty() { name } else { format!("{}\\{}", domain, name) })
+}
+
+/// Read the owning uid of a file and resolve its user name
+#[cfg(unix)]
+fn read_owner(path: &Path, accounts: &mut HashMap<String, Option<String>>) -> Option<FileOwner> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = path.symlink_metadata().ok()?.uid();
+    let sid = uid.to_string();
+    let account = accounts.entry(sid.clone()).or_insert_with(|| user_name(uid)).clone();
+    Some(FileOwner { sid, account })
+}
+
+/// User name of a uid from /etc/passwd
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let id: u32 = fields.nth(1)?.parse().ok()?;
+        (id == uid).then(|| name.to_string())
+    })
+}
+
+#[cfg(not(any(windows, unix)))]
+fn read_owner(_path: &Path, _accounts: &mut HashMap<String, Option<String>>) -> Option<FileOwner> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn owner(sid: &str, account: Option<&str>) -> FileOwner {
+        FileOwner { sid: sid.to_string(), account: account.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_parse_owner_query() {
+        assert_eq!(OwnerQuery::parse(" alice ").unwrap(), OwnerQuery::User("alice".to_string()));
+        assert_eq!(OwnerQuery::parse("CORP\\alice").unwrap(), OwnerQuery::Account("CORP\\alice".to_string()));
+        assert_eq!(OwnerQuery::parse("S-1-5-18").unwrap(), OwnerQuery::Sid("S-1-5-18".to_string()));
+        assert!(OwnerQuery::parse("  ").is_err());
+        assert!(OwnerQuery::from_args(&json!({})).unwrap().is_none());
+        assert!(OwnerQuery::from_args(&json!({"owner": 1000})).is_err());
+    }
+
+    #[test]
+    fn test_owner_matching() {
+        let alice = owner("S-1-5-21-1-2-3-1001", Some("CORP\\Alice"));
+        assert!(OwnerQuery::parse("alice").unwrap().matches(&alice));
+        assert!(OwnerQuery::parse("corp\\alice").unwrap().matches(&alice));
+        assert!(OwnerQuery::parse("s-1-5-21-1-2-3-1001").unwrap().matches(&alice));
+        assert!(!OwnerQuery::parse("OTHER\\alice").unwrap().matches(&alice));
+        assert!(!OwnerQuery::parse("bob").unwrap().matches(&alice));
+
+        // Unresolved SIDs only match by SID
+        let orphan = owner("S-1-5-21-1-2-3-1002", None);
+        assert!(!OwnerQuery::parse("alice").unwrap().matches(&orphan));
+        assert!(OwnerQuery::parse("1000").unwrap().matches(&owner("1000", None)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_filter_reads_owner_from_disk() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("mine.txt"), "x").unwrap();
+        let uid = dir.path().join("mine.txt").metadata().unwrap().uid();
+        let entry = |path: &str| FileEntry { size: 1, ..FileEntry::test(1, path) };
+
+        let mine = OwnerFilter::new(OwnerQuery::User(uid.to_string()), dir.path().to_path_buf());
+        assert!(mine.is_owned(&entry("mine.txt")));
+        assert!(!mine.is_owned(&entry("gone.txt")));
+
+        let other = OwnerFilter::new(OwnerQuery::User((uid + 1).to_string()), dir.path().to_path_buf());
+        assert!(!other.is_owned(&entry("mine.txt")));
+    }
+}
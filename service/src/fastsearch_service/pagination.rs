@@ -0,0 +1,311 @@
+//! Cursor-based paging of `fast_search` results
+//!
+//! Matches are returned in a fixed order (results inside the `workspace_hint`
+//! folder first, then best `match_score` first for scored searches, then drive,
+//! path and file id), and a cursor records the sort key of the
+//! last result handed out. The next page continues strictly after that key, so
+//! paging stays deterministic even while files are added or removed between
+//! calls: nothing already returned is repeated and nothing before the cursor
+//! shifts into the next page.
+//!
+//! Cursors are opaque hex strings; they are bound to the drive and the search
+//! arguments they were issued for and are rejected for any other search.
+
+use std::cmp::Ordering;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::mft_cache::FileEntry;
+
+/// Cursor format version
+const CURSOR_VERSION: u32 = 1;
+
+/// Arguments that don't change which files match, so they're ignored when
+/// binding a cursor to its search. `auto_fuzzy` only ever applies to a first
+/// page, so a "did you mean" page can be continued as a plain fuzzy search.
+const PAGING_ARGS: &[&str] = &["cursor", "max_results", "auto_fuzzy"];
+
+/// Position of the last result of a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageCursor {
+    /// Format version
+    v: u32,
+    /// Drive the search ran on
+    pub drive: char,
+    /// Fingerprint of the search arguments
+    pub search: u64,
+    /// Sort key of the last returned result
+    pub last: SortKey,
+}
+
+/// Position of a result in the page order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortKey {
+    /// Inside the workspace folder (sorts first)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub in_workspace: bool,
+    /// Match score (scored searches only; higher sorts first)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Drive the result is reported under
+    pub drive: char,
+    /// Path relative to the drive root
+    pub path: String,
+    /// File record number, breaking ties between equal paths
+    pub id: u64,
+}
+
+impl SortKey {
+    /// Sort key of a result
+    pub fn of(item: PageItem<'_>) -> Self {
+        let (drive, file, score, in_workspace) = item;
+        Self {
+            in_workspace,
+            score,
+            drive,
+            path: file.path.clone(),
+            id: file.id,
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A match as seen by the pager: reported drive, file, optional score and
+/// whether it is inside the workspace folder
+pub type PageItem<'a> = (char, &'a FileEntry, Option<f64>, bool);
+
+/// Compare two results in page order without allocating keys
+pub fn compare(a: PageItem<'_>, b: PageItem<'_>) -> Ordering {
+    let by_score = match (a.2, b.2) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        _ => Ordering::Equal,
+    };
+    b.3.cmp(&a.3)
+        .then(by_score)
+        .then_with(|| a.0.cmp(&b.0))
+        .then_with(|| a.1.path.cmp(&b.1.path))
+        .then_with(|| a.1.id.cmp(&b.1.id))
+}
+
+/// Whether a result comes strictly after the cursor position
+pub fn is_after(item: PageItem<'_>, last: &SortKey) -> bool {
+    let (drive, file, score, in_workspace) = item;
+    let by_score = match (score, last.score) {
+        (Some(score), Some(last)) => last.total_cmp(&score),
+        _ => Ordering::Equal,
+    };
+    last.in_workspace.cmp(&in_workspace)
+        .then(by_score)
+        .then_with(|| drive.cmp(&last.drive))
+        .then_with(|| file.path.as_str().cmp(&last.path))
+        .then_with(|| file.id.cmp(&last.id))
+        == Ordering::Greater
+}
+
+/// Whether a path (relative to the drive root) is the folder or below it
+///
+/// Paths ignore case, like NTFS; an empty folder is the whole drive.
+pub fn is_in_folder(path: &str, folder: &str) -> bool {
+    if folder.is_empty() {
+        return true;
+    }
+    match path.get(..folder.len()) {
+        Some(head) if head.eq_ignore_ascii_case(folder) => {
+            path.len() == folder.len() || path[folder.len()..].starts_with('\\')
+        }
+        _ => false,
+    }
+}
+
+/// Take the first `limit` results in page order, sorted
+///
+/// Returns the page and whether more results follow it.
+pub fn take_page<'a, T, F>(mut items: Vec<T>, limit: usize, key: F) -> (Vec<T>, bool)
+where
+    F: Fn(&T) -> PageItem<'a>,
+{
+    let rest = split_page(&mut items, limit, &key);
+    (items, !rest.is_empty())
+}
+
+/// Like `take_page`, but only results passing `accept` make it into the page
+///
+/// `accept` runs in page order and only until the page is full, so expensive
+/// checks cost about one page of work. Results after the page aren't checked,
+/// which means the last page can come out shorter than `limit` (or empty).
+pub fn take_page_verified<'a, T, F, A>(mut items: Vec<T>, limit: usize, key: F, mut accept: A) -> (Vec<T>, bool)
+where
+    F: Fn(&T) -> PageItem<'a>,
+    A: FnMut(&T) -> bool,
+{
+    let mut page = Vec::with_capacity(limit.min(items.len()));
+    while page.len() < limit && !items.is_empty() {
+        // Each batch sorts after everything before it, so the page stays in order
+        let rest = split_page(&mut items, limit - page.len(), &key);
+        page.extend(items.into_iter().filter(|item| accept(item)));
+        items = rest;
+    }
+    (page, !items.is_empty())
+}
+
+/// Keep the first `limit` results in `items`, sorted, and return the rest unsorted
+fn split_page<'a, T, F>(items: &mut Vec<T>, limit: usize, key: &F) -> Vec<T>
+where
+    F: Fn(&T) -> PageItem<'a>,
+{
+    let cmp = |a: &T, b: &T| compare(key(a), key(b));
+
+    if items.len() > limit && limit > 0 {
+        // Partition around the page boundary so only the page itself gets sorted
+        items.select_nth_unstable_by(limit - 1, cmp);
+    }
+    let rest = items.split_off(limit.min(items.len()));
+    items.sort_unstable_by(cmp);
+    rest
+}
+
+impl PageCursor {
+    /// Cursor continuing after `last` for the given search
+    pub fn new(drive: char, args: &Value, last: SortKey) -> Self {
+        Self {
+            v: CURSOR_VERSION,
+            drive,
+            search: search_fingerprint(args),
+            last,
+        }
+    }
+
+    /// Encode as an opaque token
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        json.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Decode a token and check it belongs to this search
+    pub fn decode(token: &str, drive: char, args: &Value) -> Result<Self> {
+        if token.len() % 2 != 0 || !token.is_ascii() {
+            bail!("Malformed cursor");
+        }
+        let bytes = (0..token.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&token[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .context("Malformed cursor")?;
+        let cursor: PageCursor = serde_json::from_slice(&bytes).context("Malformed cursor")?;
+
+        if cursor.v != CURSOR_VERSION {
+            bail!("Cursor is from an unsupported version");
+        }
+        if cursor.drive != drive || cursor.search != search_fingerprint(args) {
+            bail!("Cursor was issued for a different search; repeat the original arguments when paging");
+        }
+        Ok(cursor)
+    }
+}
+
+/// Stable hash of the arguments that decide which files match
+///
+/// serde_json objects keep their keys sorted, so equal arguments always
+/// serialize (and hash) the same way.
+pub fn search_fingerprint(args: &Value) -> u64 {
+    let mut args = args.clone();
+    if let Some(map) = args.as_object_mut() {
+        for key in PAGING_ARGS {
+            map.remove(*key);
+        }
+    }
+
+    // FNV-1a, which unlike DefaultHasher is stable across builds
+    args.to_string().bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let args = json!({"pattern": "*.rs", "drive": "C", "max_results": 10});
+        let cursor = PageCursor::new('C', &args, SortKey::of(('C', &FileEntry::test(7, "src\\main.rs"), None, false)));
+        let token = cursor.encode();
+
+        // A different page size is still the same search
+        let next_args = json!({"pattern": "*.rs", "drive": "C", "max_results": 50, "cursor": token});
+        assert_eq!(PageCursor::decode(&token, 'C', &next_args).unwrap(), cursor);
+
+        assert!(PageCursor::decode(&token, 'D', &next_args).is_err());
+        assert!(PageCursor::decode(&token, 'C', &json!({"pattern": "*.rs", "drive": "C", "auto_fuzzy": false})).is_ok());
+        assert!(PageCursor::decode(&token, 'C', &json!({"pattern": "*.md"})).is_err());
+        assert!(PageCursor::decode("zz", 'C', &args).is_err());
+    }
+
+    #[test]
+    fn test_pages_cover_all_results_once() {
+        let files: Vec<FileEntry> = (0..25).map(|i| FileEntry::test(i, &format!("dir\\file{:02}.txt", (i * 7) % 25))).collect();
+
+        let mut seen = Vec::new();
+        let mut last: Option<SortKey> = None;
+        loop {
+            let remaining: Vec<&FileEntry> = files.iter()
+                .filter(|f| last.as_ref().map_or(true, |last| is_after(('C', f, None, false), last)))
+                .collect();
+            let (page, has_more) = take_page(remaining, 10, |f| ('C', *f, None, false));
+            seen.extend(page.iter().map(|f| f.path.clone()));
+            last = page.last().map(|f| SortKey::of(('C', f, None, false)));
+            if !has_more {
+                break;
+            }
+        }
+
+        let mut expected: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_verified_page_skips_rejected_results() {
+        let files: Vec<FileEntry> = (0..10).map(|i| FileEntry::test(i, &format!("file{}.txt", i))).collect();
+        let mut checked = 0;
+        let (page, has_more) = take_page_verified(files.iter().collect(), 4, |f| ('C', *f, None, false), |f| {
+            checked += 1;
+            f.id % 2 == 0
+        });
+
+        let ids: Vec<u64> = page.iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![0, 2, 4, 6]);
+        assert!(has_more);
+        assert_eq!(checked, 7);
+    }
+
+    #[test]
+    fn test_scored_order() {
+        let a = FileEntry::test(1, "b.txt");
+        let b = FileEntry::test(2, "a.txt");
+        assert_eq!(compare(('C', &a, Some(0.9), false), ('C', &b, Some(0.5), false)), Ordering::Less);
+        assert_eq!(compare(('C', &a, Some(0.5), false), ('C', &b, Some(0.5), false)), Ordering::Greater);
+        assert_eq!(compare(('C', &a, None, false), ('D', &b, None, false)), Ordering::Less);
+        assert!(is_after(('C', &b, Some(0.5), false), &SortKey::of(('C', &a, Some(0.9), false))));
+    }
+
+    #[test]
+    fn test_workspace_results_sort_first() {
+        let elsewhere = FileEntry::test(1, "a\\config.yaml");
+        let project = FileEntry::test(2, "Users\\dev\\app\\config.yaml");
+        assert_eq!(compare(('C', &project, None, true), ('C', &elsewhere, None, false)), Ordering::Less);
+        assert_eq!(compare(('C', &project, Some(0.5), true), ('C', &elsewhere, Some(0.9), false)), Ordering::Less);
+        assert!(is_after(('C', &elsewhere, None, false), &SortKey::of(('C', &project, None, true))));
+        assert!(!is_after(('C', &project, None, true), &SortKey::of(('C', &elsewhere, None, false))));
+
+        assert!(is_in_folder(&project.path, "users\\DEV\\app"));
+        assert!(!is_in_folder("Users\\dev\\app2\\config.yaml", "Users\\dev\\app"));
+        assert!(is_in_folder("Users\\dev\\app", "Users\\dev\\app"));
+    }
+}
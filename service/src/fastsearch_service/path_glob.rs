@@ -0,0 +1,275 @@
+//! Wildcards in the `path` filter of searches
+//!
+//! A `path` without wildcards is a substring of the path. With `*` or `?` it
+//! is a glob over folders: `*` and `?` stay within one folder name and `**`
+//! spans any number of folders, so `C:\Users\*\Downloads` keeps everything in
+//! (and below) each user's Downloads folder. A glob starting at a drive root
+//! (`C:\...` or `\...`) is anchored there, which lets its literal folders
+//! prune the search: the cache skips entries outside the prefix before the
+//! glob is tried, and the direct scan doesn't descend into folders that can't
+//! lead to a match. Other globs match at any folder boundary, like substrings.
+
+use anyhow::{anyhow, Result};
+
+use super::matcher::MAX_PATTERN_LENGTH;
+
+/// Most `**` a glob may have; each one multiplies the ways a path can be split
+const MAX_RECURSIVE_SEGMENTS: usize = 8;
+
+/// Segment standing for any number of folders
+const RECURSIVE: &str = "**";
+
+/// A `path` filter with wildcards
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathGlob {
+    /// Lowercased folder patterns, root first
+    segments: Vec<String>,
+    /// Whether the first segment has to match the first folder below the root
+    anchored: bool,
+}
+
+impl PathGlob {
+    /// Parse a `path` argument, `None` if it has no wildcards (a substring filter)
+    pub fn parse(raw: &str) -> Result<Option<Self>> {
+        if !raw.contains(['*', '?']) {
+            return Ok(None);
+        }
+        if raw.chars().count() > MAX_PATTERN_LENGTH {
+            return Err(anyhow!("Path filter is too long (at most {} characters)", MAX_PATTERN_LENGTH));
+        }
+
+        let normalized = raw.trim().replace('/', "\\").to_lowercase();
+        let (anchored, rest) = match normalized.split_once(':') {
+            Some((letter, rest)) if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_alphabetic()) => (true, rest),
+            _ => (normalized.starts_with('\\'), normalized.as_str()),
+        };
+        if rest.contains(':') {
+            return Err(anyhow!("Path filter '{}' has a ':' outside the drive letter", raw));
+        }
+
+        let mut segments: Vec<String> = Vec::new();
+        for part in rest.split('\\').filter(|part| !part.is_empty()) {
+            // "a\**\**\b" is the same as "a\**\b"
+            if part == RECURSIVE && segments.last().map(String::as_str) == Some(RECURSIVE) {
+                continue;
+            }
+            segments.push(part.to_string());
+        }
+        // Everything below a matching folder is kept anyway
+        if segments.last().map(String::as_str) == Some(RECURSIVE) {
+            segments.pop();
+        }
+        if segments.is_empty() {
+            return Err(anyhow!("Path filter '{}' matches every folder; leave 'path' out to search the whole drive", raw));
+        }
+        if segments.iter().filter(|s| *s == RECURSIVE).count() > MAX_RECURSIVE_SEGMENTS {
+            return Err(anyhow!("Path filter '{}' has more than {} '**'", raw, MAX_RECURSIVE_SEGMENTS));
+        }
+        Ok(Some(Self { segments, anchored }))
+    }
+
+    /// The glob as a path filter again (drive letter dropped, `\` in front if anchored)
+    pub fn pattern(&self) -> String {
+        let joined = self.segments.join("\\");
+        if self.anchored { format!("\\{}", joined) } else { joined }
+    }
+
+    /// Literal folders an anchored glob starts with, lowercased with a trailing `\` ("" if none)
+    pub fn prefix(&self) -> String {
+        if !self.anchored {
+            return String::new();
+        }
+        self.segments
+            .iter()
+            .take_while(|segment| !segment.contains(['*', '?']))
+            .map(|segment| format!("{}\\", segment))
+            .collect()
+    }
+
+    /// The same glob below a folder, for drive letters `subst`'d to that folder
+    pub fn rooted_at(&self, folder: &str) -> Self {
+        if !self.anchored {
+            return self.clone();
+        }
+        let segments = folder
+            .to_lowercase()
+            .split('\\')
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .chain(self.segments.iter().cloned())
+            .collect();
+        Self { segments, anchored: true }
+    }
+
+    /// Whether a path (relative to the drive root) is a matching folder or below one
+    pub fn is_match(&self, path: &str) -> bool {
+        self.depth_below(path).is_some()
+    }
+
+    /// Levels a path is below the nearest matching folder (0 for the folder itself)
+    pub fn depth_below(&self, path: &str) -> Option<usize> {
+        let path = path.to_lowercase();
+        let prefix = self.prefix();
+        if !path.starts_with(&prefix) {
+            return None;
+        }
+        let folders: Vec<&str> = path.split('\\').filter(|part| !part.is_empty()).collect();
+        (0..=folders.len()).rev().find_map(|end| {
+            let found = if self.anchored {
+                matches_all(&self.segments, &folders[..end])
+            } else {
+                (0..end).any(|start| matches_all(&self.segments, &folders[start..end]))
+            };
+            found.then_some(folders.len() - end)
+        })
+    }
+
+    /// Whether a folder can hold matches, so a traversal needs to look inside it
+    pub fn could_contain(&self, folder: &str) -> bool {
+        if !self.anchored || self.is_match(folder) {
+            return true;
+        }
+        let folder = folder.to_lowercase();
+        let folders: Vec<&str> = folder.split('\\').filter(|part| !part.is_empty()).collect();
+        could_lead_to(&self.segments, &folders)
+    }
+}
+
+/// Whether `folders` is matched by `segments`, all of them
+fn matches_all(segments: &[String], folders: &[&str]) -> bool {
+    match segments.split_first() {
+        None => folders.is_empty(),
+        Some((segment, rest)) if segment == RECURSIVE => {
+            (0..=folders.len()).any(|skip| matches_all(rest, &folders[skip..]))
+        }
+        Some((segment, rest)) => match folders.split_first() {
+            Some((folder, below)) => wildcard_match(segment, folder) && matches_all(rest, below),
+            None => false,
+        },
+    }
+}
+
+/// Whether folders below `folders` could still be matched by `segments`
+fn could_lead_to(segments: &[String], folders: &[&str]) -> bool {
+    match (segments.split_first(), folders.split_first()) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some((segment, _)), Some(_)) if segment == RECURSIVE => true,
+        (Some((segment, rest)), Some((folder, below))) => wildcard_match(segment, folder) && could_lead_to(rest, below),
+    }
+}
+
+/// Match one folder name against a segment with `*` and `?`
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, tried)) => {
+                    p = after;
+                    n = tried + 1;
+                    star = Some((after, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(raw: &str) -> PathGlob {
+        PathGlob::parse(raw).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(PathGlob::parse("Users\\me").unwrap(), None);
+        assert_eq!(glob("C:\\Users\\*\\Downloads").pattern(), "\\users\\*\\downloads");
+        assert_eq!(glob("src/*/tests/").pattern(), "src\\*\\tests");
+        assert_eq!(glob("\\a\\**\\**\\b\\**").pattern(), "\\a\\**\\b");
+        assert!(PathGlob::parse("C:\\**").is_err());
+        assert!(PathGlob::parse("a\\b:c\\*").is_err());
+        assert!(PathGlob::parse(&"**\\x\\".repeat(10)).is_err());
+    }
+
+    #[test]
+    fn test_anchored_glob() {
+        let downloads = glob("C:\\Users\\*\\Downloads");
+        assert!(downloads.is_match("Users\\me\\Downloads"));
+        assert!(downloads.is_match("users\\me\\downloads\\setup.exe"));
+        assert!(!downloads.is_match("Backup\\Users\\me\\Downloads\\a.txt"));
+        assert!(!downloads.is_match("Users\\me\\Documents\\Downloads\\a.txt"));
+        assert_eq!(downloads.depth_below("Users\\me\\Downloads\\x\\a.txt"), Some(2));
+        assert_eq!(downloads.prefix(), "users\\");
+    }
+
+    #[test]
+    fn test_floating_glob() {
+        let tests = glob("src\\*\\tests");
+        assert!(tests.is_match("Projects\\app\\src\\core\\tests\\parse.rs"));
+        assert!(!tests.is_match("Projects\\app\\src\\tests\\parse.rs"));
+        assert_eq!(tests.prefix(), "");
+        // Measured from the nearest matching folder, like substring filters
+        assert_eq!(tests.depth_below("src\\a\\tests\\src\\b\\tests\\x.rs"), Some(1));
+
+        let logs = glob("**\\log?");
+        assert!(logs.is_match("var\\app\\logs\\today.txt"));
+        assert!(logs.is_match("Logs"));
+        assert!(!logs.is_match("var\\catalog\\x"));
+    }
+
+    #[test]
+    fn test_recursive_segments() {
+        let manifests = glob("\\Projects\\**\\target");
+        assert!(manifests.is_match("Projects\\target\\debug"));
+        assert!(manifests.is_match("projects\\a\\b\\c\\target"));
+        assert!(!manifests.is_match("Other\\a\\target"));
+        assert_eq!(manifests.prefix(), "projects\\");
+    }
+
+    #[test]
+    fn test_could_contain() {
+        let downloads = glob("C:\\Users\\*\\Downloads");
+        assert!(downloads.could_contain(""));
+        assert!(downloads.could_contain("Users"));
+        assert!(downloads.could_contain("Users\\me"));
+        assert!(downloads.could_contain("Users\\me\\Downloads\\sub"));
+        assert!(!downloads.could_contain("Windows"));
+        assert!(!downloads.could_contain("Users\\me\\AppData"));
+        assert!(glob("\\a\\**\\b").could_contain("a\\x\\y\\z"));
+        assert!(glob("src\\*").could_contain("Windows"));
+    }
+
+    #[test]
+    fn test_rooted_at() {
+        let glob = glob("\\app\\*\\src").rooted_at("Projects");
+        assert_eq!(glob.pattern(), "\\projects\\app\\*\\src");
+        assert!(glob.is_match("Projects\\app\\v2\\src\\main.rs"));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.d", "cache.d"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*b*c", "axxbyy"));
+        assert!(wildcard_match("l?g*", "log"));
+        assert!(!wildcard_match("l?g", "lg"));
+        assert!(wildcard_match("*", ""));
+    }
+}
@@ -0,0 +1,1275 @@
+//! Staged evaluation of `fast_search`
+//!
+//! Every search runs cache entries through stages of increasing cost, and each
+//! stage only sees what the previous one let through:
+//!
+//! 1. prefilter: take candidates from the cheapest index that can answer the
+//!    search (name lookup for exact names, the sorted or reversed names for
+//!    globs with a literal start or end like `README*` and `*.dll`, the
+//!    trigram index for globs and plain regexes with text in the middle like
+//!    `*invoice*`, the extension index when the extensions are pinned down,
+//!    every distinct name once for fuzzy scoring), falling back to a full scan
+//! 2. name: match the name pattern
+//! 3. filter: path, extension, type, size and date filters, exclude globs and
+//!    the boolean query, all answered from the cache
+//! 4. verify: checks that touch the disk (repository ignore files, that the
+//!    file still exists with `verify_exists`, its owner from the security
+//!    descriptor with `owner`, and what is read from the file itself: image
+//!    dimensions and capture dates, media durations, page and word counts,
+//!    content signatures and whether it is text). These run lazily in page order,
+//!    so they cost about one page of work however many entries matched.
+//!
+//! With `max_per_directory`, the page then takes at most that many results
+//! from any one folder, in the same lazy pass as the verify stage, so a folder
+//! with thousands of matches can't fill a small page on its own.
+//!
+//! Each stage counts what it let through, and the counts are returned with the
+//! results so the cost of a search can be read off its response.
+//!
+//! [`CacheStages`] runs the first three stages over the caches of the drives
+//! searched and [`VerifyStage`] takes the page through the fourth. Around them,
+//! [`SearchOptions`] parses the request, [`did_you_mean_response`] labels the
+//! fuzzy retry of a literal name that matched nothing, and [`SearchPage`] lays
+//! out the response; `fast_search` itself only resolves the drives and opens
+//! their caches.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use parking_lot::RwLockReadGuard;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::cancellation::{CancelToken, Deadline};
+use super::content_kind::{ContentKindCheck, ContentKindFilter};
+use super::content_type::ContentCheck;
+use super::density::DensitySample;
+use super::doc_stats::{DocStatsCheck, DocStatsFilter};
+use super::file_types::DocumentType;
+use super::filters::SearchFilters;
+use super::gitignore::GitignoreFilter;
+use super::hints::NoResultHints;
+use super::image_info::{ImageCheck, ImageFilter};
+use super::matcher::{PatternMatcher, PatternSet, SearchType, REGEX_SEARCH_TIMEOUT};
+use super::media_info::{MediaCheck, MediaFilter};
+use super::mft_cache::{disk_path, FileEntry, MftCache};
+use super::name_affixes::{NameAffix, NameAffixes};
+use super::owner::{OwnerFilter, OwnerQuery};
+use super::pagination::{is_in_folder, take_page, take_page_verified, PageItem};
+use super::projection::{FieldProjection, RESULT_FIELDS};
+use super::query::QueryExpr;
+use super::result_layout::{build_tree, group_by_folder, FolderGroup, FolderNode, ResultLayout};
+use super::result_sets::ResultSet;
+use super::smart::SMART_SEARCH_TYPE;
+use super::trigrams::TrigramIndex;
+use super::volumes::DriveAlias;
+
+/// Candidates scanned between checks for `timeout_ms` and cancellation
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// Where a search takes its candidates from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Prefilter {
+    /// Every distinct name is matched once (fuzzy and diacritic-insensitive search)
+    NameIndex,
+    /// Entries with exactly one of these lowercased names (and the pattern's case, if it has one)
+    ExactNames(Vec<String>),
+    /// Entries whose name starts or ends with one of these, and matches the pattern
+    Affixes(Vec<NameAffix>),
+    /// Entries whose name has all the literals of one of these lists, and matches the pattern
+    ///
+    /// A cache without a trigram index matches every entry instead.
+    Trigrams(Vec<Vec<String>>),
+    /// Entries with one of these extensions
+    Extensions(Vec<String>),
+    /// Every entry in the cache
+    FullScan,
+}
+
+impl Prefilter {
+    /// Cheapest source of candidates for a pattern and its filters
+    pub fn choose(matcher: &PatternMatcher, filters: &SearchFilters) -> Self {
+        if matcher.is_scored() {
+            return Prefilter::NameIndex;
+        }
+        if let Some(names) = matcher.exact_names() {
+            return Prefilter::ExactNames(names);
+        }
+        if let Some(affixes) = matcher.name_affixes() {
+            return Prefilter::Affixes(affixes);
+        }
+        if let Some(literals) = matcher.name_literals() {
+            return Prefilter::Trigrams(literals);
+        }
+        match filters.candidate_extensions() {
+            Some(extensions) => Prefilter::Extensions(extensions),
+            // Folding every name of the drive per search is slow; the distinct
+            // names have their folded forms in the index already
+            None if matcher.folded_inner().is_some() => Prefilter::NameIndex,
+            None => Prefilter::FullScan,
+        }
+    }
+
+    /// Name reported in the stage counts
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Prefilter::NameIndex => "name_index",
+            Prefilter::ExactNames(_) => "exact_name",
+            Prefilter::Affixes(_) => "affix_index",
+            Prefilter::Trigrams(_) => "trigram_index",
+            Prefilter::Extensions(_) => "extension_index",
+            Prefilter::FullScan => "full_scan",
+        }
+    }
+
+    /// Whether candidates have already passed the name stage
+    pub fn checks_name(&self) -> bool {
+        matches!(self, Prefilter::NameIndex | Prefilter::ExactNames(_) | Prefilter::Affixes(_) | Prefilter::Trigrams(_))
+    }
+
+    /// Number of candidates this prefilter yields for one cache
+    pub fn candidate_count(&self, files: &HashMap<u64, FileEntry>, index: &CacheIndexes<'_>) -> usize {
+        match self {
+            Prefilter::NameIndex | Prefilter::FullScan => files.len(),
+            Prefilter::ExactNames(names) => names
+                .iter()
+                .filter_map(|name| index.names.get(name))
+                .map(Vec::len)
+                .sum(),
+            Prefilter::Affixes(affixes) => affix_ids(affixes, index).len(),
+            Prefilter::Trigrams(literals) => trigram_ids(literals, index).map_or(files.len(), |ids| ids.len()),
+            Prefilter::Extensions(extensions) => extensions
+                .iter()
+                .filter_map(|ext| index.extensions.get(ext))
+                .map(Vec::len)
+                .sum(),
+        }
+    }
+
+    /// Candidates from one cache, with their score for scored searches
+    ///
+    /// With `NameIndex`, only entries whose name scored are yielded.
+    pub fn candidates<'f>(
+        &'f self,
+        matcher: &'f PatternMatcher,
+        files: &'f HashMap<u64, FileEntry>,
+        index: &CacheIndexes<'f>,
+    ) -> Box<dyn Iterator<Item = (&'f FileEntry, Option<f64>)> + 'f> {
+        let by_id = move |id: &u64| files.get(id);
+        match self {
+            Prefilter::NameIndex => {
+                let folded = index.folded;
+                Box::new(index.names.iter().flat_map(move |(name, ids)| {
+                    let score = match matcher.folded_inner() {
+                        Some(inner) => inner.score(folded.get(name).map_or(name.as_str(), String::as_str)),
+                        None => matcher.score(name),
+                    };
+                    ids.iter()
+                        .filter(move |_| score.is_some())
+                        .filter_map(by_id)
+                        .map(move |file| (file, score))
+                }))
+            }
+            Prefilter::ExactNames(names) => {
+                let by_name = index.names;
+                Box::new(
+                    names
+                        .iter()
+                        .filter_map(move |name| by_name.get(name))
+                        .flatten()
+                        .filter_map(by_id)
+                        // The index ignores case, a case-sensitive name doesn't
+                        .filter(move |file| matcher.is_file_match(&file.name, &file.path))
+                        .map(|file| (file, None)),
+                )
+            }
+            Prefilter::Affixes(affixes) => Box::new(
+                affix_ids(affixes, index)
+                    .into_iter()
+                    .filter_map(move |id| files.get(&id))
+                    .filter(move |file| matcher.is_file_match(&file.name, &file.path))
+                    .map(|file| (file, None)),
+            ),
+            Prefilter::Trigrams(literals) => {
+                let name_match = move |file: &&FileEntry| matcher.is_file_match(&file.name, &file.path);
+                match trigram_ids(literals, index) {
+                    Some(ids) => Box::new(
+                        ids.into_iter()
+                            .filter_map(move |id| files.get(&id))
+                            .filter(name_match)
+                            .map(|file| (file, None)),
+                    ),
+                    None => Box::new(files.values().filter(name_match).map(|file| (file, None))),
+                }
+            }
+            Prefilter::Extensions(extensions) => {
+                let by_extension = index.extensions;
+                Box::new(
+                    extensions
+                        .iter()
+                        .filter_map(move |ext| by_extension.get(ext))
+                        .flatten()
+                        .filter_map(by_id)
+                        .map(|file| (file, None)),
+                )
+            }
+            Prefilter::FullScan => Box::new(files.values().map(|file| (file, None))),
+        }
+    }
+}
+
+/// IDs of the entries indexed under a name with one of the affixes, each once
+///
+/// An entry can be under two of the names (its long and its short one).
+fn affix_ids(affixes: &[NameAffix], index: &CacheIndexes<'_>) -> Vec<u64> {
+    let mut ids: Vec<u64> = index.affixes
+        .names_with(affixes)
+        .iter()
+        .filter_map(|name| index.names.get(name))
+        .flatten()
+        .copied()
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// IDs of the entries indexed under a name with every literal of one of the lists, each once
+///
+/// `None` if the cache has no trigram index or a list has nothing to look up.
+fn trigram_ids(literals: &[Vec<String>], index: &CacheIndexes<'_>) -> Option<Vec<u64>> {
+    let trigrams = index.trigrams?;
+    let mut ids = Vec::new();
+    for list in literals {
+        let names = trigrams.names_containing(list)?;
+        ids.extend(names.into_iter().filter_map(|name| index.names.get(name)).flatten().copied());
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    Some(ids)
+}
+
+/// Candidates from the records of an earlier result set, with their score for scored searches
+///
+/// Unlike the prefilters these have all passed the name stage, since they
+/// come from no index of their own.
+pub fn result_set_candidates<'f>(
+    ids: &'f [u64],
+    matcher: &'f PatternMatcher,
+    files: &'f HashMap<u64, FileEntry>,
+) -> Box<dyn Iterator<Item = (&'f FileEntry, Option<f64>)> + 'f> {
+    let scored = matcher.is_scored();
+    Box::new(ids.iter().filter_map(|id| files.get(id)).filter_map(move |file| {
+        if scored {
+            // Scored like the name index, which holds lowercased names
+            matcher.score(&file.name.to_lowercase()).map(|score| (file, Some(score)))
+        } else {
+            matcher.is_file_match(&file.name, &file.path).then_some((file, None))
+        }
+    }))
+}
+
+/// The cache indexes a prefilter reads from
+pub struct CacheIndexes<'a> {
+    /// Lowercased name -> file IDs
+    pub names: &'a HashMap<String, Vec<u64>>,
+    /// Lowercased extension -> file IDs
+    pub extensions: &'a HashMap<String, Vec<u64>>,
+    /// Lowercased name -> its folded form, for names that folding changes
+    pub folded: &'a HashMap<String, String>,
+    /// Lowercased names in order and reversed
+    pub affixes: &'a NameAffixes,
+    /// Lowercased names by trigram, if the cache keeps them
+    pub trigrams: Option<&'a TrigramIndex>,
+}
+
+/// Read locks on one cache's entries and the indexes candidates are taken from
+pub struct CacheLocks<'c> {
+    pub files: RwLockReadGuard<'c, HashMap<u64, FileEntry>>,
+    pub names: RwLockReadGuard<'c, HashMap<String, Vec<u64>>>,
+    pub extensions: RwLockReadGuard<'c, HashMap<String, Vec<u64>>>,
+    folded: RwLockReadGuard<'c, HashMap<String, String>>,
+    affixes: RwLockReadGuard<'c, NameAffixes>,
+    trigrams: RwLockReadGuard<'c, Option<TrigramIndex>>,
+}
+
+impl<'c> CacheLocks<'c> {
+    pub fn of(cache: &'c MftCache) -> Self {
+        Self {
+            files: cache.get_files(),
+            names: cache.get_name_index(),
+            extensions: cache.get_extension_index(),
+            folded: cache.get_folded_names(),
+            affixes: cache.get_name_affixes(),
+            trigrams: cache.get_trigrams(),
+        }
+    }
+
+    pub fn indexes(&self) -> CacheIndexes<'_> {
+        CacheIndexes {
+            names: &self.names,
+            extensions: &self.extensions,
+            folded: &self.folded,
+            affixes: &self.affixes,
+            trigrams: self.trigrams.as_ref(),
+        }
+    }
+}
+
+/// One cache searched, as the stages see it
+pub struct ScanSource<'f> {
+    /// Drive letter its matches are reported under
+    pub shown_as: char,
+    /// Where that letter's files live in the cache
+    pub alias: &'f DriveAlias,
+    pub files: &'f HashMap<u64, FileEntry>,
+    pub index: CacheIndexes<'f>,
+}
+
+/// The stages of a search answered from the caches: prefilter, name and filter
+#[derive(Clone, Copy)]
+pub struct CacheStages<'s> {
+    pub prefilter: &'s Prefilter,
+    /// Earlier result set whose records are the candidates instead (`within_results_of`)
+    pub within: Option<&'s ResultSet>,
+    pub matcher: &'s PatternMatcher,
+    pub filters: &'s SearchFilters,
+    pub query: Option<&'s QueryExpr>,
+}
+
+/// What the name and filter stages let through
+pub struct Scan<'f> {
+    /// Every match, once per file record
+    pub matches: Vec<PageItem<'f>>,
+    /// Records that matched under more than one of their names
+    pub renamed: HashSet<(char, u64)>,
+    pub counts: StageCounts,
+    /// Candidates looked at before the scan finished or was stopped
+    pub scanned: u64,
+}
+
+impl<'s> CacheStages<'s> {
+    /// Where the candidates come from, as reported in the stage counts
+    pub fn source_name(&self) -> &'static str {
+        if self.within.is_some() {
+            "result_set"
+        } else {
+            self.prefilter.as_str()
+        }
+    }
+
+    /// Whether candidates have already passed the name stage
+    ///
+    /// Those of a search within a result set are its records, which have.
+    pub fn checks_name(&self) -> bool {
+        self.within.is_some() || self.prefilter.checks_name()
+    }
+
+    /// Number of candidates of one cache
+    pub fn candidate_count(&self, source: &ScanSource<'_>) -> usize {
+        match self.within {
+            Some(set) => set.ids(source.shown_as).len(),
+            None => self.prefilter.candidate_count(source.files, &source.index),
+        }
+    }
+
+    /// Candidates of one cache, with their score for scored searches
+    pub fn candidates<'f>(&self, source: &ScanSource<'f>) -> Box<dyn Iterator<Item = (&'f FileEntry, Option<f64>)> + 'f>
+    where
+        's: 'f,
+    {
+        match self.within {
+            Some(set) => result_set_candidates(set.ids(source.shown_as), self.matcher, source.files),
+            None => self.prefilter.candidates(self.matcher, source.files, &source.index),
+        }
+    }
+
+    /// Whether an entry passes the filters and the query
+    pub fn filter(&self, file: &FileEntry) -> bool {
+        self.filters.matches(file) && self.query.map_or(true, |query| query.matches(file))
+    }
+
+    /// Entries of one cache passing every stage up to the filters, from the prefilter's candidates
+    pub fn cache_matches<'f>(&self, files: &'f HashMap<u64, FileEntry>, index: &CacheIndexes<'f>) -> impl Iterator<Item = &'f FileEntry> + 'f
+    where
+        's: 'f,
+    {
+        let stages: CacheStages<'f> = *self;
+        stages.prefilter.candidates(stages.matcher, files, index)
+            .map(|(file, _)| file)
+            .filter(move |file| stages.prefilter.checks_name() || stages.matcher.is_file_match(&file.name, &file.path))
+            .filter(move |file| stages.filter(file))
+    }
+
+    /// Estimate from a sample of the candidates how much of the caches the search matches
+    pub fn sample_density(&self, sources: &[ScanSource<'_>]) -> DensitySample {
+        let mut density = DensitySample::new();
+        for source in sources {
+            density.add_source(
+                source.files.len(),
+                self.prefilter.candidate_count(source.files, &source.index),
+                self.prefilter.candidates(self.matcher, source.files, &source.index).map(|(file, _)| file),
+                |file| {
+                    let matched = (self.prefilter.checks_name() || self.matcher.is_file_match(&file.name, &file.path))
+                        && self.filter(file);
+                    if matched { source.alias.display_path(&file.path) } else { None }
+                },
+            );
+        }
+        density
+    }
+
+    /// Run the prefilter, name and filter stages over every cache's candidates
+    ///
+    /// Hard links and 8.3 names list a record under several names, which all
+    /// map to one entry: the record is matched once, with the best score any
+    /// of its names got. Matches in the `workspace` folder (a drive and a path
+    /// in its cache) are marked so they sort first. `observe` is called with
+    /// the count scanned so far before each candidate is looked at, and again
+    /// with each new match. The scan stops where it is once `deadline` passes.
+    pub fn scan<'f>(
+        &self,
+        sources: &[ScanSource<'f>],
+        workspace: Option<(char, &str)>,
+        deadline: &Deadline,
+        volumes: &mut [VolumeStats],
+        mut observe: impl FnMut(u64, Option<PageItem<'f>>),
+    ) -> Scan<'f>
+    where
+        's: 'f,
+    {
+        let mut scan = Scan {
+            matches: Vec::new(),
+            renamed: HashSet::new(),
+            counts: StageCounts { prefilter: self.source_name(), ..Default::default() },
+            scanned: 0,
+        };
+        let mut seen: HashMap<(char, u64), Option<usize>> = HashMap::new();
+        let checks_name = self.checks_name();
+
+        'scan: for (source, volume) in sources.iter().zip(volumes.iter_mut()) {
+            let volume_start = Instant::now();
+            scan.counts.candidates += self.candidate_count(source) as u64;
+
+            for (file, score) in self.candidates(source) {
+                scan.scanned += 1;
+                volume.scanned += 1;
+                observe(scan.scanned, None);
+                if scan.scanned % DEADLINE_CHECK_INTERVAL == 0 && deadline.passed() {
+                    volume.elapsed_ms += volume_start.elapsed().as_millis() as u64;
+                    break 'scan;
+                }
+
+                if !checks_name && !self.matcher.is_file_match(&file.name, &file.path) {
+                    continue;
+                }
+                let key = (source.shown_as, file.id);
+                if let Some(slot) = seen.get(&key) {
+                    // Another name of a record already seen; the filters gave the same answer then
+                    scan.renamed.insert(key);
+                    if let Some(i) = *slot {
+                        if score > scan.matches[i].2 {
+                            scan.matches[i].2 = score;
+                        }
+                    }
+                    continue;
+                }
+                scan.counts.name_matches += 1;
+
+                // Files outside the folder a subst'd drive maps to aren't visible through it
+                if source.alias.display_path(&file.path).is_none() || !self.filter(file) {
+                    seen.insert(key, None);
+                    continue;
+                }
+                scan.counts.filter_matches += 1;
+                volume.matches += 1;
+
+                let in_workspace = workspace.map_or(false, |(drive, folder)| {
+                    drive == source.alias.canonical && is_in_folder(&file.path, folder)
+                });
+                let item = (source.shown_as, file, score, in_workspace);
+                seen.insert(key, Some(scan.matches.len()));
+                scan.matches.push(item);
+                observe(scan.scanned, Some(item));
+            }
+            volume.elapsed_ms += volume_start.elapsed().as_millis() as u64;
+        }
+        scan
+    }
+}
+
+/// How many entries each stage let through
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StageCounts {
+    /// Where candidates came from
+    pub prefilter: &'static str,
+    /// Entries considered
+    pub candidates: u64,
+    /// Entries whose name matched the pattern
+    pub name_matches: u64,
+    /// Entries that passed the cache-answered filters
+    pub filter_matches: u64,
+    /// Entries that passed the disk checks (only checked up to a page)
+    pub verified: u64,
+    /// Entries dropped by the disk checks
+    pub rejected: u64,
+    /// Entries held back by `max_per_directory`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub capped: u64,
+}
+
+fn is_zero(count: &u64) -> bool {
+    *count == 0
+}
+
+/// How one volume of an all-drive search went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VolumeStats {
+    pub drive: String,
+    /// `hit` (cache already built), `miss` (built for this search) or `unavailable`
+    pub cache: &'static str,
+    /// Candidates taken from this volume's cache
+    pub scanned: u64,
+    /// Entries that passed the cache-answered filters
+    pub matches: u64,
+    /// Time spent on this volume, including building its cache on a miss
+    pub elapsed_ms: u64,
+    /// Why the volume wasn't searched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl VolumeStats {
+    pub fn searched(drive: char, cache_hit: bool) -> Self {
+        Self {
+            drive: drive.to_string(),
+            cache: if cache_hit { "hit" } else { "miss" },
+            scanned: 0,
+            matches: 0,
+            elapsed_ms: 0,
+            error: None,
+        }
+    }
+
+    pub fn unavailable(drive: char, error: String, elapsed_ms: u64) -> Self {
+        Self {
+            drive: drive.to_string(),
+            cache: "unavailable",
+            scanned: 0,
+            matches: 0,
+            elapsed_ms,
+            error: Some(error),
+        }
+    }
+
+    /// One line of the text answer, e.g. `D: 12 matches of 40000 scanned in 35ms (cache built)`
+    pub fn describe(&self) -> String {
+        match (&self.error, self.cache) {
+            (Some(error), _) => format!("{}: not searched ({})", self.drive, error),
+            (None, cache) => format!(
+                "{}: {} matches of {} scanned in {}ms{}",
+                self.drive,
+                self.matches,
+                self.scanned,
+                self.elapsed_ms,
+                if cache == "miss" { " (cache built)" } else { "" }
+            ),
+        }
+    }
+}
+
+/// At most so many results from any one folder (`max_per_directory`)
+#[derive(Debug, Default)]
+pub struct FolderCap {
+    limit: usize,
+    taken: HashMap<(char, String), usize>,
+    capped: BTreeSet<(char, String)>,
+}
+
+impl FolderCap {
+    /// Parse the `max_per_directory` argument
+    pub fn from_args(args: &Value) -> Result<Option<Self>> {
+        match &args["max_per_directory"] {
+            Value::Null => Ok(None),
+            value => match value.as_u64() {
+                Some(limit) if limit > 0 => Ok(Some(Self::new(limit as usize))),
+                _ => Err(anyhow!("'max_per_directory' must be a whole number of at least 1")),
+            },
+        }
+    }
+
+    pub fn new(limit: usize) -> Self {
+        Self { limit, ..Default::default() }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Whether one more result fits in the folder of `path`, counting it if so
+    pub fn admit(&mut self, drive: char, path: &str) -> bool {
+        let folder = path.rsplit_once('\\').map_or("", |(folder, _)| folder).to_lowercase();
+        let key = (drive.to_ascii_uppercase(), folder);
+        let taken = self.taken.entry(key.clone()).or_insert(0);
+        if *taken >= self.limit {
+            self.capped.insert(key);
+            return false;
+        }
+        *taken += 1;
+        true
+    }
+
+    /// Folders that had results held back, as full paths (lowercased)
+    pub fn capped_folders(&self) -> Vec<String> {
+        self.capped.iter().map(|(drive, folder)| format!("{}:\\{}", drive, folder)).collect()
+    }
+}
+
+/// Disk checks for the matches on one drive
+pub struct Verifier<'a> {
+    gitignore: Option<GitignoreFilter<'a>>,
+    verify_exists: Option<PathBuf>,
+    owner: Option<OwnerFilter>,
+    image: Option<ImageCheck<'a>>,
+    media: Option<MediaCheck<'a>>,
+    content: Option<ContentCheck<'a>>,
+    doc_stats: Option<DocStatsCheck<'a>>,
+    content_kind: Option<ContentKindCheck<'a>>,
+}
+
+impl<'a> Verifier<'a> {
+    /// Checks to run; `verify_exists` is the folder cache paths are relative to
+    pub fn new(
+        gitignore: Option<GitignoreFilter<'a>>,
+        verify_exists: Option<PathBuf>,
+        owner: Option<OwnerFilter>,
+        image: Option<ImageCheck<'a>>,
+        media: Option<MediaCheck<'a>>,
+        content: Option<ContentCheck<'a>>,
+        doc_stats: Option<DocStatsCheck<'a>>,
+        content_kind: Option<ContentKindCheck<'a>>,
+    ) -> Self {
+        Self { gitignore, verify_exists, owner, image, media, content, doc_stats, content_kind }
+    }
+
+    /// Whether there is nothing to check
+    pub fn is_noop(&self) -> bool {
+        self.gitignore.is_none() && self.verify_exists.is_none() && self.owner.is_none()
+            && self.image.is_none() && self.media.is_none() && self.content.is_none() && self.doc_stats.is_none()
+            && self.content_kind.is_none()
+    }
+
+    /// Whether a match passes every check
+    pub fn accepts(&self, file: &FileEntry) -> bool {
+        if self.gitignore.as_ref().map_or(false, |filter| filter.is_ignored(file)) {
+            return false;
+        }
+        // symlink_metadata so a dangling link still counts as present
+        if let Some(root) = &self.verify_exists {
+            if disk_path(root, &file.path).symlink_metadata().is_err() {
+                return false;
+            }
+        }
+        if !self.owner.as_ref().map_or(true, |filter| filter.is_owned(file)) {
+            return false;
+        }
+        // Last, as these read the file itself (the kind only when its extension doesn't tell)
+        self.content_kind.as_ref().map_or(true, |check| check.accepts(file))
+            && self.content.as_ref().map_or(true, |check| check.accepts(file))
+            && self.image.as_ref().map_or(true, |check| check.accepts(file))
+            && self.media.as_ref().map_or(true, |check| check.accepts(file))
+            && self.doc_stats.as_ref().map_or(true, |check| check.accepts(file))
+    }
+}
+
+/// The verify stage: each drive's disk checks, by the letter its matches are reported under
+pub struct VerifyStage<'a> {
+    drives: Vec<(char, &'a DriveAlias, Verifier<'a>)>,
+}
+
+impl<'a> VerifyStage<'a> {
+    pub fn new(drives: Vec<(char, &'a DriveAlias, Verifier<'a>)>) -> Self {
+        Self { drives }
+    }
+
+    /// Whether no drive has anything to check
+    pub fn is_noop(&self) -> bool {
+        self.drives.iter().all(|(_, _, verifier)| verifier.is_noop())
+    }
+
+    /// Whether a match passes the checks of its drive
+    pub fn accepts(&self, item: PageItem<'_>) -> bool {
+        self.drives
+            .iter()
+            .find(|(shown_as, _, _)| *shown_as == item.0)
+            .map_or(true, |(_, _, verifier)| verifier.accepts(item.1))
+    }
+
+    /// Path of a match as the caller sees it, through the drive letter searched
+    ///
+    /// Folders are capped by this path.
+    pub fn shown_path(&self, item: PageItem<'_>) -> String {
+        self.drives
+            .iter()
+            .find(|(shown_as, _, _)| *shown_as == item.0)
+            .and_then(|(_, alias, _)| alias.display_path(&item.1.path))
+            .unwrap_or(&item.1.path)
+            .to_string()
+    }
+
+    /// Take the page from the matches, verifying them (and capping their folders) in page order
+    ///
+    /// Returns the page and whether more matches follow it.
+    pub fn page<'f>(
+        &self,
+        matches: Vec<PageItem<'f>>,
+        max_results: usize,
+        mut folder_cap: Option<&mut FolderCap>,
+        counts: &mut StageCounts,
+    ) -> (Vec<PageItem<'f>>, bool) {
+        if self.is_noop() && folder_cap.is_none() {
+            let (page, has_more) = take_page(matches, max_results, |m| *m);
+            counts.verified = page.len() as u64;
+            return (page, has_more);
+        }
+        take_page_verified(matches, max_results, |m| *m, |m| {
+            if !self.accepts(*m) {
+                counts.rejected += 1;
+                return false;
+            }
+            counts.verified += 1;
+            if !folder_cap.as_mut().map_or(true, |cap| cap.admit(m.0, &self.shown_path(*m))) {
+                counts.capped += 1;
+                return false;
+            }
+            true
+        })
+    }
+}
+
+/// The arguments of a `fast_search` request, parsed
+///
+/// What needs the engine to resolve (the drive, `within_results_of` and
+/// `workspace_hint`) is left to it.
+pub struct SearchOptions {
+    pub patterns: PatternSet,
+    /// What the search is shown and logged as: its query, or else its patterns
+    pub description: String,
+    pub search_type: SearchType,
+    /// The boolean query, applied in addition to the patterns
+    pub query: Option<QueryExpr>,
+    pub max_results: usize,
+    pub filters: SearchFilters,
+    pub owner: Option<OwnerQuery>,
+    pub image_filter: Option<ImageFilter>,
+    pub media_filter: Option<MediaFilter>,
+    pub doc_stats_filter: Option<DocStatsFilter>,
+    pub content_kind: ContentKindFilter,
+    pub folder_cap: Option<FolderCap>,
+    pub respect_gitignore: bool,
+    pub verify_exists: bool,
+    pub verify_content_type: bool,
+    pub summarize_broad: bool,
+    pub layout: ResultLayout,
+    pub projection: Option<FieldProjection>,
+    /// `timeout_ms` as reported back, with the default of regex searches filled in
+    pub timeout_ms: Value,
+    pub deadline: Deadline,
+}
+
+impl SearchOptions {
+    /// Parse the arguments; errors are meant for the caller
+    ///
+    /// The deadline counts from now, and passes early once `cancel` is cancelled.
+    pub fn from_args(
+        args: &Value,
+        doc_type_extensions: &HashMap<DocumentType, HashSet<String>>,
+        cancel: &CancelToken,
+    ) -> Result<Self> {
+        let patterns = PatternSet::from_args(args)?;
+        let description = args["query"].as_str().map_or_else(|| patterns.describe(), str::to_string);
+        let search_type = match args["search_type"].as_str() {
+            Some(s) => SearchType::parse(s).ok_or_else(|| anyhow!(
+                "Unknown search_type '{}' (expected glob, regex, exact, fuzzy or smart)", s
+            ))?,
+            None => SearchType::default(),
+        };
+        let query = match args["query"].as_str() {
+            Some(q) => Some(QueryExpr::parse(q).map_err(|e| anyhow!("Invalid query: {}", e))?),
+            None => None,
+        };
+        // Regex searches have a limit of their own when the caller set none
+        let default_timeout = (search_type == SearchType::Regex).then_some(REGEX_SEARCH_TIMEOUT);
+        let timeout_ms = match &args["timeout_ms"] {
+            Value::Null => json!(default_timeout.map(|timeout| timeout.as_millis() as u64)),
+            value => value.clone(),
+        };
+        Ok(Self {
+            description,
+            search_type,
+            query,
+            max_results: args["max_results"].as_u64().unwrap_or(1000) as usize,
+            layout: ResultLayout::from_args(args)?,
+            projection: FieldProjection::from_args(args, RESULT_FIELDS)?,
+            filters: SearchFilters::from_args(args, doc_type_extensions)?,
+            owner: OwnerQuery::from_args(args)?,
+            image_filter: ImageFilter::from_args(args)?,
+            media_filter: MediaFilter::from_args(args)?,
+            doc_stats_filter: DocStatsFilter::from_args(args)?,
+            content_kind: ContentKindFilter::from_args(args)?,
+            folder_cap: FolderCap::from_args(args)?,
+            respect_gitignore: args["respect_gitignore"].as_bool().unwrap_or(false),
+            verify_exists: args["verify_exists"].as_bool().unwrap_or(false),
+            verify_content_type: args["verify_content_type"].as_bool().unwrap_or(false),
+            summarize_broad: args["summarize_broad"].as_bool().unwrap_or(true),
+            deadline: Deadline::from_args_or(args, default_timeout)?.or_cancelled(cancel),
+            timeout_ms,
+            patterns,
+        })
+    }
+
+    /// Whether every pattern is a literal name, which a typo leaves matching nothing
+    pub fn is_literal(&self) -> bool {
+        matches!(self.search_type, SearchType::Glob | SearchType::Exact)
+            && self.patterns.include.iter().all(|p| !p.trim().is_empty() && !p.contains(['*', '?']))
+    }
+
+    /// Whether a disk check looks at what a sample of the cache can't: the owner, or the files themselves
+    pub fn reads_files(&self) -> bool {
+        self.owner.is_some() || self.image_filter.is_some() || self.media_filter.is_some()
+            || self.doc_stats_filter.is_some() || self.verify_content_type || self.content_kind.kind.is_some()
+    }
+}
+
+/// Whether a literal pattern that matches nothing is retried as a fuzzy search
+///
+/// On unless the caller picked the engine; a smart search leaves that to its patterns.
+pub fn auto_fuzzy(args: &Value) -> bool {
+    args["auto_fuzzy"].as_bool().unwrap_or_else(|| match &args["search_type"] {
+        Value::Null => true,
+        Value::String(search_type) => search_type.eq_ignore_ascii_case(SMART_SEARCH_TYPE),
+        _ => false,
+    })
+}
+
+/// Whether a search response has no matches at all, rather than none on this page or in time
+pub fn found_nothing(response: &Value) -> bool {
+    response["result"]["stages"]["filter_matches"].as_u64() == Some(0)
+        && !response["result"]["truncated"].as_bool().unwrap_or(false)
+}
+
+/// Label fuzzy results returned because a literal search found nothing
+pub fn did_you_mean_response(pattern: &str, search_type: &str, mut response: Value) -> Value {
+    let text = response["result"]["content"][0]["text"].as_str().unwrap_or_default().to_string();
+    response["result"]["content"][0]["text"] = json!(format!(
+        "❓ DID YOU MEAN: nothing matched the {} pattern '{}', so these are fuzzy matches for it \
+         (continue with search_type: \"fuzzy\" to page through them).\n\n{}",
+        search_type, pattern, text
+    ));
+    response["result"]["did_you_mean"] = json!({
+        "pattern": pattern,
+        "original_search_type": search_type,
+        "search_type": SearchType::Fuzzy.as_str(),
+    });
+    response
+}
+
+/// A `fast_search` result as the response lists it
+pub struct ListedHit {
+    pub drive: char,
+    pub name: String,
+    /// Path relative to the drive it was found under
+    pub path: String,
+    /// Size, score and other notes that follow the path in the text answer
+    pub notes: String,
+    /// Structured entries: the result, then its data streams
+    pub entries: Vec<Value>,
+}
+
+/// A page of results laid out by folder, as indexes into the page
+enum FolderLayout {
+    Flat,
+    Grouped(Vec<FolderGroup>),
+    Tree(FolderNode),
+}
+
+/// A page of `fast_search` results and how the search went, to answer with
+pub struct SearchPage<'a> {
+    pub description: &'a str,
+    /// Drive argument of the search ("*" for every drive)
+    pub drive: &'a str,
+    pub hits: Vec<ListedHit>,
+    pub layout: ResultLayout,
+    /// Fields the caller picked; the text then leaves the results to the structured ones
+    pub fields: Option<&'a [String]>,
+    pub next_cursor: Option<String>,
+    /// Matches from this page on
+    pub remaining: usize,
+    /// The earlier result set this search narrowed
+    pub within: Option<&'a ResultSet>,
+    /// Result set later searches can narrow
+    pub result_set: Option<String>,
+    pub counts: StageCounts,
+    pub hints: Option<NoResultHints>,
+    pub folder_cap: Option<&'a FolderCap>,
+    /// Why data streams couldn't be listed
+    pub streams_error: Option<String>,
+    /// Candidates looked at
+    pub scanned: u64,
+    pub truncated: bool,
+    pub cancelled: bool,
+    pub timeout_ms: Value,
+    /// How each volume went, for all-drive searches only
+    pub volumes: Vec<VolumeStats>,
+    pub elapsed: Duration,
+}
+
+impl SearchPage<'_> {
+    /// The response: the text answer, and the results as structured entries
+    pub fn into_response(self) -> Value {
+        let hits = &self.hits;
+        let drive = self.drive;
+        let capped_folders = self.folder_cap.map(FolderCap::capped_folders).unwrap_or_default();
+        
+        // Lay the page out by folder, if asked to
+        let full_paths = || hits.iter().map(|hit| format!("{}:\\{}", hit.drive, hit.path)).collect::<Vec<_>>();
+        let folders = match self.layout {
+            ResultLayout::Flat => FolderLayout::Flat,
+            ResultLayout::Grouped => FolderLayout::Grouped(group_by_folder(&full_paths())),
+            ResultLayout::Tree => FolderLayout::Tree(build_tree(&full_paths())),
+        };
+        
+        let volumes_text = if self.volumes.is_empty() {
+            String::new()
+        } else {
+            let lines: Vec<String> = self.volumes.iter().map(|volume| format!("   {}", volume.describe())).collect();
+            format!("\n💽 Per drive:\n{}", lines.join("\n"))
+        };
+        
+        // Format results
+        let results_text = if hits.is_empty() {
+            let mut text = format!("No files found matching '{}' in drive {} (searched in {:.2}ms)", 
+                                   self.description, drive, self.elapsed.as_millis());
+            if let Some(hints) = &self.hints {
+                text.push('\n');
+                for line in hints.describe() {
+                    text.push_str(&format!("\n💡 {}", line));
+                }
+            }
+            text.push_str(&volumes_text);
+            if self.cancelled {
+                text.push_str("\n⚠️ Cancelled before the whole index was searched");
+            } else if self.truncated {
+                text.push_str(&format!("\n⏱️ Stopped at the {}ms timeout before the whole index was searched; raise timeout_ms or narrow the search",
+                                       self.timeout_ms));
+            }
+            text
+        } else {
+            let mut text = format!("🚀 FAST SEARCH: Found {} files matching '{}' in {:.2}ms\n\n", 
+                                 hits.len(), self.description, self.elapsed.as_millis());
+            
+            // Whoever names fields reads the structured results; listing them again would undo the savings
+            if let Some(fields) = self.fields {
+                text.push_str(&format!("Listed in the structured results only, with fields: {}\n", fields.join(", ")));
+            } else {
+                match &folders {
+                    FolderLayout::Flat => {
+                        for (i, hit) in hits.iter().enumerate() {
+                            let path = if drive == "*" {
+                                format!("{}:\\{}", hit.drive, hit.path)
+                            } else {
+                                hit.path.clone()
+                            };
+                            text.push_str(&format!("{}. {} {}\n", i + 1, path, hit.notes));
+                        }
+                    }
+                    FolderLayout::Grouped(groups) => {
+                        for group in groups {
+                            text.push_str(&format!("📁 {} ({})\n", group.folder, group.items.len()));
+                            for &i in &group.items {
+                                let line = format!("{}. {} {}", i + 1, hits[i].name, hits[i].notes);
+                                for line in line.lines() {
+                                    text.push_str(&format!("   {}\n", line));
+                                }
+                            }
+                        }
+                    }
+                    FolderLayout::Tree(tree) => {
+                        tree.render(0, &|i| format!("{}. {} {}", i + 1, hits[i].name, hits[i].notes), &mut text);
+                    }
+                }
+            }
+            
+            if let Some(cursor) = &self.next_cursor {
+                text.push_str(&format!("\n⚡ {} more results; pass cursor \"{}\" to get the next page", 
+                                     self.remaining - hits.len(), cursor));
+            }
+            if let Some(set) = self.within {
+                text.push_str(&format!("\n🔎 Searched the {} matches of '{}' ({})", set.len(), set.description, set.id));
+            }
+            if let Some(id) = &self.result_set {
+                text.push_str(&format!("\n🔎 Narrow these {} matches with within_results_of: \"{}\"", self.counts.filter_matches, id));
+            }
+            
+            if !capped_folders.is_empty() {
+                text.push_str(&format!("\n📁 Only {} results per folder shown; more are in: {}",
+                                     self.folder_cap.map_or(0, FolderCap::limit), capped_folders.join(", ")));
+            }
+            if let Some(e) = &self.streams_error {
+                text.push_str(&format!("\n⚠️ Data streams not listed: {}", e));
+            }
+            if self.cancelled {
+                text.push_str(&format!("\n⚠️ Cancelled: only the matches among the first {} candidates are listed", self.scanned));
+            } else if self.truncated {
+                text.push_str(&format!("\n⏱️ Partial results: stopped at the {}ms timeout after {} candidates; raise timeout_ms or narrow the search",
+                                       self.timeout_ms, self.scanned));
+            }
+            
+            text.push_str(&volumes_text);
+            text.push_str(&format!("\n💡 Search completed in {:.2}ms - USING MFT CACHE", self.elapsed.as_millis()));
+            text
+        };
+        
+        // Structured results so callers don't have to parse the text; each file's
+        // data streams follow it as entries of their own
+        let entries = |i: usize| hits[i].entries.clone();
+        
+        let mut response = json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": results_text
+                }],
+                "next_cursor": self.next_cursor,
+                "result_set": self.result_set,
+                "stages": self.counts,
+                "hints": self.hints
+            }
+        });
+        match &folders {
+            FolderLayout::Flat => {
+                response["result"]["results"] = json!((0..hits.len()).flat_map(entries).collect::<Vec<_>>());
+            }
+            FolderLayout::Grouped(groups) => {
+                response["result"]["groups"] = json!(groups.iter().map(|group| json!({
+                    "folder": group.folder,
+                    "count": group.items.len(),
+                    "results": group.items.iter().flat_map(|&i| entries(i)).collect::<Vec<_>>()
+                })).collect::<Vec<_>>());
+            }
+            FolderLayout::Tree(tree) => {
+                response["result"]["tree"] = tree.to_json(&entries);
+            }
+        }
+        if self.layout != ResultLayout::Flat {
+            response["result"]["output"] = json!(self.layout.as_str());
+        }
+        if !capped_folders.is_empty() {
+            response["result"]["capped_folders"] = json!(capped_folders);
+        }
+        if self.cancelled {
+            response["result"]["truncated"] = json!(true);
+            response["result"]["cancelled"] = json!(true);
+        } else if self.truncated {
+            response["result"]["truncated"] = json!(true);
+            response["result"]["timeout_ms"] = self.timeout_ms;
+        }
+        if let Some(e) = self.streams_error {
+            response["result"]["streams_error"] = json!(e);
+        }
+        if !self.volumes.is_empty() {
+            response["result"]["volumes"] = json!(self.volumes);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::matcher::{compile_pattern, compile_pattern_with_case, folded_key, PatternCache, PatternSet, SearchType};
+    use serde_json::json;
+
+    fn entry(id: u64, name: &str) -> FileEntry {
+        FileEntry::test(id, &format!("dir\\{}", name))
+    }
+
+    #[test]
+    fn test_prefilter_choice_and_candidates() {
+        let entries = [entry(1, "main.rs"), entry(2, "lib.rs"), entry(3, "README.md"), entry(4, "notes.txt"), entry(5, "Résumé.pdf")];
+        let files: HashMap<u64, FileEntry> = entries.iter().map(|e| (e.id, e.clone())).collect();
+        let mut names: HashMap<String, Vec<u64>> = HashMap::new();
+        let mut extensions: HashMap<String, Vec<u64>> = HashMap::new();
+        for e in &entries {
+            names.entry(e.name.to_lowercase()).or_default().push(e.id);
+            if let Some(ext) = &e.extension {
+                extensions.entry(ext.clone()).or_default().push(e.id);
+            }
+        }
+        let folded: HashMap<String, String> = names.keys()
+            .filter_map(|name| folded_key(name).map(|folded| (name.clone(), folded)))
+            .collect();
+        let affixes = NameAffixes::from_names(names.keys());
+        let trigrams = TrigramIndex::from_names(names.keys());
+        let index = CacheIndexes { names: &names, extensions: &extensions, folded: &folded, affixes: &affixes, trigrams: Some(&trigrams) };
+        let no_filters = SearchFilters::from_args(&json!({}), &HashMap::new()).unwrap();
+        let ids = |prefilter: &Prefilter, matcher: &PatternMatcher| {
+            let mut ids: Vec<u64> = prefilter.candidates(matcher, &files, &index).map(|(f, _)| f.id).collect();
+            ids.sort();
+            ids
+        };
+
+        let exact = compile_pattern("readme.md", SearchType::Exact).unwrap();
+        let prefilter = Prefilter::choose(&exact, &no_filters);
+        assert_eq!(prefilter, Prefilter::ExactNames(vec!["readme.md".to_string()]));
+        assert_eq!(ids(&prefilter, &exact), vec![3]);
+
+        let glob = compile_pattern("*", SearchType::Glob).unwrap();
+        let rust_only = SearchFilters::from_args(&json!({"extensions": ["rs"]}), &HashMap::new()).unwrap();
+        let prefilter = Prefilter::choose(&glob, &rust_only);
+        assert_eq!(prefilter.as_str(), "extension_index");
+        assert_eq!(prefilter.candidate_count(&files, &index), 2);
+        assert_eq!(ids(&prefilter, &glob), vec![1, 2]);
+
+        assert_eq!(Prefilter::choose(&glob, &no_filters), Prefilter::FullScan);
+
+        // Globs with a literal start or end read a range of the sorted or reversed names
+        let suffix = compile_pattern("*.RS", SearchType::Glob).unwrap();
+        let prefilter = Prefilter::choose(&suffix, &no_filters);
+        assert_eq!(prefilter, Prefilter::Affixes(vec![NameAffix::Suffix(".rs".to_string())]));
+        assert_eq!(prefilter.candidate_count(&files, &index), 2);
+        assert_eq!(ids(&prefilter, &suffix), vec![1, 2]);
+        let prefix = compile_pattern("READ*", SearchType::Glob).unwrap();
+        assert_eq!(ids(&Prefilter::choose(&prefix, &no_filters), &prefix), vec![3]);
+        // Case-sensitive globs look the names up lowercased and still match them
+        let cased = compile_pattern_with_case("read*", SearchType::Glob, true).unwrap();
+        assert_eq!(ids(&Prefilter::choose(&cased, &no_filters), &cased), Vec::<u64>::new());
+        // Any pattern without one scans
+        let either = PatternCache::new().get_or_compile_any(&["*.md".to_string(), "*a*".to_string()], SearchType::Glob, false).unwrap();
+        assert_eq!(Prefilter::choose(&either, &no_filters), Prefilter::FullScan);
+
+        // Text in the middle of a name is looked up by its trigrams
+        let middle = compile_pattern("*OTE*", SearchType::Glob).unwrap();
+        let prefilter = Prefilter::choose(&middle, &no_filters);
+        assert_eq!(prefilter, Prefilter::Trigrams(vec![vec!["ote".to_string()]]));
+        assert_eq!(prefilter.candidate_count(&files, &index), 1);
+        assert_eq!(ids(&prefilter, &middle), vec![4]);
+        // ...and matched against every entry of a cache without the index
+        let without = CacheIndexes { trigrams: None, ..index };
+        assert_eq!(prefilter.candidate_count(&files, &without), files.len());
+        let found: Vec<u64> = prefilter.candidates(&middle, &files, &without).map(|(f, _)| f.id).collect();
+        assert_eq!(found, vec![4]);
+
+        let fuzzy = compile_pattern("mrs", SearchType::Fuzzy).unwrap();
+        let prefilter = Prefilter::choose(&fuzzy, &no_filters);
+        assert!(prefilter.checks_name());
+        assert_eq!(ids(&prefilter, &fuzzy), vec![1]);
+
+        // Diacritic-insensitive searches match the folded names in the index
+        let patterns = PatternSet::from_args(&json!({"pattern": "resume.*", "ignore_diacritics": true})).unwrap();
+        let folded_glob = PatternCache::new().get_or_compile_set(&patterns, SearchType::Glob).unwrap();
+        let prefilter = Prefilter::choose(&folded_glob, &no_filters);
+        assert_eq!(prefilter, Prefilter::NameIndex);
+        assert_eq!(ids(&prefilter, &folded_glob), vec![5]);
+
+        // A result set is matched record by record, scored like the name index
+        let earlier = [1, 2, 4, 9];
+        let found: Vec<u64> = result_set_candidates(&earlier, &glob, &files).map(|(f, _)| f.id).collect();
+        assert_eq!(found, vec![1, 2, 4]);
+        let scored: Vec<(u64, bool)> = result_set_candidates(&earlier, &fuzzy, &files).map(|(f, score)| (f.id, score.is_some())).collect();
+        assert_eq!(scored, vec![(1, true)]);
+    }
+
+    #[test]
+    fn test_scan_matches_each_record_once() {
+        // Record 2 is indexed under its long and its 8.3 name
+        let entries = [entry(1, "report.txt"), entry(2, "quarterly report.txt"), FileEntry::test(3, "other\\report.log"), entry(4, "notes.md")];
+        let files: HashMap<u64, FileEntry> = entries.iter().map(|e| (e.id, e.clone())).collect();
+        let mut names: HashMap<String, Vec<u64>> = HashMap::new();
+        for (name, id) in [("report.txt", 1), ("quarterly report.txt", 2), ("report~1.txt", 2), ("report.log", 3), ("notes.md", 4)] {
+            names.entry(name.to_string()).or_default().push(id);
+        }
+        let (extensions, folded) = (HashMap::new(), HashMap::new());
+        let affixes = NameAffixes::from_names(names.keys());
+        let index = CacheIndexes { names: &names, extensions: &extensions, folded: &folded, affixes: &affixes, trigrams: None };
+        let alias = DriveAlias::whole('C');
+        let sources = [ScanSource { shown_as: 'C', alias: &alias, files: &files, index }];
+
+        let fuzzy = compile_pattern("report", SearchType::Fuzzy).unwrap();
+        let no_filters = SearchFilters::from_args(&json!({}), &HashMap::new()).unwrap();
+        let prefilter = Prefilter::choose(&fuzzy, &no_filters);
+        let stages = CacheStages { prefilter: &prefilter, within: None, matcher: &fuzzy, filters: &no_filters, query: None };
+        let mut volumes = [VolumeStats::searched('C', true)];
+        let mut found = 0;
+        let scan = stages.scan(&sources, Some(('C', "dir")), &Deadline::none(), &mut volumes, |_, item| {
+            found += item.is_some() as usize;
+        });
+
+        let mut matched: Vec<(u64, bool)> = scan.matches.iter().map(|item| (item.1.id, item.3)).collect();
+        matched.sort();
+        assert_eq!(matched, vec![(1, true), (2, true), (3, false)]);
+        assert_eq!(found, 3);
+        assert_eq!(scan.renamed, HashSet::from([('C', 2)]));
+        assert_eq!((scan.counts.prefilter, scan.counts.name_matches, scan.counts.filter_matches), ("name_index", 3, 3));
+        assert_eq!(volumes[0].matches, 3);
+    }
+
+    #[test]
+    fn test_folder_cap() {
+        assert!(FolderCap::from_args(&json!({})).unwrap().is_none());
+        assert!(FolderCap::from_args(&json!({"max_per_directory": 0})).is_err());
+
+        let mut cap = FolderCap::from_args(&json!({"max_per_directory": 2})).unwrap().unwrap();
+        let admitted: Vec<bool> = ["logs\\a.log", "Logs\\b.log", "logs\\c.log", "other\\d.log", "root.log", "logs\\sub\\e.log"]
+            .iter()
+            .map(|path| cap.admit('c', path))
+            .collect();
+        assert_eq!(admitted, vec![true, true, false, true, true, true]);
+        // The same folder on another drive is another folder
+        assert!(cap.admit('D', "logs\\f.log"));
+        assert_eq!(cap.capped_folders(), vec!["C:\\logs".to_string()]);
+    }
+
+    #[test]
+    fn test_search_options() {
+        let parse = |args: Value| SearchOptions::from_args(&args, &HashMap::new(), &CancelToken::default());
+        
+        let options = parse(json!({"pattern": "budget.xlsx"})).unwrap();
+        assert_eq!(options.description, "budget.xlsx");
+        assert_eq!(options.max_results, 1000);
+        assert!(options.is_literal());
+        assert!(!options.reads_files());
+        assert_eq!(options.timeout_ms, Value::Null);
+        assert!(!parse(json!({"pattern": "*.xlsx"})).unwrap().is_literal());
+        assert!(!parse(json!({"pattern": "budget", "search_type": "fuzzy"})).unwrap().is_literal());
+        
+        // The query is what the search is shown as, and regex searches get a timeout of their own
+        let options = parse(json!({"pattern": "^main", "search_type": "regex", "query": "ext:rs", "owner": "alice"})).unwrap();
+        assert_eq!(options.description, "ext:rs");
+        assert_eq!(options.timeout_ms, json!(REGEX_SEARCH_TIMEOUT.as_millis() as u64));
+        assert!(options.reads_files());
+        
+        let error = parse(json!({"pattern": "*", "search_type": "grep"})).err().unwrap();
+        assert_eq!(error.to_string(), "Unknown search_type 'grep' (expected glob, regex, exact, fuzzy or smart)");
+        assert!(parse(json!({"pattern": "*", "query": "AND"})).err().unwrap().to_string().starts_with("Invalid query: "));
+        assert!(parse(json!({"pattern": "*", "timeout_ms": 0})).is_err());
+    }
+    
+    #[test]
+    fn test_volume_stats() {
+        let mut stats = VolumeStats::searched('D', false);
+        stats.scanned = 40_000;
+        stats.matches = 12;
+        stats.elapsed_ms = 35;
+        assert_eq!(stats.describe(), "D: 12 matches of 40000 scanned in 35ms (cache built)");
+        assert_eq!(serde_json::to_value(&stats).unwrap(), json!({
+            "drive": "D", "cache": "miss", "scanned": 40_000, "matches": 12, "elapsed_ms": 35
+        }));
+
+        let missing = VolumeStats::unavailable('E', "Drive E: is excluded from indexing".to_string(), 0);
+        assert_eq!(missing.describe(), "E: not searched (Drive E: is excluded from indexing)");
+        assert_eq!(serde_json::to_value(&missing).unwrap()["cache"], "unavailable");
+    }
+}
@@ -0,0 +1,80 @@
+//! Field projection of search results, for the `fields` argument
+//!
+//! A structured result carries a dozen fields or more, so a page of 10,000
+//! results is megabytes on the pipe or over HTTP when the caller only wants
+//! the paths. `fields: ["path"]` keeps just the listed fields of every result
+//! (and of every partial batch); the text content then skips the per-result
+//! listing too, since a caller that names fields reads the structured results.
+//! The web API applies the same projection to its own result records.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Fields of a `fast_search` result
+pub const RESULT_FIELDS: &[&str] = &[
+    "name", "path", "drive", "size", "is_directory", "created", "modified", "accessed",
+    "match_score", "in_workspace", "project", "aliases", "alternate_paths", "link_count",
+    "is_symlink", "reparse", "reparse_target", "image", "media", "doc_stats", "content_kind",
+    "mime_type", "highlights", "attributes", "stream", "stream_of",
+];
+
+/// The fields a caller asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldProjection {
+    fields: Vec<String>,
+}
+
+impl FieldProjection {
+    /// Parse the `fields` argument (an array or a comma-separated string) against the fields there are;
+    /// `None` if it isn't given
+    pub fn from_args(args: &Value, known: &[&str]) -> Result<Option<Self>> {
+        let fields: Vec<String> = match &args["fields"] {
+            Value::Null => return Ok(None),
+            Value::String(list) => list.split(',').map(|field| field.trim().to_lowercase()).collect(),
+            Value::Array(list) => list.iter()
+                .map(|field| field.as_str().map(|field| field.trim().to_lowercase()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| anyhow!("'fields' must be an array of field names"))?,
+            _ => return Err(anyhow!("'fields' must be an array of field names")),
+        };
+        let fields: Vec<String> = fields.into_iter().filter(|field| !field.is_empty()).collect();
+        if fields.is_empty() {
+            return Err(anyhow!("'fields' names no field (known: {})", known.join(", ")));
+        }
+        if let Some(unknown) = fields.iter().find(|field| !known.contains(&field.as_str())) {
+            return Err(anyhow!("Unknown result field '{}' (known: {})", unknown, known.join(", ")));
+        }
+        Ok(Some(Self { fields }))
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Keep only the wanted fields of a result object; a wanted field the result doesn't have stays absent
+    pub fn apply(&self, mut entry: Value) -> Value {
+        if let Some(object) = entry.as_object_mut() {
+            object.retain(|key, _| self.fields.iter().any(|field| field == key));
+        }
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_keeps_only_wanted_fields() {
+        assert_eq!(FieldProjection::from_args(&json!({}), RESULT_FIELDS).unwrap(), None);
+        assert!(FieldProjection::from_args(&json!({"fields": ["path", "owner"]}), RESULT_FIELDS).is_err());
+        assert!(FieldProjection::from_args(&json!({"fields": ""}), RESULT_FIELDS).is_err());
+        assert!(FieldProjection::from_args(&json!({"fields": [1]}), RESULT_FIELDS).is_err());
+
+        let projection = FieldProjection::from_args(&json!({"fields": "Path, size"}), RESULT_FIELDS).unwrap().unwrap();
+        assert_eq!(projection.fields(), ["path", "size"]);
+        let entry = json!({"name": "a.log", "path": "logs\\a.log", "drive": "C", "size": 10, "is_directory": false});
+        assert_eq!(projection.apply(entry), json!({"path": "logs\\a.log", "size": 10}));
+    }
+}
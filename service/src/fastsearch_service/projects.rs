@@ -0,0 +1,187 @@
+//! Project root detection over the MFT cache
+//!
+//! A directory is treated as a project root when it directly contains one of
+//! the well-known marker files below. Search results are annotated with their
+//! nearest root so callers can group hits by repository.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use super::memory_profile::HeapSize;
+use super::mft_cache::FileEntry;
+
+/// Marker file names (lowercase) and the project kind they indicate, in priority order
+pub const PROJECT_MARKERS: &[(&str, &str)] = &[
+    (".git", "git"),
+    ("cargo.toml", "cargo"),
+    ("package.json", "node"),
+    ("pyproject.toml", "python"),
+    ("setup.py", "python"),
+    ("go.mod", "go"),
+    ("pom.xml", "maven"),
+    ("build.gradle", "gradle"),
+    ("cmakelists.txt", "cmake"),
+    ("composer.json", "php"),
+    ("gemfile", "ruby"),
+];
+
+/// A detected project root
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRoot {
+    /// Path of the project directory
+    pub path: String,
+    /// Display name (the directory name)
+    pub name: String,
+    /// Kind of project, taken from the marker that identified it
+    pub kind: &'static str,
+    #[serde(skip)]
+    priority: usize,
+}
+
+/// Index of project roots for a single drive
+#[derive(Debug)]
+pub struct ProjectIndex {
+    // Keyed by lowercased root path
+    roots: HashMap<String, ProjectRoot>,
+    built_at: SystemTime,
+}
+
+impl ProjectIndex {
+    /// Build the index by scanning cache entries for project markers
+    pub fn build<'a, I>(files: I, built_at: SystemTime) -> Self
+    where
+        I: IntoIterator<Item = &'a FileEntry>,
+    {
+        let mut roots: HashMap<String, ProjectRoot> = HashMap::new();
+
+        for file in files {
+            let name_lower = file.name.to_lowercase();
+            let Some(priority) = PROJECT_MARKERS.iter().position(|(marker, _)| *marker == name_lower) else {
+                continue;
+            };
+
+            let Some(root_path) = parent_path(&file.path) else {
+                continue;
+            };
+            if root_path.is_empty() {
+                continue; // Markers at the drive root don't make the whole drive a project
+            }
+
+            let key = root_path.to_lowercase();
+            let replace = roots.get(&key).map_or(true, |existing| priority < existing.priority);
+            if replace {
+                roots.insert(key, ProjectRoot {
+                    path: root_path.to_string(),
+                    name: last_component(root_path).to_string(),
+                    kind: PROJECT_MARKERS[priority].1,
+                    priority,
+                });
+            }
+        }
+
+        Self { roots, built_at }
+    }
+
+    /// Find the nearest project root containing (or equal to) the given path
+    pub fn nearest_root(&self, path: &str) -> Option<&ProjectRoot> {
+        let mut current = Some(path);
+        while let Some(candidate) = current {
+            if candidate.is_empty() {
+                break;
+            }
+            if let Some(root) = self.roots.get(&candidate.to_lowercase()) {
+                return Some(root);
+            }
+            current = parent_path(candidate);
+        }
+        None
+    }
+
+    /// Every detected root, in no particular order
+    pub fn roots(&self) -> impl Iterator<Item = &ProjectRoot> {
+        self.roots.values()
+    }
+
+    /// When this index was built (matches the cache update time it was built from)
+    pub fn built_at(&self) -> SystemTime {
+        self.built_at
+    }
+
+    /// Number of detected project roots
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Whether no project roots were detected
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+}
+
+impl HeapSize for ProjectRoot {
+    fn heap_bytes(&self) -> u64 {
+        self.path.heap_bytes() + self.name.heap_bytes()
+    }
+}
+
+impl HeapSize for ProjectIndex {
+    fn heap_bytes(&self) -> u64 {
+        self.roots.heap_bytes()
+    }
+}
+
+/// Parent portion of a backslash- or slash-separated path
+pub(crate) fn parent_path(path: &str) -> Option<&str> {
+    let trimmed = path.trim_end_matches(['\\', '/']);
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.rfind(['\\', '/']).map_or("", |idx| &trimmed[..idx]))
+}
+
+/// Last component of a backslash- or slash-separated path
+fn last_component(path: &str) -> &str {
+    let trimmed = path.trim_end_matches(['\\', '/']);
+    trimmed.rsplit(['\\', '/']).next().unwrap_or(trimmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, is_directory: bool) -> FileEntry {
+        FileEntry { is_directory, ..FileEntry::test(0, path) }
+    }
+
+    #[test]
+    fn test_nearest_root_prefers_innermost_project() {
+        let files = vec![
+            entry("Users\\dev\\repo\\.git", true),
+            entry("Users\\dev\\repo\\crates\\core\\Cargo.toml", false),
+        ];
+        let index = ProjectIndex::build(&files, SystemTime::now());
+        assert_eq!(index.len(), 2);
+
+        let root = index.nearest_root("Users\\dev\\repo\\crates\\core\\src\\lib.rs").unwrap();
+        assert_eq!(root.name, "core");
+        assert_eq!(root.kind, "cargo");
+
+        let root = index.nearest_root("Users\\dev\\repo\\README.md").unwrap();
+        assert_eq!(root.name, "repo");
+        assert_eq!(root.kind, "git");
+
+        assert!(index.nearest_root("Windows\\System32\\kernel32.dll").is_none());
+    }
+
+    #[test]
+    fn test_marker_priority() {
+        let files = vec![
+            entry("src\\app\\package.json", false),
+            entry("src\\app\\.git", true),
+        ];
+        let index = ProjectIndex::build(&files, SystemTime::now());
+        assert_eq!(index.nearest_root("src\\app\\index.js").unwrap().kind, "git");
+    }
+}
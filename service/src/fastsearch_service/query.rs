@@ -0,0 +1,450 @@
+//! Boolean query language for `fast_search`
+//!
+//! Queries combine name, extension, path, size and type predicates:
+//!
+//! ```text
+//! *.log AND path:Windows NOT ext:tmp
+//! (ext:rs OR ext:toml) path:"my project" size:>10KB
+//! type:dir name:node_modules
+//! ```
+//!
+//! Adjacent terms are joined with an implicit `AND`. Operators are the
+//! uppercase keywords `AND`, `OR` and `NOT`; parentheses group expressions
+//! and double quotes allow spaces inside a term.
+
+use anyhow::{anyhow, bail, Result};
+
+use super::matcher::{compile_pattern, PatternMatcher, SearchType};
+use super::mft_cache::FileEntry;
+
+/// A `field:` prefix a query term can start with
+#[derive(Debug, Clone, Copy)]
+pub struct QueryField {
+    /// Field name, without the colon
+    pub name: &'static str,
+    /// What the field's value matches
+    pub description: &'static str,
+    /// A term using the field
+    pub example: &'static str,
+}
+
+/// Fields query terms can use; bare terms match names like `name:`
+pub const QUERY_FIELDS: &[QueryField] = &[
+    QueryField { name: "name", description: "File name, as a glob", example: "name:node_modules" },
+    QueryField { name: "path", description: "Path contains the text (any case)", example: "path:\"program files\"" },
+    QueryField { name: "ext", description: "Extension is one of a comma-separated list", example: "ext:log,txt" },
+    QueryField {
+        name: "size",
+        description: "Size compared with `>`, `>=`, `<`, `<=`, `=` or a `min..max` range, in bytes or KB/MB/GB/TB",
+        example: "size:>10MB",
+    },
+    QueryField { name: "type", description: "`file` or `dir`", example: "type:dir" },
+];
+
+/// Comparison used by size predicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeComparison {
+    /// Strictly less than
+    Less,
+    /// Less than or equal
+    LessOrEqual,
+    /// Equal
+    Equal,
+    /// Greater than or equal
+    GreaterOrEqual,
+    /// Strictly greater than
+    Greater,
+}
+
+/// A single filter in a query
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    /// File name matches a glob (bare terms and `name:`)
+    Name(PatternMatcher),
+    /// Path contains a substring (`path:`), lowercased
+    Path(String),
+    /// Extension is one of the listed ones (`ext:log,txt`), lowercased
+    Extension(Vec<String>),
+    /// Size comparison in bytes (`size:>10MB`)
+    Size(SizeComparison, u64),
+    /// Size within an inclusive range (`size:1MB..10MB`)
+    SizeRange(u64, u64),
+    /// Entry kind (`type:file` / `type:dir`); `true` means directory
+    Directory(bool),
+}
+
+impl Predicate {
+    /// Evaluate this predicate against a cache entry
+    pub fn matches(&self, file: &FileEntry) -> bool {
+        match self {
+            Predicate::Name(matcher) => matcher.is_match(&file.name),
+            Predicate::Path(fragment) => file.path.to_lowercase().contains(fragment.as_str()),
+            Predicate::Extension(exts) => file
+                .extension
+                .as_ref()
+                .map_or(false, |ext| exts.iter().any(|e| e == ext)),
+            Predicate::Size(cmp, bytes) => match cmp {
+                SizeComparison::Less => file.size < *bytes,
+                SizeComparison::LessOrEqual => file.size <= *bytes,
+                SizeComparison::Equal => file.size == *bytes,
+                SizeComparison::GreaterOrEqual => file.size >= *bytes,
+                SizeComparison::Greater => file.size > *bytes,
+            },
+            Predicate::SizeRange(min, max) => file.size >= *min && file.size <= *max,
+            Predicate::Directory(is_dir) => file.is_directory == *is_dir,
+        }
+    }
+}
+
+/// Parsed query expression
+#[derive(Debug, Clone)]
+pub enum QueryExpr {
+    /// Both sides must match
+    And(Box<QueryExpr>, Box<QueryExpr>),
+    /// Either side must match
+    Or(Box<QueryExpr>, Box<QueryExpr>),
+    /// The inner expression must not match
+    Not(Box<QueryExpr>),
+    /// A single predicate
+    Term(Predicate),
+}
+
+impl QueryExpr {
+    /// Parse a query string into an expression tree
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            bail!("Query is empty");
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some((token, offset)) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {} at position {}", token.describe(), offset);
+        }
+        Ok(expr)
+    }
+
+    /// Evaluate the expression against a cache entry
+    pub fn matches(&self, file: &FileEntry) -> bool {
+        match self {
+            QueryExpr::And(left, right) => left.matches(file) && right.matches(file),
+            QueryExpr::Or(left, right) => left.matches(file) || right.matches(file),
+            QueryExpr::Not(inner) => !inner.matches(file),
+            QueryExpr::Term(predicate) => predicate.matches(file),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::And => "AND".to_string(),
+            Token::Or => "OR".to_string(),
+            Token::Not => "NOT".to_string(),
+            Token::Term(term) => format!("term '{}'", term),
+        }
+    }
+}
+
+/// Split a query into tokens, each paired with its character offset
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' || c == ')' {
+            tokens.push((if c == '(' { Token::LParen } else { Token::RParen }, i));
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut word = String::new();
+        let mut quoted = false;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+            if chars[i] == '"' {
+                quoted = true;
+                let close = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == '"')
+                    .ok_or_else(|| anyhow!("Unterminated quote at position {}", i))?;
+                word.extend(&chars[i + 1..i + 1 + close]);
+                i += close + 2;
+            } else {
+                word.push(chars[i]);
+                i += 1;
+            }
+        }
+
+        let token = match word.as_str() {
+            "AND" if !quoted => Token::And,
+            "OR" if !quoted => Token::Or,
+            "NOT" if !quoted => Token::Not,
+            _ => Token::Term(word),
+        };
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map_or_else(
+            || self.tokens.last().map_or(0, |(_, offset)| *offset),
+            |(_, offset)| *offset,
+        )
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = QueryExpr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                // Implicit AND between adjacent terms
+                Some(Token::Term(_)) | Some(Token::Not) | Some(Token::LParen) => {}
+                _ => break,
+            }
+            let right = self.parse_unary()?;
+            left = QueryExpr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            let inner = self.parse_unary()?;
+            return Ok(QueryExpr::Not(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<QueryExpr> {
+        let offset = self.offset();
+        match self.tokens.get(self.pos).map(|(token, _)| token.clone()) {
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    bail!("Missing ')' for '(' at position {}", offset);
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(Token::Term(term)) => {
+                self.pos += 1;
+                parse_term(&term)
+                    .map(QueryExpr::Term)
+                    .map_err(|e| anyhow!("{} at position {}", e, offset))
+            }
+            Some(token) => bail!("Expected a search term but found {} at position {}", token.describe(), offset),
+            None => bail!("Query ends unexpectedly after position {}", offset),
+        }
+    }
+}
+
+/// Parse a single term, with or without a `field:` prefix
+fn parse_term(term: &str) -> Result<Predicate> {
+    let (field, value) = match term.split_once(':') {
+        Some((field, value)) if is_field(field) => (field.to_lowercase(), value),
+        _ => return name_predicate(term),
+    };
+
+    if value.is_empty() {
+        bail!("Missing value for '{}:'", field);
+    }
+
+    match field.as_str() {
+        "name" => name_predicate(value),
+        "path" => Ok(Predicate::Path(value.to_lowercase())),
+        "ext" => Ok(Predicate::Extension(
+            value
+                .split([',', ';'])
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+        )),
+        "size" => parse_size_predicate(value),
+        "type" => match value.to_lowercase().as_str() {
+            "file" | "f" => Ok(Predicate::Directory(false)),
+            "dir" | "directory" | "folder" | "d" => Ok(Predicate::Directory(true)),
+            other => bail!("Unknown type '{}' (expected file or dir)", other),
+        },
+        _ => unreachable!("is_field covers every handled field"),
+    }
+}
+
+/// Whether a prefix is a known query field (anything else, e.g. `C:`, is part of a name)
+fn is_field(field: &str) -> bool {
+    QUERY_FIELDS.iter().any(|known| known.name.eq_ignore_ascii_case(field))
+}
+
+fn name_predicate(pattern: &str) -> Result<Predicate> {
+    compile_pattern(pattern, SearchType::Glob).map(Predicate::Name)
+}
+
+fn parse_size_predicate(value: &str) -> Result<Predicate> {
+    if let Some((min, max)) = value.split_once("..") {
+        let (min, max) = (parse_size(min)?, parse_size(max)?);
+        if min > max {
+            bail!("Size range '{}' has its minimum above its maximum", value);
+        }
+        return Ok(Predicate::SizeRange(min, max));
+    }
+
+    let (cmp, rest) = if let Some(rest) = value.strip_prefix(">=") {
+        (SizeComparison::GreaterOrEqual, rest)
+    } else if let Some(rest) = value.strip_prefix("<=") {
+        (SizeComparison::LessOrEqual, rest)
+    } else if let Some(rest) = value.strip_prefix('>') {
+        (SizeComparison::Greater, rest)
+    } else if let Some(rest) = value.strip_prefix('<') {
+        (SizeComparison::Less, rest)
+    } else if let Some(rest) = value.strip_prefix('=') {
+        (SizeComparison::Equal, rest)
+    } else {
+        (SizeComparison::Equal, value)
+    };
+
+    Ok(Predicate::Size(cmp, parse_size(rest)?))
+}
+
+/// Parse a size such as `512`, `10KB`, `1.5 GB` (binary units)
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size '{}'", value))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        "t" | "tb" | "tib" => 1024 * 1024 * 1024 * 1024,
+        other => bail!("Unknown size unit '{}' in '{}'", other, value),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Human-readable size (binary units)
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64, is_directory: bool) -> FileEntry {
+        FileEntry { size, is_directory, ..FileEntry::test(0, path) }
+    }
+
+    #[test]
+    fn test_boolean_query() {
+        let query = QueryExpr::parse("*.log AND path:Windows NOT ext:tmp").unwrap();
+        assert!(query.matches(&file("Windows\\Logs\\setup.log", 10, false)));
+        assert!(!query.matches(&file("Users\\me\\setup.log", 10, false)));
+
+        let query = QueryExpr::parse("path:windows NOT ext:tmp").unwrap();
+        assert!(!query.matches(&file("Windows\\Temp\\x.tmp", 10, false)));
+    }
+
+    #[test]
+    fn test_or_grouping_and_size() {
+        let query = QueryExpr::parse("(ext:rs OR ext:toml) size:>1KB").unwrap();
+        assert!(query.matches(&file("src\\main.rs", 4096, false)));
+        assert!(!query.matches(&file("src\\main.rs", 100, false)));
+        assert!(!query.matches(&file("src\\main.py", 4096, false)));
+
+        let query = QueryExpr::parse("type:dir name:node_modules").unwrap();
+        assert!(query.matches(&file("app\\node_modules", 0, true)));
+        assert!(!query.matches(&file("app\\node_modules", 0, false)));
+    }
+
+    #[test]
+    fn test_quoted_terms() {
+        let query = QueryExpr::parse(r#"path:"program files" *.exe"#).unwrap();
+        assert!(query.matches(&file("Program Files\\App\\app.exe", 1, false)));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(QueryExpr::parse("").is_err());
+        assert!(QueryExpr::parse("(*.log").unwrap_err().to_string().contains("Missing ')'"));
+        assert!(QueryExpr::parse("*.log AND").is_err());
+        assert!(QueryExpr::parse("size:>lots").is_err());
+        assert!(QueryExpr::parse("path:\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_field_examples_parse() {
+        for field in QUERY_FIELDS {
+            assert!(QueryExpr::parse(field.example).is_ok(), "{}", field.example);
+            assert!(field.example.starts_with(&format!("{}:", field.name)));
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10KB").unwrap(), 10 * 1024);
+        assert_eq!(parse_size("1.5 GB").unwrap(), 1536 * 1024 * 1024);
+        assert!(parse_size("10 parsecs").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! A cap on searches scanning at the same time
+//!
+//! Every `fast_search` scans the cache on the thread of its request, so a
+//! burst of tool calls (an agent fanning out, a script in a loop) would run
+//! that many full scans side by side and slow all of them down. Searches take
+//! a slot before they scan; once all slots are taken, later searches wait in
+//! line, first come first served, and their response reports where in line
+//! they were and for how long (`_meta.queue`). The number of slots comes from
+//! `FASTSEARCH_MAX_CONCURRENT_SEARCHES`, defaulting to half the cores.
+//!
+//! A search cancelled while waiting leaves the line without scanning.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use log::{debug, warn};
+use parking_lot::{Condvar, Mutex};
+
+use super::cancellation::CancelToken;
+
+/// Environment variable with the number of searches that may scan at once
+pub const MAX_CONCURRENT_SEARCHES_ENV: &str = "FASTSEARCH_MAX_CONCURRENT_SEARCHES";
+
+/// How often a waiting search looks at its cancel token
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default)]
+struct LineState {
+    /// Searches holding a slot
+    running: usize,
+    /// Tickets of the searches waiting, first in line first
+    waiting: VecDeque<u64>,
+    next_ticket: u64,
+}
+
+/// Slots for concurrent searches, and the line for them
+#[derive(Debug)]
+pub struct QueryLimiter {
+    max_concurrent: usize,
+    state: Mutex<LineState>,
+    freed: Condvar,
+}
+
+/// Where a search waited before it got its slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueWait {
+    /// Place in line when the search arrived (1 = next)
+    pub position: usize,
+    /// Searches scanning when it arrived
+    pub running: usize,
+    pub waited_ms: u64,
+}
+
+/// A slot held for as long as the permit lives
+#[derive(Debug)]
+pub struct QueryPermit<'a> {
+    limiter: &'a QueryLimiter,
+    /// `None` if the search got a slot right away
+    pub queued: Option<QueueWait>,
+}
+
+impl QueryLimiter {
+    /// Limiter with this many slots (at least one)
+    pub fn new(max_concurrent: usize) -> Self {
+        Self { max_concurrent: max_concurrent.max(1), state: Mutex::default(), freed: Condvar::new() }
+    }
+
+    /// Slots from `FASTSEARCH_MAX_CONCURRENT_SEARCHES`, half the cores if unset or invalid
+    pub fn from_env() -> Self {
+        let default = std::thread::available_parallelism().map_or(2, |cores| (cores.get() / 2).max(2));
+        let max_concurrent = match std::env::var(MAX_CONCURRENT_SEARCHES_ENV) {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|e| {
+                warn!("{}; allowing {} concurrent searches", e, default);
+                default
+            }),
+            Err(_) => default,
+        };
+        Self::new(max_concurrent)
+    }
+
+    /// Parse a slot count ("4")
+    pub fn parse(value: &str) -> Result<usize> {
+        match value.trim().parse::<usize>() {
+            Ok(0) => bail!("{} must be at least 1", MAX_CONCURRENT_SEARCHES_ENV),
+            Ok(slots) => Ok(slots),
+            Err(_) => Err(anyhow!("Invalid {} '{}' (expected a whole number)", MAX_CONCURRENT_SEARCHES_ENV, value)),
+        }
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Searches scanning and searches waiting right now
+    pub fn load(&self) -> (usize, usize) {
+        let state = self.state.lock();
+        (state.running, state.waiting.len())
+    }
+
+    /// Take a slot, waiting in line for one if all are taken
+    ///
+    /// Fails if `cancel` is cancelled before the search's turn comes.
+    pub fn acquire(&self, cancel: &CancelToken) -> Result<QueryPermit<'_>> {
+        let mut state = self.state.lock();
+        if state.waiting.is_empty() && state.running < self.max_concurrent {
+            state.running += 1;
+            return Ok(QueryPermit { limiter: self, queued: None });
+        }
+
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.waiting.push_back(ticket);
+        let position = state.waiting.len();
+        let running = state.running;
+        let arrived = Instant::now();
+        debug!("Search queued at position {} ({} running)", position, running);
+
+        loop {
+            if cancel.is_cancelled() {
+                state.waiting.retain(|waiting| *waiting != ticket);
+                // The search behind may be next now
+                self.freed.notify_all();
+                bail!("Search cancelled while waiting for one of the {} search slots", self.max_concurrent);
+            }
+            if state.waiting.front() == Some(&ticket) && state.running < self.max_concurrent {
+                state.waiting.pop_front();
+                state.running += 1;
+                // More than one slot may have come free
+                self.freed.notify_all();
+                let waited_ms = arrived.elapsed().as_millis() as u64;
+                return Ok(QueryPermit { limiter: self, queued: Some(QueueWait { position, running, waited_ms }) });
+            }
+            self.freed.wait_for(&mut state, CANCEL_POLL);
+        }
+    }
+}
+
+impl Drop for QueryPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().running -= 1;
+        self.limiter.freed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn test_waits_in_line_for_a_slot() {
+        let limiter = QueryLimiter::new(1);
+        let first = limiter.acquire(&CancelToken::new()).unwrap();
+        assert_eq!(first.queued, None);
+
+        let shared = &limiter;
+        thread::scope(|scope| {
+            let (got_slot, slot) = mpsc::channel();
+            scope.spawn(move || {
+                let permit = shared.acquire(&CancelToken::new()).unwrap();
+                got_slot.send(permit.queued).unwrap();
+            });
+            while limiter.load() != (1, 1) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            assert!(slot.try_recv().is_err());
+
+            drop(first);
+            let queued = slot.recv().unwrap().unwrap();
+            assert_eq!((queued.position, queued.running), (1, 1));
+        });
+        assert_eq!(limiter.load(), (0, 0));
+    }
+
+    #[test]
+    fn test_cancelled_while_waiting() {
+        let limiter = QueryLimiter::new(1);
+        let _running = limiter.acquire(&CancelToken::new()).unwrap();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        assert!(limiter.acquire(&cancel).is_err());
+        assert_eq!(limiter.load(), (1, 0));
+
+        assert_eq!(QueryLimiter::parse(" 4 ").unwrap(), 4);
+        assert!(QueryLimiter::parse("0").is_err());
+        assert!(QueryLimiter::parse("many").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! Reparse points: symbolic links, junctions and cloud placeholders
+//!
+//! NTFS marks links and other special entries with a reparse tag saying which
+//! filter driver interprets them. The MFT cache keeps the tag of every entry
+//! (0 for ordinary files), so `fast_search` can leave reparse points out or
+//! return only them with `reparse_points`. Link targets aren't in the MFT in a
+//! usable form, so they're read from disk for the results that need them.
+//!
+//! The MFT has no entries below a junction or a directory symlink; the files
+//! live at the target. A `path` filter running through a link is therefore
+//! followed to the target, unless `reparse_points` says otherwise.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use super::mft_cache::FileEntry;
+
+/// Links followed in a row before giving up (cycles, long chains)
+pub const MAX_LINK_HOPS: usize = 8;
+
+/// Reparse tags of the common kinds of reparse point
+pub mod reparse_tag {
+    /// Junction or volume mount point
+    pub const MOUNT_POINT: u32 = 0xA000_0003;
+    /// Symbolic link
+    pub const SYMLINK: u32 = 0xA000_000C;
+    /// Deduplicated file
+    pub const DEDUP: u32 = 0x8000_0013;
+    /// App execution alias (`WindowsApps\python.exe`)
+    pub const APPEXECLINK: u32 = 0x8000_001B;
+    /// Cloud files placeholder (OneDrive); bits 12-15 vary between providers' tags
+    pub const CLOUD: u32 = 0x9000_001A;
+    /// Bits that are the same across all cloud tags
+    pub const CLOUD_MASK: u32 = 0xFFFF_0FFF;
+}
+
+/// What a reparse point is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReparseKind {
+    Symlink,
+    Junction,
+    Cloud,
+    AppExecLink,
+    Dedup,
+    /// Any other tag (filter drivers define their own)
+    Other,
+}
+
+impl ReparseKind {
+    /// Kind of a reparse tag; `None` for entries that aren't reparse points
+    pub fn of(tag: u32) -> Option<Self> {
+        Some(match tag {
+            0 => return None,
+            reparse_tag::SYMLINK => ReparseKind::Symlink,
+            reparse_tag::MOUNT_POINT => ReparseKind::Junction,
+            reparse_tag::APPEXECLINK => ReparseKind::AppExecLink,
+            reparse_tag::DEDUP => ReparseKind::Dedup,
+            tag if tag & reparse_tag::CLOUD_MASK == reparse_tag::CLOUD => ReparseKind::Cloud,
+            _ => ReparseKind::Other,
+        })
+    }
+
+    /// Name used in results
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReparseKind::Symlink => "symlink",
+            ReparseKind::Junction => "junction",
+            ReparseKind::Cloud => "cloud",
+            ReparseKind::AppExecLink => "app_exec_link",
+            ReparseKind::Dedup => "dedup",
+            ReparseKind::Other => "other",
+        }
+    }
+
+    /// Whether the entry points somewhere else (and so has a target to report)
+    pub fn is_link(&self) -> bool {
+        matches!(self, ReparseKind::Symlink | ReparseKind::Junction)
+    }
+}
+
+/// How `fast_search` treats reparse points
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReparseMode {
+    /// Return them like any entry, and follow `path` filters through links
+    #[default]
+    Follow,
+    /// Leave them out, and don't follow links
+    Skip,
+    /// Return nothing but reparse points
+    Only,
+}
+
+impl ReparseMode {
+    /// Parse the `reparse_points` argument
+    pub fn from_args(args: &Value) -> Result<Self> {
+        match &args["reparse_points"] {
+            Value::Null => Ok(ReparseMode::default()),
+            Value::String(mode) => match mode.to_lowercase().as_str() {
+                "follow" => Ok(ReparseMode::Follow),
+                "skip" => Ok(ReparseMode::Skip),
+                "only" => Ok(ReparseMode::Only),
+                _ => Err(anyhow!("Unknown reparse_points '{}' (expected follow, skip or only)", mode)),
+            },
+            _ => Err(anyhow!("'reparse_points' must be follow, skip or only")),
+        }
+    }
+
+    /// Whether an entry passes
+    pub fn matches(&self, file: &FileEntry) -> bool {
+        match self {
+            ReparseMode::Follow => true,
+            ReparseMode::Skip => file.reparse_tag == 0,
+            ReparseMode::Only => file.reparse_tag != 0,
+        }
+    }
+}
+
+/// Target of a link as stored in it, without the `\\?\` or `\??\` prefix
+pub fn read_target(path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    let target = target.to_string_lossy();
+    let target = target.strip_prefix(r"\\?\").or_else(|| target.strip_prefix(r"\??\")).unwrap_or(&target);
+    Some(target.to_string())
+}
+
+/// Where a link points, as a drive and a path relative to that drive's root
+///
+/// `link` is the link's path relative to `root`, the folder its drive's cache
+/// paths are relative to. Targets with a drive letter may be on another
+/// drive; relative targets and absolute paths below `root` stay on `drive`.
+/// Targets outside every drive (other machines, `..` past the root) give `None`.
+pub fn resolve_target(drive: char, root: &Path, link: &str, target: &str) -> Option<(char, String)> {
+    let bytes = target.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let rest = &target[2..];
+        return normalize("", rest).map(|path| ((bytes[0] as char).to_ascii_uppercase(), path));
+    }
+    if target.starts_with(r"\\") {
+        return None;
+    }
+    if target.starts_with(['\\', '/']) {
+        // Absolute: below the drive's folder on disk, or (on Windows) from the drive root
+        let root = root.to_string_lossy().replace('/', "\\");
+        let root = root.trim_end_matches('\\');
+        let target = target.replace('/', "\\");
+        let path = match target.strip_prefix(root) {
+            Some(rest) if !root.is_empty() && (rest.is_empty() || rest.starts_with('\\')) => normalize("", rest),
+            _ if cfg!(windows) => normalize("", &target),
+            _ => None,
+        };
+        return path.map(|path| (drive, path));
+    }
+    let parent = link.rsplit_once('\\').map_or("", |(parent, _)| parent);
+    normalize(parent, target).map(|path| (drive, path))
+}
+
+/// Join `path` onto `base`, resolving `.` and `..`; `None` if it climbs above the root
+fn normalize(base: &str, path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = base.split('\\').filter(|p| !p.is_empty()).collect();
+    for part in path.split(['\\', '/']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("\\"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reparse_kinds() {
+        assert_eq!(ReparseKind::of(0), None);
+        assert_eq!(ReparseKind::of(reparse_tag::SYMLINK), Some(ReparseKind::Symlink));
+        assert_eq!(ReparseKind::of(reparse_tag::MOUNT_POINT), Some(ReparseKind::Junction));
+        // OneDrive uses one of the numbered cloud tags
+        assert_eq!(ReparseKind::of(0x9000_601A), Some(ReparseKind::Cloud));
+        assert_eq!(ReparseKind::of(0x8000_0017), Some(ReparseKind::Other));
+        assert!(ReparseKind::Junction.is_link());
+        assert!(!ReparseKind::Cloud.is_link());
+    }
+
+    #[test]
+    fn test_reparse_mode() {
+        assert_eq!(ReparseMode::from_args(&json!({})).unwrap(), ReparseMode::Follow);
+        assert_eq!(ReparseMode::from_args(&json!({"reparse_points": "Only"})).unwrap(), ReparseMode::Only);
+        assert!(ReparseMode::from_args(&json!({"reparse_points": "never"})).is_err());
+        assert!(ReparseMode::from_args(&json!({"reparse_points": true})).is_err());
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        let root = Path::new("C:\\");
+        assert_eq!(resolve_target('C', root, "Users\\me\\Docs", "D:\\Docs\\"), Some(('D', "Docs".to_string())));
+        assert_eq!(resolve_target('C', root, "Users\\me\\Docs", "d:"), Some(('D', String::new())));
+        assert_eq!(resolve_target('C', root, "Users\\me\\link", "..\\shared\\.\\x"), Some(('C', "Users\\shared\\x".to_string())));
+        assert_eq!(resolve_target('C', root, "link", "..\\x"), None);
+        assert_eq!(resolve_target('C', root, "link", "\\\\server\\share"), None);
+
+        // Directory indexes: absolute targets inside the mounted folder
+        let root = Path::new("/data/projects");
+        assert_eq!(resolve_target('P', root, "latest", "/data/projects/app/v2"), Some(('P', "app\\v2".to_string())));
+        assert_eq!(resolve_target('P', root, "latest", "/data/projects-old/app"), if cfg!(windows) {
+            Some(('P', "data\\projects-old\\app".to_string()))
+        } else {
+            None
+        });
+    }
+}
@@ -0,0 +1,460 @@
+//! Scheduled report jobs
+//!
+//! Heavy aggregations over the MFT cache (disk usage, duplicate candidates,
+//! stale files) are defined in `reports.json` and run periodically in the
+//! background. The latest result of each job is kept in memory, exposed as an
+//! MCP resource (`fastsearch://reports/<name>`) and optionally written to disk.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::info;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::json_store::{data_dir, named_file, JsonStore};
+use super::mft_cache::{FileEntry, MftCache};
+
+/// File name of the report job configuration inside the data directory
+const REPORTS_CONFIG_FILE: &str = "reports.json";
+
+/// URI prefix of report resources
+pub const REPORT_URI_PREFIX: &str = "fastsearch://reports/";
+
+/// Folder of the data directory that imported jobs write their output to
+pub const REPORTS_DIR: &str = "reports";
+
+/// Aggregation performed by a report job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportKind {
+    /// Total size and file count per directory directly below the scope
+    DiskUsage,
+    /// Files sharing the same name and size
+    Duplicates,
+    /// Files not modified for `stale_days` days
+    StaleFiles,
+}
+
+impl ReportKind {
+    /// Name of the report kind as used in the configuration
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportKind::DiskUsage => "disk_usage",
+            ReportKind::Duplicates => "duplicates",
+            ReportKind::StaleFiles => "stale_files",
+        }
+    }
+}
+
+/// A configured report job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportJob {
+    /// Unique name, also used in the resource URI
+    pub name: String,
+    /// Aggregation to run
+    pub kind: ReportKind,
+    /// Drive to analyse
+    #[serde(default = "default_drive")]
+    pub drive: char,
+    /// Directory to restrict the report to (relative to the drive root, empty for the whole drive)
+    #[serde(default)]
+    pub path: String,
+    /// How often the report is regenerated
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u64,
+    /// Optional file the report is written to after each run
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+    /// Maximum number of entries in the report
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// Ignore files smaller than this many bytes
+    #[serde(default)]
+    pub min_size: u64,
+    /// Age in days after which a file counts as stale
+    #[serde(default = "default_stale_days")]
+    pub stale_days: u64,
+}
+
+fn default_drive() -> char {
+    'C'
+}
+
+fn default_interval_minutes() -> u64 {
+    60
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+fn default_stale_days() -> u64 {
+    365
+}
+
+impl ReportJob {
+    /// Resource URI under which the latest report is exposed
+    pub fn uri(&self) -> String {
+        format!("{}{}", REPORT_URI_PREFIX, self.name)
+    }
+
+    /// The job with its output file moved to the reports folder of `data_dir`
+    ///
+    /// For jobs from a settings bundle: the service writes the output
+    /// elevated, so an imported job keeps only the file name of its output.
+    pub fn confined_to(mut self, data_dir: &Path) -> Result<Self> {
+        if let Some(output) = &self.output {
+            let output = output.to_string_lossy();
+            let name = output.rsplit(['/', '\\']).next().unwrap_or_default();
+            let confined = named_file(data_dir, REPORTS_DIR, name)
+                .with_context(|| format!("Report job '{}' has no usable output file name", self.name))?;
+            self.output = Some(confined);
+        }
+        Ok(self)
+    }
+}
+
+/// Report job configuration file contents
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportConfig {
+    /// Configured jobs
+    #[serde(default)]
+    pub jobs: Vec<ReportJob>,
+}
+
+/// Result of a report run
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Name of the job that produced the report
+    pub job: String,
+    /// Aggregation that was run
+    pub kind: ReportKind,
+    /// Drive the report covers
+    pub drive: char,
+    /// When the report was generated (UNIX timestamp)
+    pub generated: i64,
+    /// How long the aggregation took
+    pub duration_ms: u64,
+    /// Aggregation output
+    pub data: Value,
+}
+
+/// Runs configured report jobs on their interval and keeps the latest results
+#[derive(Debug)]
+pub struct ReportScheduler {
+    file: JsonStore<ReportConfig>,
+    jobs: RwLock<Vec<ReportJob>>,
+    last_run: Mutex<HashMap<String, Instant>>,
+    reports: RwLock<HashMap<String, Report>>,
+}
+
+impl ReportScheduler {
+    /// Default location: `%LOCALAPPDATA%\FastSearchMCP\reports.json`
+    pub fn default_path() -> PathBuf {
+        data_dir().join(REPORTS_CONFIG_FILE)
+    }
+
+    /// Load job definitions, starting with no jobs if the file doesn't exist or can't be read
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let file: JsonStore<ReportConfig> = JsonStore::at(&path, "report config");
+        let config = file.load().unwrap_or_default();
+
+        info!("Loaded {} report jobs from {}", config.jobs.len(), path.as_ref().display());
+        Self {
+            file,
+            ..Self::with_jobs(config.jobs)
+        }
+    }
+
+    /// Create a scheduler for the given jobs that is never written to disk
+    pub fn with_jobs(jobs: Vec<ReportJob>) -> Self {
+        Self {
+            file: JsonStore::in_memory("report config"),
+            jobs: RwLock::new(jobs),
+            last_run: Mutex::new(HashMap::new()),
+            reports: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// All configured jobs
+    pub fn jobs(&self) -> Vec<ReportJob> {
+        self.jobs.read().clone()
+    }
+
+    /// Look up a job by name
+    pub fn job(&self, name: &str) -> Option<ReportJob> {
+        self.jobs.read().iter().find(|job| job.name == name).cloned()
+    }
+
+    /// Replace the configured jobs and write them back to the config file
+    pub fn set_jobs(&self, jobs: Vec<ReportJob>) -> Result<()> {
+        *self.jobs.write() = jobs;
+        self.persist()
+    }
+
+    /// Write the job configuration to disk (atomically, via a temporary file)
+    fn persist(&self) -> Result<()> {
+        self.file.save(&ReportConfig { jobs: self.jobs() })
+    }
+
+    /// Jobs whose interval has elapsed; they are marked as run so a slow run isn't started twice
+    pub fn take_due_jobs(&self, now: Instant) -> Vec<ReportJob> {
+        let mut last_run = self.last_run.lock();
+        self.jobs
+            .read()
+            .iter()
+            .filter(|job| {
+                let interval = Duration::from_secs(job.interval_minutes.max(1) * 60);
+                match last_run.get(&job.name) {
+                    Some(last) if now.duration_since(*last) < interval => false,
+                    _ => {
+                        last_run.insert(job.name.clone(), now);
+                        true
+                    }
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Store a finished report and write it to the job's output file, if any
+    pub fn record(&self, report: Report) -> Result<()> {
+        self.last_run.lock().insert(report.job.clone(), Instant::now());
+
+        let output = self.job(&report.job).and_then(|job| job.output);
+        if let Some(output) = output {
+            if let Some(dir) = output.parent() {
+                fs::create_dir_all(dir).context("Failed to create report directory")?;
+            }
+            let temp = output.with_extension("tmp");
+            fs::write(&temp, serde_json::to_string_pretty(&report)?).context("Failed to write report")?;
+            fs::rename(&temp, &output).context("Failed to replace report file")?;
+        }
+
+        self.reports.write().insert(report.job.clone(), report);
+        Ok(())
+    }
+
+    /// Latest report produced by a job
+    pub fn latest(&self, name: &str) -> Option<Report> {
+        self.reports.read().get(name).cloned()
+    }
+
+    /// MCP resource descriptors for every configured job
+    pub fn resources(&self) -> Vec<Value> {
+        self.jobs
+            .read()
+            .iter()
+            .map(|job| json!({
+                "uri": job.uri(),
+                "name": job.name,
+                "description": format!(
+                    "{} report for {}:\\{} (every {} min)",
+                    job.kind.as_str(), job.drive, job.path, job.interval_minutes
+                ),
+                "mimeType": "application/json"
+            }))
+            .collect()
+    }
+}
+
+/// Run a report job against a drive's MFT cache
+pub fn generate_report(job: &ReportJob, cache: &MftCache) -> Report {
+    let start = Instant::now();
+    let files = cache.get_files();
+    let scope = job.path.trim_matches(|c| c == '\\' || c == '/').replace('/', "\\").to_lowercase();
+
+    let data = match job.kind {
+        ReportKind::DiskUsage => disk_usage(files.values(), &scope, job),
+        ReportKind::Duplicates => duplicates(files.values(), &scope, job),
+        ReportKind::StaleFiles => stale_files(files.values(), &scope, job, SystemTime::now()),
+    };
+
+    Report {
+        job: job.name.clone(),
+        kind: job.kind,
+        drive: job.drive,
+        generated: Utc::now().timestamp(),
+        duration_ms: start.elapsed().as_millis() as u64,
+        data,
+    }
+}
+
+/// Part of a path below the scope directory, or `None` if the path is outside it
+pub(crate) fn scoped_remainder<'a>(path: &'a str, scope: &str) -> Option<&'a str> {
+    if scope.is_empty() {
+        return Some(path);
+    }
+    let prefix = path.get(..scope.len())?;
+    if !prefix.eq_ignore_ascii_case(scope) {
+        return None;
+    }
+    path[scope.len()..].strip_prefix('\\')
+}
+
+fn in_scope(file: &FileEntry, scope: &str, job: &ReportJob) -> bool {
+    !file.is_directory && file.size >= job.min_size && scoped_remainder(&file.path, scope).is_some()
+}
+
+fn disk_usage<'a>(files: impl Iterator<Item = &'a FileEntry>, scope: &str, job: &ReportJob) -> Value {
+    let mut usage: HashMap<String, (u64, u64)> = HashMap::new();
+    let (mut total_bytes, mut total_files) = (0u64, 0u64);
+
+    for file in files.filter(|f| in_scope(f, scope, job)) {
+        let remainder = scoped_remainder(&file.path, scope).unwrap_or_default();
+        let child = match remainder.split_once('\\') {
+            Some((dir, _)) => dir.to_string(),
+            None => "(files)".to_string(),
+        };
+        let entry = usage.entry(child).or_default();
+        entry.0 += file.size;
+        entry.1 += 1;
+        total_bytes += file.size;
+        total_files += 1;
+    }
+
+    let mut entries: Vec<(String, (u64, u64))> = usage.into_iter().collect();
+    entries.sort_by(|a, b| b.1 .0.cmp(&a.1 .0));
+    entries.truncate(job.limit);
+
+    json!({
+        "total_bytes": total_bytes,
+        "total_files": total_files,
+        "entries": entries.into_iter().map(|(name, (bytes, count))| json!({
+            "name": name,
+            "bytes": bytes,
+            "files": count
+        })).collect::<Vec<_>>()
+    })
+}
+
+fn duplicates<'a>(files: impl Iterator<Item = &'a FileEntry>, scope: &str, job: &ReportJob) -> Value {
+    let mut groups: HashMap<(u64, String), Vec<&FileEntry>> = HashMap::new();
+    for file in files.filter(|f| f.size > 0 && in_scope(f, scope, job)) {
+        groups.entry((file.size, file.name.to_lowercase())).or_default().push(file);
+    }
+
+    let mut groups: Vec<(u64, Vec<&FileEntry>)> = groups
+        .into_iter()
+        .filter(|(_, entries)| entries.len() > 1)
+        .map(|((size, _), entries)| (size, entries))
+        .collect();
+    let wasted = |size: u64, count: usize| size * (count as u64 - 1);
+    groups.sort_by(|a, b| wasted(b.0, b.1.len()).cmp(&wasted(a.0, a.1.len())));
+    let total_wasted: u64 = groups.iter().map(|(size, entries)| wasted(*size, entries.len())).sum();
+    let group_count = groups.len();
+    groups.truncate(job.limit);
+
+    json!({
+        "total_wasted_bytes": total_wasted,
+        "total_groups": group_count,
+        "groups": groups.into_iter().map(|(size, entries)| json!({
+            "name": entries[0].name,
+            "size": size,
+            "count": entries.len(),
+            "wasted_bytes": wasted(size, entries.len()),
+            "paths": entries.iter().map(|f| format!("{}:\\{}", job.drive, f.path)).collect::<Vec<_>>()
+        })).collect::<Vec<_>>()
+    })
+}
+
+fn stale_files<'a>(
+    files: impl Iterator<Item = &'a FileEntry>,
+    scope: &str,
+    job: &ReportJob,
+    now: SystemTime,
+) -> Value {
+    let cutoff = now - Duration::from_secs(job.stale_days * 24 * 60 * 60);
+
+    let mut stale: Vec<&FileEntry> = files
+        .filter(|f| in_scope(f, scope, job) && f.modified < cutoff)
+        .collect();
+    let total_bytes: u64 = stale.iter().map(|f| f.size).sum();
+    let total_files = stale.len();
+    stale.sort_by(|a, b| b.size.cmp(&a.size));
+    stale.truncate(job.limit);
+
+    let timestamp = |t: SystemTime| t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    json!({
+        "cutoff": timestamp(cutoff),
+        "total_bytes": total_bytes,
+        "total_files": total_files,
+        "files": stale.into_iter().map(|f| json!({
+            "path": format!("{}:\\{}", job.drive, f.path),
+            "size": f.size,
+            "modified": timestamp(f.modified)
+        })).collect::<Vec<_>>()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(kind: ReportKind, path: &str) -> ReportJob {
+        serde_json::from_value(json!({"name": "test", "kind": kind, "path": path})).unwrap()
+    }
+
+    fn file(id: u64, path: &str, size: u64) -> FileEntry {
+        FileEntry { size, ..FileEntry::test(id, path) }
+    }
+
+    #[test]
+    fn test_disk_usage_groups_by_child_directory() {
+        let files = vec![
+            file(1, "Users\\a\\big.iso", 700),
+            file(2, "Users\\a\\small.txt", 10),
+            file(3, "Users\\b\\notes.md", 50),
+            file(4, "Users\\desktop.ini", 1),
+            file(5, "Windows\\explorer.exe", 5000),
+        ];
+        let data = disk_usage(files.iter(), "users", &job(ReportKind::DiskUsage, "Users"));
+
+        assert_eq!(data["total_bytes"], 761);
+        assert_eq!(data["entries"][0]["name"], "a");
+        assert_eq!(data["entries"][0]["bytes"], 710);
+        assert_eq!(data["entries"][2]["name"], "(files)");
+    }
+
+    #[test]
+    fn test_duplicates_ranked_by_wasted_space() {
+        let files = vec![
+            file(1, "a\\setup.exe", 100),
+            file(2, "b\\setup.exe", 100),
+            file(3, "c\\SETUP.EXE", 100),
+            file(4, "a\\photo.jpg", 400),
+            file(5, "b\\photo.jpg", 400),
+            file(6, "c\\photo.jpg", 401),
+        ];
+        let data = duplicates(files.iter(), "", &job(ReportKind::Duplicates, ""));
+
+        assert_eq!(data["total_groups"], 2);
+        assert_eq!(data["groups"][0]["name"], "photo.jpg");
+        assert_eq!(data["groups"][0]["wasted_bytes"], 400);
+        assert_eq!(data["groups"][1]["count"], 3);
+        assert_eq!(data["total_wasted_bytes"], 600);
+    }
+
+    #[test]
+    fn test_due_jobs_respect_interval() {
+        let scheduler = ReportScheduler::with_jobs(vec![job(ReportKind::DiskUsage, "")]);
+        let now = Instant::now();
+        assert_eq!(scheduler.take_due_jobs(now).len(), 1);
+        assert!(scheduler.take_due_jobs(now + Duration::from_secs(60)).is_empty());
+        assert_eq!(scheduler.take_due_jobs(now + Duration::from_secs(3600)).len(), 1);
+    }
+
+    #[test]
+    fn test_scoped_remainder() {
+        assert_eq!(scoped_remainder("Users\\a\\x", "users"), Some("a\\x"));
+        assert_eq!(scoped_remainder("UsersOld\\x", "users"), None);
+        assert_eq!(scoped_remainder("Users", "users"), None);
+        assert_eq!(scoped_remainder("Users\\x", ""), Some("Users\\x"));
+    }
+}
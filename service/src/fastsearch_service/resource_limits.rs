@@ -0,0 +1,342 @@
+//! CPU and memory limits for the service process
+//!
+//! The service runs elevated and keeps whole MFTs in memory, so a runaway
+//! search or rebuild could starve the machine it is meant to help. With limits
+//! configured (`--cpu-limit`/`--memory-limit` or the `FASTSEARCH_CPU_LIMIT` and
+//! `FASTSEARCH_MEMORY_LIMIT` variables), the process puts itself in a Windows
+//! Job Object at startup: CPU use is hard-capped at the given share of the
+//! machine, and allocations past the memory limit fail instead of paging the
+//! host to a halt. The job reports each memory limit breach; breaches are
+//! logged and make the health status `degraded` for a while.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+
+use super::query::parse_size;
+
+/// Environment variable with the CPU limit in percent of the machine
+pub const CPU_LIMIT_ENV: &str = "FASTSEARCH_CPU_LIMIT";
+
+/// Environment variable with the memory limit ("2GB", "512MB")
+pub const MEMORY_LIMIT_ENV: &str = "FASTSEARCH_MEMORY_LIMIT";
+
+/// How long after a breach the health status stays degraded
+const DEGRADED_AFTER_BREACH: Duration = Duration::from_secs(10 * 60);
+
+/// Limits the service process runs under
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Share of the machine's CPU time the process may use (1-100)
+    pub cpu_percent: Option<u32>,
+    /// Memory the process may commit, in bytes
+    pub memory_bytes: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Parse limits given on the command line, falling back to the environment
+    pub fn resolve(cpu: Option<&str>, memory: Option<&str>) -> Result<Self> {
+        let cpu = cpu.map(str::to_string).or_else(|| std::env::var(CPU_LIMIT_ENV).ok());
+        let memory = memory.map(str::to_string).or_else(|| std::env::var(MEMORY_LIMIT_ENV).ok());
+        Self::parse(cpu.as_deref(), memory.as_deref())
+    }
+
+    /// Parse a CPU percentage ("50", "50%") and a memory size ("2GB")
+    pub fn parse(cpu: Option<&str>, memory: Option<&str>) -> Result<Self> {
+        let cpu_percent = cpu
+            .map(|cpu| {
+                cpu.trim()
+                    .trim_end_matches('%')
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|percent| (1..=100).contains(percent))
+                    .ok_or_else(|| anyhow!("Invalid CPU limit '{}' (expected a percentage from 1 to 100)", cpu))
+            })
+            .transpose()?;
+        let memory_bytes = memory
+            .map(|memory| match parse_size(memory) {
+                Ok(bytes) if bytes >= 64 * 1024 * 1024 => Ok(bytes),
+                Ok(_) => Err(anyhow!("Memory limit '{}' is too small for the service (at least 64MB)", memory)),
+                Err(e) => Err(anyhow!("Invalid memory limit: {}", e)),
+            })
+            .transpose()?;
+        Ok(Self { cpu_percent, memory_bytes })
+    }
+
+    /// Whether no limit is set
+    pub fn is_empty(&self) -> bool {
+        self.cpu_percent.is_none() && self.memory_bytes.is_none()
+    }
+}
+
+/// Limits in force and the breaches seen since startup
+#[derive(Debug, Default)]
+pub struct LimitStatus {
+    limits: ResourceLimits,
+    /// Whether the process is in the job
+    contained: bool,
+    memory_breaches: u64,
+    last_breach: Option<SystemTime>,
+    peak_memory_bytes: Option<u64>,
+}
+
+impl LimitStatus {
+    /// Count a memory limit breach
+    pub fn record_memory_breach(&mut self, at: SystemTime) {
+        self.memory_breaches += 1;
+        self.last_breach = Some(at);
+    }
+
+    /// Whether a limit was breached recently
+    pub fn is_degraded(&self, now: SystemTime) -> bool {
+        self.last_breach
+            .and_then(|at| now.duration_since(at).ok())
+            .map_or(false, |since| since < DEGRADED_AFTER_BREACH)
+    }
+
+    /// Status as reported by the health endpoints
+    pub fn to_json(&self, now: SystemTime) -> Value {
+        json!({
+            "contained": self.contained,
+            "status": if self.is_degraded(now) { "degraded" } else { "ok" },
+            "cpu_limit_percent": self.limits.cpu_percent,
+            "memory_limit_bytes": self.limits.memory_bytes,
+            "peak_memory_bytes": self.peak_memory_bytes,
+            "memory_breaches": self.memory_breaches,
+            "last_breach": self.last_breach
+                .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_secs()),
+        })
+    }
+}
+
+static STATUS: Mutex<Option<LimitStatus>> = Mutex::new(None);
+
+/// Put the process in a job with the given limits and watch it for breaches
+///
+/// Does nothing without limits. Failing to set up the job is logged and the
+/// service runs unconstrained rather than not at all.
+pub fn contain_process(limits: ResourceLimits) {
+    if limits.is_empty() {
+        return;
+    }
+    let contained = match os::contain(&limits) {
+        Ok(()) => {
+            info!(
+                "Running in a job object (CPU limit {}, memory limit {})",
+                limits.cpu_percent.map_or("none".to_string(), |p| format!("{}%", p)),
+                limits.memory_bytes.map_or("none".to_string(), |b| format!("{} MB", b / 1024 / 1024)),
+            );
+            true
+        }
+        Err(e) => {
+            error!("Failed to apply resource limits, running without them: {}", e);
+            false
+        }
+    };
+    *STATUS.lock() = Some(LimitStatus { limits, contained, ..Default::default() });
+}
+
+/// Status of the resource limits for health reports (`null` without limits)
+pub fn limit_status() -> Value {
+    let mut status = STATUS.lock();
+    match status.as_mut() {
+        Some(status) => {
+            if status.contained {
+                status.peak_memory_bytes = os::peak_memory();
+            }
+            status.to_json(SystemTime::now())
+        }
+        None => Value::Null,
+    }
+}
+
+/// Whether the service recently ran into one of its limits
+pub fn is_degraded() -> bool {
+    STATUS.lock().as_ref().map_or(false, |status| status.is_degraded(SystemTime::now()))
+}
+
+/// Called by the job monitor when an allocation failed on the memory limit
+fn memory_limit_breached() {
+    warn!("Memory limit reached: an allocation in the service failed");
+    if let Some(status) = STATUS.lock().as_mut() {
+        status.record_memory_breach(SystemTime::now());
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use std::mem::{size_of, zeroed};
+    use std::ptr::null_mut;
+    use std::sync::OnceLock;
+    use std::thread;
+
+    use anyhow::{bail, Result};
+    use log::{debug, error};
+    use winapi::shared::basetsd::ULONG_PTR;
+    use winapi::shared::minwindef::{DWORD, LPVOID};
+    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::ioapiset::{CreateIoCompletionPort, GetQueuedCompletionStatus};
+    use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW, QueryInformationJobObject, SetInformationJobObject};
+    use winapi::um::minwinbase::LPOVERLAPPED;
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::winbase::INFINITE;
+    use winapi::um::winnt::{
+        JobObjectAssociateCompletionPortInformation, JobObjectCpuRateControlInformation,
+        JobObjectExtendedLimitInformation, HANDLE, JOBOBJECT_ASSOCIATE_COMPLETION_PORT,
+        JOBOBJECT_CPU_RATE_CONTROL_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_CPU_RATE_CONTROL_ENABLE, JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+        JOB_OBJECT_LIMIT_JOB_MEMORY, JOB_OBJECT_MSG_JOB_MEMORY_LIMIT,
+        JOB_OBJECT_MSG_PROCESS_MEMORY_LIMIT,
+    };
+
+    use super::{memory_limit_breached, ResourceLimits};
+
+    /// The job the process runs in; kept open for the life of the process
+    struct Job(HANDLE);
+
+    // Safety: job handles may be used from any thread
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    static JOB: OnceLock<Job> = OnceLock::new();
+
+    pub fn contain(limits: &ResourceLimits) -> Result<()> {
+        unsafe {
+            let job = CreateJobObjectW(null_mut(), null_mut());
+            if job.is_null() {
+                bail!("CreateJobObject failed: {}", std::io::Error::last_os_error());
+            }
+
+            if let Some(bytes) = limits.memory_bytes {
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_JOB_MEMORY;
+                info.JobMemoryLimit = bytes as usize;
+                set_information(job, JobObjectExtendedLimitInformation, &mut info)?;
+            }
+
+            if let Some(percent) = limits.cpu_percent {
+                // The rate is in hundredths of a percent of all processors
+                let mut info: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = zeroed();
+                info.ControlFlags = JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+                *info.u.CpuRate_mut() = percent * 100;
+                set_information(job, JobObjectCpuRateControlInformation, &mut info)?;
+            }
+
+            // Limit notifications arrive on a completion port
+            let port = CreateIoCompletionPort(INVALID_HANDLE_VALUE, null_mut(), 0, 1);
+            if port.is_null() {
+                bail!("CreateIoCompletionPort failed: {}", std::io::Error::last_os_error());
+            }
+            let mut association = JOBOBJECT_ASSOCIATE_COMPLETION_PORT {
+                CompletionKey: job as LPVOID,
+                CompletionPort: port,
+            };
+            set_information(job, JobObjectAssociateCompletionPortInformation, &mut association)?;
+
+            if AssignProcessToJobObject(job, GetCurrentProcess()) == 0 {
+                bail!("AssignProcessToJobObject failed: {}", std::io::Error::last_os_error());
+            }
+
+            let _ = JOB.set(Job(job));
+            let port = Job(port);
+            thread::Builder::new()
+                .name("job-monitor".to_string())
+                .spawn(move || watch(port))?;
+        }
+        Ok(())
+    }
+
+    /// Most memory the job has committed at once
+    pub fn peak_memory() -> Option<u64> {
+        let job = JOB.get()?;
+        unsafe {
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = zeroed();
+            let ok = QueryInformationJobObject(
+                job.0,
+                JobObjectExtendedLimitInformation,
+                &mut info as *mut _ as LPVOID,
+                size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as DWORD,
+                null_mut(),
+            );
+            (ok != 0).then_some(info.PeakJobMemoryUsed as u64)
+        }
+    }
+
+    unsafe fn set_information<T>(job: HANDLE, class: u32, info: &mut T) -> Result<()> {
+        if SetInformationJobObject(job, class, info as *mut T as LPVOID, size_of::<T>() as DWORD) == 0 {
+            bail!("SetInformationJobObject({}) failed: {}", class, std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Wait for job notifications until the port closes
+    fn watch(port: Job) {
+        loop {
+            let mut message: DWORD = 0;
+            let mut key: ULONG_PTR = 0;
+            let mut overlapped: LPOVERLAPPED = null_mut();
+            let ok = unsafe { GetQueuedCompletionStatus(port.0, &mut message, &mut key, &mut overlapped, INFINITE) };
+            if ok == 0 {
+                error!("Job monitor stopped: {}", std::io::Error::last_os_error());
+                return;
+            }
+            match message {
+                JOB_OBJECT_MSG_JOB_MEMORY_LIMIT | JOB_OBJECT_MSG_PROCESS_MEMORY_LIMIT => memory_limit_breached(),
+                other => debug!("Job notification {}", other),
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod os {
+    use anyhow::{bail, Result};
+
+    use super::ResourceLimits;
+
+    pub fn contain(_limits: &ResourceLimits) -> Result<()> {
+        bail!("job objects are only available on Windows")
+    }
+
+    pub fn peak_memory() -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = ResourceLimits::parse(Some("25%"), Some("2GB")).unwrap();
+        assert_eq!(limits.cpu_percent, Some(25));
+        assert_eq!(limits.memory_bytes, Some(2 * 1024 * 1024 * 1024));
+        assert!(ResourceLimits::parse(None, None).unwrap().is_empty());
+
+        assert!(ResourceLimits::parse(Some("0"), None).is_err());
+        assert!(ResourceLimits::parse(Some("150%"), None).is_err());
+        assert!(ResourceLimits::parse(None, Some("10MB")).is_err());
+        assert!(ResourceLimits::parse(None, Some("lots")).is_err());
+    }
+
+    #[test]
+    fn test_breach_degrades_status_for_a_while() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut status = LimitStatus { contained: true, ..Default::default() };
+        assert!(!status.is_degraded(start));
+
+        status.record_memory_breach(start);
+        assert!(status.is_degraded(start + Duration::from_secs(60)));
+        assert!(!status.is_degraded(start + DEGRADED_AFTER_BREACH));
+
+        let report = status.to_json(start);
+        assert_eq!(report["status"], "degraded");
+        assert_eq!(report["memory_breaches"], 1);
+        assert_eq!(report["last_breach"], 1_700_000_000);
+    }
+}
@@ -0,0 +1,220 @@
+//! Grouped and tree layouts for `fast_search` results
+//!
+//! A page of a few hundred paths is hard to read as a flat list, where most
+//! of every line repeats the folder of the line before. `output: "grouped"`
+//! splits the page by parent folder and `output: "tree"` nests it by folder.
+//! Folders come in the order of their best-ranked match, so the ranking
+//! still shows in either layout.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// How the results of a page are laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResultLayout {
+    /// One list in rank order
+    #[default]
+    Flat,
+    /// One list per parent folder
+    Grouped,
+    /// Nested folders
+    Tree,
+}
+
+impl ResultLayout {
+    /// Parse the `output` argument
+    pub fn from_args(args: &Value) -> Result<Self> {
+        match &args["output"] {
+            Value::Null => Ok(ResultLayout::default()),
+            Value::String(layout) => match layout.to_lowercase().as_str() {
+                "flat" => Ok(ResultLayout::Flat),
+                "grouped" => Ok(ResultLayout::Grouped),
+                "tree" => Ok(ResultLayout::Tree),
+                _ => Err(anyhow!("Unknown output '{}' (expected flat, grouped or tree)", layout)),
+            },
+            _ => Err(anyhow!("'output' must be flat, grouped or tree")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultLayout::Flat => "flat",
+            ResultLayout::Grouped => "grouped",
+            ResultLayout::Tree => "tree",
+        }
+    }
+}
+
+/// Results sharing a parent folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderGroup {
+    /// Full path of the folder, with its drive
+    pub folder: String,
+    /// Indexes of its results, in rank order
+    pub items: Vec<usize>,
+}
+
+/// A folder of the result tree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FolderNode {
+    /// Folder name; several levels (`Users\me`) when the ones above hold nothing else
+    pub name: String,
+    /// Full path of the folder, with its drive
+    pub path: String,
+    pub folders: Vec<FolderNode>,
+    /// Indexes of the results directly in the folder, in rank order
+    pub items: Vec<usize>,
+}
+
+/// A folder path from its components, with the separator a drive root needs (`C:\`)
+fn folder_path(path: &str) -> String {
+    if path.ends_with(':') {
+        format!("{}\\", path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// Split full result paths (`C:\Users\me\a.txt`) by parent folder
+pub fn group_by_folder(paths: &[String]) -> Vec<FolderGroup> {
+    let mut groups: Vec<FolderGroup> = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        let folder = folder_path(path.rsplit_once('\\').map_or("", |(folder, _)| folder));
+        match groups.iter_mut().find(|group| group.folder.eq_ignore_ascii_case(&folder)) {
+            Some(group) => group.items.push(i),
+            None => groups.push(FolderGroup { folder, items: vec![i] }),
+        }
+    }
+    groups
+}
+
+/// Nest full result paths by folder
+///
+/// Folders holding nothing but a single subfolder are merged with it, so the
+/// tree starts at the deepest folder all results share.
+pub fn build_tree(paths: &[String]) -> FolderNode {
+    let mut root = FolderNode::default();
+    for (i, path) in paths.iter().enumerate() {
+        let mut node = &mut root;
+        let mut components = path.split('\\').peekable();
+        while let Some(component) = components.next() {
+            if components.peek().is_none() {
+                node.items.push(i);
+                break;
+            }
+            let at = match node.folders.iter().position(|folder| folder.name.eq_ignore_ascii_case(component)) {
+                Some(at) => at,
+                None => {
+                    let parent = node.path.trim_end_matches('\\');
+                    let path = if parent.is_empty() { component.to_string() } else { format!("{}\\{}", parent, component) };
+                    node.folders.push(FolderNode { name: component.to_string(), path: folder_path(&path), ..Default::default() });
+                    node.folders.len() - 1
+                }
+            };
+            node = &mut node.folders[at];
+        }
+    }
+    root.collapse();
+    root
+}
+
+impl FolderNode {
+    /// Merge chains of folders that hold only one subfolder
+    fn collapse(&mut self) {
+        while self.items.is_empty() && self.folders.len() == 1 {
+            let child = self.folders.pop().unwrap_or_default();
+            self.name = if self.name.is_empty() { child.name } else { format!("{}\\{}", self.name, child.name) };
+            self.path = child.path;
+            self.folders = child.folders;
+            self.items = child.items;
+        }
+        for folder in &mut self.folders {
+            folder.collapse();
+        }
+    }
+
+    /// Results in the folder and below it
+    pub fn count(&self) -> usize {
+        self.items.len() + self.folders.iter().map(FolderNode::count).sum::<usize>()
+    }
+
+    /// Structured form, with `entries` giving the JSON of a result
+    pub fn to_json(&self, entries: &dyn Fn(usize) -> Vec<Value>) -> Value {
+        json!({
+            "name": self.name,
+            "path": self.path,
+            "count": self.count(),
+            "folders": self.folders.iter().map(|folder| folder.to_json(entries)).collect::<Vec<_>>(),
+            "results": self.items.iter().flat_map(|&i| entries(i)).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Indented text, with `line` giving the text of a result (one or more lines)
+    pub fn render(&self, depth: usize, line: &dyn Fn(usize) -> String, out: &mut String) {
+        let indent = "   ".repeat(depth);
+        let mut depth = depth;
+        if !self.name.is_empty() {
+            out.push_str(&format!("{}📁 {} ({})\n", indent, if depth == 0 { &self.path } else { &self.name }, self.count()));
+            depth += 1;
+        }
+        let indent = "   ".repeat(depth);
+        for &i in &self.items {
+            for text in line(i).lines() {
+                out.push_str(&format!("{}{}\n", indent, text));
+            }
+        }
+        for folder in &self.folders {
+            folder.render(depth, line, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(list: &[&str]) -> Vec<String> {
+        list.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn test_group_by_folder() {
+        let found = paths(&["C:\\src\\b.rs", "C:\\docs\\a.md", "C:\\SRC\\c.rs", "D:\\top.txt"]);
+        let groups = group_by_folder(&found);
+        assert_eq!(groups, vec![
+            FolderGroup { folder: "C:\\src".to_string(), items: vec![0, 2] },
+            FolderGroup { folder: "C:\\docs".to_string(), items: vec![1] },
+            FolderGroup { folder: "D:\\".to_string(), items: vec![3] },
+        ]);
+        assert!(ResultLayout::from_args(&json!({"output": "list"})).is_err());
+        assert_eq!(ResultLayout::from_args(&json!({"output": "Tree"})).unwrap(), ResultLayout::Tree);
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let found = paths(&[
+            "C:\\Users\\me\\Projects\\app\\main.rs",
+            "C:\\Users\\me\\notes.txt",
+            "C:\\Users\\me\\Projects\\lib\\lib.rs",
+        ]);
+        let tree = build_tree(&found);
+        assert_eq!(tree.name, "C:\\Users\\me");
+        assert_eq!(tree.path, "C:\\Users\\me");
+        assert_eq!(tree.items, vec![1]);
+        assert_eq!(tree.count(), 3);
+
+        let projects = &tree.folders[0];
+        assert_eq!((projects.name.as_str(), projects.path.as_str()), ("Projects", "C:\\Users\\me\\Projects"));
+        let names: Vec<&str> = projects.folders.iter().map(|folder| folder.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "lib"]);
+
+        let mut text = String::new();
+        tree.render(0, &|i| format!("{}. {}", i + 1, found[i].rsplit('\\').next().unwrap()), &mut text);
+        assert_eq!(text, "📁 C:\\Users\\me (3)\n   2. notes.txt\n   📁 Projects (2)\n      📁 app (1)\n         1. main.rs\n      📁 lib (1)\n         3. lib.rs\n");
+
+        // Results on several drives keep one branch per drive under an unnamed root
+        let tree = build_tree(&paths(&["C:\\a.txt", "D:\\b.txt"]));
+        assert_eq!(tree.name, "");
+        assert_eq!(tree.folders[1].path, "D:\\");
+    }
+}
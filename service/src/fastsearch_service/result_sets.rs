@@ -0,0 +1,162 @@
+//! Matches of earlier searches, for narrowing with `within_results_of`
+//!
+//! A search that lists its matches keeps the records that passed its cache
+//! filters under a short ID, returned as `result_set` with the first page. A
+//! later search given that ID takes its candidates from the set instead of the
+//! whole cache, so refining a search step by step costs as much as the
+//! previous result, not another pass over the drive. Sets are kept in memory
+//! only, and dropped after a while unused or when too many are kept.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use parking_lot::Mutex;
+
+use super::memory_profile::HeapSize;
+
+/// Result sets kept; the least recently used is dropped beyond this
+pub const MAX_RESULT_SETS: usize = 32;
+
+/// Larger results aren't kept; searches that broad are better narrowed by their arguments
+pub const MAX_RESULT_SET_SIZE: usize = 1_000_000;
+
+/// Sets not used for this long are dropped
+pub const RESULT_SET_IDLE: Duration = Duration::from_secs(30 * 60);
+
+/// The records a search matched
+#[derive(Debug)]
+pub struct ResultSet {
+    pub id: String,
+    /// Drive argument of the search ("*" for every drive)
+    pub drive: String,
+    /// Pattern or query of the search
+    pub description: String,
+    /// Record IDs by the drive letter they were found under
+    members: HashMap<char, Vec<u64>>,
+    len: usize,
+}
+
+impl ResultSet {
+    /// Number of records in the set
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set holds no records
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Record IDs found under a drive letter
+    pub fn ids(&self, drive: char) -> &[u64] {
+        self.members.get(&drive).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl HeapSize for ResultSet {
+    fn heap_bytes(&self) -> u64 {
+        self.id.heap_bytes() + self.drive.heap_bytes() + self.description.heap_bytes() + self.members.heap_bytes()
+    }
+}
+
+/// Result sets of recent searches, by ID
+#[derive(Debug, Default)]
+pub struct ResultSetStore {
+    next_id: AtomicU64,
+    sets: Mutex<HashMap<String, (Arc<ResultSet>, Instant)>>,
+}
+
+impl ResultSetStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the records `(drive letter, record ID)` a search matched, returning the set's ID
+    ///
+    /// Returns `None` for more records than `MAX_RESULT_SET_SIZE`.
+    pub fn insert(&self, drive: &str, description: &str, members: impl ExactSizeIterator<Item = (char, u64)>) -> Option<String> {
+        let len = members.len();
+        if len > MAX_RESULT_SET_SIZE {
+            debug!("Not keeping the {} matches of '{}' as a result set", len, description);
+            return None;
+        }
+        let mut by_drive: HashMap<char, Vec<u64>> = HashMap::new();
+        for (drive, id) in members {
+            by_drive.entry(drive).or_default().push(id);
+        }
+
+        let id = format!("rs{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let set = ResultSet {
+            id: id.clone(),
+            drive: drive.to_string(),
+            description: description.to_string(),
+            members: by_drive,
+            len,
+        };
+
+        let mut sets = self.sets.lock();
+        sets.retain(|_, (_, used)| used.elapsed() < RESULT_SET_IDLE);
+        if sets.len() >= MAX_RESULT_SETS {
+            let oldest = sets.iter().min_by_key(|(_, (_, used))| *used).map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                sets.remove(&oldest);
+            }
+        }
+        sets.insert(id.clone(), (Arc::new(set), Instant::now()));
+        Some(id)
+    }
+
+    /// The set with this ID, unless it was dropped
+    pub fn get(&self, id: &str) -> Option<Arc<ResultSet>> {
+        let mut sets = self.sets.lock();
+        let (set, used) = sets.get_mut(id).filter(|(_, used)| used.elapsed() < RESULT_SET_IDLE)?;
+        *used = Instant::now();
+        Some(Arc::clone(set))
+    }
+
+    /// Records held by the kept sets, and the bytes they take
+    pub fn memory(&self) -> (u64, u64) {
+        self.sets.lock().values().fold((0, 0), |(records, bytes), (set, _)| {
+            (records + set.len() as u64, bytes + set.heap_bytes())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_get() {
+        let store = ResultSetStore::new();
+        let id = store.insert("*", "*.rs", vec![('C', 5), ('D', 7), ('C', 9)].into_iter()).unwrap();
+
+        let set = store.get(&id).unwrap();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.ids('C'), &[5, 9]);
+        assert_eq!(set.ids('E'), &[] as &[u64]);
+        assert_eq!((set.drive.as_str(), set.description.as_str()), ("*", "*.rs"));
+        assert!(store.get("rs999").is_none());
+    }
+
+    #[test]
+    fn test_least_recently_used_set_is_dropped() {
+        let store = ResultSetStore::new();
+        let first = store.insert("C", "first", std::iter::once(('C', 1))).unwrap();
+        let second = store.insert("C", "second", std::iter::once(('C', 2))).unwrap();
+        assert_ne!(first, second);
+
+        // Using the first set keeps it over the second
+        std::thread::sleep(Duration::from_millis(2));
+        store.get(&first).unwrap();
+        for i in 0..MAX_RESULT_SETS - 2 {
+            store.insert("C", "more", std::iter::once(('C', i as u64))).unwrap();
+        }
+        store.insert("C", "last", std::iter::once(('C', 0))).unwrap();
+        assert!(store.get(&first).is_some());
+        assert!(store.get(&second).is_none());
+    }
+}
@@ -0,0 +1,326 @@
+//! Saved searches and change watches
+//!
+//! A saved search is a named set of `fast_search` arguments. Marking one as
+//! watched makes the service evaluate incoming USN Journal changes against it
+//! and emit a notification whenever a new file starts matching.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{debug, info};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::file_types::DocumentType;
+use super::filters::SearchFilters;
+use super::json_store::{data_dir, JsonStore};
+use super::matcher::{PatternCache, PatternMatcher, PatternSet, SearchType};
+use super::mft_cache::{FileEntry, MftCache};
+use super::notifications::NotificationQueue;
+use super::smart::SmartSearch;
+use super::usn_journal::{reason, UsnChange};
+
+/// File name of the saved search store inside the data directory
+const SAVED_SEARCHES_FILE: &str = "saved_searches.json";
+
+/// A named, reusable search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// Unique name of the saved search
+    pub name: String,
+    /// Arguments passed to `fast_search` when the search is run
+    pub arguments: Value,
+    /// Whether new matches should raise notifications
+    #[serde(default)]
+    pub watched: bool,
+    /// When the search was saved (UNIX timestamp)
+    pub created: i64,
+}
+
+impl SavedSearch {
+    /// Create a new saved search
+    pub fn new(name: &str, arguments: Value, watched: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            arguments,
+            watched,
+            created: Utc::now().timestamp(),
+        }
+    }
+
+    /// Drive this search targets (defaults to C, like `fast_search`)
+    pub fn drive(&self) -> char {
+        self.arguments["drive"]
+            .as_str()
+            .and_then(|d| d.chars().next())
+            .unwrap_or('C')
+            .to_ascii_uppercase()
+    }
+}
+
+/// Persistent collection of saved searches
+#[derive(Debug)]
+pub struct SavedSearchStore {
+    file: JsonStore<Vec<SavedSearch>>,
+    searches: RwLock<BTreeMap<String, SavedSearch>>,
+}
+
+impl SavedSearchStore {
+    /// Default location: `%LOCALAPPDATA%\FastSearchMCP\saved_searches.json`
+    pub fn default_path() -> PathBuf {
+        data_dir().join(SAVED_SEARCHES_FILE)
+    }
+
+    /// Open the store at the given path, starting empty if it doesn't exist or can't be read
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let file: JsonStore<Vec<SavedSearch>> = JsonStore::at(&path, "saved searches");
+        let searches: BTreeMap<String, SavedSearch> = file.load().unwrap_or_default()
+            .into_iter()
+            .map(|s| (s.name.clone(), s))
+            .collect();
+
+        info!("Loaded {} saved searches from {}", searches.len(), path.as_ref().display());
+        Self {
+            file,
+            searches: RwLock::new(searches),
+        }
+    }
+
+    /// Create a store that is never written to disk
+    pub fn in_memory() -> Self {
+        Self {
+            file: JsonStore::in_memory("saved searches"),
+            searches: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Look up a saved search by name
+    pub fn get(&self, name: &str) -> Option<SavedSearch> {
+        self.searches.read().get(name).cloned()
+    }
+
+    /// All saved searches, ordered by name
+    pub fn list(&self) -> Vec<SavedSearch> {
+        self.searches.read().values().cloned().collect()
+    }
+
+    /// Saved searches currently marked as watched
+    pub fn watched(&self) -> Vec<SavedSearch> {
+        self.searches.read().values().filter(|s| s.watched).cloned().collect()
+    }
+
+    /// Insert or replace a saved search
+    pub fn insert(&self, search: SavedSearch) -> Result<()> {
+        self.searches.write().insert(search.name.clone(), search);
+        self.persist()
+    }
+
+    /// Remove a saved search, returning it if it existed
+    pub fn remove(&self, name: &str) -> Result<Option<SavedSearch>> {
+        let removed = self.searches.write().remove(name);
+        if removed.is_some() {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    /// Mark a saved search as watched or not; returns the updated search
+    pub fn set_watched(&self, name: &str, watched: bool) -> Result<Option<SavedSearch>> {
+        let updated = {
+            let mut searches = self.searches.write();
+            searches.get_mut(name).map(|search| {
+                search.watched = watched;
+                search.clone()
+            })
+        };
+        if updated.is_some() {
+            self.persist()?;
+        }
+        Ok(updated)
+    }
+
+    /// Add many saved searches at once, replacing same-named ones
+    ///
+    /// With `replace`, searches not in `searches` are removed first.
+    pub fn import(&self, searches: Vec<SavedSearch>, replace: bool) -> Result<usize> {
+        let count = searches.len();
+        {
+            let mut current = self.searches.write();
+            if replace {
+                current.clear();
+            }
+            current.extend(searches.into_iter().map(|s| (s.name.clone(), s)));
+        }
+        self.persist()?;
+        Ok(count)
+    }
+
+    /// Write the store to disk (atomically, via a temporary file)
+    fn persist(&self) -> Result<()> {
+        self.file.save(&self.list())
+    }
+}
+
+/// Evaluates USN Journal changes against watched saved searches
+#[derive(Clone)]
+pub struct WatchEvaluator {
+    store: Arc<SavedSearchStore>,
+    pattern_cache: Arc<PatternCache>,
+    doc_type_extensions: Arc<HashMap<DocumentType, HashSet<String>>>,
+    notifications: Arc<NotificationQueue>,
+}
+
+impl WatchEvaluator {
+    /// Create an evaluator sharing the engine's store, pattern cache and notification queue
+    pub fn new(
+        store: Arc<SavedSearchStore>,
+        pattern_cache: Arc<PatternCache>,
+        doc_type_extensions: Arc<HashMap<DocumentType, HashSet<String>>>,
+        notifications: Arc<NotificationQueue>,
+    ) -> Self {
+        Self { store, pattern_cache, doc_type_extensions, notifications }
+    }
+
+    /// Check a batch of changes and queue a notification for each new match
+    ///
+    /// Returns the number of notifications queued.
+    pub fn evaluate(&self, cache: &MftCache, changes: &[UsnChange]) -> usize {
+        let drive = cache.drive_letter();
+        let watched: Vec<SavedSearch> = self.store
+            .watched()
+            .into_iter()
+            .filter(|s| s.drive() == drive)
+            .collect();
+        if watched.is_empty() {
+            return 0;
+        }
+
+        let files = cache.get_files();
+        let mut seen: HashSet<(String, u64)> = HashSet::new();
+        let mut queued = 0;
+
+        for search in &watched {
+            let matcher = match compile_matcher(&search.arguments, &self.pattern_cache) {
+                Ok(matcher) => matcher,
+                Err(e) => {
+                    debug!("Skipping watch '{}': {}", search.name, e);
+                    continue;
+                }
+            };
+            let filters = match SearchFilters::from_args(&search.arguments, &self.doc_type_extensions) {
+                Ok(filters) => filters,
+                Err(e) => {
+                    debug!("Skipping watch '{}': {}", search.name, e);
+                    continue;
+                }
+            };
+
+            for change in changes.iter().filter(|c| c.is_new_name()) {
+                if !seen.insert((search.name.clone(), change.file_id)) {
+                    continue; // A single creation produces several journal records
+                }
+                let entry = entry_for_change(&files, change);
+                if !matcher.is_file_match(&entry.name, &entry.path) || !filters.matches(&entry) {
+                    continue;
+                }
+
+                let event = if change.reason & reason::FILE_CREATE != 0 { "created" } else { "renamed" };
+                self.notifications.push_message("info", "fastsearch.watch", json!({
+                    "event": "watch_match",
+                    "saved_search": search.name,
+                    "change": event,
+                    "name": entry.name,
+                    "path": format!("{}:\\{}", drive, entry.path),
+                    "is_directory": entry.is_directory,
+                    "usn": change.usn
+                }));
+                queued += 1;
+            }
+        }
+
+        if queued > 0 {
+            info!("Queued {} watch notifications for drive {}:", queued, drive);
+        }
+        queued
+    }
+}
+
+/// Compile the name pattern(s) of saved `fast_search` arguments
+///
+/// Smart searches are compiled as the glob or regex search they read as.
+pub(crate) fn compile_matcher(arguments: &Value, pattern_cache: &PatternCache) -> Result<PatternMatcher> {
+    let smart = SmartSearch::from_args(arguments)?;
+    let (arguments, search_type) = match &smart {
+        Some(smart) => (&smart.args, smart.search_type),
+        None => (
+            arguments,
+            arguments["search_type"].as_str().and_then(SearchType::parse).unwrap_or_default(),
+        ),
+    };
+    let patterns = PatternSet::from_args(arguments)?;
+    pattern_cache.get_or_compile_set(&patterns, search_type)
+}
+
+/// The entry a journal record names, as far as the record describes it
+///
+/// The parent folder is looked up in `files` for the path; size, dates and
+/// attributes are unknown, and the record time is taken as "now".
+pub(crate) fn entry_for_change(files: &HashMap<u64, FileEntry>, change: &UsnChange) -> FileEntry {
+    let path = match files.get(&change.parent_id) {
+        Some(parent) => format!("{}\\{}", parent.path, change.name),
+        None => change.name.clone(),
+    };
+    let now = SystemTime::now();
+    FileEntry {
+        id: change.file_id,
+        name: change.name.clone(),
+        path,
+        size: 0,
+        is_directory: change.is_directory(),
+        extension: Path::new(&change.name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase()),
+        created: now,
+        modified: now,
+        accessed: now,
+        attributes: 0,
+        link_count: 1,
+        reparse_tag: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SAVED_SEARCHES_FILE);
+
+        let store = SavedSearchStore::open(&path);
+        store.insert(SavedSearch::new("dumps", json!({"pattern": "*.dmp"}), false)).unwrap();
+        store.set_watched("dumps", true).unwrap();
+
+        let reopened = SavedSearchStore::open(&path);
+        let search = reopened.get("dumps").unwrap();
+        assert!(search.watched);
+        assert_eq!(search.arguments["pattern"], "*.dmp");
+        assert_eq!(reopened.watched().len(), 1);
+
+        assert!(reopened.remove("dumps").unwrap().is_some());
+        assert!(SavedSearchStore::open(&path).list().is_empty());
+    }
+
+    #[test]
+    fn test_saved_search_drive_defaults_to_c() {
+        assert_eq!(SavedSearch::new("a", json!({}), false).drive(), 'C');
+        assert_eq!(SavedSearch::new("b", json!({"drive": "d"}), false).drive(), 'D');
+    }
+}
@@ -1,42 +1,485 @@
 // FastSearch MCP Server - CACHED MFT SEARCH IMPLEMENTATION
 
 use serde_json::{json, Value};
-use anyhow::{Result, Context};
+use anyhow::Result;
 use log::{info, debug, error};
-use std::time::Instant;
+use chrono::{DateTime, Local, Utc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::cell::{Cell, RefCell};
 use std::collections::{HashSet, HashMap};
+use std::path::PathBuf;
+#[cfg(test)]
+use std::path::Path;
 use std::sync::Arc;
 use parking_lot::RwLock;
+use fastsearch_shared::TextHighlight;
 
 // Import file_types with relative path
-use crate::file_types::{get_extensions, DocumentType, parse_document_type};
-use super::mft_cache::{MftCache, FileEntry};
+use super::file_types::{get_extensions, DocumentType};
+use super::filters::{parse_depth_arg, SearchFilters, ATTRIBUTE_ARGS};
+use super::gitignore::{GitignoreFilter, IgnoreFileCache};
+use super::content_kind::{ContentKind, ContentKindCache, ContentKindCheck};
+use super::content_type::{ContentCheck, ContentTypeCache};
+use super::doc_stats::{DocStats, DocStatsCache, DocStatsCheck};
+use super::image_info::{ImageCheck, ImageInfo, ImageInfoCache};
+use super::media_info::{MediaCheck, MediaInfo, MediaInfoCache};
+use super::highlights::highlights;
+use super::hints::{similar_extensions, similar_names, DriveMatches, NoResultHints, PathFilterHint, SpellingTarget, MAX_EXAMPLE_FOLDERS};
+use super::activity::{activity, render_heatmap, ActivityBucket, ActivityField, ActivityPeriod};
+use super::ads::{read_streams, stream_path, DataStream};
+use super::cancellation::{CancelRegistry, CancelToken, Cancelled, Deadline};
+use super::collections::{Bookmark, CollectionStore};
+use super::confirmation::{ConfirmationGate, CostEstimate, CONFIRM_HASH_BYTES, CONFIRM_TOKEN_ARG, TOKEN_LIFETIME};
+use super::denied_paths::{DeniedPath, DeniedPathStore};
+use super::density::{is_broad_count, Breakdown, BroadSummary};
+use super::dir_index::{describe_mount, file_identity, DirectoryIndexer, MountConfig};
+use super::dir_sizes::{directory_sizes, DEFAULT_SIZE_DEPTH, MAX_SIZE_DEPTH};
+use super::drive_config::{parse_drive_list, DriveConfig, DriveSelection};
+use super::duplicates::{disk_of, find_duplicates, size_groups, DuplicateCandidate, HashProgress, DEFAULT_HASH_THREADS};
+use super::envelope::{record_drive, traced};
+use super::etw;
+use super::exclude::ExcludePatterns;
+use super::features::missing_feature;
+use super::file_info::{differences, stat, LiveStat};
+use super::index_export::{export_path, export_sqlite, ExportFormat, EXPORT_SCHEMA_VERSION};
+use super::inventory::repo_inventory;
+use super::json_store::data_dir;
+use super::live_queries::{LiveQuery, LiveQueryRegistry};
+use super::maintenance::MaintenanceState;
+use super::memory_profile::{HeapSize, MemoryProfile};
+use super::matcher::{PatternCache, PatternSet, SearchType};
+use super::mft_cache::{disk_path, DriveRoot, MftCache, MftCacheConfig, FileEntry};
+use super::modes::{
+    annotate_response, build_fallback, clear_mode, drive_mode, set_mode, start_periodic_refresh, IndexMode,
+    CACHE_RETRY_INTERVAL, REFRESH_INTERVAL,
+};
+use super::notifications::{NotificationQueue, ProgressReporter};
+use super::operations::DriveOperation;
+use super::owner::OwnerFilter;
+use super::pagination::{is_after, PageCursor, PageItem, SortKey};
+use super::pipeline::{
+    auto_fuzzy, did_you_mean_response, found_nothing, CacheLocks, CacheStages, FolderCap, ListedHit, Prefilter, Scan,
+    ScanSource, SearchOptions, SearchPage, Verifier, VerifyStage, VolumeStats,
+};
+use super::projection::FieldProjection;
+use super::projects::{ProjectIndex, ProjectRoot};
+use super::query::format_size;
+use super::query_limiter::QueryLimiter;
+use super::reparse::{read_target, resolve_target, ReparseKind, ReparseMode, MAX_LINK_HOPS};
+use super::reports::{generate_report, Report, ReportJob, ReportScheduler, REPORT_URI_PREFIX};
+use super::resource_limits::limit_status;
+use super::result_layout::ResultLayout;
+use super::result_sets::ResultSetStore;
+use super::saved_searches::{SavedSearch, SavedSearchStore, WatchEvaluator};
+use super::security_scan::{read_head, security_scan, Severity};
+use super::self_test::{record_self_test, self_test};
+use super::settings::{bundle_path, SettingsBundle};
+use super::similar::{names_within, stem, DEFAULT_MAX_DISTANCE, MAX_DISTANCE_LIMIT};
+use super::size_distribution::{size_distribution, SizeGrouping};
+use super::smart::{SmartSearch, SMART_SEARCH_TYPE};
+use super::setup::{install_service, is_elevated, next_step, service_check, start_service, SetupStep};
+use super::syntax_docs::{syntax_docs_resource, syntax_reference, SYNTAX_DOCS_URI};
+use super::tool_schemas::tool_schemas;
+use super::usn_journal::{usn_buffer_memory, FileReference};
+use super::volumes::{DriveAlias, VolumeId, VolumeMap};
+use super::warm_queries::WarmQueryStore;
 
 /// SearchEngine handles all search-related functionality
 pub struct SearchEngine {
     // MFT cache for fast file searches
-    mft_cache: Arc<RwLock<HashMap<char, MftCache>>>,
+    mft_cache: Arc<RwLock<HashMap<char, Arc<MftCache>>>>,
     
     // Cache for document type extensions
-    doc_type_extensions: HashMap<DocumentType, HashSet<String>>,
+    doc_type_extensions: Arc<HashMap<DocumentType, HashSet<String>>>,
+    
+    // Compiled search patterns, keyed by search type and pattern
+    pattern_cache: Arc<PatternCache>,
+    
+    // Frequent recent searches and their match counts, kept across restarts
+    warm_queries: WarmQueryStore,
+    
+    // Detected project roots per drive, rebuilt when the MFT cache changes
+    project_indexes: RwLock<HashMap<char, Arc<ProjectIndex>>>,
+    
+    // Parsed .gitignore/.ignore files for respect_gitignore searches
+    ignore_files: IgnoreFileCache,
+    
+    // Image dimensions and capture dates read so far, per file record
+    image_info: ImageInfoCache,
+    
+    // Durations, bitrates and codecs of audio and video files read so far
+    media_info: MediaInfoCache,
+    
+    // First bytes of files checked with verify_content_type
+    content_types: ContentTypeCache,
+    
+    // Page and word counts of documents read so far
+    doc_stats: DocStatsCache,
+    
+    // Text or binary, sniffed for files whose extension doesn't tell
+    content_kinds: ContentKindCache,
+    
+    // Saved searches (some of which may be watched for new matches)
+    saved_searches: Arc<SavedSearchStore>,
+    
+    // Queries whose results are kept current for Web API subscribers
+    live_queries: Arc<LiveQueryRegistry>,
+    
+    // Bookmarked results, in named collections
+    collections: CollectionStore,
+    
+    // Server-initiated notifications waiting to be written by the transport
+    notifications: Arc<NotificationQueue>,
+    
+    // Running requests that can be cancelled with notifications/cancelled
+    cancellations: CancelRegistry,
+    
+    // Slots for searches scanning at the same time, and the line for them
+    query_limiter: QueryLimiter,
+    
+    // Tokens for expensive calls the client was asked to confirm
+    confirmations: ConfirmationGate,
+    
+    // Matches of recent searches, for narrowing with within_results_of
+    result_sets: ResultSetStore,
+    
+    // Scheduled report jobs and their latest results
+    reports: ReportScheduler,
+    
+    // Directories indexed instead of NTFS volumes (container mode), by virtual drive
+    mounts: Vec<MountConfig>,
+    
+    // Watchers keeping the mounted directory indexes current
+    dir_indexers: RwLock<HashMap<char, Arc<DirectoryIndexer>>>,
+    
+    // Folders the walks couldn't read, skipped for a while once denied repeatedly
+    denied_paths: Arc<DeniedPathStore>,
+    
+    // Drive letters and the volumes behind them (detected at startup)
+    volumes: VolumeMap,
+    
+    // Drives built at startup and drives never indexed
+    drives: DriveConfig,
+    
+    // Where the stores live; settings bundles are only read and written in its settings folder
+    data_dir: PathBuf,
+}
+
+/// A cache searched by `fast_search`, and the drive letter its results are reported under
+struct SearchSource {
+    shown_as: char,
+    alias: DriveAlias,
+    mft_cache: Arc<MftCache>,
+    project_index: Arc<ProjectIndex>,
+}
+
+/// A path tool argument, resolved to the cache holding it
+struct CachePath {
+    /// Drive letter the path was given on
+    drive: char,
+    /// Path relative to that drive's root, as given
+    path: String,
+    /// Where the drive's files live in the MFT cache
+    alias: DriveAlias,
+    /// The path as stored in the canonical drive's cache
+    cache_path: String,
+}
+
+/// Receives batches of matches while `fast_search` is still scanning
+///
+/// Called with the structured results of the batch, the number of files
+/// scanned so far and the number of files being scanned in total.
+pub type PartialResultSink<'a> = &'a dyn Fn(Vec<Value>, u64, u64);
+
+/// Matches are streamed once this many are waiting...
+const STREAM_BATCH_SIZE: usize = 100;
+
+/// ...or once this long has passed since the previous batch
+const STREAM_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Scan progress without new matches is reported at most this often
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Entries a direct scan reads when it has to match names itself
+const DIRECT_SCAN_LIMIT: usize = 200_000;
+
+/// Smallest file `find_duplicates` checks unless given `min_size`
+const DUPLICATE_MIN_SIZE: u64 = 1024 * 1024;
+
+/// Most file references `resolve_file_id` takes in one call
+const MAX_FILE_IDS: usize = 1000;
+
+/// Batches matches for a `PartialResultSink` while a search is scanning
+struct BatchStream<'a> {
+    sink: PartialResultSink<'a>,
+    batch: Vec<Value>,
+    // Matches still to stream before the page is full
+    remaining: usize,
+    total: u64,
+    sent_any: bool,
+    last_sent: Instant,
+}
+
+impl<'a> BatchStream<'a> {
+    fn new(sink: PartialResultSink<'a>, page_size: usize, total: u64) -> Self {
+        Self { sink, batch: Vec::new(), remaining: page_size, total, sent_any: false, last_sent: Instant::now() }
+    }
+    
+    fn wants_more(&self) -> bool {
+        self.remaining > 0
+    }
+    
+    /// Add a match; the first one is sent right away so clients see results immediately
+    fn push(&mut self, result: Value, scanned: u64) {
+        self.batch.push(result);
+        self.remaining = self.remaining.saturating_sub(1);
+        let due = !self.sent_any || self.last_sent.elapsed() >= STREAM_INTERVAL;
+        if due || self.batch.len() >= STREAM_BATCH_SIZE || self.remaining == 0 {
+            self.flush(scanned);
+        }
+    }
+    
+    /// Report scan progress every so often, even without new matches
+    fn tick(&mut self, scanned: u64) {
+        if scanned % 4096 == 0 && self.last_sent.elapsed() >= PROGRESS_INTERVAL {
+            self.send(scanned);
+        }
+    }
+    
+    fn flush(&mut self, scanned: u64) {
+        if !self.batch.is_empty() {
+            self.send(scanned);
+        }
+    }
+    
+    fn send(&mut self, scanned: u64) {
+        (self.sink)(std::mem::take(&mut self.batch), scanned, self.total);
+        self.sent_any = true;
+        self.last_sent = Instant::now();
+    }
+}
+
+/// One `fast_search` result, with its path relative to the drive it was found under
+struct SearchHit {
+    drive: char,
+    file: FileEntry,
+    score: Option<f64>,
+    /// Inside the folder given as `workspace_hint`
+    in_workspace: bool,
+    project: Option<ProjectRoot>,
+    aliases: Vec<String>,
+    /// Other paths of the same file record (hard links and 8.3 names), as full paths
+    alternate_paths: Vec<String>,
+    /// Named data streams, when asked for with `include_streams`
+    streams: Vec<DataStream>,
+    /// Dimensions and capture date of an image, when asked for with `include_image_info`
+    image: Option<ImageInfo>,
+    /// Duration, bitrate and codec of audio and video, when asked for with `include_media_info`
+    media: Option<MediaInfo>,
+    /// Type detected from the first bytes, with `verify_content_type`
+    mime_type: Option<&'static str>,
+    /// Page and word count of a document, when asked for with `include_doc_stats`
+    doc_stats: Option<DocStats>,
+    /// Text or binary, by extension or (with `sniff_content_kind`) from the first bytes
+    content_kind: Option<ContentKind>,
+    /// Where a symbolic link or junction points, as stored in it
+    reparse_target: Option<String>,
+    /// Where the pattern and path filter matched
+    highlights: Vec<TextHighlight>,
 }
 
 impl SearchEngine {
     /// Create a new SearchEngine instance with MFT cache
     pub fn new() -> Result<Self> {
-        info!("Initializing FastSearch Search Engine (MFT CACHE MODE)");
+        let mounts = MountConfig::load()?;
+        if mounts.is_empty() {
+            info!("Initializing FastSearch Search Engine (MFT CACHE MODE)");
+        } else {
+            info!("Initializing FastSearch Search Engine (CONTAINER MODE, {} directory mounts)", mounts.len());
+        }
         
-        // Initialize document type extensions
-        let doc_type_extensions = get_extensions()
-            .into_iter()
-            .collect();
+        let doc_type_extensions = Self::doc_type_extensions();
+        
+        etw::register();
+        
+        // Compile the patterns searched most before the restart ahead of their first use
+        let pattern_cache = Arc::new(PatternCache::new());
+        let warm_queries = WarmQueryStore::open(WarmQueryStore::default_path());
+        warm_queries.warm(&pattern_cache);
+        let live_queries = Arc::new(LiveQueryRegistry::new(Arc::clone(&pattern_cache), Arc::clone(&doc_type_extensions)));
             
         Ok(SearchEngine {
             mft_cache: Arc::new(RwLock::new(HashMap::new())),
             doc_type_extensions,
+            pattern_cache,
+            warm_queries,
+            project_indexes: RwLock::new(HashMap::new()),
+            ignore_files: IgnoreFileCache::new(),
+            image_info: ImageInfoCache::new(),
+            media_info: MediaInfoCache::new(),
+            content_types: ContentTypeCache::new(),
+            doc_stats: DocStatsCache::new(),
+            content_kinds: ContentKindCache::new(),
+            saved_searches: Arc::new(SavedSearchStore::open(SavedSearchStore::default_path())),
+            live_queries,
+            collections: CollectionStore::open(CollectionStore::default_path()),
+            notifications: Arc::new(NotificationQueue::new()),
+            cancellations: CancelRegistry::new(),
+            query_limiter: QueryLimiter::from_env(),
+            confirmations: ConfirmationGate::new(),
+            result_sets: ResultSetStore::new(),
+            reports: ReportScheduler::load(ReportScheduler::default_path()),
+            // Container mode has no drive letters of its own to map
+            volumes: if mounts.is_empty() { VolumeMap::detect() } else { VolumeMap::default() },
+            mounts,
+            dir_indexers: RwLock::new(HashMap::new()),
+            denied_paths: Arc::new(DeniedPathStore::open(DeniedPathStore::default_path())),
+            drives: DriveConfig::load(DriveConfig::default_path()),
+            data_dir: data_dir(),
         })
     }
     
+    /// Extensions of each document type, for the `doc_type` filter
+    fn doc_type_extensions() -> Arc<HashMap<DocumentType, HashSet<String>>> {
+        use strum::IntoEnumIterator;
+        Arc::new(DocumentType::iter()
+            .map(|doc_type| (doc_type, get_extensions(doc_type).into_iter().map(str::to_string).collect()))
+            .collect())
+    }
+    
+    /// An engine serving the given caches, with its stores in `data_dir`, for tests
+    ///
+    /// Nothing is read from the volumes or the user's data directory, and no
+    /// other drive can be opened.
+    #[cfg(test)]
+    pub(crate) fn with_fixture_caches(data_dir: &Path, caches: Vec<MftCache>) -> Self {
+        let doc_type_extensions = Self::doc_type_extensions();
+        let pattern_cache = Arc::new(PatternCache::new());
+        let live_queries = Arc::new(LiveQueryRegistry::new(Arc::clone(&pattern_cache), Arc::clone(&doc_type_extensions)));
+        let caches: HashMap<char, Arc<MftCache>> = caches.into_iter()
+            .map(|cache| (cache.drive_letter(), Arc::new(cache)))
+            .collect();
+        // Every other drive is excluded, so a test can't reach a real volume
+        let excluded = ('A'..='Z').filter(|drive| !caches.contains_key(drive)).collect();
+        
+        SearchEngine {
+            mft_cache: Arc::new(RwLock::new(caches)),
+            doc_type_extensions,
+            warm_queries: WarmQueryStore::open(data_dir.join("warm_queries.json")),
+            pattern_cache,
+            project_indexes: RwLock::new(HashMap::new()),
+            ignore_files: IgnoreFileCache::new(),
+            image_info: ImageInfoCache::new(),
+            media_info: MediaInfoCache::new(),
+            content_types: ContentTypeCache::new(),
+            doc_stats: DocStatsCache::new(),
+            content_kinds: ContentKindCache::new(),
+            saved_searches: Arc::new(SavedSearchStore::open(data_dir.join("saved_searches.json"))),
+            live_queries,
+            collections: CollectionStore::open(data_dir.join("collections.json")),
+            notifications: Arc::new(NotificationQueue::new()),
+            cancellations: CancelRegistry::new(),
+            query_limiter: QueryLimiter::from_env(),
+            confirmations: ConfirmationGate::new(),
+            result_sets: ResultSetStore::new(),
+            reports: ReportScheduler::load(data_dir.join("reports.json")),
+            volumes: VolumeMap::default(),
+            mounts: Vec::new(),
+            dir_indexers: RwLock::new(HashMap::new()),
+            denied_paths: Arc::new(DeniedPathStore::open(data_dir.join("denied_paths.json"))),
+            drives: DriveConfig::with_selection(DriveSelection { preload: Vec::new(), excluded }),
+            data_dir: data_dir.to_path_buf(),
+        }
+    }
+    
+    /// Build the caches of the drives configured for preloading; returns how many were built
+    ///
+    /// Runs at service start, so the first searches on those drives find their cache ready.
+    pub fn preload_drives(&self) -> usize {
+        let preload = self.drives.selection().preload;
+        let mut built = 0;
+        for drive in preload {
+            let start = Instant::now();
+            match self.get_or_create_cache(drive) {
+                Ok(cache) => {
+                    info!("Preloaded drive {}: ({} entries in {:.1}s)", drive, cache.stats().file_count, start.elapsed().as_secs_f64());
+                    self.self_test_cache(drive, &cache);
+                    built += 1;
+                }
+                Err(e) => error!("Failed to preload drive {}: {}", drive, e),
+            }
+        }
+        built
+    }
+    
+    /// Run the canary queries on a freshly loaded cache, for the health checks
+    fn self_test_cache(&self, drive: char, cache: &MftCache) {
+        // %SystemDrive% is "C:"; mounted directories never hold Windows
+        let system_drive = std::env::var("SystemDrive").ok()
+            .and_then(|system| system.chars().next())
+            .map_or('C', |letter| letter.to_ascii_uppercase());
+        let test = self_test(
+            drive,
+            &cache.get_files(),
+            &cache.get_name_index(),
+            &cache.get_path_index(),
+            drive == system_drive && !self.is_mounted(drive),
+        );
+        if test.passed {
+            info!("Drive {}: passed its self-test ({} checks)", drive, test.checks.len());
+        } else {
+            for check in test.checks.iter().filter(|check| !check.passed) {
+                error!("Drive {}: self-test failed: {}: {}", drive, check.check, check.detail.as_deref().unwrap_or_default());
+            }
+        }
+        record_self_test(test);
+    }
+    
+    /// Whether a drive letter serves an indexed directory rather than an NTFS volume
+    fn is_mounted(&self, drive: char) -> bool {
+        self.mounts.iter().any(|m| m.drive == drive)
+    }
+    
+    /// Drive searched when the caller doesn't name one
+    ///
+    /// In container mode this is the first mounted directory, since there is no C: volume.
+    fn default_drive(&self) -> char {
+        self.mounts.first().map(|m| m.drive).unwrap_or('C')
+    }
+    
+    /// Take notifications queued by background work (e.g. watched searches)
+    pub fn drain_notifications(&self) -> Vec<Value> {
+        self.notifications.drain()
+    }
+    
+    /// Block until a notification is pending or the timeout passes
+    pub fn wait_for_notifications(&self, timeout: Duration) -> bool {
+        self.notifications.wait(timeout)
+    }
+    
+    /// Handle `notifications/cancelled`; returns whether a running request was told to stop
+    pub fn cancel_request(&self, params: &Value) -> bool {
+        self.cancellations.cancel(params)
+    }
+    
+    /// A token for work started outside a JSON-RPC request (an HTTP search, say)
+    ///
+    /// It is a child of the engine's root token, so `shutdown` stops the work
+    /// as well as whoever cancels the token.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancellations.root().child()
+    }
+    
+    /// Stop all running searches, scans and cache rebuilds, when the service stops
+    ///
+    /// Caches stop saving in the background too; what they last saved is kept.
+    pub fn shutdown(&self) {
+        self.cancellations.cancel_all("service stopping");
+        for cache in self.mft_cache.read().values() {
+            cache.stop_auto_save();
+        }
+    }
+    
     pub fn handle_request(&self, request: Value) -> Result<Value> {
         debug!("Handling MCP request: {}", request);
         
@@ -45,7 +488,18 @@ impl SearchEngine {
         match method {
             "initialize" => self.handle_initialize(request),
             "tools/list" => self.handle_tools_list(),
-            "tools/call" => self.handle_tool_call(request),
+            "tools/call" => {
+                // Every tool reports which mode produced its results, and how
+                let tool = request["params"]["name"].as_str().unwrap_or_default().to_string();
+                let args = request["params"]["arguments"].clone();
+                traced(&tool, &args, || {
+                    let mut response = self.handle_tool_call(request)?;
+                    annotate_response(&mut response);
+                    Ok(response)
+                })
+            }
+            "resources/list" => self.handle_resources_list(),
+            "resources/read" => self.handle_resources_read(request),
             _ => Ok(json!({
                 "error": {
                     "code": -32601,
@@ -60,7 +514,8 @@ impl SearchEngine {
             "result": {
                 "protocolVersion": "2024-11-05",
                 "capabilities": {
-                    "tools": {}
+                    "tools": {},
+                    "resources": {}
                 },
                 "serverInfo": {
                     "name": "fastsearch-mcp",
@@ -71,102 +526,14 @@ impl SearchEngine {
     }
     
     pub(crate) fn handle_tools_list(&self) -> Result<Value> {
+        // Tools this build was made without
+        let tools: Vec<Value> = tool_schemas()
+            .into_iter()
+            .filter(|tool| missing_feature(tool["name"].as_str().unwrap_or_default()).is_none())
+            .collect();
         Ok(json!({
             "result": {
-                "tools": [
-                    {
-                        "name": "list_ntfs_drives",
-                        "description": "List all available NTFS drives on the system",
-                        "inputSchema": {
-                            "type": "object",
-                            "properties": {}
-                        }
-                    },
-                    {
-                        "name": "fast_search",
-                        "description": "Lightning-fast DIRECT file search using NTFS Master File Table (no indexing)",
-                        "inputSchema": {
-                            "type": "object",
-                            "properties": {
-                                "pattern": {
-                                    "type": "string",
-                                    "description": "File pattern to search for (*.js, README*, config.*, etc.)"
-                                },
-                                "path": {
-                                    "type": "string",
-                                    "description": "Optional path to search within (e.g., \"src/\" or \"C:\\Windows\")"
-                                },
-                                "drive": {
-                                    "type": "string",
-                                    "description": "Drive letter to search (e.g., 'C'). Use '*' to search all NTFS drives.",
-                                    "default": "C"
-                                },
-                                "max_results": {
-                                    "type": "integer",
-                                    "description": "Maximum number of results to return (default: 1000)",
-                                    "default": 1000
-                                },
-                                "type": {
-                                    "type": "string",
-                                    "description": "Type filter: 'file', 'directory', or 'any' (default)",
-                                    "enum": ["file", "directory", "any"],
-                                    "default": "any"
-                                },
-                                "doc_type": {
-                                    "type": "string",
-                                    "description": "Document type filter (e.g., 'text', 'code', 'image', 'pdf')",
-                                    "default": ""
-                                },
-                                "extensions": {
-                                    "type": "array",
-                                    "items": {
-                                        "type": "string"
-                                    },
-                                    "description": "File extensions to include (without leading .), overrides doc_type if both are specified"
-                                },
-                            },
-                            "required": ["pattern"]
-                        }
-                    },
-                    {
-                        "name": "find_large_files",
-                        "description": "Find large files by direct MFT scan",
-                        "inputSchema": {
-                            "type": "object", 
-                            "properties": {
-                                "min_size_mb": {
-                                    "type": "integer",
-                                    "description": "Minimum file size in MB",
-                                    "default": 100
-                                },
-                                "drive": {
-                                    "type": "string",
-                                    "description": "Drive letter to search",
-                                    "default": "C"
-                                },
-                                "max_results": {
-                                    "type": "integer",
-                                    "description": "Maximum number of results",
-                                    "default": 50
-                                }
-                            }
-                        }
-                    },
-                    {
-                        "name": "benchmark_search",
-                        "description": "Benchmark direct search performance",
-                        "inputSchema": {
-                            "type": "object",
-                            "properties": {
-                                "drive": {
-                                    "type": "string",
-                                    "description": "Drive letter to benchmark",
-                                    "default": "C"
-                                }
-                            }
-                        }
-                    }
-                ]
+                "tools": tools
             }
         }))
     }
@@ -179,19 +546,7 @@ impl SearchEngine {
         let mut doc_types = HashMap::new();
         
         for doc_type in DocumentType::iter() {
-            let name = match doc_type {
-                DocumentType::Text => "text",
-                DocumentType::Code => "code",
-                DocumentType::Image => "image",
-                DocumentType::Spreadsheet => "spreadsheet",
-                DocumentType::Presentation => "presentation",
-                DocumentType::Archive => "archive",
-                DocumentType::Audio => "audio",
-                DocumentType::Video => "video",
-                DocumentType::Pdf => "pdf",
-            };
-            
-            doc_types.insert(name.to_string(), get_extensions(doc_type));
+            doc_types.insert(doc_type.as_str().to_string(), get_extensions(doc_type));
         }
         
         Ok(json!({
@@ -206,18 +561,42 @@ impl SearchEngine {
         }))
     }
     
-    /// List all available NTFS drives on the system
+    /// List the NTFS drives, with the ones the drive config excludes from indexing and `drive: "*"` marked
     fn list_ntfs_drives(&self) -> Result<Value> {
         let drives = crate::ntfs_reader::get_ntfs_drives()?;
+        let selection = self.drives.selection();
+        let cached: HashSet<char> = self.mft_cache.read().keys().copied().collect();
+        
+        let details: Vec<Value> = drives.iter()
+            .filter_map(|drive| drive.chars().next())
+            .map(|letter| json!({
+                "drive": letter.to_string(),
+                "excluded": selection.is_excluded(letter),
+                "preload": selection.preload.contains(&letter),
+                "cached": cached.contains(&letter)
+            }))
+            .collect();
+        let excluded: Vec<&String> = drives.iter()
+            .filter(|drive| drive.chars().next().map_or(false, |letter| selection.is_excluded(letter)))
+            .collect();
+        
+        let mut text = format!("Available NTFS drives: {}", drives.join(", "));
+        if !excluded.is_empty() {
+            text.push_str(&format!(
+                "\nExcluded from indexing and drive \"*\" searches: {} (change with configure_drives)",
+                excluded.iter().map(|drive| drive.as_str()).collect::<Vec<_>>().join(", ")
+            ));
+        }
         
         Ok(json!({
             "result": {
                 "content": [{
                     "type": "text",
-                    "text": format!("Available NTFS drives: {}", 
-                        drives.join(", "))
+                    "text": text
                 }],
-                "drives": drives
+                "drives": drives,
+                "excluded": excluded,
+                "details": details
             }
         }))
     }
@@ -225,13 +604,77 @@ impl SearchEngine {
     fn handle_tool_call(&self, request: Value) -> Result<Value> {
         let tool_name = request["params"]["name"].as_str().unwrap_or("");
         let arguments = &request["params"]["arguments"];
+        if let Some(feature) = missing_feature(tool_name) {
+            return Ok(invalid_params(&format!("{} isn't available: the service was built without the '{}' feature", tool_name, feature)));
+        }
         
         match tool_name {
-            "fast_search" => self.fast_search(arguments),
+            "fast_search" => {
+                // A regex over drives whose cache has to be read from the MFT first waits for confirmation
+                let confirmed = match self.redeem_confirmation("fast_search", arguments) {
+                    Ok(confirmed) => confirmed,
+                    Err(response) => return Ok(response),
+                };
+                if let Some(estimate) = self.search_cost(arguments).filter(|_| !confirmed) {
+                    return self.confirmation_required("fast_search", arguments, estimate);
+                }
+                match ProgressReporter::for_request(&self.notifications, &request) {
+                    Some(progress) => {
+                        let streamed = Cell::new(0);
+                        let sink = |batch: Vec<Value>, scanned: u64, total: u64| {
+                            streamed.set(streamed.get() + batch.len());
+                            progress.report(scanned, Some(total), &format!("{} matches so far", streamed.get()), json!({
+                                "results": batch
+                            }));
+                        };
+                        self.fast_search_cancellable(&request, arguments, Some(&sink))
+                    }
+                    None => self.fast_search_cancellable(&request, arguments, None),
+                }
+            }
             "find_large_files" => self.find_large_files(arguments),
+            "directory_sizes" => self.directory_sizes(arguments),
+            "size_distribution" => self.size_distribution(arguments),
+            "activity_heatmap" => self.activity_heatmap(arguments),
+            "repo_inventory" => self.repo_inventory(arguments),
+            "security_scan" => {
+                let running = self.cancellations.start(&request);
+                self.security_scan(arguments, running.token())
+            }
+            "list_ads" => self.list_ads(arguments),
+            "get_file_info" => self.get_file_info(arguments),
+            "find_hardlinks" => self.find_hardlinks(arguments),
+            "resolve_file_id" => self.resolve_file_id(arguments),
+            "find_duplicates" => {
+                let running = self.cancellations.start(&request);
+                self.find_duplicates(arguments, ProgressReporter::for_request(&self.notifications, &request), running.token())
+            }
+            "export_index" => {
+                let running = self.cancellations.start(&request);
+                self.export_index(arguments, ProgressReporter::for_request(&self.notifications, &request), running.token())
+            }
+            "find_similar" => self.find_similar(arguments),
             "benchmark_search" => self.benchmark_search(arguments),
             "list_ntfs_drives" => self.list_ntfs_drives(),
             "list_document_types" => self.list_document_types(),
+            "save_search" => self.save_search(arguments),
+            "list_saved_searches" => self.list_saved_searches(),
+            "run_saved_search" => self.run_saved_search(arguments),
+            "watch_saved_search" => self.watch_saved_search(arguments),
+            "delete_saved_search" => self.delete_saved_search(arguments),
+            "bookmark_result" => self.bookmark_result(arguments),
+            "bookmark_results" => self.bookmark_results(arguments),
+            "list_collections" => self.list_collections(),
+            "get_collection" => self.get_collection(arguments),
+            "delete_collection" => self.delete_collection(arguments),
+            "run_report" => self.run_report(arguments),
+            "export_settings" => self.export_settings(arguments),
+            "import_settings" => self.import_settings(arguments),
+            "configure_drives" => self.configure_drives(arguments),
+            "setup_wizard" => self.setup_wizard(arguments, ProgressReporter::for_request(&self.notifications, &request)),
+            "maintenance_mode" => self.maintenance_mode(arguments),
+            "denied_paths" => self.denied_paths(arguments),
+            "memory_profile" => self.memory_profile(arguments),
             _ => Ok(json!({
                 "error": {
                     "code": -32602,
@@ -244,123 +687,2683 @@ impl SearchEngine {
     /// FAST SEARCH USING MFT CACHE
     /// 
     /// Args:
-    /// - pattern: File pattern to search for (e.g., "*.txt", "*.rs")
+    /// - pattern: File pattern to search for (e.g., "*.txt", "*.rs"), or an array of patterns to match any of
+    /// - not_pattern: Pattern(s) to leave out, also given as "!pattern" in pattern (optional)
+    /// - search_type: How to interpret the pattern (glob, regex, exact, fuzzy, smart)
+    /// - query: Boolean query (e.g. "*.log AND path:Windows NOT ext:tmp")
     /// - path_filter: Filter by path (optional)
+    /// - max_depth: Folder levels below the path to search, 1 for direct children (optional)
+    /// - exclude: Globs for paths to leave out, e.g. "node_modules/**" (optional)
+    /// - respect_gitignore: Apply the .gitignore/.ignore files of repositories (optional)
+    /// - verify_exists: Drop matches that no longer exist on disk (optional)
+    /// - owner: User name, DOMAIN\user or SID that must own matches (optional)
+    /// - reparse_points: follow, skip or only return symlinks, junctions and placeholders (default follow)
+    /// - include_streams: List alternate data streams of returned files (optional)
+    /// - include_image_info: Add dimensions and EXIF capture date of images (optional)
+    /// - taken_after/taken_before/min_megapixels: Capture date window and size of images (optional)
+    /// - verify_content_type: Drop files whose first bytes don't match their extension; adds mime_type (optional)
+    /// - content_kind: text or binary, to return only those (optional)
+    /// - sniff_content_kind: Tell text from binary by the first bytes where the extension doesn't (optional)
+    /// - max_per_directory: At most this many results per folder on a page (optional)
+    /// - include_doc_stats: Add page and word counts of documents (optional)
+    /// - min_pages/max_pages/min_words/max_words: Page and word count bounds of documents (optional)
+    /// - include_media_info: Add duration, bitrate and codec of audio and video (optional)
+    /// - min_duration/max_duration: Playing time bounds of audio and video, e.g. "30m" (optional)
+    /// - auto_fuzzy: Retry literal patterns without matches as fuzzy searches (default: on without search_type or with smart)
+    /// - summarize_broad: Summarize searches matching much of the cache instead of paging (default true)
+    /// - min_size/max_size: Size bounds, e.g. "10MB" (optional)
+    /// - hidden/system/readonly/compressed/encrypted/sparse: Require (true) or reject (false) an attribute (optional)
+    /// - created/modified/accessed_after/_before: Date windows (optional)
     /// - drive: Drive letter (e.g., "C") or "*" for all NTFS drives
-    /// - max_results: Maximum number of results to return
+    /// - workspace_hint: Folder of the current project; matches inside it are listed first (optional)
+    /// - max_results: Maximum number of results to return (page size)
+    /// - cursor: `next_cursor` of the previous page (optional)
+    /// - output: flat (default), grouped by parent folder, or a folder tree
+    /// - fields: Only these fields of each structured result, e.g. ["path"] (optional)
+    /// - within_results_of: `result_set` of an earlier search, to search only its matches (optional)
+    /// - confirm_token: Token of a confirmation_required answer (optional; checked by the tool call)
     pub fn fast_search(&self, args: &Value) -> Result<Value> {
-        let pattern = args["pattern"].as_str().unwrap_or("*");
-        let path_filter = args["path"].as_str().unwrap_or("").to_lowercase();
-        let drive = args["drive"].as_str().unwrap_or("C").to_uppercase();
-        let max_results = args["max_results"].as_u64().unwrap_or(1000) as usize;
+        self.fast_search_streaming(args, None)
+    }
+    
+    /// `fast_search` that hands matches to `partial` in batches while the scan is running
+    ///
+    /// Batches arrive in scan order and stop once a page worth of matches has been
+    /// streamed; the final response still holds the complete, sorted page.
+    pub fn fast_search_streaming(&self, args: &Value, partial: Option<PartialResultSink<'_>>) -> Result<Value> {
+        traced("fast_search", args, || {
+            let mut response = self.limited_search(args, partial, &self.cancel_token())?;
+            annotate_response(&mut response);
+            Ok(response)
+        })
+    }
+    
+    /// `fast_search_streaming` registered under the `id` of `request`, so `cancel_request` can stop it
+    ///
+    /// A cancelled search stops scanning and answers with the matches found so
+    /// far, flagged `cancelled` and `truncated`.
+    pub fn fast_search_cancellable(&self, request: &Value, args: &Value, partial: Option<PartialResultSink<'_>>) -> Result<Value> {
+        let running = self.cancellations.start(request);
+        traced("fast_search", args, || {
+            let mut response = self.limited_search(args, partial, running.token())?;
+            annotate_response(&mut response);
+            Ok(response)
+        })
+    }
+    
+    /// `fast_search_streaming` that stops when `cancel` is cancelled, like a cancelled request
+    ///
+    /// For callers with their own token from `cancel_token`, such as the Web API,
+    /// which cancels it when the client disconnects.
+    pub fn fast_search_with_cancel(&self, args: &Value, partial: Option<PartialResultSink<'_>>, cancel: &CancelToken) -> Result<Value> {
+        traced("fast_search", args, || {
+            let mut response = self.limited_search(args, partial, cancel)?;
+            annotate_response(&mut response);
+            Ok(response)
+        })
+    }
+    
+    /// `search_streaming` once one of the search slots is free
+    ///
+    /// A search that had to wait reports its place in line in `_meta.queue`.
+    fn limited_search(&self, args: &Value, partial: Option<PartialResultSink<'_>>, cancel: &CancelToken) -> Result<Value> {
+        let permit = match self.query_limiter.acquire(cancel) {
+            Ok(permit) => permit,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let mut response = self.search_streaming(args, partial, cancel)?;
+        if let Some(queued) = permit.queued {
+            info!("Search waited {}ms for a slot (position {} in line)", queued.waited_ms, queued.position);
+            if let Some(result) = response.get_mut("result").filter(|result| result.is_object()) {
+                result["_meta"]["queue"] = json!({
+                    "position": queued.position,
+                    "running": queued.running,
+                    "waited_ms": queued.waited_ms,
+                    "max_concurrent": self.query_limiter.max_concurrent()
+                });
+            }
+        }
+        Ok(response)
+    }
+    
+    /// The search behind `fast_search_streaming`, before the drive modes are added
+    fn search_streaming(&self, args: &Value, partial: Option<PartialResultSink<'_>>, cancel: &CancelToken) -> Result<Value> {
+        // Settled by what the caller sent, before a smart search fills in search_type
+        let auto_fuzzy = auto_fuzzy(args);
         
-        // Parse document type filter
-        let doc_type = args["doc_type"]
-            .as_str()
-            .and_then(|s| parse_document_type(s));
-            
-        // Parse explicit extensions if provided
-        let extensions: Option<HashSet<String>> = args["extensions"]
-            .as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.trim_start_matches('.').to_lowercase())
-                    .collect()
-            });
+        // Smart searches run as the glob or regex search their patterns read as
+        match SmartSearch::from_args(args) {
+            Ok(Some(smart)) => {
+                let mut response = self.search_streaming(&smart.args, partial, cancel)?;
+                // `*report*` found nothing: retry `report` itself as fuzzy
+                if let Some(retry) = smart.fuzzy_retry_args().filter(|_| auto_fuzzy && found_nothing(&response)) {
+                    if let Some(fuzzy) = self.fuzzy_fallback(&retry, SMART_SEARCH_TYPE, cancel)? {
+                        response = fuzzy;
+                    }
+                }
+                return Ok(smart_response(&smart, response));
+            }
+            Ok(None) => {}
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        }
+        let options = match SearchOptions::from_args(args, &self.doc_type_extensions, cancel) {
+            Ok(options) => options,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let literal = options.is_literal();
+        let reads_files = options.reads_files();
+        let SearchOptions {
+            patterns,
+            description,
+            search_type,
+            query,
+            max_results,
+            filters,
+            owner,
+            image_filter,
+            media_filter,
+            doc_stats_filter,
+            content_kind,
+            mut folder_cap,
+            respect_gitignore,
+            verify_exists,
+            verify_content_type,
+            summarize_broad,
+            layout,
+            projection,
+            timeout_ms,
+            deadline,
+        } = options;
+        let description = description.as_str();
+        let project = |entry: Value| match &projection {
+            Some(projection) => projection.apply(entry),
+            None => entry,
+        };
+        
+        // Narrow the matches of an earlier search instead of searching the whole drive
+        let within = match &args["within_results_of"] {
+            Value::Null => None,
+            Value::String(id) => match self.result_sets.get(id) {
+                Some(set) => Some(set),
+                None => return Ok(invalid_params(&format!(
+                    "Result set '{}' is unknown or expired; run the search it came from again", id
+                ))),
+            },
+            _ => return Ok(invalid_params("'within_results_of' must be the result_set of an earlier search")),
+        };
+        let drive = match args["drive"].as_str() {
+            Some(drive) => drive.to_uppercase(),
+            None => within.as_ref().map_or_else(|| self.default_drive().to_string(), |set| set.drive.clone()),
+        };
+        
+        // Matches inside the caller's project folder are ranked ahead of the rest of the drive
+        let workspace = args["workspace_hint"].as_str()
+            .filter(|hint| !hint.trim().is_empty())
+            .map(|hint| self.resolve_path_arg(hint, (drive != "*").then_some(drive.as_str())));
+        
+        // A path through a junction or directory symlink is searched at the link's target
+        if filters.reparse == ReparseMode::Follow && drive != "*" {
+            let raw_path = args["path"].as_str().unwrap_or_default();
+            if !raw_path.trim().is_empty() {
+                if let Some((target_drive, target_path)) = self.follow_path_links(raw_path, &drive) {
+                    let mut followed = args.clone();
+                    followed["drive"] = json!(target_drive.to_string());
+                    followed["path"] = json!(target_path);
+                    let response = self.search_streaming(&followed, partial, cancel)?;
+                    return Ok(followed_link_response(raw_path, target_drive, &target_path, response));
+                }
+            }
+        }
             
-        info!("Search filters - doc_type: {:?}, extensions: {:?}", doc_type, extensions);
-        info!("FAST SEARCH: pattern='{}', path='{}', drive='{}', max_results={}", 
-              pattern, path_filter, drive, max_results);
+        info!("Search filters - doc_type: {:?}, extensions: {:?}, exclude: {:?}", 
+              filters.doc_type, filters.extensions, filters.exclude.patterns());
+        info!("FAST SEARCH: pattern='{}', search_type={}, query={:?}, path='{}', drive='{}', max_results={}", 
+              patterns.describe(), search_type.as_str(), args["query"].as_str(), filters.path, drive, max_results);
         
         let search_start = Instant::now();
         
-        // Get or create MFT cache for the drive
+        // Resolve what to search: "*" covers every local NTFS volume once, and a subst'd
+        // or duplicate drive letter searches the volume (and folder) it exposes
         let drive_char = drive.chars().next().unwrap_or('C');
-        let mft_cache = self.get_or_create_cache(drive_char)?;
+        let targets: Vec<(char, DriveAlias)> = if drive == "*" {
+            self.all_drives().into_iter().map(|d| (d, DriveAlias::whole(d))).collect()
+        } else {
+            vec![(drive_char, self.volumes.resolve(drive_char))]
+        };
+        
+        // An anchored path glob starts at the root of the letter searched, which a subst'd letter moves
+        let filters = match (&filters.path_glob, targets.as_slice()) {
+            (Some(glob), [(_, alias)]) if !alias.prefix.is_empty() => {
+                SearchFilters { path_glob: Some(glob.rooted_at(&alias.prefix)), ..filters.clone() }
+            }
+            _ => filters,
+        };
+        
+        // How each volume of an all-drive search went, so a slow or uncached drive stands out
+        let mut skipped: Vec<VolumeStats> = Vec::new();
+        let mut volume_stats: Vec<VolumeStats> = Vec::new();
+        let mut sources = Vec::new();
+        for (shown_as, alias) in targets {
+            let opened = Instant::now();
+            let cache_hit = self.mft_cache.read().contains_key(&alias.canonical);
+            let mft_cache = match self.get_or_create_cache(alias.canonical) {
+                Ok(cache) => cache,
+                Err(e) if drive == "*" => {
+                    error!("Skipping drive {}: in all-drive search: {}", alias.canonical, e);
+                    skipped.push(VolumeStats::unavailable(shown_as, e.to_string(), opened.elapsed().as_millis() as u64));
+                    continue;
+                }
+                Err(e) => match drive_mode(alias.canonical) {
+                    Some(mode) if mode.mode == IndexMode::DirectScan => {
+                        return self.direct_scan_search(shown_as, &alias, &patterns, search_type, &filters, max_results, description, &deadline);
+                    }
+                    _ => return Err(e),
+                },
+            };
+            let project_index = self.get_project_index(&mft_cache);
+            let mut stats = VolumeStats::searched(shown_as, cache_hit);
+            stats.elapsed_ms = opened.elapsed().as_millis() as u64;
+            volume_stats.push(stats);
+            sources.push(SearchSource { shown_as, alias, mft_cache, project_index });
+        }
+        
+        // Compile the patterns (cached); invalid expressions are reported to the caller
+        let pattern_matcher = match self.pattern_cache.get_or_compile_set(&patterns, search_type) {
+            Ok(matcher) => matcher,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        
+        // Continue after the previous page, if this is a follow-up call
+        let cursor = match args["cursor"].as_str() {
+            Some(token) => match PageCursor::decode(token, drive_char, args) {
+                Ok(cursor) => Some(cursor),
+                Err(e) => return Ok(invalid_params(&e.to_string())),
+            },
+            None => None,
+        };
+        let after_cursor = |item: PageItem<'_>| {
+            cursor.as_ref().map_or(true, |cursor| is_after(item, &cursor.last))
+        };
+        
+        // Get read locks on the caches and the indexes candidates are taken from
+        let prefilter = Prefilter::choose(&pattern_matcher, &filters);
+        let stages = CacheStages {
+            prefilter: &prefilter,
+            within: within.as_deref(),
+            matcher: &pattern_matcher,
+            filters: &filters,
+            query: query.as_ref(),
+        };
+        let locks: Vec<CacheLocks<'_>> = sources.iter().map(|source| CacheLocks::of(&source.mft_cache)).collect();
+        let scan_sources: Vec<ScanSource<'_>> = sources.iter().zip(&locks)
+            .map(|(source, locks)| ScanSource { shown_as: source.shown_as, alias: &source.alias, files: &locks.files, index: locks.indexes() })
+            .collect();
+        
+        // Disk checks, which only ever see matches that passed every cheap stage
+        let path_maps: Vec<_> = sources.iter()
+            .map(|source| respect_gitignore.then(|| source.mft_cache.get_path_index()))
+            .collect();
+        let verify_stage = VerifyStage::new(sources.iter().zip(&locks).zip(&path_maps)
+            .map(|((source, locks), paths)| {
+                let root = self.drive_root(source.alias.canonical);
+                let gitignore = paths.as_ref().map(|paths| GitignoreFilter::new(
+                    &self.ignore_files,
+                    source.alias.canonical,
+                    root.clone(),
+                    &locks.files,
+                    paths,
+                ));
+                let owner = owner.clone().map(|query| OwnerFilter::new(query, root.clone()));
+                let disk = DriveRoot::new(source.alias.canonical, root.clone());
+                let image = image_filter.map(|filter| ImageCheck::new(filter, &self.image_info, disk.clone()));
+                let media = media_filter.map(|filter| MediaCheck::new(filter, &self.media_info, disk.clone()));
+                let content = verify_content_type.then(|| ContentCheck::new(&self.content_types, disk.clone()));
+                let doc_stats = doc_stats_filter.map(|filter| DocStatsCheck::new(filter, &self.doc_stats, disk.clone()));
+                let kind = content_kind.kind.map(|kind| ContentKindCheck::new(kind, content_kind.sniff, &self.content_kinds, disk));
+                let verifier = Verifier::new(gitignore, verify_exists.then_some(root), owner, image, media, content, doc_stats, kind);
+                (source.shown_as, &source.alias, verifier)
+            })
+            .collect());
+        
+        let total: usize = scan_sources.iter().map(|source| stages.candidate_count(source)).sum();
+        let mut stream = partial.map(|sink| BatchStream::new(sink, max_results, total as u64));
+        
+        // A first page of a search matching much of the cache is an arbitrary sliver;
+        // estimate the match count from a sample and summarize instead. The estimate
+        // can't see the owner check or what is read from the files, which may leave only a handful of the matches,
+        // and a workspace hint makes the first page the project's matches, not a sliver.
+        // A search that matched too little to be broad last time isn't sampled again.
+        let entries: usize = scan_sources.iter().map(|source| source.files.len()).sum();
+        let known_narrow = self.warm_queries.known_matches(&drive, args, entries)
+            .map_or(false, |matches| !is_broad_count(matches as f64, entries));
+        if summarize_broad && !known_narrow && cursor.is_none() && within.is_none()
+            && !pattern_matcher.is_scored() && !reads_files && workspace.is_none()
+        {
+            let density = stages.sample_density(&scan_sources);
+            if density.is_broad() {
+                let summary = density.summary();
+                info!("Broad search '{}' on drive {}: ~{} of {} entries match, returning a summary",
+                      description, drive, summary.estimated_matches, summary.total_entries);
+                self.warm_queries.record(&drive, args, &patterns.include, search_type, summary.estimated_matches, entries);
+                return Ok(broad_summary_response(description, &drive, max_results, &summary, search_start.elapsed()));
+            }
+        }
+        
+        // Collect every match; only the returned page is sorted and cloned
+        let streamed_cap = RefCell::new(folder_cap.as_ref().map(|cap| FolderCap::new(cap.limit())));
+        let found = |item: PageItem<'_>, scanned: u64, stream: &mut Option<BatchStream<'_>>| {
+            if let Some(stream) = stream.as_mut().filter(|stream| stream.wants_more()) {
+                // Streamed matches are checked right away; the page is checked again at the end
+                if !verify_stage.accepts(item) {
+                    return;
+                }
+                if !streamed_cap.borrow_mut().as_mut().map_or(true, |cap| cap.admit(item.0, &verify_stage.shown_path(item))) {
+                    return;
+                }
+                if let Some(mut hit) = self.search_hit(&sources, item) {
+                    hit.highlights = highlights(&pattern_matcher, &filters.path, &hit.file.name, &hit.file.path);
+                    stream.push(project(hit_json(&hit)), scanned);
+                }
+            }
+        };
+        let workspace_folder = workspace.as_ref().map(|workspace| (workspace.alias.canonical, workspace.cache_path.as_str()));
+        let Scan { mut matches, renamed, mut counts, scanned } = stages.scan(
+            &scan_sources,
+            workspace_folder,
+            &deadline,
+            &mut volume_stats,
+            |scanned, item| match item {
+                None => {
+                    if let Some(stream) = stream.as_mut() {
+                        stream.tick(scanned);
+                    }
+                }
+                Some(item) => {
+                    if after_cursor(item) {
+                        found(item, scanned, &mut stream);
+                    }
+                }
+            },
+        );
+        if let Some(stream) = stream.as_mut() {
+            stream.flush(scanned);
+        }
+        // Matches past the deadline weren't looked at, so what was found can't stand for the whole search
+        let truncated = deadline.was_hit();
+        let cancelled = truncated && cancel.is_cancelled();
+        if truncated {
+            info!("Search '{}' on drive {} stopped ({}) after {} candidates",
+                  description, drive, if cancelled { "cancelled" } else { "timeout" }, scanned);
+        }
+        
+        // Keep the matches for later searches to narrow; pages after the first belong to its set
+        let result_set = if cursor.is_none() && !matches.is_empty() && !truncated {
+            self.result_sets.insert(&drive, description, matches.iter().map(|item| (item.0, item.1.id)))
+        } else {
+            None
+        };
+        matches.retain(|item| after_cursor(*item));
+        
+        // Verify in page order until the page is full
+        let remaining = matches.len();
+        let (page, has_more) = verify_stage.page(matches, max_results, folder_cap.as_mut(), &mut counts);
+        debug!("Search stages: {:?}", counts);
+        if cursor.is_none() && !truncated {
+            self.warm_queries.record(&drive, args, &patterns.include, search_type, counts.filter_matches, entries);
+        }
+        let next_cursor = page.last()
+            .filter(|_| has_more && !truncated)
+            .map(|item| PageCursor::new(drive_char, args, SortKey::of(*item)).encode());
+        let mut results: Vec<SearchHit> = page.into_iter()
+            .filter_map(|item| self.search_hit(&sources, item))
+            .collect();
+        for hit in &mut results {
+            hit.highlights = highlights(&pattern_matcher, &filters.path, &hit.file.name, &hit.file.path);
+        }
+        
+        let streams_error = self.annotate_hits(&sources, &mut results, &renamed, args, content_kind.sniff);
+        
+        // Explain a search that found nothing at all (rather than only failing disk checks)
+        // Within a result set, the rest of the drive isn't what the caller asked about
+        let hints = if results.is_empty() && cursor.is_none() && within.is_none() && counts.filter_matches == 0 && !truncated {
+            self.no_result_hints(stages, &scan_sources, &patterns, search_type, args)
+        } else {
+            None
+        };
+        drop(verify_stage);
+        drop(scan_sources);
+        drop(path_maps);
+        drop(locks);
+        
+        // A literal name that matches nothing may be a typo; try it as a fuzzy search
+        if auto_fuzzy && literal && results.is_empty() && cursor.is_none() && counts.filter_matches == 0 && !truncated {
+            if let Some(response) = self.fuzzy_fallback(args, search_type.as_str(), cancel)? {
+                return Ok(response);
+            }
+        }
+        
+        let search_duration = search_start.elapsed();
+        etw::slow_query(description, &drive, results.len(), search_duration, &counts);
+        
+        // Reported for all-drive searches only; a single drive's numbers are the stage counts
+        let volumes: Vec<VolumeStats> = if drive == "*" {
+            let mut volumes: Vec<VolumeStats> = volume_stats.into_iter().chain(skipped).collect();
+            volumes.sort_by(|a, b| a.drive.cmp(&b.drive));
+            volumes
+        } else {
+            Vec::new()
+        };
+        
+        // Each file's data streams follow it as entries of their own
+        let hits = results.iter()
+            .map(|hit| ListedHit {
+                drive: hit.drive,
+                name: hit.file.name.clone(),
+                path: hit.file.path.clone(),
+                notes: describe_hit(hit),
+                entries: std::iter::once(hit_json(hit))
+                    .chain(hit.streams.iter().map(|stream| stream_json(hit, stream)))
+                    .map(project)
+                    .collect(),
+            })
+            .collect();
+        Ok(SearchPage {
+            description,
+            drive: &drive,
+            hits,
+            layout,
+            fields: projection.as_ref().map(FieldProjection::fields),
+            next_cursor,
+            remaining,
+            within: within.as_deref(),
+            result_set,
+            counts,
+            hints,
+            folder_cap: folder_cap.as_ref(),
+            streams_error,
+            scanned,
+            truncated,
+            cancelled,
+            timeout_ms,
+            volumes,
+            elapsed: search_duration,
+        }.into_response())
+    }
+    
+    /// `args` retried as a fuzzy search, labeled "did you mean", if that finds anything
+    ///
+    /// `searched_as` is the search type the patterns found nothing as.
+    fn fuzzy_fallback(&self, args: &Value, searched_as: &str, cancel: &CancelToken) -> Result<Option<Value>> {
+        let mut retry = args.clone();
+        retry["search_type"] = json!(SearchType::Fuzzy.as_str());
+        retry["auto_fuzzy"] = json!(false);
+        let response = self.search_streaming(&retry, None, cancel)?;
+        if !response["result"]["stages"]["verified"].as_u64().map_or(false, |verified| verified > 0) {
+            return Ok(None);
+        }
+        let pattern = PatternSet::from_args(args)?.describe();
+        info!("No match for '{}', returning fuzzy matches instead", pattern);
+        Ok(Some(did_you_mean_response(&pattern, searched_as, response)))
+    }
+    
+    /// Fill in what the page's results were asked to carry beyond the cache entry
+    ///
+    /// The other paths of each record, its named data streams, image, media
+    /// and document details and its content kind. Returns why the data
+    /// streams couldn't be read, if they couldn't.
+    fn annotate_hits(
+        &self,
+        sources: &[SearchSource],
+        results: &mut [SearchHit],
+        renamed: &HashSet<(char, u64)>,
+        args: &Value,
+        sniff_content_kind: bool,
+    ) -> Option<String> {
+        // The other paths of records found under several names, or that have several links
+        for source in sources {
+            let linked: HashSet<u64> = results.iter()
+                .filter(|hit| hit.drive == source.shown_as)
+                .filter(|hit| hit.file.link_count > 1 || renamed.contains(&(hit.drive, hit.file.id)))
+                .map(|hit| hit.file.id)
+                .collect();
+            if linked.is_empty() {
+                continue;
+            }
+            let mut links = source.mft_cache.link_paths_of(&linked);
+            for hit in results.iter_mut().filter(|hit| hit.drive == source.shown_as) {
+                let Some(paths) = links.remove(&hit.file.id) else { continue };
+                hit.alternate_paths = paths.iter()
+                    .filter_map(|path| source.alias.display_path(path))
+                    .filter(|path| !path.eq_ignore_ascii_case(&hit.file.path))
+                    .map(|path| format!("{}:\\{}", source.shown_as, path))
+                    .collect();
+            }
+        }
+        
+        // Named data streams of the page, read from the file records
+        let mut streams_error = None;
+        if args["include_streams"].as_bool().unwrap_or(false) {
+            for source in sources.iter().filter(|source| !self.is_mounted(source.alias.canonical)) {
+                let records: Vec<u64> = results.iter()
+                    .filter(|hit| hit.drive == source.shown_as)
+                    .map(|hit| hit.file.id)
+                    .collect();
+                if records.is_empty() {
+                    continue;
+                }
+                match read_streams(source.alias.canonical, &records) {
+                    Ok(mut found) => {
+                        for hit in results.iter_mut().filter(|hit| hit.drive == source.shown_as) {
+                            hit.streams = found.remove(&hit.file.id).unwrap_or_default();
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to read data streams on drive {}: {}", source.alias.canonical, e);
+                        streams_error = Some(e.to_string());
+                    }
+                }
+            }
+        }
+        
+        // Image dimensions and capture dates of the page, mostly known from the image check
+        let include_image_info = args["include_image_info"].as_bool().unwrap_or(false);
+        let verify_content_type = args["verify_content_type"].as_bool().unwrap_or(false);
+        let include_doc_stats = args["include_doc_stats"].as_bool().unwrap_or(false);
+        let include_media_info = args["include_media_info"].as_bool().unwrap_or(false);
+        for hit in results.iter_mut() {
+            let Some(source) = sources.iter().find(|source| source.shown_as == hit.drive) else { continue };
+            let (drive, root) = (source.alias.canonical, self.drive_root(source.shown_as));
+            if include_image_info {
+                hit.image = self.image_info.get(drive, &root, &hit.file);
+            }
+            if verify_content_type {
+                hit.mime_type = self.content_types.mime_type(drive, &root, &hit.file);
+            }
+            if include_doc_stats {
+                hit.doc_stats = self.doc_stats.get(drive, &root, &hit.file);
+            }
+            // Text or binary: free from the extension, read from the file only when asked to sniff
+            hit.content_kind = self.content_kinds.get(drive, &root, &hit.file, sniff_content_kind);
+            if include_media_info {
+                hit.media = self.media_info.get(drive, &root, &hit.file);
+            }
+        }
+        streams_error
+    }
+    
+    /// Why a search found nothing: likely misspellings, and what it finds without its path or on other drives
+    fn no_result_hints(
+        &self,
+        stages: CacheStages<'_>,
+        sources: &[ScanSource<'_>],
+        patterns: &PatternSet,
+        search_type: SearchType,
+        args: &Value,
+    ) -> Option<NoResultHints> {
+        let mut hints = NoResultHints::default();
+        let spelling = match patterns.include.as_slice() {
+            [single] => SpellingTarget::of(single, search_type),
+            _ => None,
+        };
+        match spelling {
+            Some(SpellingTarget::Name(name)) => {
+                hints.similar_names = similar_names(&name, sources.iter().map(|source| (source.files, source.index.names)));
+            }
+            Some(SpellingTarget::Extension(ext)) => {
+                hints.similar_extensions = similar_extensions(&ext, sources.iter().map(|source| source.index.extensions));
+            }
+            None => {}
+        }
+        
+        // Same search without the path filter
+        if !stages.filters.path.is_empty() {
+            let unfiltered = SearchFilters { path: String::new(), path_glob: None, max_depth: None, ..stages.filters.clone() };
+            let unfiltered_stages = CacheStages { filters: &unfiltered, ..stages };
+            let mut matches_without = 0;
+            let mut folders: Vec<String> = Vec::new();
+            for source in sources {
+                let found = unfiltered_stages.cache_matches(source.files, &source.index)
+                    .filter_map(|file| source.alias.display_path(&file.path));
+                for path in found {
+                    matches_without += 1;
+                    let folder = path.rsplit_once('\\').map_or("", |(folder, _)| folder);
+                    if folders.len() < MAX_EXAMPLE_FOLDERS && !folders.iter().any(|f| f == folder) {
+                        folders.push(folder.to_string());
+                    }
+                }
+            }
+            if matches_without > 0 {
+                hints.path_filter = Some(PathFilterHint {
+                    path: args["path"].as_str().unwrap_or_default().to_string(),
+                    matches_without,
+                    folders,
+                });
+            }
+        }
+        
+        // Same search on the other drives that are already cached
+        let searched: HashSet<char> = sources.iter().map(|source| source.alias.canonical).collect();
+        let mut others: Vec<(char, Arc<MftCache>)> = self.mft_cache.read().iter()
+            .filter(|(drive, _)| !searched.contains(drive))
+            .map(|(drive, cache)| (*drive, Arc::clone(cache)))
+            .collect();
+        others.sort_by_key(|(drive, _)| *drive);
+        for (other, cache) in others {
+            let locks = CacheLocks::of(&cache);
+            let matches = stages.cache_matches(&locks.files, &locks.indexes()).count();
+            if matches > 0 {
+                hints.other_drives.push(DriveMatches { drive: other.to_string(), matches: matches as u64 });
+            }
+        }
+        (!hints.is_empty()).then_some(hints)
+    }
+    
+    /// Answer a search by reading the MFT directly, for a drive whose cache can't be built
+    ///
+    /// Only the name patterns, path, depth and exclusions apply; the other
+    /// filters and the query need the cache.
+    fn direct_scan_search(
+        &self,
+        shown_as: char,
+        alias: &DriveAlias,
+        patterns: &PatternSet,
+        search_type: SearchType,
+        filters: &SearchFilters,
+        max_results: usize,
+        description: &str,
+        deadline: &Deadline,
+    ) -> Result<Value> {
+        let search_start = Instant::now();
+        let matcher = match self.pattern_cache.get_or_compile_set(patterns, search_type) {
+            Ok(matcher) => matcher,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        // The reader stops after `limit` matches of its own glob, so anything
+        // it can't match itself is read in bulk and matched here
+        let (scan_pattern, limit) = match patterns.include.as_slice() {
+            [glob] if search_type == SearchType::Glob && patterns.exclude.is_empty() && !patterns.case_sensitive
+                && !patterns.ignore_diacritics && !patterns.match_path && !glob.contains(['[', '{', '/', '\\']) => (glob.as_str(), max_results),
+            _ => ("*", DIRECT_SCAN_LIMIT),
+        };
+        let path = match (&filters.path_glob, alias.prefix.is_empty(), filters.path.is_empty()) {
+            // Already rooted at the alias's folder
+            (Some(glob), _, _) => glob.pattern(),
+            (None, true, _) => filters.path.clone(),
+            (None, false, true) => alias.prefix.clone(),
+            (None, false, false) => format!("{}\\{}", alias.prefix, filters.path),
+        };
+        
+        let drive = alias.canonical.to_string();
+        let files = super::ntfs_reader::search_files_direct_until(&drive, scan_pattern, &path, &filters.exclude, filters.max_depth, limit, deadline)?;
+        let scanned = files.len();
+        let results: Vec<_> = files.into_iter()
+            .filter(|file| matcher.is_file_match(&file.name, &file.full_path))
+            .take(max_results)
+            .collect();
+        let elapsed = search_start.elapsed();
+        
+        let mut text = format!("🐢 DIRECT SCAN: Found {} files matching '{}' in {:.2}ms\n\n", results.len(), description, elapsed.as_millis());
+        for (i, file) in results.iter().enumerate() {
+            let size_info = if file.is_directory { "DIR".to_string() } else { format!("{} bytes", file.size) };
+            text.push_str(&format!("{}. {} ({})\n", i + 1, file.full_path, size_info));
+        }
+        if limit == DIRECT_SCAN_LIMIT && scanned >= limit {
+            text.push_str(&format!("\n⚠️ Only the first {} entries were read; narrow the path to see the rest", limit));
+        }
+        let truncated = deadline.was_hit();
+        if truncated {
+            text.push_str(&format!("\n⏱️ Partial results: the MFT walk stopped early (timeout or cancellation) after {} entries", scanned));
+        }
+        
+        let mut response = json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "results": results.iter().map(|file| json!({
+                    "name": file.name,
+                    "path": file.full_path,
+                    "drive": shown_as.to_string(),
+                    "size": file.size,
+                    "is_directory": file.is_directory,
+                    "created": file.created,
+                    "modified": file.modified,
+                    "accessed": file.accessed,
+                })).collect::<Vec<_>>(),
+                "next_cursor": null
+            }
+        });
+        if truncated {
+            response["result"]["truncated"] = json!(true);
+        }
+        Ok(response)
+    }
+    
+    /// Turn a match into a result as seen through the drive letter it was found under
+    fn search_hit(&self, sources: &[SearchSource], item: PageItem<'_>) -> Option<SearchHit> {
+        let (shown_as, file, score, in_workspace) = item;
+        let source = sources.iter().find(|source| source.shown_as == shown_as)?;
+        
+        let mut hit = SearchHit {
+            drive: shown_as,
+            file: file.clone(),
+            score,
+            in_workspace,
+            project: source.project_index.nearest_root(&file.path).cloned(),
+            aliases: self.volumes.locations(source.alias.canonical, &file.path),
+            alternate_paths: Vec::new(),
+            streams: Vec::new(),
+            image: None,
+            media: None,
+            mime_type: None,
+            doc_stats: None,
+            content_kind: None,
+            reparse_target: None,
+            highlights: Vec::new(),
+        };
+        if ReparseKind::of(file.reparse_tag).map_or(false, |kind| kind.is_link()) {
+            hit.reparse_target = read_target(&disk_path(&self.drive_root(source.alias.canonical), &file.path));
+        }
+        hit.file.path = source.alias.display_path(&file.path)?.to_string();
+        
+        // Every other name the same file is reachable under
+        let shown = format!("{}:\\{}", shown_as, hit.file.path);
+        hit.aliases.retain(|location| !location.eq_ignore_ascii_case(&shown));
+        Some(hit)
+    }
+    
+    /// Follow the directory links a `path` filter runs through
+    ///
+    /// The MFT has no entries below a junction or directory symlink, so a path
+    /// through one only finds files at the link's target. Returns the drive and
+    /// path the filter really refers to, or `None` if it doesn't pass through a
+    /// link (or the links form a cycle).
+    fn follow_path_links(&self, raw_path: &str, drive: &str) -> Option<(char, String)> {
+        let mut current = (drive.chars().next()?, raw_path.to_string());
+        for hop in 0..=MAX_LINK_HOPS {
+            let letter = current.0.to_string();
+            let CachePath { alias, cache_path, .. } = self.resolve_path_arg(&current.1, Some(&letter));
+            match self.link_in_path(alias.canonical, &cache_path) {
+                Some(target) => current = target,
+                None => return (hop > 0).then_some(current),
+            }
+        }
+        None
+    }
+    
+    /// Rewrite a cache path through the first directory link along it
+    fn link_in_path(&self, drive: char, path: &str) -> Option<(char, String)> {
+        let cache = self.get_or_create_cache(drive).ok()?;
+        let components: Vec<&str> = path.split('\\').filter(|part| !part.is_empty()).collect();
+        let link = {
+            let files = cache.get_files();
+            let paths = cache.get_path_index();
+            let mut prefix = String::new();
+            let mut link = None;
+            for (i, part) in components.iter().enumerate() {
+                if !prefix.is_empty() {
+                    prefix.push('\\');
+                }
+                prefix.push_str(part);
+                // Folders that aren't in the cache can't be links either
+                let file = lookup_path(&paths, &prefix).and_then(|id| files.get(&id))?;
+                if ReparseKind::of(file.reparse_tag).map_or(false, |kind| kind.is_link()) {
+                    link = Some((file.path.clone(), i + 1));
+                    break;
+                }
+            }
+            link
+        };
+        let (link, rest) = link?;
+        
+        let root = self.drive_root(drive);
+        let target = read_target(&disk_path(&root, &link))?;
+        let (target_drive, target_path) = resolve_target(drive, &root, &link, &target)?;
+        let path = std::iter::once(target_path.as_str())
+            .chain(components[rest..].iter().copied())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\\");
+        debug!("Following link {}:\\{} to {}:\\{}", drive, link, target_drive, path);
+        Some((target_drive, path))
+    }
+    
+    /// Folder on disk that a drive's cache paths are relative to
+    fn drive_root(&self, drive: char) -> PathBuf {
+        match self.mounts.iter().find(|m| m.drive == drive) {
+            Some(mount) => mount.path.clone(),
+            None => PathBuf::from(format!("{}:\\", drive)),
+        }
+    }
+    
+    /// Drives searched for drive "*"
+    fn all_drives(&self) -> Vec<char> {
+        let drives = if self.mounts.is_empty() {
+            self.volumes.search_drives()
+        } else {
+            self.mounts.iter().map(|m| m.drive).collect()
+        };
+        drives.into_iter().filter(|drive| !self.drives.is_excluded(*drive)).collect()
+    }
+    
+    /// Whether using a drive means building its cache from scratch, with no cache in memory or saved on disk
+    fn is_cold(&self, drive: char) -> bool {
+        let config = MftCacheConfig::default();
+        !self.mft_cache.read().contains_key(&drive)
+            && !config.persistence_backend.store().has_saved(&config.cache_dir, &VolumeId::for_drive(drive))
+    }
+    
+    /// What a `fast_search` call would cost, when it's enough to ask before running it
+    ///
+    /// Searches on drives without a cache read their whole MFT first. That is
+    /// expected of a first search, but not for a regex or fuzzy pattern matched
+    /// against every name, or for a search of every drive at once.
+    fn search_cost(&self, args: &Value) -> Option<CostEstimate> {
+        let drive = args["drive"].as_str().map(str::to_uppercase);
+        let drives: Vec<char> = match drive.as_deref() {
+            Some("*") => self.all_drives(),
+            Some(drive) => vec![self.volumes.resolve(drive.chars().next().unwrap_or('C')).canonical],
+            None => vec![self.volumes.resolve(self.default_drive()).canonical],
+        };
+        let cold: Vec<String> = drives.into_iter()
+            .filter(|drive| self.is_cold(*drive))
+            .map(|drive| format!("{}:", drive))
+            .collect();
+        let search_type = match SmartSearch::from_args(args) {
+            Ok(Some(smart)) => smart.search_type,
+            _ => args["search_type"].as_str().and_then(SearchType::parse).unwrap_or_default(),
+        };
+        let every_name = matches!(search_type, SearchType::Regex | SearchType::Fuzzy | SearchType::Camel);
+        let every_drive = drive.as_deref() == Some("*");
+        if cold.is_empty() || !(every_name || every_drive) {
+            return None;
+        }
+        
+        let mut reasons = vec![format!("There is no cache of {} yet, so the whole MFT is read first (minutes on a large drive)", cold.join(", "))];
+        if every_name {
+            reasons.push(format!("The {} pattern is then matched against every name", search_type.as_str()));
+        }
+        Some(CostEstimate { reasons, cold_drives: cold, ..Default::default() })
+    }
+    
+    /// Use up a `confirm_token`, answering a bad one with an error response
+    fn redeem_confirmation(&self, tool: &str, args: &Value) -> std::result::Result<bool, Value> {
+        self.confirmations.redeem(tool, args).map_err(|e| invalid_params(&e.to_string()))
+    }
+    
+    /// Answer an expensive call with its estimate and a token to run it with
+    fn confirmation_required(&self, tool: &str, args: &Value, estimate: CostEstimate) -> Result<Value> {
+        let token = self.confirmations.issue(tool, args)?;
+        info!("Asking the client to confirm {}: {}", tool, estimate.reasons.join("; "));
+        
+        let mut text = format!("⚠️ CONFIRMATION REQUIRED: this {} call is expensive.\n\n", tool);
+        for reason in &estimate.reasons {
+            text.push_str(&format!("- {}\n", reason));
+        }
+        text.push_str(&format!(
+            "\n💡 Call {} again with the same arguments plus {}: \"{}\" to go ahead (valid for {} minutes), \
+             or narrow it down first (path, extensions, a glob pattern, a single drive).",
+            tool, CONFIRM_TOKEN_ARG, token, TOKEN_LIFETIME.as_secs() / 60
+        ));
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "confirmation_required": {
+                    "tool": tool,
+                    "token": token,
+                    "expires_in_secs": TOKEN_LIFETIME.as_secs(),
+                    "estimate": estimate
+                }
+            }
+        }))
+    }
+    
+    /// Save a named search, optionally watching it for new matches
+    fn save_search(&self, args: &Value) -> Result<Value> {
+        let name = match args["name"].as_str().map(str::trim) {
+            Some(name) if !name.is_empty() => name,
+            _ => return Ok(invalid_params("save_search requires a non-empty 'name'")),
+        };
+        if !args["search"].is_object() {
+            return Ok(invalid_params("save_search requires a 'search' object with fast_search arguments"));
+        }
+        let watched = args["watched"].as_bool().unwrap_or(false);
+        
+        let search = SavedSearch::new(name, args["search"].clone(), watched);
+        if watched {
+            self.ensure_monitoring(search.drive())?;
+        }
+        self.saved_searches.insert(search.clone())?;
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": format!("Saved search '{}'{}", name, if watched { " (watched)" } else { "" })
+                }],
+                "saved_search": search
+            }
+        }))
+    }
+    
+    /// List all saved searches
+    fn list_saved_searches(&self) -> Result<Value> {
+        let searches = self.saved_searches.list();
+        
+        let text = if searches.is_empty() {
+            "No saved searches".to_string()
+        } else {
+            searches.iter()
+                .map(|s| format!("{}{}: {}", s.name, if s.watched { " [watched]" } else { "" }, s.arguments))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "saved_searches": searches
+            }
+        }))
+    }
+    
+    /// Run a saved search through fast_search
+    fn run_saved_search(&self, args: &Value) -> Result<Value> {
+        let name = args["name"].as_str().unwrap_or("");
+        match self.saved_searches.get(name) {
+            Some(search) => self.fast_search(&search.arguments),
+            None => Ok(invalid_params(&format!("No saved search named '{}'", name))),
+        }
+    }
+    
+    /// Start or stop watching a saved search
+    fn watch_saved_search(&self, args: &Value) -> Result<Value> {
+        let name = args["name"].as_str().unwrap_or("");
+        let watched = args["watched"].as_bool().unwrap_or(true);
+        
+        let Some(search) = self.saved_searches.get(name) else {
+            return Ok(invalid_params(&format!("No saved search named '{}'", name)));
+        };
+        if watched {
+            self.ensure_monitoring(search.drive())?;
+        }
+        self.saved_searches.set_watched(name, watched)?;
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": if watched {
+                        format!("Watching '{}' on drive {}: new matches will be sent as notifications", name, search.drive())
+                    } else {
+                        format!("Stopped watching '{}'", name)
+                    }
+                }],
+                "name": name,
+                "drive": search.drive().to_string(),
+                "watched": watched
+            }
+        }))
+    }
+    
+    /// Delete a saved search
+    fn delete_saved_search(&self, args: &Value) -> Result<Value> {
+        let name = args["name"].as_str().unwrap_or("");
+        match self.saved_searches.remove(name)? {
+            Some(search) => Ok(json!({
+                "result": {
+                    "content": [{
+                        "type": "text",
+                        "text": format!("Deleted saved search '{}'", name)
+                    }],
+                    "deleted": search
+                }
+            })),
+            None => Ok(invalid_params(&format!("No saved search named '{}'", name))),
+        }
+    }
+    
+    /// Bookmark one path into a collection
+    fn bookmark_result(&self, args: &Value) -> Result<Value> {
+        let collection = match args["collection"].as_str().map(str::trim) {
+            Some(name) if !name.is_empty() => name,
+            _ => return Ok(invalid_params("bookmark_result requires a non-empty 'collection'")),
+        };
+        let raw_path = match args["path"].as_str() {
+            Some(path) if !path.trim().is_empty() => path,
+            _ => return Ok(invalid_params("bookmark_result requires a 'path'")),
+        };
+        let target = self.resolve_path_arg(raw_path, args["drive"].as_str());
+        let mut bookmark = Bookmark::new(&target.drive.to_string(), &target.path, args["note"].as_str());
+        
+        // Fill in what the cache knows, without building one just for this
+        let cache = self.mft_cache.read().get(&target.alias.canonical).cloned();
+        if let Some(cache) = cache {
+            let id = lookup_path(&cache.get_path_index(), &target.cache_path);
+            if let Some(file) = id.and_then(|id| cache.get_files().get(&id).cloned()) {
+                bookmark.name = file.name;
+                bookmark.size = Some(file.size);
+                bookmark.is_directory = Some(file.is_directory);
+            }
+        }
+        
+        let shown = format!("{}:\\{}", bookmark.drive, bookmark.path);
+        let (collection, added) = self.collections.add(collection, None, vec![bookmark])?;
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": if added > 0 {
+                        format!("Bookmarked {} in '{}' ({} items)", shown, collection.name, collection.items.len())
+                    } else {
+                        format!("{} was already in '{}'", shown, collection.name)
+                    }
+                }],
+                "collection": collection.name,
+                "added": added,
+                "total": collection.items.len()
+            }
+        }))
+    }
+    
+    /// Bookmark a set of results, given or found by running a search, into a collection
+    fn bookmark_results(&self, args: &Value) -> Result<Value> {
+        let collection = match args["collection"].as_str().map(str::trim) {
+            Some(name) if !name.is_empty() => name,
+            _ => return Ok(invalid_params("bookmark_results requires a non-empty 'collection'")),
+        };
+        let note = args["note"].as_str();
+        
+        let (results, source) = match (args["results"].as_array(), args["search"].is_object()) {
+            (Some(results), false) => (results.clone(), Value::Null),
+            (None, true) => {
+                // Bookmarks are taken from the flat list, whatever layout the search asked for
+                let mut search = args["search"].clone();
+                search["output"] = json!(ResultLayout::Flat.as_str());
+                let response = self.search_streaming(&search, None, &self.cancel_token())?;
+                if response.get("error").is_some() {
+                    return Ok(response);
+                }
+                let results = response["result"]["results"].as_array().cloned().unwrap_or_default();
+                (results, args["search"].clone())
+            }
+            _ => return Ok(invalid_params("bookmark_results requires either 'results' or 'search'")),
+        };
+        
+        let mut bookmarks = Vec::with_capacity(results.len());
+        // Data streams are listed as results of their own; bookmark the file they belong to
+        for result in results.iter().filter(|result| result["stream_of"].is_null()) {
+            match Bookmark::from_result(result, note) {
+                Ok(mut bookmark) => {
+                    bookmark.source = source.clone();
+                    bookmarks.push(bookmark);
+                }
+                Err(e) => return Ok(invalid_params(&e.to_string())),
+            }
+        }
+        
+        let (collection, added) = self.collections.add(collection, args["description"].as_str(), bookmarks)?;
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": format!("Bookmarked {} new of {} results in '{}' ({} items)",
+                                    added, results.len(), collection.name, collection.items.len())
+                }],
+                "collection": collection.name,
+                "added": added,
+                "total": collection.items.len()
+            }
+        }))
+    }
+    
+    /// List all bookmark collections
+    fn list_collections(&self) -> Result<Value> {
+        let collections = self.collections.list();
+        
+        let text = if collections.is_empty() {
+            "No collections".to_string()
+        } else {
+            collections.iter()
+                .map(|c| format!("{}: {} items{}", c.name, c.items.len(),
+                                 c.description.as_ref().map(|d| format!(" ({})", d)).unwrap_or_default()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "collections": collections.iter().map(|c| json!({
+                    "name": c.name,
+                    "description": c.description,
+                    "items": c.items.len(),
+                    "created": c.created,
+                    "updated": c.updated
+                })).collect::<Vec<_>>()
+            }
+        }))
+    }
+    
+    /// Return a collection with its bookmarks
+    fn get_collection(&self, args: &Value) -> Result<Value> {
+        let name = args["name"].as_str().unwrap_or("");
+        let Some(collection) = self.collections.get(name) else {
+            return Ok(invalid_params(&format!("No collection named '{}'", name)));
+        };
+        
+        let mut text = format!("📌 {} ({} items)\n", collection.name, collection.items.len());
+        if let Some(description) = &collection.description {
+            text.push_str(&format!("{}\n", description));
+        }
+        text.push('\n');
+        for (i, item) in collection.items.iter().enumerate() {
+            let note = item.note.as_ref().map(|note| format!(" - {}", note)).unwrap_or_default();
+            text.push_str(&format!("{}. {}:\\{}{}\n", i + 1, item.drive, item.path, note));
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "collection": collection
+            }
+        }))
+    }
+    
+    /// Delete a bookmark collection
+    fn delete_collection(&self, args: &Value) -> Result<Value> {
+        let name = args["name"].as_str().unwrap_or("");
+        match self.collections.remove(name)? {
+            Some(collection) => Ok(json!({
+                "result": {
+                    "content": [{
+                        "type": "text",
+                        "text": format!("Deleted collection '{}' ({} items)", name, collection.items.len())
+                    }],
+                    "deleted": collection
+                }
+            })),
+            None => Ok(invalid_params(&format!("No collection named '{}'", name))),
+        }
+    }
+    
+    /// Return the latest result of a report job, running it first if requested or never run
+    fn run_report(&self, args: &Value) -> Result<Value> {
+        let name = args["name"].as_str().unwrap_or("");
+        if self.reports.job(name).is_none() {
+            return Ok(invalid_params(&format!("No report job named '{}'", name)));
+        }
+        
+        let report = match self.reports.latest(name) {
+            Some(report) if !args["refresh"].as_bool().unwrap_or(false) => report,
+            _ => self.execute_report(name)?,
+        };
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": format!("Report '{}' ({}, drive {}:) generated in {}ms:\n{}",
+                                    report.job, report.kind.as_str(), report.drive, report.duration_ms,
+                                    serde_json::to_string_pretty(&report.data)?)
+                }],
+                "report": report
+            }
+        }))
+    }
+    
+    /// Run every report job whose interval has elapsed; returns the number of reports generated
+    pub fn run_due_reports(&self) -> usize {
+        let due = self.reports.take_due_jobs(Instant::now());
+        let mut generated = 0;
+        for job in due {
+            match self.execute_report(&job.name) {
+                Ok(_) => generated += 1,
+                Err(e) => error!("Report '{}' failed: {}", job.name, e),
+            }
+        }
+        generated
+    }
+    
+    /// Run a report job now and record its result
+    fn execute_report(&self, name: &str) -> Result<Report> {
+        let job = self.reports.job(name)
+            .ok_or_else(|| anyhow::anyhow!("No report job named '{}'", name))?;
+        let cache = self.get_or_create_cache(job.drive.to_ascii_uppercase())?;
+        
+        let report = generate_report(&job, &cache);
+        info!("Report '{}' ({}) generated in {}ms", name, job.kind.as_str(), report.duration_ms);
+        self.reports.record(report.clone())?;
+        Ok(report)
+    }
+    
+    /// List report jobs as MCP resources
+    fn handle_resources_list(&self) -> Result<Value> {
+        let mut resources = vec![syntax_docs_resource()];
+        resources.extend(self.reports.resources());
+        Ok(json!({
+            "result": {
+                "resources": resources
+            }
+        }))
+    }
+    
+    /// Search syntax reference, generated from the current tool definitions
+    pub fn syntax_reference(&self) -> Result<String> {
+        let tools = self.handle_tools_list()?;
+        let tools = tools["result"]["tools"].as_array().map_or(&[][..], Vec::as_slice);
+        Ok(syntax_reference(tools))
+    }
+    
+    /// Read the syntax reference or the latest result of a report job
+    fn handle_resources_read(&self, request: Value) -> Result<Value> {
+        let uri = request["params"]["uri"].as_str().unwrap_or("");
+        if uri == SYNTAX_DOCS_URI {
+            return Ok(json!({
+                "result": {
+                    "contents": [{
+                        "uri": uri,
+                        "mimeType": "text/markdown",
+                        "text": self.syntax_reference()?
+                    }]
+                }
+            }));
+        }
+        let Some(name) = uri.strip_prefix(REPORT_URI_PREFIX) else {
+            return Ok(invalid_params(&format!("Unknown resource '{}'", uri)));
+        };
+        if self.reports.job(name).is_none() {
+            return Ok(invalid_params(&format!("Unknown resource '{}'", uri)));
+        }
+        
+        let text = match self.reports.latest(name) {
+            Some(report) => serde_json::to_string_pretty(&report)?,
+            None => json!({"job": name, "status": "pending"}).to_string(),
+        };
+        
+        Ok(json!({
+            "result": {
+                "contents": [{
+                    "uri": uri,
+                    "mimeType": "application/json",
+                    "text": text
+                }]
+            }
+        }))
+    }
+    
+    /// Export saved searches, report jobs and the drive config as a settings bundle
+    fn export_settings(&self, args: &Value) -> Result<Value> {
+        let bundle = SettingsBundle::new(self.saved_searches.list(), self.reports.jobs(), self.drives.selection());
+        
+        let mut text = format!("Exported {} saved searches, {} report jobs and the drive config",
+                               bundle.saved_searches.len(), bundle.report_jobs.len());
+        if let Some(name) = args["path"].as_str() {
+            let path = match bundle_path(&self.data_dir, name) {
+                Ok(path) => path,
+                Err(e) => return Ok(invalid_params(&e.to_string())),
+            };
+            bundle.write_to(&path)?;
+            text.push_str(&format!(" to {}", path.display()));
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "settings": bundle
+            }
+        }))
+    }
+    
+    /// Write a drive's cache to a SQLite database (see `index_export` for the schema)
+    ///
+    /// Args:
+    /// - path: Name of the database file to write in the exports folder
+    /// - drive: Drive letter (default C)
+    /// - format: sqlite (the only format so far)
+    /// - overwrite: Replace an existing file (default false)
+    ///
+    /// The cache stays read-locked while the rows are written, so changes
+    /// from the USN Journal queue up until the export is done.
+    fn export_index(&self, args: &Value, progress: Option<ProgressReporter>, cancel: &CancelToken) -> Result<Value> {
+        let Some(name) = args["path"].as_str().filter(|path| !path.trim().is_empty()) else {
+            return Ok(invalid_params("export_index requires 'path'"));
+        };
+        // The service runs elevated: callers name a file in the exports folder, never a path
+        let path = match export_path(&self.data_dir, name) {
+            Ok(path) => path,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let shown = path.display();
+        let format = args["format"].as_str().unwrap_or("sqlite");
+        if ExportFormat::parse(format).is_none() {
+            return Ok(invalid_params(&format!("Unknown export format '{}' (expected sqlite)", format)));
+        }
+        let drive = match args["drive"].as_str() {
+            Some(drive) => drive.chars().next().unwrap_or('C').to_ascii_uppercase(),
+            None => self.default_drive(),
+        };
+        let drive = self.volumes.resolve(drive).canonical;
+        let overwrite = args["overwrite"].as_bool().unwrap_or(false);
+        
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(drive)?;
+        // The cache must not be rebuilt while it is being written out
+        let _export = match cache.operations().begin(drive, DriveOperation::Export, "export_index") {
+            Ok(export) => export,
+            Err(busy) => return Ok(busy.to_response()),
+        };
+        let report = |done: u64, total: u64| {
+            if let Some(progress) = &progress {
+                progress.report(done, Some(total), &format!("{} of {} entries written", done, total), json!({}));
+            }
+        };
+        let exported = {
+            let files = cache.get_files();
+            let paths = cache.get_path_index();
+            export_sqlite(drive, &files, &paths, &path, overwrite, cancel, Some(&report))
+        };
+        let summary = match exported {
+            Ok(summary) => summary,
+            Err(e) => return Ok(invalid_params(&format!("{:#}", e))),
+        };
+        
+        let text = if summary.cancelled {
+            format!("⚠️ Export of drive {}: cancelled; nothing was written to {}", drive, shown)
+        } else {
+            format!(
+                "🗄️ Exported drive {}: to {} in {:.1}s: {} files, {} directories, {} extensions ({})\n\n\
+                 Tables: files(id, record, directory_id, extension_id, name, path, size, created, modified, accessed, \
+                 attributes, link_count, reparse_tag), directories(id, record, parent_id, name, path), \
+                 extensions(id, extension, file_count, total_size), meta(key, value). Times are UNIX seconds, \
+                 paths are relative to the drive root.",
+                drive, shown, start.elapsed().as_secs_f64(), summary.files, summary.directories,
+                summary.extensions, format_size(summary.bytes)
+            )
+        };
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "export": summary,
+                "schema_version": EXPORT_SCHEMA_VERSION
+            }
+        }))
+    }
+    
+    /// Import a settings bundle, merging by name unless `replace` is set
+    fn import_settings(&self, args: &Value) -> Result<Value> {
+        let bundle = match (&args["settings"], args["path"].as_str()) {
+            (settings, _) if settings.is_object() => SettingsBundle::from_value(settings.clone()),
+            (_, Some(name)) => bundle_path(&self.data_dir, name).and_then(SettingsBundle::read_from),
+            _ => return Ok(invalid_params("import_settings requires 'settings' or 'path'")),
+        };
+        let mut bundle = match bundle {
+            Ok(bundle) => bundle,
+            Err(e) => return Ok(invalid_params(&format!("{:#}", e))),
+        };
+        // Reports are written elevated, so imported jobs only write into the data directory
+        let confined: Result<Vec<ReportJob>> = bundle.report_jobs.into_iter()
+            .map(|job| job.confined_to(&self.data_dir))
+            .collect();
+        bundle.report_jobs = match confined {
+            Ok(jobs) => jobs,
+            Err(e) => return Ok(invalid_params(&format!("{:#}", e))),
+        };
+        let replace = args["replace"].as_bool().unwrap_or(false);
+        
+        // Report jobs: imported jobs win over existing ones with the same name
+        let mut jobs = if replace { Vec::new() } else { self.reports.jobs() };
+        jobs.retain(|job| !bundle.report_jobs.iter().any(|j| j.name == job.name));
+        jobs.extend(bundle.report_jobs.iter().cloned());
+        self.reports.set_jobs(jobs)?;
+        
+        let watched_drives: HashSet<char> = bundle.saved_searches.iter()
+            .filter(|s| s.watched)
+            .map(|s| s.drive())
+            .collect();
+        let searches = self.saved_searches.import(bundle.saved_searches, replace)?;
+        
+        // Drive config: imported drives win over the current config's
+        let drives = match &bundle.drives {
+            Some(drives) => {
+                let selection = if replace { drives.clone() } else { self.drives.selection().merged(drives) };
+                let selection = self.drives.set_selection(selection)?;
+                self.drop_excluded_caches(&selection);
+                Some(selection)
+            }
+            None => None,
+        };
+        
+        for drive in watched_drives {
+            if self.drives.is_excluded(drive) {
+                continue;
+            }
+            if let Err(e) = self.ensure_monitoring(drive) {
+                error!("Failed to start monitoring drive {} for imported watches: {}", drive, e);
+            }
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": format!("Imported {} saved searches, {} report jobs and {} ({})",
+                                    searches, bundle.report_jobs.len(),
+                                    if drives.is_some() { "the drive config" } else { "no drive config" },
+                                    if replace { "replaced existing settings" } else { "merged by name" })
+                }],
+                "saved_searches": searches,
+                "report_jobs": bundle.report_jobs.len(),
+                "drives": drives,
+                "replaced": replace
+            }
+        }))
+    }
+    
+    /// Show or change the drives preloaded at startup and the drives never indexed
+    ///
+    /// Args:
+    /// - preload: Drive letters built at service start (optional, replaces the list)
+    /// - exclude: Drive letters never indexed (optional, replaces the list)
+    /// - build_now: Build the preloaded drives' caches right away (default false)
+    fn configure_drives(&self, args: &Value) -> Result<Value> {
+        let mut selection = self.drives.selection();
+        let changed = !args["preload"].is_null() || !args["exclude"].is_null();
+        for (key, list) in [("preload", &mut selection.preload), ("exclude", &mut selection.excluded)] {
+            if args[key].is_null() {
+                continue;
+            }
+            match parse_drive_list(&args[key], key) {
+                Ok(drives) => *list = drives,
+                Err(e) => return Ok(invalid_params(&e.to_string())),
+            }
+        }
+        let selection = if changed {
+            match self.drives.set_selection(selection) {
+                Ok(selection) => selection,
+                Err(e) => return Ok(invalid_params(&e.to_string())),
+            }
+        } else {
+            selection
+        };
+        
+        let dropped = self.drop_excluded_caches(&selection);
+        
+        let built = if args["build_now"].as_bool().unwrap_or(false) {
+            self.preload_drives()
+        } else {
+            0
+        };
+        
+        let letters = |drives: &[char]| -> String {
+            if drives.is_empty() {
+                "none".to_string()
+            } else {
+                drives.iter().map(|d| format!("{}:", d)).collect::<Vec<_>>().join(", ")
+            }
+        };
+        let mut text = format!("Drives preloaded at startup: {}\nDrives never indexed: {}",
+                               letters(&selection.preload), letters(&selection.excluded));
+        if !dropped.is_empty() {
+            text.push_str(&format!("\nDropped the caches of {}", letters(&dropped)));
+        }
+        if built > 0 {
+            text.push_str(&format!("\nBuilt {} preloaded drive caches", built));
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "drives": selection,
+                "dropped": dropped,
+                "built": built
+            }
+        }))
+    }
+    
+    /// Drop what was already built for drives the selection excludes; returns those drives
+    fn drop_excluded_caches(&self, selection: &DriveSelection) -> Vec<char> {
+        let mut caches = self.mft_cache.write();
+        let dropped: Vec<char> = caches.keys().copied().filter(|d| selection.is_excluded(*d)).collect();
+        for drive in &dropped {
+            if let Some(cache) = caches.remove(drive) {
+                if cache.is_monitoring() {
+                    if let Err(e) = cache.stop_monitoring() {
+                        error!("Failed to stop monitoring excluded drive {}: {}", drive, e);
+                    }
+                }
+            }
+            self.project_indexes.write().remove(drive);
+            self.dir_indexers.write().remove(drive);
+            clear_mode(*drive);
+        }
+        dropped
+    }
+    
+    /// Guided first-run setup: where setup stands, and the next step when asked to take it
+    ///
+    /// Args:
+    /// - step: install, start, drives or index (optional; without it only reports)
+    /// - preload/exclude: Drive letters for the drives step, as for configure_drives
+    fn setup_wizard(&self, args: &Value, progress: Option<ProgressReporter>) -> Result<Value> {
+        let step = match args["step"].as_str() {
+            Some(s) => match SetupStep::parse(s) {
+                Some(step) => Some(step),
+                None => return Ok(invalid_params(&format!(
+                    "Unknown step '{}' (expected install, start, drives or index)", s
+                ))),
+            },
+            None => None,
+        };
+        // Directory mounts (container mode) run without the Windows service
+        let has_service = self.mounts.is_empty();
+        let available = if has_service {
+            self.volumes.search_drives()
+        } else {
+            self.mounts.iter().map(|m| m.drive).collect()
+        };
+        let letters = |drives: &[char]| -> String {
+            drives.iter().map(|d| format!("{}:", d)).collect::<Vec<_>>().join(", ")
+        };
+        
+        let outcome = match step {
+            Some(SetupStep::Install) if has_service => Some(match install_service() {
+                Ok(()) => "✅ Installed and started the FastSearch service".to_string(),
+                Err(e) => format!("❌ Couldn't install the service: {}", e),
+            }),
+            Some(SetupStep::Start) if has_service => Some(match start_service() {
+                Ok(()) => "✅ Started the FastSearch service".to_string(),
+                Err(e) => format!("❌ Couldn't start the service: {}", e),
+            }),
+            Some(SetupStep::Install | SetupStep::Start) => {
+                Some("There is no Windows service to set up when indexing mounted directories".to_string())
+            }
+            Some(SetupStep::Drives) => {
+                if args["preload"].is_null() && args["exclude"].is_null() {
+                    return Ok(invalid_params(&format!(
+                        "The drives step needs 'preload' and/or 'exclude' drive letters (available: {})", letters(&available)
+                    )));
+                }
+                let response = self.configure_drives(&json!({"preload": args["preload"], "exclude": args["exclude"]}))?;
+                if !response["error"].is_null() {
+                    return Ok(response);
+                }
+                Some("✅ Saved the drive selection".to_string())
+            }
+            Some(SetupStep::Index) => {
+                let preload = self.drives.selection().preload;
+                let mut lines = Vec::new();
+                for (i, drive) in preload.iter().enumerate() {
+                    if let Some(progress) = &progress {
+                        progress.report(i as u64, Some(preload.len() as u64), &format!("Indexing drive {}:", drive), json!({}));
+                    }
+                    let start = Instant::now();
+                    lines.push(match self.get_or_create_cache(*drive) {
+                        Ok(cache) => format!("✅ Drive {}: {} entries in {:.1}s", drive, cache.stats().file_count, start.elapsed().as_secs_f64()),
+                        Err(e) => format!("❌ Drive {}: {}", drive, e),
+                    });
+                }
+                if let Some(progress) = &progress {
+                    progress.report(preload.len() as u64, Some(preload.len() as u64), "Indexing finished", json!({}));
+                }
+                Some(if lines.is_empty() { "No drives are chosen for indexing yet".to_string() } else { lines.join("\n") })
+            }
+            Some(SetupStep::Done) | None => None,
+        };
+        
+        // Where setup stands now
+        let service = has_service.then(service_check);
+        let selection = self.drives.selection();
+        let cached: Vec<char> = {
+            let mut cached: Vec<char> = self.mft_cache.read().keys().copied().collect();
+            cached.sort_unstable();
+            cached
+        };
+        let next = next_step(service.as_ref(), &selection, &cached);
+        let mark = |done: bool| if done { "✅" } else { "⬜" };
+        
+        let mut text = String::from("🧭 FASTSEARCH SETUP\n\n");
+        if let Some(outcome) = &outcome {
+            text.push_str(&format!("{}\n\n", outcome));
+        }
+        if let Some(service) = &service {
+            text.push_str(&format!("{} Windows service installed\n", mark(service.installed)));
+            text.push_str(&format!("{} Service running{}\n", mark(service.running),
+                                   service.state.as_ref().map(|state| format!(" ({})", state)).unwrap_or_default()));
+        }
+        let chosen = !selection.preload.is_empty() || !selection.excluded.is_empty();
+        text.push_str(&format!("{} Drives chosen (indexed at startup: {}; never indexed: {})\n", mark(chosen),
+                               if selection.preload.is_empty() { "none".to_string() } else { letters(&selection.preload) },
+                               if selection.excluded.is_empty() { "none".to_string() } else { letters(&selection.excluded) }));
+        let built = selection.preload.iter().all(|drive| cached.contains(drive));
+        text.push_str(&format!("{} Caches built ({})\n", mark(chosen && built),
+                               if cached.is_empty() { "none yet".to_string() } else { letters(&cached) }));
+        
+        let next_args = match next {
+            SetupStep::Drives => json!({"step": "drives", "preload": available.iter().take(1).map(|d| d.to_string()).collect::<Vec<_>>()}),
+            SetupStep::Done => Value::Null,
+            step => json!({"step": step.as_str()}),
+        };
+        text.push_str(&format!("\nNext: {}", next.describe()));
+        if next == SetupStep::Drives {
+            text.push_str(&format!(" (available: {})", letters(&available)));
+        }
+        if !next_args.is_null() {
+            text.push_str(&format!("\n💡 Call setup_wizard with {}", next_args));
+        }
+        if next == SetupStep::Install && service.as_ref().map_or(false, |service| !service.elevated) {
+            text.push_str("\n   Windows will ask for administrator rights to install the service");
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "service": service,
+                "drives": selection,
+                "available_drives": available,
+                "cached_drives": cached,
+                "next_step": next.as_str(),
+                "next_arguments": next_args
+            }
+        }))
+    }
+    
+    /// Hold or resume the background updates of cached drives
+    ///
+    /// Args:
+    /// - action: start, stop or status (default status)
+    /// - drive: Drive letter, or "*" for every cached drive (default)
+    /// - minutes: For start, end maintenance by itself after this long
+    /// - reason: For start, shown in the status
+    fn maintenance_mode(&self, args: &Value) -> Result<Value> {
+        let action = args["action"].as_str().unwrap_or("status").to_lowercase();
+        if !["start", "stop", "status"].contains(&action.as_str()) {
+            return Ok(invalid_params("'action' must be one of: start, stop, status"));
+        }
+        let duration = match &args["minutes"] {
+            Value::Null => None,
+            minutes => match minutes.as_u64().filter(|&minutes| minutes > 0) {
+                Some(minutes) => Some(Duration::from_secs(minutes * 60)),
+                None => return Ok(invalid_params("'minutes' must be a positive whole number")),
+            },
+        };
+        let reason = args["reason"].as_str().filter(|reason| !reason.trim().is_empty());
+        
+        let mut caches: Vec<(char, Arc<MftCache>)> = self.mft_cache.read().iter()
+            .map(|(drive, cache)| (*drive, Arc::clone(cache)))
+            .collect();
+        caches.sort_unstable_by_key(|(drive, _)| *drive);
+        match args["drive"].as_str().map(str::trim) {
+            None | Some("*") => {}
+            Some(drive) => {
+                let letter = drive.chars().next().unwrap_or('C').to_ascii_uppercase();
+                let canonical = self.volumes.resolve(letter).canonical;
+                caches.retain(|(drive, _)| *drive == canonical);
+                if caches.is_empty() {
+                    return Ok(invalid_params(&format!(
+                        "Drive {}: has no cache; only cached drives can be put into maintenance", letter
+                    )));
+                }
+            }
+        }
+        
+        let time = |secs: u64| DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(secs)).format("%Y-%m-%d %H:%M");
+        let describe = |state: &MaintenanceState| {
+            let mut line = format!("in maintenance since {}", time(state.since));
+            if let Some(until) = state.until {
+                line.push_str(&format!(", until {}", time(until)));
+            }
+            if let Some(reason) = &state.reason {
+                line.push_str(&format!(" ({})", reason));
+            }
+            line
+        };
+        
+        let mut lines = Vec::new();
+        let mut drives = Vec::new();
+        for (drive, cache) in &caches {
+            let lock = cache.maintenance();
+            // Beginning or ending maintenance waits for no rebuild or export
+            let _transition = match action.as_str() {
+                "start" | "stop" => match cache.operations().begin(*drive, DriveOperation::Maintenance, "maintenance_mode") {
+                    Ok(transition) => Some(transition),
+                    Err(busy) if caches.len() == 1 => return Ok(busy.to_response()),
+                    Err(busy) => {
+                        lines.push(format!("{}: skipped, {} in progress", drive, busy.running.operation.as_str()));
+                        drives.push(json!({
+                            "drive": drive.to_string(),
+                            "maintenance": lock.state(),
+                            "operation_in_progress": busy.running
+                        }));
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            let line = match action.as_str() {
+                "start" => {
+                    let state = lock.begin(reason, duration);
+                    info!("Drive {}: entered maintenance{}", drive, reason.map(|r| format!(" ({})", r)).unwrap_or_default());
+                    format!("{}: {}", drive, describe(&state))
+                }
+                "stop" => match lock.end() {
+                    Some(_) => {
+                        info!("Drive {}: left maintenance", drive);
+                        format!("{}: resumed; changes made meanwhile are being caught up", drive)
+                    }
+                    None => format!("{}: was not in maintenance", drive),
+                },
+                _ => match lock.state() {
+                    Some(state) => format!("{}: {}", drive, describe(&state)),
+                    None => format!("{}: updating normally", drive),
+                },
+            };
+            lines.push(line);
+            drives.push(json!({
+                "drive": drive.to_string(),
+                "maintenance": lock.state()
+            }));
+        }
+        
+        let text = if lines.is_empty() {
+            "🛠️ MAINTENANCE: no drive has a cache yet".to_string()
+        } else {
+            format!("🛠️ MAINTENANCE\n\n{}", lines.join("\n"))
+        };
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "action": action,
+                "drives": drives
+            }
+        }))
+    }
+    
+    /// Folders learned to be unreadable, and whether walks skip them now
+    ///
+    /// Args:
+    /// - drive: Only this drive (default: all)
+    /// - clear: Forget them, so the next walk retries them
+    fn denied_paths(&self, args: &Value) -> Result<Value> {
+        let drive = args["drive"].as_str()
+            .and_then(|drive| drive.trim().chars().next())
+            .map(|letter| self.volumes.resolve(letter.to_ascii_uppercase()).canonical);
+        let denied: Vec<DeniedPath> = self.denied_paths.list(drive);
+        let cleared = args["clear"].as_bool().unwrap_or(false).then(|| {
+            let cleared = self.denied_paths.clear(drive);
+            self.denied_paths.flush();
+            cleared
+        });
+        
+        let now = Utc::now().timestamp();
+        let excluded = denied.iter().filter(|denied| denied.is_excluded(now)).count();
+        let mut text = format!("🚫 DENIED FOLDERS: {} ({} left out of walks)\n", denied.len(), excluded);
+        for folder in &denied {
+            let status = match folder.excluded_until.filter(|_| folder.is_excluded(now)) {
+                Some(until) => format!("skipped, retried in {}h", (until - now + 3599) / 3600),
+                None => "tried on the next walk".to_string(),
+            };
+            text.push_str(&format!("{}:\\{} - denied {} times, {}\n", folder.drive, folder.path, folder.denials, status));
+        }
+        if let Some(cleared) = cleared {
+            text.push_str(&format!("\n✅ Forgot {} folders; the next walk tries them again", cleared));
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "denied": denied,
+                "excluded": excluded,
+                "cleared": cleared
+            }
+        }))
+    }
+    
+    /// Memory held by each subsystem, added up from its own data structures
+    ///
+    /// Args:
+    /// - drive: Only this drive (default: every drive and the shared subsystems)
+    pub fn memory_profile(&self, args: &Value) -> Result<Value> {
+        let only = args["drive"].as_str()
+            .and_then(|drive| drive.trim().chars().next())
+            .map(|letter| self.volumes.resolve(letter.to_ascii_uppercase()).canonical);
+        let wanted = |drive: char| only.map_or(true, |only| drive == only);
+        
+        let mut caches: Vec<(char, Arc<MftCache>)> = self.mft_cache.read().iter()
+            .filter(|(drive, _)| wanted(**drive))
+            .map(|(drive, cache)| (*drive, Arc::clone(cache)))
+            .collect();
+        caches.sort_unstable_by_key(|(drive, _)| *drive);
+        
+        let mut profile = MemoryProfile::new();
+        for (drive, cache) in &caches {
+            // One table at a time, so no lock is held for the whole walk
+            let drive = Some(*drive);
+            profile.add_map("cache.entries", drive, &*cache.get_files());
+            profile.add_map("cache.name_index", drive, &*cache.get_name_index());
+            profile.add_map("cache.extension_index", drive, &*cache.get_extension_index());
+            profile.add_map("cache.folded_names", drive, &*cache.get_folded_names());
+            let affixes = cache.get_name_affixes();
+            profile.add("cache.name_affixes", drive, affixes.len() as u64, affixes.heap_bytes());
+            drop(affixes);
+            if let Some(trigrams) = cache.get_trigrams().as_ref() {
+                profile.add("cache.trigrams", drive, trigrams.len() as u64, trigrams.heap_bytes());
+            }
+            profile.add_map("cache.path_index", drive, &*cache.get_path_index());
+        }
+        let mut projects: Vec<(char, Arc<ProjectIndex>)> = self.project_indexes.read().iter()
+            .filter(|(drive, _)| wanted(**drive))
+            .map(|(drive, index)| (*drive, Arc::clone(index)))
+            .collect();
+        projects.sort_unstable_by_key(|(drive, _)| *drive);
+        for (drive, index) in &projects {
+            profile.add("project_index", Some(*drive), index.len() as u64, index.heap_bytes());
+        }
+        for (drive, queued, bytes) in usn_buffer_memory().into_iter().filter(|(drive, _, _)| wanted(*drive)) {
+            profile.add("usn_buffers", Some(drive), queued, bytes);
+        }
+        if only.is_none() {
+            let (records, bytes) = self.result_sets.memory();
+            profile.add("result_sets", None, records, bytes);
+        }
+        
+        let total = profile.total();
+        let config = caches.first().map_or_else(MftCacheConfig::default, |(_, cache)| cache.config().clone());
+        let budget = config.memory_budget();
+        let limits = limit_status();
+        
+        let mut text = format!("🧮 MEMORY PROFILE: {} accounted\n", format_size(total));
+        if let Some((machine, budget)) = budget {
+            text.push_str(&format!(
+                "Budget: {} (max_memory_usage {:.2} of {}), {:.0}% used\n",
+                format_size(budget), config.max_memory_usage, format_size(machine),
+                total as f64 * 100.0 / budget.max(1) as f64
+            ));
+        }
+        if let Some(limit) = limits["memory_limit_bytes"].as_u64() {
+            text.push_str(&format!("Memory limit: {}", format_size(limit)));
+            if let Some(peak) = limits["peak_memory_bytes"].as_u64() {
+                text.push_str(&format!(", peak {}", format_size(peak)));
+            }
+            text.push('\n');
+        }
+        text.push_str("\nBy subsystem:\n");
+        for (subsystem, bytes) in profile.by_subsystem() {
+            text.push_str(&format!("  {}: {}\n", subsystem, format_size(bytes)));
+        }
+        let drives = profile.by_drive();
+        if !drives.is_empty() {
+            text.push_str("\nBy drive:\n");
+            for (drive, bytes) in &drives {
+                text.push_str(&format!("  {}: {}\n", drive, format_size(*bytes)));
+            }
+        }
+        text.push_str("\nSizes are counted from string and table capacities, without allocator overhead; names are stored with their entries (there is no string interner or content index)");
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "total_bytes": total,
+                "budget_bytes": budget.map(|(_, budget)| budget),
+                "machine_memory_bytes": budget.map(|(machine, _)| machine),
+                "max_memory_usage": config.max_memory_usage,
+                "resource_limits": limits,
+                "subsystems": profile.subsystems
+            }
+        }))
+    }
+    
+    /// Register a live query and run it once for its current results
+    ///
+    /// The query is registered before the initial search runs, so no change
+    /// is missed; an entry changed in between may be both in the initial
+    /// results and in an update.
+    ///
+    /// Args: the `fast_search` arguments (`drive` defaults to the default drive)
+    pub fn register_live_query(&self, args: &Value) -> Result<Value> {
+        let letter = args["drive"].as_str()
+            .and_then(|drive| drive.trim().chars().next())
+            .map(|letter| letter.to_ascii_uppercase())
+            .unwrap_or_else(|| self.default_drive());
+        let drive = self.volumes.resolve(letter).canonical;
+        if let Err(e) = self.ensure_monitoring(drive) {
+            return Ok(invalid_params(&format!("Drive {}: can't be watched for changes: {}", drive, e)));
+        }
+        let query = match self.live_queries.register(drive, args) {
+            Ok(query) => query,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        
+        let initial = self.fast_search(args)?;
+        if initial.get("error").is_some() {
+            self.live_queries.remove(query.id());
+            return Ok(initial);
+        }
+        info!("Registered live query {} on drive {}:", query.id(), drive);
+        
+        let count = initial["result"]["results"].as_array().map_or(0, Vec::len);
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": format!(
+                        "Live query {} registered on drive {}: with {} current results; subscribe to its updates to follow changes",
+                        query.id(), drive, count
+                    )
+                }],
+                "live_query": query.to_json(),
+                "initial": initial["result"]
+            }
+        }))
+    }
+    
+    /// A registered live query, to subscribe to its updates
+    pub fn live_query(&self, id: &str) -> Option<Arc<LiveQuery>> {
+        self.live_queries.get(id)
+    }
+    
+    /// Unregister a live query, ending its subscribers' streams
+    pub fn remove_live_query(&self, id: &str) -> bool {
+        let removed = self.live_queries.remove(id);
+        if removed {
+            info!("Removed live query {}", id);
+        }
+        removed
+    }
+    
+    /// Registered live queries, oldest first
+    pub fn list_live_queries(&self) -> Value {
+        json!(self.live_queries.list().iter().map(|query| query.to_json()).collect::<Vec<_>>())
+    }
+    
+    /// Make sure USN Journal monitoring is running for a drive
+    fn ensure_monitoring(&self, drive: char) -> Result<()> {
+        let cache = self.get_or_create_cache(drive)?;
+        if self.dir_indexers.read().contains_key(&drive) {
+            return Ok(()); // Directory indexes watch for changes themselves
+        }
+        if !cache.is_monitoring() {
+            cache.start_monitoring()?;
+        }
+        Ok(())
+    }
+    
+    /// Resolve a `path` tool argument to the cache it lives in
+    ///
+    /// The path is relative to the drive root and may name its drive
+    /// ("D:\\tools"); otherwise `drive` (or the default drive) is used.
+    fn resolve_path_arg(&self, raw_path: &str, drive: Option<&str>) -> CachePath {
+        let raw_path = raw_path.trim().replace('/', "\\");
+        let (drive, path) = match raw_path.split_once(':') {
+            Some((letter, rest)) if letter.len() == 1 => (letter.to_ascii_uppercase(), rest),
+            _ => (drive.unwrap_or_default().to_uppercase(), raw_path.as_str()),
+        };
+        let drive = drive.chars().next().unwrap_or_else(|| self.default_drive());
+        let path = path.trim_matches('\\').to_string();
+        let alias = self.volumes.resolve(drive);
+        let cache_path = match (alias.prefix.is_empty(), path.is_empty()) {
+            (true, _) => path.clone(),
+            (false, true) => alias.prefix.clone(),
+            (false, false) => format!("{}\\{}", alias.prefix, path),
+        };
+        CachePath { drive, path, alias, cache_path }
+    }
+    
+    /// List the alternate data streams of a file or folder subtree
+    ///
+    /// Args:
+    /// - path: File or folder relative to the drive root, optionally with its drive ("D:\\tools")
+    /// - drive: Drive letter when path doesn't include one
+    /// - recursive: Also check everything below a folder (default true)
+    /// - max_results: Maximum number of streams to return (default 1000)
+    fn list_ads(&self, args: &Value) -> Result<Value> {
+        let Some(raw_path) = args["path"].as_str() else {
+            return Ok(invalid_params("list_ads requires a 'path'"));
+        };
+        let recursive = args["recursive"].as_bool().unwrap_or(true);
+        let max_results = args["max_results"].as_u64().unwrap_or(1000) as usize;
+
+        let CachePath { drive, path, alias, cache_path } = self.resolve_path_arg(raw_path, args["drive"].as_str());
+        let path = path.as_str();
+        if self.is_mounted(drive) {
+            return Ok(invalid_params(&format!(
+                "Drive {}: is an indexed directory, which has no NTFS data streams", drive
+            )));
+        }
+
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+
+        // The entry itself and, for folders, everything below it, in path order
+        let mut targets: Vec<(u64, String)> = {
+            let files = cache.get_files();
+            let paths = cache.get_path_index();
+            let root = if cache_path.is_empty() {
+                None
+            } else {
+                match lookup_path(&paths, &cache_path).and_then(|id| files.get(&id)) {
+                    Some(file) => Some(file),
+                    None => return Ok(invalid_params(&format!("'{}' was not found on drive {}:", path, drive))),
+                }
+            };
+
+            match root {
+                Some(file) if !(file.is_directory && recursive) => vec![(file.id, file.path.clone())],
+                _ => {
+                    let prefix = format!("{}\\", cache_path).to_lowercase();
+                    files.values()
+                        .filter(|f| cache_path.is_empty() || f.path.eq_ignore_ascii_case(&cache_path) || f.path.to_lowercase().starts_with(&prefix))
+                        .map(|f| (f.id, f.path.clone()))
+                        .collect()
+                }
+            }
+        };
+        targets.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let ids: Vec<u64> = targets.iter().map(|(id, _)| *id).collect();
+        let mut found = read_streams(alias.canonical, &ids)?;
+
+        let mut streams = Vec::new();
+        let mut truncated = false;
+        for (id, file_path) in &targets {
+            let Some(file_streams) = found.remove(id) else {
+                continue;
+            };
+            let shown = alias.display_path(file_path).unwrap_or(file_path);
+            for stream in file_streams {
+                if streams.len() == max_results {
+                    truncated = true;
+                    break;
+                }
+                streams.push(json!({
+                    "path": stream_path(shown, &stream),
+                    "file": shown,
+                    "stream": stream.name,
+                    "size": stream.size,
+                }));
+            }
+        }
+        let elapsed = start.elapsed();
+
+        let location = if path.is_empty() { format!("{}:\\", drive) } else { format!("{}:\\{}", drive, path) };
+        let text = if streams.is_empty() {
+            format!("No alternate data streams found in {} ({} entries checked in {:.2}ms)",
+                    location, targets.len(), elapsed.as_millis())
+        } else {
+            let mut text = format!("🔎 ALTERNATE DATA STREAMS: {} streams in {} ({} entries checked in {:.2}ms)\n\n",
+                                   streams.len(), location, targets.len(), elapsed.as_millis());
+            for (i, stream) in streams.iter().enumerate() {
+                text.push_str(&format!("{}. {} ({} bytes)\n", i + 1, stream["path"].as_str().unwrap_or_default(), stream["size"]));
+            }
+            if truncated {
+                text.push_str(&format!("\n⚡ Stopped at {} streams; raise max_results or narrow the path to see more", max_results));
+            }
+            text
+        };
+
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "streams": streams,
+                "entries_checked": targets.len(),
+                "truncated": truncated
+            }
+        }))
+    }
+
+    /// The cached record of a file or folder next to a live look at it on disk
+    ///
+    /// Args:
+    /// - path: File or folder relative to the drive root, optionally with its drive ("D:\\tools\\app.exe")
+    /// - drive: Drive letter when path doesn't include one
+    fn get_file_info(&self, args: &Value) -> Result<Value> {
+        let Some(raw_path) = args["path"].as_str().filter(|p| !p.trim().is_empty()) else {
+            return Ok(invalid_params("get_file_info requires a 'path'"));
+        };
+        let CachePath { drive, path, alias, cache_path } = self.resolve_path_arg(raw_path, args["drive"].as_str());
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        let cached: Option<FileEntry> = {
+            let files = cache.get_files();
+            let paths = cache.get_path_index();
+            lookup_path(&paths, &cache_path).and_then(|id| files.get(&id)).cloned()
+        };
+        // The cached path has the name's real case, which matters off NTFS
+        let on_disk = disk_path(&self.drive_root(alias.canonical), cached.as_ref().map_or(&cache_path, |file| &file.path));
+        let live = stat(&on_disk);
+        if let (None, Err(e)) = (&cached, &live) {
+            return Ok(invalid_params(&format!("'{}' was not found on drive {}: ({})", path, drive, e)));
+        }
+        let shown = match &cached {
+            Some(file) => match alias.display_path(&file.path) {
+                Some(rest) => format!("{}:\\{}", drive, rest),
+                None => format!("{}:\\{}", alias.canonical, file.path),
+            },
+            None => format!("{}:\\{}", drive, path),
+        };
+        
+        let local = |time: SystemTime| DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string();
+        let listed = |attributes: u32| {
+            let names = attribute_names(attributes);
+            if names.is_empty() { "none".to_string() } else { names.join(", ") }
+        };
+        let mut text = format!("ℹ️ FILE INFO: {}{} ({:.2}ms)\n",
+                               shown, if cached.as_ref().map_or(false, |file| file.is_directory) { "\\" } else { "" },
+                               start.elapsed().as_millis());
+        let mut result = json!({
+            "path": shown,
+            "drive": drive.to_string(),
+            "in_cache": cached.is_some()
+        });
+        
+        match &cached {
+            Some(file) => {
+                let reparse = ReparseKind::of(file.reparse_tag);
+                text.push_str(&format!(
+                    "\n📇 Cache (record {}):\n  Size: {}\n  Created: {}\n  Modified: {}\n  Accessed: {}\n  Attributes: {}\n  Links: {}\n",
+                    file.id, format_size(file.size), local(file.created), local(file.modified), local(file.accessed),
+                    listed(file.attributes), file.link_count
+                ));
+                if let Some(kind) = reparse {
+                    text.push_str(&format!("  Reparse point: {}\n", kind.as_str()));
+                }
+                result["cached"] = json!({
+                    "id": file.id,
+                    "name": file.name,
+                    "path": file.path,
+                    "extension": file.extension,
+                    "size": file.size,
+                    "is_directory": file.is_directory,
+                    "created": unix_timestamp(file.created),
+                    "modified": unix_timestamp(file.modified),
+                    "accessed": unix_timestamp(file.accessed),
+                    "attributes": attribute_names(file.attributes),
+                    "attribute_flags": file.attributes,
+                    "link_count": file.link_count,
+                    "reparse": reparse.map(|kind| kind.as_str()),
+                    "reparse_tag": file.reparse_tag
+                });
+            }
+            None => text.push_str("\n📇 Cache: not indexed yet (created since the last update, or in an excluded folder)\n"),
+        }
+        
+        match &live {
+            Ok(live) => {
+                let unknown = |time: Option<SystemTime>| time.map_or_else(|| "unknown".to_string(), local);
+                let on_disk = live.size_on_disk.map_or_else(String::new, |bytes| format!(" ({} on disk)", format_size(bytes)));
+                text.push_str(&format!(
+                    "\n💽 Disk{}:\n  Size: {}{}\n  Created: {}\n  Modified: {}\n  Accessed: {}\n  Attributes: {}\n  Links: {}\n",
+                    live.file_id.map_or_else(String::new, |id| format!(" (file ID {})", id)),
+                    format_size(live.size), on_disk, unknown(live.created), unknown(live.modified), unknown(live.accessed),
+                    listed(live.attributes), live.link_count.map_or_else(|| "unknown".to_string(), |links| links.to_string())
+                ));
+                result["live"] = live_json(live);
+            }
+            Err(e) => {
+                text.push_str(&format!("\n💽 Disk: can't be read ({})\n", e));
+                result["live"] = Value::Null;
+                result["live_error"] = json!(e.to_string());
+            }
+        }
+        
+        if let (Some(file), Ok(live)) = (&cached, &live) {
+            let differences = differences(file, live);
+            if differences.is_empty() {
+                text.push_str("\n✅ The cache matches the disk");
+            } else {
+                text.push_str(&format!("\n⚠️ The cache differs from the disk on: {} (it catches up with the next update)", differences.join(", ")));
+            }
+            result["differences"] = json!(differences);
+        }
+        
+        result["content"] = json!([{
+            "type": "text",
+            "text": text
+        }]);
+        Ok(json!({ "result": result }))
+    }
+    
+    /// List the hard links of a file, or every group of hard-linked files on a drive
+    ///
+    /// Args:
+    /// - path: File whose links to list, optionally with its drive (optional)
+    /// - drive: Drive letter when path doesn't include one
+    /// - max_results: Maximum number of groups when listing a drive (default 100)
+    fn find_hardlinks(&self, args: &Value) -> Result<Value> {
+        let max_results = args["max_results"].as_u64().unwrap_or(100) as usize;
+        let CachePath { drive, path, alias, cache_path } =
+            self.resolve_path_arg(args["path"].as_str().unwrap_or_default(), args["drive"].as_str());
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        
+        // Paths as seen through the drive letter asked about, where they're visible through it
+        let shown = |p: &str| match alias.display_path(p) {
+            Some(rest) => format!("{}:\\{}", drive, rest),
+            None => format!("{}:\\{}", alias.canonical, p),
+        };
+        
+        if !path.is_empty() {
+            let (id, link_count) = {
+                let files = cache.get_files();
+                let paths = cache.get_path_index();
+                match lookup_path(&paths, &cache_path).and_then(|id| files.get(&id)) {
+                    Some(file) if file.is_directory => {
+                        return Ok(invalid_params(&format!("'{}' is a folder; folders can't have hard links", path)));
+                    }
+                    Some(file) => (file.id, file.link_count),
+                    None => return Ok(invalid_params(&format!("'{}' was not found on drive {}:", path, drive))),
+                }
+            };
+            let links: Vec<String> = if link_count <= 1 {
+                vec![cache_path.clone()]
+            } else if self.is_mounted(alias.canonical) {
+                // Directory indexes give every path its own ID; links share an inode instead
+                self.hardlink_groups(alias.canonical, &cache)
+                    .into_iter()
+                    .find(|group| group.iter().any(|p| p.eq_ignore_ascii_case(&cache_path)))
+                    .unwrap_or_else(|| vec![cache_path.clone()])
+            } else {
+                cache.link_paths(id)
+            };
+            let links: Vec<String> = links.iter().map(|p| shown(p)).collect();
+            
+            let text = if links.len() <= 1 {
+                format!("'{}' has no other hard links (link count {})", path, link_count)
+            } else {
+                let mut text = format!("🔗 HARD LINKS: {}:\\{} is one file under {} names (link count {}):\n\n",
+                                       drive, path, links.len(), link_count);
+                for (i, link) in links.iter().enumerate() {
+                    text.push_str(&format!("{}. {}\n", i + 1, link));
+                }
+                if links.len() < link_count as usize {
+                    text.push_str("\n💡 Some links are in folders outside the cache or on another drive letter's view of the volume");
+                }
+                text
+            };
+            return Ok(json!({
+                "result": {
+                    "content": [{
+                        "type": "text",
+                        "text": text
+                    }],
+                    "links": links,
+                    "link_count": link_count
+                }
+            }));
+        }
+        
+        // Every group of hard-linked files on the drive, largest groups first
+        let mut groups = self.hardlink_groups(alias.canonical, &cache);
+        groups.retain(|group| group.iter().any(|p| alias.display_path(p).is_some()));
+        groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        let total = groups.len();
+        groups.truncate(max_results);
+        let groups: Vec<Vec<String>> = groups.iter()
+            .map(|group| group.iter().map(|p| shown(p)).collect())
+            .collect();
+        
+        let text = if groups.is_empty() {
+            format!("No hard-linked files found on drive {}: (checked in {:.2}ms)", drive, start.elapsed().as_millis())
+        } else {
+            let mut text = format!("🔗 HARD LINKS: {} files on drive {}: have several names ({:.2}ms)\n\n",
+                                   total, drive, start.elapsed().as_millis());
+            for (i, group) in groups.iter().enumerate() {
+                text.push_str(&format!("{}. {}\n", i + 1, group.join("  =  ")));
+            }
+            if total > groups.len() {
+                text.push_str(&format!("\n⚡ Showing {} of {}; raise max_results to see more", groups.len(), total));
+            }
+            text
+        };
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "groups": groups,
+                "total_groups": total
+            }
+        }))
+    }
+    
+    /// Current paths of files given by their MFT file reference
+    ///
+    /// Args:
+    /// - file_id: A reference (number, `0x` hex or `record-sequence`), or an array of them
+    /// - drive: Drive letter of the volume the references are from
+    fn resolve_file_id(&self, args: &Value) -> Result<Value> {
+        let given: Vec<&Value> = match &args["file_id"] {
+            Value::Null => return Ok(invalid_params("'file_id' is required")),
+            Value::Array(ids) if ids.len() > MAX_FILE_IDS => {
+                return Ok(invalid_params(&format!("At most {} file IDs can be resolved at once", MAX_FILE_IDS)));
+            }
+            Value::Array(ids) => ids.iter().collect(),
+            id => vec![id],
+        };
+        let mut references = Vec::with_capacity(given.len());
+        for id in &given {
+            let reference = match id {
+                Value::Number(n) => n.as_u64().map(FileReference::from_u64).ok_or_else(|| anyhow::anyhow!("'{}' is not a file reference", n)),
+                Value::String(text) => FileReference::parse(text),
+                other => Err(anyhow::anyhow!("'{}' is not a file reference", other)),
+            };
+            match reference {
+                Ok(reference) => references.push(reference),
+                Err(e) => return Ok(invalid_params(&e.to_string())),
+            }
+        }
+        let drive = match args["drive"].as_str() {
+            Some(drive) => drive.chars().next().unwrap_or('C').to_ascii_uppercase(),
+            None => self.default_drive(),
+        };
+        
+        let start = Instant::now();
+        let alias = self.volumes.resolve(drive);
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        // Paths as seen through the drive letter asked about, where they're visible through it
+        let shown = |p: &str| match alias.display_path(p) {
+            Some(rest) => format!("{}:\\{}", drive, rest),
+            None => format!("{}:\\{}", alias.canonical, p),
+        };
+        
+        let mut lines = Vec::with_capacity(given.len());
+        let mut results = Vec::with_capacity(given.len());
+        let files = cache.get_files();
+        for (id, reference) in given.iter().zip(&references) {
+            let label = id.as_str().map_or_else(|| id.to_string(), str::to_string);
+            let mut result = json!({
+                "file_id": id,
+                "record": reference.record,
+                "sequence": reference.sequence,
+                "found": false
+            });
+            match files.get(&reference.record) {
+                Some(file) => {
+                    let path = shown(&file.path);
+                    lines.push(format!("{} → {}{}", label, path, if file.is_directory { "\\" } else { "" }));
+                    result["found"] = json!(true);
+                    result["path"] = json!(path);
+                    result["name"] = json!(file.name);
+                    result["is_directory"] = json!(file.is_directory);
+                    result["size"] = json!(file.size);
+                    // Every name of a hard-linked file
+                    if file.link_count > 1 {
+                        let links: Vec<String> = cache.link_paths(file.id).iter().map(|p| shown(p)).collect();
+                        result["links"] = json!(links);
+                    }
+                }
+                None => lines.push(format!("{} → not in the cache (deleted, or not indexed yet)", label)),
+            }
+            results.push(result);
+        }
+        drop(files);
+        
+        let found = results.iter().filter(|result| result["found"] == true).count();
+        let mut text = format!("🆔 FILE IDS: {} of {} resolved on drive {}: ({:.2}ms)\n\n",
+                               found, results.len(), drive, start.elapsed().as_millis());
+        text.push_str(&lines.join("\n"));
+        if self.is_mounted(alias.canonical) {
+            text.push_str(&format!("\n\n💡 Drive {}: is a directory index, whose IDs are its own rather than MFT record numbers", drive));
+        } else if references.iter().any(|reference| reference.sequence != 0) {
+            text.push_str("\n\n💡 The cache keeps record numbers only: a record reused since the reference was taken resolves to the file using it now");
+        }
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "results": results,
+                "found": found
+            }
+        }))
+    }
+    
+    /// Paths of every file in a cache that has more than one name, grouped by file
+    ///
+    /// NTFS hard links share an MFT record and so a cache ID. Directory indexes
+    /// give each path its own ID, so there the files are told apart by inode.
+    fn hardlink_groups(&self, drive: char, cache: &MftCache) -> Vec<Vec<String>> {
+        let files = cache.get_files();
+        let linked = files.values().filter(|f| f.link_count > 1 && !f.is_directory);
+        let mut groups: HashMap<(u64, u64), Vec<String>> = HashMap::new();
+        if self.is_mounted(drive) {
+            let root = self.drive_root(drive);
+            for file in linked {
+                if let Some(identity) = file_identity(&disk_path(&root, &file.path)) {
+                    groups.entry(identity).or_default().push(file.path.clone());
+                }
+            }
+        } else {
+            let ids: HashSet<u64> = linked.map(|f| f.id).collect();
+            for (path, id) in cache.get_path_index().iter() {
+                if ids.contains(id) {
+                    groups.entry((0, *id)).or_default().push(path.clone());
+                }
+            }
+        }
+        groups.into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort();
+                group
+            })
+            .collect()
+    }
+    
+    /// Find groups of files with identical contents
+    ///
+    /// Args:
+    /// - drive: Drive letter (default C)
+    /// - path/extensions/exclude/min_size/max_size: Which files to check, as for fast_search (min_size defaults to 1MB)
+    /// - threads: Files read at once from a solid-state disk (default 4); spinning disks are read one file at a time
+    /// - confirm_token: Token of a confirmation_required answer, for scans that read the MFT first or hash a lot
+    /// - max_results: Maximum number of groups (default 50)
+    ///
+    /// Reports hashing progress when asked to, and stops early when the request is cancelled.
+    fn find_duplicates(&self, args: &Value, progress: Option<ProgressReporter>, cancel: &CancelToken) -> Result<Value> {
+        let drive = match args["drive"].as_str() {
+            Some(drive) => drive.chars().next().unwrap_or('C').to_ascii_uppercase(),
+            None => self.default_drive(),
+        };
+        let max_results = args["max_results"].as_u64().unwrap_or(50) as usize;
+        let threads = args["threads"].as_u64().map_or(DEFAULT_HASH_THREADS, |threads| threads as usize);
+        let mut filters = match SearchFilters::from_args(args, &self.doc_type_extensions) {
+            Ok(filters) => filters,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        filters.min_size = filters.min_size.or(Some(DUPLICATE_MIN_SIZE));
+        
+        // Reading the MFT first or hashing a lot of data waits for the client to confirm
+        let confirmed = match self.redeem_confirmation("find_duplicates", args) {
+            Ok(confirmed) => confirmed,
+            Err(response) => return Ok(response),
+        };
+        let alias = self.volumes.resolve(drive);
+        if !confirmed && self.is_cold(alias.canonical) {
+            return self.confirmation_required("find_duplicates", args, CostEstimate {
+                reasons: vec![format!("There is no cache of {}: yet, so its whole MFT is read before any file is hashed", alias.canonical)],
+                cold_drives: vec![format!("{}:", alias.canonical)],
+                ..Default::default()
+            });
+        }
         
-        // Get read locks on the cache
-        let files = mft_cache.get_files();
-        let path_index = mft_cache.get_path_index();
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        let root = self.drive_root(alias.canonical);
+        let mounted = self.is_mounted(alias.canonical);
+        let disk = Arc::new(disk_of(&root));
         
-        // Convert pattern to regex
-        let pattern_regex = self.pattern_to_regex(pattern)?;
+        // Only files sharing their size with another file can be copies
+        let groups: Vec<Vec<DuplicateCandidate>> = {
+            let files = cache.get_files();
+            let checked = files.values()
+                .filter(|file| filters.matches(file) && alias.display_path(&file.path).is_some());
+            size_groups(checked).into_iter()
+                .map(|group| {
+                    // Hard links are one file, not copies; directory indexes list each of them
+                    let mut linked = HashSet::new();
+                    group.into_iter()
+                        .filter(|file| !mounted || file.link_count <= 1
+                            || file_identity(&disk_path(&root, &file.path)).map_or(true, |identity| linked.insert(identity)))
+                        .filter_map(|file| Some(DuplicateCandidate {
+                            shown: format!("{}:\\{}", drive, alias.display_path(&file.path)?),
+                            disk_path: disk_path(&root, &file.path),
+                            size: file.size,
+                            disk: Arc::clone(&disk),
+                        }))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|group| group.len() > 1)
+                .collect()
+        };
+        let bytes_to_read: u64 = groups.iter().flatten().map(|candidate| candidate.size).sum();
+        if !confirmed && bytes_to_read > CONFIRM_HASH_BYTES {
+            let files_to_read = groups.iter().map(Vec::len).sum::<usize>() as u64;
+            return self.confirmation_required("find_duplicates", args, CostEstimate {
+                reasons: vec![format!("Up to {} in {} same-size files would be read and hashed", format_size(bytes_to_read), files_to_read)],
+                files_to_read: Some(files_to_read),
+                bytes_to_read: Some(bytes_to_read),
+                ..Default::default()
+            });
+        }
         
-        // Filter files based on criteria
-        let mut results = Vec::new();
-        let mut result_count = 0;
+        let sink = |update: HashProgress| {
+            if let Some(progress) = &progress {
+                let step = if update.round == 1 { "Comparing beginnings" } else { "Comparing whole files" };
+                progress.report(update.files_done, Some(update.files_total), &format!(
+                    "{} of same-size files: {} of {} ({} MB read)",
+                    step, update.files_done, update.files_total, update.bytes_hashed / 1024 / 1024
+                ), json!({}));
+            }
+        };
+        let scan = find_duplicates(groups, threads, cancel, Some(&sink))?;
+        let total = scan.groups.len();
+        let total_wasted: u64 = scan.groups.iter().map(|group| group.wasted_bytes()).sum();
+        let groups = &scan.groups[..total.min(max_results)];
+        let elapsed = start.elapsed().as_millis();
         
-        for (_, file) in files.iter() {
-            // Apply path filter
-            if !path_filter.is_empty() && !file.path.to_lowercase().contains(&path_filter) {
-                continue;
+        let mut text = if groups.is_empty() {
+            format!("No duplicate files found on drive {}: ({} same-size files hashed in {}ms)", drive, scan.candidates, elapsed)
+        } else {
+            let mut text = format!("🧬 DUPLICATES: {} groups of identical files on drive {}: waste {} bytes ({} same-size files, {} MB read in {}ms)\n\n",
+                                   total, drive, total_wasted, scan.candidates, scan.bytes_hashed / 1024 / 1024, elapsed);
+            for (i, group) in groups.iter().enumerate() {
+                text.push_str(&format!("{}. {} copies of {} bytes (wastes {}): {}\n",
+                                       i + 1, group.paths.len(), group.size, group.wasted_bytes(), group.paths.join("  =  ")));
             }
-            
-            // Apply pattern filter
-            if !pattern_regex.is_match(&file.name) {
-                continue;
+            if total > groups.len() {
+                text.push_str(&format!("\n⚡ Showing {} of {}; raise max_results to see more", groups.len(), total));
             }
-            
-            // Apply extension filter if specified
-            if let Some(exts) = &extensions {
-                if let Some(ext) = &file.extension {
-                    if !exts.contains(ext) {
-                        continue;
-                    }
-                } else if !exts.is_empty() {
-                    continue; // No extension but extensions were specified
+            text
+        };
+        if scan.unreadable > 0 {
+            text.push_str(&format!("\n💡 {} files couldn't be read (in use or access denied) and were skipped", scan.unreadable));
+        }
+        if disk.seek_penalty {
+            text.push_str(&format!("\n💡 {} is a spinning disk, so files were read one at a time", disk.id));
+        }
+        if scan.cancelled {
+            text.push_str("\n⚠️ Cancelled: only the duplicates confirmed before cancelling are listed");
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "groups": groups.iter().map(|group| json!({
+                    "size": group.size,
+                    "hash": group.hash,
+                    "count": group.paths.len(),
+                    "wasted_bytes": group.wasted_bytes(),
+                    "paths": group.paths
+                })).collect::<Vec<_>>(),
+                "total_groups": total,
+                "total_wasted_bytes": total_wasted,
+                "candidates": scan.candidates,
+                "bytes_hashed": scan.bytes_hashed,
+                "unreadable": scan.unreadable,
+                "cancelled": scan.cancelled,
+                "disk": {
+                    "id": disk.id,
+                    "seek_penalty": disk.seek_penalty
                 }
             }
-            
-            // Apply document type filter
-            if let Some(doc_type) = doc_type {
-                if let Some(ext) = &file.extension {
-                    if !self.doc_type_extensions.get(&doc_type)
-                        .map_or(false, |exts| exts.contains(ext)) {
+        }))
+    }
+    
+    /// Find files named like `name`, within an edit distance
+    fn find_similar(&self, args: &Value) -> Result<Value> {
+        let Some(name) = args["name"].as_str().map(str::trim).filter(|name| !name.is_empty()) else {
+            return Ok(invalid_params("'name' is required"));
+        };
+        let max_distance = match args["max_distance"].as_u64() {
+            None => DEFAULT_MAX_DISTANCE,
+            Some(distance) if distance as usize <= MAX_DISTANCE_LIMIT => distance as usize,
+            Some(_) => return Ok(invalid_params(&format!("'max_distance' must be at most {}", MAX_DISTANCE_LIMIT))),
+        };
+        let ignore_extension = args["ignore_extension"].as_bool().unwrap_or(false);
+        let drive = match args["drive"].as_str() {
+            Some(drive) => drive.chars().next().unwrap_or('C').to_ascii_uppercase(),
+            None => self.default_drive(),
+        };
+        let max_results = args["max_results"].as_u64().unwrap_or(50) as usize;
+        let filters = match SearchFilters::from_args(args, &self.doc_type_extensions) {
+            Ok(filters) => filters,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        
+        let start = Instant::now();
+        let alias = self.volumes.resolve(drive);
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        
+        // (distance, path as shown, file), closest first
+        let (total, hits) = {
+            let files = cache.get_files();
+            let names = cache.get_name_index();
+            let trigrams = cache.get_trigrams();
+            // Only names sharing enough trigrams with the wanted one can be close enough
+            let compared = if ignore_extension { stem(name) } else { name };
+            let near: Option<Vec<&String>> = trigrams.as_ref()
+                .and_then(|trigrams| trigrams.names_near(compared, max_distance))
+                .map(|near| near.into_iter().filter_map(|key| names.get_key_value(key).map(|(key, _)| key)).collect());
+            let found = match &near {
+                Some(near) => names_within(name, near.iter().copied(), max_distance, ignore_extension),
+                None => names_within(name, names.keys(), max_distance, ignore_extension),
+            };
+            let mut hits = Vec::new();
+            for (key, distance) in found {
+                for file in names[key].iter().filter_map(|id| files.get(id)) {
+                    // Records are also indexed under their short (8.3) names; report the long one only
+                    if file.name.to_lowercase() != *key || !filters.matches(file) {
                         continue;
                     }
-                } else {
-                    continue; // No extension but document type requires one
+                    if let Some(path) = alias.display_path(&file.path) {
+                        hits.push((distance, path.to_string(), file.clone()));
+                    }
                 }
             }
-            
-            // Add to results
-            results.push(file.clone());
-            result_count += 1;
-            
-            // Early exit if we've reached max results
-            if result_count >= max_results {
-                break;
-            }
-        }
-        
-        let search_duration = search_start.elapsed();
+            hits.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+            let total = hits.len();
+            hits.truncate(max_results);
+            (total, hits)
+        };
+        let elapsed = start.elapsed().as_millis();
         
-        // Format results
-        let results_text = if results.is_empty() {
-            format!("No files found matching pattern '{}' in drive {} (searched in {:.2}ms)", 
-                    pattern, drive, search_duration.as_millis())
+        let text = if hits.is_empty() {
+            format!("No files named like '{}' (within {} edits) on drive {}: ({}ms)", name, max_distance, drive, elapsed)
         } else {
-            let mut text = format!("🚀 FAST SEARCH: Found {} files matching '{}' in {:.2}ms\n\n", 
-                                 results.len(), pattern, search_duration.as_millis());
-            
-            for (i, file) in results.iter().enumerate() {
-                let size_info = if file.is_directory { 
-                    "DIR".to_string() 
-                } else { 
-                    format!("{} bytes", file.size) 
-                };
-                text.push_str(&format!("{}. {} ({})\n", 
-                                     i + 1, 
-                                     file.path,
-                                     size_info));
+            let mut text = format!("🔤 SIMILAR NAMES: {} files named like '{}' (within {} edits) on drive {}: in {}ms\n\n",
+                                   total, name, max_distance, drive, elapsed);
+            for (i, (distance, path, file)) in hits.iter().enumerate() {
+                let size_info = if file.is_directory { "DIR".to_string() } else { format!("{} bytes", file.size) };
+                text.push_str(&format!("{}. {} ({}, {} edits)\n", i + 1, path, size_info, distance));
             }
-            
-            if results.len() >= max_results {
-                text.push_str(&format!("\n⚡ Stopped at {} results (use max_results to get more)", max_results));
+            if total > hits.len() {
+                text.push_str(&format!("\n⚡ Showing {} of {}; raise max_results or lower max_distance", hits.len(), total));
             }
-            
-            text.push_str(&format!("\n💡 Search completed in {:.2}ms - USING MFT CACHE", search_duration.as_millis()));
             text
         };
         
@@ -368,8 +3371,18 @@ impl SearchEngine {
             "result": {
                 "content": [{
                     "type": "text",
-                    "text": results_text
-                }]
+                    "text": text
+                }],
+                "results": hits.iter().map(|(distance, path, file)| json!({
+                    "name": file.name,
+                    "path": path,
+                    "drive": drive.to_string(),
+                    "size": file.size,
+                    "is_directory": file.is_directory,
+                    "modified": unix_timestamp(file.modified),
+                    "distance": distance
+                })).collect::<Vec<_>>(),
+                "total": total
             }
         }))
     }
@@ -377,15 +3390,27 @@ impl SearchEngine {
     /// Find large files by direct scan
     fn find_large_files(&self, args: &Value) -> Result<Value> {
         let min_size_mb = args["min_size_mb"].as_u64().unwrap_or(100);
-        let drive = args["drive"].as_str().unwrap_or("C");
+        let drive = match args["drive"].as_str() {
+            Some(drive) => drive.to_uppercase(),
+            None => self.default_drive().to_string(),
+        };
         let max_results = args["max_results"].as_u64().unwrap_or(50) as usize;
         
         info!("Finding large files: min_size={}MB, drive={}", min_size_mb, drive);
         
         let search_start = Instant::now();
         
-        // Search for all files and filter by size
-        let all_files = crate::ntfs_reader::search_files_direct(drive, "*", "", max_results * 10)?;
+        let exclude = match ExcludePatterns::from_args(args) {
+            Ok(exclude) => exclude,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let max_depth = match parse_depth_arg(args) {
+            Ok(max_depth) => max_depth,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        
+        // Search for all files and filter by size; excluded folders (and those past max_depth) are skipped entirely
+        let all_files = super::ntfs_reader::search_files_direct_excluding(&drive, "*", "", &exclude, max_depth, max_results * 10)?;
         
         let min_size_bytes = min_size_mb * 1024 * 1024;
         let mut large_files: Vec<_> = all_files
@@ -422,13 +3447,494 @@ impl SearchEngine {
                 "content": [{
                     "type": "text",
                     "text": results_text
-                }]
+                }],
+                "results": large_files.iter().map(|file| json!({
+                    "name": file.name,
+                    "path": file.full_path,
+                    "drive": drive,
+                    "size": file.size,
+                    "size_formatted": format_size(file.size),
+                    "created": file.created,
+                    "modified": file.modified,
+                    "accessed": file.accessed,
+                })).collect::<Vec<_>>(),
+                "total": large_files.len(),
+                "min_size_bytes": min_size_bytes
+            }
+        }))
+    }
+    
+    /// Sizes of the folders below a folder, added up from the files in the cache
+    ///
+    /// Args:
+    /// - path: Folder to break down, optionally with its drive ("D:\\Projects"); the drive root without one
+    /// - drive: Drive letter when path doesn't include one (default C)
+    /// - max_depth: Folder levels below the path to list (default 1)
+    /// - exclude: Globs for paths to leave out of the totals
+    /// - max_results: Maximum number of folders (default 20)
+    fn directory_sizes(&self, args: &Value) -> Result<Value> {
+        let max_depth = match args["max_depth"].as_u64() {
+            None => DEFAULT_SIZE_DEPTH,
+            Some(depth) if depth >= 1 && depth as usize <= MAX_SIZE_DEPTH => depth as usize,
+            Some(_) => return Ok(invalid_params(&format!("'max_depth' must be between 1 and {}", MAX_SIZE_DEPTH))),
+        };
+        let max_results = args["max_results"].as_u64().unwrap_or(20) as usize;
+        let exclude = match ExcludePatterns::from_args(args) {
+            Ok(exclude) => exclude,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let CachePath { drive, path, alias, cache_path } =
+            self.resolve_path_arg(args["path"].as_str().unwrap_or_default(), args["drive"].as_str());
+        
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        if !cache_path.is_empty() {
+            let found = lookup_path(&cache.get_path_index(), &cache_path)
+                .and_then(|id| cache.get_files().get(&id).map(|file| file.is_directory));
+            if found != Some(true) {
+                return Ok(invalid_params(&format!("{}:\\{} is not a folder in the index", drive, path)));
+            }
+        }
+        let sizes = directory_sizes(cache.get_files().values(), &cache_path, max_depth, &exclude);
+        let elapsed = start.elapsed().as_millis();
+        
+        let root = format!("{}:\\{}", drive, path);
+        let shown = |dir_path: &str| format!("{}:\\{}", drive, alias.display_path(dir_path).unwrap_or(dir_path));
+        let dirs = &sizes.dirs[..sizes.dirs.len().min(max_results)];
+        let mut text = format!("📊 DIRECTORY SIZES: {} holds {} in {} files ({}ms)\n\n",
+                               root, format_size(sizes.total_bytes), sizes.total_files, elapsed);
+        for (i, dir) in dirs.iter().enumerate() {
+            text.push_str(&format!("{}. {} {} ({} files)\n", i + 1, shown(&dir.path), format_size(dir.bytes), dir.files));
+        }
+        if sizes.own_files > 0 {
+            text.push_str(&format!("\nFiles directly in {}: {} ({} files)", root, format_size(sizes.own_bytes), sizes.own_files));
+        }
+        if sizes.dirs.len() > dirs.len() {
+            text.push_str(&format!("\n⚡ Showing {} of {} folders; raise max_results to see more", dirs.len(), sizes.dirs.len()));
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "root": root,
+                "total_bytes": sizes.total_bytes,
+                "total_files": sizes.total_files,
+                "own_bytes": sizes.own_bytes,
+                "own_files": sizes.own_files,
+                "directories": dirs.iter().map(|dir| json!({
+                    "path": shown(&dir.path),
+                    "depth": dir.depth,
+                    "bytes": dir.bytes,
+                    "files": dir.files
+                })).collect::<Vec<_>>(),
+                "total_directories": sizes.dirs.len()
+            }
+        }))
+    }
+    
+    /// Histogram of the sizes of the files below a folder, from the cache
+    ///
+    /// Args:
+    /// - path: Folder to look at, optionally with its drive ("D:\\Projects"); the drive root without one
+    /// - drive: Drive letter when path doesn't include one (default C)
+    /// - group_by: "none" (default), "doc_type" or "folder"
+    /// - max_groups: Maximum number of groups (default 10)
+    /// - doc_type, extensions, exclude, ...: The filters of fast_search, except path and max_depth
+    fn size_distribution(&self, args: &Value) -> Result<Value> {
+        let Some(grouping) = SizeGrouping::parse(args["group_by"].as_str().unwrap_or_default()) else {
+            return Ok(invalid_params("'group_by' must be one of: none, doc_type, folder"));
+        };
+        let max_groups = args["max_groups"].as_u64().unwrap_or(10) as usize;
+        // The path argument names the root here, not a substring to match
+        let filters = match SearchFilters::from_args(args, &self.doc_type_extensions) {
+            Ok(filters) => SearchFilters { path: String::new(), path_glob: None, max_depth: None, ..filters },
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let CachePath { drive, path, alias, cache_path } =
+            self.resolve_path_arg(args["path"].as_str().unwrap_or_default(), args["drive"].as_str());
+        
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        if !cache_path.is_empty() {
+            let found = lookup_path(&cache.get_path_index(), &cache_path)
+                .and_then(|id| cache.get_files().get(&id).map(|file| file.is_directory));
+            if found != Some(true) {
+                return Ok(invalid_params(&format!("{}:\\{} is not a folder in the index", drive, path)));
+            }
+        }
+        let distribution = size_distribution(cache.get_files().values(), &cache_path, grouping, |file| filters.matches(file));
+        let elapsed = start.elapsed().as_millis();
+        
+        let root = format!("{}:\\{}", drive, path);
+        let overall = &distribution.overall;
+        // Folder groups are shown as full paths on the drive they were asked for
+        let group_name = |name: &str| {
+            if grouping != SizeGrouping::Folder || name == "(files)" {
+                return name.to_string();
+            }
+            let folder = if cache_path.is_empty() { name.to_string() } else { format!("{}\\{}", cache_path, name) };
+            format!("{}:\\{}", drive, alias.display_path(&folder).unwrap_or(&folder))
+        };
+        let groups = &distribution.groups[..distribution.groups.len().min(max_groups)];
+        
+        let mut text = format!("📊 SIZE DISTRIBUTION: {} files in {} under {} ({}ms)\n{}\n\n",
+                               overall.files, format_size(overall.bytes), root, elapsed, overall.summary());
+        for bucket in overall.buckets.iter().filter(|bucket| bucket.files > 0) {
+            text.push_str(&format!("{:>14}: {} files, {}\n", bucket.label, bucket.files, format_size(bucket.bytes)));
+        }
+        if !groups.is_empty() {
+            text.push('\n');
+            for (name, histogram) in groups {
+                text.push_str(&format!("{}: {} files, {} - {}\n",
+                                       group_name(name), histogram.files, format_size(histogram.bytes), histogram.summary()));
+            }
+        }
+        if distribution.groups.len() > groups.len() {
+            text.push_str(&format!("\n⚡ Showing {} of {} groups; raise max_groups to see more", groups.len(), distribution.groups.len()));
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "root": root,
+                "overall": overall,
+                "summary": overall.summary(),
+                "groups": groups.iter().map(|(name, histogram)| json!({
+                    "name": group_name(name),
+                    "files": histogram.files,
+                    "bytes": histogram.bytes,
+                    "buckets": histogram.buckets
+                })).collect::<Vec<_>>(),
+                "total_groups": distribution.groups.len()
+            }
+        }))
+    }
+    
+    /// When the files below a folder changed, from the timestamps in the cache
+    ///
+    /// Args:
+    /// - path: Folder to look at, optionally with its drive ("D:\\Projects\\app"); the drive root without one
+    /// - drive: Drive letter when path doesn't include one (default C)
+    /// - field: modified (default), created or accessed
+    /// - period: day (default), week or month
+    /// - timezone: local (default) or utc
+    /// - max_periods: Maximum number of periods, most recent first (default 31)
+    /// - modified_after, extensions, exclude, ...: The filters of fast_search, except path and max_depth
+    fn activity_heatmap(&self, args: &Value) -> Result<Value> {
+        let Some(field) = ActivityField::parse(args["field"].as_str().unwrap_or_default()) else {
+            return Ok(invalid_params("'field' must be one of: modified, created, accessed"));
+        };
+        let Some(period) = ActivityPeriod::parse(args["period"].as_str().unwrap_or_default()) else {
+            return Ok(invalid_params("'period' must be one of: day, week, month"));
+        };
+        let utc = match args["timezone"].as_str().map(str::to_lowercase).as_deref() {
+            None | Some("local") => false,
+            Some("utc") => true,
+            Some(_) => return Ok(invalid_params("'timezone' must be local or utc")),
+        };
+        let max_periods = args["max_periods"].as_u64().unwrap_or(31) as usize;
+        // The path argument names the root here, not a substring to match
+        let filters = match SearchFilters::from_args(args, &self.doc_type_extensions) {
+            Ok(filters) => SearchFilters { path: String::new(), path_glob: None, max_depth: None, ..filters },
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let CachePath { drive, path, alias, cache_path } =
+            self.resolve_path_arg(args["path"].as_str().unwrap_or_default(), args["drive"].as_str());
+        
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        if !cache_path.is_empty() {
+            let found = lookup_path(&cache.get_path_index(), &cache_path)
+                .and_then(|id| cache.get_files().get(&id).map(|file| file.is_directory));
+            if found != Some(true) {
+                return Ok(invalid_params(&format!("{}:\\{} is not a folder in the index", drive, path)));
+            }
+        }
+        let local = |time: SystemTime| if utc {
+            DateTime::<Utc>::from(time).naive_utc()
+        } else {
+            DateTime::<Local>::from(time).naive_local()
+        };
+        let found = activity(cache.get_files().values(), &cache_path, field, period, local, |file| filters.matches(file));
+        let elapsed = start.elapsed().as_millis();
+        
+        let root = format!("{}:\\{}", drive, path);
+        let recent: Vec<&ActivityBucket> = found.periods.iter().rev().take(max_periods).collect();
+        let mut text = format!("🗓️ ACTIVITY: {} files under {} by {} time ({}ms)\n",
+                               found.files, root, field.as_str(), elapsed);
+        if let (Some(first), Some(last)) = (found.first, found.last) {
+            text.push_str(&format!("From {} to {}", first.format("%Y-%m-%d %H:%M"), last.format("%Y-%m-%d %H:%M")));
+            if let Some(busiest) = found.busiest_period() {
+                text.push_str(&format!("; busiest {}: {} ({} files)", period.as_str(), busiest.start, busiest.files));
+            }
+            if let Some((day, hour, files)) = found.busiest_hour() {
+                let weekday = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"][day];
+                text.push_str(&format!("; busiest hour: {}s {:02}:00 ({} files)", weekday, hour, files));
+            }
+            text.push_str(&format!("\n\n{}\n", render_heatmap(&found.heatmap)));
+            for bucket in &recent {
+                text.push_str(&format!("{}: {} files, {}\n", bucket.start, bucket.files, format_size(bucket.bytes)));
+            }
+            if found.periods.len() > recent.len() {
+                text.push_str(&format!("\n⚡ Showing the {} most recent of {} periods; raise max_periods to see more",
+                                       recent.len(), found.periods.len()));
+            }
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "root": root,
+                "field": field.as_str(),
+                "period": period.as_str(),
+                "timezone": if utc { "utc" } else { "local" },
+                "files": found.files,
+                "first": found.first.map(|time| time.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                "last": found.last.map(|time| time.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                "periods": recent,
+                "total_periods": found.periods.len(),
+                "heatmap": found.heatmap
             }
         }))
     }
     
+    /// License, README, CI and language of every project below a folder
+    ///
+    /// Args:
+    /// - path: Folder to look below, optionally with its drive ("D:\\Projects"); the drive root without one
+    /// - drive: Drive letter when path doesn't include one (default C)
+    /// - missing: Only projects lacking any of license, readme, ci
+    /// - max_results: Maximum number of projects (default 100)
+    /// - exclude: Globs for paths to leave out
+    fn repo_inventory(&self, args: &Value) -> Result<Value> {
+        let missing: Vec<String> = match &args["missing"] {
+            Value::Null => Vec::new(),
+            Value::String(list) => list.split(',').map(|item| item.trim().to_lowercase()).filter(|item| !item.is_empty()).collect(),
+            Value::Array(items) => items.iter().filter_map(|item| item.as_str()).map(|item| item.trim().to_lowercase()).collect(),
+            _ => return Ok(invalid_params("'missing' must be a list of license, readme, ci")),
+        };
+        if let Some(unknown) = missing.iter().find(|item| !["license", "readme", "ci"].contains(&item.as_str())) {
+            return Ok(invalid_params(&format!("Unknown 'missing' item '{}'; use license, readme or ci", unknown)));
+        }
+        let max_results = args["max_results"].as_u64().unwrap_or(100) as usize;
+        let exclude = match ExcludePatterns::from_args(args) {
+            Ok(exclude) => exclude,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let CachePath { drive, path, alias, cache_path } =
+            self.resolve_path_arg(args["path"].as_str().unwrap_or_default(), args["drive"].as_str());
+        
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        let index = self.get_project_index(&cache);
+        let inventories = {
+            let files = cache.get_files();
+            repo_inventory(files.values(), &index, &cache_path,
+                           |file| alias.display_path(&file.path).map_or(false, |shown| !exclude.excludes(shown)))
+        };
+        let total = inventories.len();
+        let matching: Vec<_> = inventories.into_iter()
+            .filter(|inventory| missing.is_empty() || inventory.missing().iter().any(|item| missing.iter().any(|wanted| wanted == item)))
+            .filter(|inventory| alias.display_path(&inventory.root.path).is_some())
+            .collect();
+        let elapsed = start.elapsed().as_millis();
+        
+        let root = format!("{}:\\{}", drive, path);
+        let shown_path = |cache_path: &str| format!("{}:\\{}", drive, alias.display_path(cache_path).unwrap_or(cache_path));
+        let count = |item: &str| matching.iter().filter(|inventory| inventory.missing().iter().any(|lacking| *lacking == item)).count();
+        let mut text = format!("📋 REPO INVENTORY: {} of {} projects under {} ({}ms)\n",
+                               matching.len(), total, root, elapsed);
+        if !matching.is_empty() {
+            text.push_str(&format!("Without a license: {}, without a README: {}, without CI: {}\n\n",
+                                   count("license"), count("readme"), count("ci")));
+        }
+        let check = |present: bool| if present { "✓" } else { "✗" };
+        for inventory in matching.iter().take(max_results) {
+            text.push_str(&format!("{} ({}, {}) license {} readme {} ci {}{}\n",
+                                   shown_path(&inventory.root.path), inventory.root.kind,
+                                   inventory.language.unwrap_or("no source"),
+                                   check(inventory.license.is_some()), check(inventory.readme.is_some()), check(!inventory.ci.is_empty()),
+                                   if inventory.ci.is_empty() { String::new() } else { format!(" ({})", inventory.ci.join(", ")) }));
+        }
+        if matching.len() > max_results {
+            text.push_str(&format!("\n⚡ Showing {} of {} projects; raise max_results to see more", max_results, matching.len()));
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "root": root,
+                "total_projects": total,
+                "matching_projects": matching.len(),
+                "projects": matching.iter().take(max_results).map(|inventory| json!({
+                    "path": shown_path(&inventory.root.path),
+                    "name": inventory.root.name,
+                    "kind": inventory.root.kind,
+                    "license": inventory.license,
+                    "readme": inventory.readme,
+                    "ci": inventory.ci,
+                    "language": inventory.language,
+                    "source_files": inventory.source_files,
+                    "missing": inventory.missing()
+                })).collect::<Vec<_>>()
+            }
+        }))
+    }
+    
+    /// Risky files below a folder: private keys, credentials, wallets and answer files
+    ///
+    /// Args:
+    /// - path: Folder to scan, optionally with its drive ("C:\\Users"); the drive root without one
+    /// - drive: Drive letter when path doesn't include one (default C)
+    /// - check_contents: Confirm findings from the start of the file where a rule can (default true)
+    /// - min_severity: critical, high, medium or low (default low)
+    /// - max_results: Maximum number of findings, worst first (default 100)
+    /// - exclude: Globs for paths to leave out
+    ///
+    /// Once the request is cancelled no more file contents are read; the
+    /// remaining findings are by name only and the result is flagged `cancelled`.
+    fn security_scan(&self, args: &Value, cancel: &CancelToken) -> Result<Value> {
+        let check_contents = args["check_contents"].as_bool().unwrap_or(true);
+        let min_severity = match args["min_severity"].as_str().map(str::trim) {
+            None | Some("") => Severity::Low,
+            Some(name) => match Severity::ALL.iter().find(|severity| severity.as_str().eq_ignore_ascii_case(name)) {
+                Some(severity) => *severity,
+                None => return Ok(invalid_params("'min_severity' must be one of: critical, high, medium, low")),
+            },
+        };
+        let max_results = args["max_results"].as_u64().unwrap_or(100) as usize;
+        let exclude = match ExcludePatterns::from_args(args) {
+            Ok(exclude) => exclude,
+            Err(e) => return Ok(invalid_params(&e.to_string())),
+        };
+        let CachePath { drive, path, alias, cache_path } =
+            self.resolve_path_arg(args["path"].as_str().unwrap_or_default(), args["drive"].as_str());
+        
+        let start = Instant::now();
+        let cache = self.get_or_create_cache(alias.canonical)?;
+        let disk_root = self.drive_root(alias.canonical);
+        let files = cache.get_files();
+        let findings: Vec<_> = security_scan(
+            files.values(),
+            &cache_path,
+            |file| alias.display_path(&file.path).map_or(false, |shown| !exclude.excludes(shown)),
+            |file| if check_contents && !cancel.is_cancelled() { read_head(&disk_path(&disk_root, &file.path)) } else { None },
+        ).into_iter().filter(|finding| finding.severity <= min_severity).collect();
+        let elapsed = start.elapsed().as_millis();
+        let cancelled = check_contents && cancel.is_cancelled();
+        
+        let root = format!("{}:\\{}", drive, path);
+        let by_severity: Vec<(Severity, usize)> = Severity::ALL.iter()
+            .map(|severity| (*severity, findings.iter().filter(|finding| finding.severity == *severity).count()))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        let mut by_category: Vec<(&str, usize)> = Vec::new();
+        for finding in &findings {
+            match by_category.iter_mut().find(|(category, _)| *category == finding.rule.category) {
+                Some((_, count)) => *count += 1,
+                None => by_category.push((finding.rule.category, 1)),
+            }
+        }
+        let shown: Vec<Value> = findings.iter().take(max_results).filter_map(|finding| {
+            let shown_path = format!("{}:\\{}", drive, alias.display_path(&finding.file.path)?);
+            Some(json!({
+                "path": shown_path,
+                "rule": finding.rule.id,
+                "category": finding.rule.category,
+                "severity": finding.severity.as_str(),
+                "description": finding.rule.description,
+                "confirmed": finding.confirmed,
+                "size": finding.file.size,
+                "modified": finding.file.modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+            }))
+        }).collect();
+        
+        let mut text = format!("🛡️ SECURITY SCAN: {} finding{} under {} ({}ms)\n",
+                               findings.len(), if findings.len() == 1 { "" } else { "s" }, root, elapsed);
+        if findings.is_empty() {
+            text.push_str("No known risky files found");
+        } else {
+            let counts: Vec<String> = by_severity.iter()
+                .map(|(severity, count)| format!("{} {}", count, severity.as_str()))
+                .collect();
+            text.push_str(&format!("{}\n\n", counts.join(", ")));
+            for (finding, item) in findings.iter().zip(&shown) {
+                let note = match finding.confirmed {
+                    Some(true) => " (confirmed)",
+                    Some(false) => " (name only, contents don't match)",
+                    None => "",
+                };
+                text.push_str(&format!("{} [{}] {}: {}{}\n", finding.severity.icon(), finding.rule.category,
+                                       finding.rule.description, item["path"].as_str().unwrap_or_default(), note));
+            }
+            if findings.len() > shown.len() {
+                text.push_str(&format!("\n⚡ Showing {} of {} findings; raise max_results to see more", shown.len(), findings.len()));
+            }
+        }
+        if cancelled {
+            text.push_str("\n⏹️ Cancelled: later findings weren't checked against their contents");
+        }
+        
+        Ok(json!({
+            "result": {
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }],
+                "root": root,
+                "total_findings": findings.len(),
+                "by_severity": by_severity.iter()
+                    .map(|(severity, count)| (severity.as_str().to_string(), json!(count)))
+                    .collect::<serde_json::Map<_, _>>(),
+                "by_category": by_category.iter()
+                    .map(|(category, count)| (category.to_string(), json!(count)))
+                    .collect::<serde_json::Map<_, _>>(),
+                "findings": shown,
+                "contents_checked": check_contents,
+                "cancelled": cancelled
+            }
+        }))
+    }
+    
+    /// Build (or reuse) the cache a search on a drive letter would use
+    pub(crate) fn open_drive(&self, letter: char) -> Result<Arc<MftCache>> {
+        let drive = self.volumes.resolve(letter.to_ascii_uppercase()).canonical;
+        self.get_or_create_cache(drive)
+    }
+
+    /// Caches built so far, by drive letter
+    pub(crate) fn open_drives(&self) -> Vec<Arc<MftCache>> {
+        let mut caches: Vec<_> = self.mft_cache.read().values().cloned().collect();
+        caches.sort_by_key(|cache| cache.drive_letter());
+        caches
+    }
+
     /// Helper to get or create MFT cache for a drive
+    ///
+    /// The drive, the age of its cache and any time spent building it go into
+    /// the envelope of the running tool call.
     fn get_or_create_cache(&self, drive: char) -> Result<Arc<MftCache>> {
+        let started = Instant::now();
+        let built = !self.mft_cache.read().contains_key(&drive);
+        let cache = self.open_or_build_cache(drive);
+        let updated = cache.as_ref().ok().map(|cache| cache.last_update());
+        record_drive(drive, updated, (built && cache.is_ok()).then(|| started.elapsed()));
+        cache
+    }
+
+    fn open_or_build_cache(&self, drive: char) -> Result<Arc<MftCache>> {
         // Check if we already have a cache for this drive
         let cache_map = self.mft_cache.read().map_err(|e| anyhow::anyhow!("Failed to acquire read lock: {}", e))?;
         if let Some(cache) = cache_map.get(&drive) {
@@ -446,56 +3952,161 @@ impl SearchEngine {
             return Ok(Arc::clone(cache));
         }
         
-        info!("Creating new MFT cache for drive {}:", drive);
-        let mft_cache = match MftCache::new(drive) {
-            Ok(cache) => Arc::new(cache),
-            Err(e) => return Err(anyhow::anyhow!("Failed to create MFT cache: {}", e)),
+        if self.drives.is_excluded(drive) {
+            return Err(anyhow::anyhow!("Drive {}: is excluded from indexing (see configure_drives)", drive));
+        }
+        
+        // In container mode only the mounted directories exist; there is no volume to read
+        let mut mount = self.mounts.iter().find(|m| m.drive == drive).cloned();
+        if mount.is_none() && !self.mounts.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Drive {}: is not mounted (available: {})",
+                drive,
+                self.mounts.iter().map(|m| format!("{}:", m.drive)).collect::<Vec<_>>().join(", ")
+            ));
+        }
+        
+        // A drive whose cache couldn't be built is scanned directly for a while before trying again
+        if let Some(mode) = drive_mode(drive).filter(|mode| mode.mode == IndexMode::DirectScan) {
+            if mode.age(SystemTime::now()) < CACHE_RETRY_INTERVAL {
+                return Err(anyhow::anyhow!(
+                    "Drive {}: has no MFT cache: {}", drive, mode.reason.unwrap_or_default()
+                ));
+            }
+        }
+        
+        // Set when the drive is walked because its volume can't be opened
+        let mut walked = None;
+        let mft_cache = if mount.is_some() {
+            Arc::new(MftCache::detached(drive))
+        } else {
+            info!("Creating new MFT cache for drive {}:", drive);
+            // Its rebuilds stop with everything else when the service stops
+            match MftCache::with_cancel(drive, MftCacheConfig::default(), self.cancellations.root().child()) {
+                Ok(cache) => Arc::new(cache),
+                Err(e) if e.is::<Cancelled>() => return Err(e),
+                Err(e) => match build_fallback(is_elevated()) {
+                    IndexMode::Walk => {
+                        mount = Some(MountConfig::drive_root(drive));
+                        walked = Some(format!("MFT unreadable: {}", e));
+                        Arc::new(MftCache::detached(drive))
+                    }
+                    _ => {
+                        set_mode(drive, IndexMode::DirectScan, Some(format!("MFT cache could not be built: {}", e)));
+                        return Err(anyhow::anyhow!("Failed to create MFT cache: {}", e));
+                    }
+                },
+            }
         };
         
+        // Evaluate watched saved searches against every batch of journal changes
+        let watcher = WatchEvaluator::new(
+            Arc::clone(&self.saved_searches),
+            Arc::clone(&self.pattern_cache),
+            Arc::clone(&self.doc_type_extensions),
+            Arc::clone(&self.notifications),
+        );
+        mft_cache.add_change_listener(Arc::new(move |cache, changes| {
+            watcher.evaluate(cache, changes);
+        }));
+        // ...and update the results of live queries on the drive
+        let live_queries = Arc::clone(&self.live_queries);
+        mft_cache.add_change_listener(Arc::new(move |cache, changes| {
+            live_queries.apply(cache, changes);
+        }));
+        
+        if let Some(mount) = mount {
+            // Directory indexes are always watched, so watched searches need nothing extra
+            let indexer = DirectoryIndexer::start(mount, Arc::clone(&mft_cache), Arc::clone(&self.denied_paths))?;
+            info!("{}", describe_mount(indexer.mount(), &mft_cache));
+            self.dir_indexers.write().insert(drive, indexer);
+            match walked {
+                Some(reason) => set_mode(drive, IndexMode::Walk, Some(reason)),
+                None => set_mode(drive, IndexMode::Mounted, None),
+            }
+        } else {
+            // Keep the cache current from the USN journal, or else by rebuilding it now and then
+            match mft_cache.start_monitoring() {
+                Ok(()) => set_mode(drive, IndexMode::Mft, None),
+                Err(e) => {
+                    start_periodic_refresh(&mft_cache, REFRESH_INTERVAL)?;
+                    set_mode(drive, IndexMode::PeriodicRefresh, Some(format!("USN journal unavailable: {}", e)));
+                }
+            }
+        }
+        
+        // A drive that came back under another letter leaves its cache behind under the old one
+        let moved: Vec<char> = cache_map
+            .iter()
+            .filter(|(letter, cache)| **letter != drive && cache.volume() == mft_cache.volume())
+            .map(|(letter, _)| *letter)
+            .collect();
+        for letter in moved {
+            if let Some(old) = cache_map.remove(&letter) {
+                info!("Drive {}: is now {}: ({}); dropping the cache under the old letter", letter, drive, old.volume());
+                old.stop_auto_save();
+                old.cancel_rebuilds();
+                if let Err(e) = old.stop_monitoring() {
+                    error!("Failed to stop monitoring drive {}: {}", letter, e);
+                }
+            }
+            self.project_indexes.write().remove(&letter);
+            clear_mode(letter);
+        }
+        
         // Insert into our cache map
         cache_map.insert(drive, Arc::clone(&mft_cache));
         
         Ok(mft_cache)
     }
     
-    /// Convert a file pattern to a regex
-    fn pattern_to_regex(&self, pattern: &str) -> Result<regex::Regex> {
-        // Handle special cases
-        if pattern == "*" || pattern == "*.*" {
-            return Ok(regex::Regex::new(".*").unwrap());
-        }
-        
-        // Escape special regex characters
-        let mut regex_str = regex::escape(pattern);
+    /// Get the project root index for a drive, rebuilding it if the cache has changed
+    fn get_project_index(&self, mft_cache: &MftCache) -> Arc<ProjectIndex> {
+        let drive = mft_cache.drive_letter();
+        let last_update = mft_cache.last_update();
         
-        // Convert wildcards to regex patterns
-        regex_str = regex_str.replace(".", "\\.."); // Escape literal dots
-        regex_str = regex_str.replace("*", ".*");     // Convert * to .*
-        regex_str = regex_str.replace("?", ".");      // Convert ? to .
+        if let Some(index) = self.project_indexes.read().get(&drive) {
+            if index.built_at() == last_update {
+                return Arc::clone(index);
+            }
+        }
         
-        // Make case-insensitive and ensure we match the whole string
-        regex_str = format!(r"^(?i){}$", regex_str);
+        let index = {
+            let files = mft_cache.get_files();
+            Arc::new(ProjectIndex::build(files.values(), last_update))
+        };
+        debug!("Detected {} project roots on drive {}:", index.len(), drive);
         
-        regex::Regex::new(&regex_str)
-            .with_context(|| format!("Invalid search pattern: {}", pattern))
+        self.project_indexes.write().insert(drive, Arc::clone(&index));
+        index
     }
     
     /// Benchmark direct search performance
     pub fn benchmark_search(&self, args: &Value) -> Result<Value> {
-        let drive = args["drive"].as_str().unwrap_or("C");
+        let drive = match args["drive"].as_str() {
+            Some(drive) => drive.to_uppercase(),
+            None => self.default_drive().to_string(),
+        };
         
         info!("Running direct search benchmark for drive: {}", drive);
         
         #[cfg(windows)]
         {
-            match crate::ntfs_reader::benchmark_mft_performance(drive) {
-                Ok(_) => {
+            match crate::ntfs_reader::benchmark_mft_performance(&drive) {
+                Ok(runs) => {
+                    let mut text = format!("Benchmark completed for drive {}:\n", drive);
+                    for run in &runs {
+                        text.push_str(&format!("  {}: {} files in {:.2}ms ({:.2} files/ms)\n",
+                                               run.pattern, run.files, run.duration_ms, run.files_per_ms));
+                    }
                     Ok(json!({
                         "result": {
                             "content": [{
                                 "type": "text",
-                                "text": format!("Benchmark completed for drive {}. Check console output for detailed results.", drive)
-                            }]
+                                "text": text
+                            }],
+                            "drive": drive,
+                            "results": runs
                         }
                     }))
                 }
@@ -525,3 +4136,263 @@ impl SearchEngine {
         }
     }
 }
+
+/// ID of a cache path, matched exactly first and then ignoring case like NTFS does
+fn lookup_path(paths: &HashMap<String, u64>, path: &str) -> Option<u64> {
+    paths.get(path).copied().or_else(|| {
+        paths.iter().find(|(p, _)| p.eq_ignore_ascii_case(path)).map(|(_, id)| *id)
+    })
+}
+
+/// Say how the patterns of a smart search were read
+///
+/// `_meta.query` echoes what the caller sent; `_meta.rewritten_query` is what was searched.
+fn smart_response(smart: &SmartSearch, mut response: Value) -> Value {
+    if response["result"].is_null() {
+        return response;
+    }
+    let text = response["result"]["content"][0]["text"].as_str().unwrap_or_default().to_string();
+    response["result"]["content"][0]["text"] = json!(format!(
+        "🧠 SMART: searched {} (pass search_type explicitly if that's not what you meant).\n\n{}",
+        smart.describe(), text
+    ));
+    response["result"]["smart"] = smart.to_json();
+    response["result"]["_meta"]["smart_rewritten"] = json!(true);
+    response["result"]["_meta"]["rewritten_query"] = smart.args.clone();
+    response
+}
+
+/// Mark a response as coming from the target of a link the path filter ran through
+fn followed_link_response(path: &str, drive: char, target: &str, mut response: Value) -> Value {
+    let text = response["result"]["content"][0]["text"].as_str().unwrap_or_default().to_string();
+    response["result"]["content"][0]["text"] = json!(format!(
+        "↪ FOLLOWED LINK: '{}' leads through a link, so these results are from {}:\\{} \
+         (set reparse_points to \"skip\" to search the link itself).\n\n{}",
+        path, drive, target, text
+    ));
+    response["result"]["followed_link"] = json!({
+        "path": path,
+        "drive": drive.to_string(),
+        "target": target,
+    });
+    response
+}
+
+/// Structured form of a named data stream, listed after the file it belongs to
+fn stream_json(hit: &SearchHit, stream: &DataStream) -> Value {
+    json!({
+        "name": stream_path(&hit.file.name, stream),
+        "path": stream_path(&hit.file.path, stream),
+        "drive": hit.drive.to_string(),
+        "size": stream.size,
+        "is_directory": false,
+        "stream": stream.name,
+        "stream_of": hit.file.path,
+    })
+}
+
+/// A result's size, score and other notes, then its data streams on lines of their own
+fn describe_hit(hit: &SearchHit) -> String {
+    let file = &hit.file;
+    let size_info = if file.is_directory { 
+        "DIR".to_string() 
+    } else { 
+        format!("{} bytes", file.size) 
+    };
+    let score_info = hit.score
+        .map(|s| format!(" {:.0}%", s * 100.0))
+        .unwrap_or_default();
+    let workspace_info = if hit.in_workspace { " [workspace]" } else { "" };
+    let project_info = hit.project.as_ref()
+        .map(|root| format!(" [project: {} ({})]", root.name, root.kind))
+        .unwrap_or_default();
+    let alias_info = if hit.aliases.is_empty() {
+        String::new()
+    } else {
+        format!(" [also at: {}]", hit.aliases.join(", "))
+    };
+    let link_info = if hit.alternate_paths.is_empty() {
+        String::new()
+    } else {
+        format!(" [same file as: {}]", hit.alternate_paths.join(", "))
+    };
+    let image_info = hit.image.as_ref()
+        .map(|image| format!(" [{}]", image.describe()))
+        .unwrap_or_default();
+    let media_info = hit.media.as_ref()
+        .map(|media| format!(" [{}]", media.describe()))
+        .unwrap_or_default();
+    let mime_info = hit.mime_type
+        .map(|mime| format!(" [{}]", mime))
+        .unwrap_or_default();
+    let doc_info = hit.doc_stats.as_ref()
+        .map(|stats| stats.describe())
+        .filter(|text| !text.is_empty())
+        .map(|text| format!(" [{}]", text))
+        .unwrap_or_default();
+    let mut text = format!("({}){}{}{}{}{}{}{}{}{}", 
+                           size_info,
+                           mime_info,
+                           doc_info,
+                           image_info,
+                           media_info,
+                           score_info,
+                           workspace_info,
+                           project_info,
+                           alias_info,
+                           link_info);
+    for stream in &hit.streams {
+        text.push_str(&format!("\n   ↳ :{} ({} bytes)", stream.name, stream.size));
+    }
+    text
+}
+
+/// Structured form of a `fast_search` result
+fn hit_json(hit: &SearchHit) -> Value {
+    let file = &hit.file;
+    let mut entry = json!({
+        "name": file.name,
+        "path": file.path,
+        "drive": hit.drive.to_string(),
+        "size": file.size,
+        "is_directory": file.is_directory,
+        "created": unix_timestamp(file.created),
+        "modified": unix_timestamp(file.modified),
+        "accessed": unix_timestamp(file.accessed),
+    });
+    if let Some(score) = hit.score {
+        entry["match_score"] = json!(score);
+    }
+    if hit.in_workspace {
+        entry["in_workspace"] = json!(true);
+    }
+    if let Some(root) = &hit.project {
+        entry["project"] = json!(root);
+    }
+    if !hit.aliases.is_empty() {
+        entry["aliases"] = json!(hit.aliases);
+    }
+    if !hit.alternate_paths.is_empty() {
+        entry["alternate_paths"] = json!(hit.alternate_paths);
+    }
+    if file.link_count > 1 {
+        entry["link_count"] = json!(file.link_count);
+    }
+    let reparse = ReparseKind::of(file.reparse_tag);
+    entry["is_symlink"] = json!(reparse.map_or(false, |kind| kind.is_link()));
+    if let Some(kind) = reparse {
+        entry["reparse"] = json!(kind.as_str());
+    }
+    if let Some(target) = &hit.reparse_target {
+        entry["reparse_target"] = json!(target);
+    }
+    if let Some(image) = &hit.image {
+        entry["image"] = json!({
+            "width": image.width,
+            "height": image.height,
+            "megapixels": (image.megapixels() * 10.0).round() / 10.0,
+            "taken": image.taken,
+        });
+    }
+    if let Some(media) = &hit.media {
+        entry["media"] = json!(media);
+    }
+    if let Some(stats) = &hit.doc_stats {
+        entry["doc_stats"] = json!(stats);
+    }
+    if let Some(kind) = hit.content_kind {
+        entry["content_kind"] = json!(kind.as_str());
+    }
+    if let Some(mime_type) = hit.mime_type {
+        entry["mime_type"] = json!(mime_type);
+    }
+    if !hit.highlights.is_empty() {
+        entry["highlights"] = json!(hit.highlights);
+    }
+    let attributes = attribute_names(file.attributes);
+    if !attributes.is_empty() {
+        entry["attributes"] = json!(attributes);
+    }
+    entry
+}
+
+/// Names of the attribute flags set, as the attribute filters of `fast_search` call them
+fn attribute_names(attributes: u32) -> Vec<&'static str> {
+    ATTRIBUTE_ARGS
+        .iter()
+        .filter(|(_, flag)| attributes & flag == *flag)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Structured form of a live stat for `get_file_info`
+fn live_json(live: &LiveStat) -> Value {
+    let reparse = ReparseKind::of(live.reparse_tag);
+    json!({
+        "size": live.size,
+        "size_on_disk": live.size_on_disk,
+        "is_directory": live.is_directory,
+        "created": live.created.map(unix_timestamp),
+        "modified": live.modified.map(unix_timestamp),
+        "accessed": live.accessed.map(unix_timestamp),
+        "attributes": attribute_names(live.attributes),
+        "attribute_flags": live.attributes,
+        "link_count": live.link_count,
+        "file_id": live.file_id,
+        "reparse": reparse.map(|kind| kind.as_str())
+    })
+}
+
+/// Seconds since the UNIX epoch (0 for earlier times)
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Build a JSON-RPC invalid params error response
+/// Response for a search that matches too much of the cache to page through usefully
+fn broad_summary_response(description: &str, drive: &str, max_results: usize, summary: &BroadSummary, elapsed: Duration) -> Value {
+    let list = |buckets: &[Breakdown], label: fn(&str) -> String| {
+        buckets.iter()
+            .map(|bucket| format!("{} (~{})", label(&bucket.key), bucket.estimated))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let text = format!(
+        "⚠️ BROAD SEARCH: '{}' matches about {} of {} entries in drive {} (estimated from {} samples in {:.2}ms), \
+         so here is a summary instead of {} arbitrary results.\n\n\
+         Most common extensions: {}\n\
+         Top-level folders with the most matches: {}\n\n\
+         💡 Narrow it down with extensions, path, exclude or a more specific pattern, \
+         or pass summarize_broad: false to page through the matches anyway.",
+        description,
+        summary.estimated_matches,
+        summary.total_entries,
+        drive,
+        summary.sampled,
+        elapsed.as_millis(),
+        max_results,
+        list(&summary.top_extensions, |ext| if ext.is_empty() { "(none)".to_string() } else { format!(".{}", ext) }),
+        list(&summary.top_folders, |folder| if folder.is_empty() { "(drive root)".to_string() } else { folder.to_string() }),
+    );
+    
+    json!({
+        "result": {
+            "content": [{
+                "type": "text",
+                "text": text
+            }],
+            "results": [],
+            "next_cursor": null,
+            "broad_summary": summary
+        }
+    })
+}
+
+fn invalid_params(message: &str) -> Value {
+    json!({
+        "error": {
+            "code": -32602,
+            "message": message
+        }
+    })
+}
@@ -0,0 +1,168 @@
+//! Canary queries run on each cache at startup
+//!
+//! A cache can load without error and still be useless: a snapshot cut short,
+//! indexes out of step with the entries, or a volume read that came back
+//! empty. Searches on such a cache find nothing, which looks like a real
+//! answer. So once a preloaded drive's cache is ready, a few lookups whose
+//! answer is known are run against it:
+//!
+//! - the cache has entries at all
+//! - a few of its entries are found again by name and by path
+//! - on the system drive, files every Windows install has (`explorer.exe`,
+//!   `kernel32.dll`, ...) are found where they belong
+//!
+//! Until every tested drive passes, the health and status checks don't report
+//! the service as ready; the failed checks are listed under `self_test`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::mft_cache::FileEntry;
+
+/// Files every Windows install has, by folder below the system drive's root
+pub const SYSTEM_CANARIES: &[(&str, &str)] = &[
+    ("Windows", "explorer.exe"),
+    ("Windows\\System32", "kernel32.dll"),
+    ("Windows\\System32", "notepad.exe"),
+    ("Windows\\System32\\drivers\\etc", "hosts"),
+];
+
+/// Entries looked up again by name and path
+const SAMPLE_SIZE: usize = 3;
+
+/// Results of the drives tested so far
+static RESULTS: Mutex<BTreeMap<char, SelfTest>> = Mutex::new(BTreeMap::new());
+
+/// One canary query and how it went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CanaryCheck {
+    pub check: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl CanaryCheck {
+    fn new(check: String, failure: Option<String>) -> Self {
+        Self { check, passed: failure.is_none(), detail: failure }
+    }
+}
+
+/// The self-test of one drive's cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelfTest {
+    pub drive: char,
+    pub passed: bool,
+    /// When it ran (UNIX timestamp)
+    pub ran_at: u64,
+    pub checks: Vec<CanaryCheck>,
+}
+
+/// Run the canary queries on a cache; `system_drive` adds the Windows files
+pub fn self_test(
+    drive: char,
+    files: &HashMap<u64, FileEntry>,
+    name_index: &HashMap<String, Vec<u64>>,
+    path_index: &HashMap<String, u64>,
+    system_drive: bool,
+) -> SelfTest {
+    let mut checks = vec![CanaryCheck::new(
+        "cache has entries".to_string(),
+        files.is_empty().then(|| "the cache is empty".to_string()),
+    )];
+
+    // The same entries every run, so a failure can be reproduced
+    let mut sample: Vec<&FileEntry> = files.values().filter(|file| !file.is_directory).collect();
+    sample.sort_unstable_by_key(|file| file.id);
+    for file in sample.into_iter().take(SAMPLE_SIZE) {
+        let by_name = name_index.get(&file.name.to_lowercase()).map_or(false, |ids| ids.contains(&file.id));
+        let by_path = path_index.get(&file.path) == Some(&file.id);
+        let failure = match (by_name, by_path) {
+            (true, true) => None,
+            (false, _) => Some("not in the name index".to_string()),
+            (true, false) => Some("not in the path index".to_string()),
+        };
+        checks.push(CanaryCheck::new(format!("lookup of {}", file.path), failure));
+    }
+
+    if system_drive {
+        for (folder, name) in SYSTEM_CANARIES {
+            let expected = format!("{}\\{}", folder, name);
+            let found = name_index.get(*name).map_or(false, |ids| {
+                ids.iter().filter_map(|id| files.get(id)).any(|file| file.path.eq_ignore_ascii_case(&expected))
+            });
+            checks.push(CanaryCheck::new(
+                format!("search for {}", expected),
+                (!found).then(|| "not found, though every Windows install has it".to_string()),
+            ));
+        }
+    }
+
+    let ran_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    SelfTest { drive, passed: checks.iter().all(|check| check.passed), ran_at, checks }
+}
+
+/// Keep a drive's result for the health checks, replacing an earlier one
+pub fn record_self_test(test: SelfTest) {
+    RESULTS.lock().insert(test.drive, test);
+}
+
+/// Whether a tested drive failed its self-test
+pub fn self_test_failed() -> bool {
+    RESULTS.lock().values().any(|test| !test.passed)
+}
+
+/// Results of every tested drive, for the health checks
+pub fn self_test_status() -> Value {
+    json!(*RESULTS.lock())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, path: &str) -> FileEntry {
+        FileEntry { size: 1, ..FileEntry::test(id, path) }
+    }
+
+    fn indexes(files: &HashMap<u64, FileEntry>) -> (HashMap<String, Vec<u64>>, HashMap<String, u64>) {
+        let mut names: HashMap<String, Vec<u64>> = HashMap::new();
+        let mut paths = HashMap::new();
+        for file in files.values() {
+            names.entry(file.name.to_lowercase()).or_default().push(file.id);
+            paths.insert(file.path.clone(), file.id);
+        }
+        (names, paths)
+    }
+
+    #[test]
+    fn test_canaries() {
+        let files: HashMap<u64, FileEntry> = [
+            entry(10, "Windows\\explorer.exe"),
+            entry(11, "Windows\\System32\\kernel32.dll"),
+            entry(12, "Windows\\System32\\notepad.exe"),
+            entry(13, "Windows\\System32\\drivers\\etc\\hosts"),
+        ].into_iter().map(|file| (file.id, file)).collect();
+        let (names, mut paths) = indexes(&files);
+        assert!(self_test('C', &files, &names, &paths, true).passed);
+
+        // Only the system drive is expected to hold Windows
+        let data: HashMap<u64, FileEntry> = [(20, entry(20, "Photos\\beach.jpg"))].into_iter().collect();
+        let (data_names, data_paths) = indexes(&data);
+        assert!(self_test('D', &data, &data_names, &data_paths, false).passed);
+        assert!(!self_test('D', &data, &data_names, &data_paths, true).passed);
+
+        paths.remove("Windows\\explorer.exe");
+        let broken = self_test('C', &files, &names, &paths, true);
+        assert!(!broken.passed);
+        let failed: Vec<&str> = broken.checks.iter().filter(|check| !check.passed).map(|check| check.check.as_str()).collect();
+        assert_eq!(failed, vec!["lookup of Windows\\explorer.exe"]);
+
+        let empty = self_test('E', &HashMap::new(), &HashMap::new(), &HashMap::new(), false);
+        assert_eq!(empty.checks[0].detail.as_deref(), Some("the cache is empty"));
+    }
+}
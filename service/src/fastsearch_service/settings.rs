@@ -0,0 +1,161 @@
+//! Settings bundles for `export_settings` / `import_settings`
+//!
+//! A bundle is a single JSON document holding everything a user configures
+//! (saved searches, report jobs, preloaded and excluded drives) so a setup can
+//! be moved between machines or kept in a dotfiles repository.
+//!
+//! The service runs elevated, so bundle files are only read from and written
+//! to the `settings` folder of its data directory; anything else has to pass
+//! the bundle inline.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::drive_config::DriveSelection;
+use super::json_store::named_file;
+use super::reports::ReportJob;
+use super::saved_searches::SavedSearch;
+
+/// Current bundle format version
+pub const SETTINGS_BUNDLE_VERSION: u32 = 1;
+
+/// Folder of the data directory that bundle files live in
+pub const SETTINGS_DIR: &str = "settings";
+
+/// Location of the bundle file `name` in the settings folder of `data_dir`
+///
+/// `name` must be a plain file name: no folders, drive or stream.
+pub fn bundle_path(data_dir: &Path, name: &str) -> Result<PathBuf> {
+    named_file(data_dir, SETTINGS_DIR, name)
+}
+
+/// Portable snapshot of the service configuration
+///
+/// Every section defaults to empty so bundles from older versions (or
+/// hand-written ones) only need to contain the sections they care about.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    /// Bundle format version
+    pub version: u32,
+    /// When the bundle was exported (UNIX timestamp)
+    #[serde(default)]
+    pub exported: i64,
+    /// Saved searches, including their watched flag
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    /// Scheduled report jobs
+    #[serde(default)]
+    pub report_jobs: Vec<ReportJob>,
+    /// Drives preloaded at startup and drives excluded from indexing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drives: Option<DriveSelection>,
+}
+
+impl SettingsBundle {
+    /// Create a bundle stamped with the current time
+    pub fn new(saved_searches: Vec<SavedSearch>, report_jobs: Vec<ReportJob>, drives: DriveSelection) -> Self {
+        Self {
+            version: SETTINGS_BUNDLE_VERSION,
+            exported: Utc::now().timestamp(),
+            saved_searches,
+            report_jobs,
+            drives: Some(drives),
+        }
+    }
+
+    /// Parse and validate a bundle from JSON
+    pub fn from_value(value: serde_json::Value) -> Result<Self> {
+        let bundle: SettingsBundle = serde_json::from_value(value).context("Invalid settings bundle")?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    /// Read and validate a bundle file
+    pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings bundle {}", path.display()))?;
+        Self::from_value(serde_json::from_str(&data).context("Settings bundle is not valid JSON")?)
+    }
+
+    /// Write the bundle as pretty-printed JSON
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).context("Failed to create settings directory")?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write settings bundle {}", path.display()))
+    }
+
+    /// Reject bundles from newer versions and entries that can't be imported
+    fn validate(&self) -> Result<()> {
+        if self.version == 0 || self.version > SETTINGS_BUNDLE_VERSION {
+            bail!(
+                "Unsupported settings bundle version {} (this service supports up to {})",
+                self.version, SETTINGS_BUNDLE_VERSION
+            );
+        }
+        if let Some(search) = self.saved_searches.iter().find(|s| s.name.trim().is_empty()) {
+            bail!("Saved search with arguments {} has no name", search.arguments);
+        }
+        if self.report_jobs.iter().any(|job| job.name.trim().is_empty()) {
+            bail!("Report job without a name");
+        }
+        if let Some(drives) = &self.drives {
+            drives.clone().normalized().context("Invalid drive config")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fastsearch-settings.json");
+
+        let bundle = SettingsBundle::new(
+            vec![SavedSearch::new("logs", json!({"pattern": "*.log"}), true)],
+            serde_json::from_value(json!([{"name": "usage", "kind": "disk_usage"}])).unwrap(),
+            DriveSelection { preload: vec!['D'], excluded: vec!['E'] },
+        );
+        bundle.write_to(&path).unwrap();
+
+        let read = SettingsBundle::read_from(&path).unwrap();
+        assert_eq!(read.saved_searches[0].name, "logs");
+        assert!(read.saved_searches[0].watched);
+        assert_eq!(read.report_jobs[0].name, "usage");
+        assert_eq!(read.drives.unwrap().excluded, vec!['E']);
+    }
+
+    #[test]
+    fn test_bundle_path_stays_in_settings_folder() {
+        let data = Path::new("data");
+        assert_eq!(bundle_path(data, "laptop.json").unwrap(), data.join(SETTINGS_DIR).join("laptop.json"));
+        for name in ["", "..", "../drives.json", "sub/laptop.json", "sub\\laptop.json", "C:laptop.json", "laptop.json:stream"] {
+            assert!(bundle_path(data, name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_bundle_validation() {
+        assert!(SettingsBundle::from_value(json!({"version": 1})).is_ok());
+        assert!(SettingsBundle::from_value(json!({"version": 99})).is_err());
+        assert!(SettingsBundle::from_value(json!({"saved_searches": []})).is_err());
+        assert!(SettingsBundle::from_value(json!({"version": 1, "drives": {"preload": ["D"], "excluded": ["d"]}})).is_err());
+        assert!(SettingsBundle::from_value(json!({
+            "version": 1,
+            "saved_searches": [{"name": " ", "arguments": {}, "created": 0}]
+        })).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! First-run setup for `setup_wizard`
+//!
+//! Getting started used to mean reading the installation notes: install the
+//! Windows service from an elevated prompt, start it, decide which drives to
+//! index, then wait for the first search to build a cache. The wizard checks
+//! where a machine is in that sequence and does the next step when asked,
+//! raising a UAC prompt for the steps that need administrator rights.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::drive_config::DriveSelection;
+
+/// Name the Windows service is installed under
+pub const SERVICE_NAME: &str = "FastSearchService";
+
+/// File name of the service executable
+const SERVICE_EXE: &str = "fastsearch-service.exe";
+
+/// State of the Windows service
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ServiceCheck {
+    pub installed: bool,
+    pub running: bool,
+    /// Service state as reported by the service manager
+    pub state: Option<String>,
+    /// Whether this process can install and start services itself
+    pub elevated: bool,
+}
+
+/// Steps of the setup, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupStep {
+    /// Install the Windows service (needs elevation)
+    Install,
+    /// Start the installed service
+    Start,
+    /// Choose drives to index at startup and drives to leave alone
+    Drives,
+    /// Build the caches of the chosen drives
+    Index,
+    /// Nothing left to do
+    Done,
+}
+
+impl SetupStep {
+    /// Name of the step, as passed in `step`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SetupStep::Install => "install",
+            SetupStep::Start => "start",
+            SetupStep::Drives => "drives",
+            SetupStep::Index => "index",
+            SetupStep::Done => "done",
+        }
+    }
+
+    /// Parse the `step` tool argument
+    pub fn parse(s: &str) -> Option<Self> {
+        [SetupStep::Install, SetupStep::Start, SetupStep::Drives, SetupStep::Index, SetupStep::Done]
+            .into_iter()
+            .find(|step| step.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// What the step does, for the checklist
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SetupStep::Install => "Install the FastSearch Windows service (asks for administrator rights)",
+            SetupStep::Start => "Start the FastSearch service",
+            SetupStep::Drives => "Choose the drives indexed at startup and the drives never indexed",
+            SetupStep::Index => "Build the caches of the chosen drives",
+            SetupStep::Done => "Setup is complete; search with fast_search",
+        }
+    }
+}
+
+/// First step that still needs doing
+///
+/// In container mode (`service` is None) there is no Windows service to set up.
+pub fn next_step(service: Option<&ServiceCheck>, drives: &DriveSelection, cached: &[char]) -> SetupStep {
+    match service {
+        Some(service) if !service.installed => SetupStep::Install,
+        Some(service) if !service.running => SetupStep::Start,
+        _ if drives.preload.is_empty() && drives.excluded.is_empty() => SetupStep::Drives,
+        _ if drives.preload.iter().any(|drive| !cached.contains(drive)) => SetupStep::Index,
+        _ => SetupStep::Done,
+    }
+}
+
+/// Path of the service executable: this one, or the one installed next to it
+pub fn service_binary() -> Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    if exe.file_name().map_or(false, |name| name.eq_ignore_ascii_case(SERVICE_EXE)) {
+        return Ok(exe);
+    }
+    Ok(exe.with_file_name(SERVICE_EXE))
+}
+
+pub use os::{install_service, is_elevated, service_check, start_service};
+
+#[cfg(windows)]
+mod os {
+    use std::ffi::OsStr;
+    use std::mem::{size_of, zeroed};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr::null_mut;
+
+    use anyhow::{bail, Context, Result};
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetCurrentProcess, GetExitCodeProcess, OpenProcessToken};
+    use winapi::um::securitybaseapi::GetTokenInformation;
+    use winapi::um::shellapi::{ShellExecuteExW, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW};
+    use winapi::um::synchapi::WaitForSingleObject;
+    use winapi::um::winbase::INFINITE;
+    use winapi::um::winnt::{TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+    use windows_service::service::{ServiceAccess, ServiceState};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+    use super::{service_binary, ServiceCheck, SERVICE_NAME};
+
+    /// Whether the service is installed and running
+    pub fn service_check() -> ServiceCheck {
+        let mut check = ServiceCheck { elevated: is_elevated(), ..Default::default() };
+        let Ok(manager) = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT) else {
+            return check;
+        };
+        if let Ok(service) = manager.open_service(SERVICE_NAME, ServiceAccess::QUERY_STATUS) {
+            check.installed = true;
+            if let Ok(status) = service.query_status() {
+                check.running = status.current_state == ServiceState::Running;
+                check.state = Some(format!("{:?}", status.current_state));
+            }
+        }
+        check
+    }
+
+    /// Whether the process runs with an elevated token
+    pub fn is_elevated() -> bool {
+        unsafe {
+            let mut token = null_mut();
+            if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+                return false;
+            }
+            let mut elevation: TOKEN_ELEVATION = zeroed();
+            let mut returned: DWORD = 0;
+            let ok = GetTokenInformation(
+                token,
+                TokenElevation,
+                &mut elevation as *mut _ as *mut _,
+                size_of::<TOKEN_ELEVATION>() as DWORD,
+                &mut returned,
+            );
+            CloseHandle(token);
+            ok != 0 && elevation.TokenIsElevated != 0
+        }
+    }
+
+    /// Install (and start) the service by running `fastsearch-service install` elevated
+    pub fn install_service() -> Result<()> {
+        let binary = service_binary()?;
+        if !binary.exists() {
+            bail!("{} not found; reinstall FastSearch or run the installer", binary.display());
+        }
+        run_elevated(&binary, "install")
+    }
+
+    /// Start the installed service, asking for elevation if this process can't
+    pub fn start_service() -> Result<()> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+        match manager.open_service(SERVICE_NAME, ServiceAccess::START) {
+            Ok(service) => Ok(service.start::<&str>(&[])?),
+            Err(_) => run_elevated(Path::new("sc.exe"), &format!("start {}", SERVICE_NAME)),
+        }
+    }
+
+    /// Run a program through a UAC prompt and wait for it to finish
+    fn run_elevated(program: &Path, args: &str) -> Result<()> {
+        let wide = |s: &OsStr| -> Vec<u16> { s.encode_wide().chain(Some(0)).collect() };
+        let verb = wide(OsStr::new("runas"));
+        let file = wide(program.as_os_str());
+        let params = wide(OsStr::new(args));
+
+        unsafe {
+            let mut info: SHELLEXECUTEINFOW = zeroed();
+            info.cbSize = size_of::<SHELLEXECUTEINFOW>() as DWORD;
+            info.fMask = SEE_MASK_NOCLOSEPROCESS;
+            info.lpVerb = verb.as_ptr();
+            info.lpFile = file.as_ptr();
+            info.lpParameters = params.as_ptr();
+            info.nShow = 0;
+            if ShellExecuteExW(&mut info) == 0 {
+                // Also what declining the UAC prompt looks like
+                return Err(std::io::Error::last_os_error())
+                    .with_context(|| format!("Failed to run {} elevated", program.display()));
+            }
+            WaitForSingleObject(info.hProcess, INFINITE);
+            let mut code: DWORD = 0;
+            GetExitCodeProcess(info.hProcess, &mut code);
+            CloseHandle(info.hProcess);
+            if code != 0 {
+                bail!("{} {} failed with exit code {}", program.display(), args, code);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(windows))]
+mod os {
+    use anyhow::{bail, Result};
+
+    use super::ServiceCheck;
+
+    pub fn service_check() -> ServiceCheck {
+        ServiceCheck::default()
+    }
+
+    pub fn is_elevated() -> bool {
+        false
+    }
+
+    pub fn install_service() -> Result<()> {
+        bail!("The FastSearch service can only be installed on Windows")
+    }
+
+    pub fn start_service() -> Result<()> {
+        bail!("The FastSearch service can only be started on Windows")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(installed: bool, running: bool) -> ServiceCheck {
+        ServiceCheck { installed, running, ..Default::default() }
+    }
+
+    #[test]
+    fn test_steps_follow_setup_order() {
+        let unset = DriveSelection::default();
+        let chosen = DriveSelection { preload: vec!['C', 'D'], excluded: vec![] };
+
+        assert_eq!(next_step(Some(&service(false, false)), &chosen, &[]), SetupStep::Install);
+        assert_eq!(next_step(Some(&service(true, false)), &chosen, &[]), SetupStep::Start);
+        assert_eq!(next_step(Some(&service(true, true)), &unset, &[]), SetupStep::Drives);
+        assert_eq!(next_step(Some(&service(true, true)), &chosen, &['C']), SetupStep::Index);
+        assert_eq!(next_step(Some(&service(true, true)), &chosen, &['C', 'D']), SetupStep::Done);
+
+        // Containers have no service, and excluding drives is a choice too
+        let excluded_only = DriveSelection { preload: vec![], excluded: vec!['E'] };
+        assert_eq!(next_step(None, &excluded_only, &[]), SetupStep::Done);
+    }
+
+    #[test]
+    fn test_parse_step() {
+        assert_eq!(SetupStep::parse(" Index "), Some(SetupStep::Index));
+        assert_eq!(SetupStep::parse("reboot"), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! Names within an edit distance of a given one, for `find_similar`
+//!
+//! Copies made by hand rarely keep their name exactly: `report_final.docx`
+//! turns up again as `report_fnial.docx` or `report_final_v2.docx`. The name
+//! index holds every distinct name once, so each is compared with the wanted
+//! name using a bounded Levenshtein distance and only close names are looked up.
+
+use super::hints::distance_within;
+
+/// Edits allowed when `max_distance` isn't given
+pub const DEFAULT_MAX_DISTANCE: usize = 3;
+
+/// Largest `max_distance` accepted; beyond it nearly every short name is "similar"
+pub const MAX_DISTANCE_LIMIT: usize = 16;
+
+/// A name without its extension (dot-files like `.gitignore` keep theirs)
+pub fn stem(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+/// Names (lowercase index keys) within `max` edits of `target`, closest first
+///
+/// With `ignore_extension` names are compared without their extensions, so
+/// `notes.txt` and `notes.md` count as the same name.
+pub fn names_within<'a>(
+    target: &str,
+    names: impl Iterator<Item = &'a String>,
+    max: usize,
+    ignore_extension: bool,
+) -> Vec<(&'a String, usize)> {
+    let target = target.to_lowercase();
+    let compared = |name: &'a str| if ignore_extension { stem(name) } else { name };
+    let target: Vec<char> = if ignore_extension { stem(&target) } else { target.as_str() }.chars().collect();
+
+    let mut found: Vec<(&String, usize)> = names
+        .filter_map(|name| distance_within(&target, compared(name.as_str()), max).map(|distance| (name, distance)))
+        .collect();
+    found.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_names_within() {
+        let index = names(&["report_final.docx", "report_fnial.docx", "report_final_v2.docx", "budget.xlsx"]);
+
+        let found = names_within("Report_Final.docx", index.iter(), 3, false);
+        let found: Vec<(&str, usize)> = found.into_iter().map(|(name, d)| (name.as_str(), d)).collect();
+        assert_eq!(found, vec![("report_final.docx", 0), ("report_fnial.docx", 2), ("report_final_v2.docx", 3)]);
+
+        assert_eq!(names_within("report_final.docx", index.iter(), 1, false).len(), 1);
+    }
+
+    #[test]
+    fn test_ignore_extension() {
+        let index = names(&["notes.md", ".gitignore", "notes"]);
+        let found = names_within("notes.txt", index.iter(), 0, true);
+        let found: Vec<&str> = found.into_iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(found, vec!["notes", "notes.md"]);
+        assert_eq!(stem(".gitignore"), ".gitignore");
+    }
+}
@@ -0,0 +1,224 @@
+//! File size histograms for `size_distribution`
+//!
+//! Whether a disk is full of many small files or a few huge ones shows in how
+//! files and bytes spread over size buckets. The buckets grow tenfold (under
+//! 1 KB, 1-10 KB, ... 10 GB and up), so both ends of the range stay visible,
+//! and empty files get a bucket of their own. Everything comes from the sizes
+//! in the MFT cache.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::file_types::document_type_of;
+use super::mft_cache::FileEntry;
+use super::reports::scoped_remainder;
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * KB;
+const GB: u64 = 1024 * MB;
+
+/// Lower bounds of the buckets after the empty one, with their labels
+const BUCKETS: [(u64, &str); 10] = [
+    (1, "under 1 KB"),
+    (KB, "1-10 KB"),
+    (10 * KB, "10-100 KB"),
+    (100 * KB, "100 KB-1 MB"),
+    (MB, "1-10 MB"),
+    (10 * MB, "10-100 MB"),
+    (100 * MB, "100 MB-1 GB"),
+    (GB, "1-10 GB"),
+    (10 * GB, "10 GB and up"),
+    (u64::MAX, ""),
+];
+
+/// Files under this size count as small in the summary
+const SMALL_FILE: u64 = 100 * KB;
+
+/// Files of this size and up count as large in the summary
+const LARGE_FILE: u64 = 100 * MB;
+
+/// Files and bytes in one size range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizeBucket {
+    pub label: &'static str,
+    /// Smallest size in the bucket
+    pub min: u64,
+    /// Size the next bucket starts at (none for the last)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<u64>,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Files counted by size bucket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizeHistogram {
+    pub files: u64,
+    pub bytes: u64,
+    pub buckets: Vec<SizeBucket>,
+}
+
+impl Default for SizeHistogram {
+    fn default() -> Self {
+        let mut buckets = vec![SizeBucket { label: "empty", min: 0, max: Some(1), files: 0, bytes: 0 }];
+        buckets.extend(BUCKETS.windows(2).map(|pair| SizeBucket {
+            label: pair[0].1,
+            min: pair[0].0,
+            max: (pair[1].0 != u64::MAX).then_some(pair[1].0),
+            files: 0,
+            bytes: 0,
+        }));
+        Self { files: 0, bytes: 0, buckets }
+    }
+}
+
+impl SizeHistogram {
+    /// Count a file of the given size
+    pub fn add(&mut self, size: u64) {
+        let bucket = self.buckets.iter().rposition(|bucket| bucket.min <= size).unwrap_or(0);
+        self.buckets[bucket].files += 1;
+        self.buckets[bucket].bytes += size;
+        self.files += 1;
+        self.bytes += size;
+    }
+
+    /// Files and bytes in the buckets entirely below `size`, or from `size` up
+    fn split_at(&self, size: u64) -> ((u64, u64), (u64, u64)) {
+        let (below, from): (Vec<_>, Vec<_>) = self.buckets.iter().partition(|bucket| bucket.min < size);
+        let total = |buckets: Vec<&SizeBucket>| buckets.iter().fold((0, 0), |(f, b), bucket| (f + bucket.files, b + bucket.bytes));
+        (total(below), total(from))
+    }
+
+    /// One sentence on whether the space goes to many small files or a few large ones
+    pub fn summary(&self) -> String {
+        if self.files == 0 {
+            return "No files".to_string();
+        }
+        let percent = |part: u64, whole: u64| if whole == 0 { 0.0 } else { part as f64 * 100.0 / whole as f64 };
+        let ((small_files, small_bytes), _) = self.split_at(SMALL_FILE);
+        let (_, (large_files, large_bytes)) = self.split_at(LARGE_FILE);
+        format!(
+            "{:.0}% of the files are under 100 KB and hold {:.0}% of the space; {} files of 100 MB or more hold {:.0}%",
+            percent(small_files, self.files),
+            percent(small_bytes, self.bytes),
+            large_files,
+            percent(large_bytes, self.bytes),
+        )
+    }
+}
+
+/// What files are broken down by, besides the overall histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeGrouping {
+    None,
+    /// Document type of the extension (`other` for the rest)
+    DocType,
+    /// Folder directly below the root (`(files)` for the root's own files)
+    Folder,
+}
+
+impl SizeGrouping {
+    /// Parse the `group_by` tool argument
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "none" => Some(SizeGrouping::None),
+            "doc_type" => Some(SizeGrouping::DocType),
+            "folder" => Some(SizeGrouping::Folder),
+            _ => None,
+        }
+    }
+}
+
+/// Histograms of the files below a root
+#[derive(Debug, Clone, Default)]
+pub struct SizeDistribution {
+    pub overall: SizeHistogram,
+    /// Histogram per group, most bytes first
+    pub groups: Vec<(String, SizeHistogram)>,
+}
+
+/// Count the files below `root` (relative to the drive root; empty for the whole drive) that `accept` lets through
+pub fn size_distribution<'a>(
+    files: impl Iterator<Item = &'a FileEntry>,
+    root: &str,
+    grouping: SizeGrouping,
+    accept: impl Fn(&FileEntry) -> bool,
+) -> SizeDistribution {
+    let mut distribution = SizeDistribution::default();
+    let mut groups: HashMap<String, SizeHistogram> = HashMap::new();
+
+    for file in files.filter(|file| !file.is_directory) {
+        let Some(remainder) = scoped_remainder(&file.path, root) else {
+            continue;
+        };
+        if !accept(file) {
+            continue;
+        }
+        distribution.overall.add(file.size);
+
+        let group = match grouping {
+            SizeGrouping::None => continue,
+            SizeGrouping::DocType => file.extension.as_deref()
+                .and_then(document_type_of)
+                .map_or("other", |doc_type| doc_type.as_str())
+                .to_string(),
+            SizeGrouping::Folder => match remainder.split_once('\\') {
+                Some((folder, _)) => folder.to_string(),
+                None => "(files)".to_string(),
+            },
+        };
+        groups.entry(group).or_default().add(file.size);
+    }
+
+    distribution.groups = groups.into_iter().collect();
+    distribution.groups.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0)));
+    distribution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: u64, path: &str, size: u64) -> FileEntry {
+        FileEntry { size, ..FileEntry::test(id, path) }
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = SizeHistogram::default();
+        for size in [0, 1, 1023, 1024, 50 * MB, 20 * GB] {
+            histogram.add(size);
+        }
+        let counts: Vec<(&str, u64)> = histogram.buckets.iter()
+            .filter(|bucket| bucket.files > 0)
+            .map(|bucket| (bucket.label, bucket.files))
+            .collect();
+        assert_eq!(counts, vec![("empty", 1), ("under 1 KB", 2), ("1-10 KB", 1), ("10-100 MB", 1), ("10 GB and up", 1)]);
+        assert_eq!(histogram.files, 6);
+        assert_eq!(histogram.buckets.last().unwrap().max, None);
+        assert!(histogram.summary().starts_with("67% of the files are under 100 KB and hold 0% of the space; 1 files of 100 MB or more"));
+    }
+
+    #[test]
+    fn test_grouping() {
+        let files = [
+            file(1, "Users\\me\\movie.mp4", 3 * GB),
+            file(2, "Users\\me\\notes.md", 2 * KB),
+            file(3, "Users\\me\\data.bin", 10),
+            file(4, "Windows\\explorer.exe", 5 * MB),
+        ];
+
+        let by_type = size_distribution(files.iter(), "users", SizeGrouping::DocType, |_| true);
+        let names: Vec<(&str, u64)> = by_type.groups.iter().map(|(name, h)| (name.as_str(), h.files)).collect();
+        assert_eq!(names, vec![("video", 1), ("text", 1), ("other", 1)]);
+        assert_eq!(by_type.overall.files, 3);
+
+        let by_folder = size_distribution(files.iter(), "", SizeGrouping::Folder, |file| file.size > 100);
+        let names: Vec<&str> = by_folder.groups.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Users", "Windows"]);
+        assert_eq!(by_folder.overall.files, 3);
+        assert_eq!(SizeGrouping::parse("Doc_Type"), Some(SizeGrouping::DocType));
+        assert_eq!(SizeGrouping::parse("owner"), None);
+    }
+}
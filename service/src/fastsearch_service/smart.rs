@@ -0,0 +1,289 @@
+//! `search_type: "smart"`: work out how each pattern was meant
+//!
+//! Callers (and models) often don't say whether `report`, `.pdf` or
+//! `^IMG_\d+` is a glob, a regex or a fragment of a name. Smart mode looks at
+//! each pattern and picks one of four readings:
+//!
+//! - regex, when it uses regex syntax (`^`, `$`, `\d`, `[a-z]`,
+//!   `a|b`, `.+`, `{2,4}`)
+//! - glob, when it has `*` or `?`
+//! - extension, for `.pdf` alone, searched as `*.pdf`
+//! - substring, for anything else, searched as `*report*`
+//!
+//! The patterns are then rewritten for one engine: globs, unless any pattern
+//! reads as a regex, in which case every pattern is turned into a regex. The
+//! response says how each pattern was read, and `_meta.rewritten_query`
+//! holds the arguments actually searched.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::matcher::{glob_to_regex, PatternSet, SearchType};
+
+/// The `search_type` value that turns detection on
+pub const SMART_SEARCH_TYPE: &str = "smart";
+
+/// How smart patterns are read, for the syntax reference
+pub const SMART_DESCRIPTION: &str = "Each pattern is read from how it looks: regex syntax (`^IMG_\\d+`) as a regex, wildcards as a glob, `.pdf` as an extension and anything else as part of the name (`invoice` finds `2024_invoice_03.pdf`); the response's `smart` field says which";
+
+/// Longest suffix taken for an extension (`.pdf`) rather than a name fragment
+const MAX_EXTENSION_LEN: usize = 10;
+
+/// How a pattern was read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpretation {
+    Glob,
+    Regex,
+    Extension,
+    Substring,
+}
+
+impl Interpretation {
+    /// Read a single pattern
+    pub fn detect(pattern: &str) -> Self {
+        let pattern = pattern.trim();
+        if looks_like_regex(pattern) {
+            Interpretation::Regex
+        } else if pattern.contains(['*', '?']) {
+            Interpretation::Glob
+        } else if is_extension(pattern) {
+            Interpretation::Extension
+        } else {
+            Interpretation::Substring
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interpretation::Glob => "glob",
+            Interpretation::Regex => "regex",
+            Interpretation::Extension => "extension",
+            Interpretation::Substring => "substring",
+        }
+    }
+
+    /// The pattern as a glob; regexes have none
+    fn to_glob(self, pattern: &str) -> String {
+        match self {
+            Interpretation::Extension => format!("*{}", pattern),
+            Interpretation::Substring => format!("*{}*", pattern),
+            Interpretation::Glob | Interpretation::Regex => pattern.to_string(),
+        }
+    }
+
+    /// The pattern as a regex matched anywhere in the name
+    fn to_regex(self, pattern: &str) -> String {
+        match self {
+            Interpretation::Regex => pattern.to_string(),
+            Interpretation::Glob => glob_to_regex(pattern),
+            Interpretation::Extension => format!("{}$", regex::escape(pattern)),
+            Interpretation::Substring => regex::escape(pattern),
+        }
+    }
+}
+
+/// How one pattern of a smart search was read and what was searched for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SmartGuess {
+    pub pattern: String,
+    pub interpretation: Interpretation,
+    /// The pattern as searched, in the syntax of `search_type`
+    pub searched_as: String,
+    /// Whether this is a pattern to leave out
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub excluded: bool,
+}
+
+/// A smart search rewritten for one engine
+#[derive(Debug, Clone)]
+pub struct SmartSearch {
+    /// Engine the rewritten patterns are for
+    pub search_type: SearchType,
+    pub guesses: Vec<SmartGuess>,
+    /// The search arguments with the rewritten patterns and `search_type`
+    pub args: Value,
+}
+
+impl SmartSearch {
+    /// Rewrite the arguments of a smart search; `None` if `search_type` isn't "smart"
+    pub fn from_args(args: &Value) -> anyhow::Result<Option<Self>> {
+        let is_smart = args["search_type"].as_str()
+            .map_or(false, |s| s.eq_ignore_ascii_case(SMART_SEARCH_TYPE));
+        if !is_smart {
+            return Ok(None);
+        }
+        let patterns = PatternSet::from_args(args)?;
+        Ok(Some(Self::rewrite(args, &patterns)))
+    }
+
+    fn rewrite(args: &Value, patterns: &PatternSet) -> Self {
+        let read = |list: &[String], excluded: bool| -> Vec<(String, Interpretation, bool)> {
+            list.iter().map(|p| (p.clone(), Interpretation::detect(p), excluded)).collect()
+        };
+        let mut read_patterns = read(&patterns.include, false);
+        read_patterns.extend(read(&patterns.exclude, true));
+
+        let search_type = if read_patterns.iter().any(|(_, reading, _)| *reading == Interpretation::Regex) {
+            SearchType::Regex
+        } else {
+            SearchType::Glob
+        };
+        let guesses: Vec<SmartGuess> = read_patterns.into_iter()
+            .map(|(pattern, interpretation, excluded)| {
+                let trimmed = pattern.trim();
+                let searched_as = match search_type {
+                    SearchType::Regex => interpretation.to_regex(trimmed),
+                    _ => interpretation.to_glob(trimmed),
+                };
+                SmartGuess { pattern, interpretation, searched_as, excluded }
+            })
+            .collect();
+
+        let mut rewritten = args.clone();
+        rewritten["search_type"] = json!(search_type.as_str());
+        rewritten["pattern"] = json!(guesses.iter().filter(|g| !g.excluded).map(|g| &g.searched_as).collect::<Vec<_>>());
+        let excluded: Vec<&String> = guesses.iter().filter(|g| g.excluded).map(|g| &g.searched_as).collect();
+        rewritten["not_pattern"] = if excluded.is_empty() { Value::Null } else { json!(excluded) };
+        Self { search_type, guesses, args: rewritten }
+    }
+
+    /// The arguments to retry as a fuzzy search when nothing matched
+    ///
+    /// Only when every pattern was plain name text (a substring or an
+    /// extension), retried as the caller wrote it: fuzzy matching has no
+    /// wildcards, so `*report*` would look for the asterisks.
+    pub fn fuzzy_retry_args(&self) -> Option<Value> {
+        let include: Vec<&SmartGuess> = self.guesses.iter().filter(|g| !g.excluded).collect();
+        let plain = include.iter()
+            .all(|g| matches!(g.interpretation, Interpretation::Substring | Interpretation::Extension));
+        if !plain || self.search_type != SearchType::Glob {
+            return None;
+        }
+        let mut args = self.args.clone();
+        args["pattern"] = json!(include.iter().map(|g| g.pattern.trim()).collect::<Vec<_>>());
+        Some(args)
+    }
+
+    /// One line per pattern: `'report' as substring (*report*)`
+    pub fn describe(&self) -> String {
+        self.guesses.iter()
+            .map(|guess| format!(
+                "{}'{}' as {} ({})",
+                if guess.excluded { "not " } else { "" },
+                guess.pattern, guess.interpretation.as_str(), guess.searched_as
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The `smart` metadata added to responses
+    pub fn to_json(&self) -> Value {
+        json!({
+            "search_type": self.search_type.as_str(),
+            "patterns": self.guesses,
+        })
+    }
+}
+
+/// Whether a pattern uses syntax only a regex has
+fn looks_like_regex(pattern: &str) -> bool {
+    if pattern.starts_with('^') || (pattern.ends_with('$') && !pattern.ends_with("\\$")) {
+        return true;
+    }
+    let chars: Vec<char> = pattern.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        let next = chars.get(i + 1).copied();
+        match c {
+            // Escapes and classes: \d, \w, \., ...
+            '\\' if next.map_or(false, |n| "dDwWsSbB.()[]{}+*?|^$\\".contains(n)) => return true,
+            // Never in a file name, unlike parentheses and brackets alone
+            '|' => return true,
+            // Classes: [a-z], [^0-9]
+            '[' if is_class(&chars[i + 1..]) => return true,
+            // Repeats: .+, ]+, )+, \d{2}
+            '+' if i > 0 && matches!(chars[i - 1], '.' | ']' | ')') => return true,
+            '{' if next.map_or(false, |n| n.is_ascii_digit()) && chars[i..].contains(&'}') => return true,
+            // `.*` in the middle reads as "anything"; `name.*` and `*.*` are globs
+            '.' if next == Some('*') && i + 2 < chars.len() && (i == 0 || chars[i - 1] != '*') => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Whether the text after a `[` is a character class rather than part of a name
+fn is_class(rest: &[char]) -> bool {
+    match rest.iter().position(|c| *c == ']') {
+        Some(end) if end > 0 => rest[0] == '^' || rest[..end].windows(3).any(|w| w[1] == '-' && w[0] < w[2]),
+        _ => false,
+    }
+}
+
+/// Whether a pattern is an extension alone: `.pdf`
+fn is_extension(pattern: &str) -> bool {
+    match pattern.strip_prefix('.') {
+        Some(ext) => !ext.is_empty()
+            && ext.len() <= MAX_EXTENSION_LEN
+            && ext.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection() {
+        assert_eq!(Interpretation::detect("*.rs"), Interpretation::Glob);
+        assert_eq!(Interpretation::detect("README?"), Interpretation::Glob);
+        assert_eq!(Interpretation::detect("report.*"), Interpretation::Glob);
+        assert_eq!(Interpretation::detect(r"^IMG_\d+\.jpg$"), Interpretation::Regex);
+        assert_eq!(Interpretation::detect("(draft|final)"), Interpretation::Regex);
+        assert_eq!(Interpretation::detect("log.*2024"), Interpretation::Regex);
+        assert_eq!(Interpretation::detect("[0-9]{4}"), Interpretation::Regex);
+        assert_eq!(Interpretation::detect(".pdf"), Interpretation::Extension);
+        assert_eq!(Interpretation::detect("report"), Interpretation::Substring);
+        assert_eq!(Interpretation::detect("Cargo.toml"), Interpretation::Substring);
+        assert_eq!(Interpretation::detect("c++"), Interpretation::Substring);
+        assert_eq!(Interpretation::detect("report (1)"), Interpretation::Substring);
+        assert_eq!(Interpretation::detect("[draft] notes"), Interpretation::Substring);
+    }
+
+    #[test]
+    fn test_globs_unless_a_regex_is_given() {
+        let smart = SmartSearch::from_args(&json!({"pattern": ["report", ".pdf"], "search_type": "smart"}))
+            .unwrap().unwrap();
+        assert_eq!(smart.search_type, SearchType::Glob);
+        assert_eq!(smart.args["pattern"], json!(["*report*", "*.pdf"]));
+        assert_eq!(smart.args["search_type"], "glob");
+
+        let smart = SmartSearch::from_args(&json!({"pattern": [r"^IMG_\d+", ".jpg"], "not_pattern": "*.tmp", "search_type": "Smart"}))
+            .unwrap().unwrap();
+        assert_eq!(smart.search_type, SearchType::Regex);
+        assert_eq!(smart.args["pattern"], json!([r"^IMG_\d+", r"\.jpg$"]));
+        assert_eq!(smart.args["not_pattern"], json!([r"^.*(\.tmp)$"]));
+        assert!(smart.describe().contains("not '*.tmp' as glob"));
+    }
+
+    #[test]
+    fn test_fuzzy_retry_of_plain_names() {
+        let smart = SmartSearch::from_args(&json!({"pattern": ["reprot", ".pdf"], "not_pattern": "draft", "search_type": "smart"}))
+            .unwrap().unwrap();
+        let retry = smart.fuzzy_retry_args().unwrap();
+        assert_eq!(retry["pattern"], json!(["reprot", ".pdf"]));
+        assert_eq!(retry["not_pattern"], json!(["*draft*"]));
+
+        let globbed = SmartSearch::from_args(&json!({"pattern": ["reprot", "*.pdf"], "search_type": "smart"})).unwrap().unwrap();
+        assert!(globbed.fuzzy_retry_args().is_none());
+        let regex = SmartSearch::from_args(&json!({"pattern": r"^IMG_\d+", "search_type": "smart"})).unwrap().unwrap();
+        assert!(regex.fuzzy_retry_args().is_none());
+    }
+
+    #[test]
+    fn test_other_search_types_are_left_alone() {
+        assert!(SmartSearch::from_args(&json!({"pattern": "report", "search_type": "glob"})).unwrap().is_none());
+        assert!(SmartSearch::from_args(&json!({"pattern": "report"})).unwrap().is_none());
+    }
+}
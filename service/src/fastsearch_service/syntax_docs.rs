@@ -0,0 +1,147 @@
+//! Search syntax reference served as an MCP resource and on `/api/docs`
+//!
+//! The reference is generated on every request from the tool schemas the
+//! server advertises, the search types and the query fields the parser
+//! accepts, so a new argument or field shows up in it without anyone having
+//! to remember the docs.
+
+use serde_json::Value;
+
+use super::matcher::SearchType;
+use super::query::QUERY_FIELDS;
+use super::smart::{SMART_DESCRIPTION, SMART_SEARCH_TYPE};
+
+/// URI of the syntax reference resource
+pub const SYNTAX_DOCS_URI: &str = "fastsearch://docs/syntax";
+
+/// Example `fast_search` calls: what they find, and their arguments
+const EXAMPLES: &[(&str, &str)] = &[
+    ("Rust sources and manifests", r#"{"pattern": ["*.rs", "Cargo.toml"]}"#),
+    ("Test modules anywhere below a src folder", r#"{"pattern": "src/**/test_*.{rs,py}"}"#),
+    ("Every Cargo.toml below a Projects folder, by whole path", r#"{"pattern": "*\\Projects\\*\\Cargo.toml", "match_path": true}"#),
+    ("Logs except installer logs", r#"{"pattern": "*.log", "not_pattern": "*install*"}"#),
+    ("Python test modules", r#"{"pattern": "^test_.*\\.py$", "search_type": "regex"}"#),
+    ("A half-remembered name", r#"{"pattern": "srchengn", "search_type": "fuzzy"}"#),
+    ("A controller by its initials", r#"{"pattern": "FBC", "search_type": "camel", "extensions": ["cs"]}"#),
+    ("Names containing invoice, as a substring", r#"{"pattern": "invoice", "search_type": "smart"}"#),
+    ("Large logs under Windows", r#"{"pattern": "*", "query": "ext:log AND path:Windows size:>10MB"}"#),
+    ("Folders named node_modules", r#"{"pattern": "*", "query": "type:dir name:node_modules"}"#),
+];
+
+/// Resource listing entry for the syntax reference
+pub fn syntax_docs_resource() -> Value {
+    serde_json::json!({
+        "uri": SYNTAX_DOCS_URI,
+        "name": "search-syntax",
+        "description": "Search types, query language, fast_search arguments and examples",
+        "mimeType": "text/markdown"
+    })
+}
+
+/// Render the syntax reference as Markdown from the `tools/list` tool definitions
+pub fn syntax_reference(tools: &[Value]) -> String {
+    let mut text = String::from("# FastSearch search syntax\n\n");
+    text.push_str("Generated from the tool definitions of this server.\n");
+
+    text.push_str("\n## Search types (`search_type`)\n\n");
+    for search_type in SearchType::ALL {
+        let default = if search_type == SearchType::default() { " (default)" } else { "" };
+        text.push_str(&format!("- `{}`{}: {}\n", search_type.as_str(), default, search_type.description()));
+    }
+    text.push_str(&format!("- `{}`: {}\n", SMART_SEARCH_TYPE, SMART_DESCRIPTION));
+
+    text.push_str("\n## Query language (`query`)\n\n");
+    text.push_str("Terms are joined with `AND`, `OR` and `NOT` (upper case); adjacent terms are ANDed. ");
+    text.push_str("Parentheses group terms and double quotes allow spaces. A term without a field matches names as a glob.\n\n");
+    text.push_str("| Field | Matches | Example |\n|---|---|---|\n");
+    for field in QUERY_FIELDS {
+        text.push_str(&format!("| `{}:` | {} | `{}` |\n", field.name, field.description, field.example));
+    }
+
+    text.push_str("\n## Examples\n\n");
+    for (what, args) in EXAMPLES {
+        text.push_str(&format!("- {}: `{}`\n", what, args));
+    }
+
+    let search = tools.iter().find(|tool| tool["name"] == "fast_search").map(|tool| &tool["inputSchema"]);
+    if let Some(properties) = search.and_then(|schema| schema["properties"].as_object()) {
+        let required = search.map_or(&Value::Null, |schema| &schema["required"]);
+        text.push_str("\n## `fast_search` arguments\n\n");
+        for (name, schema) in properties {
+            let is_required = required.as_array().map_or(false, |names| names.iter().any(|n| n == name));
+            text.push_str(&format!("- `{}` ({}): {}\n", name, describe_schema(schema, is_required), schema["description"].as_str().unwrap_or("")));
+        }
+    }
+
+    text.push_str("\n## Other tools\n\n");
+    for tool in tools.iter().filter(|tool| tool["name"] != "fast_search") {
+        text.push_str(&format!("- `{}`: {}\n", tool["name"].as_str().unwrap_or(""), tool["description"].as_str().unwrap_or("")));
+    }
+    text
+}
+
+/// Type, default and allowed values of an argument, e.g. `string, default "glob", one of glob, regex`
+fn describe_schema(schema: &Value, required: bool) -> String {
+    let mut parts = vec![match &schema["type"] {
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" or "),
+        other => other.as_str().unwrap_or("any").to_string(),
+    }];
+    if required {
+        parts.push("required".to_string());
+    }
+    if !schema["default"].is_null() {
+        parts.push(format!("default {}", schema["default"]));
+    }
+    if let Some(values) = schema["enum"].as_array() {
+        let values: Vec<String> = values.iter().map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string)).collect();
+        parts.push(format!("one of {}", values.join(", ")));
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::matcher::PatternSet;
+    use super::super::query::QueryExpr;
+    use serde_json::json;
+
+    #[test]
+    fn test_examples_are_valid() {
+        for (what, args) in EXAMPLES {
+            let args: Value = serde_json::from_str(args).unwrap();
+            assert!(PatternSet::from_args(&args).is_ok(), "{}", what);
+            if let Some(search_type) = args["search_type"].as_str() {
+                assert!(SearchType::parse(search_type).is_some() || search_type == SMART_SEARCH_TYPE, "{}", what);
+            }
+            if let Some(query) = args["query"].as_str() {
+                assert!(QueryExpr::parse(query).is_ok(), "{}", what);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reference_follows_schema() {
+        let tools = vec![
+            json!({
+                "name": "fast_search",
+                "description": "Search",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "pattern": {"type": ["string", "array"], "description": "What to find"},
+                        "search_type": {"type": "string", "enum": ["glob", "regex"], "default": "glob", "description": "How"}
+                    },
+                    "required": ["pattern"]
+                }
+            }),
+            json!({"name": "find_hardlinks", "description": "List hard links"}),
+        ];
+        let text = syntax_reference(&tools);
+        assert!(text.contains("- `pattern` (string or array, required): What to find"));
+        assert!(text.contains("- `search_type` (string, default \"glob\", one of glob, regex): How"));
+        assert!(text.contains("- `find_hardlinks`: List hard links"));
+        assert!(text.contains("| `size:` |"));
+        assert!(text.contains("- `fuzzy`: "));
+    }
+}
@@ -0,0 +1,1288 @@
+//! Input schemas of the MCP tools, as listed by `tools/list`
+//!
+//! One function per tool, in the order the tools are listed. Each schema is
+//! its own `json!` literal, small enough for the macro's default recursion
+//! limit; the arguments of `fast_search` are split into groups by what they do.
+
+use serde_json::{json, Map, Value};
+
+use super::dir_sizes::{DEFAULT_SIZE_DEPTH, MAX_SIZE_DEPTH};
+use super::duplicates::DEFAULT_HASH_THREADS;
+use super::projection::RESULT_FIELDS;
+use super::similar::{DEFAULT_MAX_DISTANCE, MAX_DISTANCE_LIMIT};
+
+/// Schemas of all the tools, including those this build was made without
+pub fn tool_schemas() -> Vec<Value> {
+    vec![
+        list_ntfs_drives(),
+        fast_search(),
+        find_large_files(),
+        directory_sizes(),
+        size_distribution(),
+        activity_heatmap(),
+        repo_inventory(),
+        security_scan(),
+        list_ads(),
+        get_file_info(),
+        find_hardlinks(),
+        resolve_file_id(),
+        find_duplicates(),
+        find_similar(),
+        benchmark_search(),
+        save_search(),
+        list_saved_searches(),
+        run_saved_search(),
+        watch_saved_search(),
+        run_report(),
+        export_settings(),
+        export_index(),
+        import_settings(),
+        configure_drives(),
+        setup_wizard(),
+        maintenance_mode(),
+        denied_paths(),
+        memory_profile(),
+        delete_saved_search(),
+        bookmark_result(),
+        bookmark_results(),
+        list_collections(),
+        get_collection(),
+        delete_collection(),
+    ]
+}
+
+/// Groups of properties, each its own `json!` object, as one object
+fn merged<const N: usize>(groups: [Value; N]) -> Value {
+    let mut properties = Map::new();
+    for group in groups {
+        if let Value::Object(group) = group {
+            properties.extend(group);
+        }
+    }
+    Value::Object(properties)
+}
+
+fn list_ntfs_drives() -> Value {
+    json!({
+        "name": "list_ntfs_drives",
+        "description": "List all available NTFS drives on the system, marking the drives excluded from indexing and from drive \"*\" searches (see configure_drives)",
+        "inputSchema": {
+            "type": "object",
+            "properties": {}
+        }
+    })
+}
+
+fn fast_search() -> Value {
+    json!({
+        "name": "fast_search",
+        "description": "Lightning-fast DIRECT file search using NTFS Master File Table (no indexing)",
+        "inputSchema": {
+            "type": "object",
+            "properties": merged([
+                fast_search_scope(),
+                fast_search_paging(),
+                fast_search_filters(),
+                fast_search_checks(),
+            ])
+        }
+    })
+}
+
+/// What `fast_search` looks for and where
+fn fast_search_scope() -> Value {
+    json!({
+        "pattern": {
+            "type": ["string", "array"],
+            "items": {"type": "string"},
+            "description": "File pattern to search for (*.js, README*, config.*, IMG_[0-9]*.{jpg,png}, etc.), or an array of patterns to match any of (e.g. [\"*.rs\", \"*.toml\"]) in one pass. A glob with a / or \\ is matched against the path from the drive root, with ** for any number of folders (src/**/test_*.{rs,py})"
+        },
+        "not_pattern": {
+            "type": ["string", "array"],
+            "items": {"type": "string"},
+            "description": "Pattern(s) names must not match, e.g. \"*install*.log\" with pattern \"*.log\". A pattern starting with '!' in pattern does the same"
+        },
+        "query": {
+            "type": "string",
+            "description": "Boolean query combining name, ext:, path:, size: and type: terms with AND, OR, NOT and parentheses (e.g. \"*.log AND path:Windows NOT ext:tmp\"). Applied in addition to pattern"
+        },
+        "search_type": {
+            "type": "string",
+            "description": "How to interpret the pattern: 'glob' wildcards, 'regex' (matched anywhere in the name), 'exact' name, 'fuzzy' (ranked by match_score), 'camel' word starts for code (\"FBC\" finds FooBarController.cs, \"mft_cache\" finds MftCache.rs), or 'smart' to read each pattern as a regex, glob, extension (\".pdf\") or name substring from how it looks (reported in the response's smart field)",
+            "enum": ["glob", "regex", "exact", "fuzzy", "camel", "smart"],
+            "default": "glob"
+        },
+        "case_sensitive": {
+            "type": "boolean",
+            "description": "Match the case of glob, regex and exact patterns (fuzzy patterns always ignore case)",
+            "default": false
+        },
+        "ignore_diacritics": {
+            "type": "boolean",
+            "description": "Match names regardless of accents and compatibility forms, so \"resume\" finds \"résumé.pdf\" (ignores case; can't be combined with case_sensitive)",
+            "default": false
+        },
+        "match_path": {
+            "type": "boolean",
+            "description": "Match pattern and not_pattern against the whole path from the drive root instead of the name, with * spanning folders: \"*\\\\Projects\\\\*\\\\Cargo.toml\" finds every Cargo.toml below a Projects folder (not with fuzzy search)",
+            "default": false
+        },
+        "path": {
+            "type": "string",
+            "description": "Optional path to search within (e.g., \"src/\" or \"C:\\Windows\"), matched as a substring; with * or ? it is a glob over folders (* and ? within one folder, ** across any number), anchored at the drive root when it starts with one: \"C:\\Users\\*\\Downloads\""
+        },
+        "max_depth": {
+            "type": "integer",
+            "description": "Only return entries up to this many folder levels below the path (1 = direct children), or below the drive root without a path",
+            "minimum": 1
+        },
+        "drive": {
+            "type": "string",
+            "description": "Drive letter to search (e.g., 'C'). Use '*' to search all NTFS drives; each volume is searched once even if it has several drive letters, and results list the other paths they're reachable under as aliases.",
+            "default": "C"
+        },
+        "workspace_hint": {
+            "type": "string",
+            "description": "Folder of the project you're working in (e.g. \"D:\\src\\myapp\"). Matches inside it are listed first and marked in_workspace; the rest of the drive follows."
+        }
+    })
+}
+
+/// How many `fast_search` results come back, and how
+fn fast_search_paging() -> Value {
+    json!({
+        "max_results": {
+            "type": "integer",
+            "description": "Maximum number of results to return (default: 1000)",
+            "default": 1000
+        },
+        "cursor": {
+            "type": "string",
+            "description": "next_cursor from a previous call with the same arguments, to get the next page of results"
+        },
+        "within_results_of": {
+            "type": "string",
+            "description": "result_set of an earlier search (returned with its first page): search only its matches, to narrow a search step by step without scanning the drive again. Sets are dropped after 30 minutes unused"
+        },
+        "timeout_ms": {
+            "type": "integer",
+            "description": "Stop scanning after this many milliseconds and return the matches found so far, flagged truncated: true (no cursor or result_set then). Default: no limit (30000 for regex searches)"
+        },
+        "summarize_broad": {
+            "type": "boolean",
+            "description": "When the search would match a large share of the drive (e.g. '*'), return an estimated breakdown by extension and folder instead of an arbitrary first page. Set to false to page through the matches anyway",
+            "default": true
+        },
+        "confirm_token": {
+            "type": "string",
+            "description": "Token of a confirmation_required answer, to run a regex, fuzzy or all-drive search on drives that have no cache yet"
+        },
+        "output": {
+            "type": "string",
+            "enum": ["flat", "grouped", "tree"],
+            "description": "Layout of the page: 'flat' lists results in rank order under 'results', 'grouped' lists them per parent folder under 'groups', 'tree' nests them by folder under 'tree'. Folders come in the order of their best-ranked match",
+            "default": "flat"
+        },
+        "fields": {
+            "type": "array",
+            "items": {"type": "string", "enum": RESULT_FIELDS},
+            "description": "Only these fields of each structured result (e.g. [\"path\"]), to keep large pages small. The text then leaves out the per-result listing"
+        }
+    })
+}
+
+/// Filters `fast_search` answers from the cache
+fn fast_search_filters() -> Value {
+    json!({
+        "type": {
+            "type": "string",
+            "description": "Type filter: 'file', 'directory', or 'any' (default)",
+            "enum": ["file", "directory", "any"],
+            "default": "any"
+        },
+        "doc_type": {
+            "type": "string",
+            "description": "Document type filter (e.g., 'text', 'code', 'image', 'pdf')",
+            "default": ""
+        },
+        "extensions": {
+            "type": "array",
+            "items": {
+                "type": "string"
+            },
+            "description": "File extensions to include (without leading .), overrides doc_type if both are specified"
+        },
+        "exclude": {
+            "type": "array",
+            "items": {
+                "type": "string"
+            },
+            "description": "Globs for paths to leave out, relative to the drive root (e.g. \"node_modules/**\", \"*.tmp\", \"/Windows/Temp\"). Names without a slash match at any depth; excluding a folder excludes everything in it"
+        },
+        "respect_gitignore": {
+            "type": "boolean",
+            "description": "Leave out matches inside git repositories that their .gitignore/.ignore files ignore (and the .git folder itself)",
+            "default": false
+        },
+        "hidden": {
+            "type": "boolean",
+            "description": "true: only hidden entries; false: leave hidden entries out"
+        },
+        "system": {
+            "type": "boolean",
+            "description": "true: only system entries; false: leave system entries out"
+        },
+        "readonly": {
+            "type": "boolean",
+            "description": "true: only read-only entries; false: leave read-only entries out"
+        },
+        "compressed": {
+            "type": "boolean",
+            "description": "true: only NTFS-compressed entries; false: leave them out"
+        },
+        "encrypted": {
+            "type": "boolean",
+            "description": "true: only EFS-encrypted entries; false: leave them out"
+        },
+        "sparse": {
+            "type": "boolean",
+            "description": "true: only sparse files; false: leave them out"
+        },
+        "owner": {
+            "type": "string",
+            "description": "Only entries owned by this account: a user name, DOMAIN\\user, or SID (S-1-5-...). Owners are read from disk for matches of the other filters, so combine with narrowing filters on large drives"
+        },
+        "min_size": {
+            "type": ["string", "integer"],
+            "description": "Minimum file size in bytes or as a string like \"10MB\" (excludes directories)"
+        },
+        "max_size": {
+            "type": ["string", "integer"],
+            "description": "Maximum file size in bytes or as a string like \"1.5GB\" (excludes directories)"
+        },
+        "modified_after": {
+            "type": ["string", "integer"],
+            "description": "Only include entries modified on or after this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\")"
+        },
+        "modified_before": {
+            "type": ["string", "integer"],
+            "description": "Only include entries modified before this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\")"
+        },
+        "created_after": {
+            "type": ["string", "integer"],
+            "description": "Only include entries created on or after this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\")"
+        },
+        "created_before": {
+            "type": ["string", "integer"],
+            "description": "Only include entries created before this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\")"
+        },
+        "accessed_after": {
+            "type": ["string", "integer"],
+            "description": "Only include entries last accessed on or after this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\")"
+        },
+        "accessed_before": {
+            "type": ["string", "integer"],
+            "description": "Only include entries last accessed before this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\")"
+        }
+    })
+}
+
+/// Disk checks and extra details of `fast_search` results, and the filters on them
+fn fast_search_checks() -> Value {
+    json!({
+        "verify_exists": {
+            "type": "boolean",
+            "description": "Check that matches still exist on disk before returning them (only the returned page is checked)",
+            "default": false
+        },
+        "auto_fuzzy": {
+            "type": "boolean",
+            "description": "When a pattern without wildcards matches nothing, retry it as a fuzzy search and return those results labeled 'did you mean'. Defaults to true when search_type isn't given or is 'smart'"
+        },
+        "reparse_points": {
+            "type": "string",
+            "enum": ["follow", "skip", "only"],
+            "description": "Symlinks, junctions and cloud placeholders: 'follow' returns them and searches a path through a link at its target, 'skip' leaves them out, 'only' returns nothing else",
+            "default": "follow"
+        },
+        "include_streams": {
+            "type": "boolean",
+            "description": "List the alternate data streams (e.g. Zone.Identifier) of returned files as extra results after each file. Read from the MFT, so it needs admin rights",
+            "default": false
+        },
+        "include_image_info": {
+            "type": "boolean",
+            "description": "Add the dimensions and EXIF capture date of image results (jpg, png, gif, bmp, webp, tiff, heic, avif). Read from the file's header the first time and cached",
+            "default": false
+        },
+        "taken_after": {
+            "type": ["string", "integer"],
+            "description": "Only images whose EXIF capture date is on or after this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\"). Images without one are left out"
+        },
+        "taken_before": {
+            "type": ["string", "integer"],
+            "description": "Only images whose EXIF capture date is before this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\"). Images without one are left out"
+        },
+        "min_megapixels": {
+            "type": "number",
+            "description": "Only images of at least this many megapixels (width × height / 1,000,000), e.g. 12"
+        },
+        "verify_content_type": {
+            "type": "boolean",
+            "description": "Read the first bytes of matching files and drop those that aren't what their extension claims (e.g. a renamed executable named .pdf). Files of formats without a signature, such as text, pass. Adds the detected mime_type to results",
+            "default": false
+        },
+        "content_kind": {
+            "type": "string",
+            "enum": ["text", "binary"],
+            "description": "Only text files or only binary ones, by extension; files whose extension doesn't tell (Makefile, .dat) only pass with sniff_content_kind. Every result carries its content_kind when known"
+        },
+        "sniff_content_kind": {
+            "type": "boolean",
+            "description": "Read the first bytes of files whose extension doesn't tell whether they are text (a NUL byte or many control characters make them binary)",
+            "default": false
+        },
+        "max_per_directory": {
+            "type": "integer",
+            "description": "At most this many results from any one folder on a page, so one folder with thousands of matches can't fill it. Folders that had more are listed under capped_folders",
+            "minimum": 1
+        },
+        "include_doc_stats": {
+            "type": "boolean",
+            "description": "Add page and word counts of document results: PDF, Word and PowerPoint (.docx/.pptx, slides as pages), OpenDocument, and words of plain text. Read the first time and cached",
+            "default": false
+        },
+        "min_pages": {
+            "type": "integer",
+            "description": "Only documents with at least this many pages (or slides), e.g. 50",
+            "minimum": 0
+        },
+        "max_pages": {
+            "type": "integer",
+            "description": "Only documents with at most this many pages (or slides)",
+            "minimum": 0
+        },
+        "min_words": {
+            "type": "integer",
+            "description": "Only documents with at least this many words",
+            "minimum": 0
+        },
+        "max_words": {
+            "type": "integer",
+            "description": "Only documents with at most this many words",
+            "minimum": 0
+        },
+        "include_media_info": {
+            "type": "boolean",
+            "description": "Add the duration, bitrate and codec of audio and video results. Read from the file's headers the first time and cached",
+            "default": false
+        },
+        "min_duration": {
+            "type": ["string", "integer"],
+            "description": "Only audio and video playing at least this long: seconds, or a duration like \"30m\" or \"1h 30m\". Combine with doc_type \"video\" or \"audio\""
+        },
+        "max_duration": {
+            "type": ["string", "integer"],
+            "description": "Only audio and video playing at most this long: seconds, or a duration like \"3m\""
+        }
+    })
+}
+
+fn find_large_files() -> Value {
+    json!({
+        "name": "find_large_files",
+        "description": "Find large files by direct MFT scan",
+        "inputSchema": {
+            "type": "object", 
+            "properties": {
+                "min_size_mb": {
+                    "type": "integer",
+                    "description": "Minimum file size in MB",
+                    "default": 100
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter to search",
+                    "default": "C"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of results",
+                    "default": 50
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": {
+                        "type": "string"
+                    },
+                    "description": "Globs for paths to skip (e.g. \"node_modules/**\"); excluded folders aren't scanned"
+                },
+                "max_depth": {
+                    "type": "integer",
+                    "description": "Only scan this many folder levels below the drive root",
+                    "minimum": 1
+                }
+            }
+        }
+    })
+}
+
+fn directory_sizes() -> Value {
+    json!({
+        "name": "directory_sizes",
+        "description": "Total size of the folders below a folder, like du: file sizes from the MFT cache are added up the folder tree, and the largest folders are listed first",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Folder to break down, optionally with its drive (e.g. \"D:\\Projects\"). Default: the drive root"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter when path doesn't include one (default C)"
+                },
+                "max_depth": {
+                    "type": "integer",
+                    "description": "Folder levels below the path to list (1 = its direct subfolders); deeper folders count toward their ancestor at that level",
+                    "default": DEFAULT_SIZE_DEPTH,
+                    "minimum": 1,
+                    "maximum": MAX_SIZE_DEPTH
+                },
+                "exclude": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string"},
+                    "description": "Globs for paths to leave out of the totals (same syntax as fast_search)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of folders to return",
+                    "default": 20
+                }
+            }
+        }
+    })
+}
+
+fn size_distribution() -> Value {
+    json!({
+        "name": "size_distribution",
+        "description": "Histogram of file sizes below a folder (empty, under 1 KB, 1-10 KB, ... 10 GB and up), overall or per document type or subfolder, to tell whether space goes to many small files or a few huge ones",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Folder to look at, optionally with its drive (e.g. \"D:\\Projects\"). Default: the drive root"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter when path doesn't include one (default C)"
+                },
+                "group_by": {
+                    "type": "string",
+                    "enum": ["none", "doc_type", "folder"],
+                    "description": "Also break the sizes down per document type or per folder directly below the path",
+                    "default": "none"
+                },
+                "max_groups": {
+                    "type": "integer",
+                    "description": "Maximum number of groups to return, most bytes first",
+                    "default": 10
+                },
+                "doc_type": {
+                    "type": "string",
+                    "description": "Only count files of this document type (same values as fast_search)"
+                },
+                "extensions": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Only count files with these extensions"
+                },
+                "exclude": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string"},
+                    "description": "Globs for paths to leave out (same syntax as fast_search)"
+                }
+            }
+        }
+    })
+}
+
+fn activity_heatmap() -> Value {
+    json!({
+        "name": "activity_heatmap",
+        "description": "When the files below a folder changed: files counted per day, week or month and on a weekday by hour heatmap, from the timestamps in the MFT cache. Useful for seeing when a project was actively worked on",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Folder to look at, optionally with its drive (e.g. \"D:\\Projects\\app\"). Default: the drive root"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter when path doesn't include one (default C)"
+                },
+                "field": {
+                    "type": "string",
+                    "enum": ["modified", "created", "accessed"],
+                    "description": "Timestamp to count",
+                    "default": "modified"
+                },
+                "period": {
+                    "type": "string",
+                    "enum": ["day", "week", "month"],
+                    "description": "Length of the periods files are counted in (weeks start on Monday)",
+                    "default": "day"
+                },
+                "timezone": {
+                    "type": "string",
+                    "enum": ["local", "utc"],
+                    "description": "Clock the days and hours are read on",
+                    "default": "local"
+                },
+                "max_periods": {
+                    "type": "integer",
+                    "description": "Maximum number of periods to return, most recent first",
+                    "default": 31
+                },
+                "modified_after": {
+                    "type": ["string", "integer"],
+                    "description": "Only count files modified on or after this date (YYYY-MM-DD, RFC 3339, UNIX timestamp, or relative like \"last 7 days\" or \"-2h\"); created_/accessed_after/_before work the same"
+                },
+                "modified_before": {
+                    "type": ["string", "integer"],
+                    "description": "Only count files modified before this date"
+                },
+                "extensions": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Only count files with these extensions"
+                },
+                "exclude": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string"},
+                    "description": "Globs for paths to leave out (same syntax as fast_search)"
+                }
+            }
+        }
+    })
+}
+
+fn repo_inventory() -> Value {
+    json!({
+        "name": "repo_inventory",
+        "description": "For every project (git repository, Cargo, npm, Python, ... root) below a folder: whether it has a LICENSE, a README and CI configuration, and its dominant language. One call instead of a search per repository when auditing many of them",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Folder to look below, optionally with its drive (e.g. \"D:\\Projects\"). Default: the drive root"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter when path doesn't include one (default C)"
+                },
+                "missing": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string", "enum": ["license", "readme", "ci"]},
+                    "description": "Only list projects lacking any of these"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of projects to return",
+                    "default": 100
+                },
+                "exclude": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string"},
+                    "description": "Globs for paths to leave out (same syntax as fast_search)"
+                }
+            }
+        }
+    })
+}
+
+fn security_scan() -> Value {
+    json!({
+        "name": "security_scan",
+        "description": "Look for risky files left below a folder: private keys, .env files, cloud and Git credentials, crypto wallets, unattended install answer files with passwords, password databases and shell history. Findings are grouped by category and ranked critical, high, medium or low",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Folder to scan, optionally with its drive (e.g. \"C:\\Users\"). Default: the drive root"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter when path doesn't include one (default C)"
+                },
+                "check_contents": {
+                    "type": "boolean",
+                    "description": "Read the start of files whose kind shows in their contents (PEM keys, answer files) to confirm them; unconfirmed ones are reported as low",
+                    "default": true
+                },
+                "min_severity": {
+                    "type": "string",
+                    "enum": ["critical", "high", "medium", "low"],
+                    "description": "Leave out findings below this severity",
+                    "default": "low"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of findings to return, worst first",
+                    "default": 100
+                },
+                "exclude": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string"},
+                    "description": "Globs for paths to leave out (same syntax as fast_search)"
+                }
+            }
+        }
+    })
+}
+
+fn list_ads() -> Value {
+    json!({
+        "name": "list_ads",
+        "description": "List alternate data streams (named $DATA streams such as Zone.Identifier) of a file, or of everything below a folder, read from the MFT",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File or folder relative to the drive root (e.g. \"Users\\me\\Downloads\"), or with its drive (\"D:\\tools\")"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter, when path doesn't include one (default C)"
+                },
+                "recursive": {
+                    "type": "boolean",
+                    "description": "For folders, also check everything below them",
+                    "default": true
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of streams to return",
+                    "default": 1000
+                }
+            },
+            "required": ["path"]
+        }
+    })
+}
+
+fn get_file_info() -> Value {
+    json!({
+        "name": "get_file_info",
+        "description": "Show everything known about one file or folder: its full record in the MFT cache next to a live look at the file on disk (timestamps, attributes, size and size on disk, hard link count, file ID), with the fields where the two disagree. For inspecting a search result without shell access",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File or folder relative to the drive root, or with its drive (\"D:\\tools\\app.exe\")"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter, when path doesn't include one (default C)"
+                }
+            },
+            "required": ["path"]
+        }
+    })
+}
+
+fn find_hardlinks() -> Value {
+    json!({
+        "name": "find_hardlinks",
+        "description": "List every path of a file that has several hard links, or all groups of hard-linked files on a drive. Hard links are one file under several names, unlike copies",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File whose links to list, relative to the drive root or with its drive (\"D:\\tools\\app.exe\"). Leave out to list all hard-linked files on the drive"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter, when path doesn't include one (default C)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of groups to return when listing a drive",
+                    "default": 100
+                }
+            }
+        }
+    })
+}
+
+fn resolve_file_id() -> Value {
+    json!({
+        "name": "resolve_file_id",
+        "description": "Find the current path of files given by their MFT file reference, as found in USN journal records or forensic tool output. Takes 64-bit references (decimal or 0x hex, sequence number in the high 16 bits) or record-sequence pairs like \"1234-5\", answered from the MFT cache",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "file_id": {
+                    "description": "File reference, or an array of up to 1000 of them",
+                    "oneOf": [
+                        {"type": ["integer", "string"]},
+                        {"type": "array", "items": {"type": ["integer", "string"]}}
+                    ]
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter of the volume the references are from (default C)"
+                }
+            },
+            "required": ["file_id"]
+        }
+    })
+}
+
+fn find_duplicates() -> Value {
+    json!({
+        "name": "find_duplicates",
+        "description": "Find files with identical contents. Files are grouped by size from the MFT cache, and only files sharing a size are read and hashed (XXH3) to confirm. Spinning disks are read one file at a time to avoid seeking. Reports progress when given a progress token and can be cancelled, returning the groups confirmed so far. Groups are returned by wasted space, largest first",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter to check (default C)"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Only check files below this folder"
+                },
+                "min_size": {
+                    "type": ["integer", "string"],
+                    "description": "Smallest file to check, as bytes or a size like \"100KB\". Smaller files are many and rarely worth it",
+                    "default": "1MB"
+                },
+                "max_size": {
+                    "type": ["integer", "string"],
+                    "description": "Largest file to check, as bytes or a size like \"4GB\""
+                },
+                "extensions": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Only check files with these extensions (e.g. [\"jpg\", \"png\"])"
+                },
+                "exclude": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string"},
+                    "description": "Folders to skip (same syntax as fast_search)"
+                },
+                "threads": {
+                    "type": "integer",
+                    "description": "Files read at once from a solid-state disk (spinning disks are always read one file at a time)",
+                    "default": DEFAULT_HASH_THREADS,
+                    "minimum": 1
+                },
+                "confirm_token": {
+                    "type": "string",
+                    "description": "Token of a confirmation_required answer, to run a scan that reads the MFT first or hashes more than 20 GB"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of groups to return",
+                    "default": 50
+                }
+            }
+        }
+    })
+}
+
+fn find_similar() -> Value {
+    json!({
+        "name": "find_similar",
+        "description": "Find files whose names are within a few edits (Levenshtein distance) of a name, e.g. typo'd or renamed copies like 'report_fnial.docx' or 'report_final_v2.docx'. Closest names first",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "File name to compare against (case is ignored)"
+                },
+                "max_distance": {
+                    "type": "integer",
+                    "description": "Most character insertions, deletions and substitutions a similar name may differ by",
+                    "default": DEFAULT_MAX_DISTANCE,
+                    "minimum": 0,
+                    "maximum": MAX_DISTANCE_LIMIT
+                },
+                "ignore_extension": {
+                    "type": "boolean",
+                    "description": "Compare names without their extensions, so 'notes.txt' also finds 'notes.md'",
+                    "default": false
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter to search (default C)"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Only look below this folder"
+                },
+                "extensions": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Only return files with these extensions"
+                },
+                "exclude": {
+                    "type": ["string", "array"],
+                    "items": {"type": "string"},
+                    "description": "Folders to skip (same syntax as fast_search)"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of files to return",
+                    "default": 50
+                }
+            },
+            "required": ["name"]
+        }
+    })
+}
+
+fn benchmark_search() -> Value {
+    json!({
+        "name": "benchmark_search",
+        "description": "Benchmark direct search performance",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter to benchmark",
+                    "default": "C"
+                }
+            }
+        }
+    })
+}
+
+fn save_search() -> Value {
+    json!({
+        "name": "save_search",
+        "description": "Save fast_search arguments under a name, optionally watching for new matches",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Name of the saved search"
+                },
+                "search": {
+                    "type": "object",
+                    "description": "fast_search arguments (pattern, search_type, path, drive, ...)"
+                },
+                "watched": {
+                    "type": "boolean",
+                    "description": "Send a notification whenever a new file starts matching",
+                    "default": false
+                }
+            },
+            "required": ["name", "search"]
+        }
+    })
+}
+
+fn list_saved_searches() -> Value {
+    json!({
+        "name": "list_saved_searches",
+        "description": "List saved searches and whether they are watched",
+        "inputSchema": {
+            "type": "object",
+            "properties": {}
+        }
+    })
+}
+
+fn run_saved_search() -> Value {
+    json!({
+        "name": "run_saved_search",
+        "description": "Run a saved search",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Name of the saved search"
+                }
+            },
+            "required": ["name"]
+        }
+    })
+}
+
+fn watch_saved_search() -> Value {
+    json!({
+        "name": "watch_saved_search",
+        "description": "Start or stop watching a saved search; new matches are sent as notifications/message",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Name of the saved search"
+                },
+                "watched": {
+                    "type": "boolean",
+                    "description": "Whether to watch the search",
+                    "default": true
+                }
+            },
+            "required": ["name"]
+        }
+    })
+}
+
+fn run_report() -> Value {
+    json!({
+        "name": "run_report",
+        "description": "Get the latest result of a scheduled report job (disk usage, duplicates, stale files), running it now if needed",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Name of the report job from reports.json"
+                },
+                "refresh": {
+                    "type": "boolean",
+                    "description": "Run the report now instead of returning the last scheduled result",
+                    "default": false
+                }
+            },
+            "required": ["name"]
+        }
+    })
+}
+
+fn export_settings() -> Value {
+    json!({
+        "name": "export_settings",
+        "description": "Export saved searches, report jobs and the drive config (preloaded and excluded drives) as a single JSON settings bundle",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Optional file name to write the bundle to, in the settings folder of the service's data directory (it is always returned as well)"
+                }
+            }
+        }
+    })
+}
+
+fn export_index() -> Value {
+    json!({
+        "name": "export_index",
+        "description": "Write a drive's cached file index to a SQLite database for ad-hoc SQL: tables files, directories and extensions plus a meta table (schema in the tool result). A snapshot of the cache; reports progress when given a progress token and can be cancelled",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File name of the database to write in the exports folder of the service's data directory, e.g. c.sqlite"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter (default C)"
+                },
+                "format": {
+                    "type": "string",
+                    "enum": ["sqlite"],
+                    "default": "sqlite"
+                },
+                "overwrite": {
+                    "type": "boolean",
+                    "description": "Replace the file if it exists",
+                    "default": false
+                }
+            },
+            "required": ["path"]
+        }
+    })
+}
+
+fn import_settings() -> Value {
+    json!({
+        "name": "import_settings",
+        "description": "Import a settings bundle produced by export_settings: saved searches, report jobs and the drive config. Imported report jobs write their output to the reports folder of the service's data directory",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "settings": {
+                    "type": "object",
+                    "description": "The settings bundle object"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "File name of a bundle in the settings folder of the service's data directory (used if settings is not given)"
+                },
+                "replace": {
+                    "type": "boolean",
+                    "description": "Replace existing settings instead of merging by name",
+                    "default": false
+                }
+            }
+        }
+    })
+}
+
+fn configure_drives() -> Value {
+    json!({
+        "name": "configure_drives",
+        "description": "Show or change which drives are indexed when the service starts and which are never indexed. Without arguments, returns the current configuration",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "preload": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Drive letters whose caches are built at service start, replacing the current list"
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Drive letters that are never indexed or searched, replacing the current list. Caches already built for them are dropped"
+                },
+                "build_now": {
+                    "type": "boolean",
+                    "description": "Also build the caches of preloaded drives now instead of at the next start or first search",
+                    "default": false
+                }
+            }
+        }
+    })
+}
+
+fn setup_wizard() -> Value {
+    json!({
+        "name": "setup_wizard",
+        "description": "Guided first-run setup. Without a step, checks whether the FastSearch service is installed and running, which drives are chosen for indexing and which caches are built, and says what to do next. With a step, does it: install or start the service (asks for administrator rights), choose drives, or build the chosen drives' caches with progress",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "step": {
+                    "type": "string",
+                    "enum": ["install", "start", "drives", "index"],
+                    "description": "Step to carry out; leave out to see where setup stands"
+                },
+                "preload": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "For the drives step: drive letters to index at startup"
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "For the drives step: drive letters never to index (backup or removable disks)"
+                }
+            }
+        }
+    })
+}
+
+fn maintenance_mode() -> Value {
+    json!({
+        "name": "maintenance_mode",
+        "description": "Put a drive's cache into read-only maintenance for a backup or benchmark: searches keep answering from the cache, while USN journal reading, auto-saves and refreshes wait until maintenance ends. Changes made meanwhile are picked up afterwards. Only drives with a cache can be put into maintenance",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["start", "stop", "status"],
+                    "description": "Begin maintenance, end it, or show which drives are in it",
+                    "default": "status"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter, or \"*\" for every cached drive",
+                    "default": "*"
+                },
+                "minutes": {
+                    "type": "integer",
+                    "description": "For start: end maintenance by itself after this many minutes"
+                },
+                "reason": {
+                    "type": "string",
+                    "description": "For start: why the drive is in maintenance, shown in the status"
+                }
+            }
+        }
+    })
+}
+
+fn denied_paths() -> Value {
+    json!({
+        "name": "denied_paths",
+        "description": "Folders that walked drives and mounted directories couldn't read. A folder denied on several walks in a row is left out of walks for a while and retried later, waiting twice as long each time it is still denied. Clearing makes the next walk try them all again",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "drive": {
+                    "type": "string",
+                    "description": "Only this drive (default: all)"
+                },
+                "clear": {
+                    "type": "boolean",
+                    "description": "Forget the listed folders so the next walk retries them",
+                    "default": false
+                }
+            }
+        }
+    })
+}
+
+fn memory_profile() -> Value {
+    json!({
+        "name": "memory_profile",
+        "description": "Diagnostics for tuning max_memory_usage and the memory limit: how much memory each part of the service holds (every drive's cache entries and indexes, project indexes, USN journal buffers, kept result sets), added up from its own data structures, against the budget max_memory_usage allows. Walks every cache, so it takes a moment on large drives",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "drive": {
+                    "type": "string",
+                    "description": "Only this drive (default: every drive and the shared subsystems)"
+                }
+            }
+        }
+    })
+}
+
+fn delete_saved_search() -> Value {
+    json!({
+        "name": "delete_saved_search",
+        "description": "Delete a saved search",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Name of the saved search"
+                }
+            },
+            "required": ["name"]
+        }
+    })
+}
+
+fn bookmark_result() -> Value {
+    json!({
+        "name": "bookmark_result",
+        "description": "Bookmark one file or folder into a named collection (created if needed), to come back to it in later steps",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "collection": {
+                    "type": "string",
+                    "description": "Name of the collection"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Path of the file, relative to the drive root or with its drive (\"D:\\docs\\a.pdf\")"
+                },
+                "drive": {
+                    "type": "string",
+                    "description": "Drive letter, when the path doesn't name one (default C)"
+                },
+                "note": {
+                    "type": "string",
+                    "description": "Why it was kept"
+                }
+            },
+            "required": ["collection", "path"]
+        }
+    })
+}
+
+fn bookmark_results() -> Value {
+    json!({
+        "name": "bookmark_results",
+        "description": "Bookmark a whole result set into a named collection: either results as returned by fast_search, or fast_search arguments to run and keep the page of",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "collection": {
+                    "type": "string",
+                    "description": "Name of the collection"
+                },
+                "results": {
+                    "type": "array",
+                    "items": {"type": "object"},
+                    "description": "Structured results (each with path and drive) to bookmark"
+                },
+                "search": {
+                    "type": "object",
+                    "description": "fast_search arguments; the results of running them are bookmarked"
+                },
+                "note": {
+                    "type": "string",
+                    "description": "Note stored with every bookmark"
+                },
+                "description": {
+                    "type": "string",
+                    "description": "Description of the collection"
+                }
+            },
+            "required": ["collection"]
+        }
+    })
+}
+
+fn list_collections() -> Value {
+    json!({
+        "name": "list_collections",
+        "description": "List the bookmark collections with their sizes",
+        "inputSchema": {
+            "type": "object",
+            "properties": {}
+        }
+    })
+}
+
+fn get_collection() -> Value {
+    json!({
+        "name": "get_collection",
+        "description": "Return the bookmarks of a collection",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Name of the collection"
+                }
+            },
+            "required": ["name"]
+        }
+    })
+}
+
+fn delete_collection() -> Value {
+    json!({
+        "name": "delete_collection",
+        "description": "Delete a bookmark collection",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Name of the collection"
+                }
+            },
+            "required": ["name"]
+        }
+    })
+}
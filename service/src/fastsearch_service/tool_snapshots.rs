@@ -0,0 +1,271 @@
+//! Snapshot tests of tool responses
+//!
+//! Each case calls a tool through `tools/call` against a fixture cache with
+//! fixed names, sizes and times, and compares its text and its structured
+//! result with the snapshots in `snapshots/`. A change in wording or in the
+//! shape of a result shows up as a snapshot diff in review; accept intended
+//! ones with `cargo insta review`.
+//!
+//! Timings are replaced with `[elapsed]`, and `_meta` (index modes are
+//! process-wide, so they depend on other tests) is left out; its envelope is
+//! checked on its own.
+
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use super::envelope::ENVELOPE_SCHEMA_VERSION;
+use super::filters::parse_date;
+use super::mft_cache::{file_attribute, FileEntry, MftCache};
+use super::reports::REPORTS_DIR;
+use super::search_engine::SearchEngine;
+
+/// Timings vary from run to run
+const FILTERS: &[(&str, &str)] = &[
+    (r"\d+(?:\.\d+)?\s?ms\b", "[elapsed]"),
+    (r#""(\w+_ms|elapsed\w*)": \d+(?:\.\d+)?"#, r#""$1": "[elapsed]""#),
+];
+
+/// Fixture entries: a Rust project, documents, a video and an SSH key on C:
+fn fixture_entries() -> Vec<FileEntry> {
+    let dir = |id: u64, path: &str| entry(id, path, 0, "2024-01-02");
+    vec![
+        dir(5, "Users"),
+        dir(6, "Users\\dev"),
+        dir(7, "Users\\dev\\project"),
+        entry(8, "Users\\dev\\project\\Cargo.toml", 812, "2024-03-01"),
+        entry(9, "Users\\dev\\project\\README.md", 2_048, "2024-03-02"),
+        entry(10, "Users\\dev\\project\\.env", 120, "2024-03-02"),
+        dir(11, "Users\\dev\\project\\src"),
+        entry(12, "Users\\dev\\project\\src\\main.rs", 4_096, "2024-03-05"),
+        entry(13, "Users\\dev\\project\\src\\mft_cache.rs", 65_536, "2024-03-06"),
+        entry(14, "Users\\dev\\project\\src\\MftCacheTests.rs", 16_384, "2024-03-06"),
+        dir(15, "Users\\dev\\Documents"),
+        entry(16, "Users\\dev\\Documents\\report.pdf", 3 * 1024 * 1024, "2024-02-10"),
+        entry(17, "Users\\dev\\Documents\\report-old.pdf", 3 * 1024 * 1024, "2023-11-20"),
+        entry(18, "Users\\dev\\Documents\\budget.xlsx", 48_000, "2024-02-28"),
+        dir(19, "Users\\dev\\Videos"),
+        entry(20, "Users\\dev\\Videos\\holiday.mp4", 1_610_612_736, "2023-08-14"),
+        dir(21, "Users\\dev\\.ssh"),
+        entry(22, "Users\\dev\\.ssh\\id_rsa", 1_679, "2023-05-01"),
+    ]
+}
+
+fn entry(id: u64, path: &str, size: u64, modified: &str) -> FileEntry {
+    let file = FileEntry::test(id, path);
+    let is_directory = size == 0;
+    let modified = parse_date(modified).unwrap();
+    FileEntry {
+        extension: file.extension.clone().filter(|_| !is_directory),
+        size,
+        is_directory,
+        created: modified - Duration::from_secs(86_400),
+        modified,
+        accessed: modified,
+        attributes: if is_directory { file_attribute::DIRECTORY } else { 0 },
+        ..file
+    }
+}
+
+/// An engine serving the fixture cache as C:, with its stores in a fresh directory
+fn fixture_engine() -> (SearchEngine, TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = MftCache::detached('C');
+    cache.replace_entries(fixture_entries());
+    (SearchEngine::with_fixture_caches(dir.path(), vec![cache]), dir)
+}
+
+/// Call a tool and return its response without `_meta`
+fn call(engine: &SearchEngine, tool: &str, arguments: Value) -> Value {
+    let mut response = engine.handle_request(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": tool, "arguments": arguments }
+    })).unwrap();
+    if let Some(result) = response.get_mut("result").and_then(Value::as_object_mut) {
+        result.remove("_meta");
+    }
+    response
+}
+
+/// Snapshot a response's text as `<name>_text` and the rest of it as `<name>`
+fn assert_tool_snapshot(name: &str, mut response: Value) {
+    let text = response["result"]["content"][0]["text"].as_str().unwrap_or_default().to_string();
+    if let Some(result) = response.get_mut("result").and_then(Value::as_object_mut) {
+        result.remove("content");
+    }
+    let filters: Vec<(&str, &str)> = FILTERS.to_vec();
+    insta::with_settings!({ filters => filters, prepend_module_to_snapshot => false }, {
+        insta::assert_snapshot!(format!("{}_text", name), text);
+        insta::assert_json_snapshot!(name.to_string(), response);
+    });
+}
+
+#[test]
+fn test_fast_search_snapshots() {
+    let (engine, _dir) = fixture_engine();
+    let cases = [
+        ("fast_search_glob", json!({"pattern": "*.rs", "drive": "C"})),
+        ("fast_search_regex", json!({"pattern": "^report.*\\.pdf$", "search_type": "regex", "drive": "C"})),
+        ("fast_search_camel", json!({"pattern": "MCT", "search_type": "camel", "drive": "C"})),
+        ("fast_search_filters", json!({"pattern": "*", "drive": "C", "min_size": "1MB", "modified_after": "2024-01-01"})),
+        ("fast_search_grouped", json!({"pattern": "*.*", "drive": "C", "path": "Users\\dev", "output": "grouped"})),
+        ("fast_search_fields", json!({"pattern": "*.pdf", "drive": "C", "fields": ["path", "size"]})),
+        ("fast_search_paged", json!({"pattern": "*", "drive": "C", "max_results": 3, "summarize_broad": false})),
+        ("fast_search_no_results", json!({"pattern": "reprot.pdf", "search_type": "exact", "drive": "C"})),
+        ("fast_search_invalid", json!({"pattern": "*", "drive": "C", "search_type": "telepathic"})),
+    ];
+    for (name, arguments) in cases {
+        assert_tool_snapshot(name, call(&engine, "fast_search", arguments));
+    }
+}
+
+#[test]
+fn test_analysis_tool_snapshots() {
+    let (engine, _dir) = fixture_engine();
+    // Not find_large_files: it reads the volume itself, not the fixture cache
+    let cases = [
+        ("directory_sizes", json!({"drive": "C", "path": "Users\\dev"})),
+        ("size_distribution", json!({"drive": "C"})),
+        ("find_similar", json!({"drive": "C", "name": "report.pdf"})),
+        ("security_scan", json!({"drive": "C", "path": "Users", "check_contents": false})),
+        ("resolve_file_id", json!({"drive": "C", "file_id": [12, "0x0003000000000016", "99-1"]})),
+    ];
+    for (tool, arguments) in cases {
+        assert_tool_snapshot(tool, call(&engine, tool, arguments));
+    }
+}
+
+#[test]
+fn test_configuration_tool_snapshots() {
+    let (engine, _dir) = fixture_engine();
+    assert_tool_snapshot("list_collections", call(&engine, "list_collections", json!({})));
+    assert_tool_snapshot("configure_drives", call(&engine, "configure_drives", json!({})));
+    assert_tool_snapshot("unknown_tool", call(&engine, "no_such_tool", json!({})));
+}
+
+/// The `_meta` envelope left out of the snapshots, checked field by field
+#[test]
+fn test_metadata_envelope() {
+    let (engine, _dir) = fixture_engine();
+    let arguments = json!({"pattern": "*.pdf", "drive": "C"});
+    let response = engine.handle_request(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": "fast_search", "arguments": arguments }
+    })).unwrap();
+    let meta = &response["result"]["_meta"];
+    assert_eq!(meta["schema_version"], json!(ENVELOPE_SCHEMA_VERSION));
+    assert_eq!(meta["tool"], "fast_search");
+    assert_eq!(meta["query"], arguments);
+    assert_eq!(meta["drives"][0]["drive"], "C");
+    assert_eq!(meta["drives"][0]["cache"], "hit");
+    assert!(meta["drives"][0]["cache_age_secs"].is_u64());
+    assert_eq!(meta["truncated"], false);
+    for part in ["total", "queue", "cache_build", "work"] {
+        assert!(meta["timing_ms"][part].is_u64(), "timing_ms.{} missing", part);
+    }
+    assert!(meta["index_modes"].is_object());
+}
+
+/// A smart search says in `_meta` that it searched something other than what was sent
+#[test]
+fn test_smart_rewrite_in_metadata() {
+    let (engine, _dir) = fixture_engine();
+    let meta_of = |arguments: &Value| {
+        let response = engine.handle_request(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "fast_search", "arguments": arguments }
+        })).unwrap();
+        response["result"]["_meta"].clone()
+    };
+    let arguments = json!({"pattern": "report", "search_type": "smart", "drive": "C"});
+    let meta = meta_of(&arguments);
+    assert_eq!(meta["query"], arguments);
+    assert_eq!(meta["smart_rewritten"], true);
+    assert_eq!(meta["rewritten_query"]["pattern"], json!(["*report*"]));
+    assert_eq!(meta["rewritten_query"]["search_type"], "glob");
+
+    let plain = meta_of(&json!({"pattern": "report*", "drive": "C"}));
+    assert!(plain.get("smart_rewritten").is_none());
+    assert!(plain.get("rewritten_query").is_none());
+}
+
+/// `get_file_info` on fixture entries, which aren't on disk: the record comes from the cache alone
+///
+/// Not snapshotted, as its text shows local times and the disk error depends on the system.
+#[test]
+fn test_get_file_info() {
+    let (engine, _dir) = fixture_engine();
+    let response = call(&engine, "get_file_info", json!({"path": "C:\\users\\dev\\Documents\\report.pdf"}));
+    let result = &response["result"];
+    assert_eq!(result["path"], "C:\\Users\\dev\\Documents\\report.pdf");
+    assert_eq!(result["in_cache"], true);
+    assert_eq!(result["cached"]["id"], 16);
+    assert_eq!(result["cached"]["size"], 3 * 1024 * 1024);
+    assert_eq!(result["cached"]["modified"], 1_707_523_200);
+    assert_eq!(result["cached"]["attributes"], json!([]));
+    assert_eq!(result["cached"]["link_count"], 1);
+    assert!(result["live"].is_null());
+    assert!(result["live_error"].is_string());
+    assert!(result.get("differences").is_none());
+    assert!(result["content"][0]["text"].as_str().unwrap().contains("Cache (record 16)"));
+
+    let missing = call(&engine, "get_file_info", json!({"path": "Users\\dev\\nothing.txt", "drive": "C"}));
+    assert_eq!(missing["error"]["code"], -32602);
+    let no_path = call(&engine, "get_file_info", json!({"drive": "C"}));
+    assert_eq!(no_path["error"]["code"], -32602);
+}
+
+/// Report jobs from a settings bundle only ever write into the data directory
+#[test]
+fn test_import_confines_report_output() {
+    let (engine, dir) = fixture_engine();
+    let bundle = |output: &str| json!({"settings": {"version": 1, "report_jobs": [
+        {"name": "usage", "kind": "disk_usage", "output": output}
+    ]}});
+
+    let imported = call(&engine, "import_settings", bundle("C:\\Windows\\System32\\drivers\\etc\\hosts"));
+    assert_eq!(imported["result"]["report_jobs"], 1);
+    let exported = call(&engine, "export_settings", json!({}));
+    let output = exported["result"]["settings"]["report_jobs"][0]["output"].as_str().unwrap().to_string();
+    assert_eq!(output, dir.path().join(REPORTS_DIR).join("hosts").to_string_lossy());
+
+    for unusable in ["..", "C:\\Windows\\", "hosts:stream"] {
+        let refused = call(&engine, "import_settings", bundle(unusable));
+        assert_eq!(refused["error"]["code"], -32602, "{}", unusable);
+    }
+}
+
+/// Tool names, descriptions and schemas, as clients and their parsers see them
+///
+/// Only with every feature: builds without some leave their tools out.
+#[cfg(all(feature = "web-api", feature = "hashing", feature = "export",
+          feature = "image-info", feature = "media-info", feature = "doc-stats"))]
+#[test]
+fn test_tools_list_snapshot() {
+    let (engine, _dir) = fixture_engine();
+    let tools = engine.handle_request(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"})).unwrap();
+    insta::assert_json_snapshot!("tools_list", tools);
+}
+
+/// A smart search for a misspelt name falls back to fuzzy, as the name without search_type would
+#[test]
+fn test_smart_search_falls_back_to_fuzzy() {
+    let (engine, _dir) = fixture_engine();
+    let response = call(&engine, "fast_search", json!({"pattern": "bdget", "search_type": "smart", "drive": "C"}));
+    let result = &response["result"];
+    assert_eq!(result["did_you_mean"]["original_search_type"], "smart");
+    assert_eq!(result["did_you_mean"]["pattern"], "bdget");
+    assert_eq!(result["smart"]["patterns"][0]["searched_as"], "*bdget*");
+    assert!(result["content"][0]["text"].as_str().unwrap().contains("budget.xlsx"));
+
+    let without = call(&engine, "fast_search", json!({"pattern": "bdget", "search_type": "smart", "drive": "C", "auto_fuzzy": false}));
+    assert!(without["result"]["did_you_mean"].is_null());
+    assert_eq!(without["result"]["stages"]["filter_matches"], 0);
+}
@@ -0,0 +1,268 @@
+//! Trigram index over the names of a cache, for substring and similar-name searches
+//!
+//! A glob like `*invoice*` or a regex like `budget` matches a part in the
+//! middle of a name, which neither the name index nor the sorted and reversed
+//! names (see `name_affixes`) can look up, so every entry of the drive used to
+//! be matched against it. Here each distinct lowercased name is listed under
+//! every three-byte run it contains. A name containing `invoice` has `inv`,
+//! `nvo`, `voi`, `oic` and `ice`, so only the names in all five lists need
+//! matching. `find_similar` uses the same lists the other way round: a name
+//! within k edits of another shares all but a few of its trigrams, at most
+//! `k * (longest character + 2)` fewer, so names sharing fewer are skipped
+//! without computing their distance.
+//!
+//! The lists cost a few bytes per trigram of each distinct name (see
+//! `cache.trigrams` in the memory profile). Set `FASTSEARCH_TRIGRAM_INDEX=0`
+//! to go without them; those searches then match every entry again.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+/// Environment variable turning the index off (`0`, `false` or `off`)
+pub const TRIGRAM_INDEX_ENV: &str = "FASTSEARCH_TRIGRAM_INDEX";
+
+/// Whether caches keep a trigram index, as set by `FASTSEARCH_TRIGRAM_INDEX` (on unless turned off)
+pub fn trigram_index_enabled() -> bool {
+    match std::env::var(TRIGRAM_INDEX_ENV) {
+        Ok(value) => !matches!(value.trim().to_lowercase().as_str(), "0" | "false" | "off" | "no"),
+        Err(_) => true,
+    }
+}
+
+/// Three bytes of a name, packed
+type Trigram = u32;
+
+/// Distinct trigrams of a text, sorted
+fn trigrams(text: &str) -> Vec<Trigram> {
+    let mut found: Vec<Trigram> = text.as_bytes()
+        .windows(3)
+        .map(|w| ((w[0] as u32) << 16) | ((w[1] as u32) << 8) | w[2] as u32)
+        .collect();
+    found.sort_unstable();
+    found.dedup();
+    found
+}
+
+/// Distinct lowercased names of a cache, listed under each of their trigrams
+#[derive(Debug, Default)]
+pub struct TrigramIndex {
+    /// Names by number; numbers of removed names are reused
+    names: Vec<Option<Arc<str>>>,
+    numbers: HashMap<Arc<str>, u32>,
+    free: Vec<u32>,
+    /// Trigram -> numbers of the names containing it, sorted
+    postings: HashMap<Trigram, Vec<u32>>,
+}
+
+impl TrigramIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the keys of a name index, listing them in parallel
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a String>) -> Self {
+        let names: Vec<Arc<str>> = names.into_iter().map(|name| Arc::from(name.as_str())).collect();
+        let mut postings: HashMap<Trigram, Vec<u32>> = names
+            .par_iter()
+            .enumerate()
+            .fold(HashMap::new, |mut postings: HashMap<Trigram, Vec<u32>>, (number, name)| {
+                for trigram in trigrams(name) {
+                    postings.entry(trigram).or_default().push(number as u32);
+                }
+                postings
+            })
+            .reduce(HashMap::new, |mut merged, part| {
+                for (trigram, mut numbers) in part {
+                    merged.entry(trigram).or_default().append(&mut numbers);
+                }
+                merged
+            });
+        postings.par_iter_mut().for_each(|(_, numbers)| numbers.sort_unstable());
+
+        let numbers = names.iter().enumerate().map(|(number, name)| (Arc::clone(name), number as u32)).collect();
+        Self { names: names.into_iter().map(Some).collect(), numbers, free: Vec::new(), postings }
+    }
+
+    /// Add a lowercased name; names already indexed are left alone
+    pub fn insert(&mut self, name: &str) {
+        if self.numbers.contains_key(name) {
+            return;
+        }
+        let name: Arc<str> = Arc::from(name);
+        let number = match self.free.pop() {
+            Some(number) => {
+                self.names[number as usize] = Some(Arc::clone(&name));
+                number
+            }
+            None => {
+                self.names.push(Some(Arc::clone(&name)));
+                (self.names.len() - 1) as u32
+            }
+        };
+        for trigram in trigrams(&name) {
+            let numbers = self.postings.entry(trigram).or_default();
+            if let Err(at) = numbers.binary_search(&number) {
+                numbers.insert(at, number);
+            }
+        }
+        self.numbers.insert(name, number);
+    }
+
+    /// Remove a name no entry has any more
+    pub fn remove(&mut self, name: &str) {
+        let Some(number) = self.numbers.remove(name) else {
+            return;
+        };
+        for trigram in trigrams(name) {
+            if let Some(numbers) = self.postings.get_mut(&trigram) {
+                if let Ok(at) = numbers.binary_search(&number) {
+                    numbers.remove(at);
+                }
+                if numbers.is_empty() {
+                    self.postings.remove(&trigram);
+                }
+            }
+        }
+        self.names[number as usize] = None;
+        self.free.push(number);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Number of distinct names
+    pub fn len(&self) -> usize {
+        self.numbers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.numbers.is_empty()
+    }
+
+    /// Names containing every trigram of each literal (lowercased), `None` if no literal has one
+    ///
+    /// The names found may still lack a literal, with its trigrams spread over
+    /// the name, so they have to be matched.
+    pub fn names_containing(&self, literals: &[String]) -> Option<Vec<&str>> {
+        let mut wanted: Vec<Trigram> = literals.iter().flat_map(|literal| trigrams(literal)).collect();
+        if wanted.is_empty() {
+            return None;
+        }
+        wanted.sort_unstable();
+        wanted.dedup();
+
+        let mut lists = Vec::with_capacity(wanted.len());
+        for trigram in &wanted {
+            match self.postings.get(trigram) {
+                Some(numbers) => lists.push(numbers),
+                None => return Some(Vec::new()),
+            }
+        }
+        // Intersect starting from the shortest list
+        lists.sort_unstable_by_key(|numbers| numbers.len());
+        let (shortest, rest) = lists.split_first()?;
+        let found = shortest.iter()
+            .filter(|number| rest.iter().all(|numbers| numbers.binary_search(number).is_ok()))
+            .filter_map(|number| self.name(*number))
+            .collect();
+        Some(found)
+    }
+
+    /// Names that could be within `max_distance` edits of `target` (lowercased), `None` if any could
+    ///
+    /// Each edit removes at most `longest character + 2` of the trigrams of
+    /// `target`; a target too short to keep any after `max_distance` edits
+    /// narrows nothing.
+    pub fn names_near(&self, target: &str, max_distance: usize) -> Option<Vec<&str>> {
+        let target = target.to_lowercase();
+        let wanted = trigrams(&target);
+        let per_edit = target.chars().map(char::len_utf8).max().unwrap_or(1) + 2;
+        let needed = wanted.len().checked_sub(max_distance * per_edit).filter(|needed| *needed > 0)?;
+
+        let mut shared: HashMap<u32, usize> = HashMap::new();
+        for numbers in wanted.iter().filter_map(|trigram| self.postings.get(trigram)) {
+            for number in numbers {
+                *shared.entry(*number).or_default() += 1;
+            }
+        }
+        let found = shared.into_iter()
+            .filter(|(_, count)| *count >= needed)
+            .filter_map(|(number, _)| self.name(number))
+            .collect();
+        Some(found)
+    }
+
+    fn name(&self, number: u32) -> Option<&str> {
+        self.names.get(number as usize)?.as_deref()
+    }
+
+    /// Heap memory of the names and lists, for the memory profile (map overhead estimated per key)
+    pub fn heap_bytes(&self) -> u64 {
+        let text: usize = self.numbers.keys().map(|name| name.len()).sum();
+        let listed: usize = self.postings.values().map(|numbers| numbers.capacity() * size_of::<u32>()).sum();
+        let names = self.names.capacity() * size_of::<Option<Arc<str>>>()
+            + self.numbers.capacity() * (size_of::<Arc<str>>() + size_of::<u32>());
+        let lists = self.postings.capacity() * (size_of::<Trigram>() + size_of::<Vec<u32>>());
+        (text + listed + names + lists) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(names: &[&str]) -> TrigramIndex {
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        TrigramIndex::from_names(&names)
+    }
+
+    fn sorted(found: Option<Vec<&str>>) -> Vec<&str> {
+        let mut found = found.unwrap();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_names_containing() {
+        let names = index(&["invoice_2024.pdf", "my-invoices.xlsx", "voice.mp3", "notes.txt", "ai"]);
+        assert_eq!(sorted(names.names_containing(&["invoice".to_string()])), vec!["invoice_2024.pdf", "my-invoices.xlsx"]);
+        assert_eq!(sorted(names.names_containing(&["voice".to_string(), ".mp".to_string()])), vec!["voice.mp3"]);
+        assert!(names.names_containing(&["zzz".to_string()]).unwrap().is_empty());
+        // Too short to have a trigram: can't narrow anything
+        assert_eq!(names.names_containing(&["ai".to_string()]), None);
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut names = index(&["report.docx", "readme.md"]);
+        names.insert("old_report.docx");
+        names.insert("report.docx");
+        assert_eq!(names.len(), 3);
+        assert_eq!(sorted(names.names_containing(&["report".to_string()])), vec!["old_report.docx", "report.docx"]);
+
+        names.remove("report.docx");
+        names.remove("missing");
+        assert_eq!(sorted(names.names_containing(&["report".to_string()])), vec!["old_report.docx"]);
+        // The freed number is taken by the next name
+        names.insert("report_v2.docx");
+        assert_eq!(names.names.len(), 3);
+        assert_eq!(sorted(names.names_containing(&["report".to_string()])), vec!["old_report.docx", "report_v2.docx"]);
+
+        names.clear();
+        assert!(names.is_empty());
+        assert!(names.names_containing(&["report".to_string()]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_names_near() {
+        let names = index(&["report_final.docx", "report_fnial.docx", "report_final_v2.docx", "budget.xlsx"]);
+        let near = sorted(names.names_near("Report_Final.docx", 3));
+        assert_eq!(near, vec!["report_final.docx", "report_final_v2.docx", "report_fnial.docx"]);
+        // Short targets keep no trigram after enough edits
+        assert_eq!(names.names_near("abc", 1), None);
+    }
+}
@@ -1,28 +1,303 @@
 //! USN Journal monitoring for cache invalidation and updates
 
+use std::mem::size_of;
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
-use log::{debug, error, info, trace};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, error, info, trace, warn};
 use ntfs::NtfsFile;
+use parking_lot::Mutex;
+use serde_json::{json, Map, Value};
 use winapi::um::winioctl::FSCTL_READ_USN_JOURNAL;
 use winapi::um::winioctl::FSCTL_QUERY_USN_JOURNAL;
-use winapi::um::winioctl::USN_JOURNAL_DATA;
 use winapi::um::winnt::HANDLE;
 
+use crate::fastsearch_service::maintenance::MaintenanceLock;
 use crate::fastsearch_service::mft_cache::MftCache;
 
+/// Output of `FSCTL_QUERY_USN_JOURNAL` (`USN_JOURNAL_DATA_V0`), which winapi 0.3 doesn't define
+#[repr(C)]
+#[allow(non_camel_case_types, non_snake_case)]
+struct USN_JOURNAL_DATA {
+    UsnJournalID: u64,
+    FirstUsn: i64,
+    NextUsn: i64,
+    LowestValidUsn: i64,
+    MaxUsn: i64,
+    MaximumSize: u64,
+    AllocationDelta: u64,
+}
+
+/// Input of `FSCTL_READ_USN_JOURNAL`, which winapi 0.3 doesn't define either
+#[repr(C)]
+#[allow(non_camel_case_types, non_snake_case)]
+struct READ_USN_JOURNAL_DATA_V0 {
+    StartUsn: i64,
+    ReasonMask: u32,
+    ReturnOnlyOnClose: u32,
+    Timeout: u64,
+    BytesToWaitFor: u64,
+    UsnJournalID: u64,
+}
+
+/// Size of the buffer used to read USN records
+const USN_READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Batches (one per read buffer) that may wait between the journal reader and the cache
+const USN_QUEUE_BATCHES: usize = 64;
+
+/// Most changes the applier takes off the queue for one cache update
+const USN_APPLY_MAX_CHANGES: usize = 100_000;
+
+/// Name bytes counted per queued change in the memory profile (names are short)
+const QUEUED_NAME_BYTES: usize = 32;
+
+/// Queue metrics of the drives being monitored
+static QUEUES: Mutex<Vec<(char, Arc<UsnQueueMetrics>)>> = Mutex::new(Vec::new());
+
+/// USN reason flags (see `USN_RECORD_V2::Reason`)
+pub mod reason {
+    /// Data in the file was overwritten
+    pub const DATA_OVERWRITE: u32 = 0x0000_0001;
+    /// The file was extended
+    pub const DATA_EXTEND: u32 = 0x0000_0002;
+    /// The file was created for the first time
+    pub const FILE_CREATE: u32 = 0x0000_0100;
+    /// The file was deleted
+    pub const FILE_DELETE: u32 = 0x0000_0200;
+    /// The file was renamed and this is the old name
+    pub const RENAME_OLD_NAME: u32 = 0x0000_1000;
+    /// The file was renamed and this is the new name
+    pub const RENAME_NEW_NAME: u32 = 0x0000_2000;
+    /// The file or directory was closed
+    pub const CLOSE: u32 = 0x8000_0000;
+}
+
+/// An NTFS file reference: the MFT record number and the sequence number of its current use
+///
+/// USN records, `$LogFile` entries and forensic tools give files by their
+/// 64-bit reference, the sequence number in the high 16 bits. Cache IDs are
+/// the record number alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileReference {
+    pub record: u64,
+    /// Bumped each time the record is reused for another file; 0 if not known
+    pub sequence: u16,
+}
+
+impl FileReference {
+    /// The bits of a file reference holding the record number
+    pub const RECORD_MASK: u64 = 0x0000_FFFF_FFFF_FFFF;
+    
+    pub fn from_u64(reference: u64) -> Self {
+        Self { record: reference & Self::RECORD_MASK, sequence: (reference >> 48) as u16 }
+    }
+    
+    /// Parse a reference as tools print it: a decimal or `0x` hex 64-bit
+    /// reference, or `record-sequence` (`1234-5`)
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let invalid = || anyhow!("'{}' is not a file reference (a number, 0x hex, or record-sequence)", text);
+        if let Some((record, sequence)) = text.split_once('-') {
+            let record: u64 = record.trim().parse().map_err(|_| invalid())?;
+            if record > Self::RECORD_MASK {
+                return Err(anyhow!("Record number {} is larger than 48 bits", record));
+            }
+            let sequence: u16 = sequence.trim().parse().map_err(|_| invalid())?;
+            return Ok(Self { record, sequence });
+        }
+        let digits = text.replace('_', "");
+        let reference = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => digits.parse(),
+        };
+        reference.map(Self::from_u64).map_err(|_| invalid())
+    }
+}
+
+/// A single change read from the USN Journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsnChange {
+    /// MFT record number of the changed file
+    pub file_id: u64,
+    /// MFT record number of the parent directory
+    pub parent_id: u64,
+    /// Update sequence number of this record
+    pub usn: i64,
+    /// Bitmask of `reason` flags
+    pub reason: u32,
+    /// NTFS file attributes at the time of the change
+    pub attributes: u32,
+    /// File name (without path)
+    pub name: String,
+}
+
+impl UsnChange {
+    /// Whether this change makes a new name appear (creation or rename target)
+    pub fn is_new_name(&self) -> bool {
+        self.reason & (reason::FILE_CREATE | reason::RENAME_NEW_NAME) != 0
+            && self.reason & reason::FILE_DELETE == 0
+    }
+
+    /// Whether the changed entry is a directory
+    pub fn is_directory(&self) -> bool {
+        self.attributes & 0x10 != 0
+    }
+}
+
+/// Parse the output buffer of `FSCTL_READ_USN_JOURNAL`
+///
+/// The buffer starts with the next USN to read from, followed by a sequence of
+/// `USN_RECORD_V2` records. Records with other major versions are skipped.
+/// Returns the next USN and the parsed changes.
+pub fn parse_usn_records(buffer: &[u8]) -> (i64, Vec<UsnChange>) {
+    fn u16_at(buf: &[u8], offset: usize) -> Option<u16> {
+        buf.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+    fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
+        buf.get(offset..offset + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+    fn u64_at(buf: &[u8], offset: usize) -> Option<u64> {
+        buf.get(offset..offset + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    let next_usn = u64_at(buffer, 0).unwrap_or(0) as i64;
+    let mut changes = Vec::new();
+    let mut offset = 8;
+
+    while let Some(record_len) = u32_at(buffer, offset).map(|len| len as usize) {
+        if record_len == 0 || offset + record_len > buffer.len() {
+            break;
+        }
+        let record = &buffer[offset..offset + record_len];
+
+        if u16_at(record, 4) == Some(2) {
+            let fields = (
+                u64_at(record, 8),
+                u64_at(record, 16),
+                u64_at(record, 24),
+                u32_at(record, 40),
+                u32_at(record, 52),
+                u16_at(record, 56),
+                u16_at(record, 58),
+            );
+            if let (Some(file_ref), Some(parent_ref), Some(usn), Some(reason), Some(attributes), Some(name_len), Some(name_offset)) = fields {
+                let name_bytes = record.get(name_offset as usize..name_offset as usize + name_len as usize);
+                if let Some(name_bytes) = name_bytes {
+                    let wide: Vec<u16> = name_bytes
+                        .chunks_exact(2)
+                        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                        .collect();
+                    changes.push(UsnChange {
+                        // The low 48 bits of a file reference are the MFT record number
+                        file_id: FileReference::from_u64(file_ref).record,
+                        parent_id: FileReference::from_u64(parent_ref).record,
+                        usn: usn as i64,
+                        reason,
+                        attributes,
+                        name: String::from_utf16_lossy(&wide),
+                    });
+                }
+            }
+        }
+
+        offset += record_len;
+    }
+
+    (next_usn, changes)
+}
+
+/// Depth and throughput of the queue between a drive's journal reader and its cache
+///
+/// The reader never waits for the cache: when the queue is full it leaves the
+/// rest of the journal where it is (the journal is the overflow buffer) and
+/// picks up from there on a later poll, counted in `deferred_reads`.
+#[derive(Debug, Default)]
+pub struct UsnQueueMetrics {
+    queued_batches: AtomicUsize,
+    queued_changes: AtomicUsize,
+    peak_changes: AtomicUsize,
+    applied_changes: AtomicU64,
+    applied_updates: AtomicU64,
+    deferred_reads: AtomicU64,
+}
+
+impl UsnQueueMetrics {
+    /// Changes read from the journal but not yet applied to the cache
+    pub fn queued_changes(&self) -> usize {
+        self.queued_changes.load(Ordering::Relaxed)
+    }
+
+    /// Metrics as reported in the service status
+    pub fn to_json(&self) -> Value {
+        json!({
+            "queued_batches": self.queued_batches.load(Ordering::Relaxed),
+            "queued_changes": self.queued_changes(),
+            "peak_queued_changes": self.peak_changes.load(Ordering::Relaxed),
+            "capacity_batches": USN_QUEUE_BATCHES,
+            "applied_changes": self.applied_changes.load(Ordering::Relaxed),
+            "applied_updates": self.applied_updates.load(Ordering::Relaxed),
+            "deferred_reads": self.deferred_reads.load(Ordering::Relaxed),
+        })
+    }
+
+    fn enqueued(&self, changes: usize) {
+        self.queued_batches.fetch_add(1, Ordering::Relaxed);
+        let depth = self.queued_changes.fetch_add(changes, Ordering::Relaxed) + changes;
+        self.peak_changes.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn dequeued(&self, changes: usize) {
+        self.queued_batches.fetch_sub(1, Ordering::Relaxed);
+        self.queued_changes.fetch_sub(changes, Ordering::Relaxed);
+    }
+}
+
+/// USN queue metrics of every monitored drive, keyed by drive letter
+pub fn usn_queue_status() -> Value {
+    let queues = QUEUES.lock();
+    let drives: Map<String, Value> = queues.iter()
+        .map(|(drive, metrics)| (drive.to_string(), metrics.to_json()))
+        .collect();
+    Value::Object(drives)
+}
+
+/// Changes queued for each monitored drive's cache, and the bytes its journal reading holds
+///
+/// The read buffer is counted along with the queue; names of queued changes
+/// are counted at a typical length rather than walked.
+pub fn usn_buffer_memory() -> Vec<(char, u64, u64)> {
+    QUEUES.lock().iter()
+        .map(|(drive, metrics)| {
+            let queued = metrics.queued_changes();
+            let bytes = USN_READ_BUFFER_SIZE + queued * (size_of::<UsnChange>() + QUEUED_NAME_BYTES);
+            (*drive, queued as u64, bytes as u64)
+        })
+        .collect()
+}
+
 /// Monitors USN Journal for changes and updates the cache accordingly
+///
+/// A reader thread polls the journal and queues what it reads; an applier
+/// thread takes the queued changes off in large coalesced batches for the
+/// callback. A burst of changes (a Windows Update, unpacking an archive)
+/// therefore costs a few cache updates rather than one per poll, and the
+/// reader never stalls behind them.
 #[derive(Debug)]
 pub struct UsnJournalMonitor {
     drive_letter: char,
     volume_handle: HANDLE,
     running: Arc<AtomicBool>,
     thread_handle: Option<thread::JoinHandle<()>>,
+    applier_handle: Option<thread::JoinHandle<()>>,
+    metrics: Arc<UsnQueueMetrics>,
+    /// While active the journal isn't read, so it holds the changes until then
+    paused: Arc<MaintenanceLock>,
 }
 
 impl UsnJournalMonitor {
@@ -33,13 +308,30 @@ impl UsnJournalMonitor {
             volume_handle,
             running: Arc::new(AtomicBool::new(false)),
             thread_handle: None,
+            applier_handle: None,
+            metrics: Arc::new(UsnQueueMetrics::default()),
+            paused: Arc::new(MaintenanceLock::new()),
         })
     }
     
+    /// Stop reading the journal while the drive is in maintenance
+    pub fn paused_by(mut self, maintenance: Arc<MaintenanceLock>) -> Self {
+        self.paused = maintenance;
+        self
+    }
+    
+    /// Queue depth and throughput of this monitor
+    pub fn queue_metrics(&self) -> Arc<UsnQueueMetrics> {
+        Arc::clone(&self.metrics)
+    }
+    
     /// Start monitoring the USN Journal for changes
+    ///
+    /// The callback receives the journal records read since it last ran,
+    /// on the applier thread.
     pub fn start<F>(&mut self, callback: F) -> Result<()>
     where
-        F: Fn() + Send + 'static + Sync,
+        F: Fn(&[UsnChange]) + Send + 'static + Sync,
     {
         if self.running.load(Ordering::Relaxed) {
             return Ok(());
@@ -50,11 +342,26 @@ impl UsnJournalMonitor {
         let running = self.running.clone();
         let volume_handle = self.volume_handle;
         let drive_letter = self.drive_letter;
+        let (sender, receiver) = mpsc::sync_channel(USN_QUEUE_BATCHES);
         
+        let metrics = Arc::clone(&self.metrics);
+        let applier = thread::Builder::new()
+            .name(format!("usn-apply-{}", drive_letter))
+            .spawn(move || Self::apply_queued(receiver, &metrics, callback))
+            .context("Failed to start USN applier thread")?;
+        
+        let metrics = Arc::clone(&self.metrics);
+        let paused = Arc::clone(&self.paused);
         let handle = thread::spawn(move || {
             let mut last_usn = 0;
             
             while running.load(Ordering::Relaxed) {
+                // Changes made meanwhile are read from where the reader left off
+                if paused.is_active() {
+                    thread::sleep(Duration::from_secs(1));
+                    continue;
+                }
+                
                 match Self::query_journal(volume_handle) {
                     Ok(journal_data) => {
                         if journal_data.NextUsn > last_usn {
@@ -66,10 +373,18 @@ impl UsnJournalMonitor {
                                     journal_data.NextUsn - last_usn
                                 );
                                 
-                                // Notify the cache to update
-                                callback();
+                                // Queue the new records for the applier, as far as there is room
+                                last_usn = Self::queue_changes_since(
+                                    volume_handle,
+                                    journal_data.UsnJournalID,
+                                    last_usn,
+                                    journal_data.NextUsn,
+                                    &sender,
+                                    &metrics,
+                                );
+                            } else {
+                                last_usn = journal_data.NextUsn;
                             }
-                            last_usn = journal_data.NextUsn;
                         }
                     }
                     Err(e) => {
@@ -83,6 +398,8 @@ impl UsnJournalMonitor {
         });
         
         self.thread_handle = Some(handle);
+        self.applier_handle = Some(applier);
+        QUEUES.lock().push((drive_letter, Arc::clone(&self.metrics)));
         info!("Started USN Journal monitoring for drive {}", drive_letter);
         
         Ok(())
@@ -96,11 +413,19 @@ impl UsnJournalMonitor {
         
         self.running.store(false, Ordering::Relaxed);
         
+        // The reader drops the queue's sender as it exits, which ends the applier
+        // once it has applied what is still queued
         if let Some(handle) = self.thread_handle.take() {
             if let Err(e) = handle.join() {
                 error!("Error joining USN Journal monitor thread: {:?}", e);
             }
         }
+        if let Some(handle) = self.applier_handle.take() {
+            if let Err(e) = handle.join() {
+                error!("Error joining USN applier thread: {:?}", e);
+            }
+        }
+        QUEUES.lock().retain(|(_, metrics)| !Arc::ptr_eq(metrics, &self.metrics));
         
         info!("Stopped USN Journal monitoring for drive {}", self.drive_letter);
         Ok(())
@@ -135,9 +460,90 @@ impl UsnJournalMonitor {
         Ok(journal_data)
     }
     
+    /// Read the records between `start_usn` and `end_usn` onto the queue
+    ///
+    /// Returns the USN to read from next time: `end_usn`, or where the read
+    /// stopped because the queue was full. Read errors are logged and skip the
+    /// rest of the range, so a damaged journal can't wedge the reader.
+    fn queue_changes_since(
+        volume_handle: HANDLE,
+        journal_id: u64,
+        start_usn: i64,
+        end_usn: i64,
+        queue: &SyncSender<Vec<UsnChange>>,
+        metrics: &UsnQueueMetrics,
+    ) -> i64 {
+        let mut buffer = vec![0u8; USN_READ_BUFFER_SIZE];
+        let mut usn = start_usn;
+        
+        while usn < end_usn {
+            let bytes = match Self::read_journal_changes(volume_handle, journal_id, usn, &mut buffer) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    error!("Failed to read USN records from {}: {}", usn, e);
+                    return end_usn;
+                }
+            };
+            
+            let (next_usn, batch) = parse_usn_records(&buffer[..bytes]);
+            trace!("Read {} USN records ({} -> {})", batch.len(), usn, next_usn);
+            
+            if !batch.is_empty() {
+                let count = batch.len();
+                match queue.try_send(batch) {
+                    Ok(()) => metrics.enqueued(count),
+                    Err(TrySendError::Full(_)) => {
+                        // Leave the rest in the journal until the applier catches up
+                        metrics.deferred_reads.fetch_add(1, Ordering::Relaxed);
+                        debug!("USN queue full, deferring reads from {}", usn);
+                        return usn;
+                    }
+                    Err(TrySendError::Disconnected(_)) => {
+                        warn!("USN applier has stopped; dropping changes from {}", usn);
+                        return end_usn;
+                    }
+                }
+            }
+            
+            if next_usn <= usn {
+                break;
+            }
+            usn = next_usn;
+        }
+        
+        end_usn
+    }
+    
+    /// Apply queued changes until the reader goes away
+    ///
+    /// Whatever piled up while the last update ran is taken in one go (up to
+    /// `USN_APPLY_MAX_CHANGES`), so a burst is applied in a few large steps.
+    fn apply_queued<F>(queue: Receiver<Vec<UsnChange>>, metrics: &UsnQueueMetrics, callback: F)
+    where
+        F: Fn(&[UsnChange]),
+    {
+        while let Ok(mut changes) = queue.recv() {
+            metrics.dequeued(changes.len());
+            while changes.len() < USN_APPLY_MAX_CHANGES {
+                match queue.try_recv() {
+                    Ok(more) => {
+                        metrics.dequeued(more.len());
+                        changes.extend(more);
+                    }
+                    Err(_) => break,
+                }
+            }
+            
+            callback(&changes);
+            metrics.applied_changes.fetch_add(changes.len() as u64, Ordering::Relaxed);
+            metrics.applied_updates.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
     /// Read changes from the USN Journal
     fn read_journal_changes(
-        &self,
+        volume_handle: HANDLE,
+        journal_id: u64,
         start_usn: i64,
         buffer: &mut [u8],
     ) -> Result<usize> {
@@ -145,13 +551,17 @@ impl UsnJournalMonitor {
         use std::ptr;
         
         let mut bytes_returned = 0;
+        let mut read_data: READ_USN_JOURNAL_DATA_V0 = unsafe { mem::zeroed() };
+        read_data.StartUsn = start_usn;
+        read_data.ReasonMask = 0xFFFF_FFFF;
+        read_data.UsnJournalID = journal_id;
         
         let result = unsafe {
             winapi::um::ioapiset::DeviceIoControl(
-                self.volume_handle,
+                volume_handle,
                 FSCTL_READ_USN_JOURNAL,
-                &start_usn as *const _ as *mut _,
-                mem::size_of::<i64>() as u32,
+                &mut read_data as *mut _ as *mut _,
+                mem::size_of::<READ_USN_JOURNAL_DATA_V0>() as u32,
                 buffer.as_mut_ptr() as *mut _,
                 buffer.len() as u32,
                 &mut bytes_returned,
@@ -195,4 +605,81 @@ mod tests {
         let monitor = UsnJournalMonitor::new('C', std::ptr::null_mut());
         assert!(monitor.is_ok());
     }
+    
+    #[test]
+    fn test_applier_coalesces_queued_batches() {
+        let change = |id: u64| UsnChange {
+            file_id: id,
+            parent_id: 5,
+            usn: id as i64,
+            reason: reason::FILE_CREATE,
+            attributes: 0,
+            name: format!("{}.txt", id),
+        };
+        let metrics = UsnQueueMetrics::default();
+        let (sender, receiver) = mpsc::sync_channel(USN_QUEUE_BATCHES);
+        for batch in 0..3 {
+            let changes = vec![change(batch * 2), change(batch * 2 + 1)];
+            metrics.enqueued(changes.len());
+            sender.try_send(changes).unwrap();
+        }
+        drop(sender);
+        assert_eq!(metrics.queued_changes(), 6);
+        
+        let updates = std::sync::Mutex::new(Vec::new());
+        UsnJournalMonitor::apply_queued(receiver, &metrics, |changes| {
+            updates.lock().unwrap().push(changes.len());
+        });
+        
+        // Everything queued before the applier got to it is one update
+        assert_eq!(*updates.lock().unwrap(), vec![6]);
+        let status = metrics.to_json();
+        assert_eq!(status["queued_changes"], 0);
+        assert_eq!(status["peak_queued_changes"], 6);
+        assert_eq!(status["applied_changes"], 6);
+        assert_eq!(status["applied_updates"], 1);
+    }
+    
+    #[test]
+    fn test_parse_usn_records() {
+        let name: Vec<u8> = "crash.dmp".encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        let record_len = (60 + name.len() + 7) & !7;
+        
+        let mut record = vec![0u8; record_len];
+        record[0..4].copy_from_slice(&(record_len as u32).to_le_bytes());
+        record[4..6].copy_from_slice(&2u16.to_le_bytes());
+        record[8..16].copy_from_slice(&(0x0005_0000_0000_1234u64).to_le_bytes());
+        record[16..24].copy_from_slice(&(0x0001_0000_0000_0005u64).to_le_bytes());
+        record[24..32].copy_from_slice(&4096i64.to_le_bytes());
+        record[40..44].copy_from_slice(&reason::FILE_CREATE.to_le_bytes());
+        record[52..56].copy_from_slice(&0x20u32.to_le_bytes());
+        record[56..58].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        record[58..60].copy_from_slice(&60u16.to_le_bytes());
+        record[60..60 + name.len()].copy_from_slice(&name);
+        
+        let mut buffer = 8192i64.to_le_bytes().to_vec();
+        buffer.extend_from_slice(&record);
+        
+        let (next_usn, changes) = parse_usn_records(&buffer);
+        assert_eq!(next_usn, 8192);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].file_id, 0x1234);
+        assert_eq!(changes[0].parent_id, 5);
+        assert_eq!(changes[0].name, "crash.dmp");
+        assert!(changes[0].is_new_name());
+        assert!(!changes[0].is_directory());
+    }
+    
+    #[test]
+    fn test_parse_file_reference() {
+        let reference = FileReference { record: 0x1234, sequence: 5 };
+        assert_eq!(FileReference::from_u64(0x0005_0000_0000_1234), reference);
+        assert_eq!(FileReference::parse("0x0005000000001234").unwrap(), reference);
+        assert_eq!(FileReference::parse(" 1407374883557940 ").unwrap(), reference);
+        assert_eq!(FileReference::parse("4660-5").unwrap(), reference);
+        assert_eq!(FileReference::parse("4660").unwrap(), FileReference { record: 4660, sequence: 0 });
+        assert!(FileReference::parse("C:\\Windows").is_err());
+        assert!(FileReference::parse("4660-70000").is_err());
+        assert!(FileReference::parse("281474976710656-1").is_err());
+    }
 }
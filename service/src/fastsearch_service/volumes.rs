@@ -0,0 +1,430 @@
+//! Drive letter to volume mapping
+//!
+//! The same NTFS volume can be reachable under several names: a `subst`'d
+//! drive letter pointing into a folder of another drive, a second drive letter
+//! for the same volume, or a volume mounted into an empty NTFS folder. Searching
+//! each letter separately would list the same file several times, so searches
+//! are resolved to the underlying volume (identified by its serial number) and
+//! the other names a file is reachable under are reported as aliases.
+//!
+//! Letters also move: an external drive plugged into another port can come
+//! back under a different one. Saved caches are therefore keyed by
+//! [`VolumeId`], the volume's GUID (or serial number), and letters in requests
+//! are only translated to a volume when the cache is opened.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::ptr;
+
+use winapi::shared::minwindef::MAX_PATH;
+use winapi::um::fileapi::{
+    GetDriveTypeW, GetLogicalDrives, GetVolumeInformationW, GetVolumeNameForVolumeMountPointW,
+    GetVolumePathNamesForVolumeNameW, QueryDosDeviceW,
+};
+use winapi::um::winbase::DRIVE_REMOTE;
+
+/// What a drive letter points at
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriveVolume {
+    /// Drive letter
+    pub letter: char,
+    /// Volume serial number (None if the drive has no media)
+    pub serial: Option<u32>,
+    /// File system name, e.g. "NTFS"
+    pub file_system: Option<String>,
+    /// For `subst` drives, the folder the letter maps to (e.g. `C:\Projects`)
+    pub subst_target: Option<String>,
+    /// Folders the volume is also mounted at (e.g. `C:\mnt\data\`)
+    pub mount_folders: Vec<String>,
+    /// Network drive; never searched through the MFT
+    pub remote: bool,
+    /// Volume GUID path, e.g. `\\?\Volume{3f2504e0-4f89-11d3-9a0c-0305e82c3301}\`
+    pub guid: Option<String>,
+}
+
+/// Identity of a volume that stays the same when its drive letter changes
+///
+/// The volume GUID where Windows has one, else the serial number, else (no
+/// media, or no volume APIs) the drive letter itself. Used as it is in cache
+/// file names, so it holds only lowercase letters, digits and dashes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VolumeId(String);
+
+impl VolumeId {
+    /// Identity from a volume GUID path (`\\?\Volume{...}\`), None if it isn't one
+    pub fn from_guid_path(path: &str) -> Option<Self> {
+        let guid = path
+            .trim_end_matches('\\')
+            .strip_prefix("\\\\?\\Volume{")?
+            .strip_suffix('}')?;
+        let valid = !guid.is_empty() && guid.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+        valid.then(|| Self(format!("vol-{}", guid.to_ascii_lowercase())))
+    }
+
+    /// Identity from a volume serial number
+    pub fn from_serial(serial: u32) -> Self {
+        Self(format!("serial-{:08x}", serial))
+    }
+
+    /// Stand-in for a drive whose volume can't be identified
+    pub fn letter(letter: char) -> Self {
+        Self(format!("drive-{}", letter.to_ascii_lowercase()))
+    }
+
+    /// Identify the volume currently behind a drive letter
+    pub fn for_drive(letter: char) -> Self {
+        DriveVolume::query(letter.to_ascii_uppercase()).id()
+    }
+
+    /// The identity as used in file names
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for VolumeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Where a drive letter's files live in the MFT cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriveAlias {
+    /// Drive whose MFT holds the files
+    pub canonical: char,
+    /// Folder on the canonical drive the letter maps to ("" for the whole volume)
+    pub prefix: String,
+}
+
+impl DriveAlias {
+    /// The whole volume behind `drive`
+    pub fn whole(drive: char) -> Self {
+        Self {
+            canonical: drive,
+            prefix: String::new(),
+        }
+    }
+
+    /// Path as seen through this alias, or None if the file is outside its folder
+    pub fn display_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.prefix.is_empty() {
+            return Some(path);
+        }
+        let head = path.get(..self.prefix.len())?;
+        match path[self.prefix.len()..].strip_prefix('\\') {
+            Some(rest) if head.eq_ignore_ascii_case(&self.prefix) && !rest.is_empty() => Some(rest),
+            _ => None,
+        }
+    }
+}
+
+/// Snapshot of the drive letters on this machine
+#[derive(Debug, Clone, Default)]
+pub struct VolumeMap {
+    drives: Vec<DriveVolume>,
+}
+
+impl VolumeMap {
+    /// Build a map from known drives
+    pub fn new(mut drives: Vec<DriveVolume>) -> Self {
+        for drive in &mut drives {
+            drive.letter = drive.letter.to_ascii_uppercase();
+        }
+        drives.sort_by_key(|d| d.letter);
+        Self { drives }
+    }
+
+    /// Query every drive letter currently assigned
+    pub fn detect() -> Self {
+        let mask = unsafe { GetLogicalDrives() };
+        let drives = (0..26u8)
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| DriveVolume::query((b'A' + i) as char))
+            .collect();
+        Self::new(drives)
+    }
+
+    /// Drive letters to search for "all drives": every local NTFS volume once
+    pub fn search_drives(&self) -> Vec<char> {
+        self.drives
+            .iter()
+            .filter(|d| !d.remote && d.subst_target.is_none())
+            .filter(|d| d.file_system.as_deref() == Some("NTFS"))
+            .filter(|d| self.canonical_letter(d) == d.letter)
+            .map(|d| d.letter)
+            .collect()
+    }
+
+    /// Resolve a drive letter to the volume (and folder) it exposes
+    pub fn resolve(&self, letter: char) -> DriveAlias {
+        self.resolve_within(letter.to_ascii_uppercase(), 0)
+    }
+
+    fn resolve_within(&self, letter: char, depth: usize) -> DriveAlias {
+        let Some(drive) = self.get(letter).filter(|d| !d.remote) else {
+            return DriveAlias::whole(letter);
+        };
+
+        if let Some(target) = drive.subst_target.as_deref().filter(|_| depth < 26) {
+            let mut chars = target.chars();
+            if let (Some(target_letter), Some(':')) = (chars.next(), chars.next()) {
+                let folder = chars.as_str().trim_matches('\\');
+                let base = self.resolve_within(target_letter.to_ascii_uppercase(), depth + 1);
+                let prefix = match (base.prefix.is_empty(), folder.is_empty()) {
+                    (true, _) => folder.to_string(),
+                    (false, true) => base.prefix,
+                    (false, false) => format!("{}\\{}", base.prefix, folder),
+                };
+                return DriveAlias { canonical: base.canonical, prefix };
+            }
+        }
+
+        DriveAlias::whole(self.canonical_letter(drive))
+    }
+
+    /// Every full path a file on `canonical` is reachable under, including the canonical one
+    pub fn locations(&self, canonical: char, path: &str) -> Vec<String> {
+        let mut locations = Vec::new();
+        for drive in self.drives.iter().filter(|d| !d.remote) {
+            let alias = self.resolve(drive.letter);
+            if alias.canonical != canonical {
+                continue;
+            }
+            if let Some(shown) = alias.display_path(path) {
+                locations.push(format!("{}:\\{}", drive.letter, shown));
+            }
+        }
+        if let Some(drive) = self.get(canonical) {
+            for folder in &drive.mount_folders {
+                locations.push(format!("{}{}", folder, path));
+            }
+        }
+        locations
+    }
+
+    fn get(&self, letter: char) -> Option<&DriveVolume> {
+        self.drives.iter().find(|d| d.letter == letter)
+    }
+
+    /// Volume a drive letter's files live on, following aliases to the canonical drive
+    pub fn volume_id(&self, letter: char) -> VolumeId {
+        let canonical = self.resolve(letter).canonical;
+        self.get(canonical).map_or_else(|| VolumeId::letter(canonical), DriveVolume::id)
+    }
+
+    /// Drive letter a volume is currently reachable under, if it is mounted at all
+    pub fn letter_of(&self, volume: &VolumeId) -> Option<char> {
+        self.drives
+            .iter()
+            .filter(|d| !d.remote && d.subst_target.is_none() && d.id() == *volume)
+            .map(|d| self.canonical_letter(d))
+            .next()
+    }
+
+    /// Lowest local drive letter for the same volume serial
+    fn canonical_letter(&self, drive: &DriveVolume) -> char {
+        let Some(serial) = drive.serial else {
+            return drive.letter;
+        };
+        self.drives
+            .iter()
+            .filter(|d| !d.remote && d.subst_target.is_none() && d.serial == Some(serial))
+            .map(|d| d.letter)
+            .next()
+            .unwrap_or(drive.letter)
+    }
+}
+
+impl DriveVolume {
+    /// Identity of the volume, independent of the letter it is mounted under
+    pub fn id(&self) -> VolumeId {
+        self.guid
+            .as_deref()
+            .and_then(VolumeId::from_guid_path)
+            .or_else(|| self.serial.map(VolumeId::from_serial))
+            .unwrap_or_else(|| VolumeId::letter(self.letter))
+    }
+
+    /// Query a drive letter through the Win32 volume APIs
+    fn query(letter: char) -> Self {
+        let root = to_wide(&format!("{}:\\", letter));
+        let mut drive = DriveVolume {
+            letter,
+            remote: unsafe { GetDriveTypeW(root.as_ptr()) } == DRIVE_REMOTE,
+            ..Default::default()
+        };
+
+        let mut serial = 0u32;
+        let mut fs_name = [0u16; MAX_PATH + 1];
+        let ok = unsafe {
+            GetVolumeInformationW(
+                root.as_ptr(),
+                ptr::null_mut(),
+                0,
+                &mut serial,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                fs_name.as_mut_ptr(),
+                fs_name.len() as u32,
+            )
+        };
+        if ok != 0 {
+            drive.serial = Some(serial);
+            drive.file_system = Some(from_wide(&fs_name));
+        }
+
+        // subst'd drives are DOS devices pointing at "\??\C:\folder"
+        let device = to_wide(&format!("{}:", letter));
+        let mut target = [0u16; 1024];
+        let len = unsafe { QueryDosDeviceW(device.as_ptr(), target.as_mut_ptr(), target.len() as u32) };
+        if len > 0 {
+            drive.subst_target = from_wide(&target)
+                .strip_prefix("\\??\\")
+                .filter(|t| t.as_bytes().get(1) == Some(&b':'))
+                .map(|t| t.trim_end_matches('\\').to_string());
+        }
+
+        if drive.subst_target.is_none() && !drive.remote {
+            drive.guid = volume_name(&root).map(|name| from_wide(&name));
+            drive.mount_folders = mount_points(&root)
+                .into_iter()
+                .filter(|path| path.len() > 3) // Drive roots are listed as letters already
+                .collect();
+        }
+        drive
+    }
+}
+
+/// Volume GUID path of the volume behind `root` (NUL-terminated)
+fn volume_name(root: &[u16]) -> Option<[u16; 64]> {
+    let mut volume_name = [0u16; 64];
+    let ok = unsafe {
+        GetVolumeNameForVolumeMountPointW(root.as_ptr(), volume_name.as_mut_ptr(), volume_name.len() as u32)
+    };
+    (ok != 0).then_some(volume_name)
+}
+
+/// All paths the volume behind `root` is mounted at
+fn mount_points(root: &[u16]) -> Vec<String> {
+    let Some(volume_name) = volume_name(root) else {
+        return Vec::new();
+    };
+
+    let mut paths = vec![0u16; 1024];
+    let mut needed = 0u32;
+    let ok = unsafe {
+        GetVolumePathNamesForVolumeNameW(volume_name.as_ptr(), paths.as_mut_ptr(), paths.len() as u32, &mut needed)
+    };
+    if ok == 0 {
+        return Vec::new();
+    }
+
+    // Double-NUL terminated list of NUL-terminated strings
+    paths[..needed as usize]
+        .split(|c| *c == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| OsString::from_wide(path).to_string_lossy().into_owned())
+        .collect()
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    OsString::from(s).encode_wide().chain(Some(0)).collect()
+}
+
+fn from_wide(buf: &[u16]) -> String {
+    let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+    OsString::from_wide(&buf[..len]).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drive(letter: char, serial: u32) -> DriveVolume {
+        DriveVolume {
+            letter,
+            serial: Some(serial),
+            file_system: Some("NTFS".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn sample() -> VolumeMap {
+        VolumeMap::new(vec![
+            drive('C', 1),
+            DriveVolume { mount_folders: vec!["C:\\mnt\\data\\".to_string()], ..drive('D', 2) },
+            drive('E', 2),
+            DriveVolume { subst_target: Some("C:\\Projects".to_string()), ..drive('S', 1) },
+            DriveVolume { subst_target: Some("S:\\app".to_string()), ..drive('T', 1) },
+            DriveVolume { remote: true, ..drive('Z', 1) },
+        ])
+    }
+
+    #[test]
+    fn test_resolve_aliases() {
+        let volumes = sample();
+        assert_eq!(volumes.resolve('c'), DriveAlias::whole('C'));
+        assert_eq!(volumes.resolve('E'), DriveAlias::whole('D'));
+        assert_eq!(volumes.resolve('S'), DriveAlias { canonical: 'C', prefix: "Projects".to_string() });
+        assert_eq!(volumes.resolve('T'), DriveAlias { canonical: 'C', prefix: "Projects\\app".to_string() });
+        assert_eq!(volumes.resolve('Q'), DriveAlias::whole('Q'));
+        assert_eq!(volumes.resolve('Z'), DriveAlias::whole('Z'));
+        assert_eq!(volumes.search_drives(), vec!['C', 'D']);
+    }
+
+    #[test]
+    fn test_display_path() {
+        let alias = DriveAlias { canonical: 'C', prefix: "Projects".to_string() };
+        assert_eq!(alias.display_path("projects\\app\\main.rs"), Some("app\\main.rs"));
+        assert_eq!(alias.display_path("Projects"), None);
+        assert_eq!(alias.display_path("ProjectsOld\\a.txt"), None);
+        assert_eq!(DriveAlias::whole('C').display_path("a.txt"), Some("a.txt"));
+    }
+
+    #[test]
+    fn test_locations() {
+        let volumes = sample();
+        assert_eq!(
+            volumes.locations('C', "Projects\\app\\main.rs"),
+            vec!["C:\\Projects\\app\\main.rs", "S:\\app\\main.rs", "T:\\main.rs"]
+        );
+        assert_eq!(
+            volumes.locations('D', "x.txt"),
+            vec!["D:\\x.txt", "E:\\x.txt", "C:\\mnt\\data\\x.txt"]
+        );
+    }
+
+    #[test]
+    fn test_volume_ids() {
+        let guid = "\\\\?\\Volume{3F2504E0-4F89-11D3-9A0C-0305E82C3301}\\";
+        let id = VolumeId::from_guid_path(guid).unwrap();
+        assert_eq!(id.as_str(), "vol-3f2504e0-4f89-11d3-9a0c-0305e82c3301");
+        assert_eq!(VolumeId::from_guid_path("C:\\"), None);
+        assert_eq!(VolumeId::from_guid_path("\\\\?\\Volume{..\\x}\\"), None);
+        assert_eq!(VolumeId::from_serial(0x1A2B).as_str(), "serial-00001a2b");
+        assert_eq!(VolumeId::letter('Q').as_str(), "drive-q");
+
+        // The GUID wins over the serial number, which wins over the letter
+        let external = DriveVolume { guid: Some(guid.to_string()), ..drive('F', 7) };
+        assert_eq!(external.id(), id);
+        assert_eq!(drive('F', 7).id(), VolumeId::from_serial(7));
+        assert_eq!(DriveVolume { letter: 'F', ..Default::default() }.id(), VolumeId::letter('F'));
+    }
+
+    #[test]
+    fn test_volume_id_survives_letter_change() {
+        let volumes = sample();
+        assert_eq!(volumes.volume_id('E'), VolumeId::from_serial(2));
+        assert_eq!(volumes.volume_id('S'), VolumeId::from_serial(1));
+        assert_eq!(volumes.letter_of(&VolumeId::from_serial(2)), Some('D'));
+        assert_eq!(volumes.letter_of(&VolumeId::from_serial(9)), None);
+
+        // The same external drive plugged in again under another letter
+        let before = VolumeMap::new(vec![drive('C', 1), drive('E', 5)]);
+        let after = VolumeMap::new(vec![drive('C', 1), drive('G', 5)]);
+        assert_eq!(before.volume_id('E'), after.volume_id('G'));
+        assert_eq!(after.letter_of(&before.volume_id('E')), Some('G'));
+    }
+}
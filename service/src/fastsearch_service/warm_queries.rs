@@ -0,0 +1,299 @@
+//! Frequent searches kept warm across service restarts
+//!
+//! After a restart the pattern cache is empty, so the first searches for the
+//! patterns an agent uses all the time pay for regex compilation again, and
+//! every first page samples the caches to rule out a broad search. The store
+//! remembers the most used recent searches together with how many entries
+//! they matched and keeps them on disk next to the saved searches. At startup
+//! the most frequent patterns are compiled into the pattern cache, and a
+//! search remembered to match too little to be broad skips the sample.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{debug, error, info};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::json_store::{data_dir, JsonStore};
+use super::matcher::{PatternCache, SearchType};
+use super::pagination::search_fingerprint;
+
+/// File name of the warm query store inside the data directory
+const WARM_QUERIES_FILE: &str = "warm_queries.json";
+
+/// Searches remembered; the least used are dropped beyond this
+pub const MAX_WARM_QUERIES: usize = 200;
+
+/// Patterns compiled at startup
+pub const WARM_PATTERNS: usize = 32;
+
+/// Searches not run for this long are forgotten when the store is loaded
+const MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Minimum time between writes of the store
+const PERSIST_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Remembered counts are trusted while the searched caches have grown or
+/// shrunk by at most this share of their size
+const MAX_ENTRY_DRIFT: f64 = 0.1;
+
+/// A search that has been run, and what it matched last time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WarmQuery {
+    /// Drive (or "*") the search ran on
+    pub drive: String,
+    /// Fingerprint of the search arguments, as used by page cursors
+    pub search: u64,
+    pub pattern: String,
+    /// Further patterns, for searches given several to match any of
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub more_patterns: Vec<String>,
+    pub search_type: String,
+    /// How often the search was run
+    pub uses: u64,
+    /// When the search was last run (UNIX timestamp)
+    pub last_used: i64,
+    /// Matches before the disk checks: the exact count, or the estimate for a broad search
+    pub matches: u64,
+    /// Entries in the searched caches when the matches were counted
+    pub entries: u64,
+}
+
+/// Persistent record of recent searches
+#[derive(Debug)]
+pub struct WarmQueryStore {
+    file: JsonStore<Vec<WarmQuery>>,
+    queries: RwLock<HashMap<(String, u64), WarmQuery>>,
+    dirty: AtomicBool,
+    last_persist: Mutex<Instant>,
+}
+
+impl WarmQueryStore {
+    /// Default location: `%LOCALAPPDATA%\FastSearchMCP\warm_queries.json`
+    pub fn default_path() -> PathBuf {
+        data_dir().join(WARM_QUERIES_FILE)
+    }
+
+    /// Open the store at the given path, starting empty if it doesn't exist or can't be read
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let file: JsonStore<Vec<WarmQuery>> = JsonStore::at(&path, "warm queries");
+        let oldest = Utc::now().timestamp() - MAX_AGE_SECS;
+        let queries: HashMap<(String, u64), WarmQuery> = file.load().unwrap_or_default()
+            .into_iter()
+            .filter(|q| q.last_used >= oldest)
+            .map(|q| ((q.drive.clone(), q.search), q))
+            .collect();
+
+        info!("Loaded {} warm queries from {}", queries.len(), path.as_ref().display());
+        Self {
+            file,
+            queries: RwLock::new(queries),
+            dirty: AtomicBool::new(false),
+            last_persist: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Create a store that is never written to disk
+    pub fn in_memory() -> Self {
+        Self {
+            file: JsonStore::in_memory("warm queries"),
+            queries: RwLock::new(HashMap::new()),
+            dirty: AtomicBool::new(false),
+            last_persist: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Number of remembered searches
+    pub fn len(&self) -> usize {
+        self.queries.read().len()
+    }
+
+    /// Whether no searches are remembered
+    pub fn is_empty(&self) -> bool {
+        self.queries.read().is_empty()
+    }
+
+    /// Distinct patterns of the most used searches, most used first
+    pub fn frequent_patterns(&self, limit: usize) -> Vec<(String, SearchType)> {
+        let queries = self.queries.read();
+        let mut ranked: Vec<&WarmQuery> = queries.values().collect();
+        ranked.sort_by(|a, b| b.uses.cmp(&a.uses).then(b.last_used.cmp(&a.last_used)));
+
+        let mut patterns: Vec<(String, SearchType)> = Vec::new();
+        for query in ranked {
+            let Some(search_type) = SearchType::parse(&query.search_type) else {
+                continue;
+            };
+            for pattern in std::iter::once(&query.pattern).chain(&query.more_patterns) {
+                if patterns.len() == limit {
+                    return patterns;
+                }
+                if !patterns.iter().any(|(p, t)| p == pattern && *t == search_type) {
+                    patterns.push((pattern.clone(), search_type));
+                }
+            }
+        }
+        patterns
+    }
+
+    /// Compile the most frequent patterns into `cache`, returning how many compiled
+    pub fn warm(&self, cache: &PatternCache) -> usize {
+        let patterns = self.frequent_patterns(WARM_PATTERNS);
+        let compiled = patterns.iter()
+            .filter(|(pattern, search_type)| cache.get_or_compile(pattern, *search_type, false).is_ok())
+            .count();
+        if compiled > 0 {
+            info!("Precompiled {} frequent search patterns", compiled);
+        }
+        compiled
+    }
+
+    /// Matches the search had last time, scaled to the current size of the caches
+    ///
+    /// `None` if the search hasn't been seen, or the caches changed too much
+    /// since for the old count to say anything.
+    pub fn known_matches(&self, drive: &str, args: &Value, entries: usize) -> Option<u64> {
+        let queries = self.queries.read();
+        let query = queries.get(&(drive.to_string(), search_fingerprint(args)))?;
+        if query.entries == 0 {
+            return None;
+        }
+        let drift = (entries as f64 - query.entries as f64).abs() / query.entries as f64;
+        (drift <= MAX_ENTRY_DRIFT).then(|| (query.matches as f64 * entries as f64 / query.entries as f64).round() as u64)
+    }
+
+    /// Remember a search and the matches it counted in `entries` cache entries
+    pub fn record(&self, drive: &str, args: &Value, patterns: &[String], search_type: SearchType, matches: u64, entries: usize) {
+        {
+            let mut queries = self.queries.write();
+            let key = (drive.to_string(), search_fingerprint(args));
+            if !queries.contains_key(&key) && queries.len() >= MAX_WARM_QUERIES {
+                // Make room by forgetting the least used search, oldest first
+                let evict = queries.iter()
+                    .min_by(|a, b| a.1.uses.cmp(&b.1.uses).then(a.1.last_used.cmp(&b.1.last_used)))
+                    .map(|(key, _)| key.clone());
+                if let Some(evict) = evict {
+                    queries.remove(&evict);
+                }
+            }
+            let query = queries.entry(key).or_insert_with(|| WarmQuery {
+                drive: drive.to_string(),
+                search: search_fingerprint(args),
+                pattern: patterns.first().cloned().unwrap_or_default(),
+                more_patterns: patterns.iter().skip(1).cloned().collect(),
+                search_type: search_type.as_str().to_string(),
+                uses: 0,
+                last_used: 0,
+                matches: 0,
+                entries: 0,
+            });
+            query.uses += 1;
+            query.last_used = Utc::now().timestamp();
+            query.matches = matches;
+            query.entries = entries as u64;
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+
+        // Searches come in bursts, so the file is rewritten at most every so often
+        let mut last_persist = self.last_persist.lock();
+        if last_persist.elapsed() >= PERSIST_INTERVAL {
+            *last_persist = Instant::now();
+            drop(last_persist);
+            self.flush();
+        }
+    }
+
+    /// Write pending changes to disk, logging failures
+    pub fn flush(&self) {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if let Err(e) = self.persist() {
+            error!("Failed to save warm queries: {}", e);
+        }
+    }
+
+    /// Write the store to disk (atomically, via a temporary file)
+    fn persist(&self) -> Result<()> {
+        let Some(path) = self.file.path() else {
+            return Ok(());
+        };
+
+        let mut queries: Vec<WarmQuery> = self.queries.read().values().cloned().collect();
+        queries.sort_by(|a, b| b.uses.cmp(&a.uses).then_with(|| a.pattern.cmp(&b.pattern)));
+        self.file.save(&queries)?;
+
+        debug!("Saved {} warm queries to {}", queries.len(), path.display());
+        Ok(())
+    }
+}
+
+impl Drop for WarmQueryStore {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_store_round_trip_and_warm() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(WARM_QUERIES_FILE);
+        let args = json!({"pattern": "*.rs", "path_filter": "src"});
+
+        let store = WarmQueryStore::open(&path);
+        store.record("C", &args, &["*.rs".to_string()], SearchType::Glob, 120, 10_000);
+        store.record("C", &args, &["*.rs".to_string()], SearchType::Glob, 150, 10_000);
+        store.record("C", &json!({"pattern": "^main"}), &["^main".to_string()], SearchType::Regex, 3, 10_000);
+        drop(store);
+
+        let reopened = WarmQueryStore::open(&path);
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.frequent_patterns(1), vec![("*.rs".to_string(), SearchType::Glob)]);
+
+        let cache = PatternCache::new();
+        assert_eq!(reopened.warm(&cache), 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_known_matches_follow_cache_size() {
+        let store = WarmQueryStore::in_memory();
+        let args = json!({"pattern": "*.log"});
+        store.record("D", &args, &["*.log".to_string()], SearchType::Glob, 500, 10_000);
+
+        // Paging arguments don't make it a different search
+        let page = json!({"pattern": "*.log", "max_results": 10});
+        assert_eq!(store.known_matches("D", &page, 10_000), Some(500));
+        assert_eq!(store.known_matches("D", &args, 10_500), Some(525));
+        assert_eq!(store.known_matches("D", &args, 20_000), None);
+        assert_eq!(store.known_matches("C", &args, 10_000), None);
+    }
+
+    #[test]
+    fn test_least_used_query_is_evicted() {
+        let store = WarmQueryStore::in_memory();
+        for i in 0..MAX_WARM_QUERIES {
+            let args = json!({"pattern": format!("f{}", i)});
+            store.record("C", &args, &[format!("f{}", i)], SearchType::Exact, 1, 100);
+            if i > 0 {
+                store.record("C", &args, &[format!("f{}", i)], SearchType::Exact, 1, 100);
+            }
+        }
+        store.record("C", &json!({"pattern": "new"}), &["new".to_string()], SearchType::Exact, 1, 100);
+
+        assert_eq!(store.len(), MAX_WARM_QUERIES);
+        assert_eq!(store.known_matches("C", &json!({"pattern": "f0"}), 100), None);
+        assert_eq!(store.known_matches("C", &json!({"pattern": "new"}), 100), Some(1));
+    }
+}
@@ -2,20 +2,52 @@
 // Exposes MCP functionality as HTTP endpoints for frontend integration
 
 use axum::{
-    extract::Query,
-    http::Method,
-    response::Json,
-    routing::{get, post},
+    extract::{ConnectInfo, Path, Query, Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+    routing::{delete, get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_stream::StreamExt;
 use tower_http::cors::{Any, CorsLayer};
-use anyhow::Result;
+use anyhow::{bail, Result};
+use log::{info, warn};
 
 use crate::McpServer;
+use super::cache_signing::integrity_warnings;
+use super::cancellation::CancelToken;
+use super::features::enabled_features;
+use super::modes::{any_degraded, index_mode_status};
+use super::projection::FieldProjection;
+use super::query::format_size;
+use super::resource_limits::{is_degraded, limit_status};
+use super::self_test::{self_test_failed, self_test_status};
+use super::usn_journal::usn_queue_status;
+
+/// Default cap on max_results in the remote profile
+const REMOTE_MAX_RESULTS_CAP: usize = 200;
+
+/// Which surface the Web API exposes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApiProfile {
+    /// Loopback only, every endpoint, no authentication
+    #[default]
+    Local,
+    /// For other machines: read-only endpoints, API key auth, capped results, caller logging
+    Remote,
+}
 
 /// Configuration for the Web API server
 #[derive(Debug, Clone)]
@@ -26,6 +58,12 @@ pub struct WebApiConfig {
     pub enable_cors: bool,
     /// Default number of results to return if not specified
     pub default_max_results: usize,
+    /// Which endpoints are exposed and how they are protected
+    pub profile: ApiProfile,
+    /// Accepted API keys (required by the remote profile)
+    pub api_keys: Vec<String>,
+    /// Upper bound on max_results for remote callers
+    pub max_results_cap: usize,
 }
 
 impl Default for WebApiConfig {
@@ -34,26 +72,131 @@ impl Default for WebApiConfig {
             port: 8080,  // Default port
             enable_cors: true,
             default_max_results: 100,
+            profile: ApiProfile::Local,
+            api_keys: Vec::new(),
+            max_results_cap: REMOTE_MAX_RESULTS_CAP,
+        }
+    }
+}
+
+impl WebApiConfig {
+    /// Hardened configuration for exposing search to other machines
+    pub fn remote(port: u16, api_keys: Vec<String>) -> Self {
+        Self {
+            port,
+            enable_cors: false,
+            profile: ApiProfile::Remote,
+            api_keys,
+            ..Default::default()
         }
     }
 }
 
 #[derive(Deserialize)]
 pub struct SearchRequest {
-    pub pattern: String,
+    /// A pattern, or an array of patterns to match any of
+    pub pattern: Value,
+    /// Pattern(s) names must not match
+    pub not_pattern: Option<Value>,
+    /// Match the case of the pattern(s) (default false)
+    pub case_sensitive: Option<bool>,
+    /// Match regardless of accents (default false)
+    pub ignore_diacritics: Option<bool>,
+    /// Match the pattern(s) against the whole path instead of the name (default false)
+    pub match_path: Option<bool>,
     pub path: Option<String>,
+    /// Folder levels below `path` to search (1 = direct children)
+    pub max_depth: Option<u64>,
+    pub drive: Option<String>,
     pub max_results: Option<usize>,
+    /// At most this many results from any one folder
+    pub max_per_directory: Option<u64>,
+    /// Stop scanning after this many milliseconds and return what was found, flagged truncated
+    pub timeout_ms: Option<u64>,
+    /// "text" or "binary"
+    pub content_kind: Option<String>,
+    /// Sniff files whose extension doesn't tell text from binary
+    pub sniff_content_kind: Option<bool>,
+    /// Date windows, as accepted by fast_search (YYYY-MM-DD, RFC 3339, UNIX timestamp or relative, like "last 7 days")
+    pub modified_after: Option<Value>,
+    pub modified_before: Option<Value>,
+    pub created_after: Option<Value>,
+    pub created_before: Option<Value>,
+    pub accessed_after: Option<Value>,
+    pub accessed_before: Option<Value>,
+    /// Globs for paths to leave out, e.g. "node_modules/**"
+    pub exclude: Option<Vec<String>>,
+    /// Apply the .gitignore/.ignore files of repositories matches are in
+    pub respect_gitignore: Option<bool>,
+    /// User name, DOMAIN\user or SID that must own matches
+    pub owner: Option<String>,
+    /// List alternate data streams of returned files as extra results
+    pub include_streams: Option<bool>,
+    /// Drop files whose first bytes don't match their extension, and add the detected MIME type
+    pub verify_content_type: Option<bool>,
+    /// Add page and word counts of document results
+    pub include_doc_stats: Option<bool>,
+    /// Page and word count bounds of documents
+    pub min_pages: Option<u64>,
+    pub max_pages: Option<u64>,
+    pub min_words: Option<u64>,
+    pub max_words: Option<u64>,
+    /// Add dimensions and EXIF capture date of image results
+    pub include_image_info: Option<bool>,
+    /// EXIF capture date window of images, as the date windows above
+    pub taken_after: Option<Value>,
+    pub taken_before: Option<Value>,
+    /// Smallest image size in megapixels
+    pub min_megapixels: Option<f64>,
+    /// Add duration, bitrate and codec of audio and video results
+    pub include_media_info: Option<bool>,
+    /// Playing time bounds: seconds or a duration like "30m"
+    pub min_duration: Option<Value>,
+    pub max_duration: Option<Value>,
+    /// "follow" (default), "skip" or "only" symlinks, junctions and cloud placeholders
+    pub reparse_points: Option<String>,
+    /// Retry literal patterns that match nothing as fuzzy searches (default true)
+    pub auto_fuzzy: Option<bool>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Only these fields of each result, e.g. ["path"]
+    pub fields: Option<Vec<String>>,
+    /// Summarize searches that match much of the drive instead of returning a page (default true)
+    pub summarize_broad: Option<bool>,
+    /// Attribute filters: `true` keeps only entries with the attribute, `false` drops them
+    pub hidden: Option<bool>,
+    pub system: Option<bool>,
+    pub readonly: Option<bool>,
+    pub compressed: Option<bool>,
+    pub encrypted: Option<bool>,
+    pub sparse: Option<bool>,
 }
 
 #[derive(Serialize)]
 pub struct SearchResponse {
     pub success: bool,
-    pub results: Vec<FileResult>,
+    /// `FileResult`s, with only the requested `fields` if any were
+    pub results: Vec<Value>,
     pub count: usize,
     pub search_time_ms: f64,
     pub message: Option<String>,
+    /// Pass back as `cursor` to get the next page; absent on the last page
+    pub next_cursor: Option<String>,
+    /// Estimated breakdown returned instead of results when the search matches
+    /// much of the drive (see `summarize_broad`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broad_summary: Option<Value>,
+    /// Similar names, other drives with matches and matches outside the path
+    /// filter, when the search found nothing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hints: Option<Value>,
 }
 
+/// Fields of a `FileResult`, for projection
+pub const FILE_RESULT_FIELDS: &[&str] = &[
+    "name", "path", "full_path", "size", "is_directory", "size_formatted", "is_symlink", "reparse_target",
+];
+
 #[derive(Serialize)]
 pub struct FileResult {
     pub name: String,
@@ -62,6 +205,11 @@ pub struct FileResult {
     pub size: u64,
     pub is_directory: bool,
     pub size_formatted: String,
+    /// Symbolic link or junction
+    pub is_symlink: bool,
+    /// Where a link points
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reparse_target: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -84,6 +232,10 @@ impl WebApiServer {
     
     /// Create a new Web API server with custom configuration
     pub fn with_config(config: WebApiConfig) -> Result<Self> {
+        if config.profile == ApiProfile::Remote && config.api_keys.iter().all(|key| key.trim().is_empty()) {
+            bail!("The remote Web API profile requires at least one API key");
+        }
+        
         Ok(Self {
             server: Arc::new(McpServer::new()?),
             config,
@@ -99,7 +251,7 @@ impl WebApiServer {
         // Set up CORS
         let cors = if self.config.enable_cors {
             CorsLayer::new()
-                .allow_methods([Method::GET, Method::POST])
+                .allow_methods([Method::GET, Method::POST, Method::DELETE])
                 .allow_headers(Any)
                 .allow_origin(Any)
         } else {
@@ -107,24 +259,45 @@ impl WebApiServer {
             CorsLayer::new()
         };
 
-        // Build our application with routes
-        let app = Router::new()
+        // The remote profile listens on all interfaces; everything else stays on loopback
+        let remote = self.config.profile == ApiProfile::Remote;
+        let addr = if remote {
+            SocketAddr::from(([0, 0, 0, 0], self.config.port))
+        } else {
+            SocketAddr::from(([127, 0, 0, 1], self.config.port))
+        };
+
+        // Build our application with routes; remote callers only get read-only endpoints
+        let mut app = Router::new()
             .route("/api/search", post(search_files))
             .route("/api/status", get(get_status))
-            .route("/api/benchmark", post(benchmark_search))
-            .route("/health", get(health_check))
-            .layer(cors)
-            .with_state(Arc::new(self));
+            .route("/api/docs", get(get_docs))
+            .route("/health", get(health_check));
+        if !remote {
+            app = app
+                .route("/api/benchmark", post(benchmark_search))
+                .route("/api/diagnostics/memory", get(memory_profile))
+                .route("/api/live", get(list_live_queries).post(register_live_query))
+                .route("/api/live/:id", delete(remove_live_query))
+                .route("/api/live/:id/events", get(live_query_events));
+        }
+        
+        let state = Arc::new(self);
+        let app = if remote {
+            app.layer(middleware::from_fn_with_state(Arc::clone(&state), remote_guard))
+        } else {
+            app.layer(cors)
+        };
+        let app = app.with_state(state);
 
         // Run the server
-        let addr = std::net::SocketAddr::from(([127, 0, 0, 1], self.config.port));
-        info!("Web API server listening on http://{}", addr);
+        info!("Web API server listening on http://{} ({:?} profile)", addr, if remote { ApiProfile::Remote } else { ApiProfile::Local });
         
         // Print the server URL for easy access
         println!("FastSearch Web API server running at http://{}", addr);
         
         axum::Server::bind(&addr)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
 
         Ok(())
@@ -136,29 +309,219 @@ async fn search_files(
     Json(request): Json<SearchRequest>,
 ) -> Json<SearchResponse> {
     let start_time = std::time::Instant::now();
+    let projection = match FieldProjection::from_args(&json!({"fields": request.fields}), FILE_RESULT_FIELDS) {
+        Ok(projection) => projection,
+        Err(e) => return Json(SearchResponse {
+            success: false,
+            results: vec![],
+            count: 0,
+            search_time_ms: 0.0,
+            message: Some(e.to_string()),
+            next_cursor: None,
+            broad_summary: None,
+            hints: None,
+        }),
+    };
+
+    // Remote callers can't ask for more than the configured cap
+    let mut max_results = request.max_results.unwrap_or(1000);
+    if server.config.profile == ApiProfile::Remote {
+        max_results = max_results.min(server.config.max_results_cap);
+    }
 
     // Convert to MCP request format
     let mut args = json!({
         "pattern": request.pattern,
-        "max_results": request.max_results.unwrap_or(1000)
+        "max_results": max_results
     });
 
     if let Some(path) = request.path {
         args["path"] = json!(path);
     }
+    if let Some(not_pattern) = request.not_pattern {
+        args["not_pattern"] = not_pattern;
+    }
+    if let Some(case_sensitive) = request.case_sensitive {
+        args["case_sensitive"] = json!(case_sensitive);
+    }
+    if let Some(ignore_diacritics) = request.ignore_diacritics {
+        args["ignore_diacritics"] = json!(ignore_diacritics);
+    }
+    if let Some(match_path) = request.match_path {
+        args["match_path"] = json!(match_path);
+    }
+    if let Some(max_depth) = request.max_depth {
+        args["max_depth"] = json!(max_depth);
+    }
+    let drive = request.drive.unwrap_or_else(|| "C".to_string()).to_uppercase();
+    args["drive"] = json!(drive);
+    
+    let dates = [
+        ("modified_after", request.modified_after),
+        ("modified_before", request.modified_before),
+        ("created_after", request.created_after),
+        ("created_before", request.created_before),
+        ("accessed_after", request.accessed_after),
+        ("accessed_before", request.accessed_before),
+        ("taken_after", request.taken_after),
+        ("taken_before", request.taken_before),
+    ];
+    for (key, value) in dates {
+        if let Some(value) = value {
+            args[key] = value;
+        }
+    }
+    if let Some(exclude) = request.exclude {
+        args["exclude"] = json!(exclude);
+    }
+    if let Some(respect_gitignore) = request.respect_gitignore {
+        args["respect_gitignore"] = json!(respect_gitignore);
+    }
+    if let Some(owner) = request.owner {
+        args["owner"] = json!(owner);
+    }
+    if let Some(include_streams) = request.include_streams {
+        args["include_streams"] = json!(include_streams);
+    }
+    if let Some(verify_content_type) = request.verify_content_type {
+        args["verify_content_type"] = json!(verify_content_type);
+    }
+    if let Some(max_per_directory) = request.max_per_directory {
+        args["max_per_directory"] = json!(max_per_directory);
+    }
+    if let Some(timeout_ms) = request.timeout_ms {
+        args["timeout_ms"] = json!(timeout_ms);
+    }
+    if let Some(content_kind) = &request.content_kind {
+        args["content_kind"] = json!(content_kind);
+    }
+    if let Some(sniff_content_kind) = request.sniff_content_kind {
+        args["sniff_content_kind"] = json!(sniff_content_kind);
+    }
+    if let Some(include_doc_stats) = request.include_doc_stats {
+        args["include_doc_stats"] = json!(include_doc_stats);
+    }
+    let counts = [
+        ("min_pages", request.min_pages),
+        ("max_pages", request.max_pages),
+        ("min_words", request.min_words),
+        ("max_words", request.max_words),
+    ];
+    for (key, value) in counts {
+        if let Some(value) = value {
+            args[key] = json!(value);
+        }
+    }
+    if let Some(include_image_info) = request.include_image_info {
+        args["include_image_info"] = json!(include_image_info);
+    }
+    if let Some(min_megapixels) = request.min_megapixels {
+        args["min_megapixels"] = json!(min_megapixels);
+    }
+    if let Some(include_media_info) = request.include_media_info {
+        args["include_media_info"] = json!(include_media_info);
+    }
+    for (key, value) in [("min_duration", request.min_duration), ("max_duration", request.max_duration)] {
+        if let Some(value) = value {
+            args[key] = value;
+        }
+    }
+    if let Some(reparse_points) = request.reparse_points {
+        args["reparse_points"] = json!(reparse_points);
+    }
+    if let Some(auto_fuzzy) = request.auto_fuzzy {
+        args["auto_fuzzy"] = json!(auto_fuzzy);
+    }
+    if let Some(cursor) = request.cursor {
+        args["cursor"] = json!(cursor);
+    }
+    if let Some(summarize_broad) = request.summarize_broad {
+        args["summarize_broad"] = json!(summarize_broad);
+    }
+    let attributes = [
+        ("hidden", request.hidden),
+        ("system", request.system),
+        ("readonly", request.readonly),
+        ("compressed", request.compressed),
+        ("encrypted", request.encrypted),
+        ("sparse", request.sparse),
+    ];
+    for (key, value) in attributes {
+        if let Some(value) = value {
+            args[key] = json!(value);
+        }
+    }
 
-    // Call MCP server
-    match server.mcp_server.fast_search(&args) {
-        Ok(_mcp_response) => {
+    // Search off the async runtime. When the client disconnects, axum drops
+    // this handler and the guard cancels the scan instead of letting it run on.
+    let cancel = server.mcp_server.cancel_token();
+    let _disconnect = CancelOnDrop(cancel.clone());
+    let searcher = Arc::clone(&server);
+    let outcome = tokio::task::spawn_blocking(move || searcher.mcp_server.fast_search_with_cancel(&args, &cancel))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result);
+    match outcome {
+        Ok(mcp_response) => {
             let search_time = start_time.elapsed().as_millis() as f64;
             
-            // Parse MCP response - for now just return success
+            if let Some(message) = mcp_response["error"]["message"].as_str() {
+                return Json(SearchResponse {
+                    success: false,
+                    results: vec![],
+                    count: 0,
+                    search_time_ms: search_time,
+                    message: Some(message.to_string()),
+                    next_cursor: None,
+                    broad_summary: None,
+                    hints: None,
+                });
+            }
+            
+            // Convert the structured fast_search results
+            let results: Vec<Value> = mcp_response["result"]["results"]
+                .as_array()
+                .map(|entries| entries.iter().map(|entry| {
+                    let path = entry["path"].as_str().unwrap_or_default();
+                    let size = entry["size"].as_u64().unwrap_or(0);
+                    let result = json!(FileResult {
+                        name: entry["name"].as_str().unwrap_or_default().to_string(),
+                        path: path.to_string(),
+                        full_path: format!("{}:\\{}", entry["drive"].as_str().unwrap_or(&drive), path),
+                        size,
+                        is_directory: entry["is_directory"].as_bool().unwrap_or(false),
+                        size_formatted: format_size(size),
+                        is_symlink: entry["is_symlink"].as_bool().unwrap_or(false),
+                        reparse_target: entry["reparse_target"].as_str().map(str::to_string),
+                    });
+                    match &projection {
+                        Some(projection) => projection.apply(result),
+                        None => result,
+                    }
+                }).collect())
+                .unwrap_or_default();
+            
+            // Broad searches come back as a summary, empty ones with hints, typo
+            // retries as "did you mean" results and paths through links from the
+            // link's target; the text content explains each
+            let broad_summary = mcp_response["result"].get("broad_summary").cloned();
+            let hints = mcp_response["result"].get("hints").filter(|hints| !hints.is_null()).cloned();
+            let explained = ["did_you_mean", "followed_link"].iter().any(|key| mcp_response["result"].get(*key).is_some());
+            let message = if broad_summary.is_some() || hints.is_some() || explained {
+                mcp_response["result"]["content"][0]["text"].as_str().unwrap_or_default().to_string()
+            } else {
+                "Direct search completed".to_string()
+            };
+            
             Json(SearchResponse {
                 success: true,
-                count: 0,
-                results: vec![],
+                count: results.len(),
+                results,
                 search_time_ms: search_time,
-                message: Some("Direct search completed".to_string()),
+                message: Some(message),
+                next_cursor: mcp_response["result"]["next_cursor"].as_str().map(str::to_string),
+                broad_summary,
+                hints,
             })
         }
         Err(e) => Json(SearchResponse {
@@ -167,13 +530,80 @@ async fn search_files(
             count: 0,
             search_time_ms: start_time.elapsed().as_millis() as f64,
             message: Some(format!("Search failed: {}", e)),
+            next_cursor: None,
+            broad_summary: None,
+            hints: None,
         }),
     }
 }
 
+/// Authenticate and log every request made through the remote profile
+/// Cancels a search when its handler is dropped, which axum does when the client goes away
+///
+/// Dropped after the search finished, it cancels a token nothing checks any more.
+struct CancelOnDrop(CancelToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+async fn remote_guard(
+    State(server): State<Arc<WebApiServer>>,
+    ConnectInfo(caller): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    
+    let presented = presented_api_key(request.headers());
+    let key_index = presented.as_deref().and_then(|presented| {
+        server.config.api_keys.iter().position(|key| constant_time_eq(key.as_bytes(), presented.as_bytes()))
+    });
+    let Some(key_index) = key_index else {
+        warn!("Rejected remote {} {} from {}: {} API key",
+              method, path, caller, if presented.is_some() { "invalid" } else { "missing" });
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"success": false, "message": "Missing or invalid API key"})),
+        ).into_response();
+    };
+    
+    let start = Instant::now();
+    let response = next.run(request).await;
+    info!("Remote {} {} from {} (key #{}) -> {} in {}ms",
+          method, path, caller, key_index + 1, response.status(), start.elapsed().as_millis());
+    response
+}
+
+/// API key from `Authorization: Bearer <key>` or `X-API-Key: <key>`
+fn presented_api_key(headers: &HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let header = headers.get("x-api-key").and_then(|value| value.to_str().ok());
+    bearer.or(header).map(|key| key.trim().to_string())
+}
+
+/// Compare secrets without exiting early on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
 async fn get_status(
     axum::extract::State(_server): axum::extract::State<Arc<WebApiServer>>,
 ) -> Json<StatusResponse> {
+    // A cache that fails its canary queries would answer every search with nothing
+    if self_test_failed() {
+        return Json(StatusResponse {
+            success: false,
+            status: "not_ready".to_string(),
+            message: "A drive's cache failed its startup self-test; see /health for the failed checks".to_string(),
+        });
+    }
     Json(StatusResponse {
         success: true,
         status: "ready".to_string(),
@@ -181,6 +611,16 @@ async fn get_status(
     })
 }
 
+/// Search syntax reference (Markdown), the same as the MCP resource
+async fn get_docs(
+    axum::extract::State(server): axum::extract::State<Arc<WebApiServer>>,
+) -> Response {
+    match server.mcp_server.syntax_reference() {
+        Ok(text) => ([(header::CONTENT_TYPE, "text/markdown; charset=utf-8")], text).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to build the syntax reference: {}", e)).into_response(),
+    }
+}
+
 async fn benchmark_search(
     axum::extract::State(server): axum::extract::State<Arc<WebApiServer>>,
     Query(params): Query<HashMap<String, String>>,
@@ -196,11 +636,142 @@ async fn benchmark_search(
     }
 }
 
+/// Memory by subsystem (local profile only: it walks every cache)
+async fn memory_profile(
+    axum::extract::State(server): axum::extract::State<Arc<WebApiServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    match server.mcp_server.memory_profile(&json!({"drive": params.get("drive")})) {
+        Ok(response) => Json(response["result"].clone()),
+        Err(e) => Json(json!({
+            "success": false,
+            "error": format!("Memory profile failed: {}", e)
+        })),
+    }
+}
+
+/// Register a live query (local profile only): `fast_search` arguments in,
+/// its ID and current results out
+async fn register_live_query(
+    axum::extract::State(server): axum::extract::State<Arc<WebApiServer>>,
+    Json(args): Json<Value>,
+) -> Json<Value> {
+    match server.mcp_server.register_live_query(&args) {
+        Ok(response) => match response["error"]["message"].as_str() {
+            Some(message) => Json(json!({"success": false, "error": message})),
+            None => Json(response["result"].clone()),
+        },
+        Err(e) => Json(json!({
+            "success": false,
+            "error": format!("Live query failed: {}", e)
+        })),
+    }
+}
+
+async fn list_live_queries(
+    axum::extract::State(server): axum::extract::State<Arc<WebApiServer>>,
+) -> Json<Value> {
+    Json(json!({"success": true, "live_queries": server.mcp_server.list_live_queries()}))
+}
+
+async fn remove_live_query(
+    axum::extract::State(server): axum::extract::State<Arc<WebApiServer>>,
+    Path(id): Path<String>,
+) -> Response {
+    if server.mcp_server.remove_live_query(&id) {
+        Json(json!({"success": true})).into_response()
+    } else {
+        unknown_live_query(&id)
+    }
+}
+
+/// Server-sent events for a live query: `added` and `removed` with the
+/// update as JSON and its `seq` as the event ID, and `lagged` with the number
+/// of updates missed by a client that fell behind (it should search again).
+/// The stream ends when the query is removed.
+async fn live_query_events(
+    axum::extract::State(server): axum::extract::State<Arc<WebApiServer>>,
+    Path(id): Path<String>,
+) -> Response {
+    let Some(query) = server.mcp_server.live_query(&id) else {
+        return unknown_live_query(&id);
+    };
+    let events = BroadcastStream::new(query.subscribe()).map(|update| {
+        Ok::<_, Infallible>(match update {
+            Ok(update) => Event::default()
+                .event(update.change.as_str())
+                .id(update.seq.to_string())
+                .json_data(&update)
+                .unwrap_or_else(|e| Event::default().event("error").data(e.to_string())),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => Event::default().event("lagged").data(missed.to_string()),
+        })
+    });
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+fn unknown_live_query(id: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({"success": false, "message": format!("No live query '{}'", id)})),
+    ).into_response()
+}
+
 async fn health_check() -> Json<Value> {
+    let warnings = integrity_warnings();
+    let status = if self_test_failed() {
+        "not_ready"
+    } else if is_degraded() || !warnings.is_empty() || any_degraded() {
+        "degraded"
+    } else {
+        "healthy"
+    };
     Json(json!({
-        "status": "healthy",
+        // Not ready while a cache fails its self-test; degraded for a while after an allocation
+        // failed on the memory limit, for good once a tampered cache was refused, and while a
+        // drive runs in a fallback mode
+        "status": status,
         "service": "FastSearch MCP Server",
         "version": "0.1.0",
-        "mode": "direct_search"
+        "mode": "direct_search",
+        "features": enabled_features(),
+        "resource_limits": limit_status(),
+        "index_modes": index_mode_status(),
+        "usn_queues": usn_queue_status(),
+        "self_test": self_test_status(),
+        "warnings": warnings
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presented_api_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(presented_api_key(&headers), None);
+
+        headers.insert("x-api-key", "abc".parse().unwrap());
+        assert_eq!(presented_api_key(&headers).as_deref(), Some("abc"));
+
+        headers.insert("authorization", "Bearer xyz".parse().unwrap());
+        assert_eq!(presented_api_key(&headers).as_deref(), Some("xyz"));
+    }
+
+    #[test]
+    fn test_remote_profile_requires_api_key() {
+        assert!(WebApiServer::with_config(WebApiConfig::remote(8080, vec![])).is_err());
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+
+    #[test]
+    fn test_dropped_handler_cancels_search() {
+        let token = CancelToken::new();
+        let guard = CancelOnDrop(token.clone());
+        assert!(!token.is_cancelled());
+        drop(guard);
+        assert!(token.is_cancelled());
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::Result;
 use clap::{Arg, Command};
+#[cfg(feature = "web-api")]
+use clap::ArgMatches;
 use log::{info, error, LevelFilter};
 use serde_json;
 use simplelog::{Config, WriteLogger};
@@ -23,6 +25,9 @@ use mcp_status::get_service_status;
 // Use modules from the fastsearch_service module
 use fastmcp_core::server::McpServer;
 use fastsearch_service::pipe_server::PipeServer;
+#[cfg(feature = "web-api")]
+use fastsearch_service::web_api::WebApiConfig;
+use fastsearch_service::{contain_process, ResourceLimits};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -78,6 +83,34 @@ async fn main() -> Result<()> {
                         .default_value("8080")
                         .value_name("PORT")
                 )
+                .arg(
+                    Arg::new("remote")
+                        .long("remote")
+                        .help("Expose the web API to other machines (read-only, API key required, capped results)")
+                        .takes_value(false)
+                )
+                .arg(
+                    Arg::new("api-key")
+                        .long("api-key")
+                        .help("API key accepted by the remote profile (repeatable; also read from FASTSEARCH_API_KEYS)")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_name("KEY")
+                )
+                .arg(
+                    Arg::new("cpu-limit")
+                        .long("cpu-limit")
+                        .help("Cap the service's CPU use at this percentage of the machine (also read from FASTSEARCH_CPU_LIMIT)")
+                        .takes_value(true)
+                        .value_name("PERCENT")
+                )
+                .arg(
+                    Arg::new("memory-limit")
+                        .long("memory-limit")
+                        .help("Fail allocations past this much memory, e.g. 2GB (also read from FASTSEARCH_MEMORY_LIMIT)")
+                        .takes_value(true)
+                        .value_name("SIZE")
+                )
         )
         .get_matches();
 
@@ -86,15 +119,39 @@ async fn main() -> Result<()> {
         Some(("install", _)) => install_service().await,
         Some(("uninstall", _)) => uninstall_service().await,
         Some(("run", sub_matches)) => {
-            let port = sub_matches.value_of("port")
-                .and_then(|p| p.parse::<u16>().ok())
-                .unwrap_or(8080);
-            run_service(port).await
+            // Apply resource limits before any thread starts allocating
+            let limits = ResourceLimits::resolve(sub_matches.value_of("cpu-limit"), sub_matches.value_of("memory-limit"))?;
+            contain_process(limits);
+            #[cfg(feature = "web-api")]
+            let service = run_service(web_api_config(sub_matches));
+            #[cfg(not(feature = "web-api"))]
+            let service = run_service();
+            service.await
         },
         _ => unreachable!(),
     }
 }
 
+/// Web API settings from the `run` options
+#[cfg(feature = "web-api")]
+fn web_api_config(sub_matches: &ArgMatches) -> WebApiConfig {
+    let port = sub_matches.value_of("port")
+        .and_then(|p| p.parse::<u16>().ok())
+        .unwrap_or(8080);
+    
+    if sub_matches.is_present("remote") {
+        let mut api_keys: Vec<String> = sub_matches.values_of("api-key")
+            .map(|keys| keys.map(str::to_string).collect())
+            .unwrap_or_default();
+        if let Ok(keys) = std::env::var("FASTSEARCH_API_KEYS") {
+            api_keys.extend(keys.split(',').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string));
+        }
+        WebApiConfig::remote(port, api_keys)
+    } else {
+        WebApiConfig { port, ..Default::default() }
+    }
+}
+
 async fn install_service() -> Result<()> {
     info!("Installing {} service...", SERVICE_NAME);
     
@@ -152,7 +209,7 @@ async fn uninstall_service() -> Result<()> {
     Ok(())
 }
 
-async fn run_service(port: u16) -> Result<()> {
+async fn run_service(#[cfg(feature = "web-api")] web_config: WebApiConfig) -> Result<()> {
     // If we're not running in a console, detach from it
     unsafe {
         if GetConsoleWindow().is_null() {
@@ -161,7 +218,10 @@ async fn run_service(port: u16) -> Result<()> {
     }
     
     info!("Starting FastSearch service in console mode...");
-    info!("Web API will be available on port {}", port);
+    #[cfg(feature = "web-api")]
+    info!("Web API will be available on port {} ({:?} profile)", web_config.port, web_config.profile);
+    #[cfg(not(feature = "web-api"))]
+    info!("Built without the web API; serving MCP and the named pipe only");
     
     // Hide the console window in release mode
     #[cfg(not(debug_assertions))]
@@ -177,9 +237,10 @@ async fn run_service(port: u16) -> Result<()> {
     });
     
     // Start the web API in a separate thread with the specified port
+    #[cfg(feature = "web-api")]
     let web_api_handle = thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
-        if let Err(e) = rt.block_on(run_web_api(port)) {
+        if let Err(e) = rt.block_on(run_web_api(web_config)) {
             error!("Web API error: {}", e);
             let _ = tx.send(());
         }
@@ -201,22 +262,73 @@ async fn run_service(port: u16) -> Result<()> {
 }
 
 async fn run_mcp_server() -> Result<()> {
-    let server = McpServer::new()?;
-    
-    // MCP server protocol: read from stdin, write to stdout
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+    let server = Arc::new(McpServer::new()?);
     
-    for line in stdin.lock().lines() {
-        let line = line?;
-        if line.trim().is_empty() {
+    // Write server-initiated notifications (watched searches, search progress, ...)
+    // as soon as they are queued
+    let notifier = Arc::clone(&server);
+    thread::spawn(move || loop {
+        if !notifier.wait_for_notifications(Duration::from_millis(500)) {
             continue;
         }
-        
+        // Drain under the stdout lock so a response can't overtake its progress updates
+        let mut stdout = io::stdout().lock();
+        for notification in notifier.drain_notifications() {
+            if let Ok(line) = serde_json::to_string(&notification) {
+                let _ = writeln!(stdout, "{}", line);
+            }
+        }
+        let _ = stdout.flush();
+    });
+    
+    // Build the caches of preloaded drives in the background
+    let preloader = Arc::clone(&server);
+    thread::spawn(move || {
+        preloader.preload_drives();
+    });
+    
+    // Precompute scheduled reports in the background
+    let reporter = Arc::clone(&server);
+    thread::spawn(move || loop {
+        reporter.run_due_reports();
+        thread::sleep(Duration::from_secs(60));
+    });
+    
+    // MCP server protocol: read from stdin, write to stdout. Lines are read on their
+    // own thread so a cancellation can reach a request that is still running.
+    let (lines_tx, lines_rx) = mpsc::channel::<String>();
+    let canceller = Arc::clone(&server);
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Ok(message) = serde_json::from_str::<Value>(&line) {
+                if message["method"] == "notifications/cancelled" {
+                    // Notifications get no response
+                    canceller.cancel_request(&message["params"]);
+                    continue;
+                }
+            }
+            if lines_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let mut stdout = io::stdout();
+    
+    for line in lines_rx {
         match serde_json::from_str::<Value>(&line) {
             Ok(request) => {
                 let response = server.handle_request(request)?;
                 let response_str = serde_json::to_string(&response)?;
+                
+                // Progress notifications queued by this request go out before its response
+                let mut stdout = stdout.lock();
+                for notification in server.drain_notifications() {
+                    writeln!(stdout, "{}", serde_json::to_string(&notification)?)?;
+                }
                 writeln!(stdout, "{}", response_str)?;
                 stdout.flush()?;
             }
@@ -235,17 +347,14 @@ async fn run_mcp_server() -> Result<()> {
         }
     }
     
+    // The client closed stdin: stop rebuilds and scans still running in the background
+    server.shutdown();
     Ok(())
 }
 
-async fn run_web_api(port: u16) -> Result<()> {
-    use fastsearch_service::{WebApiServer, web_api::WebApiConfig};
-    
-    // Create a custom config with the specified port
-    let config = WebApiConfig {
-        port,
-        ..Default::default()
-    };
+#[cfg(feature = "web-api")]
+async fn run_web_api(config: WebApiConfig) -> Result<()> {
+    use fastsearch_service::WebApiServer;
     
     let server = WebApiServer::with_config(config)?;
     server.serve().await?;
@@ -2,7 +2,7 @@ use std::io::{self, Read, Write};
 use std::os::windows::io::{AsRawHandle, FromRawHandle};
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use winapi::um::namedpipeapi::{
     CreateNamedPipeW, ConnectNamedPipe, DisconnectNamedPipe,
     PIPE_ACCESS_DUPLEX, PIPE_TYPE_MESSAGE, PIPE_READMODE_MESSAGE, PIPE_WAIT,
@@ -25,7 +25,15 @@ use winapi::shared::winerror::{
     ERROR_PIPE_CONNECTED, ERROR_NO_DATA, ERROR_BROKEN_PIPE, ERROR_PIPE_NOT_CONNECTED
 };
 use log::{info, error, warn};
-use anyhow::{Result, Context};
+use anyhow::{bail, Result, Context};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::{
+    any_degraded, enabled_features, encode_frame, etw, index_mode_status, integrity_warnings, is_degraded, limit_status,
+    parse_request_frame, pipe_search_args, self_test_failed, self_test_status, usn_queue_status, McpServer, MSG_CANCEL,
+    MSG_SEARCH, MSG_STATUS, STATUS_ERROR, STATUS_OK, STATUS_PARTIAL,
+};
 
 const PIPE_NAME: &str = r"\\.\pipe\fastsearch-service";
 const BUFFER_SIZE: usize = 65536; // 64KB buffer
@@ -33,13 +41,16 @@ const MAX_INSTANCES: DWORD = 10;
 
 pub struct PipeServer {
     pipe_name: String,
+    server: Arc<McpServer>,
     shutdown_tx: Option<mpsc::Sender<()>>,
 }
 
 impl PipeServer {
-    pub fn new() -> Result<Self> {
+    pub fn new(server: Arc<McpServer>) -> Result<Self> {
+        etw::register();
         Ok(Self {
             pipe_name: PIPE_NAME.to_string(),
+            server,
             shutdown_tx: None,
         })
     }
@@ -52,8 +63,9 @@ impl PipeServer {
 
         // Create a thread to handle incoming connections
         let pipe_name = self.pipe_name.clone();
+        let server = Arc::clone(&self.server);
         let _handle = thread::spawn(move || {
-            if let Err(e) = Self::run_pipe_server(&pipe_name, rx) {
+            if let Err(e) = Self::run_pipe_server(&pipe_name, rx, server) {
                 error!("Pipe server error: {}", e);
             }
         });
@@ -61,7 +73,7 @@ impl PipeServer {
         Ok(())
     }
 
-    fn run_pipe_server(pipe_name: &str, shutdown_rx: mpsc::Receiver<()>) -> Result<()> {
+    fn run_pipe_server(pipe_name: &str, shutdown_rx: mpsc::Receiver<()>, server: Arc<McpServer>) -> Result<()> {
         loop {
             // Check for shutdown signal
             if shutdown_rx.try_recv().is_ok() {
@@ -69,8 +81,15 @@ impl PipeServer {
                 break;
             }
 
-            // Create a new pipe instance
-            let pipe_handle = unsafe { Self::create_pipe(pipe_name) }?;
+            // Create a new pipe instance; with every instance connected, wait for a client to leave
+            let pipe_handle = match unsafe { Self::create_pipe(pipe_name) } {
+                Ok(handle) => handle,
+                Err(e) => {
+                    warn!("{:#}; retrying", e);
+                    thread::sleep(Duration::from_millis(50));
+                    continue;
+                }
+            };
             
             // Connect to the pipe
             match unsafe { ConnectNamedPipe(pipe_handle, std::ptr::null_mut()) } {
@@ -89,8 +108,9 @@ impl PipeServer {
             
             // Handle the client connection in a new thread
             let pipe_handle_copy = unsafe { std::mem::transmute_copy(&pipe_handle) };
+            let server = Arc::clone(&server);
             thread::spawn(move || {
-                if let Err(e) = Self::handle_client(pipe_handle_copy) {
+                if let Err(e) = Self::handle_client(pipe_handle_copy, &server) {
                     error!("Error handling client: {}", e);
                 }
                 unsafe { winapi::um::handleapi::CloseHandle(pipe_handle_copy) };
@@ -124,31 +144,35 @@ impl PipeServer {
         Ok(pipe_handle)
     }
 
-    fn handle_client(pipe_handle: HANDLE) -> Result<()> {
+    fn handle_client(pipe_handle: HANDLE, server: &McpServer) -> Result<()> {
         let mut buffer = vec![0u8; BUFFER_SIZE];
-        let pipe = unsafe { std::fs::File::from_raw_handle(pipe_handle as *mut _) };
-        let mut pipe = std::io::BufReader::with_capacity(BUFFER_SIZE, pipe);
+        let mut pipe = unsafe { std::fs::File::from_raw_handle(pipe_handle as *mut _) };
 
         loop {
             match pipe.read(&mut buffer) {
                 Ok(0) => break, // Connection closed by client
                 Ok(bytes_read) => {
-                    // Process the message
-                    let message = &buffer[..bytes_read];
-                    if let Ok(message_str) = std::str::from_utf8(message) {
-                        info!("Received message: {}", message_str);
-                        // TODO: Process the message and generate response
-                        let response = format!("Processed: {}", message_str);
-                        if let Err(e) = pipe.get_mut().write_all(response.as_bytes()) {
-                            error!("Failed to send response: {}", e);
-                            break;
+                    // Each request arrives as one message: header and payload together
+                    let start = Instant::now();
+                    let mut message_type = 0;
+                    let result = parse_request_frame(&buffer[..bytes_read])
+                        .and_then(|(kind, payload)| {
+                            message_type = kind;
+                            Self::dispatch(server, kind, payload, &mut pipe)
+                        });
+                    etw::pipe_request(message_type, bytes_read, start.elapsed(), result.is_ok());
+                    let written = match result {
+                        Ok(response) => serde_json::to_vec(&response)
+                            .map_err(io::Error::from)
+                            .and_then(|body| Self::write_frame(&mut pipe, STATUS_OK, &body)),
+                        Err(e) => {
+                            error!("Pipe request failed: {}", e);
+                            Self::write_frame(&mut pipe, STATUS_ERROR, e.to_string().as_bytes())
                         }
-                        if let Err(e) = pipe.get_mut().flush() {
-                            error!("Failed to flush pipe: {}", e);
-                            break;
-                        }
-                    } else {
-                        error!("Received invalid UTF-8 message");
+                    };
+                    if let Err(e) = written {
+                        error!("Failed to send response: {}", e);
+                        break;
                     }
                 }
                 Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
@@ -166,6 +190,78 @@ impl PipeServer {
         info!("Client disconnected");
         Ok(())
     }
+
+    /// Run a request; searches with `"stream": true` send partial frames before the final one
+    fn dispatch(server: &McpServer, message_type: u32, payload: &[u8], pipe: &mut std::fs::File) -> Result<Value> {
+        match message_type {
+            MSG_SEARCH => {
+                let request: Value = serde_json::from_slice(payload).context("Invalid search request")?;
+                let args = pipe_search_args(&request);
+                // Searches sent with a request_id can be stopped with MSG_CANCEL
+                let registered = json!({"id": request["request_id"].as_str().map(Self::cancel_key)});
+
+                let response = if request["stream"].as_bool().unwrap_or(false) {
+                    let pipe = std::cell::RefCell::new(pipe);
+                    let sink = |batch: Vec<Value>, scanned: u64, total: u64| {
+                        let frame = json!({"results": batch, "scanned": scanned, "total": total});
+                        let body = serde_json::to_vec(&frame).unwrap_or_default();
+                        if let Err(e) = Self::write_frame(&mut **pipe.borrow_mut(), STATUS_PARTIAL, &body) {
+                            warn!("Failed to send partial results: {}", e);
+                        }
+                    };
+                    server.fast_search_cancellable(&registered, &args, Some(&sink))?
+                } else {
+                    server.fast_search_cancellable(&registered, &args, None)?
+                };
+
+                if let Some(message) = response["error"]["message"].as_str() {
+                    bail!("{}", message);
+                }
+                Ok(response["result"].clone())
+            }
+            MSG_STATUS => {
+                let warnings = integrity_warnings();
+                let status = if self_test_failed() {
+                    "not_ready"
+                } else if is_degraded() || !warnings.is_empty() || any_degraded() {
+                    "degraded"
+                } else {
+                    "running"
+                };
+                Ok(json!({
+                    "status": status,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "features": enabled_features(),
+                    "resource_limits": limit_status(),
+                    "index_modes": index_mode_status(),
+                    "usn_queues": usn_queue_status(),
+                    "self_test": self_test_status(),
+                    "warnings": warnings
+                }))
+            }
+            MSG_CANCEL => {
+                let request: Value = serde_json::from_slice(payload).context("Invalid cancel request")?;
+                let id = request["request_id"].as_str().context("Cancel request without a request_id")?;
+                let cancelled = server.cancel_request(&json!({
+                    "requestId": Self::cancel_key(id),
+                    "reason": "cancelled over the pipe"
+                }));
+                Ok(json!({"request_id": id, "cancelled": cancelled}))
+            }
+            other => bail!("Unknown message type {}", other),
+        }
+    }
+
+    /// Key a pipe request is registered under, kept apart from MCP request ids
+    fn cancel_key(request_id: &str) -> String {
+        format!("pipe:{}", request_id)
+    }
+
+    /// Send header and payload in one write, so a message-mode reader gets the whole frame in one message
+    fn write_frame(pipe: &mut std::fs::File, status: u32, body: &[u8]) -> io::Result<()> {
+        pipe.write_all(&encode_frame(status, body))?;
+        pipe.flush()
+    }
 }
 
 impl Drop for PipeServer {
@@ -174,5 +270,7 @@ impl Drop for PipeServer {
             // Send shutdown signal
             let _ = tx.send(());
         }
+        // Searches of clients still connected stop at their next check
+        self.server.shutdown();
     }
 }
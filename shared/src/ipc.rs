@@ -0,0 +1,62 @@
+//! Binary (bincode) decoding of requests sent to the service
+
+use anyhow::{Context, Result};
+use bincode::Options;
+
+use crate::types::SearchRequest;
+
+/// Largest encoded request the service decodes
+///
+/// Length prefixes inside a request are checked against this before anything
+/// is allocated, so a few malformed bytes can't claim gigabytes.
+pub const MAX_REQUEST_SIZE: u64 = 1024 * 1024;
+
+/// Decode a search request encoded with `bincode::serialize`
+///
+/// Fails (rather than panicking or allocating) on truncated input and on
+/// requests larger than [`MAX_REQUEST_SIZE`]. Bytes after the request are ignored.
+pub fn decode_request(bytes: &[u8]) -> Result<SearchRequest> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_REQUEST_SIZE)
+        .deserialize(bytes)
+        .context("Invalid search request")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> SearchRequest {
+        serde_json::from_value(serde_json::json!({
+            "query": "*.rs",
+            "path": "src",
+            "file_types": ["rs"],
+            "doc_type": "code",
+            "exclude": ["target/**"],
+            "cursor": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_decode_request() {
+        let encoded = bincode::serialize(&request()).unwrap();
+        let decoded = decode_request(&encoded).unwrap();
+        assert_eq!(decoded.query, "*.rs");
+        assert_eq!(decoded.max_results, 50);
+        assert_eq!(decoded.exclude, vec!["target/**".to_string()]);
+
+        assert!(decode_request(&encoded[..encoded.len() / 2]).is_err());
+        assert!(decode_request(&[]).is_err());
+    }
+
+    #[test]
+    fn test_decode_request_length_limit() {
+        // A query claiming to be u64::MAX bytes long
+        let mut bytes = u64::MAX.to_le_bytes().to_vec();
+        bytes.extend_from_slice(b"abc");
+        assert!(decode_request(&bytes).is_err());
+    }
+}
@@ -5,12 +5,14 @@
 
 #![warn(missing_docs)]
 
+pub mod ipc;
 pub mod types;
 
 // Re-export all types for easier importing
 pub use types::{
-    SearchRequest, SearchResult, SearchResponse, SearchMetadata, IndexStats,
+    SearchRequest, SearchResult, SearchResponse, SearchMetadata, VolumeSearchStats, IndexStats,
     TextHighlight, ServiceStatus, ServiceHealth
 };
 
+pub use ipc::{decode_request, MAX_REQUEST_SIZE};
 pub use types::*;
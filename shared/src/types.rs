@@ -21,8 +21,14 @@ pub struct SearchRequest {
     pub path: Option<String>,
     
     /// Optional file type filters (extensions without leading .)
+    #[serde(alias = "extensions")]
     pub file_types: Option<Vec<String>>,
     
+    /// Optional document type preset (text, code, image, spreadsheet,
+    /// presentation, archive, audio, video, pdf); with `file_types`, matches need both
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_type: Option<String>,
+    
     /// Minimum file size in bytes
     pub min_size: Option<u64>,
     
@@ -32,6 +38,21 @@ pub struct SearchRequest {
     /// Only include files modified after this timestamp (UNIX epoch seconds)
     pub modified_after: Option<i64>,
     
+    /// Only include files modified before this timestamp (UNIX epoch seconds)
+    pub modified_before: Option<i64>,
+    
+    /// Only include files created after this timestamp (UNIX epoch seconds)
+    pub created_after: Option<i64>,
+    
+    /// Only include files created before this timestamp (UNIX epoch seconds)
+    pub created_before: Option<i64>,
+    
+    /// Only include files accessed after this timestamp (UNIX epoch seconds)
+    pub accessed_after: Option<i64>,
+    
+    /// Only include files accessed before this timestamp (UNIX epoch seconds)
+    pub accessed_before: Option<i64>,
+    
     /// Whether to include hidden files and directories
     #[serde(default)]
     pub include_hidden: bool,
@@ -39,6 +60,17 @@ pub struct SearchRequest {
     /// Whether to only return directories
     #[serde(default)]
     pub directories_only: bool,
+    
+    /// Glob patterns for paths to leave out (e.g. `node_modules/**`, `*.tmp`)
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    
+    /// Leave out files ignored by the `.gitignore`/`.ignore` files of their repository
+    #[serde(default)]
+    pub respect_gitignore: bool,
+    
+    /// Cursor from a previous response, to continue with the next page
+    pub cursor: Option<String>,
 }
 
 /// Default maximum number of results
@@ -81,18 +113,25 @@ pub struct SearchResult {
 }
 
 /// Text highlight information for search results
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TextHighlight {
-    /// Start position of the highlight
+    /// Field the highlight is in: `name` or `path`
+    #[serde(default = "default_highlight_field")]
+    pub field: String,
+    
+    /// Start position of the highlight, in characters
     pub start: usize,
     
-    /// End position of the highlight
+    /// End position of the highlight (exclusive), in characters
     pub end: usize,
     
-    /// Snippet of text around the highlight
+    /// The highlighted text
     pub snippet: String,
 }
 
+/// Highlights without a field are in the name
+fn default_highlight_field() -> String { "name".to_string() }
+
 /// Search response following FastMCP 2.11.3 standards
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -102,6 +141,10 @@ pub struct SearchResponse {
     
     /// Search metadata
     pub metadata: SearchMetadata,
+    
+    /// Cursor for the next page (None on the last page)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Metadata about the search operation
@@ -128,6 +171,34 @@ pub struct SearchMetadata {
     
     /// Index statistics (if available)
     pub index_stats: Option<IndexStats>,
+    
+    /// Breakdown per volume of a search over every drive (empty for single-drive searches)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<VolumeSearchStats>,
+}
+
+/// How one volume of a multi-drive search went
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct VolumeSearchStats {
+    /// Drive letter, without the colon
+    pub drive: String,
+    
+    /// `hit` (cache already built), `miss` (built for this search) or `unavailable`
+    pub cache: String,
+    
+    /// Cache entries scanned on this volume
+    pub scanned: u64,
+    
+    /// Entries on this volume that matched the pattern and filters
+    pub matches: u64,
+    
+    /// Time spent on this volume in milliseconds, including a cache build
+    pub elapsed_ms: u64,
+    
+    /// Why the volume wasn't searched, if it wasn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 /// Index statistics